@@ -1,5 +1,11 @@
 import React, { useState, useEffect } from 'react';
 import { Network, Server, Globe, Zap, Activity, TrendingUp, AlertCircle, CheckCircle, Loader, RefreshCw } from 'lucide-react';
+import { LineChart, Line, XAxis, YAxis, CartesianGrid, Tooltip, ResponsiveContainer, Legend } from 'recharts';
+
+// trinity-node's dashboard feed ([node] stats_port), e.g. ws://127.0.0.1:8090
+const STATS_FEED_URL = import.meta.env.VITE_STATS_FEED_URL;
+// Samples kept for the live chart (one per second)
+const LIVE_SAMPLES = 120;
 
 const NetworkManager = ({ nodeUrl }) => {
   const [networkInfo, setNetworkInfo] = useState(null);
@@ -8,6 +14,7 @@ const NetworkManager = ({ nodeUrl }) => {
   const [apiStats, setApiStats] = useState(null);
   const [loading, setLoading] = useState(false);
   const [error, setError] = useState('');
+  const [liveStats, setLiveStats] = useState([]);
 
   useEffect(() => {
     fetchNetworkData();
@@ -15,6 +22,19 @@ const NetworkManager = ({ nodeUrl }) => {
     return () => clearInterval(interval);
   }, [nodeUrl]);
 
+  useEffect(() => {
+    if (!STATS_FEED_URL) return undefined;
+    const socket = new WebSocket(STATS_FEED_URL);
+    socket.onmessage = (message) => {
+      const stats = JSON.parse(message.data);
+      const sample = { ...stats, time: new Date().toLocaleTimeString() };
+      setLiveStats((samples) => [...samples, sample].slice(-LIVE_SAMPLES));
+    };
+    return () => socket.close();
+  }, []);
+
+  const latestStats = liveStats[liveStats.length - 1];
+
   const fetchNetworkData = async () => {
     setLoading(true);
     try {
@@ -144,6 +164,28 @@ const NetworkManager = ({ nodeUrl }) => {
         </div>
       </div>
 
+      {/* Live Node Feed */}
+      {latestStats && (
+        <div className="bg-slate-900/50 rounded-lg p-6 border border-purple-500/20">
+          <h3 className="text-lg font-bold mb-4 flex items-center gap-2">
+            <Activity className="text-cyan-400" size={24} />
+            Live Node ({latestStats.status}, up {formatUptime(latestStats.uptime_secs)})
+          </h3>
+          <ResponsiveContainer width="100%" height={240}>
+            <LineChart data={liveStats}>
+              <CartesianGrid strokeDasharray="3 3" stroke="#334155" />
+              <XAxis dataKey="time" stroke="#a78bfa" />
+              <YAxis yAxisId="height" stroke="#a78bfa" allowDecimals={false} />
+              <YAxis yAxisId="peers" orientation="right" stroke="#22d3ee" allowDecimals={false} />
+              <Tooltip />
+              <Legend />
+              <Line yAxisId="height" type="stepAfter" dataKey="chain_height" name="Height" stroke="#a78bfa" dot={false} />
+              <Line yAxisId="peers" type="stepAfter" dataKey="peers" name="Peers" stroke="#22d3ee" dot={false} />
+            </LineChart>
+          </ResponsiveContainer>
+        </div>
+      )}
+
       {/* API Statistics */}
       {apiStats && (
         <div className="bg-slate-900/50 rounded-lg p-6 border border-purple-500/20">
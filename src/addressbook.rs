@@ -3,6 +3,8 @@
 //! This module provides a thread-safe, validated address book with atomic operations,
 //! audit trails, and comprehensive error handling.
 
+use crate::crypto::{self, Address, KeyPair, MessageSignature};
+use crate::datadir::DataDir;
 use crate::error::ChainError;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
@@ -39,6 +41,10 @@ pub struct AddressEntry {
 
     /// Number of times this entry has been modified
     pub version: u32,
+
+    /// The contact's signed proof that they control `address`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof: Option<AddressProof>,
 }
 
 impl AddressEntry {
@@ -67,13 +73,29 @@ impl AddressEntry {
             created_at: now.clone(),
             updated_at: now,
             version: 1,
+            proof: None,
         })
     }
 
+    /// Whether the entry carries a valid proof for its current address
+    pub fn is_verified(&self) -> bool {
+        let Some(proof) = &self.proof else {
+            return false;
+        };
+        match (proof.verify(), Address::parse(&self.address)) {
+            (Ok(proven), Ok(address)) => proven == address,
+            _ => false,
+        }
+    }
+
     /// Update the entry with new information
     fn update(&mut self, address: Option<String>, notes: Option<String>) -> Result<(), ChainError> {
         if let Some(addr) = address {
             validate_address(&addr)?;
+            // A proof only vouches for the address it was signed for
+            if addr != self.address {
+                self.proof = None;
+            }
             self.address = addr;
         }
 
@@ -88,6 +110,115 @@ impl AddressEntry {
     }
 }
 
+/// A contact's signed claim to an address under a label. The contact
+/// creates it with their wallet and sends it over; importing it marks the
+/// address book entry as verified.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AddressProof {
+    pub label: String,
+    pub address: String,
+    /// RFC3339 timestamp when the proof was signed
+    pub signed_at: String,
+    /// Message signature (hex) over [`AddressProof::message`]
+    pub signature: String,
+}
+
+impl AddressProof {
+    /// Sign a proof that `keypair`'s address belongs to `label`
+    pub fn create(keypair: &KeyPair, label: &str) -> Result<Self, ChainError> {
+        let label = label.trim().to_string();
+        validate_label(&label)?;
+        let address = keypair.address().to_string();
+        let signed_at = chrono::Utc::now().to_rfc3339();
+        let message = Self::message(&label, &address, &signed_at);
+        let signature = crypto::sign_message(keypair, &message)?.to_hex();
+        Ok(AddressProof {
+            label,
+            address,
+            signed_at,
+            signature,
+        })
+    }
+
+    /// The text the signature covers
+    pub fn message(label: &str, address: &str, signed_at: &str) -> String {
+        format!(
+            "TrinityChain address proof\nlabel: {}\naddress: {}\nsigned at: {}",
+            label, address, signed_at
+        )
+    }
+
+    /// Check the signature; returns the proven address
+    pub fn verify(&self) -> Result<Address, ChainError> {
+        let address = Address::parse(&self.address)?;
+        let signature = MessageSignature::from_hex(&self.signature)?;
+        let message = Self::message(&self.label, &self.address, &self.signed_at);
+        crypto::verify_message(&address, &message, &signature)?;
+        Ok(address)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ChainError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| ChainError::WalletError(format!("Failed to serialize proof: {}", e)))?;
+        fs::write(path, json)
+            .map_err(|e| ChainError::WalletError(format!("Failed to write proof: {}", e)))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ChainError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ChainError::WalletError(format!("Failed to read proof: {}", e)))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| ChainError::WalletError(format!("Failed to parse proof: {}", e)))
+    }
+}
+
+/// What an import does with a row whose label or address is already taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Keep the existing entry and leave the row out
+    Skip,
+    /// Replace the existing entry with the row
+    Overwrite,
+    /// Abort the whole import without changing the book
+    Error,
+}
+
+impl std::str::FromStr for DuplicatePolicy {
+    type Err = ChainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "skip" => Ok(DuplicatePolicy::Skip),
+            "overwrite" => Ok(DuplicatePolicy::Overwrite),
+            "error" => Ok(DuplicatePolicy::Error),
+            other => Err(ChainError::WalletError(format!(
+                "Unknown duplicate policy '{}' (expected skip, overwrite or error)",
+                other
+            ))),
+        }
+    }
+}
+
+/// What an import did, or would do in a dry run, row by row
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Labels of the new entries
+    pub added: Vec<String>,
+    /// Labels of the entries a row replaced
+    pub updated: Vec<String>,
+    /// Labels of the rows left out as duplicates
+    pub skipped: Vec<String>,
+    /// Rows that failed validation, by 1-based row number
+    pub rejected: Vec<(usize, String)>,
+}
+
+/// One contact read from an import file, not yet validated
+struct ImportRow {
+    label: String,
+    address: String,
+    notes: Option<String>,
+}
+
 /// Thread-safe address book with atomic operations
 #[derive(Debug, Clone)]
 pub struct AddressBook {
@@ -274,6 +405,39 @@ impl AddressBook {
         inner.entries.get(&key).cloned()
     }
 
+    /// Resolve a contact the user typed, such as the `alice` of `@alice`:
+    /// the entry with exactly that label, or else the only one whose label
+    /// starts with it. Fails when nothing or several entries match.
+    pub fn resolve(&self, name: &str) -> Result<AddressEntry, ChainError> {
+        let name = name.trim();
+        if let Some(entry) = self.get(name) {
+            return Ok(entry);
+        }
+
+        let prefix = name.to_lowercase();
+        let mut matches: Vec<_> = self
+            .list()
+            .into_iter()
+            .filter(|entry| !prefix.is_empty() && entry.label.to_lowercase().starts_with(&prefix))
+            .collect();
+        match matches.len() {
+            0 => Err(ChainError::WalletError(format!(
+                "No address book entry labelled '{}'",
+                name
+            ))),
+            1 => Ok(matches.remove(0)),
+            _ => Err(ChainError::WalletError(format!(
+                "'{}' is ambiguous, it matches {}",
+                name,
+                matches
+                    .iter()
+                    .map(|entry| entry.label.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+
     /// Get an entry by address (reverse lookup)
     pub fn get_by_address(&self, address: &str) -> Option<AddressEntry> {
         let inner = self.inner.read();
@@ -283,6 +447,51 @@ impl AddressBook {
             .and_then(|key| inner.entries.get(key).cloned())
     }
 
+    /// Get an entry by address, matching hex and base58check forms alike
+    pub fn find_address(&self, address: &Address) -> Option<AddressEntry> {
+        let inner = self.inner.read();
+        inner
+            .entries
+            .values()
+            .find(|entry| Address::parse(&entry.address).ok().as_ref() == Some(address))
+            .cloned()
+    }
+
+    /// Verify a contact's proof and attach it to their entry, adding one
+    /// under `label` (or the label in the proof) if the address is new.
+    /// Returns the verified entry.
+    pub fn import_proof(
+        &self,
+        proof: &AddressProof,
+        label: Option<String>,
+    ) -> Result<AddressEntry, ChainError> {
+        let address = proof
+            .verify()
+            .map_err(|e| ChainError::WalletError(format!("Address proof is invalid: {}", e)))?;
+
+        let existing = self.find_address(&address);
+        let key = match existing {
+            Some(entry) => entry.label.to_lowercase(),
+            None => {
+                let label = label.unwrap_or_else(|| proof.label.clone());
+                self.add(label.clone(), proof.address.clone(), None)?;
+                label.trim().to_lowercase()
+            }
+        };
+
+        let mut inner = self.inner.write();
+        let entry = inner
+            .entries
+            .get_mut(&key)
+            .ok_or_else(|| ChainError::WalletError("Entry vanished during import".to_string()))?;
+        entry.proof = Some(proof.clone());
+        entry.updated_at = chrono::Utc::now().to_rfc3339();
+        entry.version = entry.version.saturating_add(1);
+        let entry = entry.clone();
+        inner.touch();
+        Ok(entry)
+    }
+
     /// Search for addresses (by label, address, or notes)
     pub fn search(&self, query: &str) -> Vec<AddressEntry> {
         let inner = self.inner.read();
@@ -309,6 +518,45 @@ impl AddressBook {
         results
     }
 
+    /// Entries matching `query` as typed in a search box, best first: label
+    /// prefix, then label substring, then the query's characters in order
+    /// anywhere in the label, then an address or notes substring. An empty
+    /// query lists everything.
+    pub fn fuzzy_search(&self, query: &str) -> Vec<AddressEntry> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return self.list();
+        }
+
+        let mut ranked: Vec<(u8, AddressEntry)> = self
+            .list()
+            .into_iter()
+            .filter_map(|entry| {
+                let label = entry.label.to_lowercase();
+                let rank = if label.starts_with(&query) {
+                    0
+                } else if label.contains(&query) {
+                    1
+                } else if is_subsequence(&query, &label) {
+                    2
+                } else if entry.address.to_lowercase().contains(&query)
+                    || entry
+                        .notes
+                        .as_deref()
+                        .is_some_and(|n| n.to_lowercase().contains(&query))
+                {
+                    3
+                } else {
+                    return None;
+                };
+                Some((rank, entry))
+            })
+            .collect();
+        // `list` is sorted by label, and the sort is stable
+        ranked.sort_by_key(|(rank, _)| *rank);
+        ranked.into_iter().map(|(_, entry)| entry).collect()
+    }
+
     /// List all entries sorted by label
     pub fn list(&self) -> Vec<AddressEntry> {
         let inner = self.inner.read();
@@ -441,6 +689,194 @@ impl AddressBook {
         Ok(())
     }
 
+    /// Import contacts from a CSV file with a header row naming at least
+    /// `Label` and `Address` columns (`Notes` is optional), as written by
+    /// [`AddressBook::export_csv`]. Invalid rows are reported and left out;
+    /// duplicates are handled according to `policy`. With `dry_run` the
+    /// book is left untouched and the report says what would happen.
+    pub fn import_csv(
+        &self,
+        path: &Path,
+        policy: DuplicatePolicy,
+        dry_run: bool,
+    ) -> Result<ImportReport, ChainError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ChainError::WalletError(format!("Failed to read CSV: {}", e)))?;
+        let mut records = parse_csv(&contents).into_iter();
+        let header: Vec<String> = records
+            .next()
+            .ok_or_else(|| ChainError::WalletError("CSV file is empty".to_string()))?
+            .iter()
+            .map(|name| name.trim().to_lowercase())
+            .collect();
+        let column = |name: &str| header.iter().position(|h| h == name);
+        let (label_col, address_col) = match (column("label"), column("address")) {
+            (Some(label), Some(address)) => (label, address),
+            _ => {
+                return Err(ChainError::WalletError(
+                    "CSV header must name Label and Address columns".to_string(),
+                ))
+            }
+        };
+        let notes_col = column("notes");
+
+        let rows = records
+            .filter(|record| record.iter().any(|field| !field.trim().is_empty()))
+            .map(|record| {
+                let field = |i: usize| record.get(i).cloned();
+                Ok(ImportRow {
+                    label: field(label_col).ok_or("missing Label column")?,
+                    address: field(address_col).ok_or("missing Address column")?,
+                    notes: notes_col.and_then(field),
+                })
+            })
+            .collect();
+        self.import_rows(rows, policy, dry_run)
+    }
+
+    /// Import contacts from a JSON file: either an array of objects with
+    /// `label`, `address` and optional `notes` fields, or an address book
+    /// file saved by [`AddressBook::save`]. Rows are validated and
+    /// duplicates handled as in [`AddressBook::import_csv`].
+    pub fn import_json(
+        &self,
+        path: &Path,
+        policy: DuplicatePolicy,
+        dry_run: bool,
+    ) -> Result<ImportReport, ChainError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ChainError::WalletError(format!("Failed to read JSON: {}", e)))?;
+        let value: serde_json::Value = serde_json::from_str(&contents)
+            .map_err(|e| ChainError::WalletError(format!("Failed to parse JSON: {}", e)))?;
+
+        let items: Vec<serde_json::Value> = match value {
+            serde_json::Value::Array(items) => items,
+            serde_json::Value::Object(mut book) => match book.remove("entries") {
+                Some(serde_json::Value::Object(entries)) => {
+                    let mut entries: Vec<_> = entries.into_iter().collect();
+                    entries.sort_by(|a, b| a.0.cmp(&b.0));
+                    entries.into_iter().map(|(_, entry)| entry).collect()
+                }
+                _ => {
+                    return Err(ChainError::WalletError(
+                        "JSON object has no address book entries".to_string(),
+                    ))
+                }
+            },
+            _ => {
+                return Err(ChainError::WalletError(
+                    "JSON must be an array of contacts or an address book".to_string(),
+                ))
+            }
+        };
+
+        let rows = items
+            .iter()
+            .map(|item| {
+                let text = |name: &str| item.get(name).and_then(|v| v.as_str()).map(String::from);
+                Ok(ImportRow {
+                    label: text("label").ok_or("missing string field 'label'")?,
+                    address: text("address").ok_or("missing string field 'address'")?,
+                    notes: text("notes"),
+                })
+            })
+            .collect();
+        self.import_rows(rows, policy, dry_run)
+    }
+
+    /// Apply parsed rows to a copy of the book, then swap it in unless this
+    /// is a dry run, so a failed import never leaves the book half-changed
+    fn import_rows(
+        &self,
+        rows: Vec<Result<ImportRow, &str>>,
+        policy: DuplicatePolicy,
+        dry_run: bool,
+    ) -> Result<ImportReport, ChainError> {
+        let work = AddressBook {
+            inner: Arc::new(RwLock::new(self.inner.read().clone())),
+        };
+        let mut report = ImportReport::default();
+
+        for (i, row) in rows.into_iter().enumerate() {
+            let row_number = i + 1;
+            let row = match row {
+                Ok(row) => row,
+                Err(reason) => {
+                    report.rejected.push((row_number, reason.to_string()));
+                    continue;
+                }
+            };
+            let label = row.label.trim().to_string();
+            let address = row.address.trim().to_string();
+            let notes = row
+                .notes
+                .map(|n| n.trim().to_string())
+                .filter(|n| !n.is_empty());
+            let valid = validate_label(&label)
+                .and_then(|_| validate_address(&address))
+                .and_then(|_| notes.as_deref().map_or(Ok(()), validate_notes));
+            if let Err(e) = valid {
+                report.rejected.push((row_number, e.to_string()));
+                continue;
+            }
+
+            let by_label = work.get(&label);
+            let by_address = Address::parse(&address)
+                .ok()
+                .and_then(|parsed| work.find_address(&parsed));
+            if by_label.is_none() && by_address.is_none() {
+                work.add(label.clone(), address, notes)?;
+                report.added.push(label);
+                continue;
+            }
+
+            match policy {
+                DuplicatePolicy::Skip => report.skipped.push(label),
+                DuplicatePolicy::Error => {
+                    let existing = by_label.or(by_address).map(|e| e.label).unwrap_or_default();
+                    return Err(ChainError::WalletError(format!(
+                        "Row {}: '{}' duplicates the existing entry '{}'",
+                        row_number, label, existing
+                    )));
+                }
+                DuplicatePolicy::Overwrite => {
+                    // Drop an entry under another label holding this address
+                    if let Some(other) = by_address {
+                        if !other.label.eq_ignore_ascii_case(&label) {
+                            work.remove(&other.label)?;
+                        }
+                    }
+                    if work.contains_label(&label) {
+                        let mut inner = work.inner.write();
+                        let key = label.to_lowercase();
+                        let entry = inner.entries.get_mut(&key).ok_or_else(|| {
+                            ChainError::WalletError("Entry vanished during import".to_string())
+                        })?;
+                        let old_address = std::mem::replace(&mut entry.address, address.clone());
+                        if old_address != address {
+                            entry.proof = None;
+                        }
+                        entry.notes = notes;
+                        entry.updated_at = chrono::Utc::now().to_rfc3339();
+                        entry.version = entry.version.saturating_add(1);
+                        inner.address_index.remove(&old_address);
+                        inner.address_index.insert(address, key);
+                        inner.touch();
+                    } else {
+                        work.add(label.clone(), address, notes)?;
+                    }
+                    report.updated.push(label);
+                }
+            }
+        }
+
+        if !dry_run {
+            let imported = work.inner.read().clone();
+            *self.inner.write() = imported;
+        }
+        Ok(report)
+    }
+
     /// Clear all entries (use with caution!)
     pub fn clear(&self) -> Result<(), ChainError> {
         let mut inner = self.inner.write();
@@ -497,12 +933,9 @@ fn validate_address(address: &str) -> Result<(), ChainError> {
         )));
     }
 
-    // Basic format validation (adjust for TrinityChain address format)
-    if !address.chars().all(|c| c.is_alphanumeric()) {
-        return Err(ChainError::WalletError(
-            "Invalid address format".to_string(),
-        ));
-    }
+    Address::parse(address).map_err(|e| {
+        ChainError::WalletError(format!("Invalid address format: {}", e))
+    })?;
 
     Ok(())
 }
@@ -518,14 +951,49 @@ fn validate_notes(notes: &str) -> Result<(), ChainError> {
     Ok(())
 }
 
+/// Whether `needle`'s characters all appear in `haystack`, in order
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars();
+    needle.chars().all(|c| haystack.any(|h| h == c))
+}
+
+/// Split CSV text into records, honouring quoted fields with embedded
+/// commas, doubled quotes and line breaks
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
 // Helper functions for default paths
 
 /// Get the default address book path
 pub fn get_addressbook_path() -> PathBuf {
-    dirs::home_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join(".trinitychain")
-        .join("addressbook.json")
+    DataDir::current().wallet_dir().join("addressbook.json")
 }
 
 /// Load the default address book, creating a new one if missing
@@ -546,11 +1014,11 @@ mod tests {
     #[test]
     fn test_addressbook_add_and_get() {
         let book = AddressBook::new();
-        book.add("Alice".to_string(), "00000000000000000000000000000000000000000000000000000000000abc123".to_string(), None)
+        book.add("Alice".to_string(), "0000000000000000000000000000000000000000000000000000000000abc123".to_string(), None)
             .unwrap();
         let entry = book.get("alice").unwrap();
         assert_eq!(entry.label, "Alice");
-        assert_eq!(entry.address, "00000000000000000000000000000000000000000000000000000000000abc123");
+        assert_eq!(entry.address, "0000000000000000000000000000000000000000000000000000000000abc123");
     }
 
     #[test]
@@ -558,7 +1026,7 @@ mod tests {
         let book = AddressBook::new();
         let result = book.add(
             "  Alice  ".to_string(),
-            "00000000000000000000000000000000000000000000000000000000000abc123".to_string(),
+            "0000000000000000000000000000000000000000000000000000000000abc123".to_string(),
             Some("  Friend  ".to_string()),
         );
         
@@ -569,7 +1037,7 @@ mod tests {
 
         let entry = book.get("alice").unwrap();
         assert_eq!(entry.label, "Alice");
-        assert_eq!(entry.address, "00000000000000000000000000000000000000000000000000000000000abc123");
+        assert_eq!(entry.address, "0000000000000000000000000000000000000000000000000000000000abc123");
         assert_eq!(entry.notes.as_deref(), Some("Friend"));
     }
 
@@ -586,7 +1054,7 @@ mod tests {
     #[test]
     fn test_addressbook_case_insensitive() {
         let book = AddressBook::new();
-        book.add("Alice".to_string(), "00000000000000000000000000000000000000000000000000000000000abc123".to_string(), None)
+        book.add("Alice".to_string(), "0000000000000000000000000000000000000000000000000000000000abc123".to_string(), None)
             .unwrap();
 
         assert!(book.get("alice").is_some());
@@ -597,7 +1065,7 @@ mod tests {
     #[test]
     fn test_addressbook_duplicate_label() {
         let book = AddressBook::new();
-        book.add("Alice".to_string(), "00000000000000000000000000000000000000000000000000000000000abc123".to_string(), None)
+        book.add("Alice".to_string(), "0000000000000000000000000000000000000000000000000000000000abc123".to_string(), None)
             .unwrap();
 
         let result = book.add("alice".to_string(), "0000000000000000000000000000000000000000000000000000000000def456".to_string(), None);
@@ -607,10 +1075,10 @@ mod tests {
     #[test]
     fn test_addressbook_duplicate_address() {
         let book = AddressBook::new();
-        book.add("Alice".to_string(), "00000000000000000000000000000000000000000000000000000000000abc123".to_string(), None)
+        book.add("Alice".to_string(), "0000000000000000000000000000000000000000000000000000000000abc123".to_string(), None)
             .unwrap();
 
-        let result = book.add("Bob".to_string(), "00000000000000000000000000000000000000000000000000000000000abc123".to_string(), None);
+        let result = book.add("Bob".to_string(), "0000000000000000000000000000000000000000000000000000000000abc123".to_string(), None);
         assert!(result.is_err());
     }
 
@@ -629,18 +1097,18 @@ mod tests {
     #[test]
     fn test_addressbook_update() {
         let book = AddressBook::new();
-        book.add("Alice".to_string(), "00000000000000000000000000000000000000000000000000000000000abc123".to_string(), None)
+        book.add("Alice".to_string(), "0000000000000000000000000000000000000000000000000000000000abc123".to_string(), None)
             .unwrap();
 
         book.update(
             "Alice",
-            Some("0000000000000000000000000000000000000000000000000000000000xyz789".to_string()),
+            Some("0000000000000000000000000000000000000000000000000000000000fed789".to_string()),
             Some("Updated notes".to_string()),
         )
         .unwrap();
 
         let entry = book.get("alice").unwrap();
-        assert_eq!(entry.address, "0000000000000000000000000000000000000000000000000000000000xyz789");
+        assert_eq!(entry.address, "0000000000000000000000000000000000000000000000000000000000fed789");
         assert_eq!(entry.notes.as_deref(), Some("Updated notes"));
         assert_eq!(entry.version, 2);
     }
@@ -650,7 +1118,7 @@ mod tests {
         let book = AddressBook::new();
         book.add(
             "Alice".to_string(),
-            "00000000000000000000000000000000000000000000000000000000000abc123".to_string(),
+            "0000000000000000000000000000000000000000000000000000000000abc123".to_string(),
             Some("Friend".to_string()),
         )
         .unwrap();
@@ -668,13 +1136,58 @@ mod tests {
         assert_eq!(book.search("  ").len(), 0);
     }
 
+    #[test]
+    fn test_addressbook_fuzzy_search() {
+        let book = AddressBook::new();
+        for (label, suffix, notes) in [
+            ("Bob", "b0b", None),
+            ("Alice Cooper", "a11ce", None),
+            ("Mallory", "ba11", Some("landlord")),
+            ("Carol", "c0ffee", None),
+        ] {
+            let address = format!("{:0>64}", suffix);
+            book.add(label.to_string(), address, notes.map(String::from))
+                .unwrap();
+        }
+
+        let labels = |query: &str| -> Vec<String> {
+            book.fuzzy_search(query)
+                .into_iter()
+                .map(|e| e.label)
+                .collect()
+        };
+        // Prefix, then substring, then scattered letters
+        assert_eq!(labels("al"), vec!["Alice Cooper", "Mallory", "Carol"]);
+        assert_eq!(labels("acp"), vec!["Alice Cooper"]);
+        assert_eq!(labels("LANDLORD"), vec!["Mallory"]);
+        assert_eq!(labels("c0ff"), vec!["Carol"]);
+        assert_eq!(labels("").len(), 4);
+        assert!(labels("zz").is_empty());
+    }
+
+    #[test]
+    fn test_addressbook_resolve() {
+        let book = AddressBook::new();
+        for (label, suffix) in [("Alice", "a1"), ("Alan", "a2"), ("Bob", "b1")] {
+            let address = format!("{:0>64}", suffix);
+            book.add(label.to_string(), address, None).unwrap();
+        }
+
+        assert_eq!(book.resolve("bob").unwrap().label, "Bob");
+        assert_eq!(book.resolve("ali").unwrap().label, "Alice");
+        let ambiguous = book.resolve("al").unwrap_err().to_string();
+        assert!(ambiguous.contains("Alan, Alice"));
+        assert!(book.resolve("carol").is_err());
+        assert!(book.resolve("").is_err());
+    }
+
     #[test]
     fn test_addressbook_get_by_address() {
         let book = AddressBook::new();
-        book.add("Alice".to_string(), "00000000000000000000000000000000000000000000000000000000000abc123".to_string(), None)
+        book.add("Alice".to_string(), "0000000000000000000000000000000000000000000000000000000000abc123".to_string(), None)
             .unwrap();
 
-        let entry = book.get_by_address("00000000000000000000000000000000000000000000000000000000000abc123").unwrap();
+        let entry = book.get_by_address("0000000000000000000000000000000000000000000000000000000000abc123").unwrap();
         assert_eq!(entry.label, "Alice");
     }
 
@@ -685,7 +1198,7 @@ mod tests {
 
         let book1 = AddressBook::new();
         book1
-            .add("Alice".to_string(), "00000000000000000000000000000000000000000000000000000000000abc123".to_string(), None)
+            .add("Alice".to_string(), "0000000000000000000000000000000000000000000000000000000000abc123".to_string(), None)
             .unwrap();
         book1
             .add("Bob".to_string(), "0000000000000000000000000000000000000000000000000000000000def456".to_string(), None)
@@ -697,7 +1210,7 @@ mod tests {
         assert_eq!(book2.len(), 2);
         assert!(book2.get("alice").is_some());
         assert!(book2.get("bob").is_some());
-        assert!(book2.contains_address("00000000000000000000000000000000000000000000000000000000000abc123"));
+        assert!(book2.contains_address("0000000000000000000000000000000000000000000000000000000000abc123"));
         assert!(book2.contains_address("0000000000000000000000000000000000000000000000000000000000def456"));
     }
 
@@ -705,17 +1218,31 @@ mod tests {
     fn test_validation_label_too_long() {
         let book = AddressBook::new();
         let long_label = "a".repeat(MAX_LABEL_LENGTH + 1);
-        let result = book.add(long_label, "00000000000000000000000000000000000000000000000000000000000abc123".to_string(), None);
+        let result = book.add(long_label, "0000000000000000000000000000000000000000000000000000000000abc123".to_string(), None);
         assert!(result.is_err());
     }
 
     #[test]
     fn test_validation_empty_label() {
         let book = AddressBook::new();
-        let result = book.add("".to_string(), "00000000000000000000000000000000000000000000000000000000000abc123".to_string(), None);
+        let result = book.add("".to_string(), "0000000000000000000000000000000000000000000000000000000000abc123".to_string(), None);
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_validation_rejects_invalid_address() {
+        let book = AddressBook::new();
+        assert!(book.add("Miner".to_string(), "miner1".to_string(), None).is_err());
+
+        // A single-character typo fails the base58check checksum
+        let address = crate::crypto::Address::from([7u8; 32]).to_string();
+        let mut typo: Vec<char> = address.chars().collect();
+        typo[5] = if typo[5] == '2' { '3' } else { '2' };
+        let typo: String = typo.into_iter().collect();
+        assert!(book.add("Typo".to_string(), typo, None).is_err());
+        assert!(book.add("Carol".to_string(), address, None).is_ok());
+    }
+
     #[test]
     fn test_export_csv() {
         let temp_dir = TempDir::new().unwrap();
@@ -724,7 +1251,7 @@ mod tests {
         let book = AddressBook::new();
         book.add(
             "Alice".to_string(),
-            "00000000000000000000000000000000000000000000000000000000000abc123".to_string(),
+            "0000000000000000000000000000000000000000000000000000000000abc123".to_string(),
             Some("Friend".to_string()),
         )
         .unwrap();
@@ -735,10 +1262,88 @@ mod tests {
 
         let csv_content = fs::read_to_string(&csv_path).unwrap();
         assert!(csv_content.contains("Alice"));
-        assert!(csv_content.contains("00000000000000000000000000000000000000000000000000000000000abc123"));
+        assert!(csv_content.contains("0000000000000000000000000000000000000000000000000000000000abc123"));
         assert!(csv_content.contains("Bob"));
     }
 
+    #[test]
+    fn test_import_csv_and_json() {
+        let temp_dir = TempDir::new().unwrap();
+        let alice = "0000000000000000000000000000000000000000000000000000000000abc123";
+        let bob = "0000000000000000000000000000000000000000000000000000000000def456";
+        let carol = "0000000000000000000000000000000000000000000000000000000000fed789";
+
+        let source = AddressBook::new();
+        source
+            .add(
+                "Alice".to_string(),
+                alice.to_string(),
+                Some("Says \"hi\", often".to_string()),
+            )
+            .unwrap();
+        source
+            .add("Bob".to_string(), bob.to_string(), None)
+            .unwrap();
+        let csv_path = temp_dir.path().join("contacts.csv");
+        source.export_csv(&csv_path).unwrap();
+        let mut csv = fs::read_to_string(&csv_path).unwrap();
+        csv.push_str("Mallory,not-an-address,\n");
+        fs::write(&csv_path, csv).unwrap();
+
+        let book = AddressBook::new();
+        book.add("Bob".to_string(), carol.to_string(), None)
+            .unwrap();
+
+        let preview = book
+            .import_csv(&csv_path, DuplicatePolicy::Skip, true)
+            .unwrap();
+        assert_eq!(preview.added, vec!["Alice".to_string()]);
+        assert_eq!(preview.skipped, vec!["Bob".to_string()]);
+        assert_eq!(preview.rejected.len(), 1);
+        assert_eq!(preview.rejected[0].0, 3);
+        assert_eq!(book.len(), 1, "a dry run must not change the book");
+
+        assert!(book
+            .import_csv(&csv_path, DuplicatePolicy::Error, false)
+            .is_err());
+        assert_eq!(book.len(), 1, "a failed import must not change the book");
+
+        let report = book
+            .import_csv(&csv_path, DuplicatePolicy::Overwrite, false)
+            .unwrap();
+        assert_eq!(report.updated, vec!["Bob".to_string()]);
+        assert_eq!(book.get("bob").unwrap().address, bob);
+        assert_eq!(
+            book.get("alice").unwrap().notes.as_deref(),
+            Some("Says \"hi\", often")
+        );
+        assert!(!book.contains_address(carol));
+
+        let json_path = temp_dir.path().join("contacts.json");
+        fs::write(
+            &json_path,
+            format!(
+                r#"[{{"label": "Carol", "address": "{}"}}, {{"label": "Nobody"}}]"#,
+                carol
+            ),
+        )
+        .unwrap();
+        let report = book
+            .import_json(&json_path, DuplicatePolicy::Skip, false)
+            .unwrap();
+        assert_eq!(report.added, vec!["Carol".to_string()]);
+        assert_eq!(report.rejected.len(), 1);
+
+        // A saved address book imports as well
+        let saved_path = temp_dir.path().join("saved.json");
+        book.save(&saved_path).unwrap();
+        let fresh = AddressBook::new();
+        let report = fresh
+            .import_json(&saved_path, DuplicatePolicy::Error, false)
+            .unwrap();
+        assert_eq!(report.added.len(), 3);
+    }
+
     #[test]
     fn test_thread_safety() {
         use std::thread;
@@ -748,7 +1353,7 @@ mod tests {
 
         let handle = thread::spawn(move || {
             book_clone
-                .add("Alice".to_string(), "00000000000000000000000000000000000000000000000000000000000abc123".to_string(), None)
+                .add("Alice".to_string(), "0000000000000000000000000000000000000000000000000000000000abc123".to_string(), None)
                 .unwrap();
         });
 
@@ -758,4 +1363,35 @@ mod tests {
         handle.join().unwrap();
         assert_eq!(book.len(), 2);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_import_address_proof() {
+        let contact = KeyPair::generate().unwrap();
+        let proof = AddressProof::create(&contact, "Carol").unwrap();
+
+        let book = AddressBook::new();
+        book.add("Someone".to_string(), contact.address().to_hex(), None)
+            .unwrap();
+        assert!(!book.get("someone").unwrap().is_verified());
+
+        // Attaches to the existing entry even though it was saved as hex
+        let entry = book.import_proof(&proof, None).unwrap();
+        assert_eq!(entry.label, "Someone");
+        assert!(book.get("someone").unwrap().is_verified());
+
+        // Changing the address drops the verification
+        let other = KeyPair::generate().unwrap();
+        book.update("someone", Some(other.address().to_string()), None)
+            .unwrap();
+        assert!(!book.get("someone").unwrap().is_verified());
+
+        // New contacts are added under the proof's label
+        let fresh = AddressBook::new();
+        assert!(fresh.import_proof(&proof, None).unwrap().is_verified());
+        assert!(fresh.get("carol").is_some());
+
+        let mut forged = proof.clone();
+        forged.label = "Mallory".to_string();
+        assert!(AddressBook::new().import_proof(&forged, None).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+//! Chain analytics for TrinityChain
+//!
+//! Computes per-block statistics (block interval, transaction mix, fee rates,
+//! active addresses) incrementally as the chain grows, and aggregates them
+//! into rolling summaries and daily time series for the dashboard.
+
+use crate::blockchain::{Block, Blockchain};
+use crate::error::ChainError;
+use crate::fees::estimate_transaction_size;
+use crate::persistence::Database;
+use crate::transaction::Transaction;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+const MILLIS_PER_DAY: u64 = 86_400_000;
+
+/// Statistics for a single block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockStats {
+    pub height: u64,
+    /// Block timestamp (milliseconds)
+    pub timestamp: u64,
+    /// Milliseconds since the previous block (0 for genesis)
+    pub interval_ms: u64,
+    pub tx_count: usize,
+    pub transfer_count: usize,
+    pub subdivision_count: usize,
+    /// Sum of fees paid by non-coinbase transactions
+    pub fee_total: f64,
+    /// Median fee rate (area per KB) of non-coinbase transactions
+    pub median_fee_rate: f64,
+    /// Hex addresses that sent, received, split or mined in this block
+    pub addresses: Vec<String>,
+}
+
+impl BlockStats {
+    /// Compute statistics for `block`, given the block before it (if any)
+    pub fn from_block(block: &Block, previous: Option<&Block>) -> Self {
+        let interval_ms = previous
+            .map(|p| block.header.timestamp.saturating_sub(p.header.timestamp))
+            .unwrap_or(0);
+
+        let mut transfer_count = 0;
+        let mut subdivision_count = 0;
+        let mut fee_total = 0.0;
+        let mut fee_rates = Vec::new();
+        let mut addresses = HashSet::new();
+
+        for tx in &block.transactions {
+            match tx {
+                Transaction::Transfer(t) => {
+                    transfer_count += 1;
+                    addresses.insert(hex::encode(t.sender));
+                    addresses.insert(hex::encode(t.new_owner));
+                }
+                Transaction::BatchTransfer(t) => {
+                    transfer_count += 1;
+                    addresses.insert(hex::encode(t.sender));
+                    for output in &t.outputs {
+                        addresses.insert(hex::encode(output.recipient));
+                    }
+                }
+                Transaction::Subdivision(t) => {
+                    subdivision_count += 1;
+                    addresses.insert(hex::encode(t.owner_address));
+                }
+                Transaction::Coinbase(t) => {
+                    addresses.insert(hex::encode(t.beneficiary_address));
+                    continue;
+                }
+            }
+
+            let fee = tx.fee_area().to_num::<f64>();
+            fee_total += fee;
+            let size = estimate_transaction_size(tx).max(1) as f64;
+            fee_rates.push(fee * 1000.0 / size);
+        }
+
+        let mut addresses: Vec<String> = addresses.into_iter().collect();
+        addresses.sort();
+
+        BlockStats {
+            height: block.header.height,
+            timestamp: block.header.timestamp,
+            interval_ms,
+            tx_count: block.transactions.len(),
+            transfer_count,
+            subdivision_count,
+            fee_total,
+            median_fee_rate: median(&mut fee_rates),
+            addresses,
+        }
+    }
+}
+
+/// Rolling statistics over the most recent blocks
+#[derive(Debug, Clone, Serialize)]
+pub struct AnalyticsSummary {
+    /// Number of blocks the summary covers
+    pub blocks: usize,
+    pub from_height: u64,
+    pub to_height: u64,
+    pub avg_block_interval_secs: f64,
+    pub avg_tx_per_block: f64,
+    pub median_fee_rate: f64,
+    /// Subdivisions per block
+    pub subdivision_rate: f64,
+    pub active_addresses: usize,
+}
+
+/// Activity aggregated per UTC day
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyActivity {
+    /// Day in `YYYY-MM-DD` form
+    pub day: String,
+    pub blocks: usize,
+    pub transactions: usize,
+    pub active_addresses: usize,
+}
+
+/// Incrementally maintained chain analytics
+#[derive(Debug, Clone, Default)]
+pub struct ChainAnalytics {
+    stats: Vec<BlockStats>,
+    db_path: Option<String>,
+}
+
+impl ChainAnalytics {
+    /// Create an empty, in-memory analytics service
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open analytics backed by the database at `path`, loading any
+    /// previously computed statistics. New statistics are written back as
+    /// they are computed.
+    pub fn open(path: &str) -> Result<Self, ChainError> {
+        let stats = Database::open(path)?.load_block_stats()?;
+        Ok(ChainAnalytics {
+            stats,
+            db_path: Some(path.to_string()),
+        })
+    }
+
+    /// Height of the last analysed block
+    pub fn last_height(&self) -> Option<u64> {
+        self.stats.last().map(|s| s.height)
+    }
+
+    /// Analyse any blocks not yet processed and return how many were added.
+    /// Statistics above the chain tip (e.g. after a reorg) are discarded.
+    pub fn update(&mut self, chain: &Blockchain) -> Result<usize, ChainError> {
+        if self.stats.len() > chain.blocks.len() {
+            self.stats.truncate(chain.blocks.len());
+        }
+        // Recompute the tip in case it was replaced at the same height
+        if let Some(tip) = self.stats.len().checked_sub(1) {
+            if self.stats[tip].timestamp != chain.blocks[tip].header.timestamp {
+                self.stats.pop();
+            }
+        }
+
+        let start = self.stats.len();
+        let new_stats: Vec<BlockStats> = chain.blocks[start..]
+            .iter()
+            .enumerate()
+            .map(|(i, block)| {
+                let previous = (start + i).checked_sub(1).map(|p| &chain.blocks[p]);
+                BlockStats::from_block(block, previous)
+            })
+            .collect();
+
+        if new_stats.is_empty() {
+            return Ok(0);
+        }
+
+        if let Some(path) = &self.db_path {
+            Database::open(path)?.save_block_stats(&new_stats)?;
+        }
+
+        let added = new_stats.len();
+        self.stats.extend(new_stats);
+        Ok(added)
+    }
+
+    /// Per-block statistics for the most recent `limit` blocks, oldest first
+    pub fn series(&self, limit: usize) -> &[BlockStats] {
+        &self.stats[self.stats.len().saturating_sub(limit)..]
+    }
+
+    /// Rolling summary over the most recent `window` blocks
+    pub fn summary(&self, window: usize) -> AnalyticsSummary {
+        let recent = self.series(window);
+        let blocks = recent.len();
+        let count = blocks.max(1) as f64;
+
+        // Genesis has no interval, so only average over blocks that do
+        let intervals: Vec<u64> = recent
+            .iter()
+            .filter(|s| s.height > 0)
+            .map(|s| s.interval_ms)
+            .collect();
+        let avg_block_interval_secs = if intervals.is_empty() {
+            0.0
+        } else {
+            intervals.iter().sum::<u64>() as f64 / intervals.len() as f64 / 1000.0
+        };
+
+        let mut fee_rates: Vec<f64> = recent
+            .iter()
+            .filter(|s| s.tx_count > 1)
+            .map(|s| s.median_fee_rate)
+            .collect();
+
+        let active: HashSet<&String> = recent.iter().flat_map(|s| s.addresses.iter()).collect();
+
+        AnalyticsSummary {
+            blocks,
+            from_height: recent.first().map(|s| s.height).unwrap_or(0),
+            to_height: recent.last().map(|s| s.height).unwrap_or(0),
+            avg_block_interval_secs,
+            avg_tx_per_block: recent.iter().map(|s| s.tx_count).sum::<usize>() as f64 / count,
+            median_fee_rate: median(&mut fee_rates),
+            subdivision_rate: recent.iter().map(|s| s.subdivision_count).sum::<usize>() as f64
+                / count,
+            active_addresses: active.len(),
+        }
+    }
+
+    /// Active addresses, blocks and transactions per UTC day, oldest first
+    pub fn daily_activity(&self) -> Vec<DailyActivity> {
+        let mut days: BTreeMap<u64, (usize, usize, HashSet<&String>)> = BTreeMap::new();
+        for s in &self.stats {
+            let entry = days.entry(s.timestamp / MILLIS_PER_DAY).or_default();
+            entry.0 += 1;
+            entry.1 += s.tx_count;
+            entry.2.extend(s.addresses.iter());
+        }
+
+        days.into_iter()
+            .map(|(day, (blocks, transactions, addresses))| DailyActivity {
+                day: chrono::DateTime::from_timestamp((day * 86_400) as i64, 0)
+                    .map(|d| d.format("%Y-%m-%d").to_string())
+                    .unwrap_or_default(),
+                blocks,
+                transactions,
+                active_addresses: addresses.len(),
+            })
+            .collect()
+    }
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Address;
+
+    fn create_test_address(s: &str) -> Address {
+        let mut address = [0u8; 32];
+        let bytes = s.as_bytes();
+        address[..bytes.len()].copy_from_slice(bytes);
+        Address::from(address)
+    }
+
+    #[test]
+    fn test_median() {
+        assert_eq!(median(&mut []), 0.0);
+        assert_eq!(median(&mut [3.0, 1.0, 2.0]), 2.0);
+        assert_eq!(median(&mut [4.0, 1.0, 2.0, 3.0]), 2.5);
+    }
+
+    #[test]
+    fn test_incremental_update() {
+        let mut chain = Blockchain::new(create_test_address("miner"), 1).unwrap();
+        let mut analytics = ChainAnalytics::new();
+
+        assert_eq!(analytics.update(&chain).unwrap(), 1);
+        assert_eq!(analytics.update(&chain).unwrap(), 0);
+        assert_eq!(analytics.last_height(), Some(0));
+
+        let mut next = chain.blocks[0].clone();
+        next.header.height = 1;
+        next.header.timestamp += 30_000;
+        chain.blocks.push(next);
+
+        assert_eq!(analytics.update(&chain).unwrap(), 1);
+        let summary = analytics.summary(10);
+        assert_eq!(summary.blocks, 2);
+        assert_eq!(summary.avg_block_interval_secs, 30.0);
+        assert_eq!(summary.avg_tx_per_block, 1.0);
+        assert_eq!(summary.active_addresses, 1);
+        assert_eq!(analytics.daily_activity().len(), 1);
+    }
+
+    #[test]
+    fn test_persisted_stats_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("analytics.db");
+        let path = path.to_str().unwrap();
+
+        let chain = Blockchain::new(create_test_address("miner"), 1).unwrap();
+        let mut analytics = ChainAnalytics::open(path).unwrap();
+        analytics.update(&chain).unwrap();
+
+        let reloaded = ChainAnalytics::open(path).unwrap();
+        assert_eq!(reloaded.series(10), analytics.series(10));
+    }
+}
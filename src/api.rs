@@ -4,8 +4,11 @@
 //! mining control, network management, and wallet operations.
 
 use axum::{
-    extract::{Path, Query, Request, State},
-    http::{self, StatusCode},
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        ConnectInfo, Extension, Path, Query, Request, State,
+    },
+    http::{self, header, HeaderMap, StatusCode},
     middleware::{self, Next},
     response::{IntoResponse, Response},
     routing::{get, post},
@@ -13,8 +16,9 @@ use axum::{
 };
 use hex::decode_to_slice;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::HashSet;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -22,14 +26,33 @@ use tokio::sync::RwLock;
 use tokio::task::JoinHandle;
 use tower_http::cors::{CorsLayer, AllowOrigin};
 use tower_http::services::ServeDir;
-
-use crate::blockchain::{Block, Blockchain, Sha256Hash};
-use crate::crypto::KeyPair;
+use tracing::{error, info, warn};
+
+use crate::analytics::ChainAnalytics;
+use crate::balance::BalanceBreakdown;
+use crate::blockchain::{Block, Blockchain, Sha256Hash, TxStatus, SAFE_CONFIRMATIONS};
+use crate::cache::BlockchainCache;
+use crate::capabilities::{Capability, Scope, TokenRecord, TokenStore};
+use crate::config::{active_network, ApiConfig, ApiRateLimitConfig, NodeFeatures};
+use crate::crypto::{verify_message, Address, KeyPair, MessageSignature};
 use crate::error::ChainError;
-use crate::geometry::Coord;
-use crate::miner;
+use crate::events::{ChainEvent, EventBus};
+#[cfg(feature = "faucet")]
+use crate::faucet::{Faucet, FaucetError};
+use crate::fees::{estimate_transaction_size, FeeEstimator};
+use crate::forks::ForkTip;
+use crate::geometry::{Coord, Triangle};
+use crate::miner::{self, BlockTemplateBuilder};
 use crate::network::NetworkNode;
-use crate::transaction::{CoinbaseTx, Transaction};
+use crate::persistence::{Database, MIN_MEMO_QUERY_LEN};
+use crate::rpc::{self, RpcBody, RpcError, RpcMethod, RpcResponse};
+use crate::security::{
+    constant_time_eq, current_timestamp, ApiRateLimiter, RateLimitCounters, RouteClass, SecretBytes,
+};
+use crate::stale_tip::StaleTipAction;
+use crate::sync::SyncState;
+use crate::transaction::{Transaction, TransferTx, MAX_TRANSACTION_AREA};
+use crate::versionbits::DeploymentStatus;
 
 // API Configuration
 const DEFAULT_API_PORT: u16 = 3000;
@@ -39,22 +62,62 @@ const DEFAULT_API_PORT: u16 = 3000;
 const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
 #[allow(dead_code)]
 const MAX_REQUEST_BODY_SIZE: usize = 10 * 1024 * 1024; // 10 MB
-#[allow(dead_code)]
-const RATE_LIMIT_REQUESTS: u32 = 100;
-#[allow(dead_code)]
-const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
 
 /// Node state with mining capabilities
 #[derive(Clone)]
 pub struct Node {
     pub blockchain: Arc<RwLock<Blockchain>>,
     pub network: Arc<NetworkNode>,
+    /// The chain's event bus, subscribable without taking the chain lock
+    events: EventBus,
+    /// Blocks, balances and triangles served by the API, kept in step with
+    /// the chain by `run_api_server_on`
+    cache: BlockchainCache,
     is_mining: Arc<AtomicBool>,
     blocks_mined: Arc<AtomicU64>,
     mining_task: Arc<RwLock<Option<JoinHandle<()>>>>,
     api_stats: Arc<RwLock<ApiStats>>,
+    analytics: Arc<RwLock<ChainAnalytics>>,
+    db_path: Option<String>,
+    capabilities: Option<Arc<ApiCapabilities>>,
+    /// Per-IP limits enforced by `rate_limit_middleware`; `None` when off
+    rate_limiter: Option<Arc<ApiRateLimiter>>,
+    /// Browser origins allowed to call the API (`[api] cors_origins`)
+    cors_origins: Vec<header::HeaderValue>,
+    /// Features enabled by the node's role; decides which routes are served
+    features: NodeFeatures,
+    /// What the mining loop does while the tip is stale
+    on_stale_tip: StaleTipAction,
+    #[cfg(feature = "faucet")]
+    faucet: Option<Arc<RwLock<Faucet>>>,
+}
+
+/// Capability tokens and static API keys enforced by `capability_middleware`
+#[derive(Default)]
+struct ApiCapabilities {
+    admin_token: Option<SecretBytes>,
+    store: RwLock<TokenStore>,
+    path: Option<PathBuf>,
+    /// Static keys from `[[api.keys]]`; never persisted
+    keys: TokenStore,
+    /// Read-only routes need no token
+    public_reads: bool,
+}
+
+impl ApiCapabilities {
+    fn persist(&self, store: &TokenStore) -> Result<(), ApiError> {
+        if let Some(path) = &self.path {
+            store.save(path)?;
+        }
+        Ok(())
+    }
 }
 
+/// Bearer token a request was authorized with (`None` for the admin token
+/// and static API keys, which have no send limit to charge)
+#[derive(Clone)]
+struct ApiToken(Option<String>);
+
 /// API statistics and monitoring
 #[derive(Debug, Default)]
 struct ApiStats {
@@ -85,62 +148,216 @@ impl ApiStats {
     }
 }
 
-/// Rate limiter for API endpoints (reserved for future implementation)
-#[allow(dead_code)]
-#[derive(Debug)]
-struct RateLimiter {
-    requests: HashMap<String, (u32, Instant)>,
-}
-
-#[allow(dead_code)]
-impl RateLimiter {
-    fn new() -> Self {
-        RateLimiter {
-            requests: HashMap::new(),
-        }
-    }
-
-    fn check_rate_limit(&mut self, identifier: &str) -> Result<(), ApiError> {
-        let now = Instant::now();
-
-        // Clean up old entries
-        self.requests
-            .retain(|_, (_, timestamp)| now.duration_since(*timestamp) < RATE_LIMIT_WINDOW);
-
-        let entry = self
-            .requests
-            .entry(identifier.to_string())
-            .or_insert((0, now));
-
-        if now.duration_since(entry.1) >= RATE_LIMIT_WINDOW {
-            // Reset window
-            entry.0 = 0;
-            entry.1 = now;
-        }
-
-        if entry.0 >= RATE_LIMIT_REQUESTS {
-            return Err(ApiError::RateLimitExceeded);
-        }
-
-        entry.0 += 1;
-        Ok(())
-    }
-}
-
 impl Node {
     /// Create a new node instance
     pub fn new(blockchain: Blockchain) -> Self {
+        let events = blockchain.event_bus().clone();
         let blockchain_arc = Arc::new(RwLock::new(blockchain));
         let network_arc = Arc::new(NetworkNode::new(blockchain_arc.clone()));
 
         Self {
             blockchain: blockchain_arc,
             network: network_arc,
+            events,
+            cache: BlockchainCache::new_default(),
             is_mining: Arc::new(AtomicBool::new(false)),
             blocks_mined: Arc::new(AtomicU64::new(0)),
             mining_task: Arc::new(RwLock::new(None)),
             api_stats: Arc::new(RwLock::new(ApiStats::new())),
+            analytics: Arc::new(RwLock::new(ChainAnalytics::new())),
+            db_path: None,
+            capabilities: None,
+            rate_limiter: None,
+            cors_origins: Vec::new(),
+            features: NodeFeatures::default(),
+            on_stale_tip: StaleTipAction::default_for(active_network()),
+            #[cfg(feature = "faucet")]
+            faucet: None,
+        }
+    }
+
+    /// Count the tip as stale after `threshold` without peers or network
+    /// blocks, and then pause, warn or keep mining according to `action`
+    pub fn with_stale_tip(mut self, action: StaleTipAction, threshold: Duration) -> Self {
+        self.network.set_stale_tip_threshold(threshold);
+        self.on_stale_tip = action;
+        self
+    }
+
+    /// Serve testnet payouts from `faucet` on `POST /api/faucet`
+    #[cfg(feature = "faucet")]
+    pub fn with_faucet(mut self, faucet: Faucet) -> Self {
+        self.faucet = Some(Arc::new(RwLock::new(faucet)));
+        self
+    }
+
+    /// Persist mined blocks to, and serve indexed queries from, the
+    /// database at `path`
+    pub fn with_database(mut self, path: &str) -> Self {
+        self.db_path = Some(path.to_string());
+        self
+    }
+
+    /// Require capability tokens on wallet endpoints. `admin_token` may mint
+    /// and revoke tokens on `/api/admin/tokens`; minted tokens are saved to
+    /// `path` when given.
+    pub fn with_capabilities(
+        mut self,
+        admin_token: String,
+        store: TokenStore,
+        path: Option<PathBuf>,
+    ) -> Self {
+        let capabilities = self.capabilities_mut();
+        capabilities.admin_token = Some(SecretBytes::new(admin_token.into_bytes()));
+        capabilities.store = RwLock::new(store);
+        capabilities.path = path;
+        self
+    }
+
+    /// Accept the static `keys` (read-only or admin) as bearer tokens. Once
+    /// any credential is configured, mining control and transaction
+    /// submission need one; read-only routes stay open unless
+    /// `public_reads` is false.
+    pub fn with_api_keys(mut self, keys: TokenStore, public_reads: bool) -> Self {
+        let capabilities = self.capabilities_mut();
+        capabilities.keys = keys;
+        capabilities.public_reads = public_reads;
+        self
+    }
+
+    /// Apply `[api]` from config.toml: capability tokens once an admin token
+    /// is set (`TRINITY_ADMIN_TOKEN` takes precedence), static API keys and
+    /// whether read-only routes stay public
+    pub fn with_api_config(self, api_config: &ApiConfig) -> Result<Self, ChainError> {
+        let admin_token = std::env::var("TRINITY_ADMIN_TOKEN")
+            .ok()
+            .or_else(|| api_config.admin_token.clone())
+            .filter(|t| !t.is_empty());
+        let auth_enabled = admin_token.is_some() || !api_config.keys.is_empty();
+        let node = match admin_token {
+            Some(admin_token) => {
+                let path = match &api_config.tokens_path {
+                    Some(path) => PathBuf::from(path),
+                    None => {
+                        crate::wallet::ensure_wallet_dir()?;
+                        crate::capabilities::get_token_store_path()?
+                    }
+                };
+                let store = TokenStore::load_or_new(&path)?;
+                info!(
+                    "🔐 Capability tokens enabled ({} minted, stored in {})",
+                    store.len(),
+                    path.display()
+                );
+                self.with_capabilities(admin_token, store, Some(path))
+            }
+            None => self,
+        };
+
+        // Static API keys ([[api.keys]]) with read-only or admin scope
+        let node = if api_config.keys.is_empty() && api_config.public_reads {
+            node
+        } else {
+            let keys = TokenStore::from_api_keys(&api_config.keys, current_timestamp())?;
+            info!("🔑 {} API key(s) configured", keys.len());
+            if !api_config.public_reads {
+                info!("🔒 Read-only routes require an API key");
+            }
+            node.with_api_keys(keys, api_config.public_reads)
+        };
+        if !auth_enabled {
+            warn!("⚠️  API authentication is off: wallet, transaction and mining control routes only answer localhost");
+        }
+        Ok(node
+            .with_cors_origins(&api_config.cors_origins)?
+            .with_rate_limit(&api_config.rate_limit))
+    }
+
+    /// Let pages served from `origins` (e.g. `http://localhost:5173`) call
+    /// the API from a browser. Other origins get no CORS headers and, with no
+    /// credential configured, are refused on wallet and control routes.
+    pub fn with_cors_origins(mut self, origins: &[String]) -> Result<Self, ChainError> {
+        self.cors_origins = origins
+            .iter()
+            .map(|origin| {
+                header::HeaderValue::from_str(origin)
+                    .map_err(|_| ChainError::ApiError(format!("Invalid CORS origin: {}", origin)))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(self)
+    }
+
+    /// Whether the request has no `Origin` header (not from a browser page),
+    /// one listed in `[api] cors_origins`, or comes from the dashboard this
+    /// node serves on localhost
+    fn origin_allowed(&self, headers: &HeaderMap) -> bool {
+        let Some(origin) = headers.get(header::ORIGIN) else {
+            return true;
+        };
+        self.cors_origins.contains(origin) || is_local_dashboard(origin, headers.get(header::HOST))
+    }
+
+    /// Limit requests per client IP and route class (`[api.rate_limit]`)
+    pub fn with_rate_limit(mut self, config: &ApiRateLimitConfig) -> Self {
+        self.rate_limiter = if config.enabled {
+            info!(
+                "🚦 API rate limits per IP: {}/min reads, {}/min submissions, {}/min control",
+                config.read.per_minute, config.submit.per_minute, config.control.per_minute
+            );
+            Some(Arc::new(ApiRateLimiter::new(config.clone())))
+        } else {
+            warn!("⚠️  API rate limiting is off");
+            None
+        };
+        self
+    }
+
+    /// Adjust the P2P node (peer database, protocol policy, ...) before the
+    /// node is shared. It keeps the same chain as the API.
+    pub fn with_network(mut self, configure: impl FnOnce(NetworkNode) -> NetworkNode) -> Self {
+        let Ok(network) = Arc::try_unwrap(self.network) else {
+            panic!("the network is configured before the node is shared");
+        };
+        self.network = Arc::new(configure(network));
+        self
+    }
+
+    fn capabilities_mut(&mut self) -> &mut ApiCapabilities {
+        let capabilities = self.capabilities.get_or_insert_with(|| {
+            Arc::new(ApiCapabilities {
+                public_reads: true,
+                ..Default::default()
+            })
+        });
+        Arc::get_mut(capabilities).expect("API access is configured before the node is shared")
+    }
+
+    /// Serve only the routes enabled for the node's role (`[node]` in config.toml)
+    pub fn with_features(mut self, features: NodeFeatures) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// Use a (typically database-backed) analytics service
+    pub fn with_analytics(mut self, analytics: ChainAnalytics) -> Self {
+        self.analytics = Arc::new(RwLock::new(analytics));
+        self
+    }
+
+    /// Receive every block, reorganization and mempool event from now on,
+    /// whether it came from mining, peers or the API
+    pub fn subscribe_events(&self) -> tokio::sync::broadcast::Receiver<ChainEvent> {
+        self.events.subscribe()
+    }
+
+    /// Main-chain block `hash`, through the block cache
+    async fn lookup_block(&self, chain: &Blockchain, hash: &Sha256Hash) -> Option<Block> {
+        if let Some(block) = self.cache.blocks.get(hash).await {
+            return Some(block);
         }
+        let block = chain.get_block_by_hash(hash)?.clone();
+        self.cache.blocks.put(*hash, block.clone()).await;
+        Some(block)
     }
 
     /// Check if currently mining
@@ -162,8 +379,7 @@ impl Node {
             ));
         }
 
-        let mut address = [0u8; 32];
-        hex::decode_to_slice(&miner_address, &mut address)
+        let address = Address::parse(&miner_address)
             .map_err(|e| ApiError::InvalidInput(format!("Invalid miner address: {}", e)))?;
 
         // Check if already mining
@@ -183,42 +399,50 @@ impl Node {
 
         let node_clone = self.clone();
         let task = tokio::spawn(async move {
-            println!("Mining started for address: {}", miner_address);
+            info!("Mining started for address: {}", miner_address);
+            let mut warned_stale = false;
 
             loop {
                 if !node_clone.is_mining.load(Ordering::Relaxed) {
                     break;
                 }
 
+                // Don't silently extend a private fork while cut off from the network
+                let action = node_clone.on_stale_tip;
+                if action != StaleTipAction::Ignore && node_clone.network.check_tip().await {
+                    let pause = action == StaleTipAction::Pause;
+                    if !warned_stale {
+                        warn!(
+                            "🚨 Stale tip: no peers and no block from the network for {}s{}",
+                            node_clone.network.tip_silent_secs(),
+                            if pause { ", mining paused" } else { "" }
+                        );
+                        warned_stale = true;
+                    }
+                    if pause {
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                        continue;
+                    }
+                } else if warned_stale {
+                    info!("✅ Tip no longer stale");
+                    warned_stale = false;
+                }
+
                 let new_block = {
                     let bc = node_clone.blockchain.read().await;
 
                     if bc.blocks.is_empty() {
-                        eprintln!("Cannot mine without a genesis block.");
+                        warn!("Cannot mine without a genesis block.");
                         break;
                     }
 
-                    let last_block = bc.blocks.last().unwrap();
-                    let transactions = bc.mempool.get_all_transactions();
-                    let height = bc.blocks.len() as u64;
-                    let reward = Blockchain::calculate_block_reward(height);
-
-                    let mut address = [0u8; 32];
-                    hex::decode_to_slice(&miner_address, &mut address).unwrap();
-
-                    let coinbase_tx = Transaction::Coinbase(CoinbaseTx {
-                        reward_area: Coord::from_num(reward),
-                        beneficiary_address: address,                        nonce: 0,                    });
-
-                    let mut all_txs = vec![coinbase_tx];
-                    all_txs.extend(transactions);
-
-                    Some(Block::new(
-                        height,
-                        last_block.hash(),
-                        bc.difficulty,
-                        all_txs,
-                    ))
+                    match BlockTemplateBuilder::new(&bc, address).build() {
+                        Ok(template) => Some(template.block),
+                        Err(e) => {
+                            warn!("Cannot build a block template: {}", e);
+                            None
+                        }
+                    }
                 };
 
                 if let Some(block) = new_block {
@@ -227,21 +451,32 @@ impl Node {
                             let mut bc = node_clone.blockchain.write().await;
                             match bc.apply_block(mined_block.clone()) {
                                 Ok(_) => {
+                                    if let Some(path) = &node_clone.db_path {
+                                        if let Err(e) = Database::open(path).and_then(|db| {
+                                            db.save_blockchain_state(
+                                                &mined_block,
+                                                &bc.state,
+                                                bc.difficulty.into(),
+                                            )
+                                        }) {
+                                            warn!("⚠️  Failed to persist mined block: {}", e);
+                                        }
+                                    }
                                     node_clone.blocks_mined.fetch_add(1, Ordering::SeqCst);
                                     node_clone.network.broadcast_block(&mined_block).await;
-                                    println!(
+                                    info!(
                                         "✅ Successfully mined block at height {}",
                                         mined_block.header.height
                                     );
                                 }
                                 Err(e) => {
-                                    eprintln!("❌ Mined block was invalid: {}", e);
+                                    error!("❌ Mined block was invalid: {}", e);
                                     // Continue mining despite this error
                                 }
                             }
                         }
                         Err(e) => {
-                            eprintln!("Mining error: {}", e);
+                            warn!("Mining error: {}", e);
                             // Small delay before retrying
                             tokio::time::sleep(Duration::from_secs(1)).await;
                         }
@@ -255,7 +490,7 @@ impl Node {
             }
 
             node_clone.is_mining.store(false, Ordering::SeqCst);
-            println!("Mining has stopped.");
+            info!("Mining has stopped.");
         });
 
         *self.mining_task.write().await = Some(task);
@@ -278,7 +513,7 @@ impl Node {
             stats.mining_stops += 1;
         }
 
-        println!("Stopping mining...");
+        info!("Stopping mining...");
 
         if let Some(task) = self.mining_task.write().await.take() {
             // Give the task a moment to stop gracefully
@@ -286,7 +521,7 @@ impl Node {
 
             if !task.is_finished() {
                 task.abort();
-                println!("Mining task aborted.");
+                info!("Mining task aborted.");
             }
         }
 
@@ -308,6 +543,11 @@ impl Node {
             uptime_seconds: uptime,
             blocks_mined: self.blocks_mined(),
             is_mining: self.is_mining(),
+            rate_limited: self
+                .rate_limiter
+                .as_ref()
+                .map(|limiter| limiter.rejected())
+                .unwrap_or_default(),
         }
     }
 }
@@ -324,12 +564,16 @@ pub enum ApiError {
     MiningAlreadyRunning,
     MiningNotRunning,
     RateLimitExceeded,
+    Unauthorized(String),
     InternalError(String),
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         let (status, message) = match self {
+            ApiError::BlockchainError(e @ ChainError::DoubleSpendDetected(_)) => {
+                (StatusCode::CONFLICT, e.to_string())
+            }
             ApiError::BlockchainError(e) => (StatusCode::BAD_REQUEST, e.to_string()),
             ApiError::InvalidInput(msg) => (StatusCode::BAD_REQUEST, msg),
             ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
@@ -344,6 +588,7 @@ impl IntoResponse for ApiError {
                 StatusCode::TOO_MANY_REQUESTS,
                 "Rate limit exceeded".to_string(),
             ),
+            ApiError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
             ApiError::InternalError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
         };
 
@@ -357,6 +602,22 @@ impl From<ChainError> for ApiError {
     }
 }
 
+impl From<ApiError> for RpcError {
+    fn from(err: ApiError) -> Self {
+        match err {
+            ApiError::BlockchainError(e) => e.into(),
+            ApiError::InvalidInput(msg) => RpcError::new(rpc::INVALID_PARAMS, msg),
+            ApiError::NotFound(msg) => RpcError::new(rpc::NOT_FOUND, msg),
+            ApiError::Unauthorized(msg) => RpcError::new(rpc::UNAUTHORIZED, msg),
+            ApiError::InternalError(msg) => RpcError::new(rpc::INTERNAL_ERROR, msg),
+            other => {
+                let status = other.into_response().status();
+                RpcError::new(rpc::INTERNAL_ERROR, status.to_string())
+            }
+        }
+    }
+}
+
 #[derive(Serialize)]
 struct ErrorResponse {
     error: String,
@@ -370,6 +631,59 @@ struct ErrorResponse {
 pub struct BalanceResponse {
     pub balance: String, // Changed to String to preserve floating-point precision of Coord
     pub address: String,
+    /// Only with `?detailed=true`
+    #[serde(flatten)]
+    pub breakdown: Option<BalanceBreakdownEntry>,
+}
+
+/// A wallet's balance split by what can be spent now (see `balance::BalanceBreakdown`)
+#[derive(Serialize)]
+pub struct WalletBalanceResponse {
+    pub name: String,
+    pub address: String,
+    #[serde(flatten)]
+    pub breakdown: BalanceBreakdownEntry,
+}
+
+/// `balance::BalanceBreakdown` with amounts as strings
+#[derive(Serialize)]
+pub struct BalanceBreakdownEntry {
+    pub available: String,
+    pub available_triangles: usize,
+    /// Coinbase rewards still inside the maturity window
+    pub immature: String,
+    pub immature_triangles: usize,
+    pub pending_out: String,
+    pub locked_triangles: usize,
+    pub unconfirmed_in: String,
+    /// `available + immature + pending_out`, the plain UTXO balance
+    pub confirmed: String,
+    /// Confirmed value with fewer than `SAFE_CONFIRMATIONS` confirmations
+    pub unsettled: String,
+    pub unsettled_triangles: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_confirmations: Option<u64>,
+    /// `confirmed - unsettled`
+    pub settled: String,
+}
+
+impl From<&BalanceBreakdown> for BalanceBreakdownEntry {
+    fn from(breakdown: &BalanceBreakdown) -> Self {
+        BalanceBreakdownEntry {
+            available: breakdown.available.to_string(),
+            available_triangles: breakdown.available_triangles,
+            immature: breakdown.immature.to_string(),
+            immature_triangles: breakdown.immature_triangles,
+            pending_out: breakdown.pending_out.to_string(),
+            locked_triangles: breakdown.locked_triangles,
+            unconfirmed_in: breakdown.unconfirmed_in.to_string(),
+            confirmed: breakdown.confirmed().to_string(),
+            unsettled: breakdown.unsettled.to_string(),
+            unsettled_triangles: breakdown.unsettled_triangles,
+            min_confirmations: breakdown.min_confirmations,
+            settled: breakdown.settled().to_string(),
+        }
+    }
 }
 
 // Struct to hold a transaction and its containing block height
@@ -377,6 +691,131 @@ pub struct BalanceResponse {
 pub struct TransactionHistoryEntry {
     pub transaction: Transaction,
     pub block_height: u64,
+    pub timestamp: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OwnedTriangleEntry {
+    pub hash: String,
+    pub triangle: Triangle,
+    /// Height an immature coinbase triangle becomes spendable at
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spendable_at: Option<u64>,
+    /// Confirmations, for a triangle with fewer than `SAFE_CONFIRMATIONS`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmations: Option<u64>,
+}
+
+/// One triangle in a `/triangle/:hash/lineage` response
+#[derive(Serialize, Deserialize)]
+pub struct LineageStepEntry {
+    pub hash: String,
+    pub owner: String,
+    pub value: String,
+    pub created_at: u64,
+    pub spent_at: Option<u64>,
+    /// How the triangle came out of the next entry's: subdivision, transfer
+    /// or change. Absent for the coinbase.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LineageResponse {
+    pub triangle: String,
+    /// The triangle first, then each one it came from, ending at a coinbase
+    pub lineage: Vec<LineageStepEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TrianglesResponse {
+    pub address: String,
+    pub count: usize,
+    pub triangles: Vec<OwnedTriangleEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BlocksResponse {
+    pub blocks: Vec<Block>,
+    pub total: usize,
+    pub page: u64,
+    pub limit: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MempoolResponse {
+    pub count: usize,
+    pub transactions: Vec<Transaction>,
+    /// One summary per pending transaction, highest fee rate first
+    pub summaries: Vec<MempoolEntrySummary>,
+}
+
+/// Compact view of a pending transaction for wallet frontends
+#[derive(Serialize, Deserialize)]
+pub struct MempoolEntrySummary {
+    pub hash: String,
+    #[serde(rename = "type")]
+    pub tx_type: String,
+    pub sender: String,
+    pub fee: String,
+    pub size_bytes: usize,
+    /// Fee per 1000 bytes
+    pub fee_rate: f64,
+    pub age_secs: u64,
+}
+
+#[derive(Serialize)]
+pub struct HolderEntry {
+    pub address: String,
+    pub balance: String,
+    pub triangles: usize,
+}
+
+#[derive(Serialize)]
+pub struct SupplyResponse {
+    pub height: u64,
+    pub circulating_area: String,
+    pub total_triangles: usize,
+    pub distinct_owners: usize,
+    pub top_holders: Vec<HolderEntry>,
+}
+
+#[derive(Serialize)]
+pub struct SupplyInfoResponse {
+    pub height: u64,
+    pub circulating_supply: String,
+    /// Subsidy of the next block
+    pub current_reward: f64,
+    pub halving_era: u64,
+    pub next_halving_height: u64,
+    pub blocks_to_halving: u64,
+    pub max_supply: f64,
+}
+
+#[derive(Serialize)]
+pub struct DeploymentsResponse {
+    /// Height the states apply to (the next block)
+    pub height: u64,
+    /// Header version the next block signals, as hex
+    pub next_version: String,
+    pub deployments: Vec<DeploymentStatus>,
+}
+
+#[derive(Serialize)]
+pub struct ForksResponse {
+    pub count: usize,
+    /// The active tip first, then side branches, highest first
+    pub tips: Vec<ForkTip>,
+}
+
+#[derive(Serialize)]
+pub struct FeeEstimateResponse {
+    pub tx_size_bytes: usize,
+    pub low: u64,
+    pub standard: u64,
+    pub high: u64,
+    pub congestion_level: u8,
+    pub mempool_size: usize,
 }
 
 #[derive(Serialize)]
@@ -398,6 +837,8 @@ pub struct ApiStatsResponse {
     pub uptime_seconds: u64,
     pub blocks_mined: u64,
     pub is_mining: bool,
+    /// Requests refused with 429, by route class
+    pub rate_limited: RateLimitCounters,
 }
 
 #[derive(Deserialize)]
@@ -425,6 +866,47 @@ struct PaginationQuery {
     limit: u64,
 }
 
+#[derive(Deserialize)]
+struct BalanceQuery {
+    #[serde(default)]
+    detailed: bool,
+}
+
+/// Paging for address history; without a `limit` the full history is returned
+#[derive(Deserialize)]
+struct AddressTransactionsQuery {
+    #[serde(default = "default_page")]
+    page: u64,
+    limit: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct MemoSearchQuery {
+    q: String,
+    #[serde(default = "default_page")]
+    page: u64,
+    #[serde(default = "default_limit")]
+    limit: u64,
+}
+
+#[derive(Deserialize)]
+struct SupplyQuery {
+    /// Number of top holders to list (max 100)
+    #[serde(default = "default_limit")]
+    top: u64,
+}
+
+#[derive(Deserialize)]
+struct FeeEstimateQuery {
+    /// Transaction size in bytes to estimate for
+    #[serde(default = "default_fee_estimate_size")]
+    size: usize,
+}
+
+fn default_fee_estimate_size() -> usize {
+    250
+}
+
 fn default_page() -> u64 {
     0
 }
@@ -432,6 +914,16 @@ fn default_limit() -> u64 {
     10
 }
 
+#[derive(Deserialize)]
+struct AnalyticsQuery {
+    #[serde(default = "default_analytics_window")]
+    window: usize,
+}
+
+fn default_analytics_window() -> usize {
+    100
+}
+
 // ============================================================================
 // Utility Functions
 // ============================================================================
@@ -449,6 +941,61 @@ fn parse_hash(hash_str: &str) -> Result<Sha256Hash, ApiError> {
     Ok(hash_bytes)
 }
 
+/// Media type for bincode-encoded responses
+pub const BINCODE_MEDIA_TYPE: &str = "application/x-bincode";
+
+/// Response body encoding, negotiated from the request's `Accept` header.
+/// Block, transaction and UTXO endpoints can answer in bincode for indexers
+/// that find JSON too heavy; everything else is JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseEncoding {
+    Json,
+    Bincode,
+}
+
+impl ResponseEncoding {
+    /// Pick the encoding for an `Accept` header value (JSON unless bincode
+    /// is explicitly requested)
+    pub fn from_accept(accept: &str) -> Self {
+        let wants_bincode = accept.split(',').any(|media| {
+            let media = media.split(';').next().unwrap_or("").trim();
+            media.eq_ignore_ascii_case(BINCODE_MEDIA_TYPE)
+                || media.eq_ignore_ascii_case("application/bincode")
+        });
+        if wants_bincode {
+            ResponseEncoding::Bincode
+        } else {
+            ResponseEncoding::Json
+        }
+    }
+
+    fn from_headers(headers: &HeaderMap) -> Self {
+        headers
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .map(Self::from_accept)
+            .unwrap_or(ResponseEncoding::Json)
+    }
+
+    /// Serialize `value` as a response body in this encoding
+    fn respond<T: Serialize>(self, value: &T) -> Response {
+        let mut response = match self {
+            ResponseEncoding::Json => Json(value).into_response(),
+            ResponseEncoding::Bincode => match bincode::serialize(value) {
+                Ok(bytes) => ([(header::CONTENT_TYPE, BINCODE_MEDIA_TYPE)], bytes).into_response(),
+                Err(e) => {
+                    return ApiError::InternalError(format!("Failed to encode response: {}", e))
+                        .into_response()
+                }
+            },
+        };
+        response
+            .headers_mut()
+            .insert(header::VARY, http::HeaderValue::from_static("accept"));
+        response
+    }
+}
+
 // ============================================================================
 // Middleware
 // ============================================================================
@@ -464,22 +1011,284 @@ async fn stats_middleware(State(node): State<Arc<Node>>, req: Request, next: Nex
     response
 }
 
+/// Rate-limit bucket a request is charged to
+fn route_class(method: &http::Method, path: &str) -> RouteClass {
+    let path = path.strip_prefix("/api").unwrap_or(path);
+    if path.starts_with("/admin/") {
+        return RouteClass::Control;
+    }
+    match (method, path) {
+        (&http::Method::POST, "/mining/start" | "/mining/stop") => RouteClass::Control,
+        (&http::Method::POST, _) => RouteClass::Submit,
+        _ => RouteClass::Read,
+    }
+}
+
+/// Refuse clients over their per-IP limit with 429 and `Retry-After`
+async fn rate_limit_middleware(
+    State(node): State<Arc<Node>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let client = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| *addr);
+    let Some(limiter) = node.rate_limiter.as_ref() else {
+        return next.run(req).await;
+    };
+    // Only a server started without connect info gets here; refuse rather
+    // than let every request through unmetered
+    let Some(client) = client else {
+        warn!(
+            "⚠️  Refusing {} {}: no client address to rate limit by",
+            req.method(),
+            req.uri().path()
+        );
+        return ApiError::InternalError("Client address unavailable".to_string()).into_response();
+    };
+
+    let class = route_class(req.method(), req.uri().path());
+    match limiter.check(client.ip(), class) {
+        Ok(()) => next.run(req).await,
+        Err(wait) => {
+            let mut response = ApiError::RateLimitExceeded.into_response();
+            let seconds = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, header::HeaderValue::from(seconds));
+            response
+        }
+    }
+}
+
+/// Rate-limit bucket a JSON-RPC call needing `scope` is charged to
+fn rpc_class(scope: Scope) -> RouteClass {
+    match scope {
+        Scope::Read | Scope::ViewBalance => RouteClass::Read,
+        Scope::CreateUnsigned | Scope::Send => RouteClass::Submit,
+        Scope::Control => RouteClass::Control,
+    }
+}
+
+/// Charge one JSON-RPC call of `class` to `client`, as
+/// `rate_limit_middleware` does for REST requests
+fn check_rpc_rate_limit(
+    node: &Node,
+    client: Option<SocketAddr>,
+    class: RouteClass,
+) -> Result<(), RpcError> {
+    let Some(limiter) = node.rate_limiter.as_ref() else {
+        return Ok(());
+    };
+    let Some(client) = client else {
+        warn!("⚠️  Refusing an RPC call: no client address to rate limit by");
+        return Err(RpcError::new(
+            rpc::INTERNAL_ERROR,
+            "Client address unavailable",
+        ));
+    };
+    limiter.check(client.ip(), class).map_err(|wait| {
+        let seconds = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+        RpcError::new(
+            rpc::RATE_LIMITED,
+            format!("Rate limit exceeded; retry in {}s", seconds),
+        )
+    })
+}
+
+/// Scope an endpoint requires when capability tokens are enabled; `None`
+/// for routes anyone may call
+fn required_scope(method: &http::Method, path: &str, public_reads: bool) -> Option<Scope> {
+    let path = path.strip_prefix("/api").unwrap_or(path);
+    if path.starts_with("/admin/") {
+        return Some(Scope::Control);
+    }
+    if path.starts_with("/address/") || (path.starts_with("/wallet/") && path.ends_with("/balance")) {
+        return Some(Scope::ViewBalance);
+    }
+    match (method, path) {
+        (&http::Method::POST, "/mining/start" | "/mining/stop") => Some(Scope::Control),
+        (&http::Method::POST, "/wallet/unsigned" | "/wallet/create") => Some(Scope::CreateUnsigned),
+        (&http::Method::POST, "/transaction") => Some(Scope::Send),
+        (&http::Method::GET, "/health") => None,
+        (&http::Method::GET, _) if !public_reads => Some(Scope::Read),
+        _ => None,
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+/// Whether a node without any credential configured keeps `scope` to
+/// clients on this machine. Balances stay public, like other reads.
+fn local_only(scope: Scope) -> bool {
+    matches!(scope, Scope::CreateUnsigned | Scope::Send | Scope::Control)
+}
+
+/// Whether `origin` is this server itself reached by a loopback name. A
+/// rebound DNS name also matches its `Host`, so only loopback names count.
+fn is_local_dashboard(origin: &header::HeaderValue, host: Option<&header::HeaderValue>) -> bool {
+    let (Ok(origin), Some(Ok(host))) = (origin.to_str(), host.map(|h| h.to_str())) else {
+        return false;
+    };
+    let hostname = match host.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    let same_origin = origin
+        .strip_prefix("http://")
+        .or_else(|| origin.strip_prefix("https://"))
+        == Some(host);
+    same_origin && matches!(hostname, "localhost" | "127.0.0.1" | "::1")
+}
+
+/// Refuse a `scope` call from `client` when no credential is configured and
+/// the client is not on loopback (or its address is unknown). A loopback
+/// request from a browser page on an origin outside `[api] cors_origins` is
+/// refused too, since any site the user visits can send one.
+fn check_unauthenticated(
+    client: Option<SocketAddr>,
+    origin_allowed: bool,
+    scope: Scope,
+) -> Result<(), ApiError> {
+    if !local_only(scope) {
+        return Ok(());
+    }
+    if client.is_some_and(|addr| addr.ip().is_loopback()) {
+        if origin_allowed {
+            return Ok(());
+        }
+        return Err(ApiError::Unauthorized(
+            "Wallet and control routes are not served to this browser origin; add it to [api] cors_origins"
+                .to_string(),
+        ));
+    }
+    if client.is_none() {
+        warn!("⚠️  Refusing an unauthenticated wallet or control request with no client address");
+    }
+    Err(ApiError::Unauthorized(
+        "Wallet and control routes are only served to localhost until an admin token or API key is configured"
+            .to_string(),
+    ))
+}
+
+/// Enforce capability tokens and API keys on wallet, control and admin
+/// endpoints (and on reads when they are not public). Without any
+/// credential, wallet and control endpoints answer localhost only.
+async fn capability_middleware(
+    State(node): State<Arc<Node>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let Some(capabilities) = node.capabilities.clone() else {
+        let client = req
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| *addr);
+        let origin_allowed = node.origin_allowed(req.headers());
+        let checked = required_scope(req.method(), req.uri().path(), true)
+            .map_or(Ok(()), |scope| {
+                check_unauthenticated(client, origin_allowed, scope)
+            });
+        return match checked {
+            Ok(()) => next.run(req).await,
+            Err(e) => e.into_response(),
+        };
+    };
+
+    let Some(scope) = required_scope(req.method(), req.uri().path(), capabilities.public_reads)
+    else {
+        return next.run(req).await;
+    };
+
+    match authorize(&capabilities, req.headers(), scope).await {
+        Ok(token) => {
+            req.extensions_mut().insert(token);
+            next.run(req).await
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Check the request's bearer token grants `scope`. Admin tokens and API
+/// keys carry no send limit; capability tokens are returned so sends can be
+/// charged to them.
+async fn authorize(
+    capabilities: &ApiCapabilities,
+    headers: &HeaderMap,
+    scope: Scope,
+) -> Result<ApiToken, ApiError> {
+    let token = bearer_token(headers)
+        .ok_or_else(|| ApiError::Unauthorized("Missing bearer token".to_string()))?
+        .to_string();
+
+    let is_admin = capabilities
+        .admin_token
+        .as_ref()
+        .is_some_and(|admin| constant_time_eq(admin.expose(), token.as_bytes()));
+    if is_admin {
+        return Ok(ApiToken(None));
+    }
+
+    let now = current_timestamp();
+    if capabilities.keys.contains(&token) {
+        capabilities
+            .keys
+            .authorize(&token, scope, now)
+            .map_err(|e| ApiError::Unauthorized(e.to_string()))?;
+        return Ok(ApiToken(None));
+    }
+
+    capabilities
+        .store
+        .read()
+        .await
+        .authorize(&token, scope, now)
+        .map_err(|e| ApiError::Unauthorized(e.to_string()))?;
+    Ok(ApiToken(Some(token)))
+}
+
 // ============================================================================
 // API Server
 // ============================================================================
 
 /// Run the API server with production-grade configuration
 pub async fn run_api_server(node: Arc<Node>) -> Result<(), Box<dyn std::error::Error>> {
-    // CORS configuration - allow all origins with credentials
+    // Get port from environment or use default
+    let port = std::env::var("PORT")
+        .ok()
+        .and_then(|p| p.parse::<u16>().ok())
+        .unwrap_or(DEFAULT_API_PORT);
+    run_api_server_on(node, port).await
+}
+
+/// Serve the API and dashboard on `port`
+pub async fn run_api_server_on(
+    node: Arc<Node>,
+    port: u16,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Drop cached balances and spent triangles as blocks connect
+    tokio::spawn(node.cache.clone().follow(node.subscribe_events()));
+
+    // CORS configuration - only the origins listed in [api] cors_origins
     let cors = CorsLayer::new()
-        .allow_origin(AllowOrigin::mirror_request()) // Reflect the request's origin
+        .allow_origin(AllowOrigin::list(node.cors_origins.clone()))
         .allow_methods(vec![
             http::Method::GET,
             http::Method::POST,
             http::Method::OPTIONS,
         ]) // Explicitly allow methods
-        .allow_headers(vec![http::header::CONTENT_TYPE]) // Explicitly allow headers
-        .allow_credentials(true);
+        .allow_headers(vec![
+            http::header::CONTENT_TYPE,
+            http::header::AUTHORIZATION,
+        ]); // Explicitly allow headers
 
     // API routes
     let api_routes = Router::new()
@@ -487,11 +1296,19 @@ pub async fn run_api_server(node: Arc<Node>) -> Result<(), Box<dyn std::error::E
         .route("/blockchain/height", get(get_blockchain_height))
         .route("/blockchain/blocks", get(get_blocks))
         .route("/blockchain/block/:height", get(get_block_by_height))
+        .route("/blockchain/block/hash/:hash", get(get_block_by_hash))
         .route("/blockchain/stats", get(get_blockchain_stats))
+        .route("/blockchain/supply", get(get_supply))
+        .route("/supply", get(get_supply_info))
+        .route("/consensus/deployments", get(get_deployments))
+        .route("/forks", get(get_forks))
+        .route("/blockchain/events", get(chain_events_socket))
         // Transaction endpoints
         .route("/transaction", post(submit_transaction))
         .route("/transaction/:hash", get(get_transaction))
+        .route("/transaction/:hash/status", get(get_transaction_status))
         .route("/mempool", get(get_mempool))
+        .route("/fees/estimate", get(get_fee_estimate))
         // Mining endpoints
         .route("/mining/start", post(start_mining))
         .route("/mining/stop", post(stop_mining))
@@ -499,18 +1316,73 @@ pub async fn run_api_server(node: Arc<Node>) -> Result<(), Box<dyn std::error::E
         // Network endpoints
         .route("/network/peers", get(get_peers))
         .route("/network/info", get(get_network_info))
+        .route("/network/propagation", get(get_network_propagation))
+        .route("/network/events", get(peer_events_socket))
+        .route("/sync/status", get(get_sync_status))
         // Address endpoints
         .route("/address/:addr/balance", get(get_address_balance))
         .route("/address/:addr/transactions", get(get_address_transactions))
+        .route("/address/:addr/triangles", get(get_address_triangles))
+        .route("/triangle/:hash/lineage", get(get_triangle_lineage))
+        .route("/message/verify", post(verify_signed_message))
+        .route("/metrics/validation", get(get_validation_metrics))
+        .route("/metrics/cache", get(get_cache_metrics))
         // Wallet endpoints
         .route("/wallet/create", post(create_wallet))
+        .route("/wallet/unsigned", post(create_unsigned_transfer))
+        .route("/wallet/:name/balance", get(get_wallet_balance))
+        // Capability token administration
+        .route("/admin/tokens", get(list_tokens).post(mint_token))
+        .route("/admin/tokens/:id/revoke", post(revoke_token))
         // System endpoints
         .route("/health", get(health_check))
-        .route("/stats", get(get_api_stats))
-        .layer(middleware::from_fn_with_state(
+        .route("/stats", get(get_api_stats));
+
+    // Analytics endpoints (explorer-style roles)
+    let api_routes = if node.features.analytics {
+        api_routes
+            .route("/analytics/summary", get(get_analytics_summary))
+            .route("/analytics/blocks", get(get_analytics_blocks))
+            .route("/analytics/daily", get(get_analytics_daily))
+    } else {
+        api_routes
+    };
+
+    // Memo search (opt-in: it makes public memos easy to trawl)
+    let api_routes = if node.features.memo_index {
+        api_routes.route("/search/memo", get(search_memos))
+    } else {
+        api_routes
+    };
+
+    // Faucet endpoint
+    #[cfg(feature = "faucet")]
+    let api_routes = api_routes.route("/faucet", post(request_faucet));
+
+    // JSON-RPC authorizes and rate limits per call, so it sits outside the
+    // capability and rate limit layers
+    let rpc_routes = Router::new()
+        .route("/rpc", post(rpc_endpoint))
+        .layer(middleware::from_fn_with_state(
+            node.clone(),
+            stats_middleware,
+        ))
+        .with_state(node.clone())
+        .layer(cors.clone());
+
+    let api_routes = api_routes
+        .layer(middleware::from_fn_with_state(
+            node.clone(),
+            capability_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
             node.clone(),
             stats_middleware,
         ))
+        .layer(middleware::from_fn_with_state(
+            node.clone(),
+            rate_limit_middleware,
+        ))
         .with_state(node)
         .layer(cors.clone());
 
@@ -518,25 +1390,24 @@ pub async fn run_api_server(node: Arc<Node>) -> Result<(), Box<dyn std::error::E
     let serve_dir = ServeDir::new("dashboard/dist");
     let app = Router::new()
         .nest("/api", api_routes)
+        .merge(rpc_routes)
         .fallback_service(serve_dir)
         .layer(cors);
 
-    // Get port from environment or use default
-    let port = std::env::var("PORT")
-        .ok()
-        .and_then(|p| p.parse::<u16>().ok())
-        .unwrap_or(DEFAULT_API_PORT);
-
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
 
     // Binds the TCP listener to the address, defining the 'listener' variable
     let listener = tokio::net::TcpListener::bind(addr).await?;
 
-    println!("🚀 API server listening on http://{}", addr);
-    println!("📊 Dashboard available at http://{}", addr);
-    println!("🔗 API documentation at http://{}/api", addr);
+    info!("🚀 API server listening on http://{}", addr);
+    info!("📊 Dashboard available at http://{}", addr);
+    info!("🔗 API documentation at http://{}/api", addr);
 
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
@@ -557,54 +1428,124 @@ async fn get_blockchain_height(State(node): State<Arc<Node>>) -> impl IntoRespon
     Json(blockchain.blocks.len() as u64)
 }
 
+async fn get_supply(
+    State(node): State<Arc<Node>>,
+    Query(params): Query<SupplyQuery>,
+) -> impl IntoResponse {
+    let blockchain = node.blockchain.read().await;
+    let stats = blockchain.supply_stats(params.top.min(100) as usize);
+
+    Json(SupplyResponse {
+        height: blockchain.blocks.last().map_or(0, |b| b.header.height),
+        circulating_area: format!("{}", stats.circulating_area),
+        total_triangles: stats.total_triangles,
+        distinct_owners: stats.distinct_owners,
+        top_holders: stats
+            .top_holders
+            .into_iter()
+            .map(|h| HolderEntry {
+                address: h.address,
+                balance: format!("{}", h.balance),
+                triangles: h.triangles,
+            })
+            .collect(),
+    })
+}
+
+async fn get_supply_info(State(node): State<Arc<Node>>) -> impl IntoResponse {
+    let info = node.blockchain.read().await.supply_info();
+    Json(SupplyInfoResponse {
+        height: info.height,
+        circulating_supply: format!("{}", info.circulating_supply),
+        current_reward: info.current_reward,
+        halving_era: info.halving_era,
+        next_halving_height: info.next_halving_height,
+        blocks_to_halving: info.blocks_to_halving,
+        max_supply: info.max_supply,
+    })
+}
+
+async fn get_deployments(State(node): State<Arc<Node>>) -> impl IntoResponse {
+    let blockchain = node.blockchain.read().await;
+    Json(DeploymentsResponse {
+        height: blockchain.blocks.last().map_or(0, |b| b.header.height + 1),
+        next_version: format!("{:#010x}", blockchain.next_block_version()),
+        deployments: blockchain.deployment_statuses(),
+    })
+}
+
+async fn get_forks(State(node): State<Arc<Node>>) -> impl IntoResponse {
+    let tips = node.network.fork_tips().await;
+    Json(ForksResponse {
+        count: tips.len(),
+        tips,
+    })
+}
+
 async fn get_blocks(
     State(node): State<Arc<Node>>,
     Query(params): Query<PaginationQuery>,
-) -> impl IntoResponse {
+    headers: HeaderMap,
+) -> Response {
     let blockchain = node.blockchain.read().await;
     let total = blockchain.blocks.len();
 
     let limit = params.limit.min(100); // Max 100 blocks per request
     let offset = params.page * limit;
 
-    if offset >= total as u64 {
-        return Json(serde_json::json!({
-            "blocks": [],
-            "total": total,
-            "page": params.page,
-            "limit": limit
-        }));
-    }
-
-    let blocks: Vec<_> = blockchain
-        .blocks
-        .iter()
-        .rev()
-        .skip(offset as usize)
-        .take(limit as usize)
-        .cloned()
-        .collect();
+    let blocks: Vec<_> = if offset >= total as u64 {
+        Vec::new()
+    } else {
+        blockchain
+            .blocks
+            .iter()
+            .rev()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect()
+    };
 
-    Json(serde_json::json!({
-        "blocks": blocks,
-        "total": total,
-        "page": params.page,
-        "limit": limit
-    }))
+    ResponseEncoding::from_headers(&headers).respond(&BlocksResponse {
+        blocks,
+        total,
+        page: params.page,
+        limit,
+    })
 }
 
 async fn get_block_by_height(
     State(node): State<Arc<Node>>,
     Path(height): Path<u64>,
-) -> Result<Json<Block>, ApiError> {
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let blockchain = node.blockchain.read().await;
+
+    let not_found = || ApiError::NotFound(format!("Block at height {} not found", height));
+    let hash = blockchain
+        .get_block_by_height(height)
+        .map(Block::hash)
+        .ok_or_else(not_found)?;
+    let block = node
+        .lookup_block(&blockchain, &hash)
+        .await
+        .ok_or_else(not_found)?;
+    Ok(ResponseEncoding::from_headers(&headers).respond(&block))
+}
+
+async fn get_block_by_hash(
+    State(node): State<Arc<Node>>,
+    Path(hash_str): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let hash = parse_hash(&hash_str)?;
     let blockchain = node.blockchain.read().await;
 
-    blockchain
-        .blocks
-        .get(height as usize)
-        .cloned()
-        .ok_or_else(|| ApiError::NotFound(format!("Block at height {} not found", height)))
-        .map(Json)
+    let block = node
+        .lookup_block(&blockchain, &hash)
+        .await
+        .ok_or_else(|| ApiError::NotFound(format!("Block {} not found", hash_str)))?;
+    Ok(ResponseEncoding::from_headers(&headers).respond(&block))
 }
 
 async fn get_blockchain_stats(State(node): State<Arc<Node>>) -> impl IntoResponse {
@@ -618,22 +1559,125 @@ async fn get_blockchain_stats(State(node): State<Arc<Node>>) -> impl IntoRespons
     Json(stats)
 }
 
-async fn get_mempool(State(node): State<Arc<Node>>) -> impl IntoResponse {
+async fn get_mempool(State(node): State<Arc<Node>>, headers: HeaderMap) -> Response {
     let blockchain = node.blockchain.read().await;
     let transactions = blockchain.mempool.get_all_transactions();
-    Json(serde_json::json!({
-        "count": transactions.len(),
-        "transactions": transactions
-    }))
+    let now = chrono::Utc::now().timestamp();
+    let mut summaries: Vec<MempoolEntrySummary> = blockchain
+        .mempool
+        .entries()
+        .map(|entry| {
+            let (tx_type, sender) = match &entry.tx {
+                Transaction::Transfer(tx) => ("transfer", hex::encode(tx.sender)),
+                Transaction::Subdivision(tx) => ("subdivision", hex::encode(tx.owner_address)),
+                Transaction::Coinbase(tx) => ("coinbase", hex::encode(tx.beneficiary_address)),
+                Transaction::BatchTransfer(tx) => ("batch_transfer", hex::encode(tx.sender)),
+            };
+            let fee = entry.tx.fee_area();
+            let size_bytes = estimate_transaction_size(&entry.tx);
+            MempoolEntrySummary {
+                hash: entry.tx.hash_str(),
+                tx_type: tx_type.to_string(),
+                sender,
+                fee: format!("{}", fee),
+                size_bytes,
+                fee_rate: fee.to_num::<f64>() * 1000.0 / size_bytes.max(1) as f64,
+                age_secs: now.saturating_sub(entry.received_at()).max(0) as u64,
+            }
+        })
+        .collect();
+    summaries.sort_by(|a, b| {
+        b.fee_rate
+            .partial_cmp(&a.fee_rate)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    ResponseEncoding::from_headers(&headers).respond(&MempoolResponse {
+        count: transactions.len(),
+        transactions,
+        summaries,
+    })
+}
+
+async fn get_fee_estimate(
+    State(node): State<Arc<Node>>,
+    Query(params): Query<FeeEstimateQuery>,
+) -> impl IntoResponse {
+    let blockchain = node.blockchain.read().await;
+
+    let mut estimator = FeeEstimator::default();
+    estimator.update_from_mempool(&blockchain.mempool);
+    let stats = estimator.get_stats(&blockchain.mempool);
+    let size = params.size.max(1);
+
+    Json(FeeEstimateResponse {
+        tx_size_bytes: size,
+        low: estimator.estimate_low_priority(size),
+        standard: estimator.estimate_standard(size),
+        high: estimator.estimate_high_priority(size),
+        congestion_level: stats.congestion_level,
+        mempool_size: blockchain.mempool.len(),
+    })
 }
 
 async fn submit_transaction(
     State(node): State<Arc<Node>>,
+    token: Option<Extension<ApiToken>>,
     Json(tx): Json<Transaction>,
 ) -> Result<Json<SuccessResponse>, ApiError> {
+    let sender_token = token.and_then(|Extension(ApiToken(token))| token);
+    accept_transaction(&node, sender_token, tx).await?;
+    Ok(Json(SuccessResponse {
+        message: "Transaction submitted successfully".to_string(),
+    }))
+}
+
+/// Validate `tx`, charge it to the sender's capability token if any, add it
+/// to the mempool and broadcast it
+async fn accept_transaction(
+    node: &Node,
+    sender_token: Option<String>,
+    tx: Transaction,
+) -> Result<(), ApiError> {
     let mut blockchain = node.blockchain.write().await;
 
-    blockchain.mempool.add_transaction(tx.clone())?;
+    // Reject spends of missing or still immature triangles up front
+    if let Some(input) = tx.input_hash() {
+        if node
+            .cache
+            .utxo_lookup(&blockchain.state, &input)
+            .await
+            .is_none()
+        {
+            return Err(ChainError::TriangleNotFound(format!(
+                "Input triangle {} is not in the UTXO set",
+                hex::encode(input)
+            ))
+            .into());
+        }
+    }
+    let next_height = blockchain.blocks.last().map_or(0, |b| b.header.height + 1);
+    tx.validate(&blockchain.state, next_height)?;
+
+    // Scoped tokens are charged the area they move against their daily limit
+    match (&node.capabilities, sender_token) {
+        (Some(capabilities), Some(secret)) => {
+            let spent = match &tx {
+                Transaction::Transfer(t) => t.total_spent()?,
+                Transaction::BatchTransfer(t) => t.total_spent()?,
+                _ => tx.fee_area(),
+            };
+            let now = current_timestamp();
+            let mut store = capabilities.store.write().await;
+            store
+                .check_spend(&secret, spent, now)
+                .map_err(|e| ApiError::Unauthorized(e.to_string()))?;
+            blockchain.mempool.add_transaction(tx.clone())?;
+            store.record_spend(&secret, spent, now)?;
+            capabilities.persist(&store)?;
+        }
+        _ => blockchain.mempool.add_transaction(tx.clone())?,
+    }
 
     // Update stats
     {
@@ -643,31 +1687,189 @@ async fn submit_transaction(
 
     // Broadcast to network
     node.network.broadcast_transaction(&tx).await;
+    Ok(())
+}
 
-    Ok(Json(SuccessResponse {
-        message: "Transaction submitted successfully".to_string(),
+// ============================================================================
+// JSON-RPC
+// ============================================================================
+
+/// JSON-RPC 2.0 endpoint over the same node state (see [`crate::rpc`]).
+/// Each call is authorized for its own method's scope.
+async fn rpc_endpoint(
+    State(node): State<Arc<Node>>,
+    client: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    body: axum::body::Bytes,
+) -> Response {
+    let client = client.map(|ConnectInfo(addr)| addr);
+    let responses = match RpcBody::parse(&body) {
+        Ok(RpcBody::Single(call)) => match rpc_call(&node, client, &headers, call).await {
+            Some(response) => return Json(response).into_response(),
+            None => Vec::new(),
+        },
+        Ok(RpcBody::Batch(calls)) => {
+            let mut responses = Vec::new();
+            for call in calls {
+                responses.extend(rpc_call(&node, client, &headers, call).await);
+            }
+            responses
+        }
+        Err(response) => {
+            // A body that isn't JSON-RPC still costs a read
+            let response = check_rpc_rate_limit(&node, client, RouteClass::Read).map_or_else(
+                |e| RpcResponse::error(serde_json::Value::Null, e),
+                |()| response,
+            );
+            return Json(response).into_response();
+        }
+    };
+    // Notifications get no response at all
+    if responses.is_empty() {
+        return StatusCode::NO_CONTENT.into_response();
+    }
+    Json(responses).into_response()
+}
+
+/// Run one call, charging it to the client's rate limit by the class of its
+/// method (malformed calls count as reads); `None` for a notification
+async fn rpc_call(
+    node: &Node,
+    client: Option<SocketAddr>,
+    headers: &HeaderMap,
+    call: serde_json::Value,
+) -> Option<RpcResponse> {
+    let (request, id) = match rpc::parse_call(call) {
+        Ok(parsed) => parsed,
+        Err(response) => {
+            return Some(
+                check_rpc_rate_limit(node, client, RouteClass::Read).map_or_else(
+                    |e| RpcResponse::error(serde_json::Value::Null, e),
+                    |()| response,
+                ),
+            )
+        }
+    };
+    let method = RpcMethod::parse(&request);
+    let class = method
+        .as_ref()
+        .map_or(RouteClass::Read, |method| rpc_class(method.scope()));
+    let result = match check_rpc_rate_limit(node, client, class).and(method) {
+        Ok(method) => rpc_execute(node, client, headers, method).await,
+        Err(error) => Err(error),
+    };
+    Some(match result {
+        Ok(value) => RpcResponse::result(id?, value),
+        Err(error) => RpcResponse::error(id?, error),
+    })
+}
+
+async fn rpc_execute(
+    node: &Node,
+    client: Option<SocketAddr>,
+    headers: &HeaderMap,
+    method: RpcMethod,
+) -> Result<serde_json::Value, RpcError> {
+    let token = match &node.capabilities {
+        Some(capabilities) if method.scope() != Scope::Read || !capabilities.public_reads => {
+            authorize(capabilities, headers, method.scope()).await?
+        }
+        Some(_) => ApiToken(None),
+        None => {
+            check_unauthenticated(client, node.origin_allowed(headers), method.scope())?;
+            ApiToken(None)
+        }
+    };
+
+    match method {
+        RpcMethod::SendRawTransaction(tx) => {
+            let txid = tx.hash_str();
+            accept_transaction(node, token.0, tx).await?;
+            Ok(serde_json::Value::String(txid))
+        }
+        query => query.query(&*node.blockchain.read().await),
+    }
+}
+
+#[cfg(feature = "faucet")]
+#[derive(Deserialize)]
+struct FaucetRequest {
+    address: String,
+    #[serde(default)]
+    token: Option<String>,
+}
+
+#[cfg(feature = "faucet")]
+#[derive(Serialize)]
+struct FaucetResponse {
+    tx_hash: String,
+    amount: String,
+    address: String,
+}
+
+#[cfg(feature = "faucet")]
+async fn request_faucet(
+    State(node): State<Arc<Node>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Json(request): Json<FaucetRequest>,
+) -> Result<Json<FaucetResponse>, ApiError> {
+    let faucet = node
+        .faucet
+        .as_ref()
+        .ok_or_else(|| ApiError::NotFound("Faucet is not enabled on this node".to_string()))?;
+
+    let recipient = Address::parse(&request.address)
+        .map_err(|e| ApiError::InvalidInput(format!("Invalid address: {}", e)))?;
+
+    let client_ip = client.ip().to_string();
+    let tx = {
+        let mut blockchain = node.blockchain.write().await;
+        let mut faucet = faucet.write().await;
+        match faucet.request(
+            &mut blockchain,
+            recipient,
+            Some(&client_ip),
+            request.token.as_deref(),
+        ) {
+            Ok(tx) => tx,
+            Err(FaucetError::RateLimited { .. }) => return Err(ApiError::RateLimitExceeded),
+            Err(FaucetError::Unauthorized) => {
+                return Err(ApiError::InvalidInput(FaucetError::Unauthorized.to_string()))
+            }
+            Err(FaucetError::Chain(e)) => return Err(ApiError::BlockchainError(e)),
+        }
+    };
+
+    node.network.broadcast_transaction(&tx).await;
+
+    Ok(Json(FaucetResponse {
+        tx_hash: tx.hash_str(),
+        amount: format!("{}", faucet.read().await.amount()),
+        address: request.address,
     }))
 }
 
 async fn get_transaction(
     State(node): State<Arc<Node>>,
     Path(hash_str): Path<String>,
-) -> Result<Json<Transaction>, ApiError> {
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
     let target_hash = parse_hash(&hash_str)?;
+    let encoding = ResponseEncoding::from_headers(&headers);
     let blockchain = node.blockchain.read().await;
 
     // 1. Search in blocks (on-chain)
     for block in &blockchain.blocks {
         for tx in &block.transactions {
             if tx.hash() == target_hash {
-                return Ok(Json(tx.clone()));
+                return Ok(encoding.respond(tx));
             }
         }
     }
 
     // 2. Search in mempool (unconfirmed)
     if let Some(tx) = blockchain.mempool.get_transaction(&target_hash) {
-        return Ok(Json(tx.clone()));
+        return Ok(encoding.respond(tx));
     }
 
     Err(ApiError::NotFound(format!(
@@ -676,6 +1878,26 @@ async fn get_transaction(
     )))
 }
 
+/// Pending, confirmed (with confirmation count) or unknown. The transaction
+/// index locates confirmed transactions when the node has a database.
+async fn get_transaction_status(
+    State(node): State<Arc<Node>>,
+    Path(hash_str): Path<String>,
+) -> Result<Json<TxStatus>, ApiError> {
+    let hash = parse_hash(&hash_str)?;
+    let indexed_height = match &node.db_path {
+        Some(path) => Database::open(path)
+            .and_then(|db| db.transaction_height(&hash))
+            .unwrap_or_else(|e| {
+                warn!("⚠️  Transaction index lookup failed: {}", e);
+                None
+            }),
+        None => None,
+    };
+    let blockchain = node.blockchain.read().await;
+    Ok(Json(blockchain.transaction_status(&hash, indexed_height)))
+}
+
 async fn start_mining(
     State(node): State<Arc<Node>>,
     Json(req): Json<StartMiningRequest>,
@@ -698,7 +1920,9 @@ async fn stop_mining(State(node): State<Arc<Node>>) -> Result<Json<SuccessRespon
 async fn get_mining_status(State(node): State<Arc<Node>>) -> impl IntoResponse {
     Json(serde_json::json!({
         "is_mining": node.is_mining(),
-        "blocks_mined": node.blocks_mined()
+        "blocks_mined": node.blocks_mined(),
+        "stale_tip": node.network.check_tip().await,
+        "on_stale_tip": node.on_stale_tip.name()
     }))
 }
 
@@ -719,111 +1943,654 @@ async fn get_network_info(State(node): State<Arc<Node>>) -> impl IntoResponse {
     }))
 }
 
+async fn get_sync_status(State(node): State<Arc<Node>>) -> Json<SyncState> {
+    Json(node.network.sync_state().await)
+}
+
+async fn get_network_propagation(State(node): State<Arc<Node>>) -> impl IntoResponse {
+    let peers = node.network.propagation_stats();
+    Json(serde_json::json!({
+        "count": peers.len(),
+        "peers": peers
+    }))
+}
+
+/// WebSocket stream of peer lifecycle events, one JSON text message each
+async fn peer_events_socket(
+    State(node): State<Arc<Node>>,
+    upgrade: WebSocketUpgrade,
+) -> impl IntoResponse {
+    let events = node.network.subscribe_peer_events();
+    upgrade.on_upgrade(move |socket| forward_peer_events(socket, events))
+}
+
+async fn forward_peer_events(
+    mut socket: WebSocket,
+    mut events: tokio::sync::broadcast::Receiver<crate::peer_events::PeerEvent>,
+) {
+    use tokio::sync::broadcast::error::RecvError;
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => event,
+            // Client messages are ignored; the stream ends when it closes
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => continue,
+            },
+        };
+        let text = match event {
+            Ok(event) => serde_json::to_string(&event).unwrap_or_default(),
+            Err(RecvError::Lagged(missed)) => {
+                serde_json::json!({ "event": "lagged", "missed": missed }).to_string()
+            }
+            Err(RecvError::Closed) => return,
+        };
+        if socket.send(Message::Text(text)).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// WebSocket stream of chain events, one JSON text message each
+async fn chain_events_socket(
+    State(node): State<Arc<Node>>,
+    upgrade: WebSocketUpgrade,
+) -> impl IntoResponse {
+    let events = node.subscribe_events();
+    upgrade.on_upgrade(move |socket| forward_chain_events(socket, events))
+}
+
+async fn forward_chain_events(
+    mut socket: WebSocket,
+    mut events: tokio::sync::broadcast::Receiver<ChainEvent>,
+) {
+    use tokio::sync::broadcast::error::RecvError;
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => event,
+            // Client messages are ignored; the stream ends when it closes
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => continue,
+            },
+        };
+        let text = match event {
+            Ok(event) => event.to_json().to_string(),
+            Err(RecvError::Lagged(missed)) => {
+                serde_json::json!({ "event": "lagged", "missed": missed }).to_string()
+            }
+            Err(RecvError::Closed) => return,
+        };
+        if socket.send(Message::Text(text)).await.is_err() {
+            return;
+        }
+    }
+}
+
 async fn get_address_balance(
     State(node): State<Arc<Node>>,
     Path(addr_str): Path<String>,
+    Query(params): Query<BalanceQuery>,
 ) -> impl IntoResponse {
-    let mut addr = [0u8; 32];
-    if hex::decode_to_slice(&addr_str, &mut addr).is_err() {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: "Invalid address format".to_string(),
-            }),
-        )
-            .into_response();
-    }
+    let addr = match Address::parse(&addr_str) {
+        Ok(addr) => addr,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!("Invalid address format: {}", e),
+                }),
+            )
+                .into_response();
+        }
+    };
 
-    let blockchain = node.blockchain.read().await;
+    // The breakdown depends on the mempool and chain depth, so it is never cached
+    let breakdown = if params.detailed {
+        let blockchain = node.blockchain.read().await;
+        Some(balance_breakdown(&blockchain, addr))
+    } else {
+        None
+    };
+
+    let key = hex::encode(addr);
+    let balance = match (&breakdown, node.cache.balances.get_area(&key).await) {
+        (Some(breakdown), _) => breakdown.confirmed(),
+        (None, Some(balance)) => balance,
+        (None, None) => {
+            // Cached under the read lock, so a block connected meanwhile
+            // invalidates the entry after it is written
+            let blockchain = node.blockchain.read().await;
+            let balance = blockchain.state.get_balance(&addr);
+            node.cache.balances.set_area(key, balance).await;
+            balance
+        }
+    };
     // Format the balance (Coord) as a String to preserve floating-point precision
-    let balance = format!("{}", blockchain.state.get_balance(&addr));
+    let balance = format!("{}", balance);
 
     Json(BalanceResponse {
         balance, // Now a String
         address: addr_str,
+        breakdown: breakdown.as_ref().map(BalanceBreakdownEntry::from),
     })
     .into_response()
 }
 
+/// Balance breakdown of `address` against the node's mempool, with
+/// triangles younger than `SAFE_CONFIRMATIONS` counted as unsettled
+fn balance_breakdown(blockchain: &Blockchain, address: Address) -> BalanceBreakdown {
+    let pending = blockchain.mempool.get_all_transactions();
+    let owned: Vec<_> = blockchain
+        .state
+        .utxo_set
+        .iter()
+        .filter(|(_, triangle)| triangle.owner == address)
+        .collect();
+    let next_height = blockchain.blocks.last().map_or(0, |b| b.header.height + 1);
+    let immature = blockchain.state.immature_outputs(next_height);
+    let confirmations = blockchain.recent_confirmations(owned.iter().copied(), SAFE_CONFIRMATIONS);
+    BalanceBreakdown::compute(owned.iter().copied(), &pending, &immature, &[address])
+        .with_confirmations(owned.iter().copied(), &confirmations)
+}
+
+/// Balance breakdown of a wallet stored on the node, by name
+async fn get_wallet_balance(
+    State(node): State<Arc<Node>>,
+    Path(name): Path<String>,
+) -> Result<Json<WalletBalanceResponse>, ApiError> {
+    let address_hex = crate::wallet::named_wallet_address(&name)
+        .map_err(|e| ApiError::NotFound(e.to_string()))?;
+    let address = Address::parse(&address_hex)
+        .map_err(|e| ApiError::InternalError(format!("Wallet '{}' has a bad address: {}", name, e)))?;
+
+    let blockchain = node.blockchain.read().await;
+    let breakdown = balance_breakdown(&blockchain, address);
+
+    Ok(Json(WalletBalanceResponse {
+        name,
+        address: address_hex,
+        breakdown: BalanceBreakdownEntry::from(&breakdown),
+    }))
+}
+
 async fn get_address_transactions(
     State(node): State<Arc<Node>>,
     Path(addr_str): Path<String>,
+    Query(params): Query<AddressTransactionsQuery>,
 ) -> impl IntoResponse {
-    let mut target_addr = [0u8; 32];
-    if hex::decode_to_slice(&addr_str, &mut target_addr).is_err() {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: "Invalid address format".to_string(),
-            }),
-        )
-            .into_response();
-    }
+    let target_addr = match Address::parse(&addr_str) {
+        Ok(addr) => addr,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!("Invalid address format: {}", e),
+                }),
+            )
+                .into_response();
+        }
+    };
 
     let blockchain = node.blockchain.read().await;
 
-    // We will collect transactions found on the blockchain and transactions found in the mempool.
-    let mut transactions: Vec<TransactionHistoryEntry> = Vec::new();
-
+    // 1. Confirmed transactions, newest first. Use the address index when the
+    // node has a database and its role keeps one; otherwise scan the
+    // in-memory chain.
+    let confirmed: Result<(Vec<TransactionHistoryEntry>, u64), ChainError> = match &node.db_path {
+        Some(path) if node.features.address_index => Database::open(path).and_then(|db| {
+            let total = db.count_transactions_for_address(&target_addr)?;
+            let limit = params.limit.unwrap_or(total);
+            let entries = db
+                .get_transactions_for_address(&target_addr, params.page, limit)?
+                .into_iter()
+                .map(|e| TransactionHistoryEntry {
+                    transaction: e.transaction,
+                    block_height: e.block_height,
+                    timestamp: e.timestamp,
+                })
+                .collect();
+            Ok((entries, total))
+        }),
+        _ => {
+            let matching: Vec<TransactionHistoryEntry> = blockchain
+                .blocks
+                .iter()
+                .rev()
+                .flat_map(|block| {
+                    block.transactions.iter().rev().map(move |tx| (block, tx))
+                })
+                .filter(|(_, tx)| tx.addresses().contains(&target_addr))
+                .map(|(block, tx)| TransactionHistoryEntry {
+                    transaction: tx.clone(),
+                    block_height: block.header.height,
+                    timestamp: block.header.timestamp,
+                })
+                .collect();
+            let total = matching.len() as u64;
+            let limit = params.limit.unwrap_or(total);
+            let entries = matching
+                .into_iter()
+                .skip(params.page.saturating_mul(limit) as usize)
+                .take(limit as usize)
+                .collect();
+            Ok((entries, total))
+        }
+    };
 
-    // 1. Search confirmed transactions in the blockchain
-    // We iterate backwards from the latest block for common chronological display in wallets.
-    for block in blockchain.blocks.iter().rev() {
-        let block_height = block.header.height;
-        for tx in &block.transactions {
-            let matches = match tx {
-                // Transfer transaction: involves sender (input) or new_owner (output)
-                Transaction::Transfer(transfer_tx) => {
-                    transfer_tx.sender == target_addr || transfer_tx.new_owner == target_addr
-                }
-                // Subdivision transaction: check if the target address is the owner performing the split
-                Transaction::Subdivision(subdivision_tx) => {
-                    subdivision_tx.owner_address == target_addr
-                }
-                // Coinbase transactions only have a beneficiary (miner)
-                Transaction::Coinbase(coinbase_tx) => {
-                    coinbase_tx.beneficiary_address == target_addr
-                }
-            };
+    let (confirmed, total_confirmed) = match confirmed {
+        Ok(result) => result,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("Failed to load address history: {}", e),
+                }),
+            )
+                .into_response();
+        }
+    };
+    let mut transactions = confirmed;
 
-            if matches {
+    // 2. Unconfirmed transactions in the mempool, listed on the first page only.
+    // These entries will have a block_height of 0 (unconfirmed)
+    if params.page == 0 {
+        for tx in blockchain.mempool.get_all_transactions() {
+            if tx.addresses().contains(&target_addr) {
                 transactions.push(TransactionHistoryEntry {
-                    transaction: tx.clone(),
-                    block_height,
+                    transaction: tx,
+                    block_height: 0,
+                    timestamp: 0,
                 });
             }
         }
     }
 
-    // 2. Search unconfirmed transactions in the mempool
-    // These entries will have a block_height of 0 (unconfirmed)
-    for tx in blockchain.mempool.get_all_transactions() {
-        let matches = match &tx {
-            // Transfer transaction: involves sender (input) or new_owner (output)
-            Transaction::Transfer(transfer_tx) => {
-                transfer_tx.sender == target_addr || transfer_tx.new_owner == target_addr
-            }
-            // Subdivision transaction: check if the target address is the owner performing the split
-            Transaction::Subdivision(subdivision_tx) => subdivision_tx.owner_address == target_addr,
-            // Coinbase transactions are never in the mempool
-            Transaction::Coinbase(_) => false,
-        };
+    Json(serde_json::json!({
+        "address": addr_str,
+        "count": transactions.len(),
+        "total_confirmed": total_confirmed,
+        "page": params.page,
+        "transactions": transactions,
+    }))
+    .into_response()
+}
 
-        if matches {
-            // Unconfirmed transactions are assigned height 0
-            transactions.push(TransactionHistoryEntry {
-                transaction: tx.clone(),
-                block_height: 0,
-            });
+async fn get_address_triangles(
+    State(node): State<Arc<Node>>,
+    Path(addr_str): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let target_addr = match Address::parse(&addr_str) {
+        Ok(addr) => addr,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!("Invalid address format: {}", e),
+                }),
+            )
+                .into_response();
         }
+    };
+
+    let blockchain = node.blockchain.read().await;
+    let next_height = blockchain.blocks.last().map_or(0, |b| b.header.height + 1);
+    let owned: Vec<_> = blockchain
+        .state
+        .utxo_set
+        .iter()
+        .filter(|(_, triangle)| triangle.owner == target_addr)
+        .collect();
+    let confirmations = blockchain.recent_confirmations(owned.iter().copied(), SAFE_CONFIRMATIONS);
+    let triangles: Vec<OwnedTriangleEntry> = owned
+        .into_iter()
+        .map(|(hash, triangle)| OwnedTriangleEntry {
+            hash: hex::encode(hash),
+            triangle: triangle.clone(),
+            spendable_at: blockchain.state.spendable_at(hash, next_height),
+            confirmations: confirmations.get(hash).copied(),
+        })
+        .collect();
+
+    ResponseEncoding::from_headers(&headers).respond(&TrianglesResponse {
+        address: addr_str,
+        count: triangles.len(),
+        triangles,
+    })
+}
+
+async fn get_triangle_lineage(
+    State(node): State<Arc<Node>>,
+    Path(hash_str): Path<String>,
+) -> Result<Json<LineageResponse>, ApiError> {
+    let hash = parse_hash(&hash_str)?;
+    let blockchain = node.blockchain.read().await;
+    let steps = blockchain.trace_lineage(&hash).map_err(|e| match e {
+        ChainError::TriangleNotFound(msg) => ApiError::NotFound(msg),
+        other => other.into(),
+    })?;
+
+    let lineage = steps
+        .into_iter()
+        .map(|step| LineageStepEntry {
+            hash: hex::encode(step.hash),
+            owner: hex::encode(step.node.owner),
+            value: step.node.value.to_string(),
+            created_at: step.node.created_at,
+            spent_at: step.node.spent_at,
+            kind: step.kind.map(|kind| kind.as_str().to_string()),
+        })
+        .collect();
+    Ok(Json(LineageResponse {
+        triangle: hash_str,
+        lineage,
+    }))
+}
+
+/// Bring analytics up to date with the current chain tip
+/// Shown with every memo search result
+const MEMO_SEARCH_NOTICE: &str =
+    "Memos are public: anyone can read them on-chain and search them here";
+
+async fn search_memos(
+    State(node): State<Arc<Node>>,
+    Query(params): Query<MemoSearchQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let query = params.q.trim();
+    if query.chars().count() < MIN_MEMO_QUERY_LEN {
+        return Err(ApiError::InvalidInput(format!(
+            "Memo searches need at least {} characters",
+            MIN_MEMO_QUERY_LEN
+        )));
     }
+    let limit = params.limit.min(100);
 
-    Json(serde_json::json!({
-        "address": addr_str,
+    // The database's memo index when it has one, else a scan of the chain
+    let db = match &node.db_path {
+        Some(path) => Some(Database::open(path)?).filter(|db| db.memo_index_enabled()),
+        None => None,
+    };
+    let (transactions, total): (Vec<TransactionHistoryEntry>, u64) = match db {
+        Some(db) => {
+            let total = db.count_memo_matches(query)?;
+            let entries = db
+                .search_memos(query, params.page, limit)?
+                .into_iter()
+                .map(|e| TransactionHistoryEntry {
+                    transaction: e.transaction,
+                    block_height: e.block_height,
+                    timestamp: e.timestamp,
+                })
+                .collect();
+            (entries, total)
+        }
+        None => {
+            let blockchain = node.blockchain.read().await;
+            let matching: Vec<TransactionHistoryEntry> = blockchain
+                .blocks
+                .iter()
+                .rev()
+                .flat_map(|block| block.transactions.iter().rev().map(move |tx| (block, tx)))
+                .filter(|(_, tx)| tx.memo_contains(query))
+                .map(|(block, tx)| TransactionHistoryEntry {
+                    transaction: tx.clone(),
+                    block_height: block.header.height,
+                    timestamp: block.header.timestamp,
+                })
+                .collect();
+            let total = matching.len() as u64;
+            let entries = matching
+                .into_iter()
+                .skip(params.page.saturating_mul(limit) as usize)
+                .take(limit as usize)
+                .collect();
+            (entries, total)
+        }
+    };
+
+    Ok(Json(serde_json::json!({
+        "query": query,
+        "total": total,
+        "page": params.page,
         "count": transactions.len(),
         "transactions": transactions,
+        "notice": MEMO_SEARCH_NOTICE,
+    })))
+}
+
+async fn refresh_analytics(node: &Node) -> Result<(), ApiError> {
+    let blockchain = node.blockchain.read().await;
+    node.analytics.write().await.update(&blockchain)?;
+    Ok(())
+}
+
+async fn get_analytics_summary(
+    State(node): State<Arc<Node>>,
+    Query(params): Query<AnalyticsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    refresh_analytics(&node).await?;
+    let analytics = node.analytics.read().await;
+    Ok(Json(analytics.summary(params.window)))
+}
+
+async fn get_analytics_blocks(
+    State(node): State<Arc<Node>>,
+    Query(params): Query<AnalyticsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    refresh_analytics(&node).await?;
+    let analytics = node.analytics.read().await;
+    let series = analytics.series(params.window.min(1000));
+    Ok(Json(serde_json::json!({
+        "count": series.len(),
+        "blocks": series,
+    })))
+}
+
+async fn get_analytics_daily(State(node): State<Arc<Node>>) -> Result<impl IntoResponse, ApiError> {
+    refresh_analytics(&node).await?;
+    let analytics = node.analytics.read().await;
+    Ok(Json(analytics.daily_activity()))
+}
+
+async fn get_validation_metrics() -> impl IntoResponse {
+    Json(crate::metrics::validation_summary())
+}
+
+async fn get_cache_metrics(State(node): State<Arc<Node>>) -> impl IntoResponse {
+    Json(node.cache.stats().await)
+}
+
+#[derive(Deserialize)]
+struct VerifyMessageRequest {
+    address: String,
+    message: String,
+    /// Hex of the public key and signature, as printed by `trinity sign-message`
+    signature: String,
+}
+
+#[derive(Serialize)]
+struct VerifyMessageResponse {
+    valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+/// Check a signed message against an address: proof that whoever signed it
+/// controls the address, without moving any triangles
+async fn verify_signed_message(
+    Json(request): Json<VerifyMessageRequest>,
+) -> Result<Json<VerifyMessageResponse>, ApiError> {
+    let address = Address::parse(&request.address)
+        .map_err(|e| ApiError::InvalidInput(format!("Invalid address: {}", e)))?;
+    let signature = MessageSignature::from_hex(&request.signature)
+        .map_err(|e| ApiError::InvalidInput(e.to_string()))?;
+    let verdict = verify_message(&address, &request.message, &signature);
+    Ok(Json(VerifyMessageResponse {
+        valid: verdict.is_ok(),
+        reason: verdict.err().map(|e| e.to_string()),
+    }))
+}
+
+#[derive(Deserialize)]
+struct UnsignedTransferRequest {
+    from: String,
+    to: String,
+    amount: f64,
+    #[serde(default)]
+    fee: f64,
+    #[serde(default)]
+    memo: Option<String>,
+}
+
+#[derive(Serialize)]
+struct UnsignedTransferResponse {
+    transaction: Transaction,
+    /// Hex message the sender must sign before submitting
+    signable_message: String,
+}
+
+/// Build an unsigned transfer from the smallest sufficient triangle of
+/// `from` that is not already being spent, for signing offline
+async fn create_unsigned_transfer(
+    State(node): State<Arc<Node>>,
+    Json(request): Json<UnsignedTransferRequest>,
+) -> Result<Json<UnsignedTransferResponse>, ApiError> {
+    let sender = Address::parse(&request.from)
+        .map_err(|e| ApiError::InvalidInput(format!("Invalid sender address: {}", e)))?;
+    let recipient = Address::parse(&request.to)
+        .map_err(|e| ApiError::InvalidInput(format!("Invalid recipient address: {}", e)))?;
+    if !request.amount.is_finite()
+        || request.amount <= 0.0
+        || !request.fee.is_finite()
+        || request.fee < 0.0
+    {
+        return Err(ApiError::InvalidInput(
+            "Amount must be positive and fee non-negative".to_string(),
+        ));
+    }
+    let max = MAX_TRANSACTION_AREA.to_num::<f64>();
+    if request.amount > max || request.fee > max {
+        return Err(ApiError::InvalidInput(format!(
+            "Amount and fee may not exceed {}",
+            MAX_TRANSACTION_AREA
+        )));
+    }
+
+    let amount = Coord::from_num(request.amount);
+    let fee = Coord::from_num(request.fee);
+    let blockchain = node.blockchain.read().await;
+    let locked: HashSet<Sha256Hash> = blockchain
+        .mempool
+        .get_all_transactions()
+        .into_iter()
+        .filter_map(|tx| match tx {
+            Transaction::Transfer(t) => Some(t.input_hash),
+            Transaction::Subdivision(t) => Some(t.parent_hash),
+            Transaction::BatchTransfer(t) => Some(t.input_hash),
+            Transaction::Coinbase(_) => None,
+        })
+        .collect();
+
+    let input_hash = blockchain
+        .state
+        .utxo_set
+        .iter()
+        .filter(|(hash, triangle)| {
+            triangle.owner == sender
+                && triangle.effective_value() >= amount + fee
+                && !locked.contains(*hash)
+        })
+        .min_by_key(|(_, triangle)| triangle.effective_value())
+        .map(|(hash, _)| *hash)
+        .ok_or_else(|| {
+            ApiError::InvalidInput(format!(
+                "No unspent triangle worth at least {} owned by {}",
+                amount + fee,
+                request.from
+            ))
+        })?;
+
+    let mut tx = TransferTx::new(
+        input_hash,
+        recipient,
+        sender,
+        amount,
+        fee,
+        blockchain.blocks.len() as u64,
+    );
+    if let Some(memo) = request.memo {
+        tx = tx.with_memo(memo)?;
+    }
+
+    Ok(Json(UnsignedTransferResponse {
+        signable_message: hex::encode(tx.signable_message()),
+        transaction: Transaction::Transfer(tx),
+    }))
+}
+
+#[derive(Deserialize)]
+struct MintTokenRequest {
+    #[serde(default)]
+    label: String,
+    capabilities: Vec<Capability>,
+    #[serde(default)]
+    ttl_secs: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct MintTokenResponse {
+    /// Bearer secret; shown only once
+    token: String,
+    #[serde(flatten)]
+    record: TokenRecord,
+}
+
+fn token_admin(node: &Node) -> Result<&ApiCapabilities, ApiError> {
+    node.capabilities
+        .as_deref()
+        .ok_or_else(|| ApiError::NotFound("Capability tokens are not enabled".to_string()))
+}
+
+async fn mint_token(
+    State(node): State<Arc<Node>>,
+    Json(request): Json<MintTokenRequest>,
+) -> Result<Json<MintTokenResponse>, ApiError> {
+    let capabilities = token_admin(&node)?;
+    let mut store = capabilities.store.write().await;
+    let (token, record) = store
+        .mint(
+            &request.label,
+            request.capabilities,
+            request.ttl_secs,
+            current_timestamp(),
+        )
+        .map_err(|e| ApiError::InvalidInput(e.to_string()))?;
+    capabilities.persist(&store)?;
+
+    Ok(Json(MintTokenResponse { token, record }))
+}
+
+async fn list_tokens(State(node): State<Arc<Node>>) -> Result<Json<Vec<TokenRecord>>, ApiError> {
+    let capabilities = token_admin(&node)?;
+    let store = capabilities.store.read().await;
+    Ok(Json(store.list().into_iter().cloned().collect()))
+}
+
+async fn revoke_token(
+    State(node): State<Arc<Node>>,
+    Path(id): Path<String>,
+) -> Result<Json<SuccessResponse>, ApiError> {
+    let capabilities = token_admin(&node)?;
+    let mut store = capabilities.store.write().await;
+    if !store.revoke(&id) {
+        return Err(ApiError::NotFound(format!("Token {} not found", id)));
+    }
+    capabilities.persist(&store)?;
+
+    Ok(Json(SuccessResponse {
+        message: format!("Token {} revoked", id),
     }))
-    .into_response()
 }
 
 async fn create_wallet() -> Result<Json<WalletResponse>, ApiError> {
@@ -0,0 +1,269 @@
+//! Wallet balance breakdown
+//!
+//! A triangle spent by a pending transaction stays in the UTXO set until the
+//! spend is mined, so summing everything an address owns overstates what it
+//! can send. The outpoint locker collects the inputs of pending
+//! transactions and any coinbase triangles still inside the maturity
+//! window; the breakdown splits owned value into what is spendable now,
+//! what is immature, what pending sends have locked, and what pending
+//! transactions will deliver (payments received plus change and subdivision
+//! children).
+//!
+//! Confirmed value is not final either: a reorganization can undo the last
+//! few blocks. Triangles with fewer than
+//! [`SAFE_CONFIRMATIONS`](crate::blockchain::SAFE_CONFIRMATIONS) confirmations
+//! are also counted as unsettled, so a wallet can tell what a short reorg
+//! could take back.
+
+use crate::blockchain::Sha256Hash;
+use crate::crypto::Address;
+use crate::geometry::{Coord, Triangle, GEOMETRIC_TOLERANCE};
+use crate::transaction::Transaction;
+use std::collections::{HashMap, HashSet};
+
+/// Triangles that can't be spent yet: inputs of pending transactions and
+/// immature coinbase rewards
+#[derive(Debug, Clone, Default)]
+pub struct OutpointLocker {
+    locked: HashSet<Sha256Hash>,
+    immature: HashSet<Sha256Hash>,
+}
+
+impl OutpointLocker {
+    pub fn from_pending<'a>(pending: impl IntoIterator<Item = &'a Transaction>) -> Self {
+        let locked = pending
+            .into_iter()
+            .filter_map(|tx| match tx {
+                Transaction::Transfer(tx) => Some(tx.input_hash),
+                Transaction::Subdivision(tx) => Some(tx.parent_hash),
+                Transaction::BatchTransfer(tx) => Some(tx.input_hash),
+                Transaction::Coinbase(_) => None,
+            })
+            .collect();
+        OutpointLocker {
+            locked,
+            immature: HashSet::new(),
+        }
+    }
+
+    /// Also lock coinbase triangles that haven't matured
+    pub fn with_immature(mut self, immature: HashSet<Sha256Hash>) -> Self {
+        self.immature = immature;
+        self
+    }
+
+    pub fn is_locked(&self, hash: &Sha256Hash) -> bool {
+        self.locked.contains(hash) || self.immature.contains(hash)
+    }
+
+    pub fn is_immature(&self, hash: &Sha256Hash) -> bool {
+        self.immature.contains(hash)
+    }
+}
+
+/// Owned value split by whether it can be spent right now
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BalanceBreakdown {
+    /// Confirmed, mature triangles not spent by any pending transaction
+    pub available: Coord,
+    pub available_triangles: usize,
+    /// Coinbase rewards still inside the maturity window
+    pub immature: Coord,
+    pub immature_triangles: usize,
+    /// Confirmed triangles locked as inputs of pending transactions
+    pub pending_out: Coord,
+    pub locked_triangles: usize,
+    /// Value pending transactions will give the wallet once mined
+    pub unconfirmed_in: Coord,
+    /// Confirmed value still shallow enough for a reorganization to undo.
+    /// Overlaps the three confirmed buckets above.
+    pub unsettled: Coord,
+    pub unsettled_triangles: usize,
+    /// Confirmations of the most recently received triangle, if any is
+    /// unsettled
+    pub min_confirmations: Option<u64>,
+}
+
+impl BalanceBreakdown {
+    /// Break down the balance of `addresses`, given the confirmed triangles
+    /// they own, the pending transactions and the immature coinbase outputs
+    pub fn compute<'a>(
+        owned: impl IntoIterator<Item = (&'a Sha256Hash, &'a Triangle)>,
+        pending: &[Transaction],
+        immature: &HashSet<Sha256Hash>,
+        addresses: &[Address],
+    ) -> Self {
+        let locker = OutpointLocker::from_pending(pending).with_immature(immature.clone());
+        let mut breakdown = BalanceBreakdown::default();
+        let mut owned_values = HashMap::new();
+        for (hash, triangle) in owned {
+            let value = triangle.effective_value();
+            owned_values.insert(*hash, value);
+            if locker.is_immature(hash) {
+                breakdown.immature += value;
+                breakdown.immature_triangles += 1;
+            } else if locker.is_locked(hash) {
+                breakdown.pending_out += value;
+                breakdown.locked_triangles += 1;
+            } else {
+                breakdown.available += value;
+                breakdown.available_triangles += 1;
+            }
+        }
+
+        for tx in pending {
+            match tx {
+                Transaction::Transfer(tx) => {
+                    if addresses.contains(&tx.new_owner) {
+                        breakdown.unconfirmed_in += tx.amount;
+                    }
+                    // Change comes back to the sender
+                    if let (Some(input), Ok(spent)) =
+                        (owned_values.get(&tx.input_hash), tx.total_spent())
+                    {
+                        let change = *input - spent;
+                        if addresses.contains(&tx.sender) && change > GEOMETRIC_TOLERANCE {
+                            breakdown.unconfirmed_in += change;
+                        }
+                    }
+                }
+                Transaction::BatchTransfer(tx) => {
+                    for output in &tx.outputs {
+                        if addresses.contains(&output.recipient) {
+                            breakdown.unconfirmed_in += output.amount;
+                        }
+                    }
+                    if let (Some(input), Ok(spent)) =
+                        (owned_values.get(&tx.input_hash), tx.total_spent())
+                    {
+                        let change = *input - spent;
+                        if addresses.contains(&tx.sender) && change > GEOMETRIC_TOLERANCE {
+                            breakdown.unconfirmed_in += change;
+                        }
+                    }
+                }
+                Transaction::Subdivision(tx) if addresses.contains(&tx.owner_address) => {
+                    for child in &tx.children {
+                        breakdown.unconfirmed_in += child.effective_value();
+                    }
+                }
+                _ => {}
+            }
+        }
+        breakdown
+    }
+
+    /// Count the owned triangles listed in `confirmations` (see
+    /// [`Blockchain::recent_confirmations`](crate::blockchain::Blockchain::recent_confirmations))
+    /// as unsettled
+    pub fn with_confirmations<'a>(
+        mut self,
+        owned: impl IntoIterator<Item = (&'a Sha256Hash, &'a Triangle)>,
+        confirmations: &HashMap<Sha256Hash, u64>,
+    ) -> Self {
+        for (hash, triangle) in owned {
+            if let Some(&count) = confirmations.get(hash) {
+                self.unsettled += triangle.effective_value();
+                self.unsettled_triangles += 1;
+                self.min_confirmations =
+                    Some(self.min_confirmations.map_or(count, |m| m.min(count)));
+            }
+        }
+        self
+    }
+
+    /// Everything confirmed, spendable or not (the naive balance)
+    pub fn confirmed(&self) -> Coord {
+        self.available + self.immature + self.pending_out
+    }
+
+    /// Confirmed value deep enough that a short reorganization won't change it
+    pub fn settled(&self) -> Coord {
+        self.confirmed() - self.unsettled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Point;
+    use crate::transaction::TransferTx;
+
+    fn triangle(owner: Address, value: f64) -> Triangle {
+        Triangle::new(
+            Point::new(Coord::from_num(0), Coord::from_num(0)),
+            Point::new(Coord::from_num(1), Coord::from_num(0)),
+            Point::new(Coord::from_num(0), Coord::from_num(1)),
+            None,
+            owner,
+        )
+        .with_effective_value(Coord::from_num(value))
+    }
+
+    #[test]
+    fn test_pending_send_locks_input_and_returns_change() {
+        let me = Address::from([1; 32]);
+        let other = Address::from([2; 32]);
+        let owned = [([1; 32], triangle(me, 10.0)), ([2; 32], triangle(me, 4.0))];
+        let pending = vec![
+            // 10 -> 3 to other, 1 fee, 6 change
+            Transaction::Transfer(TransferTx::new(
+                [1; 32],
+                other,
+                me,
+                Coord::from_num(3),
+                Coord::from_num(1),
+                1,
+            )),
+            // Incoming payment
+            Transaction::Transfer(TransferTx::new(
+                [9; 32],
+                me,
+                other,
+                Coord::from_num(2),
+                Coord::from_num(0),
+                1,
+            )),
+        ];
+
+        let breakdown = BalanceBreakdown::compute(
+            owned.iter().map(|(h, t)| (h, t)),
+            &pending,
+            &HashSet::new(),
+            &[me],
+        );
+        assert_eq!(breakdown.available, Coord::from_num(4));
+        assert_eq!(breakdown.available_triangles, 1);
+        assert_eq!(breakdown.pending_out, Coord::from_num(10));
+        assert_eq!(breakdown.locked_triangles, 1);
+        assert_eq!(breakdown.unconfirmed_in, Coord::from_num(8));
+        assert_eq!(breakdown.confirmed(), Coord::from_num(14));
+    }
+
+    #[test]
+    fn test_immature_coinbase_is_not_available() {
+        let me = Address::from([1; 32]);
+        let owned = [([1; 32], triangle(me, 10.0)), ([2; 32], triangle(me, 4.0))];
+        let immature = HashSet::from([[2; 32]]);
+
+        let breakdown =
+            BalanceBreakdown::compute(owned.iter().map(|(h, t)| (h, t)), &[], &immature, &[me]);
+        assert_eq!(breakdown.available, Coord::from_num(10));
+        assert_eq!(breakdown.immature, Coord::from_num(4));
+        assert_eq!(breakdown.immature_triangles, 1);
+        assert_eq!(breakdown.confirmed(), Coord::from_num(14));
+
+        // The reward was mined two blocks ago, so a reorg could still undo it
+        let confirmations = HashMap::from([([2; 32], 2)]);
+        let breakdown =
+            breakdown.with_confirmations(owned.iter().map(|(h, t)| (h, t)), &confirmations);
+        assert_eq!(breakdown.unsettled, Coord::from_num(4));
+        assert_eq!(breakdown.unsettled_triangles, 1);
+        assert_eq!(breakdown.min_confirmations, Some(2));
+        assert_eq!(breakdown.settled(), Coord::from_num(10));
+
+        let locker = OutpointLocker::default().with_immature(immature);
+        assert!(locker.is_locked(&[2; 32]));
+        assert!(!locker.is_locked(&[1; 32]));
+    }
+}
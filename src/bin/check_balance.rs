@@ -1,20 +1,15 @@
 #![forbid(unsafe_code)]
+use trinitychain::datadir::{self, DataDir};
 use trinitychain::persistence::Database;
-use trinitychain::crypto::Address;
+use trinitychain::crypto::address_from_string;
 use trinitychain::geometry::Coord;
-use sha2::{Digest, Sha256};
-
-fn address_from_string(s: &str) -> Address {
-    let mut hasher = Sha256::new();
-    hasher.update(s.as_bytes());
-    hasher.finalize().into()
-}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    datadir::args();
     // Address to check
     let addr = address_from_string("miner");
 
-    let db = Database::open("trinitychain.db")?;
+    let db = Database::open(DataDir::current().db_path())?;
     let chain = db.load_blockchain()?;
 
     let balance: Coord = chain.state.get_balance(&addr);
@@ -1,5 +1,4 @@
 #![forbid(unsafe_code)]
-#![forbid(unsafe_code)]
 
 use colored::*;
 
@@ -17,6 +16,7 @@ fn main() {
     );
     println!();
     println!("{}", "Available binaries:".bright_green().underline());
+    println!("  - {}", "trinity".bright_white());
     println!("  - {}", "trinity-node".bright_white());
     println!("  - {}", "trinity-mine-block".bright_white());
     println!("  - {}", "trinity-miner".bright_white());
@@ -29,6 +29,7 @@ fn main() {
     println!("  - {}", "trinity-addressbook".bright_white());
     println!("  - {}", "trinity-guestbook".bright_white());
     println!("  - {}", "trinity-connect".bright_white());
+    println!("  - {}", "trinity-snapshot".bright_white());
     println!("  - {}", "trinity-server".bright_white());
     println!("  - {}", "trinity-telegram-bot".bright_white());
     println!();
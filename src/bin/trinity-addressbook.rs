@@ -3,12 +3,14 @@
 //!
 //! Command-line interface for managing TrinityChain address book
 
-use std::env;
-use trinitychain::addressbook::{self, AddressBook};
+use std::path::Path;
+use trinitychain::addressbook::{self, AddressBook, AddressProof, DuplicatePolicy};
+use trinitychain::datadir;
 use trinitychain::error::ChainError;
+use trinitychain::wallet;
 
 fn main() -> Result<(), ChainError> {
-    let args: Vec<String> = env::args().collect();
+    let args: Vec<String> = datadir::args();
 
     if args.len() < 2 {
         print_usage();
@@ -156,6 +158,109 @@ fn main() -> Result<(), ChainError> {
             println!("✅ Exported {} entries to {}", book.len(), path);
         }
 
+        "import" => {
+            if args.len() < 3 {
+                println!("Usage: trinity-addressbook import <path.csv|path.json> [--on-duplicate skip|overwrite|error] [--dry-run]");
+                return Ok(());
+            }
+            let path = Path::new(&args[2]);
+
+            let mut policy = DuplicatePolicy::Skip;
+            let mut dry_run = false;
+            let mut i = 3;
+            while i < args.len() {
+                match args[i].as_str() {
+                    "--on-duplicate" => {
+                        if i + 1 < args.len() {
+                            policy = args[i + 1].parse()?;
+                            i += 2;
+                        } else {
+                            println!("Error: --on-duplicate requires a value");
+                            return Ok(());
+                        }
+                    }
+                    "--dry-run" => {
+                        dry_run = true;
+                        i += 1;
+                    }
+                    _ => {
+                        println!("Unknown option: {}", args[i]);
+                        return Ok(());
+                    }
+                }
+            }
+
+            let book = addressbook::load_default()?;
+            let is_json = path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+            let report = if is_json {
+                book.import_json(path, policy, dry_run)?
+            } else {
+                book.import_csv(path, policy, dry_run)?
+            };
+
+            for (row, reason) in &report.rejected {
+                println!("❌ Row {}: {}", row, reason);
+            }
+            for label in &report.skipped {
+                println!("⏭️  Skipped duplicate '{}'", label);
+            }
+            let verb = if dry_run { "Would import" } else { "Imported" };
+            println!(
+                "✅ {} {} new, {} overwritten, {} skipped, {} rejected",
+                verb,
+                report.added.len(),
+                report.updated.len(),
+                report.skipped.len(),
+                report.rejected.len()
+            );
+            if dry_run {
+                println!("🔍 Dry run: the address book was not changed");
+            } else {
+                addressbook::save_default(&book)?;
+            }
+        }
+
+        "prove" => {
+            if args.len() < 4 {
+                println!("Usage: trinity-addressbook prove <label> <proof.json> [--wallet <name>]");
+                return Ok(());
+            }
+            let label = &args[2];
+            let path = Path::new(&args[3]);
+            let wallet = match args.iter().position(|a| a == "--wallet") {
+                Some(i) if i + 1 < args.len() => wallet::load_named_wallet(&args[i + 1])?,
+                Some(_) => {
+                    println!("Error: --wallet requires a value");
+                    return Ok(());
+                }
+                None => wallet::load_default_wallet()?,
+            };
+
+            let proof = AddressProof::create(&wallet.get_keypair()?, label)?;
+            proof.save(path)?;
+            println!("✅ Signed proof: {} is '{}'", proof.address, proof.label);
+            println!("📤 Send {} to your contact", path.display());
+        }
+
+        "import-proof" => {
+            if args.len() < 3 {
+                println!("Usage: trinity-addressbook import-proof <proof.json> [label]");
+                return Ok(());
+            }
+            let proof = AddressProof::load(Path::new(&args[2]))?;
+            let label = args.get(3).cloned();
+
+            let book = addressbook::load_default()?;
+            let entry = book.import_proof(&proof, label)?;
+            addressbook::save_default(&book)?;
+            println!("✅ Verified '{}' → {}", entry.label, entry.address);
+            if !entry.label.eq_ignore_ascii_case(&proof.label) {
+                println!("🏷️  The contact calls themselves '{}'", proof.label);
+            }
+        }
+
         "stats" => {
             let book = addressbook::load_default()?;
             print_stats(&book);
@@ -191,6 +296,12 @@ fn print_usage() {
     println!("    search <query>                    Search addresses");
     println!("    list                              List all addresses (alias: ls)");
     println!("    export <path.csv>                 Export to CSV file");
+    println!("    import <path.csv|path.json>       Import contacts from CSV or JSON");
+    println!("        --on-duplicate <policy>       skip (default), overwrite or error");
+    println!("        --dry-run                     Report what would change without saving");
+    println!("    prove <label> <proof.json>        Sign a proof of your address for a contact");
+    println!("        --wallet <name>               Wallet to sign with (default: default wallet)");
+    println!("    import-proof <proof.json> [label] Verify a contact's proof and import it");
     println!("    stats                             Show statistics");
     println!("    help                              Show this help message");
     println!();
@@ -201,12 +312,25 @@ fn print_usage() {
     println!("    trinity-addressbook search friend");
     println!("    trinity-addressbook list");
     println!("    trinity-addressbook export backup.csv");
+    println!("    trinity-addressbook import contacts.json --dry-run");
+    println!("    trinity-addressbook prove Bob bob-proof.json");
+    println!("    trinity-addressbook import-proof bob-proof.json");
 }
 
 fn print_entry(entry: &trinitychain::addressbook::AddressEntry) {
     println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
     println!("🏷️  Label: {}", entry.label);
     println!("📬 Address: {}", entry.address);
+    match &entry.proof {
+        Some(proof) if entry.is_verified() => {
+            println!(
+                "✅ Verified: signed as '{}' ({})",
+                proof.label, proof.signed_at
+            )
+        }
+        Some(_) => println!("⚠️  Verification: proof does not match this address"),
+        None => println!("❔ Verification: unverified"),
+    }
     if let Some(notes) = &entry.notes {
         println!("📝 Notes: {}", notes);
     }
@@ -229,7 +353,11 @@ fn print_entry_compact(entry: &trinitychain::addressbook::AddressEntry) {
         })
         .unwrap_or_default();
 
-    println!("  {} → {}{}", entry.label, entry.address, notes_preview);
+    let marker = if entry.is_verified() { "✅" } else { "  " };
+    println!(
+        "{} {} → {}{}",
+        marker, entry.label, entry.address, notes_preview
+    );
 }
 
 fn print_stats(book: &AddressBook) {
@@ -242,6 +370,8 @@ fn print_stats(book: &AddressBook) {
 
     println!("📝 Entries with notes: {}", with_notes);
     println!("📭 Entries without notes: {}", entries.len() - with_notes);
+    let verified = entries.iter().filter(|e| e.is_verified()).count();
+    println!("✅ Verified entries: {}", verified);
 
     if !entries.is_empty() {
         // Find most recently added/updated
@@ -6,22 +6,128 @@
 use std::sync::Arc;
 use trinitychain::api::{run_api_server, Node};
 use trinitychain::blockchain::Blockchain;
+use trinitychain::config::ReplicationMode;
+use trinitychain::datadir::{self, DataDir};
 use trinitychain::error::ChainError;
+use trinitychain::logging::{self, Console, LoggingConfig};
+use trinitychain::replication::{self, FollowOutcome, Standby};
+use trinitychain::shutdown::Shutdown;
 
 #[tokio::main]
 async fn main() -> Result<(), ChainError> {
-    // Initialize logging (if you have env_logger or similar)
-    // env_logger::init();
+    // No other arguments; only --datadir
+    datadir::args();
+    // Log to stderr and to rotated files under the data directory
+    match trinitychain::config::load_config() {
+        Ok(config) => logging::init(
+            &config.logging,
+            "trinity-api",
+            &config.database.path,
+            Console::Stderr,
+        )?,
+        Err(_) => logging::init(
+            &LoggingConfig::default(),
+            "trinity-api",
+            &DataDir::current().db_path().to_string_lossy(),
+            Console::Stderr,
+        )?,
+    };
 
     println!("🚀 Starting TrinityChain API Server...");
 
     // Create new blockchain (or load from persistence if you have that method)
     // For now, just create a new one
-    let blockchain = Blockchain::new([0; 32], 1).unwrap();
+    let mut blockchain = Blockchain::canonical()?;
     println!("✅ Initialized blockchain");
 
-    // Create node
-    let node = Arc::new(Node::new(blockchain));
+    // Node-local relay and mining filter
+    if let Ok(config) = trinitychain::config::load_config() {
+        if let Some(policy) = trinitychain::policy::TxPolicy::from_config(&config.policy)? {
+            println!("🚫 Transaction policy enabled");
+            blockchain.mempool.set_policy(Arc::new(policy));
+        }
+    }
+
+    // Create node; the role decides which features it serves
+    let features = trinitychain::config::load_config()
+        .map(|c| c.node.features())
+        .unwrap_or_default();
+    if !features.api {
+        eprintln!("❌ The HTTP API is disabled for this node's role ([node] api = false)");
+        return Ok(());
+    }
+    let node = Node::new(blockchain).with_features(features);
+
+    // Mining pauses (or warns) on a stale tip as configured in [miner]
+    let node = match trinitychain::config::load_config() {
+        Ok(config) => node.with_stale_tip(
+            config
+                .miner
+                .stale_tip_action(trinitychain::config::active_network()),
+            config.miner.stale_tip_threshold(),
+        ),
+        Err(_) => node,
+    };
+
+    #[cfg(feature = "faucet")]
+    let node = match trinitychain::config::load_config() {
+        Ok(config) => match trinitychain::faucet::Faucet::from_config(&config.faucet)? {
+            Some(faucet) => {
+                println!("🚰 Faucet enabled, paying {} per request", faucet.amount());
+                node.with_faucet(faucet)
+            }
+            None => node,
+        },
+        Err(_) => node,
+    };
+
+    // Capability tokens and static API keys ([api] in config.toml)
+    let api_config = trinitychain::config::load_config()
+        .map(|c| c.api)
+        .unwrap_or_default();
+    let node = node.with_api_config(&api_config)?;
+
+    let node = Arc::new(node);
+
+    // Hot standby: a primary streams its chain to standbys; a standby follows
+    // its primary and only starts serving once promoted
+    let replication = trinitychain::config::load_config()
+        .map(|c| c.replication)
+        .unwrap_or_default();
+    if replication.mode != ReplicationMode::Off {
+        let secret = replication
+            .secret()
+            .ok_or_else(|| ChainError::NetworkError("[replication] needs a secret".to_string()))?;
+        replication::check_secret(&secret)?;
+        if replication.mode == ReplicationMode::Primary {
+            println!("🪞 Replicating to standbys on port {}", replication.port);
+            tokio::spawn(replication::serve(
+                node.blockchain.clone(),
+                replication.port,
+                secret,
+                Shutdown::new(),
+            ));
+        } else {
+            let primary = replication.primary.clone().ok_or_else(|| {
+                ChainError::NetworkError(
+                    "[replication] standby needs the primary's address".to_string(),
+                )
+            })?;
+            // Without a console to promote from, losing the primary promotes
+            println!(
+                "🪞 Standby of {}; serving starts once it has been silent for {}s",
+                primary,
+                replication.failover_timeout().as_secs()
+            );
+            let standby = Standby::new(node.blockchain.clone(), primary, secret)?
+                .with_failover_timeout(replication.failover_timeout())
+                .with_auto_promote(true);
+            if standby.follow().await == FollowOutcome::Shutdown {
+                return Ok(());
+            }
+            println!("👑 Promoted to primary");
+        }
+    }
 
     // Run API server
     println!("Starting API server...");
@@ -5,8 +5,18 @@ use colored::*;
 use comfy_table::presets::UTF8_FULL;
 use comfy_table::Color as TableColor;
 use comfy_table::{Attribute, Cell, ContentArrangement, Table};
-use trinitychain::crypto::address_from_hex;
-use trinitychain::persistence::Database;
+use std::collections::{HashMap, HashSet};
+use trinitychain::balance::BalanceBreakdown;
+use trinitychain::blockchain::{Sha256Hash, COINBASE_MATURITY, SAFE_CONFIRMATIONS};
+use trinitychain::crypto::{address_from_hex, Address};
+use trinitychain::datadir::{self, DataDir};
+use trinitychain::drafts;
+use trinitychain::geometry::Triangle;
+use trinitychain::hdwallet::{self, HDKeystore};
+use trinitychain::persistence::{Database, PENDING_TRANSACTIONS};
+use trinitychain::remote::RemoteNode;
+use trinitychain::transaction::Transaction;
+use trinitychain::wallet;
 
 const LOGO: &str = r#"
 ╔═══════════════════════════════════════════════════════════════╗
@@ -23,19 +33,63 @@ const LOGO: &str = r#"
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("{}", LOGO.bright_cyan());
 
-    let args: Vec<String> = std::env::args().collect();
-    let home = std::env::var("HOME")?;
+    let mut args: Vec<String> = datadir::args();
+    let offline = drafts::offline_from_args_or_env(&mut args);
+    let remote = RemoteNode::from_args_unless_offline(&mut args, offline)?;
+    if offline {
+        println!(
+            "{}",
+            "📴 Offline mode: reading the local database only".yellow()
+        );
+    }
+    // Split the balance into available / pending-out / unconfirmed-in, and
+    // flag what a reorganization of the last few blocks could undo
+    let detailed = args.iter().any(|a| a == "--detailed");
+    args.retain(|a| a != "--detailed");
+
+    // Aggregate across every HD-derived address when --hd is given, or
+    // across one account's addresses with --account <name|index>
+    let account = hdwallet::account_from_args(&mut args)?;
+    let hd_mode = account.is_some() || args.iter().any(|a| a == "--hd");
+    let mut hd_addresses = Vec::new();
+    let mut hd_accounts = None;
 
     // Check if address was provided as argument
-    let my_address = if args.len() > 1 {
+    let mut watch_only = false;
+    let mut hd_keystore = None;
+    let my_address = if hd_mode {
+        let keystore = HDKeystore::load(&hdwallet::get_keystore_path()?).map_err(|e| {
+            eprintln!(
+                "{}",
+                "💡 Run 'trinity-wallet new-address' to create an HD keystore".yellow()
+            );
+            format!("No HD keystore found: {}", e)
+        })?;
+        let label = if let Some(account) = &account {
+            let index = keystore.resolve_account(account)?;
+            hd_addresses = keystore.account_addresses(index);
+            hd_accounts = Some(vec![index]);
+            format!(
+                "HD account {} ({} addresses)",
+                account_label(&keystore, index),
+                hd_addresses.len()
+            )
+        } else {
+            hd_addresses = keystore.addresses();
+            hd_accounts = Some(keystore.account_indices());
+            format!("HD keystore ({} addresses)", hd_addresses.len())
+        };
+        hd_keystore = Some(keystore);
+        label
+    } else if args.len() > 1 {
         args[1].clone()
     } else {
         // Otherwise, load from wallet file (support WALLET_NAME env var)
         let wallet_name = std::env::var("WALLET_NAME").unwrap_or_else(|_| String::new());
         let wallet_file = if wallet_name.is_empty() {
-            format!("{}/.trinitychain/wallet.json", home)
+            wallet::get_default_wallet_path()?
         } else {
-            format!("{}/.trinitychain/wallet_{}.json", home, wallet_name)
+            wallet::get_named_wallet_path(&wallet_name)?
         };
 
         let wallet_content = std::fs::read_to_string(&wallet_file).map_err(|e| {
@@ -50,25 +104,80 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 "{}",
                 "💡 Run 'trinity-wallet-new <name>' to create a wallet".yellow()
             );
-            format!("No wallet found at {}: {}", wallet_file, e)
+            format!("No wallet found at {}: {}", wallet_file.display(), e)
         })?;
 
         let wallet_data: serde_json::Value = serde_json::from_str(&wallet_content)
             .map_err(|e| format!("Failed to parse wallet: {}", e))?;
 
+        watch_only = wallet_data["watch_only"].as_bool().unwrap_or(false);
+
         wallet_data["address"]
             .as_str()
             .ok_or("Wallet address not found in wallet file")?
             .to_string()
     };
 
-    let my_address_bytes = address_from_hex(&my_address)?;
+    let tracked_hex = if hd_mode {
+        hd_addresses
+    } else {
+        vec![my_address.clone()]
+    };
+    let tracked_addresses = tracked_hex
+        .iter()
+        .map(|a| address_from_hex(a))
+        .collect::<Result<Vec<_>, _>>()?;
 
-    let db =
-        Database::open("trinitychain.db").map_err(|e| format!("Failed to open database: {}", e))?;
-    let chain = db
-        .load_blockchain()
-        .map_err(|e| format!("Failed to load blockchain: {}", e))?;
+    let (height, owned, pending, immature, confirmations) = if let Some(node) = &remote {
+        let mut owned = Vec::new();
+        let mut immature = HashSet::new();
+        let mut confirmations = HashMap::new();
+        for address in &tracked_hex {
+            let ((triangles, unripe), recent) = node.triangles_with_confirmations(address)?;
+            owned.extend(triangles);
+            immature.extend(unripe);
+            confirmations.extend(recent);
+        }
+        let pending = if detailed {
+            node.mempool()?
+        } else {
+            Vec::new()
+        };
+        (
+            node.height()?.saturating_sub(1),
+            owned,
+            pending,
+            immature,
+            confirmations,
+        )
+    } else {
+        let db = Database::open(DataDir::current().db_path())
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+        let chain = db
+            .load_blockchain()
+            .map_err(|e| format!("Failed to load blockchain: {}", e))?;
+        let height = chain.blocks.last().map(|b| b.header.height).unwrap_or(0);
+        let immature = chain.state.immature_outputs(height + 1);
+        let owned: Vec<_> = chain
+            .state
+            .utxo_set
+            .iter()
+            .filter(|(_, triangle)| tracked_addresses.contains(&triangle.owner))
+            .map(|(hash, triangle)| (*hash, triangle.clone()))
+            .collect();
+        let confirmations = chain.recent_confirmations(
+            owned.iter().map(|(hash, triangle)| (hash, triangle)),
+            SAFE_CONFIRMATIONS,
+        );
+        let pending = if detailed {
+            db.get_setting(&PENDING_TRANSACTIONS)
+                .map_err(|e| format!("Failed to load pending transactions: {}", e))?
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        (height, owned, pending, immature, confirmations)
+    };
 
     println!(
         "{}",
@@ -97,27 +206,48 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     println!("{}", format!("📍 Address: {}", addr_display).cyan());
+    if watch_only {
+        println!("{}", "👁️  Watch-only wallet".bright_yellow());
+    }
 
-    let height = chain.blocks.last().map(|b| b.header.height).unwrap_or(0);
     println!("{}", format!("📊 Chain Height: {}", height).bright_blue());
+    if let Some(node) = &remote {
+        println!("{}", format!("🌐 Node: {}", node.url()).bright_blue());
+    }
     println!(
         "{}",
         format!("⛓️  Network: {}", "Mainnet".bright_magenta()).bright_blue()
     );
     println!();
 
+    if detailed {
+        let breakdown = BalanceBreakdown::compute(
+            owned.iter().map(|(hash, triangle)| (hash, triangle)),
+            &pending,
+            &immature,
+            &tracked_addresses,
+        )
+        .with_confirmations(
+            owned.iter().map(|(hash, triangle)| (hash, triangle)),
+            &confirmations,
+        );
+        print_breakdown(&breakdown);
+    }
+
+    if let (Some(keystore), Some(accounts)) = (&hd_keystore, &hd_accounts) {
+        print_account_breakdown(keystore, accounts, &owned, &pending, &immature);
+    }
+
     let mut my_triangles = 0;
     let mut total_area = trinitychain::geometry::Coord::from_num(0);
     let mut triangle_list = Vec::new();
 
-    // Filter triangles owned by this address
-    for (hash, triangle) in &chain.state.utxo_set {
-        if triangle.owner == my_address_bytes {
-            my_triangles += 1;
-            total_area += triangle.effective_value();
-            let hash_hex = hex::encode(hash);
-            triangle_list.push((hash_hex, triangle.effective_value()));
-        }
+    // Tally triangles owned by the tracked addresses
+    for (hash, triangle) in &owned {
+        my_triangles += 1;
+        total_area += triangle.effective_value();
+        let hash_hex = hex::encode(hash);
+        triangle_list.push((hash_hex, triangle.effective_value()));
     }
 
     if my_triangles == 0 {
@@ -185,7 +315,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .add_attribute(Attribute::Bold),
         ]);
 
-    triangle_list.sort_by(|a, b| b.1.cmp(&a.1));
+    triangle_list.sort_by_key(|b| std::cmp::Reverse(b.1));
 
     for (idx, (hash, area)) in triangle_list.iter().enumerate() {
         let hash_short = if hash.len() > 20 {
@@ -243,3 +373,166 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// `name (#index)`, or just `#index` for an unnamed account
+fn account_label(keystore: &HDKeystore, account: u32) -> String {
+    match keystore.account_name(account) {
+        Some(name) => format!("{} (#{})", name, account),
+        None => format!("#{}", account),
+    }
+}
+
+/// One row per HD account, splitting its balance like `--detailed` does
+fn print_account_breakdown(
+    keystore: &HDKeystore,
+    accounts: &[u32],
+    owned: &[(Sha256Hash, Triangle)],
+    pending: &[Transaction],
+    immature: &HashSet<Sha256Hash>,
+) {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(
+            [
+                "Account",
+                "Addresses",
+                "Available",
+                "Immature",
+                "Pending out",
+                "Total",
+            ]
+            .into_iter()
+            .map(|h| {
+                Cell::new(h)
+                    .fg(TableColor::Cyan)
+                    .add_attribute(Attribute::Bold)
+            }),
+        );
+
+    for &account in accounts {
+        let addresses: Vec<Address> = keystore
+            .account_addresses(account)
+            .iter()
+            .filter_map(|a| address_from_hex(a).ok())
+            .collect();
+        let breakdown = BalanceBreakdown::compute(
+            owned
+                .iter()
+                .filter(|(_, triangle)| addresses.contains(&triangle.owner))
+                .map(|(hash, triangle)| (hash, triangle)),
+            pending,
+            immature,
+            &addresses,
+        );
+        let total = breakdown.available + breakdown.immature + breakdown.pending_out;
+        table.add_row(vec![
+            Cell::new(account_label(keystore, account)).fg(TableColor::Yellow),
+            Cell::new(addresses.len()).fg(TableColor::White),
+            Cell::new(format!("{:.6}", breakdown.available)).fg(TableColor::Green),
+            Cell::new(format!("{:.6}", breakdown.immature)).fg(TableColor::Magenta),
+            Cell::new(format!("{:.6}", breakdown.pending_out)).fg(TableColor::Yellow),
+            Cell::new(format!("{:.6}", total)).fg(TableColor::Cyan),
+        ]);
+    }
+
+    println!("{}", "📂 Balance by account".bright_cyan().bold());
+    println!("{}", table);
+    if pending.is_empty() {
+        println!(
+            "{}",
+            "💡 Add --detailed to count pending transactions as pending out".bright_blue()
+        );
+    }
+    println!();
+}
+
+fn print_breakdown(breakdown: &BalanceBreakdown) {
+    println!(
+        "{}",
+        "╔══════════════════════════════════════════════════════════╗".bright_cyan()
+    );
+    println!(
+        "{}",
+        "║                  ⚖️  BALANCE BREAKDOWN                    ║"
+            .bright_cyan()
+            .bold()
+    );
+    println!(
+        "{}",
+        "╠══════════════════════════════════════════════════════════╣".bright_cyan()
+    );
+    println!(
+        "{}",
+        format!(
+            "║  ✅ Available:      {:<20.6} ({} triangles) ║",
+            breakdown.available, breakdown.available_triangles
+        )
+        .green()
+    );
+    println!(
+        "{}",
+        format!(
+            "║  ⏳ Immature:       {:<20.6} ({} triangles) ║",
+            breakdown.immature, breakdown.immature_triangles
+        )
+        .bright_magenta()
+    );
+    println!(
+        "{}",
+        format!(
+            "║  📤 Pending out:    {:<20.6} ({} triangles) ║",
+            breakdown.pending_out, breakdown.locked_triangles
+        )
+        .yellow()
+    );
+    println!(
+        "{}",
+        format!("║  📥 Unconfirmed in: {:<36.6} ║", breakdown.unconfirmed_in).bright_blue()
+    );
+    println!(
+        "{}",
+        format!(
+            "║  🕐 Unsettled:      {:<20.6} ({} triangles) ║",
+            breakdown.unsettled, breakdown.unsettled_triangles
+        )
+        .bright_red()
+    );
+    println!(
+        "{}",
+        format!("║  🔒 Settled:        {:<36.6} ║", breakdown.settled()).green()
+    );
+    println!(
+        "{}",
+        "╚══════════════════════════════════════════════════════════╝".bright_cyan()
+    );
+    if breakdown.locked_triangles > 0 {
+        println!(
+            "{}",
+            "💡 Pending-out triangles are spent by unconfirmed transactions and cannot be sent again"
+                .bright_blue()
+        );
+    }
+    if let Some(confirmations) = breakdown.min_confirmations {
+        println!(
+            "{}",
+            format!(
+                "💡 Unsettled triangles have fewer than {} confirmations (newest: {}); a reorganization could still undo them",
+                SAFE_CONFIRMATIONS, confirmations
+            )
+            .bright_blue()
+        );
+    }
+    if breakdown.immature_triangles > 0 {
+        println!(
+            "{}",
+            format!(
+                "💡 Immature triangles are mining rewards, spendable {} blocks after being mined",
+                COINBASE_MATURITY
+            )
+            .bright_blue()
+        );
+    }
+    println!();
+}
@@ -1,15 +1,20 @@
 #![forbid(unsafe_code)]
-use std::env;
+use std::io::{self, BufRead, Write};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
-use trinitychain::blockchain::Blockchain;
-use trinitychain::config::load_config;
-use trinitychain::network::NetworkNode;
+use trinitychain::config::{load_config, save_bootstrap_node};
+use trinitychain::datadir;
+use trinitychain::logging::{self, Console};
+use trinitychain::network::{NetworkNode, Node};
 use trinitychain::persistence::Database;
 
+/// How often the console pings its peers to keep latencies current
+const PING_INTERVAL: Duration = Duration::from_secs(5);
+
 #[tokio::main]
 async fn main() {
-    let args: Vec<String> = env::args().collect();
+    let args: Vec<String> = datadir::args();
     if args.len() < 2 {
         print_usage();
         return;
@@ -21,37 +26,161 @@ async fn main() {
                 eprintln!("Usage: trinity-connect peer <ip:port>");
                 return;
             }
-            connect_peer(&args[2]).await;
+            console(Some(&args[2])).await;
         }
+        "console" => console(None).await,
         "info" => show_info().await,
         _ => print_usage(),
     }
 }
 
-async fn connect_peer(addr: &str) {
-    println!("🔗 Connecting to peer: {}", addr);
+/// Interactive peer management: add, list and disconnect peers until `quit`
+async fn console(first_peer: Option<&str>) {
     let config = load_config().expect("Failed to load config");
+    match logging::init(
+        &config.logging,
+        "trinity-connect",
+        &config.database.path,
+        Console::Off,
+    ) {
+        Ok(Some(path)) => println!("📝 Logging to {}", path.display()),
+        Ok(None) => {}
+        Err(e) => eprintln!("⚠️  Logging disabled: {}", e),
+    }
     let db = Database::open(&config.database.path).expect("DB open failed");
-    let blockchain = db.load_blockchain().unwrap_or_else(|_| {
-        Blockchain::new([0; 32], 1).expect("Failed to create new blockchain")
+    let blockchain = db.load_blockchain().expect("Failed to load blockchain");
+    let node = Arc::new(
+        NetworkNode::new(Arc::new(RwLock::new(blockchain)))
+            .with_peer_db(&config.database.path)
+            .with_protocol_policy(config.network.protocol_policy()),
+    );
+
+    let pinger = node.clone();
+    tokio::spawn(async move {
+        loop {
+            pinger.ping_peers().await;
+            tokio::time::sleep(PING_INTERVAL).await;
+        }
     });
-    let node = Arc::new(NetworkNode::new(Arc::new(RwLock::new(blockchain))));
 
-    let parts: Vec<&str> = addr.split(':').collect();
-    if parts.len() != 2 {
-        eprintln!("❌ Format: IP:PORT");
+    println!("🔺 TrinityChain Peer Console");
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    print_console_help();
+    if let Some(addr) = first_peer {
+        add_peer(&node, addr).await;
+    }
+
+    loop {
+        print!("trinity> ");
+        let _ = io::stdout().flush();
+        let line = tokio::task::spawn_blocking(|| {
+            let mut line = String::new();
+            match io::stdin().lock().read_line(&mut line) {
+                Ok(0) | Err(_) => None,
+                Ok(_) => Some(line),
+            }
+        })
+        .await
+        .ok()
+        .flatten();
+        let Some(line) = line else {
+            println!();
+            break;
+        };
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => {}
+            ["add", addr] => add_peer(&node, addr).await,
+            ["peers"] | ["ls"] => list_peers(&node).await,
+            ["disconnect", addr] | ["rm", addr] => disconnect_peer(&node, addr).await,
+            ["help"] => print_console_help(),
+            ["quit"] | ["exit"] => break,
+            [command, ..] => println!("Unknown command: {} (type help)", command),
+        }
+    }
+
+    node.shutdown().await;
+    println!("👋 Disconnected from all peers");
+}
+
+/// Connect to `addr` and remember it as a bootstrap node in config.toml
+async fn add_peer(node: &Arc<NetworkNode>, addr: &str) {
+    let peer: Node = match addr.parse() {
+        Ok(peer) => peer,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            return;
+        }
+    };
+
+    println!("🔗 Connecting to peer: {}", peer.addr());
+    if let Err(e) = node
+        .clone()
+        .connect_peer(peer.host.clone(), peer.port)
+        .await
+    {
+        eprintln!("❌ Failed: {}", e);
+        return;
+    }
+    println!("✅ Connected! Syncing...");
+
+    match save_bootstrap_node(&peer.addr()) {
+        Ok(true) => println!(
+            "💾 Saved {} as a bootstrap node in config.toml",
+            peer.addr()
+        ),
+        Ok(false) => {}
+        Err(e) => eprintln!("⚠️  Could not save {} to config.toml: {}", peer.addr(), e),
+    }
+}
+
+async fn list_peers(node: &NetworkNode) {
+    let mut peers = node.list_peers().await;
+    if peers.is_empty() {
+        println!("No peers connected");
         return;
     }
+    peers.sort_by_key(Node::addr);
 
-    let host = parts[0].to_string();
-    let port = parts[1].parse::<u16>().unwrap_or(8334);
+    println!("{:<28} {:>10} {:>10}", "PEER", "HEIGHT", "LATENCY");
+    for peer in peers {
+        let addr = peer.addr();
+        let height = node
+            .peer_height(&addr)
+            .await
+            .map_or_else(|| "-".to_string(), |h| h.to_string());
+        let latency = node
+            .peer_latency(&addr)
+            .map_or_else(|| "-".to_string(), |d| format!("{} ms", d.as_millis()));
+        println!("{:<28} {:>10} {:>10}", addr, height, latency);
+    }
+}
 
-    match node.clone().connect_peer(host, port).await {
-        Ok(_) => println!("✅ Connected! Syncing..."),
-        Err(e) => eprintln!("❌ Failed: {}", e),
+async fn disconnect_peer(node: &NetworkNode, addr: &str) {
+    let peer: Node = match addr.parse() {
+        Ok(peer) => peer,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            return;
+        }
+    };
+    if node.disconnect_peer(&peer).await {
+        println!("✂️  Disconnected from {}", peer.addr());
+    } else {
+        println!("Not connected to {}", peer.addr());
     }
 }
 
+fn print_console_help() {
+    println!("Commands:");
+    println!("  add <ip:port>         Connect to a peer and save it as a bootstrap node");
+    println!("  peers                 List connected peers with height and latency (alias: ls)");
+    println!("  disconnect <ip:port>  Close the connection to a peer (alias: rm)");
+    println!("  help                  Show this help");
+    println!("  quit                  Disconnect and exit (alias: exit)");
+}
+
 async fn show_info() {
     println!("🔺 TrinityChain Network Info");
     println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━");
@@ -71,6 +200,7 @@ async fn show_info() {
 
 fn print_usage() {
     println!("Usage:");
-    println!("  trinity-connect peer <ip:port>  - Connect to peer");
+    println!("  trinity-connect console         - Interactive peer console");
+    println!("  trinity-connect peer <ip:port>  - Connect to peer, then open the console");
     println!("  trinity-connect info            - Show your IP");
 }
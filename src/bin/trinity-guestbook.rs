@@ -1,9 +1,11 @@
 #![forbid(unsafe_code)]
 use clap::{Parser, Subcommand};
 use colored::*;
-use std::collections::HashSet;
+use std::path::PathBuf;
+use trinitychain::balance::OutpointLocker;
 use trinitychain::cli::load_blockchain_from_config;
 use trinitychain::crypto::{address_from_hex, address_from_string, address_to_hex};
+use trinitychain::datadir::{self, DataDir};
 use trinitychain::geometry::Coord;
 use trinitychain::transaction::{Transaction, TransferTx};
 use trinitychain::wallet;
@@ -15,6 +17,9 @@ const GUESTBOOK_ADDRESS: &str = "trinity-guestbook-address-00000000000000000";
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Data directory (default ~/.trinitychain); relative database paths are taken inside it
+    #[arg(long, global = true)]
+    datadir: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -34,6 +39,9 @@ enum Commands {
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
+    if let Some(dir) = &cli.datadir {
+        datadir::set_override(dir);
+    }
 
     match &cli.command {
         Commands::Sign { message, wallet } => {
@@ -69,23 +77,20 @@ async fn sign(message: &str, wallet_name: Option<&str>) -> Result<(), Box<dyn st
 
     let (_config, mut chain) = load_blockchain_from_config()?;
 
-    let mut locked_triangles = HashSet::new();
-    if let Ok(mempool_data) = std::fs::read_to_string("mempool.json") {
-        if let Ok(txs) = serde_json::from_str::<Vec<Transaction>>(&mempool_data) {
-            for tx in txs {
-                if let Transaction::Transfer(transfer_tx) = tx {
-                    locked_triangles.insert(transfer_tx.input_hash);
-                }
-            }
-        }
-    }
+    let pending: Vec<Transaction> = std::fs::read_to_string(DataDir::current().mempool_path())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default();
+    let next_height = chain.blocks.last().map_or(0, |b| b.header.height + 1);
+    let locker = OutpointLocker::from_pending(&pending)
+        .with_immature(chain.state.immature_outputs(next_height));
 
     let (input_hash, _input_triangle) = chain
         .state
         .utxo_set
         .iter()
         .find(|(hash, triangle)| {
-            triangle.owner == from_address_bytes && !locked_triangles.contains(*hash) && triangle.effective_value() >= Coord::from_num(0.0001)
+            triangle.owner == from_address_bytes && !locker.is_locked(hash) && triangle.effective_value() >= Coord::from_num(0.0001)
         })
         .ok_or("No UTXOs available to pay for the guestbook signing fee.")?;
 
@@ -108,7 +113,10 @@ async fn sign(message: &str, wallet_name: Option<&str>) -> Result<(), Box<dyn st
     chain.mempool.add_transaction(transaction.clone())?;
 
     let all_txs = chain.mempool.get_all_transactions();
-    std::fs::write("mempool.json", serde_json::to_string(&all_txs)?)?;
+    std::fs::write(
+        DataDir::current().mempool_path(),
+        serde_json::to_string(&all_txs)?,
+    )?;
 
     println!("{}", "Guestbook signed successfully!".bright_green());
     println!("Your message will be on the blockchain soon.");
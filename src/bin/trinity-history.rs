@@ -5,9 +5,18 @@ use colored::*;
 use comfy_table::presets::UTF8_FULL;
 use comfy_table::Color as TableColor;
 use comfy_table::{Attribute, Cell, ContentArrangement, Table};
-use trinitychain::cli::load_blockchain_from_config;
+use std::collections::HashSet;
+use trinitychain::config::load_config;
 use trinitychain::crypto::{address_from_hex, address_to_hex};
+use trinitychain::datadir;
+use trinitychain::drafts;
+use trinitychain::geometry::Coord;
+use trinitychain::hdwallet::{self, HDKeystore};
+use trinitychain::persistence::Database;
+use trinitychain::remote::RemoteNode;
 use trinitychain::transaction::Transaction;
+use trinitychain::txlabels;
+use trinitychain::wallet;
 
 const LOGO: &str = r#"
 ╔═══════════════════════════════════════════════════════════════╗
@@ -24,33 +33,112 @@ const LOGO: &str = r#"
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("{}", LOGO.bright_magenta());
 
-    let home = std::env::var("HOME")?;
-    let wallet_file = format!("{}/.trinitychain/wallet.json", home);
-
-    let wallet_content = std::fs::read_to_string(&wallet_file).map_err(|e| {
-        eprintln!("{}", "╔══════════════════════════════════════════╗".red());
-        eprintln!(
+    let mut args: Vec<String> = datadir::args();
+    let offline = drafts::offline_from_args_or_env(&mut args);
+    let remote = RemoteNode::from_args_unless_offline(&mut args, offline)?;
+    if offline {
+        println!(
             "{}",
-            "║         ❌ Wallet Not Found!            ║".red().bold()
+            "📴 Offline mode: reading the local database only".yellow()
         );
-        eprintln!("{}", "╚══════════════════════════════════════════╝".red());
-        eprintln!();
-        eprintln!("{}", "💡 Run 'wallet new' to create a wallet".yellow());
-        format!("No wallet found at {}: {}", wallet_file, e)
-    })?;
-
-    let wallet_data: serde_json::Value = serde_json::from_str(&wallet_content)
-        .map_err(|e| format!("Failed to parse wallet: {}", e))?;
-
-    let my_address = wallet_data["address"]
-        .as_str()
-        .ok_or("Wallet address not found in wallet file")?;
+    }
 
-    let my_address_bytes = address_from_hex(my_address)?;
+    let export_path = match args.iter().position(|a| a == "--export") {
+        Some(pos) if pos + 1 < args.len() => Some(args[pos + 1].clone()),
+        Some(_) => return Err("--export requires a file path".into()),
+        None => None,
+    };
+    let tx_labels = txlabels::load_default()?;
+    let account = hdwallet::account_from_args(&mut args)?;
+
+    let mut watch_only = false;
+    let (my_address, my_addresses) = if let Some(account) = &account {
+        // Every address derived for one HD account
+        let keystore = HDKeystore::load(&hdwallet::get_keystore_path()?)
+            .map_err(|e| format!("No HD keystore found: {}", e))?;
+        let index = keystore.resolve_account(account)?;
+        let addresses = keystore.account_addresses(index);
+        let name = keystore
+            .account_name(index)
+            .map_or_else(|| format!("#{}", index), |n| format!("{} (#{})", n, index));
+        (
+            format!("HD account {} ({} addresses)", name, addresses.len()),
+            addresses,
+        )
+    } else {
+        let wallet_name = std::env::var("WALLET_NAME").unwrap_or_else(|_| String::new());
+        let wallet_file = if wallet_name.is_empty() {
+            wallet::get_default_wallet_path()?
+        } else {
+            wallet::get_named_wallet_path(&wallet_name)?
+        };
+
+        let wallet_content = std::fs::read_to_string(&wallet_file).map_err(|e| {
+            eprintln!("{}", "╔══════════════════════════════════════════╗".red());
+            eprintln!(
+                "{}",
+                "║         ❌ Wallet Not Found!            ║".red().bold()
+            );
+            eprintln!("{}", "╚══════════════════════════════════════════╝".red());
+            eprintln!();
+            eprintln!("{}", "💡 Run 'wallet new' to create a wallet".yellow());
+            format!("No wallet found at {}: {}", wallet_file.display(), e)
+        })?;
+
+        let wallet_data: serde_json::Value = serde_json::from_str(&wallet_content)
+            .map_err(|e| format!("Failed to parse wallet: {}", e))?;
+
+        watch_only = wallet_data["watch_only"].as_bool().unwrap_or(false);
+
+        let address = wallet_data["address"]
+            .as_str()
+            .ok_or("Wallet address not found in wallet file")?
+            .to_string();
+        (address.clone(), vec![address])
+    };
 
-    let (_config, chain) = load_blockchain_from_config()?;
+    let mine = my_addresses
+        .iter()
+        .map(|a| address_from_hex(a))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Collect (block height, block timestamp, transaction) for every confirmed
+    // tx, once even if it touches several of the addresses
+    let mut entries: Vec<(u64, u64, Transaction)> = Vec::new();
+    let mut seen = HashSet::new();
+    let db = if remote.is_none() {
+        Some(Database::open(&load_config()?.database.path)?)
+    } else {
+        None
+    };
+    for (address_hex, address) in my_addresses.iter().zip(&mine) {
+        let found: Vec<(u64, u64, Transaction)> = if let Some(node) = &remote {
+            node.history(address_hex)?
+                .into_iter()
+                .filter(|e| e.block_height > 0)
+                .rev()
+                .map(|e| (e.block_height, e.timestamp, e.transaction))
+                .collect()
+        } else if let Some(db) = &db {
+            let count = db.count_transactions_for_address(address)?;
+            db.get_transactions_for_address(address, 0, count)?
+                .into_iter()
+                .rev()
+                .map(|e| (e.block_height, e.timestamp, e.transaction))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        for entry in found {
+            if seen.insert(entry.2.hash()) {
+                entries.push(entry);
+            }
+        }
+    }
+    // Oldest first
+    entries.sort_by_key(|(height, _, _)| *height);
 
-    let addr_display = if my_address.len() > 40 {
+    let addr_display = if account.is_none() && my_address.len() > 40 {
         format!(
             "{}...{}",
             &my_address[..20],
@@ -76,6 +164,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     );
     println!();
     println!("{}", format!("📍 Address: {}", addr_display).cyan());
+    if watch_only {
+        println!("{}", "👁️  Watch-only wallet".bright_yellow());
+    }
+    if let Some(node) = &remote {
+        println!("{}", format!("🌐 Node: {}", node.url()).cyan());
+    }
     println!();
 
     let mut tx_count = 0;
@@ -85,6 +179,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     struct TxRecord {
         block_height: u64,
+        tx_hash: String,
         tx_type: String,
         direction: String,
         details: String,
@@ -94,106 +189,158 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let mut transactions: Vec<TxRecord> = Vec::new();
 
-    // Iterate through all blocks
-    for block in &chain.blocks {
-        for tx in &block.transactions {
-            match tx {
-                Transaction::Transfer(transfer_tx) => {
-                    let is_sender = transfer_tx.sender == my_address_bytes;
-                    let is_receiver = transfer_tx.new_owner == my_address_bytes;
-
-                    if is_sender || is_receiver {
-                        tx_count += 1;
-
-                        let (direction, color) = if is_sender && is_receiver {
-                            ("↔️  Self".to_string(), TableColor::Yellow)
-                        } else if is_sender {
-                            sent_count += 1;
-                            ("📤 Sent".to_string(), TableColor::Red)
-                        } else {
-                            received_count += 1;
-                            ("📥 Received".to_string(), TableColor::Green)
-                        };
-
-                        let hash_hex = hex::encode(transfer_tx.input_hash);
-                        let hash_short = if hash_hex.len() > 16 {
-                            format!("{}...", &hash_hex[..13])
+    // Iterate through all confirmed transactions
+    for (block_height, block_timestamp, tx) in &entries {
+        match tx {
+            Transaction::Transfer(transfer_tx) => {
+                let is_sender = mine.contains(&transfer_tx.sender);
+                let is_receiver = mine.contains(&transfer_tx.new_owner);
+
+                if is_sender || is_receiver {
+                    tx_count += 1;
+
+                    let (direction, color) = if is_sender && is_receiver {
+                        ("↔️  Self".to_string(), TableColor::Yellow)
+                    } else if is_sender {
+                        sent_count += 1;
+                        ("📤 Sent".to_string(), TableColor::Red)
+                    } else {
+                        received_count += 1;
+                        ("📥 Received".to_string(), TableColor::Green)
+                    };
+
+                    let hash_hex = hex::encode(transfer_tx.input_hash);
+                    let hash_short = if hash_hex.len() > 16 {
+                        format!("{}...", &hash_hex[..13])
+                    } else {
+                        hash_hex
+                    };
+
+                    let other_party = if is_sender {
+                        let addr_hex = address_to_hex(&transfer_tx.new_owner);
+                        if addr_hex.len() > 20 {
+                            format!("To: {}...{}", &addr_hex[..8], &addr_hex[addr_hex.len() - 8..])
                         } else {
-                            hash_hex
-                        };
-
-                        let other_party = if is_sender {
-                            let addr_hex = address_to_hex(&transfer_tx.new_owner);
-                            if addr_hex.len() > 20 {
-                                format!("To: {}...{}", &addr_hex[..8], &addr_hex[addr_hex.len() - 8..])
-                            } else {
-                                format!("To: {}", addr_hex)
-                            }
+                            format!("To: {}", addr_hex)
+                        }
+                    } else {
+                        let addr_hex = address_to_hex(&transfer_tx.sender);
+                        if addr_hex.len() > 20 {
+                            format!("From: {}...{}", &addr_hex[..8], &addr_hex[addr_hex.len() - 8..])
                         } else {
-                            let addr_hex = address_to_hex(&transfer_tx.sender);
-                            if addr_hex.len() > 20 {
-                                format!("From: {}...{}", &addr_hex[..8], &addr_hex[addr_hex.len() - 8..])
-                            } else {
-                                format!("From: {}", addr_hex)
-                            }
-                        };
-
-                        let memo_str = if let Some(memo) = &transfer_tx.memo {
-                            if memo.len() > 20 {
-                                format!(" | \"{}...\"", &memo[..17])
-                            } else {
-                                format!(" | \"{}\"", memo)
-                            }
+                            format!("From: {}", addr_hex)
+                        }
+                    };
+
+                    let memo_str = if let Some(memo) = &transfer_tx.memo {
+                        if memo.len() > 20 {
+                            format!(" | \"{}...\"", &memo[..17])
                         } else {
-                            String::new()
-                        };
-
-                        transactions.push(TxRecord {
-                            block_height: block.header.height,
-                            tx_type: "Transfer".to_string(),
-                            direction,
-                            details: format!("{} | {}{}", hash_short, other_party, memo_str),
-                            timestamp: block.header.timestamp as i64,
-                            color,
-                        });
-                    }
+                            format!(" | \"{}\"", memo)
+                        }
+                    } else {
+                        String::new()
+                    };
+
+                    transactions.push(TxRecord {
+                        block_height: *block_height,
+                        tx_hash: hex::encode(tx.hash()),
+                        tx_type: "Transfer".to_string(),
+                        direction,
+                        details: format!("{} | {}{}", hash_short, other_party, memo_str),
+                        timestamp: *block_timestamp as i64,
+                        color,
+                    });
                 }
-                Transaction::Coinbase(coinbase_tx) => {
-                    if coinbase_tx.beneficiary_address == my_address_bytes {
-                        tx_count += 1;
+            }
+            Transaction::Coinbase(coinbase_tx) => {
+                if mine.contains(&coinbase_tx.beneficiary_address) {
+                    tx_count += 1;
+                    received_count += 1;
+                    mining_count += 1;
+
+                    transactions.push(TxRecord {
+                        block_height: *block_height,
+                        tx_hash: hex::encode(tx.hash()),
+                        tx_type: "Mining".to_string(),
+                        direction: "⛏️  Reward".to_string(),
+                        details: format!("Area: {}", coinbase_tx.reward_area),
+                        timestamp: *block_timestamp as i64,
+                        color: TableColor::Cyan,
+                    });
+                }
+            }
+            Transaction::BatchTransfer(batch_tx) => {
+                let is_sender = mine.contains(&batch_tx.sender);
+                let received: Vec<_> = batch_tx
+                    .outputs
+                    .iter()
+                    .filter(|o| mine.contains(&o.recipient))
+                    .collect();
+
+                if is_sender || !received.is_empty() {
+                    tx_count += 1;
+
+                    let (direction, color, details) = if is_sender {
+                        sent_count += 1;
+                        (
+                            "📤 Sent".to_string(),
+                            TableColor::Red,
+                            format!(
+                                "{} recipients | Total: {}",
+                                batch_tx.outputs.len(),
+                                batch_tx
+                                    .total_amount()
+                                    .map_or("?".to_string(), |t| t.to_string())
+                            ),
+                        )
+                    } else {
                         received_count += 1;
-                        mining_count += 1;
-
-                        transactions.push(TxRecord {
-                            block_height: block.header.height,
-                            tx_type: "Mining".to_string(),
-                            direction: "⛏️  Reward".to_string(),
-                            details: format!("Area: {}", coinbase_tx.reward_area),
-                            timestamp: block.header.timestamp as i64,
-                            color: TableColor::Cyan,
-                        });
-                    }
+                        let amount: Coord = received.iter().map(|o| o.amount).sum();
+                        let addr_hex = address_to_hex(&batch_tx.sender);
+                        (
+                            "📥 Received".to_string(),
+                            TableColor::Green,
+                            format!(
+                                "From: {}...{} | Amount: {}",
+                                &addr_hex[..8],
+                                &addr_hex[addr_hex.len() - 8..],
+                                amount
+                            ),
+                        )
+                    };
+
+                    transactions.push(TxRecord {
+                        block_height: *block_height,
+                        tx_hash: hex::encode(tx.hash()),
+                        tx_type: "Batch".to_string(),
+                        direction,
+                        details,
+                        timestamp: *block_timestamp as i64,
+                        color,
+                    });
                 }
-                Transaction::Subdivision(sub_tx) => {
-                    if sub_tx.owner_address == my_address_bytes {
-                        tx_count += 1;
-
-                        let hash_hex = hex::encode(sub_tx.parent_hash);
-                        let hash_short = if hash_hex.len() > 16 {
-                            format!("{}...", &hash_hex[..13])
-                        } else {
-                            hash_hex
-                        };
-
-                        transactions.push(TxRecord {
-                            block_height: block.header.height,
-                            tx_type: "Subdivision".to_string(),
-                            direction: "✂️  Split".to_string(),
-                            details: format!("{} → {} children", hash_short, sub_tx.children.len()),
-                            timestamp: block.header.timestamp as i64,
-                            color: TableColor::Magenta,
-                        });
-                    }
+            }
+            Transaction::Subdivision(sub_tx) => {
+                if mine.contains(&sub_tx.owner_address) {
+                    tx_count += 1;
+
+                    let hash_hex = hex::encode(sub_tx.parent_hash);
+                    let hash_short = if hash_hex.len() > 16 {
+                        format!("{}...", &hash_hex[..13])
+                    } else {
+                        hash_hex
+                    };
+
+                    transactions.push(TxRecord {
+                        block_height: *block_height,
+                        tx_hash: hex::encode(tx.hash()),
+                        tx_type: "Subdivision".to_string(),
+                        direction: "✂️  Split".to_string(),
+                        details: format!("{} → {} children", hash_short, sub_tx.children.len()),
+                        timestamp: *block_timestamp as i64,
+                        color: TableColor::Magenta,
+                    });
                 }
             }
         }
@@ -244,6 +391,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             Cell::new("Details")
                 .fg(TableColor::Cyan)
                 .add_attribute(Attribute::Bold),
+            Cell::new("Label")
+                .fg(TableColor::Cyan)
+                .add_attribute(Attribute::Bold),
             Cell::new("Date")
                 .fg(TableColor::Cyan)
                 .add_attribute(Attribute::Bold),
@@ -255,6 +405,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             Cell::new(&tx.tx_type).fg(tx.color),
             Cell::new(&tx.direction).fg(tx.color),
             Cell::new(&tx.details).fg(TableColor::White),
+            Cell::new(
+                tx_labels
+                    .get(&tx.tx_hash)
+                    .map(|l| l.label.as_str())
+                    .unwrap_or(""),
+            )
+            .fg(TableColor::Yellow),
             Cell::new(format_timestamp_short(tx.timestamp)).fg(TableColor::Grey),
         ]);
     }
@@ -262,6 +419,30 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("{}", table);
     println!();
 
+    if let Some(path) = &export_path {
+        let mut csv = String::from("Block,Transaction,Type,Direction,Details,Label,Comment,Timestamp\n");
+        for tx in &transactions {
+            let label = tx_labels.get(&tx.tx_hash);
+            csv.push_str(&format!(
+                "{},{},{},\"{}\",\"{}\",\"{}\",\"{}\",{}\n",
+                tx.block_height,
+                tx.tx_hash,
+                tx.tx_type,
+                tx.direction.replace('"', "\"\""),
+                tx.details.replace('"', "\"\""),
+                label.map(|l| l.label.as_str()).unwrap_or("").replace('"', "\"\""),
+                label
+                    .and_then(|l| l.comment.as_deref())
+                    .unwrap_or("")
+                    .replace('"', "\"\""),
+                tx.timestamp
+            ));
+        }
+        std::fs::write(path, csv)?;
+        println!("{}", format!("💾 Exported {} transactions to {}", transactions.len(), path).green());
+        println!();
+    }
+
     println!(
         "{}",
         "╔══════════════════════════════════════════════════════════╗".bright_blue()
@@ -1,14 +1,13 @@
 #![forbid(unsafe_code)]
-use trinitychain::blockchain::{Blockchain, Block};
+use trinitychain::blockchain::Blockchain;
 use trinitychain::crypto::{address_from_hex, address_to_hex};
-use trinitychain::transaction::{Transaction, CoinbaseTx};
+use trinitychain::datadir::{self, DataDir};
 use trinitychain::persistence::Database;
-use trinitychain::miner::mine_block;
-use std::env;
+use trinitychain::miner::{mine_block, BlockTemplateBuilder};
 use std::time::Instant;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = env::args().collect();
+    let args: Vec<String> = datadir::args();
     if args.len() < 2 {
         eprintln!("Usage: {} <address>", args[0]);
         return Ok(());
@@ -16,34 +15,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let address_hex = &args[1];
     let address = address_from_hex(address_hex)?;
 
-    let db = Database::open("trinitychain.db")?;
-    let mut chain = db.load_blockchain().unwrap_or_else(|_|
-        {
-            println!("⛓️  No chain found – creating genesis block...\n");
-            Blockchain::new(address, 1).unwrap()
-        });
+    let db = Database::open(DataDir::current().db_path())?;
+    let mut chain = db.load_blockchain()?;
 
-    let last_block = chain.blocks.last().cloned().unwrap();
-    let new_height = last_block.header.height + 1;
-
-    let coinbase_tx = Transaction::Coinbase(CoinbaseTx {
-        reward_area: trinitychain::geometry::Coord::from_num(1000),
-        beneficiary_address: address,
-        nonce: new_height,
-    });
-
-    let transactions = vec![coinbase_tx];
-
-    let mut new_block = Block::new(
-        new_height,
-        last_block.hash(),
-        chain.difficulty,
-        transactions,
-    );
-
-    if new_block.header.timestamp <= last_block.header.timestamp {
-        new_block.header.timestamp = last_block.header.timestamp + 1;
-    }
+    let template = BlockTemplateBuilder::new(&chain, address).build()?;
+    let new_height = template.block.header.height;
+    let reward = Blockchain::calculate_block_reward(new_height) + template.fees.to_num::<f64>();
+    let new_block = template.block;
 
     // Print mining header
     println!("\n╔═══════════════════════════════════════════════════════════════╗");
@@ -83,7 +61,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("┌──────────────────────────────── 🔺 REWARD TRIANGLE ───────────────────────────────────┐");
     println!("│ Reward Type:         Coinbase Transaction                                             │");
-    println!("│ Amount:              1000.000000 TRC (geometric area units)                          │");
+    println!("│ Amount:              {:<66} │", format!("{:.6} TRC (geometric area units)", reward));
     println!("│ Beneficiary:         {} │", beneficiary);
     
     if let Some(triangle) = reward_triangle {
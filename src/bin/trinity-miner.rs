@@ -14,16 +14,27 @@ use ratatui::{
     widgets::{Block as TuiBlock, Borders, Gauge, Paragraph, Sparkline},
     Terminal,
 };
-use std::env;
 use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::{Mutex, RwLock};
 use tokio::time::sleep;
-use trinitychain::blockchain::{Block, Blockchain};
+use tracing::{error, info};
+use trinitychain::blockchain::{Blockchain, MerkleCache, HALVING_INTERVAL};
+use trinitychain::config::{active_network, load_config};
+use trinitychain::crypto::Address;
+use trinitychain::datadir::{self, DataDir};
+use trinitychain::discovery::PeerDiscovery;
+use trinitychain::logging::{self, Console};
+use trinitychain::miner::{
+    mine_block_parallel_with, BlockTemplateBuilder, EXTRANONCE_STEP, TEMPLATE_REFRESH_INTERVAL,
+};
 use trinitychain::network::NetworkNode;
 use trinitychain::persistence::Database;
-use trinitychain::transaction::{CoinbaseTx, Transaction};
+use trinitychain::propagation::PeerPropagation;
+use trinitychain::stale_tip::{stale_tip_threshold, StaleTipAction, DEFAULT_STALE_TIP_MULTIPLE};
+use trinitychain::transaction::Transaction;
 
 #[derive(Clone)]
 struct MiningStats {
@@ -33,7 +44,9 @@ struct MiningStats {
     avg_block_time: f64,
     difficulty: u64,
     total_earned: f64,
+    circulating_supply: u64,
     max_supply: u64,
+    current_reward: f64,
     blocks_to_halving: u64,
     halving_era: u64,
     current_hash_rate: f64,
@@ -43,6 +56,8 @@ struct MiningStats {
     last_block_time: f64,
     recent_blocks: Vec<(u64, String, String)>, // (height, hash, parent_hash)
     hashrate_history: Vec<u64>,                // Last 20 hashrate samples
+    peer_propagation: Vec<PeerPropagation>,    // Best block deliverers first
+    stale_tip: Option<String>,                 // Warning while isolated from the network
 }
 
 impl Default for MiningStats {
@@ -54,8 +69,10 @@ impl Default for MiningStats {
             avg_block_time: 0.0,
             difficulty: 1,
             total_earned: 0.0,
-            max_supply: 420_000_000,
-            blocks_to_halving: 210_000,
+            circulating_supply: 0,
+            max_supply: Blockchain::max_supply() as u64,
+            current_reward: Blockchain::calculate_block_reward(1),
+            blocks_to_halving: HALVING_INTERVAL,
             halving_era: 0,
             current_hash_rate: 0.0,
             mining_status: "Starting...".to_string(),
@@ -64,6 +81,8 @@ impl Default for MiningStats {
             last_block_time: 0.0,
             recent_blocks: Vec::new(),
             hashrate_history: vec![0; 20],
+            peer_propagation: Vec::new(),
+            stale_tip: None,
         }
     }
 }
@@ -99,10 +118,11 @@ fn draw_ui(f: &mut ratatui::Frame, stats: &MiningStats, beneficiary: &str) {
         .margin(1)
         .constraints([
             Constraint::Length(3),  // Title
-            Constraint::Length(8),  // Mining Status (increased for network peers)
+            Constraint::Length(9),  // Mining Status (network peers and stale-tip warning)
             Constraint::Length(10), // Stats
             Constraint::Length(6),  // Supply Progress
             Constraint::Length(6),  // Hashrate Graph
+            Constraint::Length(8),  // Peer Propagation
             Constraint::Length(12), // Blockchain Tree
             Constraint::Min(0),     // Bottom padding
         ])
@@ -171,6 +191,13 @@ fn draw_ui(f: &mut ratatui::Frame, stats: &MiningStats, beneficiary: &str) {
                 Style::default().fg(Color::Cyan),
             ),
         ]),
+        match &stats.stale_tip {
+            Some(warning) => Line::from(vec![Span::styled(
+                warning,
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            )]),
+            None => Line::from(""),
+        },
     ];
     let status = Paragraph::new(status_text).block(
         TuiBlock::default()
@@ -219,7 +246,7 @@ fn draw_ui(f: &mut ratatui::Frame, stats: &MiningStats, beneficiary: &str) {
             Span::styled("  │  ", Style::default().fg(Color::DarkGray)),
             Span::styled("Reward: ", Style::default().fg(Color::Gray)),
             Span::styled(
-                format!("{} TRC", 1000 / (1 << stats.halving_era)),
+                format!("{} TRC", stats.current_reward),
                 Style::default()
                     .fg(Color::Green)
                     .add_modifier(Modifier::BOLD),
@@ -251,9 +278,10 @@ fn draw_ui(f: &mut ratatui::Frame, stats: &MiningStats, beneficiary: &str) {
                 .border_style(Style::default().fg(Color::Magenta)),
         )
         .gauge_style(Style::default().fg(Color::Magenta).bg(Color::Black))
+        .ratio((stats.circulating_supply as f64 / stats.max_supply.max(1) as f64).clamp(0.0, 1.0))
         .label(format!(
             "{} / {}",
-            format_number(stats.chain_height * 1000),
+            format_number(stats.circulating_supply),
             format_number(stats.max_supply)
         ));
     f.render_widget(gauge, chunks[3]);
@@ -277,6 +305,57 @@ fn draw_ui(f: &mut ratatui::Frame, stats: &MiningStats, beneficiary: &str) {
         );
     f.render_widget(hashrate_sparkline, chunks[4]);
 
+    // Peer Propagation - who delivers blocks first, who lags
+    let mut peer_lines = Vec::new();
+    if stats.peer_propagation.is_empty() {
+        peer_lines.push(Line::from(vec![Span::styled(
+            "   No blocks received from peers yet",
+            Style::default()
+                .fg(Color::DarkGray)
+                .add_modifier(Modifier::ITALIC),
+        )]));
+    } else {
+        for peer in stats.peer_propagation.iter().take(6) {
+            let color = if peer.first_rate >= 0.5 {
+                Color::Green
+            } else if peer.avg_delay_ms > 2000.0 {
+                Color::Red
+            } else {
+                Color::Gray
+            };
+            peer_lines.push(Line::from(vec![
+                Span::styled(format!(" {:<22}", peer.peer), Style::default().fg(color)),
+                Span::styled("first ", Style::default().fg(Color::DarkGray)),
+                Span::styled(
+                    format!(
+                        "{}/{} ({:.0}%)",
+                        peer.first_deliveries,
+                        peer.blocks_seen,
+                        peer.first_rate * 100.0
+                    ),
+                    Style::default().fg(color).add_modifier(Modifier::BOLD),
+                ),
+                Span::styled("  │  avg lag ", Style::default().fg(Color::DarkGray)),
+                Span::styled(
+                    format!("{:.0}ms", peer.avg_delay_ms),
+                    Style::default().fg(color),
+                ),
+                Span::styled("  │  max ", Style::default().fg(Color::DarkGray)),
+                Span::styled(
+                    format!("{}ms", peer.max_delay_ms),
+                    Style::default().fg(color),
+                ),
+            ]));
+        }
+    }
+    let peers_widget = Paragraph::new(peer_lines).block(
+        TuiBlock::default()
+            .borders(Borders::ALL)
+            .title("📡 Peers (Block Propagation)")
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+    f.render_widget(peers_widget, chunks[5]);
+
     // Blockchain Tree - Real Parent-Child Relationships
     let mut tree_lines = vec![Line::from("")];
 
@@ -344,13 +423,13 @@ fn draw_ui(f: &mut ratatui::Frame, stats: &MiningStats, beneficiary: &str) {
             .title("🌳 Blockchain Tree (Parent → Child)")
             .border_style(Style::default().fg(Color::Magenta)),
     );
-    f.render_widget(tree, chunks[5]);
+    f.render_widget(tree, chunks[6]);
 
     // Footer
     let footer_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .split(chunks[6]);
+        .split(chunks[7]);
 
     let help = Paragraph::new(vec![Line::from(vec![
         Span::styled("Press ", Style::default().fg(Color::DarkGray)),
@@ -365,14 +444,43 @@ fn draw_ui(f: &mut ratatui::Frame, stats: &MiningStats, beneficiary: &str) {
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = env::args().collect();
+    let args: Vec<String> = datadir::args();
     if args.len() < 2 {
-        println!("Usage: trinity-miner <beneficiary_address> [--threads <N>]");
+        println!("Usage: trinity-miner <beneficiary_address> [--threads <N>] [--datadir <dir>]");
+        println!("       --threads defaults to [miner] threads in config.toml");
         return Ok(());
     }
-    let beneficiary_address = args[1].clone();
-
-    let mut threads: usize = 1;
+    let beneficiary = match Address::parse(&args[1]) {
+        Ok(address) => address,
+        Err(e) => {
+            eprintln!("❌ Invalid beneficiary address: {}", e);
+            return Ok(());
+        }
+    };
+    let beneficiary_address = beneficiary.to_string();
+
+    // Network selection (mainnet/testnet/regtest) picks the default ports and database path
+    let config = load_config().ok();
+    let db_path = config.as_ref().map_or_else(
+        || DataDir::current().db_path().to_string_lossy().into_owned(),
+        |c| c.database.path.clone(),
+    );
+    let p2p_port = config.as_ref().map_or(8333, |c| c.network.p2p_port);
+    let mut threads: usize = config.as_ref().map_or(1, |c| c.miner.threads).max(1);
+    let stale_tip_threshold = config.as_ref().map_or_else(
+        || stale_tip_threshold(DEFAULT_STALE_TIP_MULTIPLE),
+        |c| c.miner.stale_tip_threshold(),
+    );
+    let on_stale_tip = config.as_ref().map_or_else(
+        || StaleTipAction::default_for(active_network()),
+        |c| c.miner.stale_tip_action(active_network()),
+    );
+    // The TUI owns the terminal, so logs only go to the file
+    let logging = config
+        .as_ref()
+        .map(|c| c.logging.clone())
+        .unwrap_or_default();
+    logging::init(&logging, "trinity-miner", &db_path, Console::Off)?;
     let mut i = 1;
     while i < args.len() {
         if args[i] == "--threads" || args[i] == "-t" {
@@ -396,28 +504,60 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let stats = Arc::new(Mutex::new(MiningStats::default()));
     let stats_clone = Arc::clone(&stats);
-    let beneficiary_clone = beneficiary_address.clone();
 
     // Create and start network node
-    let db_for_network = Database::open("trinitychain.db").expect("Failed to open database");
-    let chain_for_network = db_for_network
-        .load_blockchain()
-        .unwrap_or_else(|_| Blockchain::new([0; 32], 1).unwrap());
-    let network = Arc::new(NetworkNode::new(Arc::new(RwLock::new(chain_for_network))));
+    let db_for_network = Database::open(&db_path).expect("Failed to open database");
+    let chain_for_network = db_for_network.load_blockchain()?;
+    let network = Arc::new(
+        NetworkNode::new(Arc::new(RwLock::new(chain_for_network)))
+            .with_peer_db(&db_path)
+            .with_protocol_policy(
+                config
+                    .as_ref()
+                    .map(|c| c.network.protocol_policy())
+                    .unwrap_or_default(),
+            )
+            .with_stale_tip_threshold(stale_tip_threshold),
+    );
     let network_clone = network.clone();
 
     // Start network server in background
     tokio::spawn(async move {
-        let port = 8333; // Default P2P port
-        println!("🌐 Starting P2P network on port {}...", port);
+        let port = p2p_port;
+        info!("🌐 Starting P2P network on port {}...", port);
         if let Err(e) = network_clone.start_server(port).await {
-            eprintln!("❌ Network error: {}", e);
+            error!("❌ Network error: {}", e);
         }
     });
 
+    // Reconnect to previously known peers in the background
+    let mut discovery = PeerDiscovery::new();
+    for seed in active_network().dns_seeds() {
+        discovery.add_dns_seed(seed);
+    }
+    for peer in config.iter().flat_map(|c| c.network.bootstrap_peers()) {
+        discovery.add_bootstrap_peer(peer);
+    }
+    tokio::spawn(network.clone().maintain_connections(discovery, 8));
+
+    let network_for_ui = network.clone();
+
+    // Set on quit so the mining threads stop hashing
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_for_miner = stop.clone();
+
     // Spawn mining task
     let mining_handle = tokio::spawn(async move {
-        mining_loop(beneficiary_clone, threads, stats_clone, Some(network)).await;
+        mining_loop(
+            beneficiary,
+            threads,
+            db_path,
+            stop_for_miner,
+            stats_clone,
+            Some(network),
+            on_stale_tip,
+        )
+        .await;
     });
 
     // UI loop
@@ -426,13 +566,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         if event::poll(Duration::from_millis(100)).unwrap_or(false) {
             if let Event::Key(key) = event::read().unwrap() {
                 if key.code == KeyCode::Char('q') {
+                    stop.store(true, Ordering::SeqCst);
                     break;
                 }
             }
         }
 
         // Draw UI
-        let stats_lock = stats.lock().await.clone();
+        let mut stats_lock = stats.lock().await.clone();
+        stats_lock.network_peers = network_for_ui.list_peers().await.len();
+        stats_lock.peer_propagation = network_for_ui.propagation_stats();
         terminal
             .draw(|f| {
                 draw_ui(f, &stats_lock, &beneficiary_address);
@@ -452,20 +595,56 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Pick up blocks the node stored since `chain` was loaded, reloading the
+/// whole chain only after a reorganization
+fn refresh_chain(db: &Database, chain: &mut Blockchain) {
+    if !matches!(db.catch_up(chain), Ok(true)) {
+        if let Ok(reloaded) = db.load_blockchain() {
+            *chain = reloaded;
+        }
+    }
+}
+
 async fn mining_loop(
-    beneficiary_address: String,
-    _threads: usize,
+    beneficiary_address: Address,
+    threads: usize,
+    db_path: String,
+    stop: Arc<AtomicBool>,
     stats: Arc<Mutex<MiningStats>>,
     network: Option<Arc<NetworkNode>>,
+    on_stale_tip: StaleTipAction,
 ) {
-    let db = Database::open("trinitychain.db").expect("Failed to open database");
-    let mut chain = db.load_blockchain().unwrap_or_else(|_| Blockchain::new([0; 32], 1).unwrap());
+    let db = Database::open(&db_path).expect("Failed to open database");
+    let mut chain = db.load_blockchain().expect("Failed to load blockchain");
 
     let start_time = Instant::now();
     let mut blocks_mined = 0;
 
     loop {
-        chain = db.load_blockchain().unwrap_or_else(|_| chain.clone());
+        // Don't silently extend a private fork while cut off from the network
+        let watched = network.as_ref().filter(|_| on_stale_tip != StaleTipAction::Ignore);
+        if let Some(network) = watched {
+            let stale = network.check_tip().await;
+            let mut s = stats.lock().await;
+            s.stale_tip = stale.then(|| {
+                format!(
+                    "🚨 Stale tip: no peers and no network block for {}s",
+                    network.tip_silent_secs()
+                )
+            });
+            if stale && on_stale_tip == StaleTipAction::Pause {
+                s.mining_status = "⏸ Paused until a peer connects (stale tip)".to_string();
+                s.current_hash_rate = 0.0;
+                drop(s);
+                if stop.load(Ordering::SeqCst) {
+                    return;
+                }
+                sleep(Duration::from_secs(5)).await;
+                continue;
+            }
+        }
+
+        refresh_chain(&db, &mut chain);
 
         let last_block = match chain.blocks.last() {
             Some(block) => block,
@@ -478,19 +657,16 @@ async fn mining_loop(
         let new_height = last_block.header.height + 1;
         let difficulty = chain.difficulty;
 
-        let mut address = [0u8; 32];
-        hex::decode_to_slice(&beneficiary_address, &mut address).unwrap();
-        let coinbase_tx = Transaction::Coinbase(CoinbaseTx {
-            reward_area: trinitychain::geometry::Coord::from_num(1000),
-            beneficiary_address: address,
-            nonce: 0,
-        });
-
-        let mut new_block = Block::new(new_height, last_block.hash(), difficulty, vec![coinbase_tx]);
-
-        if new_block.header.timestamp <= last_block.header.timestamp {
-            new_block.header.timestamp = last_block.header.timestamp + 1;
-        }
+        let built = match BlockTemplateBuilder::new(&chain, beneficiary_address).build() {
+            Ok(built) => built,
+            Err(_) => {
+                sleep(Duration::from_secs(5)).await;
+                continue;
+            }
+        };
+        let reward = Blockchain::calculate_block_reward(new_height) + built.fees.to_num::<f64>();
+        let mut template = built.block;
+        let mut merkle_cache = MerkleCache::new();
 
         // Update status
         {
@@ -500,13 +676,66 @@ async fn mining_loop(
         }
 
         let mine_start = Instant::now();
-        let new_block = match trinitychain::miner::mine_block(new_block) {
-            Ok(b) => b,
-            Err(_) => {
-                sleep(Duration::from_secs(1)).await;
-                continue;
+        let mined = loop {
+            let stop_for_workers = stop.clone();
+            let stats_for_workers = stats.clone();
+            let candidate = template.clone();
+            let mined = tokio::task::spawn_blocking(move || {
+                // Workers stop when the UI shuts down or the template is due a refresh
+                let round_start = Instant::now();
+                let round_over = AtomicBool::new(false);
+                mine_block_parallel_with(candidate, threads, &round_over, |progress| {
+                    if stop_for_workers.load(Ordering::SeqCst)
+                        || round_start.elapsed() >= TEMPLATE_REFRESH_INTERVAL
+                    {
+                        round_over.store(true, Ordering::SeqCst);
+                    }
+                    let mut s = stats_for_workers.blocking_lock();
+                    s.current_hash_rate = progress.hashes_per_sec;
+                    s.hashrate_history.push(progress.hashes_per_sec as u64);
+                    if s.hashrate_history.len() > 20 {
+                        s.hashrate_history.remove(0);
+                    }
+                })
+            })
+            .await;
+            match mined {
+                Ok(Ok(Some(b))) => break Some(b),
+                // Stopped: the UI is shutting down
+                Ok(Ok(None)) if stop.load(Ordering::SeqCst) => return,
+                Ok(Ok(None)) => {
+                    // A new tip means a new template; otherwise bump the
+                    // extranonce and timestamp and rehash only the coinbase
+                    refresh_chain(&db, &mut chain);
+                    let tip = chain.blocks.last().map(|b| b.hash());
+                    if tip != Some(template.header.previous_hash) {
+                        break None;
+                    }
+                    let coinbase = template.transactions.first_mut();
+                    if let Some(Transaction::Coinbase(coinbase)) = coinbase {
+                        coinbase.nonce = coinbase.nonce.wrapping_add(EXTRANONCE_STEP);
+                    }
+                    let now = chrono::Utc::now().timestamp_millis() as u64;
+                    template.header.timestamp = template.header.timestamp.max(now);
+                    template.update_merkle_incremental(&mut merkle_cache, &[0]);
+                    // The coinbase output is keyed by its hash, so the new
+                    // extranonce changes the UTXO set too
+                    match chain.state_root_after(&template) {
+                        Ok(root) => template.header.state_root = root,
+                        Err(_) => break None,
+                    }
+                    stats.lock().await.mining_status =
+                        format!("Mining block #{} (template refreshed)...", new_height);
+                }
+                _ => {
+                    sleep(Duration::from_secs(1)).await;
+                    break None;
+                }
             }
         };
+        let Some(new_block) = mined else {
+            continue;
+        };
         let mine_duration = mine_start.elapsed().as_secs_f64();
         let hash_hex = hex::encode(new_block.hash());
 
@@ -530,8 +759,7 @@ async fn mining_loop(
         // Update stats
         {
             let current_height = new_height;
-            let halving_era = current_height / 210_000;
-            let blocks_to_halving = ((halving_era + 1) * 210_000).saturating_sub(current_height);
+            let supply = chain.supply_info();
 
             let parent_hash_hex = hex::encode(new_block.header.previous_hash);
 
@@ -540,9 +768,12 @@ async fn mining_loop(
             s.chain_height = current_height;
             s.uptime_secs = elapsed.as_secs();
             s.avg_block_time = elapsed.as_secs_f64() / blocks_mined as f64;
-            s.total_earned = blocks_mined as f64 * 1000.0;
-            s.blocks_to_halving = blocks_to_halving;
-            s.halving_era = halving_era;
+            s.total_earned += reward;
+            s.circulating_supply = supply.circulating_supply.to_num::<f64>() as u64;
+            s.max_supply = supply.max_supply as u64;
+            s.current_reward = supply.current_reward;
+            s.blocks_to_halving = supply.blocks_to_halving;
+            s.halving_era = supply.halving_era;
             s.mining_status = format!("✓ Block #{} mined!", new_height);
             s.last_block_hash = hash_hex.clone();
             s.last_block_time = mine_duration;
@@ -2,42 +2,230 @@
 //! Network node for TrinityChain - TUI Edition
 
 use crossterm::{
-    event::{self, Event, KeyCode},
+    event::{self, Event, KeyCode, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use ratatui::{backend::CrosstermBackend, Terminal};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block as TuiBlock, Borders, Gauge, Paragraph, Row, Table},
+    Terminal,
+};
+use std::collections::VecDeque;
 use std::io;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use trinitychain::blockchain::Blockchain;
-use trinitychain::config::load_config;
-use trinitychain::persistence::Database;
-
-#[derive(Clone)]
-struct NodeStats {
-    chain_height: u64,
-    uptime_secs: u64,
-    status: String,
-    last_block_hash: String,
+use tracing::{info, warn};
+use trinitychain::config::{load_config, ReplicationMode};
+use trinitychain::datadir;
+use trinitychain::logging::{self, Console};
+use trinitychain::network::NetworkNode;
+use trinitychain::peer_events::{self, PeerSortColumn, PeerTable};
+use trinitychain::persistence::{Database, MIN_PRUNE_DEPTH, PENDING_TRANSACTIONS};
+use trinitychain::policy::TxPolicy;
+use trinitychain::replication::{self, FollowOutcome, Standby, StandbyState};
+use trinitychain::shutdown::Shutdown;
+use trinitychain::stats_feed::{NodeStats, StatsFeed};
+use trinitychain::sync::{format_eta, SyncPhase, SyncState};
+use trinitychain::watchtower::{spawn_webhook, Watchtower};
+
+/// Peer events kept for the log pane
+const EVENT_LOG_LINES: usize = 8;
+
+/// How the peers pane is ordered; `s` cycles the column, `r` reverses it
+#[derive(Default)]
+struct PeersView {
+    table: PeerTable,
+    column: PeerSortColumn,
+    descending: bool,
+    log: VecDeque<String>,
 }
 
-impl Default for NodeStats {
-    fn default() -> Self {
-        Self {
-            chain_height: 0,
-            uptime_secs: 0,
-            status: "Initializing...".to_string(),
-            last_block_hash: "N/A".to_string(),
-        }
+fn format_age(secs: u64) -> String {
+    match secs {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m", s / 60),
+        s => format!("{}h{:02}m", s / 3600, (s % 3600) / 60),
     }
 }
 
-// UI drawing functions remain the same...
+/// Gauge label: heights, speed and ETA while catching up
+fn sync_label(sync: &SyncState) -> String {
+    match (sync.phase, sync.target_height) {
+        (SyncPhase::Syncing, Some(target)) => format!(
+            "{} / {} ({:.1}%)  {:.1} blocks/s  ETA {}",
+            sync.height,
+            target,
+            sync.progress() * 100.0,
+            sync.blocks_per_sec,
+            sync.eta_secs.map_or("-".to_string(), format_eta)
+        ),
+        (SyncPhase::Synced, _) => format!("Synced at {}", sync.height),
+        _ => "Waiting for peers".to_string(),
+    }
+}
+
+fn draw_ui(
+    f: &mut ratatui::Frame,
+    stats: &NodeStats,
+    sync: &SyncState,
+    peers: &PeersView,
+    feed_addr: Option<&SocketAddr>,
+) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([
+            Constraint::Length(7),                          // Node status
+            Constraint::Length(3),                          // Sync progress
+            Constraint::Min(6),                             // Peers
+            Constraint::Length(EVENT_LOG_LINES as u16 + 2), // Peer events
+        ])
+        .split(f.size());
+
+    let status_text = vec![
+        Line::from(vec![
+            Span::styled("Status: ", Style::default().fg(Color::Gray)),
+            Span::styled(
+                &stats.status,
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ]),
+        Line::from(vec![
+            Span::styled("Chain Height: ", Style::default().fg(Color::Gray)),
+            Span::styled(
+                stats.chain_height.to_string(),
+                Style::default().fg(Color::Cyan),
+            ),
+        ]),
+        Line::from(vec![
+            Span::styled("Last Block: ", Style::default().fg(Color::Gray)),
+            Span::styled(&stats.last_block_hash, Style::default().fg(Color::Yellow)),
+        ]),
+        Line::from(vec![
+            Span::styled("Uptime: ", Style::default().fg(Color::Gray)),
+            Span::styled(
+                format_age(stats.uptime_secs),
+                Style::default().fg(Color::Blue),
+            ),
+        ]),
+        Line::from(vec![
+            Span::styled("Dashboard Feed: ", Style::default().fg(Color::Gray)),
+            Span::styled(
+                feed_addr.map_or("off ([node] stats_port)".to_string(), |addr| {
+                    format!("ws://{}", addr)
+                }),
+                Style::default().fg(Color::Magenta),
+            ),
+        ]),
+    ];
+    let status = Paragraph::new(status_text).block(
+        TuiBlock::default()
+            .borders(Borders::ALL)
+            .title("🔺 TrinityChain Node")
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+    f.render_widget(status, chunks[0]);
+
+    let gauge_color = match sync.phase {
+        SyncPhase::Synced => Color::Green,
+        SyncPhase::Syncing => Color::Yellow,
+        SyncPhase::Idle | SyncPhase::Failed => Color::DarkGray,
+    };
+    let gauge = Gauge::default()
+        .block(
+            TuiBlock::default()
+                .borders(Borders::ALL)
+                .title("⏳ Sync")
+                .border_style(Style::default().fg(Color::Yellow)),
+        )
+        .gauge_style(Style::default().fg(gauge_color))
+        .ratio(sync.progress().clamp(0.0, 1.0))
+        .label(sync_label(sync));
+    f.render_widget(gauge, chunks[1]);
+
+    let header = Row::new(PeerSortColumn::ALL.map(|column| {
+        let marker = match (column == peers.column, peers.descending) {
+            (true, false) => " ▲",
+            (true, true) => " ▼",
+            (false, _) => "",
+        };
+        format!("{}{}", column.title(), marker)
+    }))
+    .style(
+        Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD),
+    );
+    let now = chrono::Utc::now().timestamp().max(0) as u64;
+    let rows = peers
+        .table
+        .sorted(peers.column, peers.descending)
+        .into_iter()
+        .map(|peer| {
+            let score_style = if peer.score > 0 {
+                Style::default().fg(Color::Red)
+            } else {
+                Style::default()
+            };
+            Row::new(vec![
+                Span::raw(peer.addr),
+                Span::raw(if peer.outbound { "outbound" } else { "inbound" }),
+                Span::raw(peer.height.map_or("-".to_string(), |h| h.to_string())),
+                Span::styled(peer.score.to_string(), score_style),
+                Span::raw(format_age(now.saturating_sub(peer.connected_at))),
+            ])
+        });
+    let widths = [
+        Constraint::Percentage(34),
+        Constraint::Percentage(16),
+        Constraint::Percentage(16),
+        Constraint::Percentage(14),
+        Constraint::Percentage(20),
+    ];
+    let table = Table::new(rows, widths).header(header).block(
+        TuiBlock::default()
+            .borders(Borders::ALL)
+            .title(format!(
+                "🌐 Peers ({}) - [s] sort column  [r] reverse  [q] quit",
+                peers.table.len()
+            ))
+            .border_style(Style::default().fg(Color::Green)),
+    );
+    f.render_widget(table, chunks[2]);
+
+    let log: Vec<Line> = peers
+        .log
+        .iter()
+        .map(|line| Line::from(line.as_str()))
+        .collect();
+    let events = Paragraph::new(log).block(
+        TuiBlock::default()
+            .borders(Borders::ALL)
+            .title("📜 Peer Events")
+            .border_style(Style::default().fg(Color::Magenta)),
+    );
+    f.render_widget(events, chunks[3]);
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // No other arguments; only --datadir
+    datadir::args();
     let config = load_config()?;
+    // The TUI owns the terminal, so logs only go to the file
+    logging::init(
+        &config.logging,
+        "trinity-node",
+        &config.database.path,
+        Console::Off,
+    )?;
     let db_path = config.database.path;
     let p2p_port = config.network.p2p_port;
 
@@ -49,33 +237,178 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut terminal = Terminal::new(backend)?;
 
     let db = Database::open(&db_path).expect("Failed to open database");
-    let blockchain = db.load_blockchain().unwrap_or_else(|_| {
-        Blockchain::new([0; 32], 1).expect("Failed to create new blockchain")
-    });
+
+    // The role's feature bundle decides which indexes this database keeps
+    let features = config.node.features();
+    if db.set_address_index(features.address_index)? {
+        info!(
+            "🗂️  Address index {} for the {} role",
+            if features.address_index { "rebuilt" } else { "dropped" },
+            config.node.role.name()
+        );
+    }
+    if db.set_memo_index(features.memo_index)? && !features.memo_index {
+        info!("📝 Memo index dropped");
+    }
+    if features.memo_index {
+        let memos = db.memo_index_stats()?;
+        warn!(
+            "📝 Memo search is on: {} memo(s) ({} bytes) can be searched by anyone using this node's API",
+            memos.entries, memos.bytes
+        );
+    }
+    // A pruned node keeps only recent block bodies on disk
+    let prune_depth = config.node.prune_depth();
+    if let Some(depth) = prune_depth {
+        let pruned = db.prune_blocks(depth)?;
+        info!(
+            "✂️  Pruning: keeping the last {} block bodies ({} discarded)",
+            depth.max(MIN_PRUNE_DEPTH),
+            pruned
+        );
+    }
+    let mut blockchain = db.load_blockchain()?;
+
+    // Node-local relay and mining filter
+    if let Some(policy) = TxPolicy::from_config(&config.policy)? {
+        info!(
+            "🚫 Transaction policy: {} blocked address(es){}",
+            policy.blocked_count(),
+            if config.policy.command.is_empty() { "" } else { " and a policy command" }
+        );
+        blockchain.mempool.set_policy(Arc::new(policy));
+    }
+
+    // Restore the mempool saved at the last shutdown; spent inputs drop out here
+    let next_height = blockchain.blocks.last().map_or(0, |b| b.header.height + 1);
+    for tx in db.get_setting(&PENDING_TRANSACTIONS)?.unwrap_or_default() {
+        if tx.validate(&blockchain.state, next_height).is_ok() {
+            let _ = blockchain.mempool.add_transaction(tx);
+        }
+    }
 
     let blockchain = Arc::new(tokio::sync::RwLock::new(blockchain));
+
+    let shutdown = Shutdown::new();
+    let mut network = NetworkNode::new(blockchain.clone())
+        .with_peer_db(&db_path)
+        .with_shutdown(shutdown.clone())
+        .with_protocol_policy(config.network.protocol_policy())
+        .with_stale_tip_threshold(config.miner.stale_tip_threshold());
+
+    // Watch cold-storage triangles and addresses for spends
+    if let Some(watchtower) = Watchtower::from_config(&config.watchtower)? {
+        let watchtower = Arc::new(watchtower);
+        if let Some(url) = &config.watchtower.webhook_url {
+            spawn_webhook(&watchtower, url)?;
+        }
+        let interval = Duration::from_secs(config.watchtower.poll_interval_secs.max(1));
+        tokio::spawn(watchtower.clone().watch(blockchain.clone(), interval));
+        network = network.with_watchtower(watchtower);
+    }
+    let network = Arc::new(network);
+    let mut peer_events = network.subscribe_peer_events();
+    if let Some(url) = &config.network.peer_events_webhook {
+        peer_events::spawn_webhook(network.subscribe_peer_events(), url)?;
+    }
+    // Losing every peer for long enough is logged like a peer event
+    let mut tip_events = network.subscribe_tip_events();
+    tokio::spawn(network.clone().monitor_tip(Duration::from_secs(10)));
+    let mut peers = PeersView::default();
     let stats = Arc::new(tokio::sync::Mutex::new(NodeStats::default()));
     let start_time = Instant::now();
 
-    // Start P2P networking in background
-    let _p2p_task = tokio::spawn(async move {
-        println!("🌐 P2P Server listening on port {}", p2p_port);
-        // Network initialization would happen here
-    });
+    // Live stats for the web dashboard, local connections only
+    let feed = StatsFeed::new();
+    let feed_addr = match config.node.stats_port {
+        Some(port) => match feed.serve(&format!("127.0.0.1:{}", port)) {
+            Ok(addr) => Some(addr),
+            Err(e) => {
+                warn!("⚠️  Dashboard stats feed disabled: {}", e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    // Hot standby: a primary streams its chain, a standby follows one
+    let replication = config.replication;
+    let standby = match replication.mode {
+        ReplicationMode::Off => None,
+        ReplicationMode::Primary => {
+            let secret = replication.secret().ok_or("[replication] needs a secret")?;
+            replication::check_secret(&secret)?;
+            tokio::spawn(replication::serve(
+                blockchain.clone(),
+                replication.port,
+                secret,
+                shutdown.clone(),
+            ));
+            None
+        }
+        ReplicationMode::Standby => {
+            let primary = replication
+                .primary
+                .clone()
+                .ok_or("[replication] standby needs the primary's address")?;
+            let secret = replication.secret().ok_or("[replication] needs a secret")?;
+            let standby = Standby::new(blockchain.clone(), primary, secret)?
+                .with_failover_timeout(replication.failover_timeout())
+                .with_auto_promote(replication.auto_promote)
+                .with_database(&db_path)
+                .with_shutdown(shutdown.clone());
+            Some(Arc::new(standby))
+        }
+    };
+
+    // Start P2P networking in background; it stops when `shutdown` triggers.
+    // A standby only takes blocks from its primary until promoted.
+    let p2p_task = match &standby {
+        None => tokio::spawn(network.clone().start_server(p2p_port)),
+        Some(standby) => {
+            let standby = standby.clone();
+            let network = network.clone();
+            tokio::spawn(async move {
+                match standby.follow().await {
+                    FollowOutcome::Promoted => network.start_server(p2p_port).await,
+                    FollowOutcome::Shutdown => Ok(()),
+                }
+            })
+        }
+    };
 
     // Main UI loop
     loop {
         if event::poll(Duration::from_millis(100))? {
             if let Event::Key(key) = event::read()? {
-                if key.code == KeyCode::Char('q') {
+                let ctrl_c = key.code == KeyCode::Char('c')
+                    && key.modifiers.contains(KeyModifiers::CONTROL);
+                if key.code == KeyCode::Char('q') || ctrl_c {
                     break;
                 }
+                match key.code {
+                    KeyCode::Char('s') => peers.column = peers.column.next(),
+                    KeyCode::Char('r') => peers.descending = !peers.descending,
+                    KeyCode::Char('p') => {
+                        if let Some(standby) = &standby {
+                            standby.promote();
+                        }
+                    }
+                    _ => {}
+                }
             }
         }
 
         {
             let mut s = stats.lock().await;
-            s.status = "Running".to_string();
+            s.status = match standby.as_ref().map(|standby| standby.status()) {
+                Some(status) if status.state != StandbyState::Promoted => format!(
+                    "Standby of {} ({}) - [p] promote",
+                    status.primary,
+                    status.state.name()
+                ),
+                _ => "Running".to_string(),
+            };
             s.uptime_secs = start_time.elapsed().as_secs();
 
             let bc = blockchain.read().await;
@@ -83,18 +416,56 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             if let Some(last_block) = bc.blocks.last() {
                 s.last_block_hash = hex::encode(last_block.hash());
             }
+            s.peers = peers.table.len();
+        }
+
+        // Missed events (lagged) only cost log lines; the table resyncs as
+        // peers reconnect
+        while let Ok(event) = peer_events.try_recv() {
+            peers.table.apply(&event);
+            peers.log.push_back(event.describe());
+            if peers.log.len() > EVENT_LOG_LINES {
+                peers.log.pop_front();
+            }
+        }
+        while let Ok(event) = tip_events.try_recv() {
+            peers.log.push_back(event.describe());
+            if peers.log.len() > EVENT_LOG_LINES {
+                peers.log.pop_front();
+            }
         }
 
-        let _stats_clone = stats.lock().await.clone();
-        terminal.draw(|_f| {
-            // Minimal UI - can be expanded with ratatui widgets
-        })?;
+        let stats_clone = stats.lock().await.clone();
+        feed.publish(stats_clone.clone());
+        let sync = network.sync_state().await;
+        terminal.draw(|f| draw_ui(f, &stats_clone, &sync, &peers, feed_addr.as_ref()))?;
     }
 
+    // Stop the listener and peers first so no block is applied mid-flush
+    stats.lock().await.status = "Shutting down...".to_string();
+    network.shutdown().await;
+    let _ = p2p_task.await;
+    let flushed = {
+        let chain = blockchain.read().await;
+        db.flush_blockchain(&chain)
+    };
+    let pruned = match (&flushed, prune_depth) {
+        (Ok(_), Some(depth)) => db.prune_blocks(depth).ok(),
+        _ => None,
+    };
+
     // Cleanup
     disable_raw_mode()?;
     execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
     terminal.show_cursor()?;
 
+    match flushed {
+        Ok(written) => println!("💾 Saved chain state ({} new blocks)", written),
+        Err(e) => eprintln!("❌ Failed to save chain state: {}", e),
+    }
+    if let Some(pruned) = pruned.filter(|&n| n > 0) {
+        println!("✂️  Pruned {} old block bodies", pruned);
+    }
+    println!("👋 Node stopped");
     Ok(())
 }
@@ -3,17 +3,20 @@
 
 use colored::*;
 use indicatif::{ProgressBar, ProgressStyle};
+use rpassword::prompt_password;
 use std::collections::HashSet;
-use std::env;
-use std::sync::Arc;
+use std::io::{self, Write};
 use std::time::Duration;
-use tokio::sync::RwLock;
+use trinitychain::balance::{BalanceBreakdown, OutpointLocker};
+use trinitychain::blockchain::{Sha256Hash, COINBASE_MATURITY};
 use trinitychain::cli::load_blockchain_from_config;
-use trinitychain::crypto::address_from_hex;
-use trinitychain::geometry::Coord;
-use trinitychain::network::NetworkNode;
+use trinitychain::crypto::{address_from_hex, Address};
+use trinitychain::datadir::{self, DataDir};
+use trinitychain::geometry::{Coord, Triangle};
+use trinitychain::hdwallet::{self, HDKeystore};
+use trinitychain::remote::RemoteNode;
 use trinitychain::transaction::{Transaction, TransferTx};
-use trinitychain::wallet;
+use trinitychain::{addressbook, drafts, txlabels, wallet};
 
 const LOGO: &str = r#"
 ╔═══════════════════════════════════════════════════════════════╗
@@ -29,7 +32,10 @@ const LOGO: &str = r#"
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = datadir::args();
+    let offline = drafts::offline_from_args_or_env(&mut args);
+    let remote = RemoteNode::from_args_unless_offline(&mut args, offline)?;
+    let account = hdwallet::account_from_args(&mut args)?;
 
     if args.len() < 3 {
         println!("{}", LOGO.bright_cyan());
@@ -59,6 +65,26 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             "{}",
             "║    send <to_address> <amount> [--from <wallet_name>] [memo] ║".white()
         );
+        println!(
+            "{}",
+            "║    <to_address> may be an address book label: @alice     ║".white()
+        );
+        println!(
+            "{}",
+            "║    Or spend from an HD account: --account <name|index>   ║".white()
+        );
+        println!(
+            "{}",
+            "║    Private notes: --label <text> [--comment <text>]      ║".white()
+        );
+        println!(
+            "{}",
+            "║    Add --node-url <url> (or TRINITY_NODE_URL) for remote ║".white()
+        );
+        println!(
+            "{}",
+            "║    Add --offline to queue it as a draft, sending nothing ║".white()
+        );
         println!(
             "{}",
             "║                                                          ║".bright_yellow()
@@ -71,10 +97,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             "{}",
             "║    send abc123... 100                                    ║".white()
         );
+        println!(
+            "{}",
+            "║    send @alice 25                                        ║".white()
+        );
         println!(
             "{}",
             "║    send abc123... 100 --from alice \"Payment for services\" ║".white()
         );
+        println!(
+            "{}",
+            "║    send abc123... 100 --label \"rent March\"              ║".white()
+        );
         println!(
             "{}",
             "║                                                          ║".bright_yellow()
@@ -88,26 +122,80 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     println!("{}", LOGO.bright_cyan());
+    if offline {
+        println!(
+            "{}",
+            "📴 Offline mode: no network calls; the transfer is queued as a draft"
+                .yellow()
+                .bold()
+        );
+        println!();
+    }
 
-    let to_address = &args[1];
-    let to_address_bytes = address_from_hex(to_address)?;
+    // `@label` pays an address book contact, once the user confirms the
+    // address it resolved to
+    let (to_address, to_address_bytes) = match args[1].strip_prefix('@') {
+        Some(name) => {
+            let entry = addressbook::load_default()?.resolve(name)?;
+            let address = address_from_hex(&entry.address)?;
+            let verified = if entry.is_verified() {
+                " ✅ verified"
+            } else {
+                " (unverified)"
+            };
+            println!(
+                "{}",
+                format!(
+                    "📇 @{} resolves to {}{}",
+                    entry.label, entry.address, verified
+                )
+                .cyan()
+            );
+            print!("   Send to this address? (yes/no): ");
+            io::stdout().flush()?;
+            let mut response = String::new();
+            io::stdin().read_line(&mut response)?;
+            if !matches!(response.trim().to_lowercase().as_str(), "yes" | "y") {
+                println!("Transfer cancelled.");
+                std::process::exit(0);
+            }
+            println!();
+            (entry.address, address)
+        }
+        None => (args[1].clone(), address_from_hex(&args[1])?),
+    };
     let amount: f64 = args[2].parse()?;
     let amount_coord = Coord::from_num(amount);
 
     let mut wallet_name: Option<String> = None;
     let mut memo: Option<String> = None;
+    let mut label: Option<String> = None;
+    let mut comment: Option<String> = None;
 
     let mut i = 3;
     while i < args.len() {
         if args[i] == "--from" {
             wallet_name = Some(args[i + 1].clone());
             i += 2;
+        } else if args[i] == "--label" && i + 1 < args.len() {
+            label = Some(args[i + 1].clone());
+            i += 2;
+        } else if args[i] == "--comment" && i + 1 < args.len() {
+            comment = Some(args[i + 1].clone());
+            i += 2;
         } else {
             memo = Some(args[i..].join(" "));
             break;
         }
     }
 
+    if comment.is_some() && label.is_none() {
+        return Err("--comment requires --label".into());
+    }
+    if account.is_some() && wallet_name.is_some() {
+        return Err("--account and --from are mutually exclusive".into());
+    }
+
     println!(
         "{}",
         "┌─────────────────────────────────────────────────────────────┐".bright_magenta()
@@ -135,63 +223,145 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     pb.set_message("Loading wallet...");
     pb.enable_steady_tick(Duration::from_millis(100));
 
-    let from_wallet = if let Some(name) = wallet_name {
-        wallet::load_named_wallet(&name)?
+    // Keys the payment may be drawn from: the wallet's, or every address
+    // derived for the HD account
+    let keypairs = if let Some(account) = &account {
+        let keystore = HDKeystore::load(&hdwallet::get_keystore_path()?)
+            .map_err(|e| format!("No HD keystore found: {}", e))?;
+        let index = keystore.resolve_account(account)?;
+        let password = pb.suspend(|| prompt_password("Enter your HD keystore password: "))?;
+        let hd = keystore.unlock(&password)?;
+        keystore
+            .account_addresses(index)
+            .iter()
+            .map(|address| keystore.keypair_for(&hd, address))
+            .collect::<Result<Vec<_>, _>>()?
     } else {
-        wallet::load_default_wallet()?
-    };
-
-    let from_address = from_wallet.address.clone();
-    let from_address_bytes = address_from_hex(&from_address)?;
-    let keypair = from_wallet.get_keypair()?;
-
-    pb.set_message("Loading blockchain...");
-
-    let (_config, mut chain) = load_blockchain_from_config()?;
+        let from_wallet = if let Some(name) = wallet_name {
+            wallet::load_named_wallet(&name)?
+        } else {
+            wallet::load_default_wallet()?
+        };
 
-    // Track locked triangles from pending transactions
-    let mut locked_triangles = HashSet::new();
+        if from_wallet.watch_only {
+            pb.finish_and_clear();
+            eprintln!(
+                "{}",
+                "❌ This is a watch-only wallet and cannot sign transactions"
+                    .red()
+                    .bold()
+            );
+            eprintln!(
+                "{}",
+                "💡 Send from the machine that holds the wallet's secret key".yellow()
+            );
+            return Err("Cannot send from a watch-only wallet".into());
+        }
+        vec![from_wallet.get_keypair()?]
+    };
+    let from_addresses: Vec<Address> = keypairs.iter().map(|k| k.address()).collect();
+
+    let mut local_chain = None;
+
+    let (input_hash, from_address_bytes, nonce) = if let Some(node) = &remote {
+        pb.set_message(format!("Querying {}...", node.url()));
+
+        // Triangles already spent by pending transactions are locked, as
+        // are coinbase rewards that haven't matured
+        let pending = node.mempool()?;
+        let mut owned = Vec::new();
+        let mut immature = HashSet::new();
+        for address in &from_addresses {
+            let (triangles, unripe) = node.triangles_with_maturity(&hex::encode(address))?;
+            owned.extend(triangles);
+            immature.extend(unripe);
+        }
+        let locker = OutpointLocker::from_pending(&pending).with_immature(immature.clone());
+
+        pb.set_message("Finding a suitable triangle...");
+
+        let (input_hash, input_triangle) = owned
+            .iter()
+            .find(|(hash, triangle)| {
+                triangle.effective_value() >= amount_coord && !locker.is_locked(hash)
+            })
+            .ok_or_else(|| {
+                no_input_error(
+                    owned.iter().map(|(h, t)| (h, t)),
+                    &pending,
+                    &immature,
+                    &from_addresses,
+                    amount_coord,
+                )
+            })?;
+
+        (*input_hash, input_triangle.owner, node.height()?)
+    } else {
+        pb.set_message("Loading blockchain...");
 
-    // Load existing mempool from disk
-    if let Ok(mempool_data) = std::fs::read_to_string("mempool.json") {
-        let transactions: Result<Vec<Transaction>, _> = serde_json::from_str(&mempool_data);
-        if let Ok(txs) = transactions {
-            let txs_clone = txs.clone();
+        let (_config, mut chain) = load_blockchain_from_config()?;
 
-            for tx in txs {
-                let _ = chain.mempool.add_transaction(tx);
-            }
+        // Load existing mempool from disk
+        if let Ok(mempool_data) = std::fs::read_to_string(DataDir::current().mempool_path()) {
+            let transactions: Result<Vec<Transaction>, _> = serde_json::from_str(&mempool_data);
+            if let Ok(txs) = transactions {
+                for tx in txs {
+                    let _ = chain.mempool.add_transaction(tx);
+                }
 
-            if !chain.mempool.is_empty() {
-                pb.println(format!(
-                    "📬 {} pending transaction(s) already in mempool",
-                    chain.mempool.len()
-                ));
+                if !chain.mempool.is_empty() {
+                    pb.println(format!(
+                        "📬 {} pending transaction(s) already in mempool",
+                        chain.mempool.len()
+                    ));
+                }
             }
+        }
 
-            // Collect locked UTXOs from pending transfers
-            for tx in txs_clone {
-                if let Transaction::Transfer(transfer_tx) = tx {
-                    locked_triangles.insert(transfer_tx.input_hash);
-                }
+        // Drafts queued while offline spend their inputs too
+        if offline {
+            for draft in drafts::load_default()?.iter() {
+                let _ = chain.mempool.add_transaction(draft.transaction.clone());
             }
         }
-    }
-    pb.set_message("Finding a suitable triangle...");
 
-    let (input_hash, _input_triangle) = chain
-        .state
-        .utxo_set
-        .iter()
-        .find(|(hash, triangle)| {
-            triangle.owner == from_address_bytes
-                && triangle.effective_value() >= amount_coord
-                && !locked_triangles.contains(*hash)
-        })
-        .ok_or("No single triangle with sufficient value found for the transfer")?;
+        // Triangles already spent by pending transactions are locked, as
+        // are coinbase rewards that haven't matured
+        let pending = chain.mempool.get_all_transactions();
+        let next_height = chain.blocks.last().map_or(0, |b| b.header.height + 1);
+        let immature = chain.state.immature_outputs(next_height);
+        let locker = OutpointLocker::from_pending(&pending).with_immature(immature.clone());
+        pb.set_message("Finding a suitable triangle...");
+
+        let owned = || {
+            chain
+                .state
+                .utxo_set
+                .iter()
+                .filter(|(_, triangle)| from_addresses.contains(&triangle.owner))
+        };
+        let (input_hash, input_triangle) = owned()
+            .find(|(hash, triangle)| {
+                triangle.effective_value() >= amount_coord && !locker.is_locked(hash)
+            })
+            .ok_or_else(|| {
+                no_input_error(owned(), &pending, &immature, &from_addresses, amount_coord)
+            })?;
+
+        let (input_hash, owner) = (*input_hash, input_triangle.owner);
+        let nonce = chain.blocks.len() as u64;
+        local_chain = Some(chain);
+        (input_hash, owner, nonce)
+    };
 
     pb.finish_and_clear();
 
+    let from_address = hex::encode(from_address_bytes);
+    let keypair = keypairs
+        .iter()
+        .find(|k| k.address() == from_address_bytes)
+        .ok_or("Selected triangle has no matching key")?;
+
     let from_display = if from_address.len() > 20 {
         format!(
             "{}...{}",
@@ -201,6 +371,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     } else {
         from_address.clone()
     };
+    // Contacts who signed an address proof are shown as verified
+    let contact = addressbook::load_default()
+        .ok()
+        .and_then(|book| book.find_address(&to_address_bytes));
     let to_display = if to_address.len() > 20 {
         format!(
             "{}...{}",
@@ -227,6 +401,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     );
     println!("{}", format!("║  👤 From: {:<47} ║", from_display).cyan());
     println!("{}", format!("║  🎯 To: {:<49} ║", to_display).cyan());
+    if let Some(entry) = &contact {
+        let mut contact_display = entry.label.clone();
+        if entry.is_verified() {
+            contact_display.push_str(" ✅ verified");
+        } else {
+            contact_display.push_str(" (unverified)");
+        }
+        println!(
+            "{}",
+            format!("║  📇 Contact: {:<44} ║", contact_display).cyan()
+        );
+    }
     println!("{}", format!("║  💸 Amount: {:<45} ║", amount).cyan());
     if let Some(ref m) = memo {
         let memo_display = if m.len() > 45 {
@@ -236,6 +422,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         };
         println!("{}", format!("║  📝 Memo: {:<47} ║", memo_display).cyan());
     }
+    if let Some(ref l) = label {
+        println!("{}", format!("║  🏷️  Label (private): {:<35} ║", l).cyan());
+    }
     println!(
         "{}",
         "╚══════════════════════════════════════════════════════════╝".bright_cyan()
@@ -255,12 +444,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let fee = Coord::from_num(0);
     let mut tx = TransferTx::new(
-        *input_hash,
+        input_hash,
         to_address_bytes,
         from_address_bytes,
         amount_coord,
         fee,
-        chain.blocks.len() as u64,
+        nonce,
     );
 
     if let Some(m) = memo {
@@ -275,19 +464,79 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tx.sign(signature.to_vec(), public_key.to_vec());
 
     let transaction = Transaction::Transfer(tx);
-    chain.mempool.add_transaction(transaction.clone())?;
-
-    pb.set_message("Saving mempool...");
-    let all_txs = chain.mempool.get_all_transactions();
-    std::fs::write("mempool.json", serde_json::to_string(&all_txs)?)?;
+    let tx_hash = hex::encode(transaction.hash());
+
+    // Validate the private label before anything is submitted
+    let mut tx_labels = None;
+    if let Some(l) = &label {
+        let mut labels = txlabels::load_default()?;
+        labels.set(&tx_hash, l, comment.as_deref())?;
+        tx_labels = Some(labels);
+    }
 
-    pb.set_message("Broadcasting to network...");
+    if let Some(node) = &remote {
+        pb.set_message("Submitting to remote node...");
+        node.submit_transaction(&transaction)?;
+    } else if offline {
+        pb.set_message("Queueing draft...");
+        let mut queue = drafts::load_default()?;
+        queue.push(transaction.clone())?;
+        drafts::save_default(&queue)?;
+    } else if let Some(mut chain) = local_chain {
+        chain.mempool.add_transaction(transaction.clone())?;
+
+        // No node is contacted here: the transaction only reaches the
+        // network once a node submits it
+        pb.set_message("Saving mempool...");
+        let all_txs = chain.mempool.get_all_transactions();
+        std::fs::write(
+            DataDir::current().mempool_path(),
+            serde_json::to_string(&all_txs)?,
+        )?;
+    }
 
-    let network_node = NetworkNode::new(Arc::new(RwLock::new(chain)));
-    network_node.broadcast_transaction(&transaction).await;
+    if let Some(labels) = &tx_labels {
+        pb.set_message("Saving transaction label...");
+        txlabels::save_default(labels)?;
+    }
 
     pb.finish_and_clear();
 
+    if remote.is_none() {
+        let (title, first, second) = if offline {
+            (
+                "║           📴 QUEUED AS A DRAFT (OFFLINE)                 ║",
+                "║  Nothing was sent. When online, submit it with          ║",
+                "║  `trinity tx drafts --send`                             ║",
+            )
+        } else {
+            (
+                "║           📝 SAVED TO LOCAL MEMPOOL ONLY                 ║",
+                "║  No node was contacted and nothing was broadcast.       ║",
+                "║  Resend with --node-url <url> to reach the network.     ║",
+            )
+        };
+        println!(
+            "{}",
+            "╔══════════════════════════════════════════════════════════╗".bright_yellow()
+        );
+        println!("{}", title.bright_yellow().bold());
+        println!(
+            "{}",
+            "╠══════════════════════════════════════════════════════════╣".bright_yellow()
+        );
+        println!("{}", first.yellow());
+        println!("{}", second.yellow());
+        println!(
+            "{}",
+            "╚══════════════════════════════════════════════════════════╝".bright_yellow()
+        );
+        println!();
+        println!("{}", format!("🔑 Transaction: {}", tx_hash).bright_black());
+        println!();
+        return Ok(());
+    }
+
     println!(
         "{}",
         "╔══════════════════════════════════════════════════════════╗".bright_green()
@@ -315,6 +564,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         "╚══════════════════════════════════════════════════════════╝".bright_green()
     );
     println!();
+    println!("{}", format!("🔑 Transaction: {}", tx_hash).bright_black());
     println!(
         "{}",
         "🎉 Transfer complete! The triangle is on its way!".bright_blue()
@@ -323,3 +573,33 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// Explain why no input could be chosen, pointing at pending sends and
+/// immature rewards when they are what makes the balance look sufficient
+fn no_input_error<'a>(
+    owned: impl IntoIterator<Item = (&'a Sha256Hash, &'a Triangle)>,
+    pending: &[Transaction],
+    immature: &HashSet<Sha256Hash>,
+    owners: &[Address],
+    amount: Coord,
+) -> String {
+    let breakdown = BalanceBreakdown::compute(owned, pending, immature, owners);
+    let mut message = format!(
+        "No single unlocked triangle worth {} (available: {} in {} triangle(s))",
+        amount, breakdown.available, breakdown.available_triangles
+    );
+    if breakdown.locked_triangles > 0 {
+        message.push_str(&format!(
+            "; another {} is locked by pending transactions until they confirm \
+             (see `trinity-balance --detailed`)",
+            breakdown.pending_out
+        ));
+    }
+    if breakdown.immature_triangles > 0 {
+        message.push_str(&format!(
+            "; another {} is immature mining rewards (spendable {} blocks after being mined)",
+            breakdown.immature, COINBASE_MATURITY
+        ));
+    }
+    message
+}
@@ -25,6 +25,8 @@ use tokio::sync::{Mutex, RwLock};
 use tower_http::cors::{Any, CorsLayer};
 use trinitychain::blockchain::Blockchain;
 use trinitychain::config::load_config;
+use trinitychain::datadir;
+use trinitychain::logging::{self, Console};
 use trinitychain::persistence::Database;
 
 #[derive(Clone)]
@@ -65,11 +67,13 @@ async fn get_stats(State(state): State<ServerData>) -> Json<Value> {
     let stats = state.stats.lock().await;
     let chain = state.chain.read().await;
 
+    let supply = chain.supply_info();
+
     Json(json!({
-        "chainHeight": chain.blocks.last().map(|b| b.header.height).unwrap_or(0),
+        "chainHeight": supply.height,
         "difficulty": chain.difficulty,
-        "totalSupply": 0,
-        "maxSupply": 420000000,
+        "totalSupply": supply.circulating_supply.to_string(),
+        "maxSupply": supply.max_supply,
         "uptime": stats.uptime_secs,
     }))
 }
@@ -250,8 +254,17 @@ fn draw_ui(f: &mut ratatui::Frame, stats: &ServerStats) {
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // No other arguments; only --datadir
+    datadir::args();
     let config = load_config()?;
     let port = config.network.api_port;
+    // The TUI owns the terminal, so logs only go to the file
+    logging::init(
+        &config.logging,
+        "trinity-server",
+        &config.database.path,
+        Console::Off,
+    )?;
 
     // Setup terminal
     enable_raw_mode()?;
@@ -261,9 +274,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut terminal = Terminal::new(backend)?;
 
     let db = Database::open(&config.database.path).expect("Failed to open database");
-    let chain = db.load_blockchain().unwrap_or_else(|_| {
-        Blockchain::new([0; 32], 1).expect("Failed to create new blockchain")
-    });
+    let chain = db.load_blockchain()?;
 
     let state = ServerData {
         chain: Arc::new(RwLock::new(chain.clone())),
@@ -1,19 +1,283 @@
 #![forbid(unsafe_code)]
-use log::{info, warn};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::sync::Arc;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
 use teloxide::{prelude::*, utils::command::BotCommands};
-use tokio::sync::{Mutex, RwLock};
-use trinitychain::blockchain::Blockchain;
+use tokio::sync::{broadcast, Mutex, RwLock};
+use tracing::{info, warn};
+use trinitychain::addressbook::{self, AddressBook};
+use trinitychain::balance::BalanceBreakdown;
+use trinitychain::blockchain::{Block, Blockchain, Sha256Hash, TxStatus};
 use trinitychain::cli::load_blockchain_from_config;
+use trinitychain::config::active_network;
+use trinitychain::crypto::Address;
+use trinitychain::datadir;
+use trinitychain::discovery::PeerDiscovery;
+use trinitychain::error::ChainError;
+use trinitychain::events::ChainEvent;
+use trinitychain::logging::{self, Console};
 use trinitychain::network::NetworkNode;
+use trinitychain::persistence::{Database, ADDRESS_WATCHES};
+use trinitychain::transaction::Transaction;
 
 type RateLimiter = Arc<Mutex<HashMap<i64, std::time::Instant>>>;
 
+/// Hex addresses watched by each chat
+type Watches = BTreeMap<i64, BTreeSet<String>>;
+
+/// Callback data prefix of the block paging buttons, followed by a height
+const BLOCK_CALLBACK: &str = "block:";
+
 #[derive(Clone)]
 struct BotState {
     chain: Arc<RwLock<Blockchain>>,
     network: Option<Arc<NetworkNode>>,
+    /// Labels users may look up instead of typing an address
+    addressbook: AddressBook,
+    /// `/watch` subscriptions, saved to the database on every change
+    watches: Arc<Mutex<Watches>>,
+    db_path: String,
+    #[cfg(feature = "faucet")]
+    faucet: Option<Arc<Mutex<trinitychain::faucet::Faucet>>>,
+}
+
+/// The address a user typed, or the one filed under an address book label
+/// (with or without a leading `@`), plus its label if it has one
+fn resolve_address(book: &AddressBook, input: &str) -> Result<(Address, Option<String>), String> {
+    let input = input.trim();
+    if let Ok(address) = Address::parse(input) {
+        return Ok((address, book.find_address(&address).map(|e| e.label)));
+    }
+    let entry = book
+        .resolve(input.trim_start_matches('@'))
+        .map_err(|e| e.to_string())?;
+    let address = Address::parse(&entry.address).map_err(|e| e.to_string())?;
+    Ok((address, Some(entry.label)))
+}
+
+fn parse_hash(input: &str) -> Option<Sha256Hash> {
+    hex::decode(input.trim()).ok()?.try_into().ok()
+}
+
+fn format_timestamp(millis: u64) -> String {
+    chrono::DateTime::from_timestamp_millis(millis as i64).map_or_else(
+        || millis.to_string(),
+        |t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+    )
+}
+
+fn block_summary(block: &Block, tip_height: u64) -> String {
+    let header = &block.header;
+    let reward = block
+        .transactions
+        .iter()
+        .find_map(|tx| match tx {
+            Transaction::Coinbase(cb) => Some(cb.reward_area.to_string()),
+            _ => None,
+        })
+        .unwrap_or_else(|| "none".to_string());
+    format!(
+        "🧱 Block {}\n\n\
+        🔗 Hash: {}\n\
+        🕒 Time: {}\n\
+        📦 Transactions: {}\n\
+        🎁 Reward: {} area\n\
+        ⬅️ Parent: {}\n\
+        ⚡ Difficulty: {}\n\
+        ✅ Confirmations: {}",
+        header.height,
+        hex::encode(block.hash()),
+        format_timestamp(header.timestamp),
+        block.transactions.len(),
+        reward,
+        hex::encode(header.previous_hash),
+        header.difficulty,
+        tip_height.saturating_sub(header.height) + 1
+    )
+}
+
+/// Older/newer buttons around the block at `height`
+fn block_keyboard(height: u64, tip_height: u64) -> InlineKeyboardMarkup {
+    let mut row = Vec::new();
+    if height > 0 {
+        row.push(InlineKeyboardButton::callback(
+            "◀️ Older",
+            format!("{}{}", BLOCK_CALLBACK, height - 1),
+        ));
+    }
+    if height < tip_height {
+        row.push(InlineKeyboardButton::callback(
+            "Newer ▶️",
+            format!("{}{}", BLOCK_CALLBACK, height + 1),
+        ));
+        row.push(InlineKeyboardButton::callback(
+            "⏭️ Tip",
+            format!("{}{}", BLOCK_CALLBACK, tip_height),
+        ));
+    }
+    InlineKeyboardMarkup::new(vec![row])
+}
+
+/// Summary and paging buttons for a block by height or hash (the tip when
+/// `block_ref` is empty)
+fn render_block(
+    chain: &Blockchain,
+    block_ref: &str,
+) -> Result<(String, InlineKeyboardMarkup), String> {
+    let block_ref = block_ref.trim();
+    let tip_height = chain.blocks.last().map_or(0, |b| b.header.height);
+    let block = if block_ref.is_empty() {
+        chain.blocks.last()
+    } else if let Ok(height) = block_ref.parse::<u64>() {
+        chain.get_block_by_height(height)
+    } else {
+        let hash = parse_hash(block_ref).ok_or("Expected a block height or 64-character hash")?;
+        chain.get_block_by_hash(&hash)
+    };
+    let block = block.ok_or("Block not found")?;
+    Ok((
+        block_summary(block, tip_height),
+        block_keyboard(block.header.height, tip_height),
+    ))
+}
+
+/// Summary of a transaction in the mempool or on the main chain, with a
+/// button to its block once confirmed
+fn render_tx(
+    chain: &Blockchain,
+    hash: &str,
+) -> Result<(String, Option<InlineKeyboardMarkup>), String> {
+    let hash = parse_hash(hash).ok_or("Usage: /tx <64-character transaction hash>")?;
+    let tx = chain
+        .mempool
+        .get_transaction(&hash)
+        .or_else(|| {
+            chain
+                .blocks
+                .iter()
+                .rev()
+                .flat_map(|b| &b.transactions)
+                .find(|tx| tx.hash() == hash)
+        })
+        .ok_or("Transaction not found in the mempool or on the main chain")?;
+
+    let mut response = format!(
+        "🧾 Transaction {}\n\n\
+        🏷️ Type: {}\n\
+        💸 Fee: {} area",
+        hex::encode(hash),
+        tx.kind(),
+        tx.fee_area()
+    );
+    if let Transaction::Coinbase(cb) = tx {
+        response.push_str(&format!(
+            "\n🎁 Reward: {} area to {}",
+            cb.reward_area,
+            hex::encode(cb.beneficiary_address)
+        ));
+    }
+    let keyboard = match chain.transaction_status(&hash, None) {
+        TxStatus::Confirmed {
+            block_height,
+            block_hash,
+            confirmations,
+        } => {
+            response.push_str(&format!(
+                "\n✅ Confirmed in block {} ({})\n🔒 Confirmations: {}",
+                block_height, block_hash, confirmations
+            ));
+            Some(InlineKeyboardMarkup::new(vec![vec![
+                InlineKeyboardButton::callback(
+                    format!("🧱 Block {}", block_height),
+                    format!("{}{}", BLOCK_CALLBACK, block_height),
+                ),
+            ]]))
+        }
+        TxStatus::Pending => {
+            response.push_str("\n⏳ Pending in the mempool");
+            None
+        }
+        TxStatus::Unknown => None,
+    };
+    Ok((response, keyboard))
+}
+
+fn save_watches(db_path: &str, watches: &Watches) -> Result<(), ChainError> {
+    Database::open(db_path)?.set_setting(&ADDRESS_WATCHES, watches)
+}
+
+/// Addresses a transaction moves funds out of (`true`) or into (`false`)
+fn fund_movements(tx: &Transaction) -> Vec<(Address, bool)> {
+    match tx {
+        Transaction::Transfer(t) if t.sender == t.new_owner => Vec::new(),
+        Transaction::Transfer(t) => vec![(t.sender, true), (t.new_owner, false)],
+        Transaction::BatchTransfer(t) => {
+            let mut movements = vec![(t.sender, true)];
+            for output in t.outputs.iter().filter(|o| o.recipient != t.sender) {
+                movements.push((output.recipient, false));
+            }
+            movements
+        }
+        Transaction::Coinbase(t) => vec![(t.beneficiary_address, false)],
+        Transaction::Subdivision(_) => Vec::new(),
+    }
+}
+
+/// Message each chat about its watched addresses that sent or received
+/// funds in blocks joining the main chain
+async fn notify_watchers(
+    bot: Bot,
+    state: Arc<BotState>,
+    mut events: broadcast::Receiver<ChainEvent>,
+) {
+    loop {
+        let block = match events.recv().await {
+            Ok(ChainEvent::BlockConnected(block)) => block,
+            Ok(_) => continue,
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                warn!("Watch notifications skipped {} chain event(s)", missed);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+
+        let watches = state.watches.lock().await.clone();
+        for (chat_id, addresses) in watches {
+            let mut lines = Vec::new();
+            for tx in &block.transactions {
+                for (address, sent) in fund_movements(tx) {
+                    let hex_address = hex::encode(address);
+                    if !addresses.contains(&hex_address) {
+                        continue;
+                    }
+                    let name = match state.addressbook.find_address(&address) {
+                        Some(entry) => format!("{} ({})", entry.label, hex_address),
+                        None => hex_address,
+                    };
+                    let (emoji, verb) = if sent {
+                        ("📤", "sent")
+                    } else {
+                        ("📥", "received")
+                    };
+                    lines.push(format!(
+                        "{} {} {} funds ({} {})",
+                        emoji,
+                        name,
+                        verb,
+                        tx.kind(),
+                        tx.hash_str()
+                    ));
+                }
+            }
+            if lines.is_empty() {
+                continue;
+            }
+            let msg = format!("🔔 Block #{}\n\n{}", block.header.height, lines.join("\n"));
+            if let Err(e) = bot.send_message(ChatId(chat_id), msg).await {
+                warn!("Failed to deliver watch notification to {}: {}", chat_id, e);
+            }
+        }
+    }
 }
 
 #[derive(BotCommands, Clone)]
@@ -28,10 +292,20 @@ enum Command {
     Help,
     #[command(description = "view blockchain statistics")]
     Stats,
-    #[command(description = "check wallet balance (address)")]
-    Balance,
+    #[command(description = "check a balance (address or address book label)")]
+    Balance(String),
     #[command(description = "view recent blocks")]
     Blocks,
+    #[command(description = "show a block (height or hash, latest if omitted)")]
+    Block(String),
+    #[command(description = "look up a transaction (hash)")]
+    Tx(String),
+    #[command(description = "get notified when an address sends or receives funds")]
+    Watch(String),
+    #[command(description = "stop watching an address")]
+    Unwatch(String),
+    #[command(description = "list the addresses this chat watches")]
+    Watches,
     #[command(description = "see genesis block")]
     Genesis,
     #[command(description = "count total triangles in UTXO")]
@@ -54,6 +328,9 @@ enum Command {
     Status,
     #[command(description = "broadcast raw tx hex to peers")]
     Broadcast(String),
+    #[cfg(feature = "faucet")]
+    #[command(description = "get testnet funds (address [token])")]
+    Faucet(String),
 }
 
 async fn answer(
@@ -81,35 +358,232 @@ async fn answer(
         }
         Command::Stats => {
             let chain = state.chain.read().await;
-            let height = chain.blocks.last().map_or(0, |b| b.header.height);
-            let total_supply: f64 = chain
-                .blocks
-                .iter()
-                .flat_map(|b| &b.transactions)
-                .filter_map(|tx| {
-                    if let trinitychain::transaction::Transaction::Coinbase(ctx) = tx {
-                        Some(ctx.reward_area.to_num::<f64>())
-                    } else {
-                        None
-                    }
-                })
-                .sum();
-            let current_reward =
-                trinitychain::blockchain::Blockchain::calculate_block_reward(height);
+            let supply = chain.supply_info();
             let triangles = chain.state.utxo_set.len();
 
             let response = format!(
                 "📊 Blockchain Statistics:\n\n\
                 🏔️ Height: {}\n\
-                💰 Total Supply: {} area\n\
+                💰 Total Supply: {} / {} area\n\
                 🎁 Current Block Reward: {} area\n\
+                ⏳ Next Halving: {} blocks (era {})\n\
                 🔺 Active Triangles: {}\n\
                 ⚡ Mining Difficulty: {}",
-                height, total_supply, current_reward, triangles, chain.difficulty
+                supply.height,
+                supply.circulating_supply,
+                supply.max_supply,
+                supply.current_reward,
+                supply.blocks_to_halving,
+                supply.halving_era,
+                triangles,
+                chain.difficulty
             );
             bot.send_message(message.chat.id, response).await?;
             info!("Handled /stats command for user: {:?}", message.from());
         }
+        Command::Balance(args) => {
+            if args.trim().is_empty() {
+                bot.send_message(message.chat.id, "Usage: /balance <address|label>")
+                    .await?;
+                return Ok(());
+            }
+            let (address, label) = match resolve_address(&state.addressbook, &args) {
+                Ok(resolved) => resolved,
+                Err(e) => {
+                    bot.send_message(message.chat.id, format!("❌ {}", e))
+                        .await?;
+                    return Ok(());
+                }
+            };
+
+            let response = {
+                let chain = state.chain.read().await;
+                let owned = chain
+                    .state
+                    .utxo_set
+                    .iter()
+                    .filter(|(_, triangle)| triangle.owner == address);
+                let pending = chain.mempool.get_all_transactions();
+                let next_height = chain.blocks.last().map_or(0, |b| b.header.height + 1);
+                let immature = chain.state.immature_outputs(next_height);
+                let breakdown = BalanceBreakdown::compute(owned, &pending, &immature, &[address]);
+                let triangles = breakdown.available_triangles
+                    + breakdown.immature_triangles
+                    + breakdown.locked_triangles;
+
+                let name = match &label {
+                    Some(label) => format!("{} ({})", label, address),
+                    None => address.to_string(),
+                };
+                let mut response = format!(
+                    "💰 Balance of {}\n\n\
+                    🔺 Triangles: {}\n\
+                    📐 Total area: {}\n\
+                    ✅ Spendable: {} ({} triangles)",
+                    name,
+                    triangles,
+                    breakdown.confirmed(),
+                    breakdown.available,
+                    breakdown.available_triangles
+                );
+                if breakdown.immature_triangles > 0 {
+                    response.push_str(&format!(
+                        "\n⏳ Immature: {} ({} triangles)",
+                        breakdown.immature, breakdown.immature_triangles
+                    ));
+                }
+                if breakdown.locked_triangles > 0 {
+                    response.push_str(&format!(
+                        "\n📤 Pending out: {} ({} triangles)",
+                        breakdown.pending_out, breakdown.locked_triangles
+                    ));
+                }
+                if breakdown.unconfirmed_in > 0 {
+                    response.push_str(&format!("\n📥 Pending in: {}", breakdown.unconfirmed_in));
+                }
+                response
+            };
+            bot.send_message(message.chat.id, response).await?;
+            info!("Handled /balance command for user: {:?}", message.from());
+        }
+        Command::Block(block_ref) => {
+            let rendered = render_block(&*state.chain.read().await, &block_ref);
+            match rendered {
+                Ok((summary, keyboard)) => {
+                    bot.send_message(message.chat.id, summary)
+                        .reply_markup(keyboard)
+                        .await?;
+                }
+                Err(e) => {
+                    bot.send_message(message.chat.id, format!("❌ {}", e))
+                        .await?;
+                }
+            }
+            info!("Handled /block command for user: {:?}", message.from());
+        }
+        Command::Tx(hash) => {
+            let rendered = render_tx(&*state.chain.read().await, &hash);
+            match rendered {
+                Ok((summary, Some(keyboard))) => {
+                    bot.send_message(message.chat.id, summary)
+                        .reply_markup(keyboard)
+                        .await?;
+                }
+                Ok((summary, None)) => {
+                    bot.send_message(message.chat.id, summary).await?;
+                }
+                Err(e) => {
+                    bot.send_message(message.chat.id, format!("❌ {}", e))
+                        .await?;
+                }
+            }
+            info!("Handled /tx command for user: {:?}", message.from());
+        }
+        Command::Watch(args) | Command::Unwatch(args) if args.trim().is_empty() => {
+            bot.send_message(
+                message.chat.id,
+                "Usage: /watch <address|label> or /unwatch <address|label>",
+            )
+            .await?;
+        }
+        Command::Watch(args) => {
+            let (address, label) = match resolve_address(&state.addressbook, &args) {
+                Ok(resolved) => resolved,
+                Err(e) => {
+                    bot.send_message(message.chat.id, format!("❌ {}", e))
+                        .await?;
+                    return Ok(());
+                }
+            };
+            let name = label.unwrap_or_else(|| address.to_string());
+
+            let mut watches = state.watches.lock().await;
+            let added = watches
+                .entry(message.chat.id.0)
+                .or_default()
+                .insert(hex::encode(address));
+            let response = if !added {
+                format!("👀 Already watching {}", name)
+            } else if let Err(e) = save_watches(&state.db_path, &watches) {
+                warn!("Failed to save watches: {}", e);
+                format!(
+                    "👀 Watching {} until the bot restarts (could not save: {})",
+                    name, e
+                )
+            } else {
+                format!(
+                    "👀 Watching {}. You'll get a message when it sends or receives funds.",
+                    name
+                )
+            };
+            drop(watches);
+            bot.send_message(message.chat.id, response).await?;
+            info!("Handled /watch command for user: {:?}", message.from());
+        }
+        Command::Unwatch(args) => {
+            let (address, label) = match resolve_address(&state.addressbook, &args) {
+                Ok(resolved) => resolved,
+                Err(e) => {
+                    bot.send_message(message.chat.id, format!("❌ {}", e))
+                        .await?;
+                    return Ok(());
+                }
+            };
+            let name = label.unwrap_or_else(|| address.to_string());
+
+            let mut watches = state.watches.lock().await;
+            let chat = message.chat.id.0;
+            let removed = watches
+                .get_mut(&chat)
+                .is_some_and(|watched| watched.remove(&hex::encode(address)));
+            if watches.get(&chat).is_some_and(BTreeSet::is_empty) {
+                watches.remove(&chat);
+            }
+            let response = if !removed {
+                format!("Not watching {}", name)
+            } else if let Err(e) = save_watches(&state.db_path, &watches) {
+                warn!("Failed to save watches: {}", e);
+                format!(
+                    "🔕 Stopped watching {} until the bot restarts (could not save: {})",
+                    name, e
+                )
+            } else {
+                format!("🔕 Stopped watching {}", name)
+            };
+            drop(watches);
+            bot.send_message(message.chat.id, response).await?;
+            info!("Handled /unwatch command for user: {:?}", message.from());
+        }
+        Command::Watches => {
+            let watched = state
+                .watches
+                .lock()
+                .await
+                .get(&message.chat.id.0)
+                .cloned()
+                .unwrap_or_default();
+            let response = if watched.is_empty() {
+                "👀 This chat watches no addresses. Use /watch <address|label> to add one."
+                    .to_string()
+            } else {
+                let list = watched
+                    .iter()
+                    .map(|hex_address| {
+                        let label = Address::parse(hex_address)
+                            .ok()
+                            .and_then(|a| state.addressbook.find_address(&a));
+                        match label {
+                            Some(entry) => format!("• {} ({})", entry.label, hex_address),
+                            None => format!("• {}", hex_address),
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                format!("👀 Watched addresses ({})\n\n{}", watched.len(), list)
+            };
+            bot.send_message(message.chat.id, response).await?;
+            info!("Handled /watches command for user: {:?}", message.from());
+        }
         Command::Mempool => {
             let chain = state.chain.read().await;
             let pool_size = chain.mempool.len();
@@ -258,6 +732,50 @@ async fn answer(
 
             info!("Handled /broadcast command for user: {:?}", message.from());
         }
+        #[cfg(feature = "faucet")]
+        Command::Faucet(args) => {
+            let Some(faucet) = state.faucet.as_ref() else {
+                bot.send_message(message.chat.id, "🚰 The faucet is not enabled on this bot.")
+                    .await?;
+                return Ok(());
+            };
+
+            let mut parts = args.split_whitespace();
+            let recipient = parts
+                .next()
+                .and_then(|a| trinitychain::crypto::address_from_hex(a).ok());
+            let Some(recipient) = recipient else {
+                bot.send_message(message.chat.id, "Usage: /faucet <address> [token]")
+                    .await?;
+                return Ok(());
+            };
+            let token = parts.next();
+            let requester = message.from().map(|u| format!("telegram:{}", u.id.0));
+
+            let result = {
+                let mut chain = state.chain.write().await;
+                faucet
+                    .lock()
+                    .await
+                    .request(&mut chain, recipient, requester.as_deref(), token)
+            };
+
+            match result {
+                Ok(tx) => {
+                    if let Some(node) = state.network.as_ref() {
+                        node.broadcast_transaction(&tx).await;
+                    }
+                    let amount = faucet.lock().await.amount();
+                    let msg = format!("🚰 Sent {} area\nTransaction: {}", amount, tx.hash_str());
+                    bot.send_message(message.chat.id, msg).await?;
+                }
+                Err(e) => {
+                    warn!("Faucet request refused for {:?}: {}", message.from(), e);
+                    bot.send_message(message.chat.id, format!("❌ {}", e)).await?;
+                }
+            }
+            info!("Handled /faucet command for user: {:?}", message.from());
+        }
         _ => {
             bot.send_message(message.chat.id, "Command not implemented yet.")
                 .await?;
@@ -266,29 +784,136 @@ async fn answer(
     Ok(())
 }
 
+/// Page the block summary a button belongs to, editing it in place
+async fn page_block(bot: Bot, query: CallbackQuery, state: Arc<BotState>) -> ResponseResult<()> {
+    let height = query
+        .data
+        .as_deref()
+        .and_then(|data| data.strip_prefix(BLOCK_CALLBACK))
+        .unwrap_or_default()
+        .to_string();
+    let rendered = render_block(&*state.chain.read().await, &height);
+    match (rendered, query.message) {
+        (Ok((summary, keyboard)), Some(message)) => {
+            bot.answer_callback_query(query.id).await?;
+            bot.edit_message_text(message.chat.id, message.id, summary)
+                .reply_markup(keyboard)
+                .await?;
+        }
+        (Err(e), _) => {
+            bot.answer_callback_query(query.id).text(e).await?;
+        }
+        (Ok(_), None) => {
+            bot.answer_callback_query(query.id).await?;
+        }
+    }
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() {
-    env_logger::init();
+    // No other arguments; only --datadir
+    datadir::args();
+    let (_config, mut chain) = load_blockchain_from_config().expect("Failed to load blockchain");
+    logging::init(
+        &_config.logging,
+        "trinity-telegram-bot",
+        &_config.database.path,
+        Console::Stderr,
+    )
+    .expect("Failed to set up logging");
     info!("Starting TrinityChain Telegram Bot...");
 
     let bot = Bot::from_env();
     let admin_token = std::env::var("BOT_ADMIN_TOKEN").ok();
     let rate_limiter: RateLimiter = Arc::new(Mutex::new(HashMap::new()));
 
-    let (_config, chain) = load_blockchain_from_config().expect("Failed to load blockchain");
+    if let Some(policy) = trinitychain::policy::TxPolicy::from_config(&_config.policy)
+        .expect("Invalid transaction policy")
+    {
+        chain.mempool.set_policy(Arc::new(policy));
+    }
     let chain = Arc::new(RwLock::new(chain));
 
-    let network = Arc::new(NetworkNode::new(Arc::clone(&chain)));
+    #[cfg(feature = "faucet")]
+    let faucet = trinitychain::faucet::Faucet::from_config(&_config.faucet)
+        .expect("Failed to load faucet wallet")
+        .map(|f| Arc::new(Mutex::new(f)));
+
+    let mut network = NetworkNode::new(Arc::clone(&chain))
+        .with_protocol_policy(_config.network.protocol_policy());
+
+    // Forward watchtower alerts to a chat when WATCHTOWER_CHAT_ID is set
+    let watchtower = trinitychain::watchtower::Watchtower::from_config(&_config.watchtower)
+        .expect("Invalid watchtower configuration")
+        .map(Arc::new);
+    if let Some(watchtower) = watchtower {
+        if let Some(chat_id) = std::env::var("WATCHTOWER_CHAT_ID")
+            .ok()
+            .and_then(|id| id.parse::<i64>().ok())
+        {
+            let mut alerts = watchtower.subscribe();
+            let alert_bot = bot.clone();
+            tokio::spawn(async move {
+                while let Ok(alert) = alerts.recv().await {
+                    if let Err(e) = alert_bot.send_message(ChatId(chat_id), alert.describe()).await {
+                        warn!("Failed to deliver watchtower alert: {}", e);
+                    }
+                }
+            });
+            info!("Watchtower alerts will be sent to chat {}", chat_id);
+        }
+        let interval = std::time::Duration::from_secs(_config.watchtower.poll_interval_secs.max(1));
+        tokio::spawn(watchtower.clone().watch(Arc::clone(&chain), interval));
+        network = network.with_watchtower(watchtower);
+    }
+
+    let network = Arc::new(network);
+    let mut discovery = PeerDiscovery::new();
+    for seed in active_network().dns_seeds() {
+        discovery.add_dns_seed(seed);
+    }
+    for peer in _config.network.bootstrap_peers() {
+        discovery.add_bootstrap_peer(peer);
+    }
+    tokio::spawn(network.clone().maintain_connections(discovery, 8));
+    let events = chain.read().await.subscribe_events();
+
+    let addressbook = addressbook::load_default().unwrap_or_else(|e| {
+        warn!(
+            "Address book unavailable, /balance takes addresses only: {}",
+            e
+        );
+        AddressBook::new()
+    });
+    let watches = Database::open(&_config.database.path)
+        .and_then(|db| db.get_setting(&ADDRESS_WATCHES))
+        .unwrap_or_else(|e| {
+            warn!("Failed to load watches: {}", e);
+            None
+        })
+        .unwrap_or_default();
     let state = Arc::new(BotState {
         chain,
         network: Some(network),
+        addressbook,
+        watches: Arc::new(Mutex::new(watches)),
+        db_path: _config.database.path.clone(),
+        #[cfg(feature = "faucet")]
+        faucet,
     });
 
+    tokio::spawn(notify_watchers(bot.clone(), state.clone(), events));
+
     Dispatcher::builder(
         bot,
-        Update::filter_message()
-            .filter_command::<Command>()
-            .endpoint(answer),
+        dptree::entry()
+            .branch(
+                Update::filter_message()
+                    .filter_command::<Command>()
+                    .endpoint(answer),
+            )
+            .branch(Update::filter_callback_query().endpoint(page_block)),
     )
     .dependencies(dptree::deps![state, admin_token, rate_limiter])
     .enable_ctrlc_handler()
@@ -1,6 +1,8 @@
 #![forbid(unsafe_code)]
 use clap::{Parser, Subcommand};
 use colored::*;
+use std::path::PathBuf;
+use trinitychain::datadir;
 use trinitychain::wallet;
 
 #[derive(Parser)]
@@ -8,6 +10,9 @@ use trinitychain::wallet;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Data directory (default ~/.trinitychain); relative database paths are taken inside it
+    #[arg(long, global = true)]
+    datadir: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -25,6 +30,9 @@ enum Commands {
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
+    if let Some(dir) = &cli.datadir {
+        datadir::set_override(dir);
+    }
 
     match &cli.command {
         Commands::Login { name } => {
@@ -2,9 +2,11 @@
 //! Backup wallet with password encryption
 
 use std::io::{self, Write};
+use trinitychain::datadir;
 use trinitychain::wallet::{self, EncryptedWallet};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    datadir::args();
     println!("🔐 Wallet Backup Tool\n");
 
     // Load current wallet
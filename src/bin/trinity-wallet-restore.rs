@@ -3,10 +3,11 @@
 
 use std::io::{self, Write};
 use std::path::PathBuf;
+use trinitychain::datadir;
 use trinitychain::wallet::{self, EncryptedWallet};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = std::env::args().collect();
+    let args: Vec<String> = datadir::args();
 
     println!("🔓 Wallet Restore Tool\n");
 
@@ -2,6 +2,10 @@
 //! Wallet CLI for TrinityChain - Beautiful edition!
 
 use colored::*;
+use rpassword::prompt_password;
+use trinitychain::crypto::Address;
+use trinitychain::datadir;
+use trinitychain::hdwallet::{self, HDKeystore, HDWallet};
 use trinitychain::wallet::{self};
 
 const LOGO: &str = r#"
@@ -20,7 +24,7 @@ const LOGO: &str = r#"
 "#;
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
+    let args: Vec<String> = datadir::args();
 
     if args.len() < 2 {
         print_usage();
@@ -42,6 +46,28 @@ fn main() {
                 show_address(None)
             }
         }
+        "new-address" => new_hd_address(args.get(2).cloned()),
+        "new-account" => {
+            if args.len() > 2 {
+                new_hd_account(&args[2])
+            } else {
+                println!(
+                    "{}",
+                    "❌ Usage: trinity-wallet new-account <name>".red().bold()
+                );
+            }
+        }
+        "accounts" => list_hd_accounts(),
+        "watch" => {
+            if args.len() > 3 {
+                create_watch_only_wallet(&args[2], &args[3])
+            } else {
+                println!(
+                    "{}",
+                    "❌ Usage: trinity-wallet watch <name> <address>".red().bold()
+                );
+            }
+        }
         "list" => list_wallets(),
         "help" => print_usage(),
         _ => {
@@ -145,6 +171,291 @@ fn create_wallet(name: Option<String>) {
     }
 }
 
+/// Unlock the HD keystore, creating one with a fresh recovery phrase (also
+/// returned) if there is none yet
+fn unlock_or_create_keystore(
+) -> Result<(HDKeystore, HDWallet, Option<String>), Box<dyn std::error::Error>> {
+    wallet::ensure_wallet_dir()?;
+    let path = hdwallet::get_keystore_path()?;
+
+    if path.exists() {
+        let keystore = HDKeystore::load(&path)?;
+        let password = prompt_password("Enter your HD keystore password: ")?;
+        let hd = keystore.unlock(&password)?;
+        Ok((keystore, hd, None))
+    } else {
+        let mnemonic = HDWallet::generate_mnemonic(24)?;
+        let password = prompt_password("Enter a password for your new HD keystore: ")?;
+        let (keystore, hd) = HDKeystore::create(&mnemonic, None, &password)?;
+        Ok((keystore, hd, Some(mnemonic)))
+    }
+}
+
+fn print_recovery_phrase(mnemonic: &str) {
+    println!("{}", "🌱 Created a new HD keystore".bright_green().bold());
+    println!();
+    println!("{}", "⚠️  WRITE DOWN YOUR RECOVERY PHRASE:".yellow().bold());
+    println!("{}", format!("   {}", mnemonic).white().bold());
+    println!();
+}
+
+fn new_hd_address(account: Option<String>) {
+    print_banner();
+
+    let result =
+        (|| -> Result<(hdwallet::DerivedKey, Option<String>), Box<dyn std::error::Error>> {
+            let (mut keystore, hd, mnemonic) = unlock_or_create_keystore()?;
+            let account = match &account {
+                Some(account) => keystore.resolve_account(account)?,
+                None => 0,
+            };
+            // A new keystore already holds the first address of account 0
+            let key = if mnemonic.is_some() && account == 0 {
+                keystore.keys[0].clone()
+            } else {
+                keystore.next_address(&hd, account)?
+            };
+            keystore.save(&hdwallet::get_keystore_path()?)?;
+            Ok((key, mnemonic))
+        })();
+
+    match result {
+        Ok((key, mnemonic)) => {
+            if let Some(mnemonic) = mnemonic {
+                print_recovery_phrase(&mnemonic);
+            }
+            println!(
+                "{}",
+                "╔══════════════════════════════════════════════════════════╗".green()
+            );
+            println!(
+                "{}",
+                "║              ✨ Fresh Receive Address ✨                 ║"
+                    .green()
+                    .bold()
+            );
+            println!(
+                "{}",
+                "╠══════════════════════════════════════════════════════════╣".green()
+            );
+            println!(
+                "{}",
+                format!("║  📍 Address: {:<42} ║", &key.address[..42]).green()
+            );
+            println!(
+                "{}",
+                format!("║             {:<42} ║", &key.address[42..]).green()
+            );
+            println!("{}", format!("║  🧭 Path: {:<46} ║", key.path).green());
+            println!(
+                "{}",
+                "╚══════════════════════════════════════════════════════════╝".green()
+            );
+            println!();
+            println!(
+                "{}",
+                "💡 Check all derived balances with: trinity-balance --hd".bright_blue()
+            );
+            println!();
+        }
+        Err(e) => {
+            println!("{}", "╔══════════════════════════════════════════╗".red());
+            println!(
+                "{}",
+                "║      ❌ Address Derivation Failed!      ║".red().bold()
+            );
+            println!("{}", "╠══════════════════════════════════════════╣".red());
+            println!("{}", format!("║  Error: {:<32} ║", e.to_string()).red());
+            println!("{}", "╚══════════════════════════════════════════╝".red());
+            println!();
+        }
+    }
+}
+
+fn new_hd_account(name: &str) {
+    print_banner();
+
+    let result =
+        (|| -> Result<(u32, hdwallet::DerivedKey, Option<String>), Box<dyn std::error::Error>> {
+            let (mut keystore, hd, mnemonic) = unlock_or_create_keystore()?;
+            let (account, key) = keystore.create_account(&hd, name)?;
+            keystore.save(&hdwallet::get_keystore_path()?)?;
+            Ok((account, key, mnemonic))
+        })();
+
+    match result {
+        Ok((account, key, mnemonic)) => {
+            if let Some(mnemonic) = mnemonic {
+                print_recovery_phrase(&mnemonic);
+            }
+            println!(
+                "{}",
+                "╔══════════════════════════════════════════════════════════╗".green()
+            );
+            println!(
+                "{}",
+                "║                 📂 HD Account Created 📂                 ║"
+                    .green()
+                    .bold()
+            );
+            println!(
+                "{}",
+                "╠══════════════════════════════════════════════════════════╣".green()
+            );
+            println!(
+                "{}",
+                format!(
+                    "║  🏷️  Account: {:<43} ║",
+                    format!("{} (#{})", name, account)
+                )
+                .green()
+            );
+            println!(
+                "{}",
+                format!("║  📍 Address: {:<42} ║", &key.address[..42]).green()
+            );
+            println!(
+                "{}",
+                format!("║             {:<42} ║", &key.address[42..]).green()
+            );
+            println!("{}", format!("║  🧭 Path: {:<46} ║", key.path).green());
+            println!(
+                "{}",
+                "╚══════════════════════════════════════════════════════════╝".green()
+            );
+            println!();
+            println!(
+                "{}",
+                format!(
+                    "💡 More addresses: trinity-wallet new-address {}  |  Balance: trinity-balance --account {}",
+                    name, name
+                )
+                .bright_blue()
+            );
+            println!();
+        }
+        Err(e) => {
+            println!("{}", "╔══════════════════════════════════════════╗".red());
+            println!(
+                "{}",
+                "║      ❌ Account Creation Failed!        ║".red().bold()
+            );
+            println!("{}", "╠══════════════════════════════════════════╣".red());
+            println!("{}", format!("║  Error: {:<32} ║", e.to_string()).red());
+            println!("{}", "╚══════════════════════════════════════════╝".red());
+            println!();
+        }
+    }
+}
+
+fn list_hd_accounts() {
+    print_banner();
+
+    let keystore = match hdwallet::get_keystore_path().and_then(|path| HDKeystore::load(&path)) {
+        Ok(keystore) => keystore,
+        Err(e) => {
+            println!("{}", format!("❌ No HD keystore: {}", e).red());
+            println!(
+                "{}",
+                "💡 Run 'trinity-wallet new-address' to create one".yellow()
+            );
+            println!();
+            return;
+        }
+    };
+
+    println!(
+        "{}",
+        "╔══════════════════════════════════════════════════════════╗".magenta()
+    );
+    println!(
+        "{}",
+        "║                    📂 HD Accounts                        ║"
+            .magenta()
+            .bold()
+    );
+    println!(
+        "{}",
+        "╠══════════════════════════════════════════════════════════╣".magenta()
+    );
+    for account in keystore.account_indices() {
+        let name = keystore.account_name(account).unwrap_or("-");
+        let addresses = keystore.account_addresses(account).len();
+        println!(
+            "{}",
+            format!(
+                "║  #{:<4} {:<28} {:>6} address(es) ║",
+                account, name, addresses
+            )
+            .magenta()
+        );
+    }
+    println!(
+        "{}",
+        "╚══════════════════════════════════════════════════════════╝".magenta()
+    );
+    println!();
+    println!(
+        "{}",
+        "💡 Per-account balances: trinity-balance --hd".bright_blue()
+    );
+    println!();
+}
+
+fn create_watch_only_wallet(name: &str, address: &str) {
+    print_banner();
+
+    match wallet::create_watch_only_wallet(name, address) {
+        Ok(wallet) => {
+            println!(
+                "{}",
+                "╔══════════════════════════════════════════════════════════╗".green()
+            );
+            println!(
+                "{}",
+                "║           👁️  Watch-Only Wallet Created! 👁️              ║"
+                    .green()
+                    .bold()
+            );
+            println!(
+                "{}",
+                "╠══════════════════════════════════════════════════════════╣".green()
+            );
+            println!("{}", format!("║  🏷️  Name: {:<45} ║", name).green());
+            println!(
+                "{}",
+                format!("║  📍 Address: {:<42} ║", &wallet.address[..42]).green()
+            );
+            println!(
+                "{}",
+                format!("║             {:<42} ║", &wallet.address[42..]).green()
+            );
+            println!(
+                "{}",
+                "╚══════════════════════════════════════════════════════════╝".green()
+            );
+            println!();
+            println!(
+                "{}",
+                format!("💡 Check its balance with: WALLET_NAME={} trinity-balance", name)
+                    .bright_blue()
+            );
+            println!();
+        }
+        Err(e) => {
+            println!("{}", "╔══════════════════════════════════════════╗".red());
+            println!(
+                "{}",
+                "║    ❌ Watch-Only Wallet Failed!         ║".red().bold()
+            );
+            println!("{}", "╠══════════════════════════════════════════╣".red());
+            println!("{}", format!("║  Error: {:<32} ║", e.to_string()).red());
+            println!("{}", "╚══════════════════════════════════════════╝".red());
+            println!();
+        }
+    }
+}
+
 fn show_address(address: Option<String>) {
     print_banner();
 
@@ -206,10 +517,20 @@ fn show_address(address: Option<String>) {
                 "╚══════════════════════════════════════════════════════════╝".cyan()
             );
             println!();
+            if let Ok(address) = Address::parse(&wallet.address) {
+                println!(
+                    "{}",
+                    format!("🔐 Checksummed: {}", address).bright_white()
+                );
+            }
             println!(
                 "{}",
                 "💡 Tip: Share this address to receive triangles!".bright_blue()
             );
+            println!(
+                "{}",
+                "   Either form works; the checksummed one catches typos.".bright_blue()
+            );
             println!();
         }
         Err(e) => {
@@ -340,6 +661,22 @@ fn print_usage() {
         "{}",
         "║    📍 address   Show your wallet address                ║".bright_yellow()
     );
+    println!(
+        "{}",
+        "║    🌱 new-address  Derive a fresh HD receive address    ║".bright_yellow()
+    );
+    println!(
+        "{}",
+        "║    📂 new-account  Open a named HD account              ║".bright_yellow()
+    );
+    println!(
+        "{}",
+        "║    📂 accounts  List HD accounts                        ║".bright_yellow()
+    );
+    println!(
+        "{}",
+        "║    👁️  watch     Track an address without its key        ║".bright_yellow()
+    );
     println!(
         "{}",
         "║    📋 list      List all available wallets              ║".bright_yellow()
@@ -376,6 +713,18 @@ fn print_usage() {
         "{}",
         "║    $ trinity-wallet list                                 ║".white()
     );
+    println!(
+        "{}",
+        "║    $ trinity-wallet watch cold <address>                 ║".white()
+    );
+    println!(
+        "{}",
+        "║    $ trinity-wallet new-account savings                  ║".white()
+    );
+    println!(
+        "{}",
+        "║    $ trinity-wallet new-address savings                  ║".white()
+    );
     println!(
         "{}",
         "║                                                          ║".bright_yellow()
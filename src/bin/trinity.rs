@@ -0,0 +1,2567 @@
+#![forbid(unsafe_code)]
+//! Node maintenance and diagnostics commands
+
+use clap::{Parser, Subcommand};
+use colored::*;
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use indicatif::{ProgressBar, ProgressStyle};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block as TuiBlock, Borders, Clear, Paragraph, Row, Table, TableState, Wrap},
+    Terminal,
+};
+use rpassword::prompt_password;
+use std::collections::HashSet;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use trinitychain::addressbook::{self, AddressEntry};
+use trinitychain::balance::{BalanceBreakdown, OutpointLocker};
+use trinitychain::blockchain::{
+    Blockchain, Sha256Hash, TriangleState, TxStatus, DIFFICULTY_ADJUSTMENT_INTERVAL,
+    TARGET_BLOCK_TIME,
+};
+use trinitychain::chain_export::{self, ExportFormat, ImportOptions, ImportProgress};
+use trinitychain::cli::{load_blockchain_from_config, parse_recipients};
+use trinitychain::config::{self, load_config, Config, Network};
+use trinitychain::crypto::{self, Address, ExternalSigner, MessageSignature, Signer};
+use trinitychain::datadir::{self, DataDir};
+use trinitychain::difficulty_sim::{
+    HashrateProfile, RetargetAlgorithm, Simulation, DEFAULT_MAX_BLOCKS, HISTOGRAM_BUCKETS,
+};
+use trinitychain::drafts;
+use trinitychain::forks::{ForkStatus, COMPETING_FORK_DEPTH};
+use trinitychain::geometry::{Coord, Triangle, GEOMETRIC_TOLERANCE};
+use trinitychain::hdwallet::{self, HDKeystore, HDWallet, COIN_TYPE};
+use trinitychain::lineage::{LineageFormat, LineageGraph};
+use trinitychain::persistence::{Database, IBD_COMMIT_BLOCKS};
+use trinitychain::rawtx::{self, RawFormat};
+use trinitychain::remote::RemoteNode;
+use trinitychain::state_diff::StateDiff;
+use trinitychain::sync::{format_eta, SyncPhase};
+use trinitychain::transaction::{BatchTransferTx, Transaction};
+use trinitychain::wallet::{self, EncryptedWallet, Wallet};
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+    /// Make no network calls: remote nodes are never contacted and signed
+    /// transactions are queued as drafts (also TRINITY_OFFLINE=1)
+    #[arg(long, global = true)]
+    offline: bool,
+    /// Data directory (default ~/.trinitychain); relative database paths are taken inside it
+    #[arg(long, global = true)]
+    datadir: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Re-validates a stored block and prints per-transaction timings
+    BenchValidate {
+        /// Hash of the block to validate (hex)
+        block_hash: String,
+    },
+    /// Replays the stored chain from genesis, re-checking every block and the UTXO set
+    VerifyChain,
+    /// Traces a triangle back through its subdivisions and transfers to the coinbase that minted it
+    Lineage {
+        /// Triangle hash (hex); spent triangles can be traced too
+        triangle_hash: String,
+    },
+    /// Writes the triangle subdivision/transfer graph for Graphviz or Gephi
+    ExportLineage {
+        /// Output format: dot or graphml
+        #[arg(long, default_value = "dot")]
+        format: LineageFormat,
+        /// Only triangles owned by this address (and their direct relatives)
+        #[arg(long)]
+        owner: Option<String>,
+        /// Only this triangle and its descendants
+        #[arg(long)]
+        root: Option<String>,
+        /// Output file (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Dumps blocks and transactions as JSON Lines or CSV for offline analysis
+    ExportChain {
+        /// Output format: json (one block per line) or csv (one row per output)
+        #[arg(long, default_value = "json")]
+        format: ExportFormat,
+        /// First block height
+        #[arg(long, default_value_t = 0)]
+        from: u64,
+        /// Last block height (defaults to the tip)
+        #[arg(long)]
+        to: Option<u64>,
+        /// Output file (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Replays blocks from a raw export or another node's database through full validation
+    ImportChain {
+        /// `export-chain --format raw` output, or a chain database file
+        source: PathBuf,
+        /// Blocks applied per database transaction
+        #[arg(long, default_value_t = IBD_COMMIT_BLOCKS)]
+        commit_every: usize,
+        /// Write a UTXO snapshot every this many blocks
+        #[arg(long)]
+        snapshot_every: Option<u64>,
+        /// Where snapshots go (defaults to snapshots/ beside the database)
+        #[arg(long)]
+        snapshot_dir: Option<PathBuf>,
+    },
+    /// Reports whether a transaction is pending, confirmed (and how deeply) or unknown
+    TxStatus {
+        /// Transaction hash (hex)
+        tx_hash: String,
+        /// Ask this node's API instead of the local database (also TRINITY_NODE_URL)
+        #[arg(long)]
+        node_url: Option<String>,
+    },
+    /// Signs a message with the key of one of your addresses, proving you control it
+    SignMessage {
+        /// Address to sign with; the wallet holding it is found automatically
+        address: String,
+        message: String,
+    },
+    /// Checks a message signed by `sign-message` against an address
+    VerifyMessage {
+        address: String,
+        message: String,
+        /// Signature printed by `sign-message` (hex)
+        signature: String,
+    },
+    /// Lists every chain tip a node knows of, to spot network splits early
+    Forks {
+        /// Node to ask (also TRINITY_NODE_URL); only a running node sees side branches
+        #[arg(long)]
+        node_url: Option<String>,
+        /// Print the tips as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Shows how far a running node is from its best peer's tip
+    SyncStatus {
+        /// Node to ask (also TRINITY_NODE_URL)
+        #[arg(long)]
+        node_url: Option<String>,
+        /// Print the status as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Lists triangles created and spent and balance changes between two heights
+    StateDiff {
+        /// Height of the earlier state
+        from: u64,
+        /// Height of the later state
+        to: u64,
+        /// Only changes touching this address
+        #[arg(long)]
+        address: Option<String>,
+        /// Print the diff as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Finds confirmed transactions whose memo contains the given text
+    SearchMemo {
+        /// Text to look for (at least 3 characters, ASCII case ignored)
+        query: String,
+        /// Maximum number of transactions to list
+        #[arg(long, default_value_t = 20)]
+        limit: u64,
+        /// Ask this node's API instead of the local database (also TRINITY_NODE_URL)
+        #[arg(long)]
+        node_url: Option<String>,
+    },
+    /// Pays several recipients out of one triangle in a single batch transfer
+    SendMany {
+        /// Recipients: CSV lines of `address,amount`, or a JSON array of {"address", "amount"}
+        #[arg(long)]
+        file: PathBuf,
+        /// Wallet to pay from (defaults to the default wallet)
+        #[arg(long)]
+        from: Option<String>,
+        /// Fee area paid to the miner
+        #[arg(long, default_value_t = 0.0)]
+        fee: f64,
+        /// Public memo stored with the transaction
+        #[arg(long)]
+        memo: Option<String>,
+        /// Submit through this node's API instead of the local mempool (also TRINITY_NODE_URL)
+        #[arg(long)]
+        node_url: Option<String>,
+    },
+    /// Builds, signs and broadcasts transactions as separate steps, for air-gapped signing
+    Tx {
+        #[command(subcommand)]
+        command: TxCommand,
+    },
+    /// Replays difficulty adjustment against a synthetic hash-rate timeline
+    SimulateDifficulty {
+        /// JSON file: {"initial_difficulty": N, "segments": [{"duration_secs": S, "hashrate": H}]}
+        #[arg(long)]
+        hashrate_profile: PathBuf,
+        /// Retarget rule: interval (consensus), asert or asert:<half-life secs>; repeat to compare
+        #[arg(long = "algorithm", default_value = "interval")]
+        algorithms: Vec<RetargetAlgorithm>,
+        /// Random seed; the same seed and profile always give the same chain
+        #[arg(long, default_value_t = 1)]
+        seed: u64,
+        /// Stop after this many blocks even if the profile has not ended
+        #[arg(long, default_value_t = DEFAULT_MAX_BLOCKS)]
+        max_blocks: usize,
+        /// Also write every simulated block to this CSV file (first algorithm only)
+        #[arg(long)]
+        csv: Option<PathBuf>,
+    },
+    /// Opens the interactive address book manager
+    AddressBook,
+    /// HD wallet tools
+    Wallet {
+        #[command(subcommand)]
+        command: WalletCommand,
+    },
+    /// Shows or edits node settings (config.toml plus TRINITY_<SECTION>__<KEY> overrides)
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Runs a node: P2P networking and the HTTP API over the same chain
+    #[cfg(feature = "api")]
+    Server {
+        /// API port (defaults to [network] api_port in config.toml)
+        #[arg(long)]
+        port: Option<u16>,
+    },
+    /// Prints the wallet compatibility vectors, or checks a vector file against this build
+    #[cfg(feature = "test-vectors")]
+    TestVectors {
+        /// Vector file to check instead of printing
+        #[arg(long)]
+        verify: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Prints the effective settings as TOML, secrets redacted, and reports invalid ones
+    Show {
+        /// Only this section or setting, e.g. `network` or `network.p2p_port`
+        key: Option<String>,
+        /// Print the defaults a fresh config.toml starts from instead
+        #[arg(long)]
+        defaults: bool,
+        /// Network whose defaults to print with --defaults
+        #[arg(long, default_value = "mainnet", requires = "defaults")]
+        chain: Network,
+    },
+    /// Changes one setting in config.toml, keeping its comments and layout
+    Set {
+        /// Setting path, e.g. `miner.threads` or `logging.modules.network`
+        key: String,
+        /// New value as TOML (`4`, `true`, `["a:1"]`); bare words are strings
+        value: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum WalletCommand {
+    /// Lists addresses derived from your seed, or checks which index produced an address
+    Derive {
+        /// Path template; the final `i` (or `i'`) is replaced by each index
+        #[arg(long, default_value_t = format!("m/44'/{}'/0'/0/i", COIN_TYPE))]
+        path: String,
+        /// Number of indices to derive (or search)
+        #[arg(long, default_value_t = 10)]
+        count: u32,
+        /// First index
+        #[arg(long, default_value_t = 0)]
+        start: u32,
+        /// Confirm this address belongs to the seed and print its index
+        #[arg(long)]
+        verify: Option<String>,
+        /// Read the recovery phrase from the prompt instead of the HD keystore
+        #[arg(long)]
+        mnemonic: bool,
+    },
+    /// Prints a wallet's key as a 24-word recovery phrase, or writes an encrypted copy
+    Backup {
+        /// Wallet to back up (defaults to the default wallet)
+        #[arg(long)]
+        name: Option<String>,
+        /// Write the key, sealed with a new backup password, to this file instead
+        #[arg(long)]
+        encrypted: Option<PathBuf>,
+    },
+    /// Rebuilds a wallet file from a recovery phrase, sealed with a new password
+    Restore {
+        /// Wallet to restore into (defaults to the default wallet)
+        #[arg(long)]
+        name: Option<String>,
+        /// The phrase is an HD seed phrase: restore its first receive address
+        /// (m/44'/1618'/0'/0/0) using the BIP-39 passphrase
+        #[arg(long)]
+        hd: bool,
+        /// Replace an existing wallet file
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum TxCommand {
+    /// Builds an unsigned transaction; needs chain data but no keys
+    Create {
+        /// Address (or wallet name, watch-only is fine) whose triangle is spent
+        #[arg(long)]
+        from: String,
+        /// Recipient as `address,amount`; repeat for a batch transfer
+        #[arg(long)]
+        to: Vec<String>,
+        /// More recipients: CSV lines of `address,amount`, or a JSON array of {"address", "amount"}
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// Triangle to spend (hex); defaults to the smallest unlocked one that covers the payment
+        #[arg(long)]
+        input: Option<String>,
+        /// Fee area paid to the miner
+        #[arg(long, default_value_t = 0.0)]
+        fee: f64,
+        /// Public memo stored with the transaction
+        #[arg(long)]
+        memo: Option<String>,
+        /// Output encoding: json or hex
+        #[arg(long, default_value = "json")]
+        format: RawFormat,
+        /// Write the unsigned transaction here instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Read chain state from this node's API instead of the local database (also TRINITY_NODE_URL)
+        #[arg(long)]
+        node_url: Option<String>,
+    },
+    /// Signs a raw transaction with a local wallet or an external signer; never touches the chain or network
+    Sign {
+        /// Raw transaction file (JSON or hex), or `-` for stdin
+        file: PathBuf,
+        /// Wallet to sign with (defaults to the default wallet)
+        #[arg(long)]
+        wallet: Option<String>,
+        /// Sign with this external signer command instead of a wallet, e.g. a hardware wallet bridge
+        #[arg(long, conflicts_with = "wallet")]
+        signer: Option<String>,
+        /// Output encoding (defaults to the input's)
+        #[arg(long)]
+        format: Option<RawFormat>,
+        /// Write the signed transaction here instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Submits a signed raw transaction to the mempool (queues it as a draft when offline)
+    Broadcast {
+        /// Signed transaction file (JSON or hex), or `-` for stdin
+        file: PathBuf,
+        /// Submit through this node's API instead of the local mempool (also TRINITY_NODE_URL)
+        #[arg(long)]
+        node_url: Option<String>,
+    },
+    /// Lists transactions queued while offline, or submits them
+    Drafts {
+        /// Submit every draft; those accepted leave the queue
+        #[arg(long, conflicts_with = "drop")]
+        send: bool,
+        /// Remove the draft whose hash starts with this prefix
+        #[arg(long)]
+        drop: Option<String>,
+        /// Submit through this node's API instead of the local mempool (also TRINITY_NODE_URL)
+        #[arg(long)]
+        node_url: Option<String>,
+    },
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    if let Some(dir) = &cli.datadir {
+        datadir::set_override(dir);
+    }
+    let offline = cli.offline || drafts::offline_from_env();
+    if offline {
+        eprintln!(
+            "{}",
+            "📴 Offline mode: no network calls; transactions are queued as drafts".yellow()
+        );
+    }
+
+    // Wallet tools work offline and never touch the chain database
+    if let Commands::Wallet { command } = cli.command {
+        return match command {
+            WalletCommand::Derive {
+                path,
+                count,
+                start,
+                verify,
+                mnemonic,
+            } => wallet_derive(&path, count, start, verify, mnemonic),
+            WalletCommand::Backup { name, encrypted } => wallet_backup(name, encrypted),
+            WalletCommand::Restore { name, hd, force } => wallet_restore(name, hd, force),
+        };
+    }
+
+    // Settings are read and written without opening the chain
+    if let Commands::Config { command } = cli.command {
+        return match command {
+            ConfigCommand::Show {
+                key,
+                defaults,
+                chain,
+            } => config_show(key, defaults.then_some(chain)),
+            ConfigCommand::Set { key, value } => config_set(&key, &value),
+        };
+    }
+
+    #[cfg(feature = "test-vectors")]
+    if let Commands::TestVectors { verify } = cli.command {
+        return test_vectors(verify);
+    }
+
+    // The address book is a local file, independent of the chain
+    if let Commands::AddressBook = cli.command {
+        return address_book_tui();
+    }
+
+    // Simulation needs neither a node nor a database
+    if let Commands::SimulateDifficulty {
+        hashrate_profile,
+        algorithms,
+        seed,
+        max_blocks,
+        csv,
+    } = cli.command
+    {
+        return simulate_difficulty(&hashrate_profile, &algorithms, seed, max_blocks, csv);
+    }
+
+    // Sending loads the chain itself, or talks to a remote node
+    if let Commands::SendMany {
+        file,
+        from,
+        fee,
+        memo,
+        node_url,
+    } = cli.command
+    {
+        return send_many(&file, from, fee, memo, node_url, offline);
+    }
+
+    if let Commands::Tx { command } = cli.command {
+        return match command {
+            TxCommand::Create {
+                from,
+                to,
+                file,
+                input,
+                fee,
+                memo,
+                format,
+                output,
+                node_url,
+            } => tx_create(
+                &from, &to, file, input, fee, memo, format, output, node_url, offline,
+            ),
+            TxCommand::Sign {
+                file,
+                wallet,
+                signer,
+                format,
+                output,
+            } => tx_sign(&file, wallet, signer, format, output),
+            TxCommand::Broadcast { file, node_url } => tx_broadcast(&file, node_url, offline),
+            TxCommand::Drafts {
+                send,
+                drop,
+                node_url,
+            } => tx_drafts(send, drop, node_url, offline),
+        };
+    }
+
+    // Message signatures need a wallet, never the chain
+    if let Commands::SignMessage { address, message } = &cli.command {
+        return sign_message(address, message);
+    }
+    if let Commands::VerifyMessage {
+        address,
+        message,
+        signature,
+    } = &cli.command
+    {
+        return verify_message(address, message, signature);
+    }
+
+    // Side branches only live in a running node's memory
+    if let Commands::Forks { node_url, json } = cli.command {
+        return forks(node_url, json, offline);
+    }
+
+    // Sync progress only lives in a running node's memory
+    if let Commands::SyncStatus { node_url, json } = cli.command {
+        return sync_status(node_url, json, offline);
+    }
+
+    // A remote node is asked directly, so the local database may be locked or absent
+    if let Commands::TxStatus { tx_hash, node_url } = &cli.command {
+        if let Some(node) = RemoteNode::from_url_unless_offline(node_url.clone(), offline)? {
+            let status = node.transaction_status(tx_hash)?;
+            print_tx_status(tx_hash, &status, &node.url());
+            return Ok(());
+        }
+    }
+
+    if let Commands::SearchMemo {
+        query,
+        limit,
+        node_url,
+    } = &cli.command
+    {
+        if let Some(node) = RemoteNode::from_url_unless_offline(node_url.clone(), offline)? {
+            let (found, total) = node.search_memos(query, *limit)?;
+            let found: Vec<_> = found
+                .into_iter()
+                .map(|e| (e.transaction, e.block_height))
+                .collect();
+            print_memo_matches(query, &found, total, &node.url());
+            return Ok(());
+        }
+    }
+
+    let config = load_config()?;
+    let db = Database::open(&config.database.path)?;
+
+    match cli.command {
+        Commands::BenchValidate { block_hash } => bench_validate(&db, &block_hash)?,
+        Commands::VerifyChain => verify_chain(&db)?,
+        Commands::Lineage { triangle_hash } => lineage(&db, &triangle_hash)?,
+        Commands::ExportLineage {
+            format,
+            owner,
+            root,
+            output,
+        } => export_lineage(&db, format, owner, root, output)?,
+        Commands::ExportChain {
+            format,
+            from,
+            to,
+            output,
+        } => export_chain(&db, format, from, to, output)?,
+        Commands::ImportChain {
+            source,
+            commit_every,
+            snapshot_every,
+            snapshot_dir,
+        } => {
+            let mut options = ImportOptions::default().with_commit_every(commit_every);
+            if let Some(every) = snapshot_every {
+                let dir = snapshot_dir.unwrap_or_else(|| DataDir::current().snapshots_dir());
+                options = options.with_snapshots(every, dir);
+            }
+            import_chain(&db, &source, &options)?
+        }
+        Commands::TxStatus { tx_hash, .. } => {
+            let mut hash = [0u8; 32];
+            hex::decode_to_slice(&tx_hash, &mut hash)
+                .map_err(|e| format!("Invalid transaction hash: {}", e))?;
+            let status = db.transaction_status(&hash)?;
+            print_tx_status(&tx_hash, &status, &config.database.path);
+        }
+        Commands::StateDiff {
+            from,
+            to,
+            address,
+            json,
+        } => state_diff(&db, from, to, address, json)?,
+        Commands::SearchMemo { query, limit, .. } => {
+            search_memo(&db, &query, limit, &config.database.path)?
+        }
+        Commands::SendMany { .. }
+        | Commands::Tx { .. }
+        | Commands::Forks { .. }
+        | Commands::SyncStatus { .. }
+        | Commands::SignMessage { .. }
+        | Commands::VerifyMessage { .. }
+        | Commands::SimulateDifficulty { .. }
+        | Commands::AddressBook
+        | Commands::Config { .. }
+        | Commands::Wallet { .. } => unreachable!(),
+        #[cfg(feature = "api")]
+        Commands::Server { port } => server(&config, &db, port)?,
+        #[cfg(feature = "test-vectors")]
+        Commands::TestVectors { .. } => unreachable!(),
+    }
+
+    Ok(())
+}
+
+/// Serve the stored chain over P2P and HTTP. The API node and the P2P node
+/// share one `Arc<RwLock<Blockchain>>`, so blocks mined and transactions
+/// submitted through the API are broadcast to peers, and blocks from peers
+/// are visible to the API.
+#[cfg(feature = "api")]
+fn server(
+    config: &trinitychain::config::Config,
+    db: &Database,
+    port: Option<u16>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use std::sync::Arc;
+    use trinitychain::api::{run_api_server_on, Node};
+    use trinitychain::config::active_network;
+    use trinitychain::discovery::PeerDiscovery;
+    use trinitychain::policy::TxPolicy;
+
+    let features = config.node.features();
+    if !features.api {
+        return Err("The HTTP API is disabled for this node's role ([node] api = false)".into());
+    }
+
+    let mut blockchain = db.load_blockchain()?;
+    if let Some(policy) = TxPolicy::from_config(&config.policy)? {
+        println!("{}", "🚫 Transaction policy enabled".yellow());
+        blockchain.mempool.set_policy(Arc::new(policy));
+    }
+    let height = blockchain.blocks.last().map_or(0, |b| b.header.height);
+
+    let db_path = config.database.path.clone();
+    let node = Node::new(blockchain)
+        .with_features(features)
+        .with_database(&db_path)
+        .with_stale_tip(
+            config.miner.stale_tip_action(active_network()),
+            config.miner.stale_tip_threshold(),
+        )
+        .with_network(|network| {
+            network
+                .with_peer_db(&db_path)
+                .with_protocol_policy(config.network.protocol_policy())
+        })
+        .with_api_config(&config.api)?;
+    let node = Arc::new(node);
+
+    let p2p_port = config.network.p2p_port;
+    let api_port = port.unwrap_or(config.network.api_port);
+    let mut discovery = PeerDiscovery::new();
+    for seed in active_network().dns_seeds() {
+        discovery.add_dns_seed(seed);
+    }
+    for peer in config.network.bootstrap_peers() {
+        discovery.add_bootstrap_peer(peer);
+    }
+
+    println!(
+        "{}",
+        format!(
+            "🚀 Serving chain at height {} on {}",
+            height,
+            active_network().name()
+        )
+        .bright_cyan()
+    );
+    println!("   🌐 P2P  0.0.0.0:{}", p2p_port);
+    println!("   🔗 API  http://0.0.0.0:{}/api", api_port);
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let network = node.network.clone();
+        tokio::spawn(async move {
+            if let Err(e) = network.start_server(p2p_port).await {
+                eprintln!("{} P2P server stopped: {}", "❌".red(), e);
+            }
+        });
+        tokio::spawn(node.network.clone().maintain_connections(discovery, 8));
+        run_api_server_on(node, api_port).await
+    })
+}
+
+#[cfg(feature = "test-vectors")]
+fn test_vectors(verify: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+    use trinitychain::vectors::{self, VectorSuite};
+
+    let path = match verify {
+        Some(path) => path,
+        None => {
+            println!("{}", serde_json::to_string_pretty(&vectors::generate()?)?);
+            return Ok(());
+        }
+    };
+    let suite: VectorSuite = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+    let failures = vectors::verify(&suite);
+    if failures.is_empty() {
+        let keys: usize = suite.wallets.iter().map(|w| w.keys.len()).sum();
+        println!(
+            "{} {} key and {} transaction vector(s) match",
+            "✅".green(),
+            keys,
+            suite.transactions.len()
+        );
+        return Ok(());
+    }
+    for failure in &failures {
+        println!("{} {}", "❌".red(), failure);
+    }
+    let summary = format!("{} vector mismatch(es) in {}", failures.len(), path.display());
+    Err(summary.into())
+}
+
+fn bench_validate(db: &Database, block_hash: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let chain = db.load_blockchain()?;
+    let block = chain
+        .blocks
+        .iter()
+        .find(|b| hex::encode(b.hash()) == block_hash.to_lowercase())
+        .ok_or_else(|| format!("Block {} not found", block_hash))?;
+    let height = block.header.height;
+
+    if let Some(base) = db.header_only_height()? {
+        if height <= base {
+            return Err(format!(
+                "Block #{} was pruned or imported from a snapshot and has no transactions",
+                height
+            )
+            .into());
+        }
+    }
+
+    // Rebuild the UTXO set as it was before this block
+    let mut state = TriangleState::new();
+    for parent in &chain.blocks[..height as usize] {
+        for tx in &parent.transactions {
+            state.apply_transaction(tx, parent.header.height)?;
+        }
+    }
+
+    println!(
+        "{}",
+        format!(
+            "⏱️  Validating block #{} ({} transactions)",
+            height,
+            block.transactions.len()
+        )
+        .bright_cyan()
+    );
+    println!(
+        "   {:<5} {:<12} {:<16} {:>12} {:>12}",
+        "#", "Type", "Hash", "Signature", "State"
+    );
+
+    let started = Instant::now();
+    let mut signature_total = Duration::ZERO;
+    let mut state_total = Duration::ZERO;
+    for (i, tx) in block.transactions.iter().enumerate() {
+        let signature_start = Instant::now();
+        if !matches!(tx, Transaction::Coinbase(_)) {
+            tx.validate_stateless(height)?;
+        }
+        let signature = signature_start.elapsed();
+
+        let state_start = Instant::now();
+        if !matches!(tx, Transaction::Coinbase(_)) {
+            tx.validate_state(&state, height)?;
+        }
+        state.apply_transaction(tx, height)?;
+        let state_time = state_start.elapsed();
+
+        signature_total += signature;
+        state_total += state_time;
+        println!(
+            "   {:<5} {:<12} {:<16} {:>10}µs {:>10}µs",
+            i,
+            tx.kind(),
+            &tx.hash_str()[..16],
+            signature.as_micros(),
+            state_time.as_micros()
+        );
+    }
+
+    println!();
+    println!("   Signature checks: {}µs", signature_total.as_micros());
+    println!("   State checks:     {}µs", state_total.as_micros());
+    println!(
+        "{}",
+        format!("✅ Block validated in {}µs", started.elapsed().as_micros()).green()
+    );
+    Ok(())
+}
+
+fn verify_chain(db: &Database) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(base) = db.header_only_height()? {
+        return Err(format!(
+            "Blocks up to #{} were pruned or imported from a snapshot; the chain can't be replayed",
+            base
+        )
+        .into());
+    }
+    let chain = db.load_blockchain()?;
+
+    println!(
+        "{}",
+        format!("🔍 Verifying {} blocks from genesis", chain.blocks.len()).bright_cyan()
+    );
+    let pb = ProgressBar::new(chain.blocks.len() as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{bar:40.cyan/blue} {pos}/{len} blocks ({eta} left)")
+            .unwrap(),
+    );
+    let started = Instant::now();
+    let report = chain.verify_full(|_| pb.inc(1))?;
+    pb.finish_and_clear();
+
+    println!(
+        "   Checked {} blocks in {:.1}s",
+        report.blocks_checked,
+        started.elapsed().as_secs_f64()
+    );
+    for issue in &report.issues {
+        println!(
+            "   {} #{} {}: {}",
+            "✗".red(),
+            issue.height,
+            &hex::encode(issue.hash)[..16],
+            issue.reason
+        );
+    }
+    if !report.utxo_set_matches {
+        println!(
+            "   {} The stored UTXO set differs from the one rebuilt by the replay",
+            "✗".red()
+        );
+    }
+
+    if report.is_ok() {
+        println!("{}", "✅ Chain and UTXO set are consistent".green());
+        Ok(())
+    } else {
+        Err(format!(
+            "Found {} invalid block(s){}",
+            report.issues.len(),
+            if report.utxo_set_matches {
+                ""
+            } else {
+                " and a mismatched UTXO set"
+            }
+        )
+        .into())
+    }
+}
+
+fn print_tx_status(tx_hash: &str, status: &TxStatus, source: &str) {
+    println!("{}", format!("🔎 Transaction {}", tx_hash).bright_cyan());
+    match status {
+        TxStatus::Pending => println!("   {}", "⏳ Pending in the mempool".yellow()),
+        TxStatus::Confirmed {
+            block_height,
+            block_hash,
+            confirmations,
+        } => {
+            println!(
+                "   {}",
+                format!(
+                    "✅ Confirmed in block #{} ({} confirmation{})",
+                    block_height,
+                    confirmations,
+                    if *confirmations == 1 { "" } else { "s" }
+                )
+                .green()
+            );
+            println!("   Block hash: {}", block_hash);
+        }
+        TxStatus::Unknown => println!(
+            "   {}",
+            "❓ Unknown: not pending and not on the main chain".red()
+        ),
+    }
+    println!("   {}", format!("Source: {}", source).dimmed());
+}
+
+fn sign_message(address: &str, message: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let address = Address::parse(address)?;
+    let name = wallet::wallet_name_for_address(&address)?
+        .ok_or_else(|| format!("None of your wallets holds {}", address))?;
+    let signing_wallet = match name.as_str() {
+        "default" => wallet::load_default_wallet()?,
+        name => wallet::load_named_wallet(name)?,
+    };
+    if signing_wallet.watch_only {
+        return Err(format!("Wallet '{}' is watch-only and cannot sign", name).into());
+    }
+    let signature = crypto::sign_message(&signing_wallet.get_keypair()?, message)?;
+
+    println!(
+        "{}",
+        format!("✍️  Message signed by {} (wallet '{}')", address, name).bright_cyan()
+    );
+    println!("   Message:   {:?}", message);
+    println!("   Signature: {}", signature.to_hex());
+    println!(
+        "   {}",
+        "Anyone can check it with `trinity verify-message <address> <message> <signature>`"
+            .dimmed()
+    );
+    Ok(())
+}
+
+fn verify_message(
+    address: &str,
+    message: &str,
+    signature: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let address = Address::parse(address)?;
+    let signature = MessageSignature::from_hex(signature)?;
+    crypto::verify_message(&address, message, &signature)?;
+    println!(
+        "{}",
+        format!("✅ Valid: the message was signed by {}", address).green()
+    );
+    Ok(())
+}
+
+fn forks(
+    node_url: Option<String>,
+    json: bool,
+    offline: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if offline {
+        return Err("Listing forks asks a running node, which offline mode forbids".into());
+    }
+    let node = RemoteNode::from_url_or_env(node_url)?.ok_or(
+        "Side branches are only known to a running node; pass --node-url or set TRINITY_NODE_URL",
+    )?;
+    let tips = node.forks()?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&tips)?);
+        return Ok(());
+    }
+
+    let active_height = tips
+        .iter()
+        .find(|t| t.status == ForkStatus::Active)
+        .map_or(0, |t| t.height);
+    println!(
+        "{}",
+        format!("🍴 {} known chain tip(s)", tips.len()).bright_cyan()
+    );
+    println!(
+        "   {:<9} {:>8} {:>7} {:>8} {:>7}  {:<16} {:<20} Peers",
+        "Status", "Height", "Branch", "Fork at", "Behind", "Hash", "Last seen"
+    );
+    for tip in &tips {
+        let status = match tip.status {
+            ForkStatus::Active => format!("{:<9}", "active").green(),
+            ForkStatus::Fork => format!("{:<9}", "fork").yellow(),
+            ForkStatus::Detached => format!("{:<9}", "detached").yellow(),
+            ForkStatus::Invalid => format!("{:<9}", "invalid").red(),
+        };
+        let last_seen = tip
+            .last_seen
+            .and_then(|secs| chrono::DateTime::from_timestamp(secs as i64, 0))
+            .map_or("-".to_string(), |t| {
+                t.format("%Y-%m-%d %H:%M:%S").to_string()
+            });
+        println!(
+            "   {} {:>8} {:>7} {:>8} {:>7}  {:<16} {:<20} {}",
+            status,
+            tip.height,
+            tip.branch_length,
+            tip.fork_height.map_or("-".to_string(), |h| h.to_string()),
+            active_height as i64 - tip.height as i64,
+            &tip.hash[..16.min(tip.hash.len())],
+            last_seen,
+            tip.peers.join(", ")
+        );
+    }
+
+    // A valid branch within a few blocks of the tip means miners disagree
+    let competing = tips
+        .iter()
+        .filter(|t| {
+            t.status == ForkStatus::Fork && t.height + COMPETING_FORK_DEPTH >= active_height
+        })
+        .count();
+    if competing > 0 {
+        println!(
+            "{}",
+            format!(
+                "⚠️  {} competing branch(es) within {} blocks of the tip",
+                competing, COMPETING_FORK_DEPTH
+            )
+            .yellow()
+        );
+    }
+    println!("   {}", format!("Source: {}", node.url()).dimmed());
+    Ok(())
+}
+
+fn sync_status(
+    node_url: Option<String>,
+    json: bool,
+    offline: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if offline {
+        return Err("Sync status asks a running node, which offline mode forbids".into());
+    }
+    let node = RemoteNode::from_url_or_env(node_url)?.ok_or(
+        "Sync progress is only known to a running node; pass --node-url or set TRINITY_NODE_URL",
+    )?;
+    let status = node.sync_status()?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&status)?);
+        return Ok(());
+    }
+
+    let headline = match status.phase {
+        SyncPhase::Synced => "✅ Synced".green(),
+        SyncPhase::Syncing => "🔄 Syncing".yellow(),
+        SyncPhase::Idle => "💤 Idle: no peer has announced a height".dimmed(),
+        SyncPhase::Failed => "❌ Sync failed".red(),
+    };
+    println!("{}", headline);
+    println!("   Height:  {}", status.height);
+    if let Some(target) = status.target_height {
+        println!(
+            "   Target:  {} ({} to go, {:.1}%)",
+            target,
+            status.remaining(),
+            status.progress() * 100.0
+        );
+    }
+    println!("   Speed:   {:.1} blocks/s", status.blocks_per_sec);
+    if let Some(eta) = status.eta_secs {
+        println!("   ETA:     {}", format_eta(eta));
+    }
+    println!("   {}", format!("Source: {}", node.url()).dimmed());
+    Ok(())
+}
+
+fn state_diff(
+    db: &Database,
+    from: u64,
+    to: u64,
+    address: Option<String>,
+    json: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(base) = db.header_only_height()? {
+        return Err(format!(
+            "Blocks up to #{} were pruned or imported from a snapshot; there is no history to replay",
+            base
+        )
+        .into());
+    }
+    let chain = db.load_blockchain()?;
+    let mut diff = StateDiff::between(&chain.blocks, from, to)?;
+    if let Some(address) = address {
+        diff = diff.filter_address(&Address::parse(&address)?);
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+        return Ok(());
+    }
+
+    println!(
+        "{}",
+        format!("🔀 State diff #{} → #{}", diff.from_height, diff.to_height).bright_cyan()
+    );
+    println!("   {}", format!("Created ({})", diff.created.len()).green());
+    for change in &diff.created {
+        println!(
+            "   + {}  {:>14}  {}",
+            change.hash, change.value, change.owner
+        );
+    }
+    println!("   {}", format!("Spent ({})", diff.spent.len()).red());
+    for change in &diff.spent {
+        println!(
+            "   - {}  {:>14}  {}",
+            change.hash, change.value, change.owner
+        );
+    }
+    println!(
+        "   {}",
+        format!("Balances ({})", diff.balances.len()).yellow()
+    );
+    for balance in &diff.balances {
+        println!(
+            "   {}  {} → {}  ({:+})",
+            balance.address, balance.before, balance.after, balance.delta
+        );
+    }
+    println!(
+        "   {}",
+        format!("Net unspent value: {:+}", diff.net_value()).dimmed()
+    );
+    Ok(())
+}
+
+fn search_memo(
+    db: &Database,
+    query: &str,
+    limit: u64,
+    source: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !db.memo_index_enabled() {
+        let hint = "set `memo_index = true` under [node] in config.toml and restart the node";
+        return Err(format!("The memo index is off; {} to build it", hint).into());
+    }
+    let stats = db.memo_index_stats()?;
+    println!(
+        "{}",
+        format!(
+            "📝 Memo index: {} memo(s), {} bytes on disk",
+            stats.entries, stats.bytes
+        )
+        .dimmed()
+    );
+
+    let total = db.count_memo_matches(query)?;
+    let found: Vec<_> = db
+        .search_memos(query, 0, limit)?
+        .into_iter()
+        .map(|e| (e.transaction, e.block_height))
+        .collect();
+    print_memo_matches(query, &found, total, source);
+    Ok(())
+}
+
+fn print_memo_matches(query: &str, found: &[(Transaction, u64)], total: u64, source: &str) {
+    println!(
+        "{}",
+        format!("🔎 {} transaction(s) with \"{}\" in the memo", total, query).bright_cyan()
+    );
+    for (tx, height) in found {
+        println!(
+            "   #{:<8} {}  {}",
+            height,
+            tx.hash_str(),
+            tx.memo().unwrap_or_default()
+        );
+    }
+    if total > found.len() as u64 {
+        println!("   … {} more (raise --limit)", total - found.len() as u64);
+    }
+    println!(
+        "   {}",
+        "⚠️  Memos are public on-chain: anyone can search them like this".yellow()
+    );
+    println!("   {}", format!("Source: {}", source).dimmed());
+}
+
+fn lineage(db: &Database, triangle_hash: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(base) = db.header_only_height()? {
+        return Err(format!(
+            "Blocks up to #{} were pruned or imported from a snapshot; their history is not available",
+            base
+        )
+        .into());
+    }
+    let hash: [u8; 32] = hex::decode(triangle_hash)?
+        .try_into()
+        .map_err(|_| "Triangle hash must be 32 bytes")?;
+    let chain = db.load_blockchain()?;
+    let steps = chain.trace_lineage(&hash)?;
+
+    println!(
+        "{}",
+        format!("🧬 Lineage of triangle {}", &triangle_hash[..16]).bright_cyan()
+    );
+    for step in &steps {
+        let status = match step.node.spent_at {
+            Some(height) => format!("spent #{}", height),
+            None => "unspent".green().to_string(),
+        };
+        println!(
+            "   {}  owner {}  value {}  created #{}  {}",
+            &hex::encode(step.hash)[..16],
+            &hex::encode(step.node.owner)[..16],
+            step.node.value,
+            step.node.created_at,
+            status
+        );
+        match step.kind {
+            Some(kind) => println!("      ↑ {} of", kind.as_str()),
+            None => println!("      {}", "⛏️  minted by coinbase".yellow()),
+        }
+    }
+    Ok(())
+}
+
+fn export_lineage(
+    db: &Database,
+    format: LineageFormat,
+    owner: Option<String>,
+    root: Option<String>,
+    output: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(base) = db.header_only_height()? {
+        return Err(format!(
+            "Blocks up to #{} were pruned or imported from a snapshot; their history is not available",
+            base
+        )
+        .into());
+    }
+    let chain = db.load_blockchain()?;
+    let mut graph = LineageGraph::from_blocks(&chain.blocks)?;
+
+    if let Some(root) = root {
+        let root: [u8; 32] = hex::decode(&root)?
+            .try_into()
+            .map_err(|_| "Triangle hash must be 32 bytes")?;
+        graph = graph.subtree(&root)?;
+    }
+    if let Some(owner) = owner {
+        graph = graph.filter_owner(&Address::parse(&owner)?);
+    }
+
+    let rendered = graph.render(format);
+    match output {
+        Some(path) => {
+            std::fs::write(&path, rendered)?;
+            eprintln!(
+                "{}",
+                format!(
+                    "✅ Wrote {} triangles and {} edges to {}",
+                    graph.nodes.len(),
+                    graph.edges.len(),
+                    path.display()
+                )
+                .green()
+            );
+        }
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
+
+fn export_chain(
+    db: &Database,
+    format: ExportFormat,
+    from: u64,
+    to: Option<u64>,
+    output: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let to = to.unwrap_or(u64::MAX);
+    if from > to {
+        return Err(format!("--from {} is above --to {}", from, to).into());
+    }
+    match output {
+        Some(path) => {
+            let file = std::io::BufWriter::new(std::fs::File::create(&path)?);
+            let summary = chain_export::export_chain(db, from, to, format, file)?;
+            eprintln!(
+                "{}",
+                format!(
+                    "✅ Wrote {} blocks and {} transactions to {}",
+                    summary.blocks,
+                    summary.transactions,
+                    path.display()
+                )
+                .green()
+            );
+        }
+        None => {
+            let stdout = std::io::stdout();
+            chain_export::export_chain(db, from, to, format, stdout.lock())?;
+        }
+    }
+    Ok(())
+}
+
+fn import_chain(
+    db: &Database,
+    source: &Path,
+    options: &ImportOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut chain = db.load_blockchain()?;
+    let start = chain.blocks.last().map_or(0, |b| b.header.height);
+    println!(
+        "{}",
+        format!(
+            "📥 Importing {} onto the local chain at #{}...",
+            source.display(),
+            start
+        )
+        .bright_cyan()
+    );
+
+    let started = Instant::now();
+    let report = |p: &ImportProgress| {
+        let rate = p.applied as f64 / started.elapsed().as_secs_f64().max(0.001);
+        println!(
+            "   ⏳ #{} ({} blocks validated, {:.0} blocks/s)",
+            p.height, p.applied, rate
+        );
+        if let Some(path) = &p.snapshot {
+            println!("   📸 Snapshot written to {}", path.display());
+        }
+    };
+
+    let mut magic = [0u8; 16];
+    let is_database = std::fs::File::open(source)?.read_exact(&mut magic).is_ok()
+        && &magic == b"SQLite format 3\0";
+    let status = if is_database {
+        // The genesis block is checked against the local one, not the network's
+        let source_db = Database::open(source)?.with_custom_genesis();
+        let blocks = source_db.blocks_from(0, IBD_COMMIT_BLOCKS as u64);
+        chain_export::import_chain(db, &mut chain, blocks, options, report)?
+    } else {
+        let reader = std::io::BufReader::new(std::fs::File::open(source)?);
+        let blocks = chain_export::read_raw_blocks(reader);
+        chain_export::import_chain(db, &mut chain, blocks, options, report)?
+    };
+
+    println!(
+        "{}",
+        format!(
+            "✅ Validated and stored {} blocks ({} already present); tip is #{}",
+            status.applied,
+            status.skipped,
+            chain.blocks.last().map_or(0, |b| b.header.height)
+        )
+        .green()
+    );
+    Ok(())
+}
+
+fn send_many(
+    file: &Path,
+    from: Option<String>,
+    fee: f64,
+    memo: Option<String>,
+    node_url: Option<String>,
+    offline: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let outputs = parse_recipients(&std::fs::read_to_string(file)?)?;
+    if outputs.is_empty() {
+        return Err(format!("{} lists no recipients", file.display()).into());
+    }
+    if outputs.len() > BatchTransferTx::MAX_OUTPUTS {
+        return Err(format!(
+            "{} lists {} recipients; a batch pays at most {}",
+            file.display(),
+            outputs.len(),
+            BatchTransferTx::MAX_OUTPUTS
+        )
+        .into());
+    }
+    let fee = Coord::from_num(fee);
+
+    let from_wallet = match &from {
+        Some(name) => wallet::load_named_wallet(name)?,
+        None => wallet::load_default_wallet()?,
+    };
+    if from_wallet.watch_only {
+        return Err("Cannot send from a watch-only wallet".into());
+    }
+    let sender = Address::parse(&from_wallet.address)?;
+    let keypair = from_wallet.get_keypair()?;
+
+    let remote = RemoteNode::from_url_unless_offline(node_url, offline)?;
+    let state = SpendState::load(remote.as_ref(), sender)?;
+
+    // The smallest unlocked triangle that covers everything and leaves change
+    let total: Coord = outputs.iter().map(|o| o.amount).sum::<Coord>() + fee;
+    let (input_hash, input) = state.select_input(sender, total)?;
+
+    let mut tx = BatchTransferTx::new(*input_hash, sender, outputs, fee, state.nonce);
+    if let Some(memo) = memo {
+        tx = tx.with_memo(memo)?;
+    }
+    let signature = keypair.sign(&tx.signable_message())?;
+    tx.sign(signature.to_vec(), keypair.public_key_bytes().to_vec());
+
+    println!(
+        "{}",
+        format!(
+            "💸 Paying {} recipients from {}",
+            tx.outputs.len(),
+            from.as_deref().unwrap_or("the default wallet")
+        )
+        .bright_cyan()
+    );
+    println!("   {:<64} {:>14}", "Recipient", "Amount");
+    for output in &tx.outputs {
+        println!("   {:<64} {:>14}", output.recipient, output.amount);
+    }
+    println!(
+        "   Input {} ({}), fee {}, change {}",
+        hex::encode(input_hash),
+        input.effective_value(),
+        fee,
+        input.effective_value() - total
+    );
+
+    let transaction = Transaction::BatchTransfer(tx);
+    transaction.validate_stateless(u64::MAX)?;
+    submit(&transaction, remote.as_ref(), state.chain, offline)
+}
+
+/// What spending from one address needs to know about the chain
+struct SpendState {
+    owned: Vec<(Sha256Hash, Triangle)>,
+    immature: HashSet<Sha256Hash>,
+    pending: Vec<Transaction>,
+    nonce: u64,
+    /// The local chain with mempool.json merged in, unless a remote node is used
+    chain: Option<Blockchain>,
+}
+
+impl SpendState {
+    fn load(
+        remote: Option<&RemoteNode>,
+        sender: Address,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        // Queued drafts spend their inputs as surely as pending transactions
+        let queued = drafts::load_default()?;
+        let queued = queued.iter().map(|d| d.transaction.clone());
+        if let Some(node) = remote {
+            let (owned, immature) = node.triangles_with_maturity(&sender.to_hex())?;
+            let mut pending = node.mempool()?;
+            pending.extend(queued);
+            return Ok(SpendState {
+                owned,
+                immature,
+                pending,
+                nonce: node.height()?,
+                chain: None,
+            });
+        }
+        let (chain, mut pending) = load_local_mempool()?;
+        pending.extend(queued);
+        let owned = chain
+            .state
+            .utxo_set
+            .iter()
+            .filter(|(_, triangle)| triangle.owner == sender)
+            .map(|(hash, triangle)| (*hash, triangle.clone()))
+            .collect();
+        let next_height = chain.blocks.last().map_or(0, |b| b.header.height + 1);
+        Ok(SpendState {
+            owned,
+            immature: chain.state.immature_outputs(next_height),
+            pending,
+            nonce: chain.blocks.len() as u64,
+            chain: Some(chain),
+        })
+    }
+
+    /// The smallest unlocked triangle worth more than `total`
+    fn select_input(
+        &self,
+        sender: Address,
+        total: Coord,
+    ) -> Result<&(Sha256Hash, Triangle), Box<dyn std::error::Error>> {
+        let locker =
+            OutpointLocker::from_pending(&self.pending).with_immature(self.immature.clone());
+        rawtx::select_input(&self.owned, &locker, total).ok_or_else(|| {
+            let breakdown = BalanceBreakdown::compute(
+                self.owned.iter().map(|(h, t)| (h, t)),
+                &self.pending,
+                &self.immature,
+                &[sender],
+            );
+            format!(
+                "No single unlocked triangle worth more than {} (available: {} in {} triangle(s))",
+                total, breakdown.available, breakdown.available_triangles
+            )
+            .into()
+        })
+    }
+}
+
+/// The local chain with the transactions of mempool.json added to its mempool
+fn load_local_mempool() -> Result<(Blockchain, Vec<Transaction>), Box<dyn std::error::Error>> {
+    let (_config, mut chain) = load_blockchain_from_config()?;
+    let pending: Vec<Transaction> = std::fs::read_to_string(DataDir::current().mempool_path())
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default();
+    for tx in &pending {
+        let _ = chain.mempool.add_transaction(tx.clone());
+    }
+    Ok((chain, pending))
+}
+
+/// Hand a signed transaction to the remote node or the local mempool.json,
+/// or queue it as a draft when offline, and say which happened
+fn submit(
+    transaction: &Transaction,
+    remote: Option<&RemoteNode>,
+    chain: Option<Blockchain>,
+    offline: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let hash = transaction.hash_str();
+    if offline {
+        let mut queue = drafts::load_default()?;
+        queue.push(transaction.clone())?;
+        drafts::save_default(&queue)?;
+        println!(
+            "{}",
+            format!(
+                "📴 Transaction {} queued as a draft; nothing was sent",
+                hash
+            )
+            .yellow()
+        );
+        println!("   Submit it with `trinity tx drafts --send` once online");
+    } else if let Some(node) = remote {
+        node.submit_transaction(transaction)?;
+        println!(
+            "{}",
+            format!("✅ Transaction {} submitted to {}", hash, node.url()).green()
+        );
+    } else if let Some(mut chain) = chain {
+        chain.mempool.add_transaction(transaction.clone())?;
+        let all_txs = chain.mempool.get_all_transactions();
+        std::fs::write(
+            DataDir::current().mempool_path(),
+            serde_json::to_string(&all_txs)?,
+        )?;
+        println!(
+            "{}",
+            format!("📝 Transaction {} saved to the local mempool.json", hash).yellow()
+        );
+        println!("   No node was contacted; use --node-url to reach the network");
+    }
+    Ok(())
+}
+
+/// Contents of `path`, or of stdin for `-`
+fn read_input(path: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    if path == Path::new("-") {
+        let mut contents = String::new();
+        std::io::Read::read_to_string(&mut std::io::stdin(), &mut contents)?;
+        Ok(contents)
+    } else {
+        Ok(std::fs::read_to_string(path)?)
+    }
+}
+
+/// Write `contents` to `path`, or print it
+fn write_output(contents: &str, path: Option<PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
+    match path {
+        Some(path) => {
+            std::fs::write(&path, format!("{}\n", contents))?;
+            eprintln!("{}", format!("📝 Wrote {}", path.display()).green());
+        }
+        None => println!("{}", contents),
+    }
+    Ok(())
+}
+
+/// One-line-per-field summary of what a raw transaction does
+fn describe_raw(tx: &Transaction) {
+    match tx {
+        Transaction::Transfer(tx) => {
+            eprintln!("   Transfer from {}", tx.sender);
+            eprintln!("   Input {}", hex::encode(tx.input_hash));
+            eprintln!("   Pay {} to {}", tx.amount, tx.new_owner);
+            eprintln!("   Fee {}, nonce {}", tx.fee_area, tx.nonce);
+        }
+        Transaction::BatchTransfer(tx) => {
+            eprintln!("   Batch transfer from {}", tx.sender);
+            eprintln!("   Input {}", hex::encode(tx.input_hash));
+            for output in &tx.outputs {
+                eprintln!("   Pay {} to {}", output.amount, output.recipient);
+            }
+            eprintln!("   Fee {}, nonce {}", tx.fee_area, tx.nonce);
+        }
+        Transaction::Subdivision(tx) => {
+            eprintln!("   Subdivision by {}", tx.owner_address);
+            eprintln!("   Parent {}", hex::encode(tx.parent_hash));
+            eprintln!("   Fee {}, nonce {}", tx.fee_area, tx.nonce);
+        }
+        Transaction::Coinbase(tx) => {
+            eprintln!(
+                "   Coinbase paying {} to {}",
+                tx.reward_area, tx.beneficiary_address
+            );
+        }
+    }
+    if let Some(memo) = match tx {
+        Transaction::Transfer(tx) => tx.memo.as_ref(),
+        Transaction::BatchTransfer(tx) => tx.memo.as_ref(),
+        _ => None,
+    } {
+        eprintln!("   Memo {:?}", memo);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn tx_create(
+    from: &str,
+    to: &[String],
+    file: Option<PathBuf>,
+    input: Option<String>,
+    fee: f64,
+    memo: Option<String>,
+    format: RawFormat,
+    output: Option<PathBuf>,
+    node_url: Option<String>,
+    offline: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut recipients = to.join("\n");
+    if let Some(file) = &file {
+        let contents = std::fs::read_to_string(file)?;
+        if contents.trim_start().starts_with('[') {
+            if !to.is_empty() {
+                return Err("Combine --to only with a CSV recipients file".into());
+            }
+            recipients = contents;
+        } else {
+            recipients = format!("{}\n{}", recipients, contents);
+        }
+    }
+    let outputs = parse_recipients(&recipients)?;
+    if outputs.is_empty() {
+        return Err("Give at least one recipient with --to or --file".into());
+    }
+    let fee = Coord::from_num(fee);
+
+    // An address, or the name of a wallet (watch-only is enough) holding it
+    let sender = match Address::parse(from) {
+        Ok(address) => address,
+        Err(_) => Address::parse(&wallet::load_named_wallet(from)?.address)?,
+    };
+
+    let remote = RemoteNode::from_url_unless_offline(node_url, offline)?;
+    let state = SpendState::load(remote.as_ref(), sender)?;
+    let total: Coord = outputs.iter().map(|o| o.amount).sum::<Coord>() + fee;
+    let input_hash = match input {
+        Some(input) => {
+            let hash: Sha256Hash = hex::decode(input.trim())
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or_else(|| format!("Input '{}' is not a 32-byte hex hash", input))?;
+            let (_, triangle) = state
+                .owned
+                .iter()
+                .find(|(owned, _)| *owned == hash)
+                .ok_or_else(|| format!("{} owns no triangle {}", sender, input))?;
+            if triangle.effective_value() - total < GEOMETRIC_TOLERANCE {
+                return Err(format!(
+                    "Triangle {} is worth {}, not more than {}",
+                    input,
+                    triangle.effective_value(),
+                    total
+                )
+                .into());
+            }
+            hash
+        }
+        None => state.select_input(sender, total)?.0,
+    };
+
+    let tx = rawtx::create(input_hash, sender, outputs, fee, state.nonce, memo)?;
+    eprintln!(
+        "{}",
+        format!("🧾 Unsigned transaction {}", tx.hash_str()).bright_cyan()
+    );
+    describe_raw(&tx);
+    write_output(&rawtx::encode(&tx, format)?, output)
+}
+
+fn tx_sign(
+    file: &Path,
+    wallet_name: Option<String>,
+    signer: Option<String>,
+    format: Option<RawFormat>,
+    output: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let raw = read_input(file)?;
+    let mut tx = rawtx::decode(&raw)?;
+    let format = format.unwrap_or(if raw.trim_start().starts_with('{') {
+        RawFormat::Json
+    } else {
+        RawFormat::Hex
+    });
+
+    // The key stays wherever it lives: in a wallet file, or behind a signer
+    let key: Box<dyn Signer> = match &signer {
+        Some(command) => Box::new(ExternalSigner::new(command)?),
+        None => {
+            let signing_wallet = match &wallet_name {
+                Some(name) => wallet::load_named_wallet(name)?,
+                None => wallet::load_default_wallet()?,
+            };
+            if signing_wallet.watch_only {
+                return Err("Cannot sign with a watch-only wallet".into());
+            }
+            Box::new(signing_wallet.get_keypair()?)
+        }
+    };
+    eprintln!(
+        "{}",
+        format!("✍️  Signing transaction {}", tx.hash_str()).bright_cyan()
+    );
+    describe_raw(&tx);
+    if rawtx::is_signed(&tx) {
+        eprintln!("{}", "⚠️  Replacing the existing signature".yellow());
+    }
+    if let Some(command) = &signer {
+        eprintln!(
+            "{}",
+            format!("🔐 Waiting for {} ({})", command, key.address()?).bright_blue()
+        );
+    }
+    rawtx::sign(&mut tx, key.as_ref())?;
+    tx.validate_stateless(u64::MAX)?;
+    write_output(&rawtx::encode(&tx, format)?, output)
+}
+
+fn tx_broadcast(
+    file: &Path,
+    node_url: Option<String>,
+    offline: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let tx = rawtx::decode(&read_input(file)?)?;
+    if !rawtx::is_signed(&tx) {
+        return Err("Transaction is not signed; run `trinity tx sign` first".into());
+    }
+    tx.validate_stateless(u64::MAX)?;
+
+    let remote = RemoteNode::from_url_unless_offline(node_url, offline)?;
+    let chain = match remote {
+        Some(_) => None,
+        None if offline => None,
+        None => Some(load_local_mempool()?.0),
+    };
+    submit(&tx, remote.as_ref(), chain, offline)
+}
+
+fn tx_drafts(
+    send: bool,
+    drop: Option<String>,
+    node_url: Option<String>,
+    offline: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut queue = drafts::load_default()?;
+    if let Some(prefix) = drop {
+        let draft = queue
+            .remove(&prefix)
+            .ok_or_else(|| format!("No single draft matches '{}'", prefix))?;
+        drafts::save_default(&queue)?;
+        println!(
+            "{}",
+            format!("🗑️  Dropped draft {}", draft.transaction.hash_str()).green()
+        );
+        return Ok(());
+    }
+
+    if !send {
+        println!("{}", format!("📴 {} draft(s)", queue.len()).bright_cyan());
+        for draft in queue.iter() {
+            eprintln!(
+                "{}",
+                format!(
+                    "🧾 {}  queued {}",
+                    draft.transaction.hash_str(),
+                    draft.created_at
+                )
+                .bright_white()
+            );
+            describe_raw(&draft.transaction);
+        }
+        return Ok(());
+    }
+
+    if offline {
+        return Err("Sending drafts needs the network, which offline mode forbids".into());
+    }
+    let remote = RemoteNode::from_url_or_env(node_url)?;
+    let mut chain = match remote {
+        Some(_) => None,
+        None => Some(load_local_mempool()?.0),
+    };
+    let mut sent = Vec::new();
+    for draft in queue.iter() {
+        let tx = &draft.transaction;
+        let result = match (&remote, chain.as_mut()) {
+            (Some(node), _) => node.submit_transaction(tx),
+            (None, Some(chain)) => chain.mempool.add_transaction(tx.clone()),
+            (None, None) => unreachable!(),
+        };
+        match result {
+            Ok(()) => {
+                println!("{}", format!("✅ {} submitted", tx.hash_str()).green());
+                sent.push(tx.hash_str());
+            }
+            Err(e) => println!("{}", format!("❌ {} kept: {}", tx.hash_str(), e).red()),
+        }
+    }
+    if let Some(chain) = &chain {
+        let all_txs = chain.mempool.get_all_transactions();
+        std::fs::write(
+            DataDir::current().mempool_path(),
+            serde_json::to_string(&all_txs)?,
+        )?;
+        println!("   Saved to the local mempool.json; no node was contacted");
+    }
+    for hash in &sent {
+        queue.remove(hash);
+    }
+    drafts::save_default(&queue)?;
+    println!(
+        "{}",
+        format!("📬 {} sent, {} still queued", sent.len(), queue.len()).bright_cyan()
+    );
+    Ok(())
+}
+
+fn config_show(
+    key: Option<String>,
+    defaults: Option<Network>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let config = match defaults {
+        Some(network) => Config::defaults_for(network),
+        None => {
+            let config = config::effective_config()?;
+            for (path, _) in config::env_overrides() {
+                println!("# {} from {}", path, config::env_var_name(&path));
+            }
+            config
+        }
+    };
+
+    match &key {
+        // Whole config in schema order rather than sorted
+        None => print!("{}", toml::to_string_pretty(&config)?),
+        Some(key) => {
+            let value = config.to_toml_value()?;
+            match Config::lookup(&value, key) {
+                Some(toml::Value::Table(table)) => print!("{}", toml::to_string_pretty(table)?),
+                Some(value) => println!("{}", value),
+                None => return Err(format!("No setting named '{}'", key).into()),
+            }
+        }
+    }
+
+    if let Err(errors) = config.validate() {
+        eprintln!();
+        for error in &errors.0 {
+            eprintln!("{}", format!("⚠️  {}", error).yellow());
+        }
+    }
+    Ok(())
+}
+
+fn config_set(key: &str, value: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let stored = match config::set_config_value(key, value) {
+        Ok(stored) => stored,
+        Err(e) => {
+            eprintln!("{}", format!("❌ {}", e).red());
+            return Err(format!("{} left unchanged", config::CONFIG_PATH).into());
+        }
+    };
+    println!(
+        "{}",
+        format!("✅ Set {} = {} in {}", key, stored, config::CONFIG_PATH).green()
+    );
+    if config::env_overrides().iter().any(|(path, _)| path == key) {
+        println!(
+            "   {}",
+            format!(
+                "⚠️  {} is set and takes precedence",
+                config::env_var_name(key)
+            )
+            .yellow()
+        );
+    }
+    Ok(())
+}
+
+fn simulate_difficulty(
+    profile_path: &Path,
+    algorithms: &[RetargetAlgorithm],
+    seed: u64,
+    max_blocks: usize,
+    csv: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let profile = HashrateProfile::load(profile_path)?;
+    println!(
+        "{}",
+        format!(
+            "⛏️  Simulating {} over {:.1}h of hash rate ({} segments, seed {})",
+            profile_path.display(),
+            profile.duration() / 3600.0,
+            profile.segments.len(),
+            seed
+        )
+        .bright_cyan()
+    );
+    println!(
+        "   Target block time {}s, retarget interval {} blocks",
+        TARGET_BLOCK_TIME, DIFFICULTY_ADJUSTMENT_INTERVAL
+    );
+
+    for (i, algorithm) in algorithms.iter().enumerate() {
+        let run = Simulation::run(&profile, *algorithm, seed, max_blocks);
+        print_simulation(&profile, &run);
+
+        if let (0, Some(path)) = (i, &csv) {
+            let mut out = String::from("height,timestamp,block_time,difficulty,segment\n");
+            for block in &run.blocks {
+                out.push_str(&format!(
+                    "{},{:.3},{:.3},{},{}\n",
+                    block.height,
+                    block.timestamp,
+                    block.block_time,
+                    block.difficulty,
+                    block.segment
+                ));
+            }
+            std::fs::write(path, out)?;
+            println!(
+                "   {}",
+                format!("✅ Wrote {} blocks to {}", run.blocks.len(), path.display()).green()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn print_simulation(profile: &HashrateProfile, run: &Simulation) {
+    println!();
+    println!("{}", format!("📊 {}", run.algorithm.name()).bright_yellow());
+    let Some(stats) = run.stats() else {
+        println!("   {}", "No blocks were found".red());
+        return;
+    };
+    let (min_difficulty, max_difficulty) = run.difficulty_range().unwrap_or_default();
+    println!(
+        "   Blocks: {}   Difficulty: {}..{}",
+        stats.count, min_difficulty, max_difficulty
+    );
+    println!(
+        "   Block time: mean {:.1}s  sd {:.1}s  min {:.1}s  median {:.1}s  p90 {:.1}s  p99 {:.1}s  max {:.1}s",
+        stats.mean, stats.std_dev, stats.min, stats.median, stats.p90, stats.p99, stats.max
+    );
+    if run.truncated {
+        println!(
+            "   {}",
+            format!(
+                "⚠️  Stopped at the {} block limit before the profile ended",
+                stats.count
+            )
+            .yellow()
+        );
+    } else if run.trailing_secs > TARGET_BLOCK_TIME as f64 * 10.0 {
+        println!(
+            "   {}",
+            format!(
+                "⚠️  No block in the last {:.0}s of the profile",
+                run.trailing_secs
+            )
+            .yellow()
+        );
+    }
+
+    println!("   {:<14} {:>8}", "Block time", "Blocks");
+    let histogram = run.histogram();
+    let mut lower = 0.0;
+    for (upper, count) in HISTOGRAM_BUCKETS.iter().zip(histogram) {
+        let label = if upper.is_finite() {
+            format!("{}-{}x", lower, upper)
+        } else {
+            format!(">{}x", lower)
+        };
+        let bar = "█".repeat((count * 40).div_ceil(stats.count));
+        println!("   {:<14} {:>8} {}", label, count, bar);
+        lower = *upper;
+    }
+
+    println!(
+        "   {:<4} {:>14} {:>8} {:>10} {:>10} {:>10}",
+        "Seg", "Hashrate", "Blocks", "Mean", "Median", "p90"
+    );
+    for (i, segment) in profile.segments.iter().enumerate() {
+        match run.segment_stats(i) {
+            Some(s) => println!(
+                "   {:<4} {:>14.0} {:>8} {:>9.1}s {:>9.1}s {:>9.1}s",
+                i, segment.hashrate, s.count, s.mean, s.median, s.p90
+            ),
+            None => println!("   {:<4} {:>14.0} {:>8}", i, segment.hashrate, 0),
+        }
+    }
+}
+
+fn wallet_derive(
+    template: &str,
+    count: u32,
+    start: u32,
+    verify: Option<String>,
+    mnemonic: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Fail on a bad template before asking for secrets
+    hdwallet::expand_path(template, start)?;
+    let target = verify.as_deref().map(Address::parse).transpose()?;
+
+    let hd = if mnemonic {
+        let phrase = prompt_password("Enter your recovery phrase: ")?;
+        let passphrase = prompt_password("Enter the BIP-39 passphrase (empty for none): ")?;
+        HDWallet::from_mnemonic(&phrase, Some(&passphrase))?
+    } else {
+        let keystore = HDKeystore::load(&hdwallet::get_keystore_path()?)?;
+        let password = prompt_password("Enter your HD keystore password: ")?;
+        keystore.unlock(&password)?
+    };
+
+    if let Some(target) = target {
+        return match hd.find_index(template, &target, start, count)? {
+            Some(index) => {
+                println!(
+                    "{}",
+                    format!(
+                        "✅ {} is index {} ({})",
+                        target,
+                        index,
+                        hdwallet::expand_path(template, index)?
+                    )
+                    .green()
+                );
+                Ok(())
+            }
+            None => Err(format!(
+                "{} was not derived from this seed at {} (indices {}..{})",
+                target,
+                template,
+                start,
+                start.saturating_add(count)
+            )
+            .into()),
+        };
+    }
+
+    println!("{}", format!("🔑 Addresses for {}", template).bright_cyan());
+    println!("   {:<24} Address", "Path");
+    for index in start..start.saturating_add(count) {
+        let path = hdwallet::expand_path(template, index)?;
+        let address = hd.derive_keypair_at(&path)?.address();
+        println!("   {:<24} {}", path, address);
+    }
+    Ok(())
+}
+
+/// Ask for a new password twice
+fn prompt_new_password(prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let password = prompt_password(format!("{}: ", prompt))?;
+    if password.len() < 8 {
+        return Err("Password must be at least 8 characters".into());
+    }
+    if prompt_password(format!("Confirm {}: ", prompt.to_lowercase()))? != password {
+        return Err("Passwords do not match".into());
+    }
+    Ok(password)
+}
+
+fn wallet_backup(
+    name: Option<String>,
+    encrypted: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let wallet = match &name {
+        Some(name) => wallet::load_named_wallet(name)?,
+        None => wallet::load_default_wallet()?,
+    };
+    let keypair = wallet.get_keypair()?;
+
+    if let Some(path) = encrypted {
+        let password = prompt_new_password("Backup password")?;
+        EncryptedWallet::from_wallet(&wallet, &password)?.save(&path)?;
+        println!("{}", "✅ Encrypted backup written".green());
+        println!("📁 {}", path.display());
+        println!("📍 Address: {}", wallet.address);
+        return Ok(());
+    }
+
+    let phrase = hdwallet::key_to_mnemonic(&keypair.secret_key.secret_bytes())?;
+    println!("{}", "🔑 Recovery phrase".bright_cyan());
+    for (i, word) in phrase.split_whitespace().enumerate() {
+        print!("{:>4}. {:<10}", i + 1, word);
+        if (i + 1).is_multiple_of(6) {
+            println!();
+        }
+    }
+    println!("📍 Address: {}", wallet.address);
+    println!();
+    println!("{}", "⚠️  These words are the private key itself:".yellow());
+    println!(
+        "{}",
+        "   • Anyone who sees them can spend this wallet".yellow()
+    );
+    println!(
+        "{}",
+        "   • Write them down offline; never store them in a file".yellow()
+    );
+    println!("{}", "   • Restore with: trinity wallet restore".yellow());
+    Ok(())
+}
+
+fn wallet_restore(
+    name: Option<String>,
+    hd: bool,
+    force: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = match &name {
+        Some(name) => wallet::get_named_wallet_path(name)?,
+        None => wallet::get_default_wallet_path()?,
+    };
+    if path.exists() && !force {
+        return Err(format!(
+            "A wallet already exists at {} (use --force to replace it)",
+            path.display()
+        )
+        .into());
+    }
+
+    let phrase = prompt_password("Enter your recovery phrase: ")?;
+    let keypair = if hd {
+        let passphrase = prompt_password("Enter the BIP-39 passphrase (empty for none): ")?;
+        HDWallet::from_mnemonic(&phrase, Some(&passphrase))?
+            .derive_keypair_at(&hdwallet::derivation_path(0, 0))?
+    } else {
+        hdwallet::key_from_mnemonic(&phrase)?
+    };
+    let wallet = Wallet::from_keypair(name, &keypair);
+    println!("📍 Address: {}", wallet.address);
+
+    let password = prompt_new_password("New wallet password")?;
+    wallet::ensure_wallet_dir()?;
+    EncryptedWallet::from_wallet(&wallet, &password)?.save(&path)?;
+    println!("{}", "✅ Wallet restored".green());
+    println!("📁 {}", path.display());
+    Ok(())
+}
+
+/// Screen the address book manager is showing
+enum BookMode {
+    Browse,
+    Search,
+    Form(ContactForm),
+    ConfirmDelete,
+}
+
+/// The add/edit form; `editing` is the label of the entry being edited,
+/// which stays fixed
+struct ContactForm {
+    editing: Option<String>,
+    fields: [String; 3],
+    focus: usize,
+}
+
+const FORM_FIELDS: [&str; 3] = ["Label", "Address", "Notes"];
+
+struct BookView {
+    book: addressbook::AddressBook,
+    query: String,
+    entries: Vec<AddressEntry>,
+    table: TableState,
+    mode: BookMode,
+    status: String,
+}
+
+impl BookView {
+    fn refresh(&mut self) {
+        self.entries = self.book.fuzzy_search(&self.query);
+        let selected = self.table.selected().unwrap_or(0);
+        self.table
+            .select((!self.entries.is_empty()).then(|| selected.min(self.entries.len() - 1)));
+    }
+
+    fn current(&self) -> Option<&AddressEntry> {
+        self.table.selected().and_then(|i| self.entries.get(i))
+    }
+
+    fn move_selection(&mut self, down: bool) {
+        if let Some(i) = self.table.selected() {
+            let last = self.entries.len().saturating_sub(1);
+            self.table.select(Some(if down {
+                (i + 1).min(last)
+            } else {
+                i.saturating_sub(1)
+            }));
+        }
+    }
+
+    fn save_form(&mut self, form: &ContactForm) -> Result<String, Box<dyn std::error::Error>> {
+        let [label, address, notes] = &form.fields;
+        let notes = (!notes.trim().is_empty()).then(|| notes.trim().to_string());
+        let label = match &form.editing {
+            Some(label) => {
+                self.book
+                    .update(label, Some(address.trim().to_string()), notes)?;
+                label.clone()
+            }
+            None => {
+                self.book.add(label.clone(), address.clone(), notes)?;
+                label.trim().to_string()
+            }
+        };
+        addressbook::save_default(&self.book)?;
+        self.refresh();
+        if let Some(i) = self.entries.iter().position(|e| e.label == label) {
+            self.table.select(Some(i));
+        }
+        Ok(format!("Saved '{}'", label))
+    }
+}
+
+/// Put `text` on the system clipboard through the terminal (OSC 52), which
+/// also works over SSH in terminals that allow it
+fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    stdout.flush()
+}
+
+fn address_book_tui() -> Result<(), Box<dyn std::error::Error>> {
+    let book = addressbook::load_default()?;
+    let mut view = BookView {
+        book,
+        query: String::new(),
+        entries: Vec::new(),
+        table: TableState::default(),
+        mode: BookMode::Browse,
+        status: format!("Loaded {}", addressbook::get_addressbook_path().display()),
+    };
+    view.refresh();
+
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let result = run_address_book(&mut terminal, &mut view);
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    result
+}
+
+fn run_address_book(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    view: &mut BookView,
+) -> Result<(), Box<dyn std::error::Error>> {
+    loop {
+        terminal.draw(|f| draw_address_book(f, view))?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return Ok(());
+        }
+
+        match std::mem::replace(&mut view.mode, BookMode::Browse) {
+            BookMode::Browse => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Down | KeyCode::Char('j') => view.move_selection(true),
+                KeyCode::Up | KeyCode::Char('k') => view.move_selection(false),
+                KeyCode::Char('/') => view.mode = BookMode::Search,
+                KeyCode::Char('a') => {
+                    view.mode = BookMode::Form(ContactForm {
+                        editing: None,
+                        fields: Default::default(),
+                        focus: 0,
+                    })
+                }
+                KeyCode::Char('e') | KeyCode::Enter => {
+                    if let Some(entry) = view.current() {
+                        view.mode = BookMode::Form(ContactForm {
+                            editing: Some(entry.label.clone()),
+                            fields: [
+                                entry.label.clone(),
+                                entry.address.clone(),
+                                entry.notes.clone().unwrap_or_default(),
+                            ],
+                            focus: 1,
+                        });
+                    }
+                }
+                KeyCode::Char('d') | KeyCode::Delete if view.current().is_some() => {
+                    view.mode = BookMode::ConfirmDelete
+                }
+                KeyCode::Char('c') | KeyCode::Char('y') => {
+                    if let Some(entry) = view.current() {
+                        view.status = match copy_to_clipboard(&entry.address) {
+                            Ok(()) => format!("Copied the address of '{}'", entry.label),
+                            Err(e) => format!("Copy failed: {}", e),
+                        };
+                    }
+                }
+                _ => {}
+            },
+            BookMode::Search => match key.code {
+                KeyCode::Enter => {}
+                KeyCode::Esc => {
+                    view.query.clear();
+                    view.refresh();
+                }
+                KeyCode::Backspace => {
+                    view.query.pop();
+                    view.refresh();
+                    view.mode = BookMode::Search;
+                }
+                KeyCode::Char(c) => {
+                    view.query.push(c);
+                    view.table.select(Some(0));
+                    view.refresh();
+                    view.mode = BookMode::Search;
+                }
+                _ => view.mode = BookMode::Search,
+            },
+            BookMode::Form(mut form) => {
+                // The label of an existing entry can't change
+                let first = if form.editing.is_some() { 1 } else { 0 };
+                match key.code {
+                    KeyCode::Esc => view.status = "Cancelled".to_string(),
+                    KeyCode::Enter => match view.save_form(&form) {
+                        Ok(status) => view.status = status,
+                        Err(e) => {
+                            view.status = format!("❌ {}", e);
+                            view.mode = BookMode::Form(form);
+                        }
+                    },
+                    other => {
+                        match other {
+                            KeyCode::Tab | KeyCode::Down => {
+                                form.focus = (form.focus + 1).min(FORM_FIELDS.len() - 1)
+                            }
+                            KeyCode::BackTab | KeyCode::Up => {
+                                form.focus = form.focus.saturating_sub(1).max(first)
+                            }
+                            KeyCode::Backspace => {
+                                form.fields[form.focus].pop();
+                            }
+                            KeyCode::Char(c) => form.fields[form.focus].push(c),
+                            _ => {}
+                        }
+                        view.mode = BookMode::Form(form);
+                    }
+                }
+            }
+            BookMode::ConfirmDelete => {
+                if let (KeyCode::Char('y'), Some(entry)) = (key.code, view.current()) {
+                    let label = entry.label.clone();
+                    view.status = match view
+                        .book
+                        .remove(&label)
+                        .and_then(|_| addressbook::save_default(&view.book))
+                    {
+                        Ok(()) => format!("Deleted '{}'", label),
+                        Err(e) => format!("❌ {}", e),
+                    };
+                    view.refresh();
+                } else {
+                    view.status = "Kept the entry".to_string();
+                }
+            }
+        }
+    }
+}
+
+fn draw_address_book(f: &mut ratatui::Frame, view: &mut BookView) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([
+            Constraint::Length(3), // Search
+            Constraint::Min(6),    // Entries and details
+            Constraint::Length(3), // Keys and status
+        ])
+        .split(f.size());
+
+    let searching = matches!(view.mode, BookMode::Search);
+    let search = Paragraph::new(Line::from(vec![
+        Span::styled("🔍 ", Style::default().fg(Color::Gray)),
+        Span::raw(view.query.as_str()),
+        Span::styled(
+            if searching { "█" } else { "" },
+            Style::default().fg(Color::Cyan),
+        ),
+    ]))
+    .block(
+        TuiBlock::default()
+            .borders(Borders::ALL)
+            .title("📇 TrinityChain Address Book")
+            .border_style(Style::default().fg(if searching {
+                Color::Yellow
+            } else {
+                Color::Cyan
+            })),
+    );
+    f.render_widget(search, chunks[0]);
+
+    let body = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(45), Constraint::Percentage(55)])
+        .split(chunks[1]);
+
+    let rows = view.entries.iter().map(|entry| {
+        let mark = if entry.is_verified() { "✅" } else { "" };
+        Row::new(vec![
+            Span::raw(entry.label.clone()),
+            Span::raw(mark),
+            Span::styled(
+                short_address(&entry.address),
+                Style::default().fg(Color::Gray),
+            ),
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Percentage(45),
+            Constraint::Length(3),
+            Constraint::Percentage(45),
+        ],
+    )
+    .header(
+        Row::new(vec!["Label", "", "Address"]).style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ),
+    )
+    .highlight_style(Style::default().bg(Color::DarkGray))
+    .highlight_symbol("▶ ")
+    .block(
+        TuiBlock::default()
+            .borders(Borders::ALL)
+            .title(format!(
+                "Contacts ({}/{})",
+                view.entries.len(),
+                view.book.len()
+            ))
+            .border_style(Style::default().fg(Color::Green)),
+    );
+    f.render_stateful_widget(table, body[0], &mut view.table);
+
+    let details = match view.current() {
+        Some(entry) => {
+            let field = |name: &str, value: String| {
+                Line::from(vec![
+                    Span::styled(format!("{:<10}", name), Style::default().fg(Color::Gray)),
+                    Span::raw(value),
+                ])
+            };
+            vec![
+                field("Label", entry.label.clone()),
+                field("Address", entry.address.clone()),
+                field(
+                    "Notes",
+                    entry.notes.clone().unwrap_or_else(|| "-".to_string()),
+                ),
+                field(
+                    "Verified",
+                    if entry.is_verified() { "yes" } else { "no" }.to_string(),
+                ),
+                field("Created", entry.created_at.clone()),
+                field("Updated", entry.updated_at.clone()),
+            ]
+        }
+        None => vec![Line::from("No contacts. Press [a] to add one.")],
+    };
+    let details = Paragraph::new(details).wrap(Wrap { trim: false }).block(
+        TuiBlock::default()
+            .borders(Borders::ALL)
+            .title("Details")
+            .border_style(Style::default().fg(Color::Magenta)),
+    );
+    f.render_widget(details, body[1]);
+
+    let keys = match view.mode {
+        BookMode::Browse => {
+            "[↑↓] select  [/] search  [a] add  [e] edit  [d] delete  [c] copy address  [q] quit"
+        }
+        BookMode::Search => "Type to filter  [Enter] keep filter  [Esc] clear",
+        BookMode::Form(_) => "[Tab/↑↓] field  [Enter] save  [Esc] cancel",
+        BookMode::ConfirmDelete => "Delete this contact? [y] yes  [any other key] no",
+    };
+    let footer = Paragraph::new(vec![
+        Line::from(Span::styled(keys, Style::default().fg(Color::Cyan))),
+        Line::from(view.status.as_str()),
+    ]);
+    f.render_widget(footer, chunks[2]);
+
+    if let BookMode::Form(form) = &view.mode {
+        let area = centered(f.size(), 70, 9);
+        let lines: Vec<Line> = FORM_FIELDS
+            .iter()
+            .zip(&form.fields)
+            .enumerate()
+            .map(|(i, (name, value))| {
+                let focused = i == form.focus;
+                let locked = i == 0 && form.editing.is_some();
+                let style = if focused {
+                    Style::default().fg(Color::Yellow)
+                } else if locked {
+                    Style::default().fg(Color::DarkGray)
+                } else {
+                    Style::default()
+                };
+                Line::from(vec![
+                    Span::styled(format!("{:<9}", name), Style::default().fg(Color::Gray)),
+                    Span::styled(
+                        format!("{}{}", value, if focused { "█" } else { "" }),
+                        style,
+                    ),
+                ])
+            })
+            .collect();
+        let title = if form.editing.is_some() {
+            "✏️  Edit contact"
+        } else {
+            "➕ Add contact"
+        };
+        f.render_widget(Clear, area);
+        f.render_widget(
+            Paragraph::new(lines).block(
+                TuiBlock::default()
+                    .borders(Borders::ALL)
+                    .title(title)
+                    .border_style(Style::default().fg(Color::Yellow)),
+            ),
+            area,
+        );
+    }
+}
+
+/// A `width`% wide, `height` rows tall rectangle in the middle of `area`
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let height = height.min(area.height);
+    let width = area.width * width / 100;
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+fn short_address(address: &str) -> String {
+    if address.len() > 20 {
+        format!("{}…{}", &address[..8], &address[address.len() - 8..])
+    } else {
+        address.to_string()
+    }
+}
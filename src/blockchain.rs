@@ -1,14 +1,24 @@
 //! Core blockchain implementation for TrinityChain, including block structure,
 //! chain validation, UTXO management, and mining difficulty adjustment.
 
+use crate::config::{active_network, Network};
 use crate::error::ChainError;
+use crate::events::{ChainEvent, EventBus};
 use crate::geometry::{Coord, Point, Triangle, GEOMETRIC_TOLERANCE};
+use crate::lineage::{LineageGraph, LineageStep};
 use crate::mempool::Mempool;
+use crate::metrics::{self, ValidationMetrics};
 use crate::miner::mine_block;
+use crate::sighash::SighashVersion;
 use crate::crypto::Address;
 use crate::transaction::{CoinbaseTx, Transaction, TransferTx};
+use crate::versionbits::{self, DeploymentState, DeploymentStatus, DEPLOYMENTS};
+use rayon::prelude::*;
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::broadcast;
 
 // ============================================================================
 // Constants
@@ -18,6 +28,58 @@ use std::collections::HashMap;
 pub const DIFFICULTY_ADJUSTMENT_INTERVAL: u64 = 10;
 /// The desired time between blocks in seconds.
 pub const TARGET_BLOCK_TIME: u64 = 30;
+/// Blocks a coinbase triangle must wait before it can be spent: one mined
+/// at height `h` is spendable from height `h + COINBASE_MATURITY`.
+pub const COINBASE_MATURITY: u64 = 20;
+/// Confirmations after which wallets treat a triangle as settled. Value
+/// received in fewer blocks than this can still vanish in a reorganization.
+pub const SAFE_CONFIRMATIONS: u64 = 6;
+
+/// Deepest a triangle may be subdivided. Each level halves the sides, so
+/// this keeps vertices well within fixed-point precision.
+pub const MAX_SUBDIVISION_DEPTH: u8 = 24;
+
+/// Area minted by the genesis coinbase
+pub const GENESIS_SUPPLY: f64 = 1_000_000.0;
+/// Difficulty of the canonical genesis blocks, and of the first retarget
+/// interval
+pub const GENESIS_DIFFICULTY: u32 = 1;
+/// Subsidy of the first blocks, before any halving
+pub const INITIAL_BLOCK_REWARD: f64 = 50.0;
+/// Blocks between subsidy halvings
+pub const HALVING_INTERVAL: u64 = 210_000;
+
+/// Blocks whose timestamps make up the median time past
+pub const MEDIAN_TIME_SPAN: usize = 11;
+/// How far (in milliseconds) a block timestamp may run ahead of the local
+/// clock before the block is refused
+pub const MAX_FUTURE_BLOCK_TIME_MS: u64 = 2 * 60 * 60 * 1000;
+
+/// Blocks with at least this many transactions hash them on the rayon pool
+/// when computing the merkle root
+pub const PARALLEL_MERKLE_THRESHOLD: usize = 256;
+
+/// Smallest effective value a subdivision child may carry, so repeated
+/// subdivision can't flood the UTXO set with dust.
+pub const MIN_SUBDIVISION_VALUE: Coord = Coord::from_bits(4_294_967); // Approx 0.001
+
+/// Difficulty for the next interval, given the current difficulty and how
+/// long (in milliseconds) the last `DIFFICULTY_ADJUSTMENT_INTERVAL` blocks took.
+///
+/// This is the consensus retarget rule; `difficulty_sim` replays it against
+/// synthetic hash rates.
+pub fn retarget(difficulty: u32, actual_time_ms: u64) -> u32 {
+    let expected_time = (DIFFICULTY_ADJUSTMENT_INTERVAL * TARGET_BLOCK_TIME) * 1000; // in milliseconds
+
+    let ratio = actual_time_ms as f64 / expected_time as f64;
+
+    // Clamp the ratio to prevent drastic changes
+    let ratio = ratio.clamp(0.25, 4.0);
+
+    let new_difficulty = (difficulty as f64 * ratio) as u32;
+    // Ensure difficulty is at least 1
+    new_difficulty.max(1)
+}
 
 // ============================================================================
 // Types
@@ -29,22 +91,38 @@ pub type Sha256Hash = [u8; 32];
 /// A single block header
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct BlockHeader {
+    /// Version bits signalling soft-fork deployments (see [`crate::versionbits`]).
+    /// 0 only in the genesis header, which predates signalling.
+    pub version: u32,
     pub height: u64,
     pub timestamp: u64,
     pub previous_hash: Sha256Hash,
     pub merkle_root: Sha256Hash,
+    /// Commitment to the UTXO set after this block (see
+    /// [`TriangleState::state_root`]). Zero only in the genesis header,
+    /// which predates the commitment.
+    pub state_root: Sha256Hash,
     pub difficulty: u32,
     pub nonce: u64,
 }
 
 impl BlockHeader {
-    /// Calculate the hash of the block header
+    /// Calculate the hash of the block header. A version 0 header hashes
+    /// without the version, and a zero state root is left out, so the
+    /// genesis block keeps the hash it had before either field existed.
+    /// `apply_block` requires both in every other block.
     pub fn hash(&self) -> Sha256Hash {
         let mut hasher = Sha256::new();
+        if self.version != 0 {
+            hasher.update(self.version.to_le_bytes());
+        }
         hasher.update(self.height.to_le_bytes());
         hasher.update(self.timestamp.to_le_bytes());
         hasher.update(self.previous_hash);
         hasher.update(self.merkle_root);
+        if self.state_root != [0; 32] {
+            hasher.update(self.state_root);
+        }
         hasher.update(self.difficulty.to_le_bytes());
         hasher.update(self.nonce.to_le_bytes());
         hasher.finalize().into()
@@ -71,10 +149,12 @@ impl Block {
 
         Block {
             header: BlockHeader {
+                version: 0,
                 height,
                 timestamp,
                 previous_hash,
                 merkle_root,
+                state_root: [0; 32],
                 difficulty,
                 nonce: 0,
             },
@@ -87,15 +167,61 @@ impl Block {
         self.header.hash()
     }
 
+    /// Whether only the header was kept (pruned or imported from a
+    /// snapshot). Every valid block has a coinbase, so no body is empty.
+    pub fn is_header_only(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
     /// Standard Merkle Root calculation (hashing all transaction hashes together)
     pub fn calculate_merkle_root(transactions: &[Transaction]) -> Sha256Hash {
+        Block::merkle_root_from_leaves(&Block::transaction_hashes(transactions))
+    }
+
+    /// Hash of every transaction, in order; large blocks are hashed in parallel
+    pub fn transaction_hashes(transactions: &[Transaction]) -> Vec<Sha256Hash> {
+        if transactions.len() >= PARALLEL_MERKLE_THRESHOLD {
+            transactions.par_iter().map(Transaction::hash).collect()
+        } else {
+            transactions.iter().map(Transaction::hash).collect()
+        }
+    }
+
+    /// Merkle root over precomputed transaction hashes
+    pub fn merkle_root_from_leaves(leaves: &[Sha256Hash]) -> Sha256Hash {
         let mut hasher = Sha256::new();
-        for tx in transactions {
-            hasher.update(tx.hash());
+        for leaf in leaves {
+            hasher.update(leaf);
         }
         hasher.finalize().into()
     }
 
+    /// Refresh the merkle root of a template after the transactions at
+    /// `changed` were replaced, e.g. a coinbase with a new extranonce. Only
+    /// those, and any positions past the end of `cache`, are rehashed; the
+    /// other hashes come from `cache`, which is updated to match. Indices
+    /// that moved (an insertion or removal in the middle) count as changed.
+    /// An empty cache hashes everything.
+    pub fn update_merkle_incremental(
+        &mut self,
+        cache: &mut MerkleCache,
+        changed: &[usize],
+    ) -> Sha256Hash {
+        let cached = cache.leaves.len().min(self.transactions.len());
+        cache.leaves.truncate(cached);
+        for &index in changed {
+            if index < cached {
+                cache.leaves[index] = self.transactions[index].hash();
+            }
+        }
+        cache
+            .leaves
+            .extend(Block::transaction_hashes(&self.transactions[cached..]));
+
+        self.header.merkle_root = Block::merkle_root_from_leaves(&cache.leaves);
+        self.header.merkle_root
+    }
+
     pub fn hash_as_u256(hash: &Sha256Hash) -> [u8; 32] {
         *hash
     }
@@ -120,6 +246,101 @@ impl Block {
     }
 }
 
+/// Transaction hashes of a block template, kept between template refreshes
+/// by [`Block::update_merkle_incremental`]
+#[derive(Debug, Clone, Default)]
+pub struct MerkleCache {
+    leaves: Vec<Sha256Hash>,
+}
+
+impl MerkleCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of cached transaction hashes
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Forget every hash, e.g. when the template is rebuilt on a new tip
+    pub fn clear(&mut self) {
+        self.leaves.clear();
+    }
+}
+
+/// An address and its holdings, as listed in [`SupplyStats::top_holders`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Holder {
+    /// Hex-encoded address
+    pub address: String,
+    pub balance: Coord,
+    pub triangles: usize,
+}
+
+/// Circulating supply and richlist, computed from the UTXO set
+#[derive(Debug, Clone)]
+pub struct SupplyStats {
+    /// Total effective value of all unspent triangles
+    pub circulating_area: Coord,
+    pub total_triangles: usize,
+    pub distinct_owners: usize,
+    /// Largest holders, richest first
+    pub top_holders: Vec<Holder>,
+}
+
+/// Issuance at the chain tip, from [`Blockchain::supply_info`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SupplyInfo {
+    /// Tip height
+    pub height: u64,
+    /// Total effective value of all unspent triangles
+    pub circulating_supply: Coord,
+    /// Subsidy of the next block
+    pub current_reward: f64,
+    /// Halvings applied to the next block's subsidy
+    pub halving_era: u64,
+    /// First height paying the next halved subsidy
+    pub next_halving_height: u64,
+    /// Blocks to mine until that height is reached
+    pub blocks_to_halving: u64,
+    /// Genesis supply plus every subsidy the schedule will pay
+    pub max_supply: f64,
+}
+
+/// Where a transaction stands, as reported by `trinity tx-status` and
+/// `/api/transaction/:hash/status`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum TxStatus {
+    /// Waiting in the mempool
+    Pending,
+    /// Included in the main chain
+    Confirmed {
+        block_height: u64,
+        /// Hex-encoded hash of the containing block
+        block_hash: String,
+        /// 1 when the containing block is the tip
+        confirmations: u64,
+    },
+    /// Neither pending nor on the main chain (never seen, dropped or reorganized away)
+    Unknown,
+}
+
+impl TxStatus {
+    pub fn confirmed(block_height: u64, block_hash: &Sha256Hash, tip_height: u64) -> Self {
+        TxStatus::Confirmed {
+            block_height,
+            block_hash: hex::encode(block_hash),
+            confirmations: tip_height.saturating_sub(block_height) + 1,
+        }
+    }
+}
+
 // ============================================================================
 // State Management (UTXO Cache)
 // ============================================================================
@@ -131,6 +352,18 @@ pub struct TriangleState {
     pub utxo_set: HashMap<Sha256Hash, Triangle>,
     /// Derived balances: Maps Address -> Total Area (Coord)
     pub address_balances: HashMap<Address, Coord>,
+    /// Height each unspent coinbase triangle was mined at, until it matures.
+    /// The genesis allocation is spendable immediately and never listed.
+    #[serde(default)]
+    pub coinbase_heights: HashMap<Sha256Hash, u64>,
+}
+
+/// The UTXO changes made by one block. A triangle both created and spent
+/// within the block appears in neither list.
+#[derive(Debug, Clone, Default)]
+pub struct UtxoDelta {
+    pub created: Vec<(Sha256Hash, Triangle)>,
+    pub spent: Vec<Sha256Hash>,
 }
 
 impl TriangleState {
@@ -139,6 +372,64 @@ impl TriangleState {
         Self::default()
     }
 
+    /// Deterministic commitment to the UTXO set: SHA-256 over every
+    /// triangle hash and its effective value, in hash order
+    pub fn state_root(&self) -> Sha256Hash {
+        Self::root_of(self.utxo_set.iter())
+    }
+
+    /// [`state_root`](Self::state_root) of an arbitrary UTXO listing, such as
+    /// the one carried by a snapshot
+    pub fn root_of<'a>(utxos: impl Iterator<Item = (&'a Sha256Hash, &'a Triangle)>) -> Sha256Hash {
+        let mut leaves: Vec<(&Sha256Hash, Coord)> = utxos
+            .map(|(hash, triangle)| (hash, triangle.effective_value()))
+            .collect();
+        leaves.sort_unstable_by_key(|(hash, _)| *hash);
+
+        let mut hasher = Sha256::new();
+        for (hash, value) in leaves {
+            hasher.update(hash);
+            hasher.update(value.to_le_bytes());
+        }
+        hasher.finalize().into()
+    }
+
+    /// The [`UtxoDelta`] of applying `block` to a state whose triangles are
+    /// fetched with `lookup`. Only the block's inputs are looked up and
+    /// replayed, so the cost follows the block size, not the UTXO set's.
+    pub fn block_delta(
+        block: &Block,
+        mut lookup: impl FnMut(&Sha256Hash) -> Result<Option<Triangle>, ChainError>,
+    ) -> Result<UtxoDelta, ChainError> {
+        let mut scratch = TriangleState::new();
+        for hash in block
+            .transactions
+            .iter()
+            .filter_map(Transaction::input_hash)
+        {
+            if let Some(triangle) = lookup(&hash)? {
+                scratch.utxo_set.insert(hash, triangle);
+            }
+        }
+        let inputs: HashSet<Sha256Hash> = scratch.utxo_set.keys().copied().collect();
+
+        for tx in &block.transactions {
+            scratch.apply_transaction(tx, block.header.height)?;
+        }
+
+        let spent = inputs
+            .iter()
+            .filter(|hash| !scratch.utxo_set.contains_key(*hash))
+            .copied()
+            .collect();
+        let created = scratch
+            .utxo_set
+            .into_iter()
+            .filter(|(hash, _)| !inputs.contains(hash))
+            .collect();
+        Ok(UtxoDelta { created, spent })
+    }
+
     /// Rebuilds the address_balances HashMap by iterating through the current utxo_set.
     /// This should be called after loading the utxo_set from persistence.
     pub fn rebuild_address_balances(&mut self) {
@@ -151,6 +442,53 @@ impl TriangleState {
         }
     }
 
+    /// Rebuilds `coinbase_heights` from the coinbases of the most recent
+    /// `blocks`. Only the last `COINBASE_MATURITY` blocks can hold immature
+    /// coinbases, so the rest of the chain is not needed.
+    pub fn rebuild_coinbase_heights(&mut self, blocks: &[Block]) {
+        self.coinbase_heights.clear();
+        let recent = blocks.len().saturating_sub(COINBASE_MATURITY as usize);
+        for block in &blocks[recent..] {
+            let height = block.header.height;
+            if let Some(coinbase @ Transaction::Coinbase(_)) = block.transactions.first() {
+                let hash = coinbase.hash();
+                if height > 0 && self.utxo_set.contains_key(&hash) {
+                    self.coinbase_heights.insert(hash, height);
+                }
+            }
+        }
+    }
+
+    /// Height from which the triangle `hash` can be spent, if it is an
+    /// immature coinbase at `height`
+    pub fn spendable_at(&self, hash: &Sha256Hash, height: u64) -> Option<u64> {
+        let mature_at = self.coinbase_heights.get(hash)? + COINBASE_MATURITY;
+        (height < mature_at).then_some(mature_at)
+    }
+
+    /// Fails if `hash` is a coinbase triangle too young to spend in a block
+    /// at `height`
+    pub fn check_mature(&self, hash: &Sha256Hash, height: u64) -> Result<(), ChainError> {
+        match self.spendable_at(hash, height) {
+            Some(mature_at) => Err(ChainError::ImmatureCoinbase(format!(
+                "triangle {} can be spent from height {} (now {})",
+                hex::encode(hash),
+                mature_at,
+                height
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Coinbase triangles that cannot yet be spent in a block at `height`
+    pub fn immature_outputs(&self, height: u64) -> HashSet<Sha256Hash> {
+        self.coinbase_heights
+            .keys()
+            .filter(|hash| self.spendable_at(hash, height).is_some())
+            .copied()
+            .collect()
+    }
+
     /// Gets the current total area owned by an address.
     pub fn get_balance(&self, address: &Address) -> Coord {
         *self
@@ -159,18 +497,58 @@ impl TriangleState {
             .unwrap_or(&Coord::from_num(0))
     }
 
+    /// UTXO key of the change output a transfer leaves for its sender.
+    ///
+    /// The change is keyed by a pseudo-transaction spending the main transfer
+    /// output, so it gets a hash distinct from the recipient's triangle.
+    pub fn change_output_hash(tx: &TransferTx, remaining_value: Coord) -> Sha256Hash {
+        Transaction::Transfer(TransferTx {
+            input_hash: Transaction::Transfer(tx.clone()).hash(),
+            new_owner: tx.sender,
+            sender: tx.sender,
+            amount: remaining_value,
+            fee_area: Coord::from_num(0), // No fee on change.
+            nonce: tx.nonce + 1,          // Different nonce to ensure different hash.
+            signature: None,
+            public_key: None,
+            memo: Some("Change".to_string()),
+        })
+        .hash()
+    }
+
+    /// UTXO key of output `index` of a batch transfer. The change, if any,
+    /// follows the payments at index `outputs.len()`.
+    pub fn batch_output_hash(tx_hash: &Sha256Hash, index: usize) -> Sha256Hash {
+        let mut hasher = Sha256::new();
+        hasher.update("batch_output".as_bytes());
+        hasher.update(tx_hash);
+        hasher.update((index as u64).to_le_bytes());
+        hasher.finalize().into()
+    }
+
     /// Updates the UTXO set and derived balances based on a transaction.
     /// This is the core state transition logic for the blockchain. It is critical
     /// that this function is correct and deterministic.
     pub fn apply_transaction(
         &mut self,
         tx: &Transaction,
-        _block_height: u64,
+        block_height: u64,
     ) -> Result<(), ChainError> {
+
         match tx {
             // ================== 1. Coinbase Transaction ==================
             // Creates new value (area) and assigns it to the miner's address (beneficiary).
             Transaction::Coinbase(tx) => {
+                // A coinbase hashes the same as an earlier one with the same
+                // reward, miner and nonce; it must not overwrite that output.
+                let tx_hash = Transaction::Coinbase(tx.clone()).hash();
+                if self.utxo_set.contains_key(&tx_hash) {
+                    return Err(ChainError::InvalidTransaction(format!(
+                        "Coinbase {} duplicates an unspent output",
+                        hex::encode(tx_hash)
+                    )));
+                }
+
                 // a) Create the new Genesis Triangle for the block reward.
                 // This triangle has a specific area but its geometric points are zero,
                 // as it doesn't represent a real geometric shape but rather a value.
@@ -184,7 +562,6 @@ impl TriangleState {
                 .with_effective_value(tx.reward_area);
 
                 // b) Add the new triangle to the UTXO set, indexed by the transaction hash.
-                let tx_hash = Transaction::Coinbase(tx.clone()).hash();
                 self.utxo_set.insert(tx_hash, new_triangle);
 
                 // c) Update the balance for the beneficiary address.
@@ -192,6 +569,14 @@ impl TriangleState {
                     .address_balances
                     .entry(tx.beneficiary_address)
                     .or_insert(Coord::from_num(0)) += tx.reward_area;
+
+                // d) Track the new triangle until it matures, forgetting the
+                // ones that have.
+                self.coinbase_heights
+                    .retain(|_, height| *height + COINBASE_MATURITY > block_height);
+                if block_height > 0 {
+                    self.coinbase_heights.insert(tx_hash, block_height);
+                }
             }
 
             // ================== 2. Transfer Transaction ==================
@@ -199,6 +584,7 @@ impl TriangleState {
             // and optionally one for the sender's change).
             Transaction::Transfer(tx) => {
                 // a) Find and remove the input UTXO being spent.
+                let total_spent = tx.total_spent()?;
                 let input_hash = tx.input_hash;
                 let consumed_triangle = self.utxo_set.remove(&input_hash).ok_or_else(|| {
                     ChainError::TriangleNotFound(format!(
@@ -219,7 +605,6 @@ impl TriangleState {
                 }
 
                 let input_value = consumed_triangle.effective_value();
-                let total_spent = tx.amount + tx.fee_area;
                 let remaining_value = input_value - total_spent;
 
                 // c) Decrease the sender's balance by the full value of the consumed UTXO.
@@ -250,20 +635,7 @@ impl TriangleState {
 
                 // f) Handle the change. If there's remaining value, create a new UTXO for the sender.
                 if remaining_value > GEOMETRIC_TOLERANCE {
-                    // Create a pseudo-transaction for the change to get a unique hash.
-                    let change_tx = Transaction::Transfer(TransferTx {
-                        input_hash: tx_hash, // The "input" for the change is the hash of the main transfer output.
-                        new_owner: tx.sender,
-                        sender: tx.sender,
-                        amount: remaining_value,
-                        fee_area: Coord::from_num(0), // No fee on change.
-                        nonce: tx.nonce + 1,          // Different nonce to ensure different hash.
-                        signature: None,
-                        public_key: None,
-                        memo: Some("Change".to_string()),
-                    });
-
-                    let change_hash = change_tx.hash();
+                    let change_hash = Self::change_output_hash(tx, remaining_value);
                     let change_triangle = consumed_triangle
                         .change_owner(tx.sender)
                         .with_effective_value(remaining_value);
@@ -337,6 +709,83 @@ impl TriangleState {
                         .or_insert(Coord::from_num(0)) += child.effective_value();
                 }
             }
+
+            // ================== 4. Batch Transfer Transaction ==================
+            // Consumes one UTXO and creates one UTXO per recipient, plus the
+            // sender's change.
+            Transaction::BatchTransfer(tx) => {
+                // a) Find the input UTXO and check it before changing anything.
+                let input_hash = tx.input_hash;
+                let consumed_triangle =
+                    self.utxo_set.get(&input_hash).cloned().ok_or_else(|| {
+                        ChainError::TriangleNotFound(format!(
+                            "Input UTXO not found for batch transfer: {}",
+                            hex::encode(input_hash)
+                        ))
+                    })?;
+                if consumed_triangle.owner != tx.sender {
+                    return Err(ChainError::InvalidTransaction(format!(
+                        "Sender {} does not own input UTXO (owned by {})",
+                        hex::encode(tx.sender),
+                        hex::encode(consumed_triangle.owner)
+                    )));
+                }
+
+                let input_value = consumed_triangle.effective_value();
+                let remaining_value = input_value - tx.total_spent()?;
+                if remaining_value < Coord::from_num(0) {
+                    return Err(ChainError::InvalidTransaction(format!(
+                        "Batch transfer outputs + fee exceed input value {}",
+                        input_value
+                    )));
+                }
+
+                // b) Spend the input.
+                self.utxo_set.remove(&input_hash);
+                let sender_balance = self
+                    .address_balances
+                    .entry(tx.sender)
+                    .or_insert(Coord::from_num(0));
+                *sender_balance -= input_value;
+                if *sender_balance < Coord::from_num(0) {
+                    *sender_balance = Coord::from_num(0);
+                }
+
+                // c) One new UTXO per output.
+                let tx_hash = Transaction::BatchTransfer(tx.clone()).hash();
+                for (index, output) in tx.outputs.iter().enumerate() {
+                    let triangle = consumed_triangle
+                        .clone()
+                        .change_owner(output.recipient)
+                        .with_effective_value(output.amount);
+                    self.utxo_set
+                        .insert(Self::batch_output_hash(&tx_hash, index), triangle);
+                    *self
+                        .address_balances
+                        .entry(output.recipient)
+                        .or_insert(Coord::from_num(0)) += output.amount;
+                }
+
+                // d) Change back to the sender.
+                if remaining_value > GEOMETRIC_TOLERANCE {
+                    let change_triangle = consumed_triangle
+                        .change_owner(tx.sender)
+                        .with_effective_value(remaining_value);
+                    self.utxo_set.insert(
+                        Self::batch_output_hash(&tx_hash, tx.outputs.len()),
+                        change_triangle,
+                    );
+                    *self
+                        .address_balances
+                        .entry(tx.sender)
+                        .or_insert(Coord::from_num(0)) += remaining_value;
+                }
+            }
+        }
+
+        // Spent triangles no longer need a maturity record
+        if let Some(input_hash) = tx.input_hash() {
+            self.coinbase_heights.remove(&input_hash);
         }
         Ok(())
     }
@@ -353,47 +802,145 @@ pub struct Blockchain {
     pub difficulty: u32,
     pub mempool: Mempool,
     pub state: TriangleState, // UTXO Cache (TriangleState)
+    /// Position in `blocks` of every block, by hash. Code that edits
+    /// `blocks` directly must call [`Blockchain::rebuild_block_index`].
+    pub(crate) block_index: HashMap<Sha256Hash, usize>,
+    /// Where block, reorganization and mempool changes are published
+    pub(crate) events: EventBus,
+}
+
+/// A block that failed re-validation in [`Blockchain::verify_full`]
+#[derive(Debug, Clone)]
+pub struct ChainIssue {
+    pub height: u64,
+    pub hash: Sha256Hash,
+    pub reason: String,
+}
+
+/// Outcome of replaying the whole chain with [`Blockchain::verify_full`]
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    pub blocks_checked: u64,
+    /// Every block that failed, in height order
+    pub issues: Vec<ChainIssue>,
+    /// Whether the UTXO set rebuilt by the replay matches the loaded one
+    pub utxo_set_matches: bool,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty() && self.utxo_set_matches
+    }
 }
 
+/// Result of switching the main chain to a longer branch
+#[derive(Debug, Clone, Default)]
+pub struct ReorgOutcome {
+    /// Height of the last block both branches share
+    pub fork_height: u64,
+    /// Blocks removed from the main chain, oldest first
+    pub disconnected: Vec<Block>,
+    /// Transactions of the disconnected blocks returned to the mempool; these
+    /// should be rebroadcast
+    pub resubmitted: Vec<Transaction>,
+    /// Transactions of the disconnected blocks that could not be returned,
+    /// with the reason (spent by the new branch, conflicting, invalid)
+    pub dropped: Vec<(Sha256Hash, String)>,
+}
+
+/// A clone gets its own event bus: changes to the copy are not announced
+/// to the original's subscribers
 impl Clone for Blockchain {
     fn clone(&self) -> Self {
-        Self {
+        let mut clone = Self {
             blocks: self.blocks.clone(),
             difficulty: self.difficulty,
             mempool: self.mempool.clone(),
             state: self.state.clone(),
-        }
+            block_index: self.block_index.clone(),
+            events: EventBus::new(),
+        };
+        clone.mempool.set_event_bus(clone.events.clone());
+        clone
     }
 }
 
 impl Blockchain {
-    /// Initializer for a new blockchain with the genesis block.
+    /// A fresh chain at the active network's canonical genesis block, the
+    /// one every node of the network starts from
+    pub fn canonical() -> Result<Self, ChainError> {
+        let network = active_network();
+        let genesis = Self::genesis_block(network);
+        Self::check_genesis(&genesis)?;
+        Self::from_genesis(genesis, GENESIS_DIFFICULTY)
+    }
+
+    /// A chain with a custom genesis block paying the genesis supply to
+    /// `genesis_miner_address`, mined on the spot. For tests and private
+    /// regtest chains: other nodes won't share this genesis, and a database
+    /// holding it only loads on regtest or with
+    /// [`Database::with_custom_genesis`](crate::persistence::Database::with_custom_genesis).
     pub fn new(
         genesis_miner_address: Address,
         initial_difficulty: u32,
     ) -> Result<Self, ChainError> {
-        let genesis_block = Self::create_genesis_block(genesis_miner_address, initial_difficulty)?;
+        // Regtest mines every block at difficulty 1
+        let initial_difficulty = if active_network().fixed_difficulty() {
+            1
+        } else {
+            initial_difficulty
+        };
+        let genesis =
+            Self::genesis_template(active_network(), genesis_miner_address, initial_difficulty);
+        Self::from_genesis(mine_block(genesis)?, initial_difficulty)
+    }
 
+    fn from_genesis(genesis: Block, difficulty: u32) -> Result<Self, ChainError> {
         let mut blockchain = Blockchain {
             blocks: vec![],
-            difficulty: initial_difficulty,
+            difficulty,
             mempool: Mempool::new(),
             state: TriangleState::new(),
+            block_index: HashMap::new(),
+            events: EventBus::new(),
         };
+        blockchain.mempool.set_event_bus(blockchain.events.clone());
 
         // Apply the genesis block to initialize the state
-        blockchain.apply_block(genesis_block)?;
+        blockchain.apply_block(genesis)?;
         Ok(blockchain)
     }
 
-    /// Creates the immutable genesis block.
-    fn create_genesis_block(
-        miner_address: Address,
-        initial_difficulty: u32,
-    ) -> Result<Block, ChainError> {
+    /// The canonical genesis block of `network`: the genesis supply paid to
+    /// the zero address (so nobody can spend it), at the network's genesis
+    /// timestamp and nonce
+    pub fn genesis_block(network: Network) -> Block {
+        let mut genesis = Self::genesis_template(network, Address::ZERO, GENESIS_DIFFICULTY);
+        genesis.header.nonce = network.genesis_nonce();
+        genesis
+    }
+
+    /// Fails unless `genesis` is the active network's canonical genesis
+    /// block. Regtest accepts any genesis.
+    pub fn check_genesis(genesis: &Block) -> Result<(), ChainError> {
+        let network = active_network();
+        let hash = genesis.hash();
+        if network.allows_custom_genesis() || hash == network.genesis_hash() {
+            return Ok(());
+        }
+        Err(ChainError::InvalidBlock(format!(
+            "Genesis block {} is not the {} genesis {}",
+            hex::encode(hash),
+            network.name(),
+            hex::encode(network.genesis_hash())
+        )))
+    }
+
+    /// An unmined genesis block paying the genesis supply to `miner_address`
+    fn genesis_template(network: Network, miner_address: Address, difficulty: u32) -> Block {
         // Create a special genesis transaction (Coinbase)
         let coinbase_tx = Transaction::Coinbase(CoinbaseTx {
-            reward_area: Coord::from_num(1_000_000.0), // Initial fixed supply
+            reward_area: Coord::from_num(GENESIS_SUPPLY), // Initial fixed supply
             beneficiary_address: miner_address,
             nonce: 0,
         });
@@ -402,33 +949,224 @@ impl Blockchain {
         let merkle_root = Block::calculate_merkle_root(&transactions);
 
         let header = BlockHeader {
+            version: 0,
             height: 0,
-            timestamp: 1672531200000, // Jan 1, 2023
+            timestamp: network.genesis_timestamp(),
             previous_hash: [0u8; 32],
             merkle_root,
-            difficulty: initial_difficulty,
+            // Left zero so the genesis hash, and with it the network's
+            // identity, is unchanged
+            state_root: [0; 32],
+            difficulty,
             nonce: 0,
         };
 
-        let genesis_block = Block {
+        Block {
             header,
             transactions,
-        };
-
-        mine_block(genesis_block)
+        }
     }
 
     /// Calculates the block reward based on height (halving model)
     pub fn calculate_block_reward(height: u64) -> f64 {
-        const INITIAL_REWARD: f64 = 50.0; // In area units
-        const HALVING_INTERVAL: u64 = 210000;
-
         let halving_count = height / HALVING_INTERVAL;
         if halving_count >= 64 {
             // Prevent overflow and reward going to 0
             0.0
         } else {
-            INITIAL_REWARD / (2u64.pow(halving_count as u32) as f64)
+            INITIAL_BLOCK_REWARD / (2u64.pow(halving_count as u32) as f64)
+        }
+    }
+
+    /// The genesis supply plus every subsidy the halving schedule will pay
+    pub fn max_supply() -> f64 {
+        // Era 0 starts after the genesis block
+        (0..64u64).fold(GENESIS_SUPPLY, |total, era| {
+            let blocks = if era == 0 {
+                HALVING_INTERVAL - 1
+            } else {
+                HALVING_INTERVAL
+            };
+            total + blocks as f64 * Self::calculate_block_reward(era * HALVING_INTERVAL)
+        })
+    }
+
+    /// Supply and halving countdown at the tip, shared by the API, the
+    /// miner dashboard and the Telegram bot
+    pub fn supply_info(&self) -> SupplyInfo {
+        let height = self.blocks.last().map_or(0, |b| b.header.height);
+        let next_height = self.next_height();
+        let halving_era = next_height / HALVING_INTERVAL;
+        let next_halving_height = (halving_era + 1).saturating_mul(HALVING_INTERVAL);
+        SupplyInfo {
+            height,
+            circulating_supply: self.supply_stats(0).circulating_area,
+            current_reward: Self::calculate_block_reward(next_height),
+            halving_era,
+            next_halving_height,
+            blocks_to_halving: next_halving_height - height,
+            max_supply: Self::max_supply(),
+        }
+    }
+
+    /// The main-chain block with `hash`, found through the block index
+    pub fn get_block_by_hash(&self, hash: &Sha256Hash) -> Option<&Block> {
+        self.block_index
+            .get(hash)
+            .and_then(|&index| self.blocks.get(index))
+    }
+
+    /// The main-chain block at `height`
+    pub fn get_block_by_height(&self, height: u64) -> Option<&Block> {
+        let first = self.blocks.first()?.header.height;
+        let index = usize::try_from(height.checked_sub(first)?).ok()?;
+        self.blocks.get(index)
+    }
+
+    /// Receive every [`ChainEvent`] this chain publishes from now on
+    pub fn subscribe_events(&self) -> broadcast::Receiver<ChainEvent> {
+        self.events.subscribe()
+    }
+
+    /// The bus this chain publishes on, for wrappers that hand out
+    /// subscriptions without holding the chain lock
+    pub fn event_bus(&self) -> &EventBus {
+        &self.events
+    }
+
+    /// Re-index `blocks` after it was replaced or edited in place
+    pub fn rebuild_block_index(&mut self) {
+        self.block_index = self
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(index, block)| (block.hash(), index))
+            .collect();
+    }
+
+    /// Median timestamp of the last `MEDIAN_TIME_SPAN` blocks. The next
+    /// block must be stamped strictly after it.
+    pub fn median_time_past(&self) -> u64 {
+        let start = self.blocks.len().saturating_sub(MEDIAN_TIME_SPAN);
+        let mut times: Vec<u64> = self.blocks[start..]
+            .iter()
+            .map(|b| b.header.timestamp)
+            .collect();
+        if times.is_empty() {
+            return 0;
+        }
+        times.sort_unstable();
+        times[times.len() / 2]
+    }
+
+    /// State root of the UTXO set after applying `block`'s transactions on
+    /// top of the tip, for filling in a candidate header
+    pub fn state_root_after(&self, block: &Block) -> Result<Sha256Hash, ChainError> {
+        let mut state = self.state.clone();
+        for tx in &block.transactions {
+            state.apply_transaction(tx, block.header.height)?;
+        }
+        Ok(state.state_root())
+    }
+
+    /// Sums the fees paid by a set of transactions (coinbase pays no fee).
+    pub fn total_fees(transactions: &[Transaction]) -> Coord {
+        transactions
+            .iter()
+            .fold(Coord::from_num(0), |acc, tx| acc.saturating_add(tx.fee_area()))
+    }
+
+    /// The most a coinbase at `height` may claim: the block subsidy for that
+    /// height plus the fees of the transactions it includes, capped at what
+    /// a single coinbase may carry.
+    pub fn max_coinbase_reward(height: u64, fees: Coord) -> Coord {
+        Coord::from_num(Self::calculate_block_reward(height))
+            .saturating_add(fees)
+            .min(CoinbaseTx::MAX_REWARD_AREA)
+    }
+
+    /// Header version the next block should carry, signalling every
+    /// deployment that is started or locked in.
+    pub fn next_block_version(&self) -> u32 {
+        let headers = self.headers();
+        versionbits::block_version(DEPLOYMENTS, &headers, self.next_height())
+    }
+
+    /// State of every known deployment for the next block.
+    pub fn deployment_statuses(&self) -> Vec<DeploymentStatus> {
+        let headers = self.headers();
+        let height = self.next_height();
+        DEPLOYMENTS
+            .iter()
+            .map(|d| d.status_at(&headers, height))
+            .collect()
+    }
+
+    /// Whether the deployment `name` is active for a block at `height`, for
+    /// gating consensus rules it introduces. Unknown names are never active.
+    pub fn is_deployment_active(&self, name: &str, height: u64) -> bool {
+        DEPLOYMENTS.iter().any(|d| {
+            d.name == name && d.state_at(&self.headers(), height) == DeploymentState::Active
+        })
+    }
+
+    fn headers(&self) -> Vec<&BlockHeader> {
+        self.blocks.iter().map(|b| &b.header).collect()
+    }
+
+    fn next_height(&self) -> u64 {
+        self.blocks.last().map_or(0, |b| b.header.height + 1)
+    }
+
+    /// Status of a transaction. `indexed_height` is where the transaction
+    /// index places it, if known; otherwise the chain is searched from the tip.
+    pub fn transaction_status(&self, hash: &Sha256Hash, indexed_height: Option<u64>) -> TxStatus {
+        if self.mempool.get_transaction(hash).is_some() {
+            return TxStatus::Pending;
+        }
+        let contains = |block: &&Block| block.transactions.iter().any(|tx| tx.hash() == *hash);
+        let block = indexed_height
+            .and_then(|height| self.get_block_by_height(height))
+            .filter(contains)
+            .or_else(|| self.blocks.iter().rev().find(contains));
+        match (block, self.blocks.last()) {
+            (Some(block), Some(tip)) => {
+                TxStatus::confirmed(block.header.height, &block.hash(), tip.header.height)
+            }
+            _ => TxStatus::Unknown,
+        }
+    }
+
+    /// Circulating area, triangle and owner counts, and the `top_n` largest
+    /// holders, from the current UTXO set (no block scan needed).
+    pub fn supply_stats(&self, top_n: usize) -> SupplyStats {
+        let mut holdings: HashMap<Address, (Coord, usize)> = HashMap::new();
+        let mut circulating_area = Coord::from_num(0);
+        for triangle in self.state.utxo_set.values() {
+            let value = triangle.effective_value();
+            circulating_area = circulating_area.saturating_add(value);
+            let entry = holdings.entry(triangle.owner).or_insert((Coord::from_num(0), 0));
+            entry.0 = entry.0.saturating_add(value);
+            entry.1 += 1;
+        }
+
+        let distinct_owners = holdings.len();
+        let mut holders: Vec<(Address, (Coord, usize))> = holdings.into_iter().collect();
+        holders.sort_by(|a, b| b.1 .0.cmp(&a.1 .0).then(a.0.cmp(&b.0)));
+
+        SupplyStats {
+            circulating_area,
+            total_triangles: self.state.utxo_set.len(),
+            distinct_owners,
+            top_holders: holders
+                .into_iter()
+                .take(top_n)
+                .map(|(address, (balance, triangles))| Holder {
+                    address: hex::encode(address),
+                    balance,
+                    triangles,
+                })
+                .collect(),
         }
     }
 
@@ -440,13 +1178,7 @@ impl Blockchain {
     fn validate_no_double_spend(block: &Block) -> Result<(), ChainError> {
         let mut seen_inputs = HashMap::new();
         for tx in &block.transactions {
-            let input_hash = match tx {
-                Transaction::Transfer(t) => Some(t.input_hash),
-                Transaction::Subdivision(s) => Some(s.parent_hash),
-                _ => None, // Coinbase has no input
-            };
-
-            if let Some(hash) = input_hash {
+            if let Some(hash) = tx.input_hash() {
                 if let Some(conflicting_tx_hash) = seen_inputs.get(&hash) {
                     return Err(ChainError::InvalidTransaction(format!(
                         "Double spend detected in block. UTXO {} is spent by both {} and {}",
@@ -475,7 +1207,15 @@ impl Blockchain {
                 )
             })?;
 
-            // a) Check for sequential height
+            // a) A block further ahead whose parent is not our tip cannot be
+            //    connected yet; callers may hold it until the parent arrives.
+            if block.header.height > last_block.header.height + 1
+                && block.header.previous_hash != last_block.hash()
+            {
+                return Err(ChainError::OrphanBlock);
+            }
+
+            // b) Check for sequential height
             if block.header.height != last_block.header.height + 1 {
                 return Err(ChainError::InvalidBlock(format!(
                     "Invalid block height. Expected {}, but got {}.",
@@ -484,7 +1224,7 @@ impl Blockchain {
                 )));
             }
 
-            // b) Check for correct previous hash link
+            // c) Check for correct previous hash link
             if block.header.previous_hash != last_block.hash() {
                 return Err(ChainError::InvalidBlock(format!(
                     "Invalid previous block hash. Expected {}, but got {}.",
@@ -492,6 +1232,33 @@ impl Blockchain {
                     hex::encode(block.header.previous_hash)
                 )));
             }
+
+            // d) The header must claim the difficulty the retarget schedule
+            //    sets for this height; verify_pow checks against the header,
+            //    so a miner could otherwise pick an easy target
+            if block.header.difficulty != self.difficulty {
+                return Err(ChainError::InvalidBlock(format!(
+                    "Block difficulty {} does not match the expected difficulty {}.",
+                    block.header.difficulty, self.difficulty
+                )));
+            }
+
+            // e) The timestamp must move past the median of recent blocks, so
+            //    a single miner can't drag chain time backwards
+            let median = self.median_time_past();
+            if block.header.timestamp <= median {
+                return Err(ChainError::InvalidBlock(format!(
+                    "Block timestamp {} is not after the median time past {}.",
+                    block.header.timestamp, median
+                )));
+            }
+
+            // f) ...nor run far ahead of our clock. This depends on local
+            //    time, so the block may become acceptable later.
+            let limit = chrono::Utc::now().timestamp_millis() as u64 + MAX_FUTURE_BLOCK_TIME_MS;
+            if block.header.timestamp > limit {
+                return Err(ChainError::FutureBlock);
+            }
         } else if !self.blocks.is_empty() {
             // Genesis block can only be applied to an empty chain
             return Err(ChainError::InvalidBlock(
@@ -514,25 +1281,60 @@ impl Blockchain {
         // a) Check for double spending within the block itself
         Self::validate_no_double_spend(&block)?;
 
-        // b) Validate and apply each transaction sequentially
+        // b) Validate and apply each transaction sequentially, timing the
+        //    signature and state phases separately.
+        let validation_start = Instant::now();
+        let mut timings = ValidationMetrics::new();
         for (i, tx) in block.transactions.iter().enumerate() {
             // All transactions must adhere to size limits.
             tx.validate_size()?;
 
-            // The first transaction MUST be a Coinbase transaction.
+            let signature_start = Instant::now();
+            // The first transaction MUST be a Coinbase transaction, and the
+            // only one: a second coinbase would mint outside the reward cap.
             if i == 0 {
-                if !matches!(tx, Transaction::Coinbase(_)) {
+                let Transaction::Coinbase(coinbase) = tx else {
                     return Err(ChainError::InvalidBlock(
                         "First transaction in a block must be a Coinbase transaction.".to_string(),
                     ));
+                };
+                // The genesis coinbase mints the initial supply, beyond any block cap
+                if !is_genesis {
+                    coinbase.validate()?;
                 }
+            } else if matches!(tx, Transaction::Coinbase(_)) {
+                return Err(ChainError::InvalidBlock(format!(
+                    "Transaction {} is a Coinbase; only the first transaction may be one.",
+                    i
+                )));
             } else {
-                // All other transactions must be standard and pass signature/state checks.
-                tx.validate(&temp_state)?;
+                // All other transactions must be standard and pass signature checks.
+                tx.validate_stateless(block.header.height)?;
             }
+            let signature_time = signature_start.elapsed();
 
+            let state_start = Instant::now();
+            if i > 0 {
+                tx.validate_state(&temp_state, block.header.height)?;
+            }
             // Apply the transaction to the temporary state, updating the UTXO set.
             temp_state.apply_transaction(tx, block.header.height)?;
+            timings.record_transaction(tx.kind(), signature_time, state_start.elapsed());
+        }
+        timings.record_block(validation_start.elapsed());
+
+        // c) The coinbase may claim at most the subsidy plus the included fees.
+        if !is_genesis {
+            if let Some(Transaction::Coinbase(coinbase)) = block.transactions.first() {
+                let fees = Self::total_fees(&block.transactions[1..]);
+                let max_reward = Self::max_coinbase_reward(block.header.height, fees);
+                if coinbase.reward_area > max_reward {
+                    return Err(ChainError::InvalidBlock(format!(
+                        "Coinbase reward {} exceeds allowed {} (subsidy + {} in fees).",
+                        coinbase.reward_area, max_reward, fees
+                    )));
+                }
+            }
         }
 
         // 4. ==================== Final Block Validation ====================
@@ -546,9 +1348,30 @@ impl Blockchain {
             )));
         }
 
+        // b) Every header after genesis signals version bits and commits to
+        //    the resulting UTXO set. Only the genesis header is version 0
+        //    with a zero root, which BlockHeader::hash leaves out.
+        if !is_genesis {
+            if block.header.version == 0 {
+                return Err(ChainError::InvalidBlock(format!(
+                    "Block #{} has header version 0; only the genesis block may.",
+                    block.header.height
+                )));
+            }
+            let expected_state_root = temp_state.state_root();
+            if expected_state_root != block.header.state_root {
+                return Err(ChainError::InvalidBlock(format!(
+                    "State root mismatch. Expected {}, but got {}.",
+                    hex::encode(expected_state_root),
+                    hex::encode(block.header.state_root)
+                )));
+            }
+        }
+
         // 5. ==================== Commit to Chain State ====================
         // All checks passed. The block is valid.
-        // a) Add the block to the blockchain.
+        // a) Add the block to the blockchain and its index.
+        self.block_index.insert(block.hash(), self.blocks.len());
         self.blocks.push(block.clone());
         // b) Commit the temporary state as the new official state.
         self.state = temp_state;
@@ -557,16 +1380,257 @@ impl Blockchain {
         for tx in &block.transactions {
             self.mempool.remove_transaction(&tx.hash());
         }
+        //    Pending transactions signed under a scheme retired at the next
+        //    height could no longer be mined.
+        let next_height = block.header.height + 1;
+        if SighashVersion::minimum_at(next_height) > SighashVersion::minimum_at(block.header.height) {
+            self.mempool.prune(&self.state, next_height);
+        }
 
         // d) Adjust difficulty.
         self.adjust_difficulty();
 
+        // e) Only blocks that were accepted count towards validation metrics.
+        metrics::record_validation(&timings);
+
+        if self.events.has_subscribers() {
+            self.events
+                .publish(ChainEvent::BlockConnected(Arc::new(block)));
+        }
         Ok(())
     }
 
+    /// Switch the main chain to `branch`, a sequence of blocks whose first
+    /// block's parent is in the main chain and which ends above the current
+    /// tip.
+    ///
+    /// The branch is fully validated against the state at the fork point
+    /// before anything changes; on error the chain is left untouched.
+    /// Transactions of the disconnected blocks that the new branch does not
+    /// include go back to the mempool if they are still valid and do not
+    /// conflict with a pending transaction.
+    pub fn reorganize(&mut self, branch: Vec<Block>) -> Result<ReorgOutcome, ChainError> {
+        let (first, last) = match (branch.first(), branch.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Err(ChainError::ForkNotFound),
+        };
+        let fork_index = self
+            .blocks
+            .iter()
+            .rposition(|b| b.hash() == first.header.previous_hash)
+            .ok_or(ChainError::ForkNotFound)?;
+        let tip_height = self.blocks.last().map_or(0, |b| b.header.height);
+        if last.header.height <= tip_height {
+            return Err(ChainError::InvalidBlock(format!(
+                "Branch ending at #{} is not longer than the main chain (#{})",
+                last.header.height, tip_height
+            )));
+        }
+
+        if self.blocks[..=fork_index].iter().any(Block::is_header_only) {
+            return Err(ChainError::InvalidBlock(format!(
+                "Cannot reorganize at #{}: earlier blocks are stored header-only",
+                self.blocks[fork_index].header.height
+            )));
+        }
+
+        // Rebuild the chain as it was right after the fork block. The
+        // difficulty in effect after a block is the one recorded in its
+        // header, adjusted if that block closed a retarget interval.
+        let mut candidate = Blockchain {
+            blocks: self.blocks[..=fork_index].to_vec(),
+            difficulty: self.blocks[fork_index].header.difficulty,
+            mempool: Mempool::new(),
+            state: TriangleState::new(),
+            block_index: HashMap::new(),
+            events: EventBus::new(),
+        };
+        candidate.rebuild_block_index();
+        for block in &candidate.blocks {
+            for tx in &block.transactions {
+                candidate.state.apply_transaction(tx, block.header.height)?;
+            }
+        }
+        candidate.adjust_difficulty();
+        for block in branch {
+            candidate.apply_block(block)?;
+        }
+
+        // Commit the new branch
+        let disconnected = self.blocks.split_off(fork_index + 1);
+        self.blocks = candidate.blocks;
+        self.block_index = candidate.block_index;
+        self.state = candidate.state;
+        self.difficulty = candidate.difficulty;
+        if self.events.has_subscribers() {
+            for block in disconnected.iter().rev() {
+                self.events
+                    .publish(ChainEvent::BlockDisconnected(Arc::new(block.clone())));
+            }
+            for block in &self.blocks[fork_index + 1..] {
+                self.events
+                    .publish(ChainEvent::BlockConnected(Arc::new(block.clone())));
+            }
+            self.events.publish(ChainEvent::Reorganized {
+                fork_height: self.blocks[fork_index].header.height,
+                disconnected: disconnected.len(),
+                connected: self.blocks.len() - fork_index - 1,
+            });
+        }
+
+        let confirmed: HashSet<Sha256Hash> = self.blocks[fork_index + 1..]
+            .iter()
+            .flat_map(|b| b.transactions.iter().map(|tx| tx.hash()))
+            .collect();
+        for hash in &confirmed {
+            self.mempool.remove_transaction(hash);
+        }
+        // Pending transactions may spend triangles the new branch consumed
+        let next_height = self.blocks.last().map_or(0, |b| b.header.height + 1);
+        self.mempool.prune(&self.state, next_height);
+
+        let mut outcome = ReorgOutcome {
+            fork_height: self.blocks[fork_index].header.height,
+            ..Default::default()
+        };
+        for tx in disconnected.iter().flat_map(|b| b.transactions.iter()) {
+            let hash = tx.hash();
+            if matches!(tx, Transaction::Coinbase(_)) || confirmed.contains(&hash) {
+                continue;
+            }
+            // The mempool rejects a transaction conflicting with a pending
+            // one unless it pays a higher fee
+            match tx
+                .validate(&self.state, next_height)
+                .and_then(|_| self.mempool.add_transaction(tx.clone()))
+            {
+                Ok(()) => outcome.resubmitted.push(tx.clone()),
+                Err(e) => outcome.dropped.push((hash, e.to_string())),
+            }
+        }
+        outcome.disconnected = disconnected;
+        Ok(outcome)
+    }
+
+    /// Replay the whole chain from genesis through [`apply_block`](Self::apply_block),
+    /// re-checking proof of work, links, merkle and state roots, signatures
+    /// and every UTXO transition, then compare the rebuilt UTXO set with
+    /// this chain's. A failing block is recorded and connected anyway so
+    /// the blocks after it are still checked. `progress` is called with the
+    /// height of each block once checked.
+    ///
+    /// Fails without checking anything when blocks are stored header-only,
+    /// since the state they lead to can't be rebuilt.
+    pub fn verify_full(&self, mut progress: impl FnMut(u64)) -> Result<VerifyReport, ChainError> {
+        self.require_full_history()?;
+
+        let mut replay = Blockchain {
+            blocks: Vec::with_capacity(self.blocks.len()),
+            difficulty: self.blocks.first().map_or(1, |b| b.header.difficulty),
+            mempool: Mempool::new(),
+            state: TriangleState::new(),
+            block_index: HashMap::new(),
+            events: EventBus::new(),
+        };
+        let mut report = VerifyReport::default();
+        for block in &self.blocks {
+            let height = block.header.height;
+            if let Err(e) = replay.apply_block(block.clone()) {
+                report.issues.push(ChainIssue {
+                    height,
+                    hash: block.hash(),
+                    reason: e.to_string(),
+                });
+                // Carry on from the stored block, applying what can be
+                for tx in &block.transactions {
+                    let _ = replay.state.apply_transaction(tx, height);
+                }
+                replay.block_index.insert(block.hash(), replay.blocks.len());
+                replay.blocks.push(block.clone());
+                replay.adjust_difficulty();
+            }
+            report.blocks_checked += 1;
+            progress(height);
+        }
+
+        report.utxo_set_matches = replay.state.state_root() == self.state.state_root();
+        Ok(report)
+    }
+
+    /// Confirmations of the `owned` triangles created in the last `depth`
+    /// blocks, by hash. Older triangles are left out; a change output is
+    /// matched by its value, so only triangles of the UTXO set can be found.
+    pub fn recent_confirmations<'a>(
+        &self,
+        owned: impl IntoIterator<Item = (&'a Sha256Hash, &'a Triangle)>,
+        depth: u64,
+    ) -> HashMap<Sha256Hash, u64> {
+        let owned: HashMap<Sha256Hash, &Triangle> =
+            owned.into_iter().map(|(hash, t)| (*hash, t)).collect();
+        let Some(tip) = self.blocks.last().map(|b| b.header.height) else {
+            return HashMap::new();
+        };
+        let recent = self.blocks.len().saturating_sub(depth as usize);
+        let mut confirmations = HashMap::new();
+        for block in &self.blocks[recent..] {
+            let count = tip - block.header.height + 1;
+            for tx in &block.transactions {
+                let tx_hash = tx.hash();
+                let mut created = vec![tx_hash];
+                match tx {
+                    Transaction::Transfer(tx) => {
+                        created.extend(owned.iter().filter(|(_, t)| t.owner == tx.sender).map(
+                            |(_, t)| TriangleState::change_output_hash(tx, t.effective_value()),
+                        ))
+                    }
+                    Transaction::BatchTransfer(tx) => created.extend(
+                        (0..=tx.outputs.len())
+                            .map(|index| TriangleState::batch_output_hash(&tx_hash, index)),
+                    ),
+                    Transaction::Subdivision(tx) => {
+                        created.extend(tx.children.iter().map(Triangle::hash))
+                    }
+                    Transaction::Coinbase(_) => {}
+                }
+                for hash in created {
+                    if owned.contains_key(&hash) {
+                        confirmations.insert(hash, count);
+                    }
+                }
+            }
+        }
+        confirmations
+    }
+
+    /// The provenance of a triangle, spent or not: the triangle followed by
+    /// each one it was subdivided or transferred from, back to the coinbase
+    /// that minted the value. Replays the chain, so fails on header-only
+    /// blocks.
+    pub fn trace_lineage(
+        &self,
+        triangle_hash: &Sha256Hash,
+    ) -> Result<Vec<LineageStep>, ChainError> {
+        self.require_full_history()?;
+        LineageGraph::from_blocks(&self.blocks)?.ancestry(triangle_hash)
+    }
+
+    /// Replaying from genesis needs every block body
+    fn require_full_history(&self) -> Result<(), ChainError> {
+        match self.blocks.iter().rev().find(|b| b.is_header_only()) {
+            Some(block) => Err(ChainError::InvalidBlock(format!(
+                "Cannot replay the chain: blocks up to #{} are stored header-only",
+                block.header.height
+            ))),
+            None => Ok(()),
+        }
+    }
+
     /// Adjusts the blockchain difficulty based on the time it took to mine the last
     /// `DIFFICULTY_ADJUSTMENT_INTERVAL` blocks.
-    fn adjust_difficulty(&mut self) {
+    pub(crate) fn adjust_difficulty(&mut self) {
+        if active_network().fixed_difficulty() {
+            return;
+        }
         let current_height = self.blocks.last().map_or(0, |b| b.header.height);
         if current_height > 0 && current_height.is_multiple_of(DIFFICULTY_ADJUSTMENT_INTERVAL) {
             let last_adjustment_block = self
@@ -575,22 +1639,41 @@ impl Blockchain {
 
             if let Some(last_adjustment_block) = last_adjustment_block {
                 let last_block = self.blocks.last().unwrap();
-                let actual_time =
-                    last_block.header.timestamp - last_adjustment_block.header.timestamp;
-                let expected_time = (DIFFICULTY_ADJUSTMENT_INTERVAL * TARGET_BLOCK_TIME) * 1000; // in milliseconds
-
-                let ratio = actual_time as f64 / expected_time as f64;
-
-                // Clamp the ratio to prevent drastic changes
-                let ratio = ratio.clamp(0.25, 4.0);
-
-                let new_difficulty = (self.difficulty as f64 * ratio) as u32;
-                // Ensure difficulty is at least 1
-                self.difficulty = new_difficulty.max(1);
+                // Timestamps only have to beat the median time past, so the
+                // interval's last block may be stamped before its first
+                let actual_time = last_block
+                    .header
+                    .timestamp
+                    .saturating_sub(last_adjustment_block.header.timestamp);
+                self.difficulty = retarget(self.difficulty, actual_time);
             }
         }
     }
 
+    /// Cheap checks for a block that cannot be connected yet, so unmined
+    /// junk never reaches the orphan pool: the claimed difficulty is at most
+    /// one retarget below ours, the hash meets it, and the merkle root
+    /// matches the transactions.
+    pub fn check_unconnected_block(&self, block: &Block) -> Result<(), ChainError> {
+        if block.header.difficulty.saturating_mul(4) < self.difficulty {
+            return Err(ChainError::InvalidBlock(format!(
+                "Block difficulty {} is far below the current difficulty {}.",
+                block.header.difficulty, self.difficulty
+            )));
+        }
+        if !self.verify_pow(block) {
+            return Err(ChainError::InvalidBlock(
+                "Invalid Proof-of-Work: Block hash does not meet difficulty target.".to_string(),
+            ));
+        }
+        if Block::calculate_merkle_root(&block.transactions) != block.header.merkle_root {
+            return Err(ChainError::InvalidBlock(
+                "Merkle root mismatch.".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
     /// Verifies the Proof-of-Work constraint of a block.
     fn verify_pow(&self, block: &Block) -> bool {
         let hash_target = Block::hash_to_target(&block.header.difficulty);
@@ -608,13 +1691,14 @@ impl Blockchain {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fixtures::seal_block;
     use crate::geometry::{Coord, Point};
     use crate::transaction::{SubdivisionTx, TransferTx};
     fn create_test_address(id: &str) -> Address {
         let mut address = [0u8; 32];
         let bytes = id.as_bytes();
         address[..bytes.len()].copy_from_slice(bytes);
-        address
+        Address::from(address)
     }
 
     fn create_test_blockchain() -> Blockchain {
@@ -642,18 +1726,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_canonical_genesis_matches_constants() {
+        for network in [Network::Mainnet, Network::Testnet, Network::Regtest] {
+            let genesis = Blockchain::genesis_block(network);
+            assert_eq!(genesis.hash(), network.genesis_hash(), "{}", network.name());
+
+            // The stored nonce is the first one meeting the genesis difficulty
+            let template = Blockchain::genesis_template(network, Address::ZERO, GENESIS_DIFFICULTY);
+            assert_eq!(
+                mine_block(template).unwrap().header.nonce,
+                network.genesis_nonce()
+            );
+        }
+
+        let chain = Blockchain::canonical().unwrap();
+        assert_eq!(chain.blocks[0].hash(), Network::Mainnet.genesis_hash());
+        assert_eq!(chain.difficulty, GENESIS_DIFFICULTY);
+        assert!(Blockchain::check_genesis(&create_test_blockchain().blocks[0]).is_err());
+    }
+
     #[test]
     fn test_block_header_hash() {
-        let header = BlockHeader {
+        let mut header = BlockHeader {
+            version: 0,
             height: 1,
             timestamp: 12345,
             previous_hash: [1; 32],
             merkle_root: [2; 32],
+            state_root: [0; 32],
             difficulty: 10,
             nonce: 42,
         };
         let hash = header.hash();
         assert_ne!(hash, [0; 32]);
+        header.version = crate::versionbits::VERSIONBITS_TOP_BITS;
+        assert_ne!(header.hash(), hash);
+        let signalling = header.hash();
+        header.state_root = [3; 32];
+        assert_ne!(header.hash(), signalling);
     }
 
     #[test]
@@ -666,7 +1777,7 @@ mod tests {
         assert_ne!(root_empty, [0u8; 32]); // Should be hash of empty data, not zeros
 
         // Test with one transaction
-        let root_one = Block::calculate_merkle_root(&[tx1.clone()]);
+        let root_one = Block::calculate_merkle_root(std::slice::from_ref(&tx1));
         let mut hasher = Sha256::new();
         hasher.update(tx1.hash());
         let expected_one: Sha256Hash = hasher.finalize().into();
@@ -681,6 +1792,43 @@ mod tests {
         assert_eq!(root_multiple, expected_multiple);
     }
 
+    #[test]
+    fn test_update_merkle_incremental() {
+        let coinbase = |nonce: u64| {
+            Transaction::Coinbase(CoinbaseTx {
+                reward_area: Coord::from_num(1),
+                beneficiary_address: create_test_address("test"),
+                nonce,
+            })
+        };
+        let transactions: Vec<Transaction> = (0..PARALLEL_MERKLE_THRESHOLD as u64 + 4)
+            .map(coinbase)
+            .collect();
+        let mut block = Block::new(1, [0; 32], 1, transactions.clone());
+        let mut cache = MerkleCache::new();
+        assert_eq!(
+            block.update_merkle_incremental(&mut cache, &[]),
+            Block::calculate_merkle_root(&transactions)
+        );
+        assert_eq!(cache.len(), transactions.len());
+
+        // New extranonce in the coinbase, and a replaced transaction
+        block.transactions[0] = coinbase(1_000);
+        block.transactions[7] = coinbase(1_001);
+        let root = block.update_merkle_incremental(&mut cache, &[0, 7]);
+        assert_eq!(root, Block::calculate_merkle_root(&block.transactions));
+        assert_eq!(block.header.merkle_root, root);
+
+        // Appended and dropped transactions
+        block.transactions.push(coinbase(1_002));
+        let root = block.update_merkle_incremental(&mut cache, &[]);
+        assert_eq!(root, Block::calculate_merkle_root(&block.transactions));
+        block.transactions.truncate(3);
+        let root = block.update_merkle_incremental(&mut cache, &[]);
+        assert_eq!(root, Block::calculate_merkle_root(&block.transactions));
+        assert_eq!(cache.len(), 3);
+    }
+
     #[test]
     fn test_hash_to_target() {
         let target1 = Block::hash_to_target(&8);
@@ -705,6 +1853,13 @@ mod tests {
         assert_eq!(Blockchain::calculate_block_reward(420000), 12.5);
         // Test far in the future
         assert_eq!(Blockchain::calculate_block_reward(210000 * 64), 0.0);
+        // The subsidies sum to about 21M on top of the genesis supply
+        let issued = Blockchain::max_supply() - GENESIS_SUPPLY;
+        assert!((issued - 21_000_000.0).abs() < 100.0, "{}", issued);
+        assert_eq!(
+            Blockchain::max_coinbase_reward(210000, Coord::from_num(2)),
+            Coord::from_num(27)
+        );
     }
 
     #[test]
@@ -719,7 +1874,7 @@ mod tests {
         });
 
         let block = Block::new(1, last_block.hash(), 1, vec![tx]);
-        let mined_block = mine_block(block).unwrap();
+        let mined_block = seal_block(&blockchain, block);
 
         let result = blockchain.apply_block(mined_block);
         assert!(result.is_ok());
@@ -730,6 +1885,278 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_total_fees() {
+        let mut transfer = TransferTx::new(
+            [1; 32],
+            create_test_address("bob"),
+            create_test_address("alice"),
+            Coord::from_num(10.0),
+            Coord::from_num(2.5),
+            0,
+        );
+        let tx1 = Transaction::Transfer(transfer.clone());
+        transfer.fee_area = Coord::from_num(1.5);
+        let tx2 = Transaction::Transfer(transfer);
+
+        let fees = Blockchain::total_fees(&[create_test_transaction(7), tx1, tx2]);
+        assert_eq!(fees, Coord::from_num(4.0));
+    }
+
+    #[test]
+    fn test_supply_stats() {
+        let mut blockchain = create_test_blockchain();
+        let point = |x: f64, y: f64| Point::new(Coord::from_num(x), Coord::from_num(y));
+        for (i, owner) in ["alice", "alice", "bob"].iter().enumerate() {
+            let triangle = Triangle::new_with_value(
+                point(0.0, 0.0),
+                point(1.0, 0.0),
+                point(0.0, i as f64 + 1.0),
+                None,
+                create_test_address(owner),
+                Coord::from_num(10),
+            );
+            blockchain.state.utxo_set.insert(triangle.hash(), triangle);
+        }
+
+        let stats = blockchain.supply_stats(2);
+        assert_eq!(stats.circulating_area, Coord::from_num(1_000_030));
+        assert_eq!(stats.total_triangles, 4);
+        assert_eq!(stats.distinct_owners, 3);
+        assert_eq!(stats.top_holders.len(), 2);
+        assert_eq!(
+            stats.top_holders[0].address,
+            hex::encode(create_test_address("miner1"))
+        );
+        assert_eq!(
+            stats.top_holders[1],
+            Holder {
+                address: hex::encode(create_test_address("alice")),
+                balance: Coord::from_num(20),
+                triangles: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_second_coinbase_rejected() {
+        let mut blockchain = Blockchain::new(create_test_address("miner"), 1).unwrap();
+        let genesis_hash = blockchain.blocks[0].hash();
+        let coinbase = |reward: f64, name: &str| {
+            Transaction::Coinbase(CoinbaseTx {
+                reward_area: Coord::from_num(reward),
+                beneficiary_address: create_test_address(name),
+                nonce: 0,
+            })
+        };
+
+        let block = Block::new(
+            1,
+            genesis_hash,
+            1,
+            vec![coinbase(50.0, "miner"), coinbase(1000.0, "extra")],
+        );
+        let result = blockchain.apply_block(seal_block(&blockchain, block));
+        assert!(
+            matches!(result, Err(ChainError::InvalidBlock(msg)) if msg.contains("only the first"))
+        );
+
+        // The first coinbase is checked on its own terms too
+        let burn = Transaction::Coinbase(CoinbaseTx {
+            reward_area: Coord::from_num(50.0),
+            beneficiary_address: Address::ZERO,
+            nonce: 0,
+        });
+        let unpaid = Block::new(1, genesis_hash, 1, vec![burn]);
+        let result = blockchain.apply_block(seal_block(&blockchain, unpaid));
+        assert!(matches!(result, Err(ChainError::InvalidTransaction(_))));
+
+        let block = Block::new(1, genesis_hash, 1, vec![coinbase(50.0, "miner")]);
+        blockchain
+            .apply_block(seal_block(&blockchain, block))
+            .unwrap();
+        assert_eq!(blockchain.blocks.len(), 2);
+    }
+
+    #[test]
+    fn test_duplicate_coinbase_rejected() {
+        use crate::fixtures::mine_next_block;
+
+        let miner = create_test_address("miner");
+        let mut blockchain = Blockchain::new(miner, 1).unwrap();
+
+        // Block 1's coinbase carries the nonce block 2's defaults to
+        let coinbase = Transaction::Coinbase(CoinbaseTx {
+            reward_area: Blockchain::max_coinbase_reward(1, Coord::from_num(0)),
+            beneficiary_address: miner,
+            nonce: 2,
+        });
+        let block = Block::new(1, blockchain.blocks[0].hash(), 1, vec![coinbase.clone()]);
+        blockchain
+            .apply_block(seal_block(&blockchain, block))
+            .unwrap();
+        let balance = blockchain.state.get_balance(&miner);
+
+        let block = mine_next_block(&blockchain, miner, vec![]);
+        assert_eq!(block.transactions[0].hash(), coinbase.hash());
+        let result = blockchain.apply_block(block);
+        assert!(
+            matches!(result, Err(ChainError::InvalidTransaction(msg)) if msg.contains("duplicates"))
+        );
+        assert_eq!(blockchain.blocks.len(), 2);
+        assert_eq!(blockchain.state.get_balance(&miner), balance);
+        assert!(blockchain.state.utxo_set.contains_key(&coinbase.hash()));
+    }
+
+    #[test]
+    fn test_coinbase_collects_fees() {
+        let keypair = crate::crypto::KeyPair::generate().unwrap();
+        let sender = keypair.address();
+        let mut blockchain = Blockchain::new(sender, 1).unwrap();
+        let genesis = blockchain.blocks[0].clone();
+
+        let mut transfer = TransferTx::new(
+            genesis.transactions[0].hash(),
+            create_test_address("recipient"),
+            sender,
+            Coord::from_num(100.0),
+            Coord::from_num(5.0),
+            0,
+        );
+        let signature = keypair.sign(&transfer.signable_message()).unwrap();
+        transfer.sign(signature.to_vec(), keypair.public_key_bytes().to_vec());
+        let transfer = Transaction::Transfer(transfer);
+
+        let coinbase_with = |reward: Coord| {
+            Transaction::Coinbase(CoinbaseTx {
+                reward_area: reward,
+                beneficiary_address: create_test_address("miner2"),
+                nonce: 0,
+            })
+        };
+        let allowed = Blockchain::max_coinbase_reward(1, Coord::from_num(5.0));
+        assert_eq!(allowed, Coord::from_num(55.0));
+
+        // Claiming more than subsidy + fees is rejected
+        let greedy = Block::new(
+            1,
+            genesis.hash(),
+            1,
+            vec![coinbase_with(allowed + Coord::from_num(1.0)), transfer.clone()],
+        );
+        let result = blockchain.apply_block(seal_block(&blockchain, greedy));
+        assert!(matches!(result, Err(ChainError::InvalidBlock(msg)) if msg.contains("Coinbase reward")));
+
+        // Claiming exactly subsidy + fees is accepted
+        let block = Block::new(1, genesis.hash(), 1, vec![coinbase_with(allowed), transfer]);
+        blockchain
+            .apply_block(seal_block(&blockchain, block))
+            .unwrap();
+        assert_eq!(
+            blockchain.state.get_balance(&create_test_address("miner2")),
+            allowed
+        );
+    }
+
+    #[test]
+    fn test_apply_block_batch_transfer() {
+        let keypair = crate::crypto::KeyPair::generate().unwrap();
+        let sender = keypair.address();
+        let mut blockchain = Blockchain::new(sender, 1).unwrap();
+        let input_hash = blockchain.blocks[0].transactions[0].hash();
+        let input_value = blockchain.state.utxo_set[&input_hash].effective_value();
+        let (alice, bob) = (create_test_address("alice"), create_test_address("bob"));
+
+        let batch = crate::fixtures::signed_batch_transfer(
+            &keypair,
+            input_hash,
+            &[(alice, Coord::from_num(100)), (bob, Coord::from_num(50))],
+            Coord::from_num(5),
+            0,
+        );
+        let tx_hash = batch.hash();
+        let miner = create_test_address("miner2");
+        let block = crate::fixtures::mine_next_block(&blockchain, miner, vec![batch]);
+        blockchain.apply_block(block).unwrap();
+
+        assert!(!blockchain.state.utxo_set.contains_key(&input_hash));
+        let output = |index| {
+            let hash = TriangleState::batch_output_hash(&tx_hash, index);
+            let triangle = &blockchain.state.utxo_set[&hash];
+            (triangle.owner, triangle.effective_value())
+        };
+        assert_eq!(output(0), (alice, Coord::from_num(100)));
+        assert_eq!(output(1), (bob, Coord::from_num(50)));
+        let change = input_value - Coord::from_num(155);
+        assert_eq!(output(2), (sender, change));
+        assert_eq!(blockchain.state.get_balance(&bob), Coord::from_num(50));
+        assert_eq!(blockchain.state.get_balance(&sender), change);
+    }
+
+    #[test]
+    fn test_coinbase_maturity() {
+        use crate::fixtures::{mine_next_block, signed_transfer};
+
+        let keypair = crate::crypto::KeyPair::generate().unwrap();
+        let mut blockchain = Blockchain::new(create_test_address("genesis"), 1).unwrap();
+        let block = mine_next_block(&blockchain, keypair.address(), vec![]);
+        let reward = block.transactions[0].hash();
+        blockchain.apply_block(block).unwrap();
+        let mature_at = 1 + COINBASE_MATURITY;
+        assert_eq!(blockchain.state.spendable_at(&reward, 2), Some(mature_at));
+        assert!(blockchain.state.immature_outputs(2).contains(&reward));
+
+        let spend = |nonce| {
+            signed_transfer(
+                &keypair,
+                reward,
+                create_test_address("payee"),
+                Coord::from_num(1),
+                Coord::from_num(0),
+                nonce,
+            )
+        };
+        while blockchain.blocks.len() < mature_at as usize - 1 {
+            let block = mine_next_block(&blockchain, create_test_address("miner"), vec![]);
+            blockchain.apply_block(block).unwrap();
+        }
+        let early = mine_next_block(&blockchain, create_test_address("miner"), vec![spend(0)]);
+        let err = blockchain.apply_block(early).unwrap_err();
+        assert!(matches!(err, ChainError::ImmatureCoinbase(_)), "{}", err);
+
+        let block = mine_next_block(&blockchain, create_test_address("miner"), vec![]);
+        blockchain.apply_block(block).unwrap();
+        assert_eq!(blockchain.state.spendable_at(&reward, mature_at), None);
+
+        // A reloaded chain recovers the same maturity windows
+        let mut rebuilt = blockchain.state.clone();
+        rebuilt.coinbase_heights.clear();
+        rebuilt.rebuild_coinbase_heights(&blockchain.blocks);
+        assert_eq!(rebuilt.coinbase_heights, blockchain.state.coinbase_heights);
+
+        let block = mine_next_block(&blockchain, create_test_address("miner"), vec![spend(1)]);
+        blockchain.apply_block(block).unwrap();
+        assert!(!blockchain.state.utxo_set.contains_key(&reward));
+        assert!(!blockchain.state.coinbase_heights.contains_key(&reward));
+
+        // The payment and its change are one block deep
+        let payee = create_test_address("payee");
+        let owned: Vec<_> = blockchain
+            .state
+            .utxo_set
+            .iter()
+            .filter(|(_, t)| t.owner == keypair.address() || t.owner == payee)
+            .collect();
+        assert_eq!(owned.len(), 2);
+        let confirmations =
+            blockchain.recent_confirmations(owned.iter().copied(), SAFE_CONFIRMATIONS);
+        assert_eq!(confirmations.len(), 2);
+        assert!(confirmations.values().all(|&count| count == 1));
+        assert!(blockchain
+            .recent_confirmations(owned.iter().copied(), 0)
+            .is_empty());
+    }
+
     #[test]
     fn test_apply_block_invalid_height() {
         let mut blockchain = create_test_blockchain();
@@ -741,6 +2168,16 @@ mod tests {
         assert!(matches!(result, Err(ChainError::InvalidBlock(_))));
     }
 
+    #[test]
+    fn test_apply_block_orphan() {
+        let mut blockchain = create_test_blockchain();
+
+        let block = Block::new(3, [7; 32], 1, vec![]); // Parent unknown
+
+        let result = blockchain.apply_block(block);
+        assert!(matches!(result, Err(ChainError::OrphanBlock)));
+    }
+
     #[test]
     fn test_apply_block_invalid_prev_hash() {
         let mut blockchain = create_test_blockchain();
@@ -757,6 +2194,7 @@ mod tests {
         let last_block = blockchain.blocks.last().unwrap().clone();
 
         // Block without mining
+        blockchain.difficulty = 20;
         let block = Block::new(1, last_block.hash(), 20, vec![]); // High difficulty
 
         let result = blockchain.apply_block(block);
@@ -765,6 +2203,110 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_header_difficulty_must_match_chain() {
+        use crate::fixtures::mine_next_block;
+
+        let miner = create_test_address("miner");
+        let mut blockchain = Blockchain::new(miner, 1).unwrap();
+
+        // Mined honestly against an easier target than the chain expects
+        let mut easy = blockchain.clone();
+        easy.difficulty = 0;
+        let lying = mine_next_block(&easy, miner, vec![]);
+        let err = blockchain.apply_block(lying).unwrap_err();
+        assert!(err.to_string().contains("expected difficulty 1"), "{}", err);
+
+        // Once the first interval closes, a block still claiming the old
+        // difficulty is rejected
+        while blockchain.blocks.len() <= DIFFICULTY_ADJUSTMENT_INTERVAL as usize {
+            let block = mine_next_block(&blockchain, miner, vec![]);
+            assert_eq!(block.header.difficulty, 1);
+            blockchain.apply_block(block).unwrap();
+        }
+        assert_ne!(blockchain.difficulty, 1);
+        let mut stale = blockchain.clone();
+        stale.difficulty = 1;
+        let err = blockchain
+            .apply_block(mine_next_block(&stale, miner, vec![]))
+            .unwrap_err();
+        assert!(err.to_string().contains("Block difficulty 1"), "{}", err);
+        blockchain
+            .apply_block(mine_next_block(&blockchain, miner, vec![]))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_apply_block_timestamp_bounds() {
+        let mut blockchain = create_test_blockchain();
+        let genesis = blockchain.blocks.last().unwrap().clone();
+        assert_eq!(blockchain.median_time_past(), genesis.header.timestamp);
+
+        // Not after the median time past
+        let mut block = Block::new(1, genesis.hash(), 1, vec![]);
+        block.header.timestamp = genesis.header.timestamp;
+        let result = blockchain.apply_block(seal_block(&blockchain, block));
+        assert!(matches!(result, Err(ChainError::InvalidBlock(msg)) if msg.contains("median")));
+
+        // More than two hours ahead of the clock
+        let mut block = Block::new(1, genesis.hash(), 1, vec![]);
+        block.header.timestamp += MAX_FUTURE_BLOCK_TIME_MS + 60_000;
+        let result = blockchain.apply_block(seal_block(&blockchain, block));
+        assert!(matches!(result, Err(ChainError::FutureBlock)));
+
+        // Earlier than its parent is fine while it beats the median
+        let mut parent = genesis.header.timestamp;
+        for height in 1..=3 {
+            let mut block = Block::new(height, blockchain.blocks.last().unwrap().hash(), 1, vec![]);
+            block.header.timestamp = parent + 1_000;
+            parent = block.header.timestamp;
+            blockchain
+                .apply_block(seal_block(&blockchain, block))
+                .unwrap();
+        }
+        let mut block = Block::new(4, blockchain.blocks.last().unwrap().hash(), 1, vec![]);
+        block.header.timestamp = parent - 500;
+        blockchain
+            .apply_block(seal_block(&blockchain, block))
+            .unwrap();
+        assert_eq!(
+            blockchain.median_time_past(),
+            genesis.header.timestamp + 2_000
+        );
+    }
+
+    #[test]
+    fn test_apply_block_checks_state_root() {
+        let mut blockchain = create_test_blockchain();
+        let miner = create_test_address("miner2");
+        let block = crate::fixtures::mine_next_block(&blockchain, miner, vec![]);
+        assert_ne!(block.header.state_root, [0; 32]);
+
+        let mut wrong = block.clone();
+        wrong.header.state_root = [9; 32];
+        let result = blockchain.apply_block(mine_block(wrong).unwrap());
+        assert!(matches!(result, Err(ChainError::InvalidBlock(msg)) if msg.contains("State root")));
+
+        // Leaving out the commitment, as the genesis header does, is no way
+        // around it
+        let mut legacy = block.clone();
+        legacy.header.version = 0;
+        legacy.header.state_root = [0; 32];
+        let result = blockchain.apply_block(mine_block(legacy).unwrap());
+        assert!(matches!(result, Err(ChainError::InvalidBlock(msg)) if msg.contains("version 0")));
+
+        let mut unsigned = block.clone();
+        unsigned.header.state_root = [0; 32];
+        let result = blockchain.apply_block(mine_block(unsigned).unwrap());
+        assert!(matches!(result, Err(ChainError::InvalidBlock(msg)) if msg.contains("State root")));
+
+        blockchain.apply_block(block).unwrap();
+        assert_eq!(
+            blockchain.blocks[1].header.state_root,
+            blockchain.state.state_root()
+        );
+    }
+
     #[test]
     fn test_apply_block_double_spend_in_block() {
         let mut blockchain = create_test_blockchain();
@@ -804,13 +2346,89 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_apply_block_requires_owner_signature() {
+        use crate::crypto::KeyPair;
+        use crate::fixtures::{mine_next_block, signed_subdivision, signed_transfer};
+
+        let owner = KeyPair::generate().unwrap();
+        let thief = KeyPair::generate().unwrap();
+        let mut blockchain = Blockchain::new(owner.address(), 1).unwrap();
+        let (input_hash, input) = blockchain
+            .state
+            .utxo_set
+            .iter()
+            .next()
+            .map(|(h, t)| (*h, t.clone()))
+            .unwrap();
+        let recipient = create_test_address("recipient");
+
+        // Unsigned transfer
+        let mut unsigned = signed_transfer(
+            &owner,
+            input_hash,
+            recipient,
+            Coord::from_num(10),
+            Coord::from_num(0),
+            1,
+        );
+        if let Transaction::Transfer(tx) = &mut unsigned {
+            tx.signature = None;
+            tx.public_key = None;
+        }
+        let block = mine_next_block(&blockchain, owner.address(), vec![unsigned]);
+        assert!(blockchain.apply_block(block).is_err());
+
+        // Validly signed, but by a key that does not own the sender address
+        let mut forged = signed_transfer(
+            &thief,
+            input_hash,
+            recipient,
+            Coord::from_num(10),
+            Coord::from_num(0),
+            1,
+        );
+        if let Transaction::Transfer(tx) = &mut forged {
+            tx.sender = owner.address();
+            let signature = thief.sign(&tx.signable_message()).unwrap();
+            tx.sign(signature.to_vec(), thief.public_key_bytes().to_vec());
+        }
+        let block = mine_next_block(&blockchain, owner.address(), vec![forged]);
+        assert!(matches!(
+            blockchain.apply_block(block),
+            Err(ChainError::InvalidTransaction(msg)) if msg.contains("Public key does not belong")
+        ));
+
+        // Subdivision signed by someone other than the owner
+        let mut forged = signed_subdivision(&thief, input_hash, &input, Coord::from_num(0), 1);
+        if let Transaction::Subdivision(tx) = &mut forged {
+            tx.owner_address = owner.address();
+        }
+        let block = mine_next_block(&blockchain, owner.address(), vec![forged]);
+        assert!(blockchain.apply_block(block).is_err());
+        assert_eq!(blockchain.blocks.len(), 1);
+
+        // The owner's own signature is accepted
+        let transfer = signed_transfer(
+            &owner,
+            input_hash,
+            recipient,
+            Coord::from_num(10),
+            Coord::from_num(1),
+            1,
+        );
+        let block = mine_next_block(&blockchain, owner.address(), vec![transfer]);
+        blockchain.apply_block(block).unwrap();
+        assert_eq!(blockchain.state.get_balance(&recipient), Coord::from_num(10));
+    }
+
     #[test]
     fn test_state_apply_coinbase_tx() {
         let mut state = TriangleState::new();
         let address = create_test_address("miner");
         let tx = Transaction::Coinbase(CoinbaseTx {
             reward_area: Coord::from_num(100.0),
-            beneficiary_address: address.clone(),
+            beneficiary_address: address,
             nonce: 0,
         });
 
@@ -832,20 +2450,20 @@ mod tests {
             Point::new(Coord::from_num(10), Coord::from_num(0)),
             Point::new(Coord::from_num(5), Coord::from_num(10)),
             None,
-            sender.clone(),
+            sender,
         )
         .with_effective_value(Coord::from_num(1000.0));
         let input_hash = initial_triangle.hash();
         state.utxo_set.insert(input_hash, initial_triangle);
         state
             .address_balances
-            .insert(sender.clone(), Coord::from_num(1000.0));
+            .insert(sender, Coord::from_num(1000.0));
 
         // 2. Create and apply the transfer
         let tx = Transaction::Transfer(TransferTx {
             input_hash,
-            new_owner: recipient.clone(),
-            sender: sender.clone(),
+            new_owner: recipient,
+            sender,
             amount: Coord::from_num(700.0),
             fee_area: Coord::from_num(50.0),
             nonce: 0,
@@ -895,12 +2513,12 @@ mod tests {
             Point::new(Coord::from_num(20), Coord::from_num(0)),
             Point::new(Coord::from_num(10), Coord::from_num(10)),
             None,
-            owner.clone(),
+            owner,
         ); // Area = 100
         let parent_hash = parent_triangle.hash();
         let parent_value = parent_triangle.effective_value();
         state.utxo_set.insert(parent_hash, parent_triangle.clone());
-        state.address_balances.insert(owner.clone(), parent_value);
+        state.address_balances.insert(owner, parent_value);
 
         // 2. Define fee and calculate the total value for the children.
         let fee = Coord::from_num(10.0);
@@ -922,7 +2540,7 @@ mod tests {
         // 4. Create and apply the subdivision transaction.
         let subdivision_tx = SubdivisionTx {
             parent_hash,
-            owner_address: owner.clone(),
+            owner_address: owner,
             children,
             fee_area: fee,
             nonce: 0,
@@ -964,4 +2582,133 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_verify_full_reports_corruption() {
+        use crate::fixtures::mine_next_block;
+
+        let miner = create_test_address("miner");
+        let mut chain = Blockchain::new(miner, 1).unwrap();
+        for _ in 0..4 {
+            let block = mine_next_block(&chain, miner, vec![]);
+            chain.apply_block(block).unwrap();
+        }
+        let mut checked = Vec::new();
+        let report = chain.verify_full(|height| checked.push(height)).unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.blocks_checked, 5);
+        assert_eq!(checked, vec![0, 1, 2, 3, 4]);
+
+        // A rewritten coinbase no longer matches the merkle root, and the
+        // loaded UTXO set lost a triangle
+        if let Transaction::Coinbase(coinbase) = &mut chain.blocks[2].transactions[0] {
+            coinbase.beneficiary_address = create_test_address("thief");
+        }
+        let spent = chain.blocks[3].transactions[0].hash();
+        chain.state.utxo_set.remove(&spent);
+
+        let report = chain.verify_full(|_| {}).unwrap();
+        assert!(!report.is_ok());
+        assert_eq!(report.blocks_checked, 5);
+        // Later state roots committed to the original output too
+        assert_eq!(report.issues[0].height, 2);
+        assert!(report.issues[0].reason.contains("Merkle root mismatch"));
+        assert!(report.issues[1..]
+            .iter()
+            .all(|issue| issue.reason.contains("State root mismatch")));
+        assert!(!report.utxo_set_matches);
+    }
+
+    #[test]
+    fn test_reorganize_resubmits_disconnected_transactions() {
+        use crate::crypto::KeyPair;
+        use crate::fixtures::{mine_next_block, signed_transfer};
+
+        let owner = KeyPair::generate().unwrap();
+        let mut chain = Blockchain::new(owner.address(), 1).unwrap();
+        let fork = chain.clone();
+        let (input_hash, _) = chain.state.utxo_set.iter().next().map(|(h, t)| (*h, t.clone())).unwrap();
+        let payment = signed_transfer(
+            &owner,
+            input_hash,
+            create_test_address("recipient"),
+            Coord::from_num(10),
+            Coord::from_num(0),
+            1,
+        );
+        let block = mine_next_block(&chain, owner.address(), vec![payment.clone()]);
+        chain.apply_block(block).unwrap();
+
+        // A longer branch from genesis that does not include the payment
+        let mut other = fork.clone();
+        let mut branch = Vec::new();
+        for _ in 0..2 {
+            let block = mine_next_block(&other, create_test_address("rival"), vec![]);
+            other.apply_block(block.clone()).unwrap();
+            branch.push(block);
+        }
+
+        // Not longer than the main chain
+        assert!(chain.reorganize(branch[..1].to_vec()).is_err());
+
+        let outcome = chain.reorganize(branch.clone()).unwrap();
+        assert_eq!(outcome.fork_height, 0);
+        assert_eq!(outcome.disconnected.len(), 1);
+        assert_eq!(outcome.resubmitted.len(), 1);
+        assert_eq!(chain.blocks.last().unwrap().hash(), branch[1].hash());
+        assert_eq!(chain.state.utxo_set.len(), other.state.utxo_set.len());
+        assert!(chain.mempool.get_transaction(&payment.hash()).is_some());
+
+        // The block index follows the new branch
+        let disconnected = outcome.disconnected[0].hash();
+        assert!(chain.get_block_by_hash(&disconnected).is_none());
+        let tip = chain.get_block_by_hash(&branch[1].hash()).unwrap();
+        assert_eq!(tip.header.height, 2);
+        assert_eq!(
+            chain.get_block_by_height(1).unwrap().hash(),
+            branch[0].hash()
+        );
+        assert!(chain.get_block_by_height(3).is_none());
+    }
+
+    #[test]
+    fn test_reorganize_drops_conflicting_transactions() {
+        use crate::crypto::KeyPair;
+        use crate::fixtures::{mine_next_block, signed_transfer};
+
+        let owner = KeyPair::generate().unwrap();
+        let mut chain = Blockchain::new(owner.address(), 1).unwrap();
+        let mut other = chain.clone();
+        let (input_hash, _) = chain.state.utxo_set.iter().next().map(|(h, t)| (*h, t.clone())).unwrap();
+        let pay = |to: &str, nonce| {
+            signed_transfer(
+                &owner,
+                input_hash,
+                create_test_address(to),
+                Coord::from_num(10),
+                Coord::from_num(0),
+                nonce,
+            )
+        };
+
+        let block = mine_next_block(&chain, owner.address(), vec![pay("alice", 1)]);
+        chain.apply_block(block).unwrap();
+        let before = chain.blocks.clone();
+
+        // The rival branch spends the same triangle elsewhere
+        let mut branch = vec![mine_next_block(&other, owner.address(), vec![pay("bob", 2)])];
+        other.apply_block(branch[0].clone()).unwrap();
+        branch.push(mine_next_block(&other, owner.address(), vec![]));
+
+        // A corrupted branch is rejected without touching the chain
+        let mut corrupted = branch.clone();
+        corrupted[1].header.merkle_root = [0; 32];
+        assert!(chain.reorganize(corrupted).is_err());
+        assert_eq!(chain.blocks.len(), before.len());
+
+        let outcome = chain.reorganize(branch).unwrap();
+        assert!(outcome.resubmitted.is_empty());
+        assert_eq!(outcome.dropped.len(), 1);
+        assert!(chain.mempool.is_empty());
+    }
 }
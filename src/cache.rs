@@ -4,13 +4,21 @@
 //! - Recent blocks (100 block limit)
 //! - UTXO set entries (hot triangles)
 //! - Address balances
-use crate::blockchain::{Block, Sha256Hash};
-use crate::geometry::Triangle;
+//! - Block validation outcomes
+//!
+//! Every cache counts its hits and misses. [`BlockchainCache::follow`]
+//! keeps the block, UTXO and balance caches consistent with the chain by
+//! invalidating entries as [`ChainEvent`]s arrive.
+use crate::blockchain::{Block, Sha256Hash, TriangleState};
+use crate::events::ChainEvent;
+use crate::geometry::{Coord, Triangle};
 use lru::LruCache;
+use serde::Serialize;
 use std::collections::HashMap;
 use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
 use std::marker::PhantomData;
 
 // Re-export for convenience in implementing the trait bounds
@@ -20,10 +28,10 @@ pub use std::hash::Hash;
 /// It uses `std::borrow::Borrow` to allow getting/removing elements without cloning the key.
 pub trait CacheInner<K, V> {
     /// Retrieve a value from the cache without mutating the cache's state (e.g., LruCache promotion).
-    fn get_non_mut<Q: ?Sized>(&self, key: &Q) -> Option<&V>
+    fn get_non_mut<Q>(&self, key: &Q) -> Option<&V>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Hash + Eq;
+        Q: Hash + Eq + ?Sized;
 
     /// Insert a value into the cache.
     fn put(&mut self, key: K, value: V);
@@ -40,10 +48,10 @@ pub trait CacheInner<K, V> {
     }
 
     /// Remove a value from the cache.
-    fn remove<Q: ?Sized>(&mut self, key: &Q) -> Option<V>
+    fn remove<Q>(&mut self, key: &Q) -> Option<V>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Hash + Eq;
+        Q: Hash + Eq + ?Sized;
 }
 
 impl<K, V> CacheInner<K, V> for LruCache<K, V>
@@ -51,10 +59,10 @@ where
     K: Hash + Eq + Clone,
     V: Clone,
 {
-    fn get_non_mut<Q: ?Sized>(&self, key: &Q) -> Option<&V>
+    fn get_non_mut<Q>(&self, key: &Q) -> Option<&V>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Hash + Eq,
+        Q: Hash + Eq + ?Sized,
     {
         // Use the non-mutating peek for read operations
         self.peek(key)
@@ -72,10 +80,10 @@ where
         self.len()
     }
 
-    fn remove<Q: ?Sized>(&mut self, key: &Q) -> Option<V>
+    fn remove<Q>(&mut self, key: &Q) -> Option<V>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Hash + Eq,
+        Q: Hash + Eq + ?Sized,
     {
         self.pop(key)
     }
@@ -87,10 +95,10 @@ where
     K: Hash + Eq + Clone,
     V: Clone,
 {
-    fn get_non_mut<Q: ?Sized>(&self, key: &Q) -> Option<&V>
+    fn get_non_mut<Q>(&self, key: &Q) -> Option<&V>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Hash + Eq,
+        Q: Hash + Eq + ?Sized,
     {
         // HashMap get doesn't mutate, so it's the direct implementation
         self.get(key)
@@ -108,18 +116,43 @@ where
         self.len()
     }
 
-    fn remove<Q: ?Sized>(&mut self, key: &Q) -> Option<V>
+    fn remove<Q>(&mut self, key: &Q) -> Option<V>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Hash + Eq,
+        Q: Hash + Eq + ?Sized,
     {
         self.remove(key)
     }
 }
 
+/// Size and hit/miss counts of one cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    /// `None` for caches without eviction
+    pub capacity: Option<usize>,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// Fraction of lookups answered from the cache (0 before any lookup)
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
 /// Generic, thread-safe cache wrapper using RwLock.
 pub struct ThreadSafeCache<K, V, T: CacheInner<K, V>> {
     cache: Arc<RwLock<T>>,
+    /// Lookups through `get` that found / did not find an entry, shared by clones
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
     _phantom_k: PhantomData<K>, // Marker for K
     _phantom_v: PhantomData<V>, // Marker for V
 }
@@ -135,6 +168,8 @@ where
     pub fn new_default() -> Self {
         Self {
             cache: Arc::new(RwLock::new(T::default())),
+            hits: Arc::new(AtomicU64::new(0)),
+            misses: Arc::new(AtomicU64::new(0)),
             _phantom_k: PhantomData,
             _phantom_v: PhantomData,
         }
@@ -154,6 +189,8 @@ where
         let cache = LruCache::new(capacity_nz);
         Self {
             cache: Arc::new(RwLock::new(cache)),
+            hits: Arc::new(AtomicU64::new(0)),
+            misses: Arc::new(AtomicU64::new(0)),
             _phantom_k: PhantomData,
             _phantom_v: PhantomData,
         }
@@ -164,6 +201,14 @@ where
         let cache = self.cache.read().await;
         cache.cap().get()
     }
+
+    /// Size, capacity and hit/miss counts
+    pub async fn cache_stats(&self) -> CacheStats {
+        CacheStats {
+            capacity: Some(self.capacity().await),
+            ..self.counts().await
+        }
+    }
 }
 
 impl<K, V, T: CacheInner<K, V>> ThreadSafeCache<K, V, T>
@@ -172,13 +217,30 @@ where
     V: Clone,
 {
     /// Get a value from cache. Uses read lock for non-mutating access.
-    pub async fn get<Q: ?Sized>(&self, key: &Q) -> Option<V>
+    pub async fn get<Q>(&self, key: &Q) -> Option<V>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Hash + Eq,
+        Q: Hash + Eq + ?Sized,
     {
         let cache = self.cache.read().await;
-        cache.get_non_mut(key).cloned()
+        let value = cache.get_non_mut(key).cloned();
+        let counter = if value.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    /// Size and hit/miss counts, without a capacity
+    async fn counts(&self) -> CacheStats {
+        CacheStats {
+            entries: self.len().await,
+            capacity: None,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
     }
 
     /// Put a value in cache. Uses write lock.
@@ -188,10 +250,10 @@ where
     }
 
     /// Remove a value from cache. Uses write lock.
-    pub async fn remove<Q: ?Sized>(&self, key: &Q) -> Option<V>
+    pub async fn remove<Q>(&self, key: &Q) -> Option<V>
     where
         K: std::borrow::Borrow<Q>,
-        Q: Hash + Eq,
+        Q: Hash + Eq + ?Sized,
     {
         let mut cache = self.cache.write().await;
         cache.remove(key)
@@ -220,6 +282,8 @@ impl<K, V, T: CacheInner<K, V>> Clone for ThreadSafeCache<K, V, T> {
     fn clone(&self) -> Self {
         Self {
             cache: Arc::clone(&self.cache),
+            hits: Arc::clone(&self.hits),
+            misses: Arc::clone(&self.misses),
             _phantom_k: PhantomData,
             _phantom_v: PhantomData,
         }
@@ -294,6 +358,25 @@ impl BalanceCache {
     pub async fn size(&self) -> usize {
         self.len().await
     }
+
+    /// Cached confirmed balance of `address`, kept as raw `Coord` bits
+    pub async fn get_area(&self, address: &str) -> Option<Coord> {
+        self.get_balance(address)
+            .await
+            .map(|bits| Coord::from_bits(bits as i64))
+    }
+
+    /// Cache a confirmed balance; negative balances are never cached
+    pub async fn set_area(&self, address: String, balance: Coord) {
+        if let Ok(bits) = u64::try_from(balance.to_bits()) {
+            self.set(address, bits).await;
+        }
+    }
+
+    /// Size and hit/miss counts
+    pub async fn cache_stats(&self) -> CacheStats {
+        self.counts().await
+    }
 }
 
 impl Default for BalanceCache {
@@ -302,12 +385,56 @@ impl Default for BalanceCache {
     }
 }
 
+/// Outcome of fully validating a block against its parent state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockVerdict {
+    /// The block validated and was connected
+    Valid,
+    /// The block failed validation, with the reason
+    Invalid(String),
+}
+
+/// Cache of block validation outcomes keyed by block hash, so a block
+/// relayed by several peers is only validated once
+pub type ValidationCache =
+    ThreadSafeCache<Sha256Hash, BlockVerdict, LruCache<Sha256Hash, BlockVerdict>>;
+
+impl ValidationCache {
+    pub const DEFAULT_CAPACITY: usize = 1000;
+
+    pub fn new(capacity: usize) -> Self {
+        Self::new_lru(capacity)
+    }
+
+    pub async fn record_valid(&self, hash: Sha256Hash) {
+        self.put(hash, BlockVerdict::Valid).await;
+    }
+
+    pub async fn record_invalid(&self, hash: Sha256Hash, reason: &str) {
+        self.put(hash, BlockVerdict::Invalid(reason.to_string())).await;
+    }
+
+    /// Previously recorded outcome for a block, if any
+    pub async fn verdict(&self, hash: &Sha256Hash) -> Option<BlockVerdict> {
+        self.get(hash).await
+    }
+}
 
 /// Combined cache for all blockchain data
 pub struct BlockchainCache {
     pub blocks: BlockCache,
     pub utxo: UtxoCache,
     pub balances: BalanceCache,
+    pub validation: ValidationCache,
+}
+
+/// Size and hit/miss counts of every cache in a [`BlockchainCache`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct BlockchainCacheStats {
+    pub blocks: CacheStats,
+    pub utxo: CacheStats,
+    pub balances: CacheStats,
+    pub validation: CacheStats,
 }
 
 impl BlockchainCache {
@@ -317,6 +444,7 @@ impl BlockchainCache {
             blocks: BlockCache::new(block_capacity),
             utxo: UtxoCache::new(utxo_capacity),
             balances: BalanceCache::new(),
+            validation: ValidationCache::new(ValidationCache::DEFAULT_CAPACITY),
         }
     }
 
@@ -326,6 +454,7 @@ impl BlockchainCache {
             blocks: BlockCache::new(BlockCache::DEFAULT_CAPACITY),
             utxo: UtxoCache::new(UtxoCache::DEFAULT_CAPACITY),
             balances: BalanceCache::new_default(),
+            validation: ValidationCache::new(ValidationCache::DEFAULT_CAPACITY),
         }
     }
 
@@ -334,6 +463,64 @@ impl BlockchainCache {
         self.blocks.clear().await;
         self.utxo.clear().await;
         self.balances.invalidate_all().await;
+        self.validation.clear().await;
+    }
+
+    pub async fn stats(&self) -> BlockchainCacheStats {
+        BlockchainCacheStats {
+            blocks: self.blocks.cache_stats().await,
+            utxo: self.utxo.cache_stats().await,
+            balances: self.balances.cache_stats().await,
+            validation: self.validation.cache_stats().await,
+        }
+    }
+
+    /// The unspent triangle `hash`, from the UTXO cache or else from
+    /// `state` (and then cached). Entries are dropped as blocks spend them.
+    pub async fn utxo_lookup(&self, state: &TriangleState, hash: &Sha256Hash) -> Option<Triangle> {
+        if let Some(triangle) = self.utxo.get(hash).await {
+            return Some(triangle);
+        }
+        let triangle = state.utxo_set.get(hash)?.clone();
+        self.utxo.put(*hash, triangle.clone()).await;
+        Some(triangle)
+    }
+
+    /// Drop the entries `event` made stale. Blocks only ever spend cached
+    /// triangles and change the balances of the addresses they touch; a
+    /// disconnected block also removes its outputs, so the UTXO cache is
+    /// cleared then.
+    pub async fn apply_event(&self, event: &ChainEvent) {
+        let block = match event {
+            ChainEvent::BlockConnected(block) => block,
+            ChainEvent::BlockDisconnected(block) => {
+                self.blocks.remove(&block.hash()).await;
+                self.utxo.clear().await;
+                block
+            }
+            ChainEvent::Reorganized { .. } | ChainEvent::TransactionAccepted(_) => return,
+        };
+        for tx in &block.transactions {
+            for address in tx.addresses() {
+                self.balances.invalidate(&hex::encode(address)).await;
+            }
+            if let Some(input) = tx.input_hash() {
+                self.utxo.remove(&input).await;
+            }
+        }
+    }
+
+    /// Apply every event from `events` until the chain goes away. A
+    /// subscriber that fell behind cannot tell what it missed, so the caches
+    /// are cleared.
+    pub async fn follow(self, mut events: broadcast::Receiver<ChainEvent>) {
+        loop {
+            match events.recv().await {
+                Ok(event) => self.apply_event(&event).await,
+                Err(broadcast::error::RecvError::Lagged(_)) => self.clear_all().await,
+                Err(broadcast::error::RecvError::Closed) => return,
+            }
+        }
     }
 }
 
@@ -343,6 +530,7 @@ impl Clone for BlockchainCache {
             blocks: self.blocks.clone(),
             utxo: self.utxo.clone(),
             balances: self.balances.clone(),
+            validation: self.validation.clone(),
         }
     }
 }
@@ -358,12 +546,14 @@ mod tests {
         let hash = [0u8; 32];
         let block = Block {
             header: BlockHeader {
+                version: 0,
                 height: 1,
                 previous_hash: [0; 32],
                 timestamp: 0,
                 difficulty: 1,
                 nonce: 0,
                 merkle_root: [0; 32],
+                state_root: [0; 32],
             },
             transactions: vec![],
         };
@@ -393,6 +583,77 @@ mod tests {
         assert!(balance.is_none());
     }
 
+    #[tokio::test]
+    async fn test_validation_cache() {
+        let cache = ValidationCache::new(10);
+        let (good, bad) = ([1u8; 32], [2u8; 32]);
+
+        assert_eq!(cache.verdict(&good).await, None);
+        cache.record_valid(good).await;
+        cache.record_invalid(bad, "Merkle root mismatch").await;
+
+        assert_eq!(cache.verdict(&good).await, Some(BlockVerdict::Valid));
+        assert_eq!(
+            cache.verdict(&bad).await,
+            Some(BlockVerdict::Invalid("Merkle root mismatch".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_events_invalidate_and_lookups_are_counted() {
+        use crate::blockchain::Blockchain;
+        use crate::crypto::{Address, KeyPair};
+        use crate::fixtures::{mine_next_block, signed_transfer};
+
+        let owner = KeyPair::generate().unwrap();
+        let mut chain = Blockchain::new(owner.address(), 1).unwrap();
+        let mut events = chain.subscribe_events();
+        let cache = BlockchainCache::new_default();
+
+        let input = *chain.state.utxo_set.keys().next().unwrap();
+        let owner_hex = hex::encode(owner.address());
+        let bystander_hex = hex::encode([9u8; 32]);
+        assert!(cache.utxo_lookup(&chain.state, &input).await.is_some());
+        assert!(cache.utxo_lookup(&chain.state, &input).await.is_some());
+        cache
+            .balances
+            .set_area(owner_hex.clone(), chain.state.get_balance(&owner.address()))
+            .await;
+        cache
+            .balances
+            .set_area(bystander_hex.clone(), Coord::from_num(7))
+            .await;
+
+        let payment = signed_transfer(
+            &owner,
+            input,
+            Address::from([2; 32]),
+            Coord::from_num(10),
+            Coord::from_num(0),
+            1,
+        );
+        let block = mine_next_block(&chain, owner.address(), vec![payment]);
+        chain.apply_block(block).unwrap();
+        while let Ok(event) = events.try_recv() {
+            cache.apply_event(&event).await;
+        }
+
+        // The spent input and the payer's balance are gone, others stay
+        assert!(cache.utxo.get(&input).await.is_none());
+        assert!(cache.balances.get_area(&owner_hex).await.is_none());
+        assert_eq!(
+            cache.balances.get_area(&bystander_hex).await,
+            Some(Coord::from_num(7))
+        );
+
+        let stats = cache.stats().await;
+        assert_eq!((stats.utxo.hits, stats.utxo.misses), (1, 2));
+        assert_eq!((stats.balances.hits, stats.balances.misses), (1, 1));
+        assert_eq!(stats.utxo.capacity, Some(UtxoCache::DEFAULT_CAPACITY));
+        assert_eq!(stats.balances.capacity, None);
+        assert!((stats.utxo.hit_rate() - 1.0 / 3.0).abs() < 1e-9);
+    }
+
     #[tokio::test]
     async fn test_utxo_cache_lru_eviction() {
         let cache = UtxoCache::new(5);
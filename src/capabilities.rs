@@ -0,0 +1,460 @@
+//! Capability tokens for the wallet API
+//!
+//! An operator holding the admin token mints scoped bearer tokens for
+//! integrations: view balances only, build unsigned transactions only, or
+//! send up to a daily limit. Only a SHA-256 digest of each token is stored,
+//! so a leaked token file does not leak usable credentials.
+//!
+//! Static API keys from `[[api.keys]]` in `config.toml` go through the same
+//! checks with a read-only or an admin scope, so a public explorer can serve
+//! read routes to anyone while mining control and transaction submission
+//! stay private.
+
+use crate::config::{ApiKeyConfig, ApiKeyScope};
+use crate::error::ChainError;
+use crate::geometry::Coord;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+const MAX_LABEL_LENGTH: usize = 64;
+/// Shortest static API key accepted from the config
+const MIN_API_KEY_LENGTH: usize = 16;
+
+/// What a token is allowed to do
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "scope", rename_all = "snake_case")]
+pub enum Capability {
+    /// Read-only routes (chain, mempool, network, balances)
+    ReadOnly,
+    /// Query balances, history and owned triangles
+    ViewBalance,
+    /// Build unsigned transactions for offline signing
+    CreateUnsigned,
+    /// Everything above, plus submitting transfers moving at most
+    /// `daily_limit` area (amount plus fee) per UTC day
+    Send { daily_limit: f64 },
+    /// Everything, including mining control and token administration,
+    /// without a send limit
+    Admin,
+}
+
+/// The permission an endpoint requires
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// Read-only routes, when the node does not serve them publicly
+    Read,
+    ViewBalance,
+    CreateUnsigned,
+    Send,
+    /// Starting and stopping mining, managing tokens
+    Control,
+}
+
+impl Capability {
+    /// Whether this capability satisfies `scope`
+    pub fn grants(&self, scope: Scope) -> bool {
+        match self {
+            Capability::ReadOnly => matches!(scope, Scope::Read | Scope::ViewBalance),
+            Capability::ViewBalance => matches!(scope, Scope::Read | Scope::ViewBalance),
+            Capability::CreateUnsigned => matches!(scope, Scope::Read | Scope::CreateUnsigned),
+            Capability::Send { .. } => scope != Scope::Control,
+            Capability::Admin => true,
+        }
+    }
+}
+
+/// A minted token as stored by the node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenRecord {
+    /// Public identifier used to list and revoke the token
+    pub id: String,
+    pub label: String,
+    pub capabilities: Vec<Capability>,
+    /// Unix time the token was minted
+    pub created_at: u64,
+    /// Unix time after which the token is rejected
+    pub expires_at: Option<u64>,
+    /// UTC day (days since the epoch) `spent_today` refers to
+    #[serde(default)]
+    pub spend_day: u64,
+    /// Area sent with this token on `spend_day`
+    #[serde(default)]
+    pub spent_today: f64,
+}
+
+impl TokenRecord {
+    fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|t| now >= t)
+    }
+
+    /// Highest daily send limit granted, if the token may send at all
+    pub fn daily_limit(&self) -> Option<f64> {
+        self.capabilities
+            .iter()
+            .filter_map(|c| match c {
+                Capability::Send { daily_limit } => Some(*daily_limit),
+                Capability::Admin => Some(f64::INFINITY),
+                _ => None,
+            })
+            .reduce(f64::max)
+    }
+}
+
+/// Minted capability tokens keyed by the hex SHA-256 of the secret
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TokenStore {
+    tokens: HashMap<String, TokenRecord>,
+}
+
+fn digest(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+impl TokenStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load tokens from `path`, or start empty if the file doesn't exist
+    pub fn load_or_new(path: &Path) -> Result<Self, ChainError> {
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+        let contents = fs::read_to_string(path).map_err(|e| {
+            ChainError::AuthenticationError(format!("Failed to read token store: {}", e))
+        })?;
+        serde_json::from_str(&contents).map_err(|e| {
+            ChainError::AuthenticationError(format!("Failed to parse token store: {}", e))
+        })
+    }
+
+    /// Atomically write tokens to `path`
+    pub fn save(&self, path: &Path) -> Result<(), ChainError> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| {
+            ChainError::AuthenticationError(format!("Failed to serialize token store: {}", e))
+        })?;
+
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let mut temp_file = NamedTempFile::new_in(dir)
+            .map_err(|e| ChainError::IoError(format!("Failed to create temp file: {}", e)))?;
+        write!(temp_file, "{}", json)
+            .map_err(|e| ChainError::IoError(format!("Failed to write to temp file: {}", e)))?;
+        temp_file
+            .persist(path)
+            .map_err(|e| ChainError::IoError(format!("Failed to persist token store: {}", e)))?;
+        Ok(())
+    }
+
+    /// Mint a token with the given capabilities. Returns the bearer secret,
+    /// which is not stored and cannot be recovered, and the stored record.
+    pub fn mint(
+        &mut self,
+        label: &str,
+        capabilities: Vec<Capability>,
+        ttl_secs: Option<u64>,
+        now: u64,
+    ) -> Result<(String, TokenRecord), ChainError> {
+        let label = label.trim();
+        if label.len() > MAX_LABEL_LENGTH {
+            return Err(ChainError::InvalidTransaction(format!(
+                "Token label too long (max {} characters)",
+                MAX_LABEL_LENGTH
+            )));
+        }
+        if capabilities.is_empty() {
+            return Err(ChainError::InvalidTransaction(
+                "A token needs at least one capability".to_string(),
+            ));
+        }
+        if capabilities
+            .iter()
+            .any(|c| matches!(c, Capability::Send { daily_limit } if !daily_limit.is_finite() || *daily_limit < 0.0))
+        {
+            return Err(ChainError::InvalidTransaction(
+                "Daily send limit must be a non-negative number".to_string(),
+            ));
+        }
+
+        let mut bytes = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut bytes);
+        let secret = format!("tct_{}", hex::encode(bytes));
+        let hash = digest(&secret);
+
+        let record = TokenRecord {
+            id: hash[..16].to_string(),
+            label: label.to_string(),
+            capabilities,
+            created_at: now,
+            expires_at: ttl_secs.map(|ttl| now.saturating_add(ttl)),
+            spend_day: now / SECONDS_PER_DAY,
+            spent_today: 0.0,
+        };
+        self.tokens.insert(hash, record.clone());
+        Ok((secret, record))
+    }
+
+    /// Static keys from `[[api.keys]]`, kept apart from minted tokens so
+    /// they are never written to the token file
+    pub fn from_api_keys(keys: &[ApiKeyConfig], now: u64) -> Result<Self, ChainError> {
+        let mut store = Self::new();
+        for key in keys {
+            let secret = key.key.trim();
+            if secret.len() < MIN_API_KEY_LENGTH {
+                return Err(ChainError::AuthenticationError(format!(
+                    "API key {:?} is too short (min {} characters)",
+                    key.label, MIN_API_KEY_LENGTH
+                )));
+            }
+            let capability = match key.scope {
+                ApiKeyScope::Read => Capability::ReadOnly,
+                ApiKeyScope::Admin => Capability::Admin,
+            };
+            let hash = digest(secret);
+            if store.tokens.contains_key(&hash) {
+                return Err(ChainError::AuthenticationError(format!(
+                    "API key {:?} is listed twice",
+                    key.label
+                )));
+            }
+            let record = TokenRecord {
+                id: hash[..16].to_string(),
+                label: key.label.clone(),
+                capabilities: vec![capability],
+                created_at: now,
+                expires_at: None,
+                spend_day: now / SECONDS_PER_DAY,
+                spent_today: 0.0,
+            };
+            store.tokens.insert(hash, record);
+        }
+        Ok(store)
+    }
+
+    /// Whether `secret` is one of this store's tokens, live or not
+    pub fn contains(&self, secret: &str) -> bool {
+        self.tokens.contains_key(&digest(secret))
+    }
+
+    /// Revoke the token with public identifier `id`
+    pub fn revoke(&mut self, id: &str) -> bool {
+        let before = self.tokens.len();
+        self.tokens.retain(|_, record| record.id != id);
+        self.tokens.len() != before
+    }
+
+    /// All minted tokens, oldest first
+    pub fn list(&self) -> Vec<&TokenRecord> {
+        let mut records: Vec<_> = self.tokens.values().collect();
+        records.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+        records
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Check that `secret` is a live token granting `scope`
+    pub fn authorize(
+        &self,
+        secret: &str,
+        scope: Scope,
+        now: u64,
+    ) -> Result<&TokenRecord, ChainError> {
+        let record = self
+            .tokens
+            .get(&digest(secret))
+            .ok_or_else(|| ChainError::AuthenticationError("Unknown API token".to_string()))?;
+        if record.is_expired(now) {
+            return Err(ChainError::AuthenticationError(
+                "API token has expired".to_string(),
+            ));
+        }
+        if !record.capabilities.iter().any(|c| c.grants(scope)) {
+            return Err(ChainError::AuthenticationError(format!(
+                "API token does not grant the {:?} scope",
+                scope
+            )));
+        }
+        Ok(record)
+    }
+
+    /// Check that `secret` may send `amount` more today without charging it
+    pub fn check_spend(&self, secret: &str, amount: Coord, now: u64) -> Result<(), ChainError> {
+        let record = self.authorize(secret, Scope::Send, now)?;
+        let limit = record.daily_limit().unwrap_or(0.0);
+        let spent = if record.spend_day == now / SECONDS_PER_DAY {
+            record.spent_today
+        } else {
+            0.0
+        };
+
+        if spent + amount.to_num::<f64>() > limit {
+            return Err(ChainError::AuthenticationError(format!(
+                "Daily send limit of {} exceeded ({} already sent today)",
+                limit, spent
+            )));
+        }
+        Ok(())
+    }
+
+    /// Charge `amount` against the token's daily send limit, refusing the
+    /// spend (and charging nothing) if it would exceed the limit
+    pub fn record_spend(
+        &mut self,
+        secret: &str,
+        amount: Coord,
+        now: u64,
+    ) -> Result<(), ChainError> {
+        self.check_spend(secret, amount, now)?;
+        if let Some(record) = self.tokens.get_mut(&digest(secret)) {
+            let today = now / SECONDS_PER_DAY;
+            if record.spend_day != today {
+                record.spend_day = today;
+                record.spent_today = 0.0;
+            }
+            record.spent_today += amount.to_num::<f64>();
+        }
+        Ok(())
+    }
+}
+
+/// Get the default token store path
+pub fn get_token_store_path() -> Result<PathBuf, ChainError> {
+    Ok(crate::wallet::get_wallet_dir()?.join("api_tokens.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    #[test]
+    fn test_scopes_are_enforced() {
+        let mut store = TokenStore::new();
+        let (viewer, _) = store
+            .mint("telegram", vec![Capability::ViewBalance], None, NOW)
+            .unwrap();
+        let (builder, record) = store
+            .mint("signer", vec![Capability::CreateUnsigned], Some(60), NOW)
+            .unwrap();
+
+        assert!(store.authorize(&viewer, Scope::ViewBalance, NOW).is_ok());
+        assert!(store
+            .authorize(&viewer, Scope::CreateUnsigned, NOW)
+            .is_err());
+        assert!(store.authorize(&viewer, Scope::Send, NOW).is_err());
+        assert!(store
+            .authorize(&builder, Scope::CreateUnsigned, NOW)
+            .is_ok());
+        assert!(store
+            .authorize(&builder, Scope::CreateUnsigned, NOW + 60)
+            .is_err());
+        assert!(store
+            .authorize("tct_bogus", Scope::ViewBalance, NOW)
+            .is_err());
+
+        assert!(store.revoke(&record.id));
+        assert!(store
+            .authorize(&builder, Scope::CreateUnsigned, NOW)
+            .is_err());
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn test_config_api_keys() {
+        const READ: &str = "read-key-0123456789";
+        const ADMIN: &str = "admin-key-0123456789";
+        let key = |label: &str, key: &str, scope| ApiKeyConfig {
+            label: label.to_string(),
+            key: key.to_string(),
+            scope,
+        };
+        let keys = TokenStore::from_api_keys(
+            &[
+                key("explorer", READ, ApiKeyScope::Read),
+                key("ops", ADMIN, ApiKeyScope::Admin),
+            ],
+            NOW,
+        )
+        .unwrap();
+
+        assert!(keys.authorize(READ, Scope::Read, NOW).is_ok());
+        assert!(keys.authorize(READ, Scope::ViewBalance, NOW).is_ok());
+        assert!(keys.authorize(READ, Scope::Send, NOW).is_err());
+        assert!(keys.authorize(READ, Scope::Control, NOW).is_err());
+        assert!(keys.authorize(ADMIN, Scope::Control, NOW).is_ok());
+        assert!(keys.contains(ADMIN));
+        assert!(!keys.contains("tct_bogus"));
+
+        // Weak or duplicated keys are refused outright
+        assert!(TokenStore::from_api_keys(&[key("short", "abc", ApiKeyScope::Read)], NOW).is_err());
+        let twice = key("a", "same-key-0123456789", ApiKeyScope::Read);
+        assert!(TokenStore::from_api_keys(&[twice.clone(), twice], NOW).is_err());
+    }
+
+    #[test]
+    fn test_daily_send_limit() {
+        let mut store = TokenStore::new();
+        let (sender, _) = store
+            .mint(
+                "bot",
+                vec![Capability::Send { daily_limit: 10.0 }],
+                None,
+                NOW,
+            )
+            .unwrap();
+
+        assert!(store.authorize(&sender, Scope::ViewBalance, NOW).is_ok());
+        store
+            .record_spend(&sender, Coord::from_num(6), NOW)
+            .unwrap();
+        assert!(store
+            .record_spend(&sender, Coord::from_num(5), NOW)
+            .is_err());
+        store
+            .record_spend(&sender, Coord::from_num(4), NOW)
+            .unwrap();
+
+        // The allowance resets the next UTC day
+        store
+            .record_spend(&sender, Coord::from_num(5), NOW + SECONDS_PER_DAY)
+            .unwrap();
+
+        assert!(store
+            .mint(
+                "bad",
+                vec![Capability::Send { daily_limit: -1.0 }],
+                None,
+                NOW
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_store_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("api_tokens.json");
+
+        let mut store = TokenStore::load_or_new(&path).unwrap();
+        let (secret, _) = store
+            .mint("viewer", vec![Capability::ViewBalance], None, NOW)
+            .unwrap();
+        store.save(&path).unwrap();
+
+        assert!(!fs::read_to_string(&path).unwrap().contains(&secret));
+        let loaded = TokenStore::load_or_new(&path).unwrap();
+        assert!(loaded.authorize(&secret, Scope::ViewBalance, NOW).is_ok());
+    }
+}
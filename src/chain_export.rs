@@ -0,0 +1,600 @@
+//! Block and transaction dumps, and replaying them into a database
+//!
+//! `trinity export-chain` streams stored blocks through
+//! [`Database::iterate_blocks`] into one of three layouts, documented in
+//! `documentation/CLI_REFERENCE.md`:
+//!
+//! - **json**: JSON Lines, one [`BlockRecord`] per line with its
+//!   transactions nested (`pandas.read_json(path, lines=True)`)
+//! - **csv**: one row per transaction output, with the block's height, hash
+//!   and timestamp repeated on each row (`pandas.read_csv(path)`)
+//! - **raw**: JSON Lines of complete blocks, signatures included, for
+//!   `trinity import-chain`
+//!
+//! In the analysis layouts hashes and addresses are hex. Amounts are
+//! decimal strings, exact to the chain's fixed-point precision, so they
+//! survive a round trip; convert them with `astype(float)` for arithmetic.
+//!
+//! [`import_chain`] replays a raw export, or another node's database, through
+//! full block validation instead of trusting the copied state.
+
+use crate::blockchain::{Block, Blockchain};
+use crate::crypto::Address;
+use crate::error::ChainError;
+use crate::geometry::Coord;
+use crate::persistence::Database;
+use crate::transaction::Transaction;
+use serde::Serialize;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+/// Column names of the CSV layout, in order
+pub const CSV_COLUMNS: [&str; 13] = [
+    "height",
+    "block_hash",
+    "timestamp",
+    "tx_index",
+    "tx_hash",
+    "kind",
+    "output_index",
+    "sender",
+    "recipient",
+    "amount",
+    "fee",
+    "input_hash",
+    "memo",
+];
+
+/// Layouts `trinity export-chain` can write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+    Raw,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = ChainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "csv" => Ok(ExportFormat::Csv),
+            "raw" => Ok(ExportFormat::Raw),
+            other => Err(ChainError::InvalidTransaction(format!(
+                "Unknown export format '{}' (expected json, csv or raw)",
+                other
+            ))),
+        }
+    }
+}
+
+/// One block of a JSON export
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockRecord {
+    pub height: u64,
+    pub hash: String,
+    pub previous_hash: String,
+    /// Milliseconds since the Unix epoch
+    pub timestamp: u64,
+    pub difficulty: u32,
+    pub nonce: u64,
+    pub version: u32,
+    pub merkle_root: String,
+    pub state_root: String,
+    /// The body was pruned or came from a snapshot; `transactions` is empty
+    pub header_only: bool,
+    pub transactions: Vec<TransactionRecord>,
+}
+
+/// One transaction of a JSON export
+#[derive(Debug, Clone, Serialize)]
+pub struct TransactionRecord {
+    /// Position in the block; the coinbase is 0
+    pub index: usize,
+    pub hash: String,
+    /// `coinbase`, `transfer`, `batch_transfer` or `subdivision`
+    pub kind: String,
+    /// Spending address; `null` for the coinbase
+    pub sender: Option<String>,
+    /// Triangle spent; `null` for the coinbase
+    pub input_hash: Option<String>,
+    pub fee: String,
+    pub memo: Option<String>,
+    /// Value created, by recipient. A subdivision pays its children to the
+    /// owner; change is not listed.
+    pub outputs: Vec<OutputRecord>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputRecord {
+    pub recipient: String,
+    pub amount: String,
+}
+
+impl BlockRecord {
+    pub fn from_block(block: &Block) -> Self {
+        let header = &block.header;
+        BlockRecord {
+            height: header.height,
+            hash: hex::encode(block.hash()),
+            previous_hash: hex::encode(header.previous_hash),
+            timestamp: header.timestamp,
+            difficulty: header.difficulty,
+            nonce: header.nonce,
+            version: header.version,
+            merkle_root: hex::encode(header.merkle_root),
+            state_root: hex::encode(header.state_root),
+            header_only: block.is_header_only(),
+            transactions: block
+                .transactions
+                .iter()
+                .enumerate()
+                .map(|(index, tx)| TransactionRecord::from_transaction(index, tx))
+                .collect(),
+        }
+    }
+}
+
+impl TransactionRecord {
+    pub fn from_transaction(index: usize, tx: &Transaction) -> Self {
+        let output = |recipient: &Address, amount: Coord| OutputRecord {
+            recipient: recipient.to_hex(),
+            amount: amount.to_string(),
+        };
+        let (sender, input_hash, fee, memo, outputs) = match tx {
+            Transaction::Coinbase(tx) => (
+                None,
+                None,
+                Default::default(),
+                None,
+                vec![output(&tx.beneficiary_address, tx.reward_area)],
+            ),
+            Transaction::Transfer(tx) => (
+                Some(tx.sender),
+                Some(tx.input_hash),
+                tx.fee_area,
+                tx.memo.clone(),
+                vec![output(&tx.new_owner, tx.amount)],
+            ),
+            Transaction::BatchTransfer(tx) => (
+                Some(tx.sender),
+                Some(tx.input_hash),
+                tx.fee_area,
+                tx.memo.clone(),
+                tx.outputs
+                    .iter()
+                    .map(|o| output(&o.recipient, o.amount))
+                    .collect(),
+            ),
+            Transaction::Subdivision(tx) => (
+                Some(tx.owner_address),
+                Some(tx.parent_hash),
+                tx.fee_area,
+                None,
+                tx.children
+                    .iter()
+                    .map(|child| output(&tx.owner_address, child.effective_value()))
+                    .collect(),
+            ),
+        };
+        TransactionRecord {
+            index,
+            hash: hex::encode(tx.hash()),
+            kind: tx.kind().to_string(),
+            sender: sender.map(|a| a.to_hex()),
+            input_hash: input_hash.map(hex::encode),
+            fee: fee.to_string(),
+            memo,
+            outputs,
+        }
+    }
+}
+
+/// What an export wrote
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExportSummary {
+    pub blocks: u64,
+    pub transactions: u64,
+}
+
+/// Write the stored blocks with heights `from..=to` to `out` as `format`,
+/// one block at a time
+pub fn export_chain(
+    db: &Database,
+    from: u64,
+    to: u64,
+    format: ExportFormat,
+    mut out: impl Write,
+) -> Result<ExportSummary, ChainError> {
+    let mut summary = ExportSummary::default();
+    if format == ExportFormat::Csv {
+        writeln!(out, "{}", CSV_COLUMNS.join(","))?;
+    }
+    db.iterate_blocks(from, to, |block| {
+        summary.blocks += 1;
+        summary.transactions += block.transactions.len() as u64;
+        let height = block.header.height;
+        let json_error = |e: serde_json::Error| {
+            ChainError::IoError(format!("Failed to write block {}: {}", height, e))
+        };
+        match format {
+            ExportFormat::Json => {
+                serde_json::to_writer(&mut out, &BlockRecord::from_block(&block))
+                    .map_err(json_error)?;
+                writeln!(out)?;
+            }
+            ExportFormat::Csv => write_csv_rows(&mut out, &BlockRecord::from_block(&block))?,
+            ExportFormat::Raw => {
+                serde_json::to_writer(&mut out, &block).map_err(json_error)?;
+                writeln!(out)?;
+            }
+        }
+        Ok(())
+    })?;
+    out.flush()?;
+    Ok(summary)
+}
+
+/// One row per output; the fee is on a transaction's first row only, so
+/// summing the column gives the fees paid
+fn write_csv_rows(out: &mut impl Write, block: &BlockRecord) -> Result<(), ChainError> {
+    for tx in &block.transactions {
+        for (output_index, output) in tx.outputs.iter().enumerate() {
+            let fee = if output_index == 0 {
+                tx.fee.as_str()
+            } else {
+                "0"
+            };
+            let fields = [
+                block.height.to_string(),
+                block.hash.clone(),
+                block.timestamp.to_string(),
+                tx.index.to_string(),
+                tx.hash.clone(),
+                tx.kind.clone(),
+                output_index.to_string(),
+                tx.sender.clone().unwrap_or_default(),
+                output.recipient.clone(),
+                output.amount.clone(),
+                fee.to_string(),
+                tx.input_hash.clone().unwrap_or_default(),
+                tx.memo.clone().unwrap_or_default(),
+            ];
+            let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+            writeln!(out, "{}", row.join(","))?;
+        }
+    }
+    Ok(())
+}
+
+/// Quote a field holding a comma, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Read a raw export, one block per line
+pub fn read_raw_blocks(reader: impl BufRead) -> impl Iterator<Item = Result<Block, ChainError>> {
+    reader
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|(index, line)| {
+            serde_json::from_str(&line?).map_err(|e| {
+                ChainError::InvalidBlock(format!(
+                    "Line {} is not a raw block ({}); export with --format raw",
+                    index + 1,
+                    e
+                ))
+            })
+        })
+}
+
+/// Tuning for [`import_chain`]
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    /// Blocks applied per database transaction
+    pub commit_every: usize,
+    /// Write a UTXO snapshot into `snapshot_dir` at every multiple of this
+    /// height
+    pub snapshot_every: Option<u64>,
+    pub snapshot_dir: PathBuf,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        ImportOptions {
+            commit_every: crate::persistence::IBD_COMMIT_BLOCKS,
+            snapshot_every: None,
+            snapshot_dir: PathBuf::from("snapshots"),
+        }
+    }
+}
+
+impl ImportOptions {
+    pub fn with_commit_every(mut self, blocks: usize) -> Self {
+        self.commit_every = blocks.max(1);
+        self
+    }
+
+    pub fn with_snapshots(mut self, every: u64, dir: impl Into<PathBuf>) -> Self {
+        self.snapshot_every = Some(every.max(1));
+        self.snapshot_dir = dir.into();
+        self
+    }
+}
+
+/// Where an import stands, reported after each commit
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportProgress {
+    /// Height of the local tip
+    pub height: u64,
+    /// Blocks validated and stored so far
+    pub applied: u64,
+    /// Source blocks the database already held
+    pub skipped: u64,
+    /// Snapshot written at this commit, if any
+    pub snapshot: Option<PathBuf>,
+}
+
+/// Apply `blocks` to `chain`, which must be `db`'s stored chain, validating
+/// each with [`Blockchain::apply_block`] and storing them in batches.
+/// Source blocks at heights the chain already has must match it and are
+/// skipped, so an interrupted import can be rerun. Stops at the first
+/// invalid block, after storing every block before it.
+pub fn import_chain(
+    db: &Database,
+    chain: &mut Blockchain,
+    blocks: impl IntoIterator<Item = Result<Block, ChainError>>,
+    options: &ImportOptions,
+    mut progress: impl FnMut(&ImportProgress),
+) -> Result<ImportProgress, ChainError> {
+    let mut status = ImportProgress::default();
+    let mut batch = Vec::new();
+    for block in blocks {
+        let block = block?;
+        let height = block.header.height;
+        let tip = chain.blocks.last().map_or(0, |b| b.header.height);
+        if height <= tip {
+            let local = &chain.blocks[height as usize];
+            if local.hash() != block.hash() {
+                return Err(ChainError::InvalidBlock(format!(
+                    "Source block #{} ({}) does not match the local chain ({})",
+                    height,
+                    hex::encode(block.hash()),
+                    hex::encode(local.hash())
+                )));
+            }
+            status.skipped += 1;
+            continue;
+        }
+        if block.is_header_only() {
+            return Err(ChainError::InvalidBlock(format!(
+                "Source block #{} has no transactions (pruned or from a snapshot) and can't be validated",
+                height
+            )));
+        }
+        batch.push(block);
+
+        let snapshot_due = options
+            .snapshot_every
+            .is_some_and(|every| height.is_multiple_of(every));
+        if batch.len() >= options.commit_every || snapshot_due {
+            commit_batch(db, chain, &mut batch, options, snapshot_due, &mut status)?;
+            progress(&status);
+        }
+    }
+    if !batch.is_empty() {
+        commit_batch(db, chain, &mut batch, options, false, &mut status)?;
+        progress(&status);
+    }
+    Ok(status)
+}
+
+fn commit_batch(
+    db: &Database,
+    chain: &mut Blockchain,
+    batch: &mut Vec<Block>,
+    options: &ImportOptions,
+    snapshot: bool,
+    status: &mut ImportProgress,
+) -> Result<(), ChainError> {
+    let tip = |chain: &Blockchain| chain.blocks.last().map_or(0, |b| b.header.height);
+    let before = tip(chain);
+    let result = db.apply_blocks(chain, batch.drain(..), options.commit_every);
+    status.height = tip(chain);
+    status.applied += status.height - before;
+    if let Err(e) = result {
+        // Storage failures are not the block's fault
+        if matches!(e, ChainError::DatabaseError(_)) {
+            return Err(e);
+        }
+        return Err(ChainError::InvalidBlock(format!(
+            "Block #{} failed validation: {}",
+            status.height + 1,
+            e
+        )));
+    }
+    status.snapshot = None;
+    if snapshot {
+        std::fs::create_dir_all(&options.snapshot_dir)?;
+        let path = options
+            .snapshot_dir
+            .join(format!("chain-{}.snapshot", status.height));
+        db.snapshot_tip(chain, &path)?;
+        status.snapshot = Some(path);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::Blockchain;
+    use crate::crypto::KeyPair;
+    use crate::fixtures::mine_next_block;
+    use crate::transaction::TransferTx;
+
+    #[test]
+    fn test_export_json_and_csv() {
+        let keypair = KeyPair::generate().unwrap();
+        let payee = Address::from([7; 32]);
+        let mut chain = Blockchain::new(keypair.address(), 1).unwrap();
+        let db = Database::open(":memory:").unwrap().with_custom_genesis();
+        db.save_blockchain_state(&chain.blocks[0], &chain.state, 1)
+            .unwrap();
+
+        let genesis_output = chain.blocks[0].transactions[0].hash();
+        let mut transfer = TransferTx::new(
+            genesis_output,
+            payee,
+            keypair.address(),
+            Coord::from_num(5),
+            Coord::from_num(1),
+            1,
+        )
+        .with_memo("rent, \"march\"".to_string())
+        .unwrap();
+        let signature = keypair.sign(&transfer.signable_message()).unwrap();
+        transfer.sign(signature.to_vec(), keypair.public_key_bytes().to_vec());
+        let transfer = Transaction::Transfer(transfer);
+        for transactions in [vec![transfer], vec![]] {
+            let block = mine_next_block(&chain, keypair.address(), transactions);
+            chain.apply_block(block.clone()).unwrap();
+            db.save_blockchain_state(&block, &chain.state, 1).unwrap();
+        }
+
+        let mut json = Vec::new();
+        let summary = export_chain(&db, 1, 1, ExportFormat::Json, &mut json).unwrap();
+        assert_eq!(
+            summary,
+            ExportSummary {
+                blocks: 1,
+                transactions: 2
+            }
+        );
+        let lines: Vec<serde_json::Value> = String::from_utf8(json)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 1);
+        let tx = &lines[0]["transactions"][1];
+        assert_eq!(tx["kind"], "transfer");
+        assert_eq!(tx["sender"], keypair.address().to_hex());
+        assert_eq!(tx["outputs"][0]["recipient"], payee.to_hex());
+        assert_eq!(tx["outputs"][0]["amount"], "5");
+        assert_eq!(tx["fee"], "1");
+
+        let mut csv = Vec::new();
+        let summary = export_chain(&db, 0, u64::MAX, ExportFormat::Csv, &mut csv).unwrap();
+        assert_eq!(summary.blocks, 3);
+        let csv = String::from_utf8(csv).unwrap();
+        let mut rows = csv.lines();
+        assert_eq!(rows.next().unwrap(), CSV_COLUMNS.join(","));
+        // Genesis, block 1 (coinbase and transfer), block 2
+        assert_eq!(rows.clone().count(), 4);
+        let transfer_row = rows.find(|row| row.contains(",transfer,")).unwrap();
+        assert!(
+            transfer_row.ends_with(",\"rent, \"\"march\"\"\""),
+            "{}",
+            transfer_row
+        );
+        assert!("json".parse::<ExportFormat>().is_ok());
+        assert!("xml".parse::<ExportFormat>().is_err());
+    }
+
+    #[test]
+    fn test_raw_export_imports_with_validation() {
+        let keypair = KeyPair::generate().unwrap();
+        let mut source_chain = Blockchain::new(keypair.address(), 1).unwrap();
+        let source = Database::open(":memory:").unwrap().with_custom_genesis();
+        source
+            .save_blockchain_state(&source_chain.blocks[0], &source_chain.state, 1)
+            .unwrap();
+        let genesis_state = source_chain.state.clone();
+        for _ in 0..4 {
+            let block = mine_next_block(&source_chain, keypair.address(), vec![]);
+            source_chain.apply_block(block.clone()).unwrap();
+            source
+                .save_blockchain_state(&block, &source_chain.state, 1)
+                .unwrap();
+        }
+        let mut raw = Vec::new();
+        export_chain(&source, 0, u64::MAX, ExportFormat::Raw, &mut raw).unwrap();
+
+        let target = Database::open(":memory:").unwrap().with_custom_genesis();
+        let genesis = &source_chain.blocks[0];
+        target
+            .save_blockchain_state(genesis, &genesis_state, 1)
+            .unwrap();
+        let mut chain = target.load_blockchain().unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let options = ImportOptions::default()
+            .with_commit_every(3)
+            .with_snapshots(2, dir.path());
+        let mut reports = Vec::new();
+        let status = import_chain(
+            &target,
+            &mut chain,
+            read_raw_blocks(raw.as_slice()),
+            &options,
+            |p| reports.push(p.clone()),
+        )
+        .unwrap();
+        assert_eq!((status.height, status.applied, status.skipped), (4, 4, 1));
+        // Snapshots at 2 and 4 end batches early
+        assert_eq!(
+            reports.iter().map(|p| p.height).collect::<Vec<_>>(),
+            vec![2, 4]
+        );
+        assert!(dir.path().join("chain-2.snapshot").exists());
+        assert!(dir.path().join("chain-4.snapshot").exists());
+        let stored = target.load_blockchain().unwrap();
+        assert_eq!(
+            stored.blocks.last().unwrap().hash(),
+            source_chain.blocks.last().unwrap().hash()
+        );
+
+        // Rerunning skips what is stored; another node's database works too
+        let status = import_chain(
+            &target,
+            &mut chain,
+            source.blocks_from(0, 2),
+            &ImportOptions::default(),
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!((status.applied, status.skipped), (0, 5));
+
+        // A tampered block fails validation and nothing after it is stored
+        let mut tampered = source_chain.blocks[1].clone();
+        if let Transaction::Coinbase(coinbase) = &mut tampered.transactions[0] {
+            coinbase.reward_area += Coord::from_num(1);
+        }
+        let fresh = Database::open(":memory:").unwrap().with_custom_genesis();
+        fresh
+            .save_blockchain_state(genesis, &genesis_state, 1)
+            .unwrap();
+        let mut chain = fresh.load_blockchain().unwrap();
+        let blocks = vec![Ok(tampered), Ok(source_chain.blocks[2].clone())];
+        assert!(import_chain(
+            &fresh,
+            &mut chain,
+            blocks,
+            &ImportOptions::default(),
+            |_| {}
+        )
+        .is_err());
+        assert_eq!(fresh.load_blockchain().unwrap().blocks.len(), 1);
+        assert!(read_raw_blocks("{\"height\":1}\n".as_bytes())
+            .next()
+            .unwrap()
+            .is_err());
+    }
+}
@@ -2,7 +2,12 @@
 
 use crate::blockchain::Blockchain;
 use crate::config::{load_config, Config};
+use crate::crypto::Address;
+use crate::error::ChainError;
+use crate::geometry::Coord;
 use crate::persistence::Database;
+use crate::transaction::TransferOutput;
+use serde::Deserialize;
 
 pub fn load_blockchain_from_config() -> Result<(Config, Blockchain), Box<dyn std::error::Error>> {
     let config = load_config()?;
@@ -10,3 +15,89 @@ pub fn load_blockchain_from_config() -> Result<(Config, Blockchain), Box<dyn std
     let blockchain = db.load_blockchain()?;
     Ok((config, blockchain))
 }
+
+/// Read batch transfer recipients: either CSV lines of `address,amount`
+/// (blank lines, `#` comments and an `address,amount` header are skipped)
+/// or a JSON array of `{"address": ..., "amount": ...}` objects
+pub fn parse_recipients(contents: &str) -> Result<Vec<TransferOutput>, ChainError> {
+    #[derive(Deserialize)]
+    struct Entry {
+        address: String,
+        amount: f64,
+    }
+
+    let entries: Vec<(String, f64)> = if contents.trim_start().starts_with('[') {
+        let entries: Vec<Entry> = serde_json::from_str(contents).map_err(|e| {
+            ChainError::InvalidTransaction(format!("Invalid recipients JSON: {}", e))
+        })?;
+        entries.into_iter().map(|e| (e.address, e.amount)).collect()
+    } else {
+        let mut entries = Vec::new();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line == "address,amount" {
+                continue;
+            }
+            let (address, amount) = line
+                .split_once(',')
+                .and_then(|(address, amount)| Some((address, amount.trim().parse().ok()?)))
+                .ok_or_else(|| {
+                    ChainError::InvalidTransaction(format!(
+                        "Line {}: expected `address,amount`, got '{}'",
+                        number + 1,
+                        line
+                    ))
+                })?;
+            entries.push((address.to_string(), amount));
+        }
+        entries
+    };
+
+    let mut outputs: Vec<TransferOutput> = Vec::new();
+    for (address, amount) in entries {
+        let recipient = Address::parse(&address)?;
+        if outputs.iter().any(|o| o.recipient == recipient) {
+            return Err(ChainError::InvalidTransaction(format!(
+                "Recipient {} is listed more than once",
+                address.trim()
+            )));
+        }
+        if !(amount.is_finite() && amount > 0.0) {
+            return Err(ChainError::InvalidTransaction(format!(
+                "Amount for {} must be positive",
+                address.trim()
+            )));
+        }
+        outputs.push(TransferOutput {
+            recipient,
+            amount: Coord::from_num(amount),
+        });
+    }
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_recipients_csv_and_json() {
+        let alice = Address::from([1; 32]);
+        let bob = Address::from([2; 32]);
+        let csv = format!("address,amount\n# rent\n{},2.5\n\n{}, 4\n", alice, bob);
+        let outputs = parse_recipients(&csv).unwrap();
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[1].recipient, bob);
+        assert_eq!(outputs[1].amount, Coord::from_num(4));
+
+        let json = format!(r#"[{{"address": "{}", "amount": 2.5}}]"#, alice);
+        assert_eq!(
+            parse_recipients(&json).unwrap()[0].amount,
+            Coord::from_num(2.5)
+        );
+
+        assert!(parse_recipients(&format!("{},1\n{},2", alice, alice)).is_err());
+        assert!(parse_recipients(&format!("{},-1", alice)).is_err());
+        assert!(parse_recipients("not-a-line").is_err());
+    }
+}
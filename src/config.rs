@@ -1,35 +1,358 @@
 //! Configuration management for TrinityChain
+//!
+//! Settings come from `config.toml`, then `TRINITY_<SECTION>__<KEY>`
+//! environment variables (`TRINITY_NETWORK__P2P_PORT=9000` overrides
+//! `[network] p2p_port`), then the selected network's defaults. The result
+//! is checked by [`Config::validate`], which names the offending setting by
+//! its dotted path. `trinity config show` prints the effective settings and
+//! `trinity config set` edits `config.toml` in place.
 
-use serde::Deserialize;
+use crate::blockchain::Sha256Hash;
+use crate::crypto::Address;
+use crate::datadir::DataDir;
+use crate::discovery::{mainnet_dns_seeds, testnet_dns_seeds, DnsSeed};
+use crate::error::ChainError;
+use crate::logging::{parse_level, LoggingConfig};
+use crate::network::{Node, ProtocolDeadline, ProtocolPolicy, MIN_PROTOCOL_VERSION};
+use crate::security::SecretBytes;
+use crate::stale_tip::{stale_tip_threshold, StaleTipAction, DEFAULT_STALE_TIP_MULTIPLE};
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
 use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Duration;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Config {
+    #[serde(default)]
     pub network: NetworkConfig,
+    #[serde(default)]
     pub database: DatabaseConfig,
+    #[serde(default)]
     pub miner: MinerConfig,
     #[serde(default)]
     pub ai_validation: AIValidationConfig,
+    #[serde(default)]
+    pub faucet: FaucetConfig,
+    #[serde(default)]
+    pub watchtower: WatchtowerConfig,
+    #[serde(default)]
+    pub api: ApiConfig,
+    #[serde(default)]
+    pub node: NodeConfig,
+    #[serde(default)]
+    pub client: ClientConfig,
+    #[serde(default)]
+    pub policy: PolicyConfig,
+    #[serde(default)]
+    pub replication: ReplicationConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct NetworkConfig {
+    /// Which chain to run on
+    #[serde(default)]
+    pub chain: Network,
+    /// Defaults to the chain's standard port when omitted
+    #[serde(default)]
     pub p2p_port: u16,
+    /// Defaults to the chain's standard port when omitted
+    #[serde(default)]
     pub api_port: u16,
+    /// `host:port` peers to try when nothing else is known
+    #[serde(default)]
+    pub bootstrap_nodes: Vec<String>,
+    /// Every peer lifecycle event is POSTed here as JSON when set
+    #[serde(default)]
+    pub peer_events_webhook: Option<String>,
+    /// Oldest peer protocol version accepted (never below the built-in minimum)
+    #[serde(default)]
+    pub min_protocol_version: Option<u32>,
+    /// Raise the minimum at a block height, disconnecting older peers
+    #[serde(default)]
+    pub protocol_deadlines: Vec<ProtocolDeadline>,
+}
+
+impl NetworkConfig {
+    /// The bootstrap nodes that parse as `host:port`
+    pub fn bootstrap_peers(&self) -> Vec<Node> {
+        self.bootstrap_nodes
+            .iter()
+            .filter_map(|addr| addr.parse().ok())
+            .collect()
+    }
+
+    /// Peer protocol versions this node accepts
+    pub fn protocol_policy(&self) -> ProtocolPolicy {
+        let min_version = self.min_protocol_version.unwrap_or(MIN_PROTOCOL_VERSION);
+        self.protocol_deadlines
+            .iter()
+            .fold(ProtocolPolicy::new(min_version), |policy, deadline| {
+                policy.with_deadline(*deadline)
+            })
+    }
+}
+
+// ============================================================================
+// Networks
+// ============================================================================
+
+/// The chain a node runs on. Each has its own genesis block, ports, seeds,
+/// network magic, address prefix and data directory, so a test node can
+/// never touch mainnet data or peers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    #[default]
+    Mainnet,
+    Testnet,
+    /// Local testing: no seeds, difficulty pinned at 1 so blocks mine instantly
+    Regtest,
 }
 
-#[derive(Debug, Deserialize)]
+impl Network {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "mainnet",
+            Network::Testnet => "testnet",
+            Network::Regtest => "regtest",
+        }
+    }
+
+    pub fn default_p2p_port(&self) -> u16 {
+        match self {
+            Network::Mainnet => 8333,
+            Network::Testnet => 18333,
+            Network::Regtest => 18444,
+        }
+    }
+
+    pub fn default_api_port(&self) -> u16 {
+        match self {
+            Network::Mainnet => 3000,
+            Network::Testnet => 13000,
+            Network::Regtest => 13100,
+        }
+    }
+
+    pub fn dns_seeds(&self) -> Vec<DnsSeed> {
+        match self {
+            Network::Mainnet => mainnet_dns_seeds(),
+            Network::Testnet => testnet_dns_seeds(),
+            Network::Regtest => Vec::new(),
+        }
+    }
+
+    /// Handshake magic; peers on another network are refused
+    pub fn magic(&self) -> u32 {
+        match self {
+            Network::Mainnet => 0x5452_4931, // "TRI1"
+            Network::Testnet => 0x5452_4954, // "TRIT"
+            Network::Regtest => 0x5452_4952, // "TRIR"
+        }
+    }
+
+    /// Version byte of base58check addresses
+    pub fn address_version(&self) -> u8 {
+        match self {
+            Network::Mainnet => 0x41,
+            Network::Testnet => 0x7f,
+            Network::Regtest => 0x6f,
+        }
+    }
+
+    /// Genesis block timestamp (milliseconds); distinct per network so each
+    /// has its own genesis hash
+    pub fn genesis_timestamp(&self) -> u64 {
+        match self {
+            Network::Mainnet => 1672531200000, // Jan 1, 2023
+            Network::Testnet => 1672531200001,
+            Network::Regtest => 1672531200002,
+        }
+    }
+
+    /// Nonce of the canonical genesis block, found once so nodes never mine
+    /// it at startup
+    pub fn genesis_nonce(&self) -> u64 {
+        match self {
+            Network::Mainnet => 3,
+            Network::Testnet => 15,
+            Network::Regtest => 3,
+        }
+    }
+
+    /// Hash of the canonical genesis block. A chain starting from any other
+    /// block belongs to a different network.
+    pub fn genesis_hash(&self) -> Sha256Hash {
+        let hex = match self {
+            Network::Mainnet => "0c865a512b3a211dbf1ba203c0ba09f255e9956fc630899771d21f7a9fddc1a8",
+            Network::Testnet => "0c14223966ca1006e295569a532f42d42db3ed9e75361a29521bed02d152eb3d",
+            Network::Regtest => "03a519d33ea24f542b731ffbdc6a74ec8072536fbe2d72f6a935a34b8bf1075b",
+        };
+        let mut hash = [0; 32];
+        hex::decode_to_slice(hex, &mut hash).expect("genesis hashes are valid hex");
+        hash
+    }
+
+    /// Whether a chain may start from a custom genesis block, as tests and
+    /// private regtest setups mint their own
+    pub fn allows_custom_genesis(&self) -> bool {
+        *self == Network::Regtest
+    }
+
+    /// Flag-day height from which only v1 sighash signatures are valid
+    pub fn sighash_v1_height(&self) -> u64 {
+        match self {
+            Network::Mainnet => 250_000,
+            Network::Testnet => 50_000,
+            Network::Regtest => 0,
+        }
+    }
+
+    /// Whether difficulty retargeting is disabled
+    pub fn fixed_difficulty(&self) -> bool {
+        *self == Network::Regtest
+    }
+
+    /// Database path for this network: mainnet uses `path` as is, other
+    /// networks keep their data in a subdirectory named after the network
+    pub fn data_path(&self, path: &str) -> String {
+        if *self == Network::Mainnet {
+            return path.to_string();
+        }
+        let path = Path::new(path);
+        let file = path.file_name().unwrap_or(path.as_os_str());
+        path.parent()
+            .unwrap_or(Path::new(""))
+            .join(self.name())
+            .join(file)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn from_index(index: u8) -> Self {
+        match index {
+            1 => Network::Testnet,
+            2 => Network::Regtest,
+            _ => Network::Mainnet,
+        }
+    }
+
+    fn index(&self) -> u8 {
+        match self {
+            Network::Mainnet => 0,
+            Network::Testnet => 1,
+            Network::Regtest => 2,
+        }
+    }
+}
+
+impl std::str::FromStr for Network {
+    type Err = ChainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "mainnet" => Ok(Network::Mainnet),
+            "testnet" => Ok(Network::Testnet),
+            "regtest" => Ok(Network::Regtest),
+            other => Err(ChainError::InvalidTransaction(format!(
+                "Unknown network '{}' (expected mainnet, testnet or regtest)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Network this process runs on, set by `load_config`
+static ACTIVE_NETWORK: AtomicU8 = AtomicU8::new(0);
+
+/// The network selected by the loaded config (mainnet until one is loaded)
+pub fn active_network() -> Network {
+    Network::from_index(ACTIVE_NETWORK.load(Ordering::Relaxed))
+}
+
+/// Select the network for this process
+pub fn set_active_network(network: Network) {
+    ACTIVE_NETWORK.store(network.index(), Ordering::Relaxed);
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct DatabaseConfig {
+    /// Relative paths are taken inside `data_dir` when one is chosen
+    #[serde(default = "default_database_path")]
     pub path: String,
+    /// Data directory for wallets and local files; see [`crate::datadir`]
+    #[serde(default)]
+    pub data_dir: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            path: default_database_path(),
+            data_dir: None,
+        }
+    }
+}
+
+fn default_database_path() -> String {
+    DEFAULT_DATABASE_PATH.to_string()
+}
+
+/// Database path of a mainnet node without a `[database]` section
+pub const DEFAULT_DATABASE_PATH: &str = "trinitychain.db";
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct MinerConfig {
+    #[serde(default = "default_miner_threads")]
     pub threads: usize,
+    /// Address mining rewards go to; may be left empty
+    #[serde(default)]
     pub beneficiary_address: String,
+    /// Target block times without a block from the network, while no peer
+    /// is connected, before the tip counts as stale
+    #[serde(default = "default_stale_tip_multiple")]
+    pub stale_tip_multiple: u64,
+    /// What miners do on a stale tip; defaults to `ignore` on regtest and
+    /// `pause` elsewhere
+    #[serde(default)]
+    pub on_stale_tip: Option<StaleTipAction>,
+}
+
+impl Default for MinerConfig {
+    fn default() -> Self {
+        MinerConfig {
+            threads: default_miner_threads(),
+            beneficiary_address: String::new(),
+            stale_tip_multiple: default_stale_tip_multiple(),
+            on_stale_tip: None,
+        }
+    }
 }
 
-#[derive(Debug, Deserialize)]
+fn default_miner_threads() -> usize {
+    1
+}
+
+fn default_stale_tip_multiple() -> u64 {
+    DEFAULT_STALE_TIP_MULTIPLE
+}
+
+impl MinerConfig {
+    pub fn stale_tip_threshold(&self) -> Duration {
+        stale_tip_threshold(self.stale_tip_multiple)
+    }
+
+    pub fn stale_tip_action(&self, network: Network) -> StaleTipAction {
+        self.on_stale_tip
+            .unwrap_or_else(|| StaleTipAction::default_for(network))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct AIValidationConfig {
     #[serde(default = "default_enabled")]
     pub enabled: bool,
@@ -74,8 +397,1289 @@ fn default_timeout() -> u64 {
     30
 }
 
+/// Testnet faucet settings (used when built with the `faucet` feature)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FaucetConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Wallet to pay from (`wallet_<name>.json` in the data directory); the default
+    /// wallet when unset
+    #[serde(default)]
+    pub wallet: Option<String>,
+    /// Area paid per request
+    #[serde(default = "default_faucet_amount")]
+    pub amount: f64,
+    /// Minimum time between payouts to the same address
+    #[serde(default = "default_faucet_address_cooldown")]
+    pub address_cooldown_secs: u64,
+    /// Minimum time between payouts to the same IP or chat user
+    #[serde(default = "default_faucet_ip_cooldown")]
+    pub ip_cooldown_secs: u64,
+    /// When non-empty, requests must present one of these tokens
+    #[serde(
+        default,
+        serialize_with = "redact",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub access_tokens: Vec<String>,
+}
+
+impl Default for FaucetConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            wallet: None,
+            amount: default_faucet_amount(),
+            address_cooldown_secs: default_faucet_address_cooldown(),
+            ip_cooldown_secs: default_faucet_ip_cooldown(),
+            access_tokens: Vec::new(),
+        }
+    }
+}
+
+fn default_faucet_amount() -> f64 {
+    10.0
+}
+
+fn default_faucet_address_cooldown() -> u64 {
+    86_400
+}
+
+fn default_faucet_ip_cooldown() -> u64 {
+    3_600
+}
+
+/// Cold-storage watchtower settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchtowerConfig {
+    /// Hex triangle hashes whose spending should raise an alert
+    #[serde(default)]
+    pub triangles: Vec<String>,
+    /// Hex addresses whose spends should raise an alert
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    /// Optional URL that receives each alert as a JSON `POST`
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    #[serde(default = "default_watchtower_poll_interval")]
+    pub poll_interval_secs: u64,
+}
+
+impl Default for WatchtowerConfig {
+    fn default() -> Self {
+        Self {
+            triangles: Vec::new(),
+            addresses: Vec::new(),
+            webhook_url: None,
+            poll_interval_secs: default_watchtower_poll_interval(),
+        }
+    }
+}
+
+fn default_watchtower_poll_interval() -> u64 {
+    1
+}
+
+/// HTTP API access control
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiConfig {
+    /// Token allowed to mint and revoke capability tokens. When unset (and
+    /// `TRINITY_ADMIN_TOKEN` is not set) the wallet API is unauthenticated.
+    #[serde(
+        default,
+        serialize_with = "redact",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub admin_token: Option<String>,
+    /// Where minted tokens are stored; `api_tokens.json` in the data directory
+    /// when unset
+    #[serde(default)]
+    pub tokens_path: Option<String>,
+    /// Static API keys (`[[api.keys]]`); any key switches authentication on
+    #[serde(default)]
+    pub keys: Vec<ApiKeyConfig>,
+    /// Serve read-only routes without a key. Turn off to require a key
+    /// with at least the `read` scope on every route but `/api/health`.
+    #[serde(default = "default_public_reads")]
+    pub public_reads: bool,
+    /// Per-IP request limits (`[api.rate_limit]`)
+    #[serde(default)]
+    pub rate_limit: ApiRateLimitConfig,
+    /// Browser origins (scheme, host and port, e.g. `http://localhost:5173`)
+    /// allowed to call the API; none by default
+    #[serde(default)]
+    pub cors_origins: Vec<String>,
+}
+
+fn default_public_reads() -> bool {
+    true
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        ApiConfig {
+            admin_token: None,
+            tokens_path: None,
+            keys: Vec::new(),
+            public_reads: default_public_reads(),
+            rate_limit: ApiRateLimitConfig::default(),
+            cors_origins: Vec::new(),
+        }
+    }
+}
+
+/// Per-IP token buckets on the HTTP API, one per route class. Requests over
+/// the limit get `429 Too Many Requests` with `Retry-After`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiRateLimitConfig {
+    #[serde(default = "default_rate_limit_enabled")]
+    pub enabled: bool,
+    /// GET routes
+    #[serde(default = "default_read_limit")]
+    pub read: RouteRateLimit,
+    /// POST routes that submit or create something: transactions, wallets,
+    /// unsigned transfers, the faucet, JSON-RPC
+    #[serde(default = "default_submit_limit")]
+    pub submit: RouteRateLimit,
+    /// Mining control and token administration
+    #[serde(default = "default_control_limit")]
+    pub control: RouteRateLimit,
+}
+
+/// Sustained rate and burst size of one route class
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouteRateLimit {
+    /// Requests per minute once the burst is used up
+    pub per_minute: u32,
+    /// Requests a client may make at once after being idle
+    pub burst: u32,
+}
+
+fn default_rate_limit_enabled() -> bool {
+    true
+}
+
+fn default_read_limit() -> RouteRateLimit {
+    RouteRateLimit {
+        per_minute: 600,
+        burst: 120,
+    }
+}
+
+fn default_submit_limit() -> RouteRateLimit {
+    RouteRateLimit {
+        per_minute: 30,
+        burst: 10,
+    }
+}
+
+fn default_control_limit() -> RouteRateLimit {
+    RouteRateLimit {
+        per_minute: 30,
+        burst: 5,
+    }
+}
+
+impl Default for ApiRateLimitConfig {
+    fn default() -> Self {
+        ApiRateLimitConfig {
+            enabled: default_rate_limit_enabled(),
+            read: default_read_limit(),
+            submit: default_submit_limit(),
+            control: default_control_limit(),
+        }
+    }
+}
+
+/// A static API key from `[[api.keys]]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyConfig {
+    /// Shown in logs and errors instead of the key
+    #[serde(default)]
+    pub label: String,
+    /// Bearer secret clients send in `Authorization: Bearer <key>`
+    #[serde(serialize_with = "redact")]
+    pub key: String,
+    pub scope: ApiKeyScope,
+}
+
+/// What a static API key may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiKeyScope {
+    /// Read-only routes, including balances and history
+    Read,
+    /// Everything: mining control, submitting transactions, minting tokens
+    Admin,
+}
+
+/// Node-local relay and mining filter; never affects block validity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyConfig {
+    /// Addresses whose transactions this node neither relays nor mines
+    #[serde(default)]
+    pub blocked_addresses: Vec<String>,
+    /// Program and arguments asked about each transaction (JSON on stdin,
+    /// exit status 0 allows it)
+    #[serde(default)]
+    pub command: Vec<String>,
+    #[serde(default = "default_policy_command_timeout")]
+    pub command_timeout_ms: u64,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            blocked_addresses: Vec::new(),
+            command: Vec::new(),
+            command_timeout_ms: default_policy_command_timeout(),
+        }
+    }
+}
+
+fn default_policy_command_timeout() -> u64 {
+    2000
+}
+
+/// Environment variable that overrides `[replication] secret`
+pub const REPLICATION_SECRET_ENV: &str = "TRINITY_REPLICATION_SECRET";
+
+/// Whether a node replicates its chain to standbys or follows a primary
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplicationMode {
+    #[default]
+    Off,
+    /// Serve the chain to standbys on `[replication] port`
+    Primary,
+    /// Follow `[replication] primary` until promoted
+    Standby,
+}
+
+/// `[replication]`: hot standby pairs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicationConfig {
+    #[serde(default)]
+    pub mode: ReplicationMode,
+    /// Shared by the primary and its standbys; `TRINITY_REPLICATION_SECRET`
+    /// takes precedence
+    #[serde(
+        default,
+        serialize_with = "redact",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub secret: Option<String>,
+    /// Port the primary serves standbys on; defaults to the P2P port plus one
+    #[serde(default)]
+    pub port: u16,
+    /// Standby: the primary's replication address (`host:port`)
+    #[serde(default)]
+    pub primary: Option<String>,
+    /// Standby: silence after which the primary counts as lost
+    #[serde(default = "default_failover_timeout")]
+    pub failover_timeout_secs: u64,
+    /// Standby: promote itself once the primary is lost instead of waiting
+    /// for the operator
+    #[serde(default)]
+    pub auto_promote: bool,
+}
+
+impl Default for ReplicationConfig {
+    fn default() -> Self {
+        Self {
+            mode: ReplicationMode::Off,
+            secret: None,
+            port: 0,
+            primary: None,
+            failover_timeout_secs: default_failover_timeout(),
+            auto_promote: false,
+        }
+    }
+}
+
+fn default_failover_timeout() -> u64 {
+    30
+}
+
+impl ReplicationConfig {
+    /// The shared secret, from the environment or the config file
+    pub fn secret(&self) -> Option<SecretBytes> {
+        std::env::var(REPLICATION_SECRET_ENV)
+            .ok()
+            .or_else(|| self.secret.clone())
+            .filter(|s| !s.is_empty())
+            .map(|s| SecretBytes::new(s.into_bytes()))
+    }
+
+    pub fn failover_timeout(&self) -> Duration {
+        Duration::from_secs(self.failover_timeout_secs.max(1))
+    }
+}
+
+/// Settings for the wallet CLI tools
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClientConfig {
+    /// Node API the tools query instead of the local database; overridden by
+    /// `--node-url` and `TRINITY_NODE_URL`
+    #[serde(default)]
+    pub node_url: Option<String>,
+}
+
+/// The `[client]` section of `config.toml`, read on its own so wallet tools
+/// work with a partial or missing node configuration
+pub fn load_client_config() -> ClientConfig {
+    #[derive(Deserialize)]
+    struct ClientOnly {
+        #[serde(default)]
+        client: ClientConfig,
+    }
+
+    fs::read_to_string("config.toml")
+        .ok()
+        .and_then(|config| toml::from_str::<ClientOnly>(&config).ok())
+        .map(|config| config.client)
+        .unwrap_or_default()
+}
+
+// ============================================================================
+// Node Roles
+// ============================================================================
+
+/// Block bodies a pruned node keeps by default: about a day of blocks
+pub const DEFAULT_PRUNE_DEPTH: u64 = 2_880;
+
+/// Preset bundle of node features, chosen with `[node] role`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeRole {
+    /// Everything a general-purpose node has always done
+    #[default]
+    Full,
+    /// Keeps all history and every index
+    Archive,
+    /// Serves the API, analytics and block filters to explorers
+    Explorer,
+    /// Serves block templates with minimal indexing
+    Miner,
+    /// Light mode for a personal wallet
+    Wallet,
+}
+
+/// Features a node runs, resolved from its role and any overrides
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeFeatures {
+    /// Discard old block bodies
+    pub prune: bool,
+    /// Index transactions by address (`/api/address/:addr/transactions`)
+    pub address_index: bool,
+    /// Index memos for `/api/search/memo`
+    pub memo_index: bool,
+    /// Serve the HTTP API
+    pub api: bool,
+    /// Serve `/api/analytics/*`
+    pub analytics: bool,
+    /// Build compact block filters for light clients
+    pub block_filters: bool,
+    /// Serve block templates to external miners
+    pub template_server: bool,
+    /// Follow headers and only the wallet's own transactions
+    pub light_client: bool,
+}
+
+impl NodeRole {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NodeRole::Full => "full",
+            NodeRole::Archive => "archive",
+            NodeRole::Explorer => "explorer",
+            NodeRole::Miner => "miner",
+            NodeRole::Wallet => "wallet",
+        }
+    }
+
+    /// The role's feature bundle
+    pub fn preset(&self) -> NodeFeatures {
+        let full = NodeFeatures {
+            prune: false,
+            address_index: true,
+            memo_index: false,
+            api: true,
+            analytics: true,
+            block_filters: false,
+            template_server: false,
+            light_client: false,
+        };
+        match self {
+            NodeRole::Full => full,
+            NodeRole::Archive => NodeFeatures {
+                memo_index: true,
+                analytics: false,
+                block_filters: true,
+                ..full
+            },
+            NodeRole::Explorer => NodeFeatures {
+                block_filters: true,
+                ..full
+            },
+            NodeRole::Miner => NodeFeatures {
+                prune: true,
+                address_index: false,
+                analytics: false,
+                template_server: true,
+                ..full
+            },
+            NodeRole::Wallet => NodeFeatures {
+                prune: true,
+                address_index: false,
+                api: false,
+                analytics: false,
+                light_client: true,
+                ..full
+            },
+        }
+    }
+}
+
+/// `[node]`: a role preset plus optional per-feature overrides
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NodeConfig {
+    #[serde(default)]
+    pub role: NodeRole,
+    #[serde(default)]
+    pub prune: Option<bool>,
+    #[serde(default)]
+    pub address_index: Option<bool>,
+    #[serde(default)]
+    pub memo_index: Option<bool>,
+    #[serde(default)]
+    pub api: Option<bool>,
+    #[serde(default)]
+    pub analytics: Option<bool>,
+    #[serde(default)]
+    pub block_filters: Option<bool>,
+    #[serde(default)]
+    pub template_server: Option<bool>,
+    #[serde(default)]
+    pub light_client: Option<bool>,
+    /// Recent block bodies a pruned node keeps (`DEFAULT_PRUNE_DEPTH` when unset)
+    #[serde(default)]
+    pub prune_depth: Option<u64>,
+    /// Serve live node statistics to the dashboard on `127.0.0.1:<port>`
+    /// over WebSocket when set
+    #[serde(default)]
+    pub stats_port: Option<u16>,
+}
+
+impl NodeConfig {
+    /// The role's preset with any explicitly configured features applied
+    pub fn features(&self) -> NodeFeatures {
+        let preset = self.role.preset();
+        NodeFeatures {
+            prune: self.prune.unwrap_or(preset.prune),
+            address_index: self.address_index.unwrap_or(preset.address_index),
+            memo_index: self.memo_index.unwrap_or(preset.memo_index),
+            api: self.api.unwrap_or(preset.api),
+            analytics: self.analytics.unwrap_or(preset.analytics),
+            block_filters: self.block_filters.unwrap_or(preset.block_filters),
+            template_server: self.template_server.unwrap_or(preset.template_server),
+            light_client: self.light_client.unwrap_or(preset.light_client),
+        }
+    }
+
+    /// How many recent block bodies to keep, or `None` when not pruning
+    pub fn prune_depth(&self) -> Option<u64> {
+        self.features()
+            .prune
+            .then(|| self.prune_depth.unwrap_or(DEFAULT_PRUNE_DEPTH))
+    }
+}
+
+impl Default for NodeFeatures {
+    fn default() -> Self {
+        NodeRole::default().preset()
+    }
+}
+
+// ============================================================================
+// Loading, Overrides and Validation
+// ============================================================================
+
+/// Config file read by every binary, relative to the working directory
+pub const CONFIG_PATH: &str = "config.toml";
+
+/// Prefix of environment variables overriding settings; the rest of the
+/// name is the setting's path with `__` between levels, so
+/// `TRINITY_AI_VALIDATION__ENABLED=false` sets `ai_validation.enabled`
+pub const CONFIG_ENV_PREFIX: &str = "TRINITY_";
+
+/// Shown by `trinity config show` in place of tokens, keys and secrets
+pub const REDACTED: &str = "<redacted>";
+
+fn redact<T, S: Serializer>(_: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(REDACTED)
+}
+
+/// A setting that failed validation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    /// Dotted path of the setting, e.g. `network.bootstrap_nodes[1]`
+    pub path: String,
+    pub message: String,
+}
+
+impl ConfigError {
+    fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        ConfigError {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Every problem [`Config::validate`] found, reported together
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigErrors(pub Vec<ConfigError>);
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid configuration:")?;
+        for error in &self.0 {
+            write!(f, "\n  {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
+/// `(setting path, raw value)` for every `TRINITY_<SECTION>__<KEY>`
+/// variable in the environment, sorted by path
+pub fn env_overrides() -> Vec<(String, String)> {
+    let mut overrides: Vec<_> = std::env::vars()
+        .filter_map(|(name, value)| Some((env_setting_path(&name)?, value)))
+        .collect();
+    overrides.sort();
+    overrides
+}
+
+/// The variable overriding the setting at `path`:
+/// `TRINITY_NETWORK__P2P_PORT` for `network.p2p_port`
+pub fn env_var_name(path: &str) -> String {
+    format!(
+        "{}{}",
+        CONFIG_ENV_PREFIX,
+        path.replace('.', "__").to_uppercase()
+    )
+}
+
+/// `network.p2p_port` for `TRINITY_NETWORK__P2P_PORT`; `None` for other
+/// variables, including single-level ones like `TRINITY_NODE_URL`
+fn env_setting_path(name: &str) -> Option<String> {
+    let rest = name.strip_prefix(CONFIG_ENV_PREFIX)?;
+    let segments: Vec<&str> = rest.split("__").collect();
+    if segments.len() < 2 || segments.iter().any(|s| s.is_empty()) {
+        return None;
+    }
+    Some(segments.join(".").to_lowercase())
+}
+
+/// A raw setting value as TOML (`9000`, `true`, `["a", "b"]`, `"text"`),
+/// or as a plain string when it is not valid TOML (`testnet`, `data/x.db`)
+pub fn parse_setting_value(raw: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+}
+
+/// Set the dotted `path` in `table`, creating intermediate tables
+fn set_path(table: &mut toml::Table, path: &str, value: toml::Value) -> Result<(), ConfigError> {
+    let segments: Vec<&str> = path.split('.').collect();
+    let (key, parents) = segments
+        .split_last()
+        .filter(|_| segments.iter().all(|s| !s.is_empty()))
+        .ok_or_else(|| ConfigError::new(path, "not a setting path"))?;
+    let mut current = table;
+    for (depth, parent) in parents.iter().enumerate() {
+        current = current
+            .entry(parent.to_string())
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| {
+                ConfigError::new(segments[..=depth].join("."), "is a value, not a section")
+            })?;
+    }
+    current.insert(key.to_string(), value);
+    Ok(())
+}
+
+/// Parse a config file with `overrides` (setting path, raw value) applied.
+/// Network defaults are not filled in.
+pub fn parse_config(
+    text: &str,
+    overrides: &[(String, String)],
+) -> Result<Config, Box<dyn std::error::Error>> {
+    let mut table: toml::Table = toml::from_str(text)?;
+    for (path, raw) in overrides {
+        set_path(&mut table, path, parse_setting_value(raw))?;
+    }
+    Ok(toml::Value::Table(table).try_into()?)
+}
+
+/// `config.toml` with environment overrides, the selected network's
+/// defaults and the database path resolved against the data directory
+pub fn read_config() -> Result<Config, Box<dyn std::error::Error>> {
+    let config_str = fs::read_to_string(CONFIG_PATH)?;
+    let mut config = parse_config(&config_str, &env_overrides())?;
+    config.apply_network_defaults();
+    config.database.path = DataDir::resolve(&config)
+        .db_path()
+        .to_string_lossy()
+        .into_owned();
+    Ok(config)
+}
+
+/// [`read_config`], made the active network but not yet validated
+pub fn effective_config() -> Result<Config, Box<dyn std::error::Error>> {
+    let config = read_config()?;
+    set_active_network(config.network.chain);
+    Ok(config)
+}
+
+/// Load `config.toml` with environment overrides, fill in the selected
+/// network's defaults, make it the active network and validate it
 pub fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
-    let config_str = fs::read_to_string("config.toml")?;
-    let config: Config = toml::from_str(&config_str)?;
+    let config = effective_config()?;
+    config.validate()?;
+
+    if let Some(dir) = Path::new(&config.database.path).parent() {
+        if !dir.as_os_str().is_empty() {
+            fs::create_dir_all(dir)?;
+        }
+    }
     Ok(config)
 }
+
+/// Add `addr` to `bootstrap_nodes` in `config.toml`, keeping the rest of the
+/// file as it is. Returns whether it was added (false if already listed).
+pub fn save_bootstrap_node(addr: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    let config_str = fs::read_to_string(CONFIG_PATH)?;
+    match with_bootstrap_node(&config_str, addr)? {
+        Some(updated) => {
+            fs::write(CONFIG_PATH, updated)?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// `config` with `addr` appended to `[network] bootstrap_nodes`, creating
+/// the key if needed; `None` if it is already there
+fn with_bootstrap_node(
+    config: &str,
+    addr: &str,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    #[derive(Deserialize)]
+    struct Bootstrap {
+        #[serde(default)]
+        network: Option<BootstrapNetwork>,
+    }
+    #[derive(Deserialize)]
+    struct BootstrapNetwork {
+        #[serde(default)]
+        bootstrap_nodes: Vec<String>,
+    }
+
+    let parsed: Bootstrap = toml::from_str(config)?;
+    let mut nodes = parsed
+        .network
+        .map(|n| n.bootstrap_nodes)
+        .unwrap_or_default();
+    if nodes.iter().any(|node| node == addr) {
+        return Ok(None);
+    }
+    nodes.push(addr.to_string());
+    Ok(Some(with_setting_line(
+        config,
+        "network",
+        "bootstrap_nodes",
+        &toml::Value::from(nodes),
+    )))
+}
+
+/// `config` with `key = value` in `[section]`: the existing line replaced,
+/// or a new line at the top of the section, appending the section if needed.
+/// Comments and the rest of the file are kept as they are.
+fn with_setting_line(config: &str, section: &str, key: &str, value: &toml::Value) -> String {
+    let header = format!("[{}]", section);
+    let line = format!("{} = {}", key, value);
+
+    let mut lines: Vec<String> = config.lines().map(String::from).collect();
+    let mut current = "";
+    let mut section_at = None;
+    let mut existing = None;
+    for (i, text) in lines.iter().enumerate() {
+        let trimmed = text.trim();
+        if trimmed.starts_with('[') {
+            current = trimmed;
+            if current == header {
+                section_at = Some(i);
+            }
+        } else if current == header {
+            if let Some((name, old)) = trimmed.split_once('=') {
+                if name.trim() == key {
+                    existing = Some((i, old.trim_start().starts_with('[')));
+                    break;
+                }
+            }
+        }
+    }
+    match (existing, section_at) {
+        (Some((start, is_array)), _) => {
+            // An old array may span several lines
+            let end = if is_array {
+                (start..lines.len())
+                    .find(|&i| lines[i].contains(']'))
+                    .unwrap_or(start)
+            } else {
+                start
+            };
+            lines.splice(start..=end, [line]);
+        }
+        (None, Some(i)) => lines.insert(i + 1, line),
+        (None, None) => {
+            if lines.last().is_some_and(|last| !last.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.extend([header, line]);
+        }
+    }
+
+    let mut updated = lines.join("\n");
+    if config.is_empty() || config.ends_with('\n') {
+        updated.push('\n');
+    }
+    updated
+}
+
+/// Set `path` (`section.key`) to `raw` in `config.toml`, creating the file
+/// if needed. The result must parse and validate, and `path` must name a
+/// known setting, or nothing is written. Returns the value as stored.
+pub fn set_config_value(path: &str, raw: &str) -> Result<toml::Value, Box<dyn std::error::Error>> {
+    let config_str = match fs::read_to_string(CONFIG_PATH) {
+        Ok(config_str) => config_str,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let value = parse_setting_value(raw);
+    let updated = with_setting(&config_str, path, &value)?;
+
+    let mut config = parse_config(&updated, &[])?;
+    if Config::lookup(&config.to_toml_value()?, path).is_none() {
+        return Err(ConfigError::new(path, "unknown setting").into());
+    }
+    config.apply_network_defaults();
+    set_active_network(config.network.chain);
+    config.validate()?;
+
+    fs::write(CONFIG_PATH, updated)?;
+    Ok(value)
+}
+
+/// `config` with the setting at `path` set to `value`
+fn with_setting(config: &str, path: &str, value: &toml::Value) -> Result<String, ConfigError> {
+    let (section, key) = path
+        .rsplit_once('.')
+        .filter(|(section, key)| !section.is_empty() && !key.is_empty())
+        .ok_or_else(|| ConfigError::new(path, "expected <section>.<key>"))?;
+    // Confirm the edited file still parses
+    let mut table: toml::Table =
+        toml::from_str(config).map_err(|e| ConfigError::new(CONFIG_PATH, e.to_string()))?;
+    set_path(&mut table, path, value.clone())?;
+    Ok(with_setting_line(config, section, key, value))
+}
+
+impl Config {
+    /// Fill unset ports with the network's defaults and move the database
+    /// into the network's data directory
+    pub fn apply_network_defaults(&mut self) {
+        let chain = self.network.chain;
+        if self.network.p2p_port == 0 {
+            self.network.p2p_port = chain.default_p2p_port();
+        }
+        if self.network.api_port == 0 {
+            self.network.api_port = chain.default_api_port();
+        }
+        if self.replication.port == 0 {
+            self.replication.port = self.network.p2p_port.saturating_add(1);
+        }
+        self.database.path = chain.data_path(&self.database.path);
+    }
+
+    /// The settings a fresh `config.toml` for `network` starts from, ports
+    /// filled in
+    pub fn defaults_for(network: Network) -> Config {
+        let mut config = Config::default();
+        config.network.chain = network;
+        config.network.p2p_port = network.default_p2p_port();
+        config.network.api_port = network.default_api_port();
+        config.replication.port = config.network.p2p_port.saturating_add(1);
+        config
+    }
+
+    /// Every setting as a TOML table; secrets read [`REDACTED`]
+    pub fn to_toml_value(&self) -> Result<toml::Value, toml::ser::Error> {
+        toml::Value::try_from(self)
+    }
+
+    /// The value at a dotted setting path (`network`, `network.p2p_port`)
+    pub fn lookup<'a>(value: &'a toml::Value, path: &str) -> Option<&'a toml::Value> {
+        path.split('.')
+            .try_fold(value, |value, segment| value.as_table()?.get(segment))
+    }
+
+    /// Check settings the types alone cannot, such as addresses, URLs and
+    /// port clashes. Addresses are parsed for the active network, so call
+    /// this after `set_active_network`.
+    pub fn validate(&self) -> Result<(), ConfigErrors> {
+        let mut errors = Vec::new();
+        let mut check = |ok: bool, path: String, message: &str| {
+            if !ok {
+                errors.push(ConfigError::new(path, message));
+            }
+        };
+        let is_url = |url: &str| url.starts_with("http://") || url.starts_with("https://");
+        let is_hash = |hash: &str| hash.len() == 64 && hex::decode(hash).is_ok();
+
+        let network = &self.network;
+        for (i, addr) in network.bootstrap_nodes.iter().enumerate() {
+            check(
+                addr.parse::<Node>().is_ok(),
+                format!("network.bootstrap_nodes[{}]", i),
+                "expected host:port",
+            );
+        }
+        check(
+            network.p2p_port != network.api_port,
+            "network.api_port".into(),
+            "same port as network.p2p_port",
+        );
+        if let Some(url) = &network.peer_events_webhook {
+            check(
+                is_url(url),
+                "network.peer_events_webhook".into(),
+                "expected an http(s) URL",
+            );
+        }
+
+        check(
+            !self.database.path.trim().is_empty(),
+            "database.path".into(),
+            "must not be empty",
+        );
+
+        let miner = &self.miner;
+        check(
+            miner.threads >= 1,
+            "miner.threads".into(),
+            "must be at least 1",
+        );
+        check(
+            miner.beneficiary_address.is_empty()
+                || Address::parse(&miner.beneficiary_address).is_ok(),
+            "miner.beneficiary_address".into(),
+            "not a valid address for this network",
+        );
+        check(
+            miner.stale_tip_multiple >= 1,
+            "miner.stale_tip_multiple".into(),
+            "must be at least 1",
+        );
+
+        let faucet = &self.faucet;
+        check(
+            !faucet.enabled || (faucet.amount.is_finite() && faucet.amount > 0.0),
+            "faucet.amount".into(),
+            "must be positive",
+        );
+
+        let watchtower = &self.watchtower;
+        for (i, hash) in watchtower.triangles.iter().enumerate() {
+            check(
+                is_hash(hash),
+                format!("watchtower.triangles[{}]", i),
+                "expected a 64-character hex triangle hash",
+            );
+        }
+        for (i, addr) in watchtower.addresses.iter().enumerate() {
+            check(
+                Address::parse(addr).is_ok(),
+                format!("watchtower.addresses[{}]", i),
+                "not a valid address for this network",
+            );
+        }
+        if let Some(url) = &watchtower.webhook_url {
+            check(
+                is_url(url),
+                "watchtower.webhook_url".into(),
+                "expected an http(s) URL",
+            );
+        }
+
+        for (i, key) in self.api.keys.iter().enumerate() {
+            check(
+                !key.key.is_empty(),
+                format!("api.keys[{}].key", i),
+                "must not be empty",
+            );
+        }
+        let rate_limit = &self.api.rate_limit;
+        for (class, limit) in [
+            ("read", rate_limit.read),
+            ("submit", rate_limit.submit),
+            ("control", rate_limit.control),
+        ] {
+            check(
+                limit.per_minute >= 1,
+                format!("api.rate_limit.{}.per_minute", class),
+                "must be at least 1",
+            );
+            check(
+                limit.burst >= 1,
+                format!("api.rate_limit.{}.burst", class),
+                "must be at least 1",
+            );
+        }
+
+        check(
+            self.node.prune_depth != Some(0),
+            "node.prune_depth".into(),
+            "must be at least 1",
+        );
+
+        for (i, addr) in self.policy.blocked_addresses.iter().enumerate() {
+            check(
+                Address::parse(addr).is_ok(),
+                format!("policy.blocked_addresses[{}]", i),
+                "not a valid address for this network",
+            );
+        }
+
+        let replication = &self.replication;
+        if replication.mode != ReplicationMode::Off {
+            check(
+                replication
+                    .secret()
+                    .is_some_and(|secret| crate::replication::check_secret(&secret).is_ok()),
+                "replication.secret".into(),
+                "missing or too short",
+            );
+            check(
+                replication.port != network.p2p_port && replication.port != network.api_port,
+                "replication.port".into(),
+                "clashes with the P2P or API port",
+            );
+        }
+        if replication.mode == ReplicationMode::Standby {
+            check(
+                replication
+                    .primary
+                    .as_ref()
+                    .is_some_and(|primary| primary.parse::<Node>().is_ok()),
+                "replication.primary".into(),
+                "a standby needs the primary's host:port",
+            );
+        }
+
+        let logging = &self.logging;
+        check(
+            parse_level(&logging.level).is_ok(),
+            "logging.level".into(),
+            "expected trace, debug, info, warn, error or off",
+        );
+        for (module, level) in &logging.modules {
+            check(
+                parse_level(level).is_ok(),
+                format!("logging.modules.{}", module),
+                "expected trace, debug, info, warn, error or off",
+            );
+        }
+        check(
+            logging.max_file_mb >= 1,
+            "logging.max_file_mb".into(),
+            "must be at least 1",
+        );
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigErrors(errors))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINIMAL: &str = r#"
+        [network]
+        chain = "testnet"
+
+        [database]
+        path = "data/trinity.db"
+
+        [miner]
+        threads = 1
+        beneficiary_address = ""
+    "#;
+
+    #[test]
+    fn test_bootstrap_node_saved_in_place() {
+        let updated = with_bootstrap_node(MINIMAL, "10.0.0.1:8333")
+            .unwrap()
+            .unwrap();
+        let config: Config = toml::from_str(&updated).unwrap();
+        assert_eq!(config.network.bootstrap_nodes, vec!["10.0.0.1:8333"]);
+        assert_eq!(config.database.path, "data/trinity.db");
+
+        let updated = with_bootstrap_node(&updated, "seed.example:18333")
+            .unwrap()
+            .unwrap();
+        let config: Config = toml::from_str(&updated).unwrap();
+        assert_eq!(config.network.bootstrap_peers().len(), 2);
+        assert_eq!(config.network.bootstrap_peers()[1].host, "seed.example");
+        assert!(with_bootstrap_node(&updated, "10.0.0.1:8333")
+            .unwrap()
+            .is_none());
+
+        let multiline = MINIMAL.replace(
+            "chain = \"testnet\"",
+            "chain = \"testnet\"\nbootstrap_nodes = [\n    \"10.0.0.1:8333\",\n]",
+        );
+        let updated = with_bootstrap_node(&multiline, "10.0.0.2:8333")
+            .unwrap()
+            .unwrap();
+        let config: Config = toml::from_str(&updated).unwrap();
+        assert_eq!(config.network.bootstrap_nodes.len(), 2);
+    }
+
+    #[test]
+    fn test_network_defaults_applied() {
+        let mut config: Config = toml::from_str(MINIMAL).unwrap();
+        config.apply_network_defaults();
+        assert_eq!(config.network.chain, Network::Testnet);
+        assert_eq!(config.network.p2p_port, 18333);
+        assert_eq!(config.network.api_port, 13000);
+        assert_eq!(config.replication.mode, ReplicationMode::Off);
+        assert_eq!(config.replication.port, 18334);
+        assert_eq!(config.database.path, "data/testnet/trinity.db");
+
+        let mainnet: Config =
+            toml::from_str(&MINIMAL.replace("chain = \"testnet\"", "p2p_port = 9000")).unwrap();
+        assert_eq!(mainnet.network.chain, Network::Mainnet);
+        assert_eq!(mainnet.network.p2p_port, 9000);
+        assert_eq!(Network::Mainnet.data_path("trinity.db"), "trinity.db");
+        assert_eq!(Network::Regtest.data_path("trinity.db"), "regtest/trinity.db");
+    }
+
+    #[test]
+    fn test_protocol_policy_from_config() {
+        let config: Config = toml::from_str(&MINIMAL.replace(
+            "chain = \"testnet\"",
+            "min_protocol_version = 4\n\
+             [[network.protocol_deadlines]]\n\
+             height = 500\n\
+             min_version = 5",
+        ))
+        .unwrap();
+        let policy = config.network.protocol_policy();
+        assert_eq!(policy.min_version_at(499), 4);
+        assert_eq!(policy.min_version_at(500), 5);
+
+        let defaults: Config = toml::from_str(MINIMAL).unwrap();
+        assert_eq!(
+            defaults.network.protocol_policy(),
+            ProtocolPolicy::default()
+        );
+    }
+
+    #[test]
+    fn test_networks_are_distinct() {
+        let networks = [Network::Mainnet, Network::Testnet, Network::Regtest];
+        for (i, a) in networks.iter().enumerate() {
+            assert_eq!(Network::from_index(a.index()), *a);
+            for b in &networks[i + 1..] {
+                assert_ne!(a.magic(), b.magic());
+                assert_ne!(a.address_version(), b.address_version());
+                assert_ne!(a.genesis_timestamp(), b.genesis_timestamp());
+                assert_ne!(a.default_p2p_port(), b.default_p2p_port());
+            }
+        }
+        assert!(Network::Regtest.dns_seeds().is_empty());
+    }
+
+    #[test]
+    fn test_client_section_optional() {
+        let config: Config = toml::from_str(MINIMAL).unwrap();
+        assert!(config.client.node_url.is_none());
+
+        let config: Config = toml::from_str(&format!(
+            "{}\n[client]\nnode_url = \"http://10.0.0.1:3000\"\n",
+            MINIMAL
+        ))
+        .unwrap();
+        assert_eq!(config.client.node_url.as_deref(), Some("http://10.0.0.1:3000"));
+    }
+
+    #[test]
+    fn test_role_presets_and_overrides() {
+        let config: Config = toml::from_str(MINIMAL).unwrap();
+        assert_eq!(config.node.role, NodeRole::Full);
+        assert_eq!(config.node.features(), NodeFeatures::default());
+
+        let archive = NodeRole::Archive.preset();
+        assert!(!archive.prune && archive.address_index && archive.block_filters);
+        let miner = NodeRole::Miner.preset();
+        assert!(miner.template_server && !miner.address_index);
+        let wallet = NodeRole::Wallet.preset();
+        assert!(wallet.light_client && !wallet.api);
+
+        let config: Config = toml::from_str(&format!(
+            "{}\n[node]\nrole = \"miner\"\naddress_index = true\n",
+            MINIMAL
+        ))
+        .unwrap();
+        let features = config.node.features();
+        assert!(features.template_server && features.prune);
+        assert!(features.address_index);
+        assert_eq!(config.node.prune_depth(), Some(DEFAULT_PRUNE_DEPTH));
+
+        let config: Config = toml::from_str(&format!(
+            "{}\n[node]\nprune = true\nprune_depth = 500\n",
+            MINIMAL
+        ))
+        .unwrap();
+        assert_eq!(config.node.prune_depth(), Some(500));
+        let config: Config = toml::from_str(MINIMAL).unwrap();
+        assert_eq!(config.node.prune_depth(), None);
+
+        assert!(toml::from_str::<Config>(&format!("{}\n[node]\nrole = \"relay\"\n", MINIMAL)).is_err());
+    }
+
+    #[test]
+    fn test_env_overrides_and_validation_paths() {
+        assert_eq!(
+            env_setting_path("TRINITY_NETWORK__P2P_PORT").as_deref(),
+            Some("network.p2p_port")
+        );
+        assert_eq!(
+            env_setting_path("TRINITY_AI_VALIDATION__ENABLED").as_deref(),
+            Some("ai_validation.enabled")
+        );
+        assert_eq!(env_setting_path("TRINITY_NODE_URL"), None);
+        assert_eq!(env_var_name("logging.level"), "TRINITY_LOGGING__LEVEL");
+
+        let overrides = [
+            ("network.p2p_port".to_string(), "9000".to_string()),
+            (
+                "network.bootstrap_nodes".to_string(),
+                r#"["10.0.0.1:8333"]"#.to_string(),
+            ),
+            ("database.path".to_string(), "data/other.db".to_string()),
+            ("logging.level".to_string(), "debug".to_string()),
+        ];
+        let config = parse_config(MINIMAL, &overrides).unwrap();
+        assert_eq!(config.network.p2p_port, 9000);
+        assert_eq!(config.network.bootstrap_nodes, vec!["10.0.0.1:8333"]);
+        assert_eq!(config.database.path, "data/other.db");
+        assert_eq!(config.logging.level, "debug");
+
+        let bad_type = [("network.p2p_port".to_string(), "many".to_string())];
+        let error = parse_config(MINIMAL, &bad_type).unwrap_err().to_string();
+        assert!(error.contains("network.p2p_port"), "{}", error);
+
+        let mut config = parse_config(
+            MINIMAL,
+            &[
+                (
+                    "network.bootstrap_nodes".to_string(),
+                    r#"["10.0.0.1:8333", "nope"]"#.to_string(),
+                ),
+                ("network.api_port".to_string(), "18333".to_string()),
+                ("miner.threads".to_string(), "0".to_string()),
+                ("replication.mode".to_string(), "standby".to_string()),
+                ("logging.modules.network".to_string(), "loud".to_string()),
+            ],
+        )
+        .unwrap();
+        config.apply_network_defaults();
+        let paths: Vec<String> = config
+            .validate()
+            .unwrap_err()
+            .0
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "network.bootstrap_nodes[1]",
+                "network.api_port",
+                "miner.threads",
+                "replication.secret",
+                "replication.primary",
+                "logging.modules.network",
+            ]
+        );
+
+        let mut defaults = Config::defaults_for(Network::Testnet);
+        assert_eq!(defaults.network.p2p_port, 18333);
+        assert!(defaults.validate().is_ok());
+        defaults.api.admin_token = Some("hunter2".to_string());
+        let shown = defaults.to_toml_value().unwrap();
+        assert_eq!(
+            Config::lookup(&shown, "api.admin_token").unwrap().as_str(),
+            Some(REDACTED)
+        );
+        assert!(Config::lookup(&shown, "replication.secret").is_none());
+        assert!(Config::lookup(&shown, "network.p2pport").is_none());
+    }
+
+    #[test]
+    fn test_setting_written_in_place() {
+        let commented = format!(
+            "# my node\n{}",
+            MINIMAL.replace("threads = 1", "threads = 1 # cores")
+        );
+        let updated = with_setting(&commented, "miner.threads", &toml::Value::Integer(4)).unwrap();
+        assert!(updated.starts_with("# my node\n"));
+        let config: Config = toml::from_str(&updated).unwrap();
+        assert_eq!(config.miner.threads, 4);
+        assert_eq!(config.database.path, "data/trinity.db");
+
+        let updated = with_setting(
+            &updated,
+            "logging.modules.network",
+            &parse_setting_value("debug"),
+        )
+        .unwrap();
+        let config: Config = toml::from_str(&updated).unwrap();
+        assert_eq!(config.logging.modules["network"], "debug");
+        assert_eq!(config.miner.threads, 4);
+
+        assert!(with_setting(MINIMAL, "threads", &toml::Value::Integer(4)).is_err());
+        assert!(with_setting(MINIMAL, "miner.threads.max", &toml::Value::Integer(4)).is_err());
+        let fresh = with_setting("", "miner.threads", &toml::Value::Integer(2)).unwrap();
+        assert_eq!(fresh, "[miner]\nthreads = 2\n");
+        assert_eq!(toml::from_str::<Config>(&fresh).unwrap().miner.threads, 2);
+    }
+}
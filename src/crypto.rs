@@ -1,6 +1,8 @@
 //! Cryptographic primitives for TrinityChain
 
+use crate::config::{active_network, Network};
 use crate::error::ChainError;
+use crate::security::SecretBytes;
 use once_cell::sync::Lazy;
 use rand::rngs::OsRng;
 use secp256k1::{
@@ -14,31 +16,189 @@ use sha2::{Digest, Sha256};
 /// This prevents repeated, unnecessary context creation.
 static SECP256K1_CONTEXT: Lazy<Secp256k1<All>> = Lazy::new(Secp256k1::new);
 
-/// Type alias for the derived address, which is a 32-byte hash.
-/// We use a fixed-size array for internal type safety and performance.
-pub type Address = [u8; 32];
+/// An address: the SHA-256 hash of a compressed public key.
+///
+/// Addresses are shown as base58check (version byte plus a 4-byte checksum)
+/// so a mistyped address is rejected instead of silently burning funds. The
+/// version byte depends on the active network, so a testnet address cannot
+/// be pasted into a mainnet wallet.
+/// Parsing also accepts the legacy 64-character hex form. On the wire and on
+/// disk an address serializes exactly like the raw `[u8; 32]` it used to be,
+/// so existing chain data, databases and wallets load unchanged.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Address([u8; 32]);
+
+impl Address {
+    /// The all-zero address, used as a placeholder (e.g. an unset beneficiary)
+    pub const ZERO: Address = Address([0; 32]);
+
+    pub const fn from_bytes(bytes: [u8; 32]) -> Self {
+        Address(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// The address belonging to a compressed public key
+    pub fn from_public_key(public_key_bytes: &[u8]) -> Self {
+        Address(Sha256::digest(public_key_bytes).into())
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == [0; 32]
+    }
+
+    /// Legacy 64-character hex form
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+
+    /// Parse a legacy 64-character hex address
+    pub fn from_hex(hex_str: &str) -> Result<Self, ChainError> {
+        let mut bytes = [0u8; 32];
+        hex::decode_to_slice(hex_str, &mut bytes)
+            .map_err(|e| ChainError::CryptoError(format!("Invalid hex address: {}", e)))?;
+        Ok(Address(bytes))
+    }
+
+    /// Parse a base58check address, or a legacy 64-character hex address
+    pub fn parse(s: &str) -> Result<Self, ChainError> {
+        let s = s.trim();
+        if s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Self::from_hex(s);
+        }
+
+        let bytes = bs58::decode(s)
+            .with_check(None)
+            .into_vec()
+            .map_err(|e| ChainError::CryptoError(format!("Invalid address {}: {}", s, e)))?;
+        let version = *bytes
+            .first()
+            .ok_or_else(|| ChainError::CryptoError(format!("Invalid address {}: empty", s)))?;
+        let network = active_network();
+        if version != network.address_version() {
+            let other = [Network::Mainnet, Network::Testnet, Network::Regtest]
+                .into_iter()
+                .find(|n| n.address_version() == version);
+            return Err(ChainError::CryptoError(match other {
+                Some(other) => format!(
+                    "Address {} is a {} address, but this node runs on {}",
+                    s,
+                    other.name(),
+                    network.name()
+                ),
+                None => format!("Invalid address {}: unknown version byte {:#04x}", s, version),
+            }));
+        }
+        // The decoded payload still carries the version byte
+        let payload: [u8; 32] = bytes[1..].try_into().map_err(|_| {
+            ChainError::CryptoError(format!(
+                "Address must be 32 bytes, got {}",
+                bytes.len() - 1
+            ))
+        })?;
+        Ok(Address(payload))
+    }
+}
+
+impl From<[u8; 32]> for Address {
+    fn from(bytes: [u8; 32]) -> Self {
+        Address(bytes)
+    }
+}
+
+impl From<Address> for [u8; 32] {
+    fn from(address: Address) -> Self {
+        address.0
+    }
+}
+
+impl PartialEq<[u8; 32]> for Address {
+    fn eq(&self, other: &[u8; 32]) -> bool {
+        self.0 == *other
+    }
+}
+
+impl AsRef<[u8]> for Address {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl std::ops::Deref for Address {
+    type Target = [u8; 32];
+
+    fn deref(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let encoded = bs58::encode(self.0)
+            .with_check_version(active_network().address_version())
+            .into_string();
+        f.write_str(&encoded)
+    }
+}
+
+impl std::fmt::Debug for Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Address({})", self.to_hex())
+    }
+}
+
+impl std::str::FromStr for Address {
+    type Err = ChainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Address::parse(s)
+    }
+}
+
+impl serde::Serialize for Address {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Address {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if !deserializer.is_human_readable() {
+            return <[u8; 32]>::deserialize(deserializer).map(Address);
+        }
+
+        // JSON: the legacy byte array, or an encoded address string
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Bytes([u8; 32]),
+            Text(String),
+        }
+        match Repr::deserialize(deserializer)? {
+            Repr::Bytes(bytes) => Ok(Address(bytes)),
+            Repr::Text(text) => Address::parse(&text).map_err(serde::de::Error::custom),
+        }
+    }
+}
 
 /// Convenience function to create an address from a string (hashes the string).
 /// Useful for testing and debugging.
 pub fn address_from_string(s: &str) -> Address {
     let mut hasher = Sha256::new();
     hasher.update(s.as_bytes());
-    hasher.finalize().into()
+    Address(hasher.finalize().into())
 }
 
 /// Convert an address to a hex string for display.
 pub fn address_to_hex(addr: &Address) -> String {
-    hex::encode(addr)
+    addr.to_hex()
 }
 
-/// Convert a hex string to an address.
+/// Parse an address given as base58check or legacy hex.
 pub fn address_from_hex(hex_str: &str) -> Result<Address, ChainError> {
-    let bytes = hex::decode(hex_str)
-        .map_err(|e| ChainError::CryptoError(format!("Invalid hex address: {}", e)))?;
-    if bytes.len() != 32 {
-        return Err(ChainError::CryptoError(format!("Address must be 32 bytes, got {}", bytes.len())));
-    }
-    Ok(bytes.try_into().unwrap())
+    Address::parse(hex_str)
 }
 
 #[derive(Debug, Clone)]
@@ -47,6 +207,14 @@ pub struct KeyPair {
     pub public_key: PublicKey,
 }
 
+impl Drop for KeyPair {
+    /// Overwrite the secret key. Best effort: copies made by `secret_bytes()`
+    /// on the `SecretKey` are not tracked.
+    fn drop(&mut self) {
+        self.secret_key.non_secure_erase();
+    }
+}
+
 impl KeyPair {
     /// Generates a new random KeyPair using the OS random number generator.
     pub fn generate() -> Result<Self, ChainError> {
@@ -88,11 +256,14 @@ impl KeyPair {
         Ok(Self::from_secret_key(secret_key))
     }
 
+    /// The raw secret key, wiped from memory when the returned value is dropped.
+    pub fn secret_bytes(&self) -> SecretBytes {
+        SecretBytes::new(self.secret_key.secret_bytes().to_vec())
+    }
+
     /// Computes the blockchain address (SHA-256 hash of the compressed public key).
     pub fn address(&self) -> Address {
-        // Use serialize() which returns a fixed-size array
-        let pubkey_bytes: [u8; PUBLIC_KEY_SIZE] = self.public_key.serialize();
-        Sha256::digest(pubkey_bytes).into()
+        address_from_public_key(&self.public_key.serialize())
     }
 
     /// Returns the KeyPair's public key as a compressed byte array.
@@ -116,6 +287,133 @@ impl KeyPair {
     }
 }
 
+// ============================================================================
+// Signers
+// ============================================================================
+
+/// Holder of a signing key. Transactions and messages are signed through
+/// this trait, so the key can live in a local [`KeyPair`], in another
+/// process ([`ExternalSigner`]) or on a hardware wallet.
+pub trait Signer {
+    /// The compressed public key of the signing key
+    fn public_key(&self) -> Result<[u8; PUBLIC_KEY_SIZE], ChainError>;
+
+    /// Sign `message`, which is hashed with SHA-256 first, returning the
+    /// compact ECDSA signature
+    fn sign(&self, message: &[u8]) -> Result<[u8; COMPACT_SIGNATURE_SIZE], ChainError>;
+
+    /// The address of the signing key
+    fn address(&self) -> Result<Address, ChainError> {
+        Ok(address_from_public_key(&self.public_key()?))
+    }
+}
+
+impl Signer for KeyPair {
+    fn public_key(&self) -> Result<[u8; PUBLIC_KEY_SIZE], ChainError> {
+        Ok(self.public_key_bytes())
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<[u8; COMPACT_SIGNATURE_SIZE], ChainError> {
+        KeyPair::sign(self, message)
+    }
+}
+
+/// A signer in another process, e.g. a hardware wallet bridge.
+///
+/// The command is run once per request with one extra argument:
+/// - `public-key`: print the compressed public key as hex
+/// - `sign`: read the hex payload from stdin, print the 64-byte compact
+///   ECDSA signature over its SHA-256 hash as hex
+///
+/// The public key is fetched once, and every signature is verified against
+/// it before use.
+#[derive(Debug, Clone)]
+pub struct ExternalSigner {
+    program: String,
+    args: Vec<String>,
+    public_key: [u8; PUBLIC_KEY_SIZE],
+}
+
+impl ExternalSigner {
+    /// Connect to the signer run by `command` (a program and its arguments,
+    /// separated by whitespace), asking it for its public key
+    pub fn new(command: &str) -> Result<Self, ChainError> {
+        let mut words = command.split_whitespace().map(str::to_string);
+        let program = words
+            .next()
+            .ok_or_else(|| ChainError::CryptoError("Empty signer command".to_string()))?;
+        let mut signer = ExternalSigner {
+            program,
+            args: words.collect(),
+            public_key: [0; PUBLIC_KEY_SIZE],
+        };
+        let key = hex::decode(signer.request("public-key", "")?).map_err(|e| {
+            ChainError::CryptoError(format!("Signer returned a bad public key: {}", e))
+        })?;
+        signer.public_key = PublicKey::from_slice(&key)
+            .map_err(|e| {
+                ChainError::CryptoError(format!("Signer returned a bad public key: {}", e))
+            })?
+            .serialize();
+        Ok(signer)
+    }
+
+    /// Run the signer with `action`, writing `input` to its stdin; returns
+    /// its trimmed stdout
+    fn request(&self, action: &str, input: &str) -> Result<String, ChainError> {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .arg(action)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| {
+                ChainError::CryptoError(format!("Failed to run signer '{}': {}", self.program, e))
+            })?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // A signer that doesn't read its input fails below, not here
+            let _ = stdin.write_all(input.as_bytes());
+        }
+        let output = child
+            .wait_with_output()
+            .map_err(|e| ChainError::CryptoError(format!("Signer failed: {}", e)))?;
+        if !output.status.success() {
+            return Err(ChainError::CryptoError(format!(
+                "Signer '{}' refused to {} ({})",
+                self.program, action, output.status
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+impl Signer for ExternalSigner {
+    fn public_key(&self) -> Result<[u8; PUBLIC_KEY_SIZE], ChainError> {
+        Ok(self.public_key)
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<[u8; COMPACT_SIGNATURE_SIZE], ChainError> {
+        let signature = hex::decode(self.request("sign", &hex::encode(message))?).map_err(|e| {
+            ChainError::CryptoError(format!("Signer returned a bad signature: {}", e))
+        })?;
+        verify_signature(&self.public_key, message, &signature).map_err(|e| {
+            ChainError::CryptoError(format!("Signer returned an invalid signature: {}", e))
+        })?;
+        Ok(signature
+            .try_into()
+            .expect("verified signatures are compact"))
+    }
+}
+
+/// Derives the address belonging to a compressed public key
+pub fn address_from_public_key(public_key_bytes: &[u8]) -> Address {
+    Address::from_public_key(public_key_bytes)
+}
+
 /// Verifies an ECDSA signature given the raw public key bytes, message, and signature bytes.
 pub fn verify_signature(
     public_key_bytes: &[u8],
@@ -157,6 +455,89 @@ pub fn verify_signature(
         .map_err(|_| ChainError::CryptoError("Signature verification failed".to_string()))
 }
 
+// ============================================================================
+// Message signing
+// ============================================================================
+
+/// Prefix of every signed message, so a message signature can never be
+/// replayed as a transaction signature
+pub const MESSAGE_PREFIX: &str = "TrinityChain Signed Message:\n";
+
+/// A signature over a text message, carrying the public key since an
+/// address alone cannot verify one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageSignature {
+    pub public_key: [u8; PUBLIC_KEY_SIZE],
+    pub signature: [u8; COMPACT_SIGNATURE_SIZE],
+}
+
+impl MessageSignature {
+    /// Hex of the public key followed by the compact signature
+    pub fn to_hex(&self) -> String {
+        format!(
+            "{}{}",
+            hex::encode(self.public_key),
+            hex::encode(self.signature)
+        )
+    }
+
+    pub fn from_hex(s: &str) -> Result<Self, ChainError> {
+        let bytes = hex::decode(s.trim())
+            .map_err(|e| ChainError::CryptoError(format!("Invalid message signature: {}", e)))?;
+        if bytes.len() != PUBLIC_KEY_SIZE + COMPACT_SIGNATURE_SIZE {
+            return Err(ChainError::CryptoError(format!(
+                "Message signature must be {} bytes, got {}",
+                PUBLIC_KEY_SIZE + COMPACT_SIGNATURE_SIZE,
+                bytes.len()
+            )));
+        }
+        let (public_key, signature) = bytes.split_at(PUBLIC_KEY_SIZE);
+        Ok(MessageSignature {
+            public_key: public_key.try_into().expect("split at the key size"),
+            signature: signature.try_into().expect("rest is the signature"),
+        })
+    }
+}
+
+/// The bytes actually signed for `message`: the prefix, the message length
+/// and the message
+fn message_payload(message: &str) -> Vec<u8> {
+    let mut payload = MESSAGE_PREFIX.as_bytes().to_vec();
+    payload.extend_from_slice(&(message.len() as u64).to_le_bytes());
+    payload.extend_from_slice(message.as_bytes());
+    payload
+}
+
+/// Sign a text message with `signer`, proving control of its address
+pub fn sign_message<S: Signer + ?Sized>(
+    signer: &S,
+    message: &str,
+) -> Result<MessageSignature, ChainError> {
+    Ok(MessageSignature {
+        public_key: signer.public_key()?,
+        signature: signer.sign(&message_payload(message))?,
+    })
+}
+
+/// Check that `signature` signs `message` with the key behind `address`
+pub fn verify_message(
+    address: &Address,
+    message: &str,
+    signature: &MessageSignature,
+) -> Result<(), ChainError> {
+    if address_from_public_key(&signature.public_key) != *address {
+        return Err(ChainError::CryptoError(format!(
+            "Message was signed by another address ({})",
+            address_from_public_key(&signature.public_key)
+        )));
+    }
+    verify_signature(
+        &signature.public_key,
+        &message_payload(message),
+        &signature.signature,
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -255,4 +636,100 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("Secret key must be"));
     }
+
+    #[test]
+    fn test_address_checksum_roundtrip() {
+        let address = KeyPair::generate().unwrap().address();
+        let encoded = address.to_string();
+        assert_eq!(Address::parse(&encoded).unwrap(), address);
+        // Legacy hex is still accepted
+        assert_eq!(Address::parse(&address.to_hex()).unwrap(), address);
+
+        // A single changed character fails the checksum
+        let mut typo: Vec<char> = encoded.chars().collect();
+        typo[3] = if typo[3] == '2' { '3' } else { '2' };
+        assert!(Address::parse(&typo.into_iter().collect::<String>()).is_err());
+        assert!(Address::parse("miner1").is_err());
+        assert!(Address::parse("").is_err());
+
+        // Another network's address is refused by name
+        let testnet = bs58::encode(*address.as_bytes())
+            .with_check_version(Network::Testnet.address_version())
+            .into_string();
+        let err = Address::parse(&testnet).unwrap_err().to_string();
+        assert!(err.contains("testnet"), "{}", err);
+    }
+
+    #[test]
+    fn test_address_serialization_is_backwards_compatible() {
+        let address = Address::from([7u8; 32]);
+
+        // Binary encoding is unchanged from the raw byte array
+        assert_eq!(
+            bincode::serialize(&address).unwrap(),
+            bincode::serialize(&[7u8; 32]).unwrap()
+        );
+
+        // JSON accepts the legacy byte array as well as both string forms
+        let legacy = serde_json::to_string(&[7u8; 32]).unwrap();
+        assert_eq!(serde_json::from_str::<Address>(&legacy).unwrap(), address);
+        let text = format!("\"{}\"", address);
+        assert_eq!(serde_json::from_str::<Address>(&text).unwrap(), address);
+        let hex_text = format!("\"{}\"", address.to_hex());
+        assert_eq!(serde_json::from_str::<Address>(&hex_text).unwrap(), address);
+        assert!(serde_json::from_str::<Address>("\"miner1\"").is_err());
+    }
+
+    #[test]
+    fn test_message_signing() {
+        let keypair = KeyPair::generate().unwrap();
+        let signature = sign_message(&keypair, "I own this address").unwrap();
+        let decoded = MessageSignature::from_hex(&signature.to_hex()).unwrap();
+        assert_eq!(decoded, signature);
+        assert!(verify_message(&keypair.address(), "I own this address", &decoded).is_ok());
+        assert!(verify_message(&keypair.address(), "I own that address", &decoded).is_err());
+
+        let other = KeyPair::generate().unwrap();
+        assert!(verify_message(&other.address(), "I own this address", &decoded).is_err());
+        // The prefix keeps message signatures apart from raw signatures
+        let raw = keypair.sign(b"I own this address").unwrap();
+        assert_ne!(raw, signature.signature);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_external_signer() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // ECDSA signatures are deterministic, so a script can replay one
+        let keypair = KeyPair::generate().unwrap();
+        let payload = b"spend triangle";
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("signer.sh");
+        let write_script = |signature: &[u8]| {
+            std::fs::write(
+                &script,
+                format!(
+                    "#!/bin/sh\ncat > /dev/null\ncase \"$3\" in\n  public-key) echo {} ;;\n  sign) echo {} ;;\n  *) exit 1 ;;\nesac\n",
+                    hex::encode(keypair.public_key_bytes()),
+                    hex::encode(signature)
+                ),
+            )
+            .unwrap();
+            std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        };
+        write_script(&keypair.sign(payload).unwrap());
+
+        let command = format!("{} --device test", script.display());
+        let signer = ExternalSigner::new(&command).unwrap();
+        assert_eq!(signer.address().unwrap(), keypair.address());
+        let signature = signer.sign(payload).unwrap();
+        assert!(verify_signature(&keypair.public_key_bytes(), payload, &signature).is_ok());
+
+        // A signature over anything else is caught
+        assert!(signer.sign(b"another payload").is_err());
+        write_script(&[0; COMPACT_SIGNATURE_SIZE]);
+        assert!(signer.sign(payload).is_err());
+        assert!(ExternalSigner::new("/nonexistent/signer").is_err());
+    }
 }
@@ -0,0 +1,204 @@
+//! Data directory
+//!
+//! Wallets, the address book, API tokens and other per-user files live in
+//! one data directory. It is resolved once per process, from the first of:
+//!
+//! 1. `--datadir <dir>` on the command line
+//! 2. `[database] data_dir` in `config.toml` (or `TRINITY_DATABASE__DATA_DIR`)
+//! 3. `~/.trinitychain`, if it exists
+//! 4. `$XDG_DATA_HOME/trinitychain`, if `XDG_DATA_HOME` is set
+//! 5. `~/.trinitychain`
+//!
+//! When the directory is chosen explicitly (1 or 2), a relative
+//! `database.path` is taken inside it; otherwise it stays relative to the
+//! working directory as it always has. The local `mempool.json` and
+//! `peers.json` sit next to the database, so each network keeps its own.
+
+use crate::config::{self, Config};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Command-line flag every binary accepts
+pub const DATADIR_FLAG: &str = "--datadir";
+
+/// Name of the data directory under the home directory
+pub const DEFAULT_DIR_NAME: &str = ".trinitychain";
+
+/// Directory given with `--datadir`
+static OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// The process's data directory, resolved on first use
+static CURRENT: OnceLock<DataDir> = OnceLock::new();
+
+/// Use `dir` as the data directory, as `--datadir` does. Has no effect once
+/// the data directory has been resolved.
+pub fn set_override(dir: impl Into<PathBuf>) {
+    let _ = OVERRIDE.set(dir.into());
+}
+
+/// The process arguments with `--datadir <dir>` (or `--datadir=<dir>`)
+/// taken out and applied, for binaries that parse their own arguments
+pub fn args() -> Vec<String> {
+    let (args, dir) = split_datadir(std::env::args());
+    if let Some(dir) = dir {
+        set_override(dir);
+    }
+    args
+}
+
+fn split_datadir(args: impl IntoIterator<Item = String>) -> (Vec<String>, Option<String>) {
+    let mut rest = Vec::new();
+    let mut dir = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == DATADIR_FLAG {
+            dir = args.next();
+        } else if let Some(value) = arg.strip_prefix("--datadir=") {
+            dir = Some(value.to_string());
+        } else {
+            rest.push(arg);
+        }
+    }
+    (rest, dir)
+}
+
+/// Where this process keeps its files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataDir {
+    root: PathBuf,
+    database: PathBuf,
+}
+
+impl DataDir {
+    /// The data directory for `config`, whose network defaults are already
+    /// applied. Resolving twice gives the same paths.
+    pub fn resolve(config: &Config) -> DataDir {
+        let explicit = OVERRIDE
+            .get()
+            .cloned()
+            .or_else(|| config.database.data_dir.as_ref().map(PathBuf::from));
+        let database = PathBuf::from(&config.database.path);
+        match explicit {
+            Some(root) => {
+                let root = absolute(root);
+                DataDir {
+                    database: root.join(database),
+                    root,
+                }
+            }
+            None => DataDir {
+                root: absolute(default_root()),
+                database,
+            },
+        }
+    }
+
+    /// The data directory of this process, from `config.toml` when there is
+    /// one and the defaults otherwise
+    pub fn current() -> &'static DataDir {
+        CURRENT.get_or_init(|| {
+            let config = config::read_config().unwrap_or_else(|_| {
+                let mut config =
+                    config::parse_config("", &config::env_overrides()).unwrap_or_default();
+                config.apply_network_defaults();
+                config
+            });
+            DataDir::resolve(&config)
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Wallet files, the HD keystore, labels, drafts and the address book
+    pub fn wallet_dir(&self) -> &Path {
+        &self.root
+    }
+
+    /// The chain database
+    pub fn db_path(&self) -> &Path {
+        &self.database
+    }
+
+    /// Transactions queued locally by the wallet tools for the next block
+    pub fn mempool_path(&self) -> PathBuf {
+        self.beside_database("mempool.json")
+    }
+
+    /// Peer addresses kept outside the database
+    pub fn peers_path(&self) -> PathBuf {
+        self.beside_database("peers.json")
+    }
+
+    /// UTXO snapshots written by `trinity import-chain`
+    pub fn snapshots_dir(&self) -> PathBuf {
+        self.beside_database("snapshots")
+    }
+
+    fn beside_database(&self, file: &str) -> PathBuf {
+        self.database.parent().unwrap_or(Path::new("")).join(file)
+    }
+}
+
+fn default_root() -> PathBuf {
+    let Some(home) = dirs::home_dir() else {
+        return PathBuf::from(DEFAULT_DIR_NAME);
+    };
+    let legacy = home.join(DEFAULT_DIR_NAME);
+    match std::env::var_os("XDG_DATA_HOME") {
+        Some(xdg) if !xdg.is_empty() && !legacy.exists() => PathBuf::from(xdg).join("trinitychain"),
+        _ => legacy,
+    }
+}
+
+fn absolute(path: PathBuf) -> PathBuf {
+    std::path::absolute(&path).unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_datadir_flag_and_paths() {
+        let args = [
+            "trinity-send",
+            "--datadir",
+            "/srv/trinity",
+            "bob",
+            "--datadir=/x",
+            "5",
+        ]
+        .map(String::from);
+        let (rest, dir) = split_datadir(args);
+        assert_eq!(rest, vec!["trinity-send", "bob", "5"]);
+        assert_eq!(dir.as_deref(), Some("/x"));
+
+        let mut config = Config::default();
+        config.network.chain = config::Network::Testnet;
+        config.database.data_dir = Some("/srv/trinity".to_string());
+        config.apply_network_defaults();
+        let dir = DataDir::resolve(&config);
+        assert_eq!(dir.wallet_dir(), Path::new("/srv/trinity"));
+        assert_eq!(
+            dir.db_path(),
+            Path::new("/srv/trinity/testnet/trinitychain.db")
+        );
+        assert_eq!(
+            dir.mempool_path(),
+            Path::new("/srv/trinity/testnet/mempool.json")
+        );
+
+        // Resolving the resolved config changes nothing
+        config.database.path = dir.db_path().to_string_lossy().into_owned();
+        assert_eq!(DataDir::resolve(&config), dir);
+
+        // Without a chosen directory the database stays where it was
+        let mut config = Config::default();
+        config.apply_network_defaults();
+        let dir = DataDir::resolve(&config);
+        assert_eq!(dir.db_path(), Path::new("trinitychain.db"));
+        assert_eq!(dir.peers_path(), Path::new("peers.json"));
+    }
+}
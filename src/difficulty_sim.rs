@@ -0,0 +1,411 @@
+//! Difficulty adjustment simulation
+//!
+//! Replays a retarget algorithm against a synthetic hash-rate timeline so
+//! consensus parameter changes can be judged on block-time distributions
+//! before they ship. Each block needs an exponentially distributed amount of
+//! work with mean `2^difficulty` hashes; that work is consumed at whatever
+//! hash rate the profile gives over time, so a hash-rate jump in the middle
+//! of a block is accounted for exactly. Runs are seeded and reproducible.
+
+use crate::blockchain::{retarget, DIFFICULTY_ADJUSTMENT_INTERVAL, TARGET_BLOCK_TIME};
+use crate::error::ChainError;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Upper bound on simulated blocks unless the caller picks another
+pub const DEFAULT_MAX_BLOCKS: usize = 100_000;
+
+/// Block time buckets of the histogram, as multiples of `TARGET_BLOCK_TIME`
+pub const HISTOGRAM_BUCKETS: [f64; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, f64::INFINITY];
+
+/// A stretch of time mined at a constant hash rate
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HashrateSegment {
+    pub duration_secs: f64,
+    /// Hashes per second
+    pub hashrate: f64,
+}
+
+/// Hash rate over time, as read from a `--hashrate-profile` file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HashrateProfile {
+    /// Difficulty of the first block; defaults to the one whose expected
+    /// block time at the first segment's hash rate is closest to target
+    #[serde(default)]
+    pub initial_difficulty: Option<u32>,
+    pub segments: Vec<HashrateSegment>,
+}
+
+impl HashrateProfile {
+    pub fn load(path: &Path) -> Result<Self, ChainError> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ChainError::IoError(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        let profile: HashrateProfile = serde_json::from_str(&contents).map_err(|e| {
+            ChainError::IoError(format!(
+                "Invalid hash-rate profile {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    pub fn validate(&self) -> Result<(), ChainError> {
+        if self.segments.is_empty() {
+            return Err(ChainError::IoError(
+                "Hash-rate profile has no segments".to_string(),
+            ));
+        }
+        for (i, segment) in self.segments.iter().enumerate() {
+            let valid_duration = segment.duration_secs.is_finite() && segment.duration_secs > 0.0;
+            let valid_hashrate = segment.hashrate.is_finite() && segment.hashrate >= 0.0;
+            if !valid_duration || !valid_hashrate {
+                return Err(ChainError::IoError(format!(
+                    "Hash-rate segment {} needs a positive duration and a non-negative hash rate",
+                    i
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Total simulated time in seconds
+    pub fn duration(&self) -> f64 {
+        self.segments.iter().map(|s| s.duration_secs).sum()
+    }
+
+    fn starting_difficulty(&self) -> u32 {
+        self.initial_difficulty.unwrap_or_else(|| {
+            let hashes = self.segments[0].hashrate * TARGET_BLOCK_TIME as f64;
+            if hashes > 1.0 {
+                hashes.log2().round().max(1.0) as u32
+            } else {
+                1
+            }
+        })
+    }
+}
+
+/// Difficulty adjustment rule to replay
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RetargetAlgorithm {
+    /// The consensus rule: every `DIFFICULTY_ADJUSTMENT_INTERVAL` blocks,
+    /// scale difficulty by actual over expected interval time
+    Interval,
+    /// ASERT anchored at the first block: difficulty falls by one for every
+    /// `half_life_secs` the chain runs behind schedule (and rises when ahead)
+    Asert { half_life_secs: f64 },
+}
+
+impl RetargetAlgorithm {
+    pub fn name(&self) -> String {
+        match self {
+            RetargetAlgorithm::Interval => "interval (consensus)".to_string(),
+            RetargetAlgorithm::Asert { half_life_secs } => {
+                format!("asert (half-life {}s)", half_life_secs)
+            }
+        }
+    }
+
+    /// Difficulty of the block after `blocks.last()`
+    fn next_difficulty(&self, blocks: &[SimulatedBlock], current: u32) -> u32 {
+        let Some(last) = blocks.last() else {
+            return current;
+        };
+        match self {
+            RetargetAlgorithm::Interval => {
+                let height = last.height;
+                if height.is_multiple_of(DIFFICULTY_ADJUSTMENT_INTERVAL) {
+                    // blocks[0] is height 1; the genesis timestamp is 0
+                    let start = (height - DIFFICULTY_ADJUSTMENT_INTERVAL) as usize;
+                    let start_secs = start.checked_sub(1).map_or(0.0, |i| blocks[i].timestamp);
+                    let actual_ms = ((last.timestamp - start_secs) * 1000.0) as u64;
+                    retarget(current, actual_ms)
+                } else {
+                    current
+                }
+            }
+            RetargetAlgorithm::Asert { half_life_secs } => {
+                let anchor = blocks[0].difficulty as f64;
+                let ideal = last.height as f64 * TARGET_BLOCK_TIME as f64;
+                let drift = (ideal - last.timestamp) / half_life_secs;
+                (anchor + drift).round().max(1.0) as u32
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for RetargetAlgorithm {
+    type Err = ChainError;
+
+    /// `interval`, `asert` (one-hour half-life) or `asert:<seconds>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.to_lowercase();
+        match lower.split_once(':') {
+            None if lower == "interval" => Ok(RetargetAlgorithm::Interval),
+            None if lower == "asert" => Ok(RetargetAlgorithm::Asert {
+                half_life_secs: 3600.0,
+            }),
+            Some(("asert", half_life)) => match half_life.parse::<f64>() {
+                Ok(h) if h.is_finite() && h > 0.0 => {
+                    Ok(RetargetAlgorithm::Asert { half_life_secs: h })
+                }
+                _ => Err(ChainError::InvalidBlock(format!(
+                    "Invalid ASERT half-life '{}'",
+                    half_life
+                ))),
+            },
+            _ => Err(ChainError::InvalidBlock(format!(
+                "Unknown retarget algorithm '{}' (expected interval, asert or asert:<seconds>)",
+                s
+            ))),
+        }
+    }
+}
+
+/// One block of a simulated chain
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimulatedBlock {
+    pub height: u64,
+    /// Seconds since the simulated genesis block
+    pub timestamp: f64,
+    /// Seconds since the previous block
+    pub block_time: f64,
+    pub difficulty: u32,
+    /// Profile segment the block was found in
+    pub segment: usize,
+}
+
+/// Outcome of one simulation run
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    pub algorithm: RetargetAlgorithm,
+    pub blocks: Vec<SimulatedBlock>,
+    /// The run stopped at the block limit before the profile ended
+    pub truncated: bool,
+    /// Seconds since the last block when the profile ended (or was truncated)
+    pub trailing_secs: f64,
+}
+
+impl Simulation {
+    /// Mine blocks through `profile` until it ends or `max_blocks` are found
+    pub fn run(
+        profile: &HashrateProfile,
+        algorithm: RetargetAlgorithm,
+        seed: u64,
+        max_blocks: usize,
+    ) -> Simulation {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut blocks: Vec<SimulatedBlock> = Vec::new();
+        let mut difficulty = profile.starting_difficulty();
+        let mut now = 0.0;
+        let mut segment = 0;
+        let mut segment_end = profile.segments[0].duration_secs;
+
+        while blocks.len() < max_blocks {
+            // Work (in hashes) this block needs: exponential with mean 2^difficulty
+            let mut work = -(1.0 - rng.gen::<f64>()).ln() * 2f64.powi(difficulty as i32);
+            let started = now;
+            let found = loop {
+                let hashrate = profile.segments[segment].hashrate;
+                let remaining = segment_end - now;
+                if hashrate > 0.0 && work <= hashrate * remaining {
+                    now += work / hashrate;
+                    break true;
+                }
+                work -= hashrate * remaining;
+                now = segment_end;
+                segment += 1;
+                if segment == profile.segments.len() {
+                    break false;
+                }
+                segment_end += profile.segments[segment].duration_secs;
+            };
+            if !found {
+                return Simulation {
+                    algorithm,
+                    blocks,
+                    truncated: false,
+                    trailing_secs: now - started,
+                };
+            }
+
+            blocks.push(SimulatedBlock {
+                height: blocks.len() as u64 + 1,
+                timestamp: now,
+                block_time: now - started,
+                difficulty,
+                segment,
+            });
+            difficulty = algorithm.next_difficulty(&blocks, difficulty);
+        }
+
+        Simulation {
+            algorithm,
+            blocks,
+            truncated: true,
+            trailing_secs: 0.0,
+        }
+    }
+
+    /// Distribution of all block times
+    pub fn stats(&self) -> Option<BlockTimeStats> {
+        BlockTimeStats::from_times(self.blocks.iter().map(|b| b.block_time).collect())
+    }
+
+    /// Distribution of the block times found in profile segment `segment`
+    pub fn segment_stats(&self, segment: usize) -> Option<BlockTimeStats> {
+        BlockTimeStats::from_times(
+            self.blocks
+                .iter()
+                .filter(|b| b.segment == segment)
+                .map(|b| b.block_time)
+                .collect(),
+        )
+    }
+
+    /// Lowest and highest difficulty mined
+    pub fn difficulty_range(&self) -> Option<(u32, u32)> {
+        let min = self.blocks.iter().map(|b| b.difficulty).min()?;
+        let max = self.blocks.iter().map(|b| b.difficulty).max()?;
+        Some((min, max))
+    }
+
+    /// Block counts per `HISTOGRAM_BUCKETS` bucket
+    pub fn histogram(&self) -> [usize; HISTOGRAM_BUCKETS.len()] {
+        let mut counts = [0; HISTOGRAM_BUCKETS.len()];
+        for block in &self.blocks {
+            let multiple = block.block_time / TARGET_BLOCK_TIME as f64;
+            let bucket = HISTOGRAM_BUCKETS
+                .iter()
+                .position(|upper| multiple < *upper)
+                .unwrap_or(HISTOGRAM_BUCKETS.len() - 1);
+            counts[bucket] += 1;
+        }
+        counts
+    }
+}
+
+/// Summary of a set of block times, in seconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockTimeStats {
+    pub count: usize,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub median: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl BlockTimeStats {
+    pub fn from_times(mut times: Vec<f64>) -> Option<Self> {
+        if times.is_empty() {
+            return None;
+        }
+        times.sort_by(f64::total_cmp);
+        let count = times.len();
+        let mean = times.iter().sum::<f64>() / count as f64;
+        let variance = times.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / count as f64;
+        // Nearest-rank percentile
+        let percentile = |p: f64| times[((p * count as f64).ceil() as usize).clamp(1, count) - 1];
+        Some(BlockTimeStats {
+            count,
+            mean,
+            std_dev: variance.sqrt(),
+            min: times[0],
+            median: percentile(0.5),
+            p90: percentile(0.9),
+            p99: percentile(0.99),
+            max: times[count - 1],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(segments: &[(f64, f64)], initial_difficulty: Option<u32>) -> HashrateProfile {
+        HashrateProfile {
+            initial_difficulty,
+            segments: segments
+                .iter()
+                .map(|&(duration_secs, hashrate)| HashrateSegment {
+                    duration_secs,
+                    hashrate,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_steady_hashrate_averages_target() {
+        // 2^10 hashes per block at 1024/30 H/s: 30s blocks on average
+        let steady = profile(&[(30_000.0, 1024.0 / 30.0)], Some(10));
+        let asert = RetargetAlgorithm::Asert {
+            half_life_secs: 1e12,
+        };
+        let run = Simulation::run(&steady, asert, 7, DEFAULT_MAX_BLOCKS);
+        assert!(!run.truncated);
+        assert_eq!(run.difficulty_range(), Some((10, 10)));
+        let stats = run.stats().unwrap();
+        assert!((stats.mean - 30.0).abs() < 3.0, "mean {}", stats.mean);
+        assert_eq!(run.histogram().iter().sum::<usize>(), stats.count);
+
+        // Same seed, same chain
+        let again = Simulation::run(&steady, asert, 7, DEFAULT_MAX_BLOCKS);
+        assert_eq!(again.blocks, run.blocks);
+    }
+
+    #[test]
+    fn test_interval_rule_matches_consensus_retarget() {
+        let fast = profile(&[(1_000.0, 1_000_000.0)], Some(8));
+        let run = Simulation::run(&fast, RetargetAlgorithm::Interval, 1, 25);
+        assert!(run.truncated);
+        assert_eq!(run.blocks.len(), 25);
+        // Blocks 1-10 at the initial difficulty, 11-20 at the first retarget
+        assert!(run.blocks[..10].iter().all(|b| b.difficulty == 8));
+        let elapsed_ms = (run.blocks[9].timestamp * 1000.0) as u64;
+        assert_eq!(run.blocks[10].difficulty, retarget(8, elapsed_ms));
+    }
+
+    #[test]
+    fn test_outage_spans_segments() {
+        // Hash rate drops to zero for an hour mid-run
+        let outage = profile(&[(600.0, 1e9), (3_600.0, 0.0), (600.0, 1e9)], Some(30));
+        let asert = RetargetAlgorithm::Asert {
+            half_life_secs: 1e12,
+        };
+        let run = Simulation::run(&outage, asert, 3, DEFAULT_MAX_BLOCKS);
+        assert!(run.segment_stats(1).is_none());
+        assert!(run.stats().unwrap().max >= 3_600.0);
+    }
+
+    #[test]
+    fn test_parse_algorithm_and_percentiles() {
+        assert_eq!(
+            "interval".parse::<RetargetAlgorithm>().unwrap(),
+            RetargetAlgorithm::Interval
+        );
+        assert_eq!(
+            "ASERT:600".parse::<RetargetAlgorithm>().unwrap(),
+            RetargetAlgorithm::Asert {
+                half_life_secs: 600.0
+            }
+        );
+        assert!("asert:-1".parse::<RetargetAlgorithm>().is_err());
+        assert!("lwma".parse::<RetargetAlgorithm>().is_err());
+
+        let stats = BlockTimeStats::from_times((1..=100).map(f64::from).collect()).unwrap();
+        assert_eq!(stats.median, 50.0);
+        assert_eq!(stats.p90, 90.0);
+        assert_eq!(stats.p99, 99.0);
+        assert_eq!(stats.max, 100.0);
+    }
+}
@@ -1,10 +1,129 @@
 //! Peer discovery and bootstrap module
 //!
-//! Handles finding and connecting to peers via DNS seeds and manual configuration
+//! Handles finding and connecting to peers via DNS seeds, manual configuration
+//! and the persisted peer database
 
 use crate::error::ChainError;
 use crate::network::Node;
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tracing::warn;
+
+/// Delay before the first reconnect attempt to a failed peer
+pub const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(5);
+
+/// Upper bound on the reconnect delay
+pub const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30 * 60);
+
+/// Ban score at which a peer is disconnected and banned
+pub const BAN_THRESHOLD: u32 = 100;
+
+/// How long a host stays banned after crossing the threshold
+pub const BAN_DURATION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Exponential backoff delay after `failures` consecutive failed attempts
+pub fn backoff_delay(failures: u32) -> Duration {
+    if failures == 0 {
+        return Duration::ZERO;
+    }
+    RECONNECT_BASE_DELAY
+        .saturating_mul(1u32 << (failures - 1).min(16))
+        .min(RECONNECT_MAX_DELAY)
+}
+
+/// A peer as stored in the peer database
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerRecord {
+    pub node: Node,
+    /// Unix timestamp (seconds) of the last successful handshake, 0 if never
+    pub last_seen: u64,
+    /// Consecutive failed connection attempts
+    pub failures: u32,
+}
+
+impl PeerRecord {
+    pub fn new(node: Node) -> Self {
+        Self {
+            node,
+            last_seen: 0,
+            failures: 0,
+        }
+    }
+}
+
+/// A banned host as stored in the peer database. Bans apply to every port
+/// of the host, since inbound peers connect from ephemeral ports.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BanEntry {
+    pub host: String,
+    /// Unix timestamp (seconds) at which the ban lifts
+    pub banned_until: u64,
+    pub reason: String,
+}
+
+impl BanEntry {
+    /// Ban `host` for `BAN_DURATION` starting at `now`
+    pub fn new(host: String, reason: String, now: u64) -> Self {
+        Self {
+            host,
+            banned_until: now.saturating_add(BAN_DURATION.as_secs()),
+            reason,
+        }
+    }
+
+    pub fn is_active(&self, now: u64) -> bool {
+        now < self.banned_until
+    }
+}
+
+/// Hosts currently banned
+#[derive(Debug, Clone, Default)]
+pub struct BanList {
+    bans: HashMap<String, BanEntry>,
+}
+
+impl BanList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or extend a ban; an existing longer ban is kept
+    pub fn insert(&mut self, entry: BanEntry) {
+        match self.bans.get(&entry.host) {
+            Some(existing) if existing.banned_until >= entry.banned_until => {}
+            _ => {
+                self.bans.insert(entry.host.clone(), entry);
+            }
+        }
+    }
+
+    pub fn is_banned(&self, host: &str, now: u64) -> bool {
+        self.bans.get(host).is_some_and(|ban| ban.is_active(now))
+    }
+
+    /// Forget bans that have lifted, returning their hosts
+    pub fn prune(&mut self, now: u64) -> Vec<String> {
+        let expired: Vec<String> = self
+            .bans
+            .values()
+            .filter(|ban| !ban.is_active(now))
+            .map(|ban| ban.host.clone())
+            .collect();
+        for host in &expired {
+            self.bans.remove(host);
+        }
+        expired
+    }
+
+    pub fn len(&self) -> usize {
+        self.bans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bans.is_empty()
+    }
+}
 
 /// DNS seed configuration for peer discovery
 #[derive(Debug, Clone)]
@@ -76,6 +195,16 @@ impl PeerDiscovery {
         self.known_peers.insert(peer.addr());
     }
 
+    /// Add persisted peers to the known set, skipping banned hosts
+    pub fn add_peer_records(&mut self, records: &[PeerRecord], bans: &BanList, now: u64) {
+        for record in records
+            .iter()
+            .filter(|r| !bans.is_banned(&r.node.host, now))
+        {
+            self.known_peers.insert(record.node.addr());
+        }
+    }
+
     /// Get all known peers
     pub fn get_known_peers(&self) -> Vec<Node> {
         // For now, return bootstrap peers
@@ -97,7 +226,7 @@ impl PeerDiscovery {
                     }
                 }
                 Err(e) => {
-                    eprintln!("⚠️  Failed to resolve DNS seed: {}", e);
+                    warn!("⚠️  Failed to resolve DNS seed: {}", e);
                 }
             }
         }
@@ -217,6 +346,46 @@ mod tests {
         assert_eq!(discovery.peer_count(), 1);
     }
 
+    #[test]
+    fn test_backoff_delay() {
+        assert_eq!(backoff_delay(0), Duration::ZERO);
+        assert_eq!(backoff_delay(1), RECONNECT_BASE_DELAY);
+        assert_eq!(backoff_delay(3), RECONNECT_BASE_DELAY * 4);
+        assert_eq!(backoff_delay(40), RECONNECT_MAX_DELAY);
+    }
+
+    #[test]
+    fn test_add_peer_records_skips_banned() {
+        let mut discovery = PeerDiscovery::new();
+        let good = PeerRecord::new(Node::new("127.0.0.1".to_string(), 8333));
+        let banned = PeerRecord::new(Node::new("127.0.0.2".to_string(), 8333));
+        let mut bans = BanList::new();
+        bans.insert(BanEntry::new("127.0.0.2".to_string(), "test".to_string(), 1_000));
+
+        discovery.add_peer_records(&[good.clone(), banned.clone()], &bans, 1_000);
+        assert_eq!(discovery.peer_count(), 1);
+
+        // Once the ban lifts the peer is dialable again
+        let lifted = 1_000 + BAN_DURATION.as_secs();
+        discovery.add_peer_records(&[good, banned], &bans, lifted);
+        assert_eq!(discovery.peer_count(), 2);
+    }
+
+    #[test]
+    fn test_ban_list_expiry() {
+        let mut bans = BanList::new();
+        let ban = BanEntry::new("10.0.0.1".to_string(), "invalid block".to_string(), 100);
+        bans.insert(ban.clone());
+        // A shorter ban does not shorten an existing one
+        bans.insert(BanEntry::new("10.0.0.1".to_string(), "spam".to_string(), 50));
+
+        assert!(bans.is_banned("10.0.0.1", ban.banned_until - 1));
+        assert!(!bans.is_banned("10.0.0.2", 100));
+        assert!(bans.prune(ban.banned_until - 1).is_empty());
+        assert_eq!(bans.prune(ban.banned_until), vec!["10.0.0.1".to_string()]);
+        assert!(bans.is_empty());
+    }
+
     #[test]
     fn test_get_random_peers() {
         let mut discovery = PeerDiscovery::new();
@@ -0,0 +1,208 @@
+//! Offline mode and the draft transaction queue
+//!
+//! With `--offline` (or `TRINITY_OFFLINE=1`) wallet tools promise not to
+//! touch the network: no remote node is contacted and nothing is broadcast.
+//! Transactions they sign are queued here as drafts, next to the wallet
+//! files, until `trinity tx drafts --send` submits them from a connected
+//! machine. A draft is never reported as sent.
+
+use crate::error::ChainError;
+use crate::transaction::Transaction;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+/// Environment variable that turns offline mode on (`1` or `true`)
+pub const OFFLINE_ENV: &str = "TRINITY_OFFLINE";
+
+/// Remove an `--offline` flag from `args`; offline mode is also on when
+/// `TRINITY_OFFLINE` is set
+pub fn offline_from_args_or_env(args: &mut Vec<String>) -> bool {
+    let flag = match args.iter().position(|a| a == "--offline") {
+        Some(pos) => {
+            args.remove(pos);
+            true
+        }
+        None => false,
+    };
+    flag || offline_from_env()
+}
+
+/// Whether `TRINITY_OFFLINE` asks for offline mode
+pub fn offline_from_env() -> bool {
+    std::env::var(OFFLINE_ENV)
+        .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
+/// A signed transaction waiting to be submitted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Draft {
+    pub transaction: Transaction,
+    /// RFC3339 timestamp when the draft was queued
+    pub created_at: String,
+}
+
+/// Signed transactions queued while offline, oldest first
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Drafts {
+    drafts: Vec<Draft>,
+}
+
+impl Drafts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load drafts from `path`, or start empty if the file doesn't exist
+    pub fn load_or_new(path: &Path) -> Result<Self, ChainError> {
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ChainError::WalletError(format!("Failed to read drafts: {}", e)))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| ChainError::WalletError(format!("Failed to parse drafts: {}", e)))
+    }
+
+    /// Atomically write drafts to `path`
+    pub fn save(&self, path: &Path) -> Result<(), ChainError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| ChainError::WalletError(format!("Failed to serialize drafts: {}", e)))?;
+
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let mut temp_file = NamedTempFile::new_in(dir)
+            .map_err(|e| ChainError::WalletError(format!("Failed to create temp file: {}", e)))?;
+        write!(temp_file, "{}", json)
+            .map_err(|e| ChainError::WalletError(format!("Failed to write to temp file: {}", e)))?;
+        temp_file
+            .persist(path)
+            .map_err(|e| ChainError::WalletError(format!("Failed to persist drafts: {}", e)))?;
+        Ok(())
+    }
+
+    /// Queue a signed transaction; a transaction already queued is kept once
+    pub fn push(&mut self, transaction: Transaction) -> Result<(), ChainError> {
+        transaction.validate_stateless(u64::MAX)?;
+        let hash = transaction.hash();
+        if self.drafts.iter().any(|d| d.transaction.hash() == hash) {
+            return Ok(());
+        }
+        self.drafts.push(Draft {
+            transaction,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        });
+        Ok(())
+    }
+
+    /// Remove the draft whose hash (hex) starts with `prefix`; ambiguous
+    /// prefixes remove nothing
+    pub fn remove(&mut self, prefix: &str) -> Option<Draft> {
+        let prefix = prefix.to_lowercase();
+        let mut matches = self
+            .drafts
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| d.transaction.hash_str().starts_with(&prefix));
+        match (matches.next(), matches.next()) {
+            (Some((index, _)), None) => Some(self.drafts.remove(index)),
+            _ => None,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Draft> {
+        self.drafts.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.drafts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.drafts.is_empty()
+    }
+}
+
+/// Get the default draft queue path
+pub fn get_drafts_path() -> Result<PathBuf, ChainError> {
+    Ok(crate::wallet::get_wallet_dir()?.join("drafts.json"))
+}
+
+/// Load the default draft queue, starting empty if missing
+pub fn load_default() -> Result<Drafts, ChainError> {
+    Drafts::load_or_new(&get_drafts_path()?)
+}
+
+/// Save to the default draft queue location
+pub fn save_default(drafts: &Drafts) -> Result<(), ChainError> {
+    crate::wallet::ensure_wallet_dir()?;
+    drafts.save(&get_drafts_path()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KeyPair;
+    use crate::geometry::Coord;
+    use crate::rawtx;
+    use crate::transaction::TransferOutput;
+
+    fn signed(nonce: u64) -> Transaction {
+        let keypair = KeyPair::generate().unwrap();
+        let outputs = vec![TransferOutput {
+            recipient: KeyPair::generate().unwrap().address(),
+            amount: Coord::from_num(1),
+        }];
+        let mut tx = rawtx::create(
+            [1; 32],
+            keypair.address(),
+            outputs,
+            Coord::from_num(0),
+            nonce,
+            None,
+        )
+        .unwrap();
+        rawtx::sign(&mut tx, &keypair).unwrap();
+        tx
+    }
+
+    #[test]
+    fn test_drafts_queue_and_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("drafts.json");
+        let mut drafts = Drafts::load_or_new(&path).unwrap();
+        assert!(drafts.is_empty());
+
+        let first = signed(1);
+        drafts.push(first.clone()).unwrap();
+        drafts.push(first.clone()).unwrap();
+        drafts.push(signed(2)).unwrap();
+        assert_eq!(drafts.len(), 2);
+        drafts.save(&path).unwrap();
+
+        let mut loaded = Drafts::load_or_new(&path).unwrap();
+        assert_eq!(loaded.len(), 2);
+        let removed = loaded.remove(&first.hash_str()[..12]).unwrap();
+        assert_eq!(removed.transaction.hash(), first.hash());
+        assert_eq!(loaded.len(), 1);
+        assert!(loaded.remove("").is_some());
+        assert!(loaded.remove("").is_none());
+
+        // Unsigned transactions can't be queued
+        let unsigned = rawtx::create(
+            [1; 32],
+            KeyPair::generate().unwrap().address(),
+            vec![TransferOutput {
+                recipient: KeyPair::generate().unwrap().address(),
+                amount: Coord::from_num(1),
+            }],
+            Coord::from_num(0),
+            0,
+            None,
+        )
+        .unwrap();
+        assert!(loaded.push(unsigned).is_err());
+    }
+}
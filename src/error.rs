@@ -11,6 +11,7 @@ pub enum ChainError {
     InvalidMerkleRoot,
     InvalidTransaction(String),
     TriangleNotFound(String),
+    ImmatureCoinbase(String),
     CryptoError(String),
     WalletError(String),
     OrphanBlock,
@@ -23,6 +24,7 @@ pub enum ChainError {
     InvalidBlock(String),
     DoubleSpendDetected(String),
     BlockAlreadyExists,
+    FutureBlock,
 }
 
 impl fmt::Display for ChainError {
@@ -33,6 +35,7 @@ impl fmt::Display for ChainError {
             ChainError::InvalidMerkleRoot => write!(f, "Invalid Merkle root"),
             ChainError::InvalidTransaction(msg) => write!(f, "Invalid transaction: {}", msg),
             ChainError::TriangleNotFound(msg) => write!(f, "Triangle not found: {}", msg),
+            ChainError::ImmatureCoinbase(msg) => write!(f, "Immature coinbase: {}", msg),
             ChainError::CryptoError(msg) => write!(f, "Cryptographic error: {}", msg),
             ChainError::DatabaseError(msg) => write!(f, "Database error: {}", msg),
             ChainError::NetworkError(msg) => write!(f, "Network error: {}", msg),
@@ -47,6 +50,7 @@ impl fmt::Display for ChainError {
             ChainError::InvalidBlock(msg) => write!(f, "Invalid block: {}", msg),
             ChainError::DoubleSpendDetected(msg) => write!(f, "Double spend detected: {}", msg),
             ChainError::BlockAlreadyExists => write!(f, "Block already exists"),
+            ChainError::FutureBlock => write!(f, "Block timestamp is too far in the future"),
         }
     }
 }
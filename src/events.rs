@@ -0,0 +1,187 @@
+//! Chain events
+//!
+//! Every [`Blockchain`](crate::blockchain::Blockchain) owns an [`EventBus`]
+//! and publishes a [`ChainEvent`] whenever a block joins or leaves the main
+//! chain, the chain reorganizes, or a transaction enters its mempool. The
+//! WebSocket API, Telegram notifications, cache invalidation and metrics
+//! subscribe to the bus instead of re-reading the database to notice changes.
+//!
+//! Events are published while the chain's write lock is held, so a
+//! subscriber that takes the read lock after receiving one sees the chain
+//! with that change applied (and possibly later ones).
+
+use crate::blockchain::Block;
+use crate::transaction::Transaction;
+use serde_json::json;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+/// Events buffered per subscriber before slow subscribers start missing them
+pub const CHAIN_EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// A change to the main chain or the mempool
+#[derive(Debug, Clone)]
+pub enum ChainEvent {
+    /// A block joined the main chain, either extending the tip or as part
+    /// of a reorganization
+    BlockConnected(Arc<Block>),
+    /// A block left the main chain during a reorganization. Disconnected
+    /// blocks are published newest first, before the new branch connects.
+    BlockDisconnected(Arc<Block>),
+    /// The main chain switched to a longer branch. Follows the
+    /// `BlockDisconnected` and `BlockConnected` events of the switch.
+    Reorganized {
+        /// Height of the last block both branches share
+        fork_height: u64,
+        disconnected: usize,
+        connected: usize,
+    },
+    /// A transaction entered the mempool, including transactions returned
+    /// to it by a reorganization
+    TransactionAccepted(Arc<Transaction>),
+}
+
+impl ChainEvent {
+    /// Compact JSON form sent to WebSocket clients: blocks and transactions
+    /// by hash rather than in full
+    pub fn to_json(&self) -> serde_json::Value {
+        let block_json = |event: &str, block: &Block| {
+            json!({
+                "event": event,
+                "height": block.header.height,
+                "hash": hex::encode(block.hash()),
+                "transactions": block.transactions.len(),
+            })
+        };
+        match self {
+            ChainEvent::BlockConnected(block) => block_json("block_connected", block),
+            ChainEvent::BlockDisconnected(block) => block_json("block_disconnected", block),
+            ChainEvent::Reorganized {
+                fork_height,
+                disconnected,
+                connected,
+            } => json!({
+                "event": "reorganized",
+                "fork_height": fork_height,
+                "disconnected": disconnected,
+                "connected": connected,
+            }),
+            ChainEvent::TransactionAccepted(tx) => json!({
+                "event": "transaction_accepted",
+                "hash": tx.hash_str(),
+                "kind": tx.kind(),
+            }),
+        }
+    }
+}
+
+/// Broadcast channel of [`ChainEvent`]s. Clones publish to and subscribe on
+/// the same channel.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<ChainEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        EventBus {
+            sender: broadcast::channel(CHAIN_EVENT_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    /// Receive every event published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<ChainEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Whether anyone is listening; publishers skip building events otherwise
+    pub fn has_subscribers(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+
+    pub fn publish(&self, event: ChainEvent) {
+        // No subscribers is fine
+        let _ = self.sender.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::Blockchain;
+    use crate::crypto::{Address, KeyPair};
+    use crate::fixtures::{mine_next_block, signed_transfer};
+    use crate::geometry::Coord;
+
+    #[test]
+    fn test_chain_publishes_block_reorg_and_mempool_events() {
+        let owner = KeyPair::generate().unwrap();
+        let mut chain = Blockchain::new(owner.address(), 1).unwrap();
+        let mut other = chain.clone();
+        let mut events = chain.subscribe_events();
+
+        let input = *chain.state.utxo_set.keys().next().unwrap();
+        let payment = signed_transfer(
+            &owner,
+            input,
+            Address::from([2; 32]),
+            Coord::from_num(10),
+            Coord::from_num(0),
+            1,
+        );
+        chain.mempool.add_transaction(payment.clone()).unwrap();
+        assert!(matches!(
+            events.try_recv(),
+            Ok(ChainEvent::TransactionAccepted(tx)) if tx.hash() == payment.hash()
+        ));
+
+        let block = mine_next_block(&chain, owner.address(), vec![payment.clone()]);
+        chain.apply_block(block.clone()).unwrap();
+        let event = events.try_recv().unwrap();
+        assert!(matches!(&event, ChainEvent::BlockConnected(b) if b.hash() == block.hash()));
+        assert_eq!(event.to_json()["event"], "block_connected");
+        assert_eq!(event.to_json()["height"], 1);
+
+        // A clone publishes on its own bus
+        let mut branch = Vec::new();
+        for _ in 0..2 {
+            let block = mine_next_block(&other, Address::from([3; 32]), vec![]);
+            other.apply_block(block.clone()).unwrap();
+            branch.push(block);
+        }
+        assert!(events.try_recv().is_err());
+
+        // Old branch out newest first, new branch in, then the summary and
+        // the payment going back to the mempool
+        chain.reorganize(branch.clone()).unwrap();
+        assert!(matches!(
+            events.try_recv(),
+            Ok(ChainEvent::BlockDisconnected(b)) if b.hash() == block.hash()
+        ));
+        for expected in &branch {
+            assert!(matches!(
+                events.try_recv(),
+                Ok(ChainEvent::BlockConnected(b)) if b.hash() == expected.hash()
+            ));
+        }
+        assert!(matches!(
+            events.try_recv(),
+            Ok(ChainEvent::Reorganized {
+                fork_height: 0,
+                disconnected: 1,
+                connected: 2
+            })
+        ));
+        assert!(matches!(
+            events.try_recv(),
+            Ok(ChainEvent::TransactionAccepted(tx)) if tx.hash() == payment.hash()
+        ));
+        assert!(events.try_recv().is_err());
+    }
+}
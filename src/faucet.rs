@@ -0,0 +1,281 @@
+//! Testnet faucet for TrinityChain
+//!
+//! Pays a small, fixed amount from a configured faucet wallet to addresses
+//! that ask for it. Requests are rate limited per recipient address and per
+//! requester (client IP for the API, user id for the Telegram bot), and can
+//! be gated behind access tokens handed out by a captcha page or an operator.
+
+use crate::blockchain::Blockchain;
+use crate::config::FaucetConfig;
+use crate::crypto::{Address, KeyPair};
+use crate::error::ChainError;
+use crate::geometry::Coord;
+use crate::transaction::{Transaction, TransferTx};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Why a faucet request was refused
+#[derive(Debug, Clone)]
+pub enum FaucetError {
+    /// The recipient or requester was paid too recently
+    RateLimited { retry_after: Duration },
+    /// A valid access token is required and was not supplied
+    Unauthorized,
+    /// The payout could not be built or queued
+    Chain(ChainError),
+}
+
+impl fmt::Display for FaucetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FaucetError::RateLimited { retry_after } => write!(
+                f,
+                "Faucet rate limit: try again in {}",
+                humantime::format_duration(Duration::from_secs(retry_after.as_secs().max(1)))
+            ),
+            FaucetError::Unauthorized => write!(f, "Missing or invalid faucet access token"),
+            FaucetError::Chain(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for FaucetError {}
+
+impl From<ChainError> for FaucetError {
+    fn from(err: ChainError) -> Self {
+        FaucetError::Chain(err)
+    }
+}
+
+/// Faucet state: the funding key plus recent payouts for rate limiting
+pub struct Faucet {
+    keypair: KeyPair,
+    amount: Coord,
+    address_cooldown: Duration,
+    requester_cooldown: Duration,
+    access_tokens: HashSet<String>,
+    paid_addresses: HashMap<Address, Instant>,
+    paid_requesters: HashMap<String, Instant>,
+}
+
+impl Faucet {
+    /// Create a faucet that pays out from `keypair` according to `config`
+    pub fn new(keypair: KeyPair, config: &FaucetConfig) -> Self {
+        Faucet {
+            keypair,
+            amount: Coord::from_num(config.amount),
+            address_cooldown: Duration::from_secs(config.address_cooldown_secs),
+            requester_cooldown: Duration::from_secs(config.ip_cooldown_secs),
+            access_tokens: config.access_tokens.iter().cloned().collect(),
+            paid_addresses: HashMap::new(),
+            paid_requesters: HashMap::new(),
+        }
+    }
+
+    /// Build the faucet described by `config`, funded from its configured
+    /// wallet. Returns `None` when the faucet is disabled.
+    pub fn from_config(config: &FaucetConfig) -> Result<Option<Self>, ChainError> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let wallet = match &config.wallet {
+            Some(name) => crate::wallet::load_named_wallet(name)?,
+            None => crate::wallet::load_default_wallet()?,
+        };
+        Ok(Some(Faucet::new(wallet.get_keypair()?, config)))
+    }
+
+    /// Address the faucet pays from
+    pub fn address(&self) -> Address {
+        self.keypair.address()
+    }
+
+    /// Amount paid per request
+    pub fn amount(&self) -> Coord {
+        self.amount
+    }
+
+    /// Check the access token and rate limits without paying anything
+    pub fn check(
+        &self,
+        recipient: &Address,
+        requester: Option<&str>,
+        token: Option<&str>,
+    ) -> Result<(), FaucetError> {
+        if !self.access_tokens.is_empty() && !token.is_some_and(|t| self.access_tokens.contains(t))
+        {
+            return Err(FaucetError::Unauthorized);
+        }
+
+        let now = Instant::now();
+        let remaining = |last: Option<&Instant>, cooldown: Duration| {
+            last.map(|l| cooldown.saturating_sub(now.duration_since(*l)))
+                .filter(|d| !d.is_zero())
+        };
+
+        let retry_after = remaining(self.paid_addresses.get(recipient), self.address_cooldown)
+            .into_iter()
+            .chain(remaining(
+                requester.and_then(|r| self.paid_requesters.get(r)),
+                self.requester_cooldown,
+            ))
+            .max();
+
+        match retry_after {
+            Some(retry_after) => Err(FaucetError::RateLimited { retry_after }),
+            None => Ok(()),
+        }
+    }
+
+    /// Build and sign a payout to `recipient` from a faucet triangle that is
+    /// not already being spent in the mempool
+    pub fn build_payout(
+        &self,
+        chain: &Blockchain,
+        recipient: Address,
+    ) -> Result<Transaction, ChainError> {
+        let sender = self.address();
+        let locked: HashSet<_> = chain
+            .mempool
+            .get_all_transactions()
+            .into_iter()
+            .filter_map(|tx| match tx {
+                Transaction::Transfer(t) => Some(t.input_hash),
+                _ => None,
+            })
+            .collect();
+
+        // Spend the smallest sufficient triangle to keep large ones intact
+        let input_hash = chain
+            .state
+            .utxo_set
+            .iter()
+            .filter(|(hash, triangle)| {
+                triangle.owner == sender
+                    && triangle.effective_value() >= self.amount
+                    && !locked.contains(*hash)
+            })
+            .min_by_key(|(_, triangle)| triangle.effective_value())
+            .map(|(hash, _)| *hash)
+            .ok_or_else(|| {
+                ChainError::WalletError(format!(
+                    "Faucet has no unspent triangle worth at least {}",
+                    self.amount
+                ))
+            })?;
+
+        let mut tx = TransferTx::new(
+            input_hash,
+            recipient,
+            sender,
+            self.amount,
+            Coord::from_num(0),
+            chain.blocks.len() as u64,
+        )
+        .with_memo("faucet".to_string())?;
+
+        let signature = self.keypair.sign(&tx.signable_message())?;
+        tx.sign(signature.to_vec(), self.keypair.public_key_bytes().to_vec());
+        Ok(Transaction::Transfer(tx))
+    }
+
+    /// Check limits, pay `recipient` into the chain's mempool and record the
+    /// payout. The caller is responsible for broadcasting the transaction.
+    pub fn request(
+        &mut self,
+        chain: &mut Blockchain,
+        recipient: Address,
+        requester: Option<&str>,
+        token: Option<&str>,
+    ) -> Result<Transaction, FaucetError> {
+        self.check(&recipient, requester, token)?;
+
+        let tx = self.build_payout(chain, recipient)?;
+        chain.mempool.add_transaction(tx.clone())?;
+
+        let now = Instant::now();
+        self.paid_addresses.insert(recipient, now);
+        if let Some(requester) = requester {
+            self.paid_requesters.insert(requester.to_string(), now);
+        }
+        self.prune(now);
+        Ok(tx)
+    }
+
+    /// Forget payouts whose cooldowns have expired
+    fn prune(&mut self, now: Instant) {
+        let (address_cooldown, requester_cooldown) =
+            (self.address_cooldown, self.requester_cooldown);
+        self.paid_addresses
+            .retain(|_, last| now.duration_since(*last) < address_cooldown);
+        self.paid_requesters
+            .retain(|_, last| now.duration_since(*last) < requester_cooldown);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> FaucetConfig {
+        FaucetConfig {
+            amount: 10.0,
+            ..FaucetConfig::default()
+        }
+    }
+
+    fn funded_faucet(config: &FaucetConfig) -> (Faucet, Blockchain) {
+        let keypair = KeyPair::generate().unwrap();
+        let chain = Blockchain::new(keypair.address(), 1).unwrap();
+        (Faucet::new(keypair, config), chain)
+    }
+
+    #[test]
+    fn test_faucet_pays_and_rate_limits() {
+        let (mut faucet, mut chain) = funded_faucet(&test_config());
+        let alice = Address::from([1u8; 32]);
+
+        let tx = faucet
+            .request(&mut chain, alice, Some("10.0.0.1"), None)
+            .unwrap();
+        assert!(tx.addresses().contains(&alice));
+        assert_eq!(chain.mempool.len(), 1);
+
+        // Same recipient, and same requester with another recipient, are refused
+        assert!(matches!(
+            faucet.check(&alice, None, None),
+            Err(FaucetError::RateLimited { .. })
+        ));
+        assert!(matches!(
+            faucet.check(&Address::from([2u8; 32]), Some("10.0.0.1"), None),
+            Err(FaucetError::RateLimited { .. })
+        ));
+        assert!(faucet.check(&Address::from([2u8; 32]), Some("10.0.0.2"), None).is_ok());
+
+        // The only faucet triangle is now locked by the pending payout
+        assert!(matches!(
+            faucet.request(&mut chain, Address::from([2u8; 32]), Some("10.0.0.2"), None),
+            Err(FaucetError::Chain(_))
+        ));
+    }
+
+    #[test]
+    fn test_faucet_access_tokens() {
+        let config = FaucetConfig {
+            access_tokens: vec!["let-me-in".to_string()],
+            ..test_config()
+        };
+        let (faucet, _chain) = funded_faucet(&config);
+
+        assert!(matches!(
+            faucet.check(&Address::from([1u8; 32]), None, None),
+            Err(FaucetError::Unauthorized)
+        ));
+        assert!(matches!(
+            faucet.check(&Address::from([1u8; 32]), None, Some("wrong")),
+            Err(FaucetError::Unauthorized)
+        ));
+        assert!(faucet.check(&Address::from([1u8; 32]), None, Some("let-me-in")).is_ok());
+    }
+}
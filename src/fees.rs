@@ -119,6 +119,11 @@ pub fn estimate_transaction_size(tx: &Transaction) -> usize {
             let memo_size = tx.memo.as_ref().map(|m| m.len()).unwrap_or(0);
             base + memo_size
         }
+        Transaction::BatchTransfer(tx) => {
+            // Transfer fields plus ~40 bytes per output
+            let memo_size = tx.memo.as_ref().map(|m| m.len()).unwrap_or(0);
+            160 + tx.outputs.len() * 40 + memo_size
+        }
         Transaction::Subdivision(tx) => {
             // ~100 bytes for parent hash + 3 children + signature
             100 + (tx.children.len() * 50)
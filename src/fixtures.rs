@@ -0,0 +1,113 @@
+//! Validly signed test fixtures
+//!
+//! Consensus rejects unsigned transactions and signatures whose public key
+//! does not derive the spending address, so tests that build blocks by hand
+//! need properly signed transactions. Available to unit tests and, with the
+//! `test-fixtures` feature, to integration tests.
+
+use crate::blockchain::{Block, Blockchain, Sha256Hash};
+use crate::crypto::{Address, KeyPair};
+use crate::geometry::{Coord, Triangle};
+use crate::miner::mine_block;
+use crate::transaction::{
+    BatchTransferTx, CoinbaseTx, SubdivisionTx, Transaction, TransferOutput, TransferTx,
+};
+
+/// A transfer of `amount` from `keypair`'s address, signed by `keypair`
+pub fn signed_transfer(
+    keypair: &KeyPair,
+    input_hash: Sha256Hash,
+    new_owner: Address,
+    amount: Coord,
+    fee: Coord,
+    nonce: u64,
+) -> Transaction {
+    let mut tx = TransferTx::new(input_hash, new_owner, keypair.address(), amount, fee, nonce);
+    let signature = keypair
+        .sign(&tx.signable_message())
+        .expect("signing a transfer cannot fail");
+    tx.sign(signature.to_vec(), keypair.public_key_bytes().to_vec());
+    Transaction::Transfer(tx)
+}
+
+/// A batch transfer paying every `(recipient, amount)` in `outputs` out of
+/// one of `keypair`'s triangles, signed by `keypair`
+pub fn signed_batch_transfer(
+    keypair: &KeyPair,
+    input_hash: Sha256Hash,
+    outputs: &[(Address, Coord)],
+    fee: Coord,
+    nonce: u64,
+) -> Transaction {
+    let outputs = outputs
+        .iter()
+        .map(|&(recipient, amount)| TransferOutput { recipient, amount })
+        .collect();
+    let mut tx = BatchTransferTx::new(input_hash, keypair.address(), outputs, fee, nonce);
+    let signature = keypair
+        .sign(&tx.signable_message())
+        .expect("signing a batch transfer cannot fail");
+    tx.sign(signature.to_vec(), keypair.public_key_bytes().to_vec());
+    Transaction::BatchTransfer(tx)
+}
+
+/// A subdivision of `parent` (stored under `parent_hash`) into its three
+/// children, splitting the parent's value minus `fee` between them
+pub fn signed_subdivision(
+    keypair: &KeyPair,
+    parent_hash: Sha256Hash,
+    parent: &Triangle,
+    fee: Coord,
+    nonce: u64,
+) -> Transaction {
+    let value = parent.effective_value() - fee;
+    let [a, b, c] = parent.subdivide();
+    let children = vec![
+        a.with_effective_value(value / 3),
+        b.with_effective_value(value / 3),
+        c.with_effective_value(value - value / 3 * 2),
+    ];
+
+    let mut tx = SubdivisionTx::new(parent_hash, children, keypair.address(), fee, nonce);
+    let signature = keypair
+        .sign(&tx.signable_message())
+        .expect("signing a subdivision cannot fail");
+    tx.sign(signature.to_vec(), keypair.public_key_bytes().to_vec());
+    Transaction::Subdivision(tx)
+}
+
+/// Mine the block after `chain`'s tip paying the full reward to
+/// `beneficiary` and including `transactions`
+pub fn mine_next_block(
+    chain: &Blockchain,
+    beneficiary: Address,
+    transactions: Vec<Transaction>,
+) -> Block {
+    let previous = chain.blocks.last().expect("chain has a genesis block");
+    let height = previous.header.height + 1;
+    let fees = Blockchain::total_fees(&transactions);
+
+    let coinbase = Transaction::Coinbase(CoinbaseTx {
+        reward_area: Blockchain::max_coinbase_reward(height, fees),
+        beneficiary_address: beneficiary,
+        nonce: height,
+    });
+    let mut all = vec![coinbase];
+    all.extend(transactions);
+
+    let mut block = Block::new(height, previous.hash(), chain.difficulty, all);
+    block.header.timestamp = block.header.timestamp.max(previous.header.timestamp + 1);
+    seal_block(chain, block)
+}
+
+/// Set the header version and state root `block` needs on top of `chain`'s
+/// tip, then mine it
+pub fn seal_block(chain: &Blockchain, mut block: Block) -> Block {
+    block.header.version = chain.next_block_version();
+    // Tests also mine blocks with invalid transactions, which apply_block
+    // rejects before looking at the state root
+    if let Ok(root) = chain.state_root_after(&block) {
+        block.header.state_root = root;
+    }
+    mine_block(block).expect("mining a test block cannot fail")
+}
@@ -0,0 +1,273 @@
+//! Fork tracking for chain-split monitoring
+//!
+//! The node only keeps its best chain, so a competing branch is invisible
+//! until it wins a reorg. The [`ForkTree`] remembers the header of every
+//! recent block heard about, whichever branch it extends, together with the
+//! peers that sent it. Comparing those headers against the main chain yields
+//! every known tip with its branch length, accumulated work and fork point,
+//! so operators see the network splitting while it is still happening.
+
+use crate::blockchain::{Block, BlockHeader, Sha256Hash};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Headers remembered; the lowest are dropped first
+pub const MAX_TRACKED_BLOCKS: usize = 4096;
+
+/// Valid side branches ending within this many blocks of the active tip are
+/// competing for the next reorg
+pub const COMPETING_FORK_DEPTH: u64 = 6;
+
+/// Expected hashes to find a block at `difficulty` leading zero bits
+pub fn block_work(difficulty: u32) -> u128 {
+    1u128.checked_shl(difficulty).unwrap_or(u128::MAX)
+}
+
+/// How a tip relates to the main chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForkStatus {
+    /// The tip of the main chain
+    Active,
+    /// A side branch forking off the main chain
+    Fork,
+    /// A branch whose fork point with the main chain isn't known
+    Detached,
+    /// A branch containing a block that failed validation
+    Invalid,
+}
+
+/// One known chain tip
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForkTip {
+    /// Tip block hash (hex)
+    pub hash: String,
+    pub height: u64,
+    pub status: ForkStatus,
+    /// Blocks on the branch after the fork point (the whole chain for the
+    /// active tip)
+    pub branch_length: u64,
+    /// Height of the last block shared with the main chain
+    pub fork_height: Option<u64>,
+    /// Work of the chain ending at this tip, from genesis
+    pub work: u128,
+    /// Unix time the tip block was last received
+    pub last_seen: Option<u64>,
+    /// Peers that sent the tip block
+    pub peers: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+struct TrackedBlock {
+    header: BlockHeader,
+    peers: BTreeSet<String>,
+    last_seen: u64,
+    invalid: bool,
+}
+
+/// Headers of recent blocks on every known branch
+#[derive(Debug, Default)]
+pub struct ForkTree {
+    blocks: HashMap<Sha256Hash, TrackedBlock>,
+}
+
+impl ForkTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Record that `source` (a peer address, or a local name for mined
+    /// blocks) delivered the block with `header` at unix time `now`
+    pub fn record(&mut self, header: &BlockHeader, source: &str, now: u64) {
+        let entry = self
+            .blocks
+            .entry(header.hash())
+            .or_insert_with(|| TrackedBlock {
+                header: header.clone(),
+                peers: BTreeSet::new(),
+                last_seen: now,
+                invalid: false,
+            });
+        entry.peers.insert(source.to_string());
+        entry.last_seen = entry.last_seen.max(now);
+
+        while self.blocks.len() > MAX_TRACKED_BLOCKS {
+            let lowest = self
+                .blocks
+                .iter()
+                .min_by_key(|(_, b)| b.header.height)
+                .map(|(hash, _)| *hash);
+            match lowest {
+                Some(hash) => self.blocks.remove(&hash),
+                None => break,
+            };
+        }
+    }
+
+    /// Flag a block that failed validation; branches through it are reported
+    /// as invalid
+    pub fn mark_invalid(&mut self, hash: &Sha256Hash) {
+        if let Some(block) = self.blocks.get_mut(hash) {
+            block.invalid = true;
+        }
+    }
+
+    /// Every known tip compared against `main`, the best chain from genesis:
+    /// the active tip first, then the others, highest first
+    pub fn tips(&self, main: &[Block]) -> Vec<ForkTip> {
+        let main_index: HashMap<Sha256Hash, usize> = main
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (b.hash(), i))
+            .collect();
+        // Work of the main chain up to and including each block
+        let mut main_work = Vec::with_capacity(main.len());
+        let mut work = 0u128;
+        for block in main {
+            work = work.saturating_add(block_work(block.header.difficulty));
+            main_work.push(work);
+        }
+
+        let mut tips = Vec::new();
+        if let Some(tip) = main.last() {
+            let tracked = self.blocks.get(&tip.hash());
+            tips.push(ForkTip {
+                hash: hex::encode(tip.hash()),
+                height: tip.header.height,
+                status: ForkStatus::Active,
+                branch_length: main.len() as u64,
+                fork_height: Some(tip.header.height),
+                work,
+                last_seen: tracked.map(|b| b.last_seen),
+                peers: tracked.map_or_else(Vec::new, |b| b.peers.iter().cloned().collect()),
+            });
+        }
+
+        let parents: HashSet<Sha256Hash> = self
+            .blocks
+            .values()
+            .map(|b| b.header.previous_hash)
+            .collect();
+        let mut side = Vec::new();
+        for (hash, block) in &self.blocks {
+            if main_index.contains_key(hash) || parents.contains(hash) {
+                continue;
+            }
+            // Walk back to the main chain
+            let mut branch_length = 0;
+            let mut branch_work = 0u128;
+            let mut invalid = false;
+            let mut cursor = Some(block);
+            let mut fork_index = None;
+            while let Some(current) = cursor {
+                branch_length += 1;
+                branch_work = branch_work.saturating_add(block_work(current.header.difficulty));
+                invalid |= current.invalid;
+                let parent = current.header.previous_hash;
+                if let Some(&index) = main_index.get(&parent) {
+                    fork_index = Some(index);
+                    break;
+                }
+                cursor = self.blocks.get(&parent);
+            }
+            let status = match fork_index {
+                _ if invalid => ForkStatus::Invalid,
+                Some(_) => ForkStatus::Fork,
+                None => ForkStatus::Detached,
+            };
+            side.push(ForkTip {
+                hash: hex::encode(hash),
+                height: block.header.height,
+                status,
+                branch_length,
+                fork_height: fork_index.map(|i| main[i].header.height),
+                work: fork_index.map_or(branch_work, |i| main_work[i].saturating_add(branch_work)),
+                last_seen: Some(block.last_seen),
+                peers: block.peers.iter().cloned().collect(),
+            });
+        }
+        side.sort_by(|a, b| b.height.cmp(&a.height).then_with(|| a.hash.cmp(&b.hash)));
+        tips.extend(side);
+        tips
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn child(parent: &Block, nonce: u64) -> Block {
+        let mut block = Block::new(
+            parent.header.height + 1,
+            parent.hash(),
+            parent.header.difficulty,
+            vec![],
+        );
+        block.header.nonce = nonce;
+        block
+    }
+
+    #[test]
+    fn test_tips_report_side_branches() {
+        let genesis = Block::new(0, [0; 32], 2, vec![]);
+        let a1 = child(&genesis, 1);
+        let a2 = child(&a1, 1);
+        let main = vec![genesis.clone(), a1.clone(), a2.clone()];
+        // A competing branch off genesis, and a block whose parent is unknown
+        let b1 = child(&genesis, 2);
+        let b2 = child(&b1, 2);
+        let stray = child(&child(&a2, 9), 9);
+
+        let mut tree = ForkTree::new();
+        tree.record(&a2.header, "peer-a", 10);
+        tree.record(&b1.header, "peer-b", 11);
+        tree.record(&b2.header, "peer-b", 12);
+        tree.record(&b2.header, "peer-c", 13);
+        tree.record(&stray.header, "peer-d", 14);
+
+        let tips = tree.tips(&main);
+        assert_eq!(tips.len(), 3);
+        assert_eq!(tips[0].status, ForkStatus::Active);
+        assert_eq!(tips[0].hash, hex::encode(a2.hash()));
+        assert_eq!(tips[0].work, 3 * block_work(2));
+        assert_eq!(tips[0].peers, vec!["peer-a".to_string()]);
+
+        let fork = tips
+            .iter()
+            .find(|t| t.hash == hex::encode(b2.hash()))
+            .unwrap();
+        assert_eq!(fork.status, ForkStatus::Fork);
+        assert_eq!(fork.branch_length, 2);
+        assert_eq!(fork.fork_height, Some(0));
+        assert_eq!(fork.work, 3 * block_work(2));
+        assert_eq!(fork.last_seen, Some(13));
+        assert_eq!(fork.peers.len(), 2);
+
+        let detached = tips
+            .iter()
+            .find(|t| t.hash == hex::encode(stray.hash()))
+            .unwrap();
+        assert_eq!(detached.status, ForkStatus::Detached);
+        assert_eq!(detached.fork_height, None);
+
+        tree.mark_invalid(&b1.hash());
+        let tips = tree.tips(&main);
+        let fork = tips
+            .iter()
+            .find(|t| t.hash == hex::encode(b2.hash()))
+            .unwrap();
+        assert_eq!(fork.status, ForkStatus::Invalid);
+
+        // Survives the trip through the API
+        let json = serde_json::to_string(&tips).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<ForkTip>>(&json).unwrap(), tips);
+    }
+}
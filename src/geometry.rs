@@ -3,6 +3,7 @@ use crate::crypto::Address;
 use fixed::types::I32F32;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 
 /// Coordinate type for deterministic geometric calculations.
 pub type Coord = I32F32;
@@ -75,6 +76,10 @@ pub struct Triangle {
     /// Effective value of this triangle.
     #[serde(default)]
     pub value: Option<Coord>,
+    /// Subdivisions between this triangle and its coinbase ancestor (0 for
+    /// coinbase triangles). Not part of the hash: it follows from lineage.
+    #[serde(default)]
+    pub depth: u8,
 }
 
 impl Triangle {
@@ -93,6 +98,7 @@ impl Triangle {
             parent_hash,
             owner,
             value: None,
+            depth: 0,
         }
     }
 
@@ -112,6 +118,7 @@ impl Triangle {
             parent_hash,
             owner,
             value: Some(value),
+            depth: 0,
         }
     }
 
@@ -189,7 +196,7 @@ impl Triangle {
             Point::new(Coord::from_num(1.7320508), Coord::from_num(0)),
             Point::new(Coord::from_num(0.8660254), Coord::from_num(1.5)),
             None,
-            [0; 32],
+            Address::ZERO,
         )
     }
 
@@ -203,7 +210,7 @@ impl Triangle {
     ///
     /// Value is conserved by explicitly assigning one-third of the parent's
     /// effective value to each child, decoupling spendable value from raw
-    /// geometric area.
+    /// geometric area. Children are one level deeper than the parent.
     pub fn subdivide(&self) -> [Triangle; 3] {
         // Calculate midpoints of the triangle's sides
         let mid_ab = self.a.midpoint(&self.b);
@@ -239,7 +246,10 @@ impl Triangle {
             child_value,
         );
 
-        [t1, t2, t3]
+        [t1, t2, t3].map(|mut child| {
+            child.depth = self.depth.saturating_add(1);
+            child
+        })
     }
 
     // ------------------------------------------------------------------------
@@ -253,6 +263,249 @@ impl Triangle {
         }
         self.area() > GEOMETRIC_TOLERANCE
     }
+
+    // ------------------------------------------------------------------------
+    // 1.9 Spatial Queries
+    // ------------------------------------------------------------------------
+
+    /// Smallest axis-aligned box containing the triangle.
+    pub fn bounding_box(&self) -> BoundingBox {
+        BoundingBox::around(&[self.a, self.b, self.c])
+    }
+
+    /// Whether `p` lies inside the triangle or on its boundary.
+    pub fn contains_point(&self, p: &Point) -> bool {
+        if !self.bounding_box().contains_point(p) {
+            return false;
+        }
+        let d1 = orientation(&self.a, &self.b, p);
+        let d2 = orientation(&self.b, &self.c, p);
+        let d3 = orientation(&self.c, &self.a, p);
+        let has_negative = d1 < 0 || d2 < 0 || d3 < 0;
+        let has_positive = d1 > 0 || d2 > 0 || d3 > 0;
+        !(has_negative && has_positive)
+    }
+
+    /// Whether the two triangles' boundaries meet: a shared corner, a corner
+    /// lying on the other's side, or overlapping sides. Subdivision children
+    /// meet their siblings at the midpoints of the parent's sides, so this
+    /// is how neighbors are found in the subdivision tree.
+    pub fn touches(&self, other: &Triangle) -> bool {
+        let on_boundary = |t: &Triangle, p: &Point| {
+            on_segment(p, &t.a, &t.b) || on_segment(p, &t.b, &t.c) || on_segment(p, &t.c, &t.a)
+        };
+        [self.a, self.b, self.c]
+            .iter()
+            .any(|p| on_boundary(other, p))
+            || [other.a, other.b, other.c]
+                .iter()
+                .any(|p| on_boundary(self, p))
+    }
+}
+
+/// Twice the signed area of `a b p`: positive when `p` is left of `a -> b`.
+/// Computed on the raw fixed-point bits in 128 bits, so it is exact.
+fn orientation(a: &Point, b: &Point, p: &Point) -> i128 {
+    let (ax, ay) = (a.x.to_bits() as i128, a.y.to_bits() as i128);
+    let (bx, by) = (b.x.to_bits() as i128, b.y.to_bits() as i128);
+    let (px, py) = (p.x.to_bits() as i128, p.y.to_bits() as i128);
+    (bx - ax) * (py - ay) - (by - ay) * (px - ax)
+}
+
+/// Whether `p` lies on the segment `a b` (endpoints included)
+fn on_segment(p: &Point, a: &Point, b: &Point) -> bool {
+    orientation(a, b, p) == 0 && BoundingBox::around(&[*a, *b]).contains_point(p)
+}
+
+// ----------------------------------------------------------------------------
+// Spatial Index
+// ----------------------------------------------------------------------------
+
+/// Most triangles a quadtree leaf holds before it splits
+const QUADTREE_NODE_CAPACITY: usize = 8;
+
+/// Deepest a quadtree node may be; beyond this leaves just grow
+const QUADTREE_MAX_DEPTH: usize = 16;
+
+/// Axis-aligned rectangle, edges included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl BoundingBox {
+    pub fn new(min: Point, max: Point) -> Self {
+        BoundingBox { min, max }
+    }
+
+    /// Smallest box containing every point of a non-empty slice.
+    pub fn around(points: &[Point]) -> Self {
+        let mut bounds = BoundingBox::new(points[0], points[0]);
+        for p in &points[1..] {
+            bounds.min.x = bounds.min.x.min(p.x);
+            bounds.min.y = bounds.min.y.min(p.y);
+            bounds.max.x = bounds.max.x.max(p.x);
+            bounds.max.y = bounds.max.y.max(p.y);
+        }
+        bounds
+    }
+
+    pub fn contains_point(&self, p: &Point) -> bool {
+        self.min.x <= p.x && p.x <= self.max.x && self.min.y <= p.y && p.y <= self.max.y
+    }
+
+    /// Whether `other` lies entirely inside this box.
+    pub fn contains(&self, other: &BoundingBox) -> bool {
+        self.contains_point(&other.min) && self.contains_point(&other.max)
+    }
+
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+    }
+
+    fn union(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox::around(&[self.min, self.max, other.min, other.max])
+    }
+
+    fn quadrants(&self) -> [BoundingBox; 4] {
+        let mid = self.min.midpoint(&self.max);
+        [
+            BoundingBox::new(self.min, mid),
+            BoundingBox::new(Point::new(mid.x, self.min.y), Point::new(self.max.x, mid.y)),
+            BoundingBox::new(Point::new(self.min.x, mid.y), Point::new(mid.x, self.max.y)),
+            BoundingBox::new(mid, self.max),
+        ]
+    }
+}
+
+/// Quadtree node. A triangle is stored in the deepest node whose bounds
+/// contain its whole bounding box, so triangles straddling a split stay in
+/// the parent.
+#[derive(Debug, Clone)]
+struct QuadNode {
+    bounds: BoundingBox,
+    items: Vec<(Sha256Hash, BoundingBox)>,
+    children: Option<Box<[QuadNode; 4]>>,
+}
+
+impl QuadNode {
+    fn new(bounds: BoundingBox) -> Self {
+        QuadNode {
+            bounds,
+            items: Vec::new(),
+            children: None,
+        }
+    }
+
+    fn insert(&mut self, hash: Sha256Hash, bbox: BoundingBox, depth: usize) {
+        if let Some(children) = &mut self.children {
+            match children
+                .iter_mut()
+                .find(|child| child.bounds.contains(&bbox))
+            {
+                Some(child) => child.insert(hash, bbox, depth + 1),
+                None => self.items.push((hash, bbox)),
+            }
+            return;
+        }
+        self.items.push((hash, bbox));
+        if self.items.len() > QUADTREE_NODE_CAPACITY && depth < QUADTREE_MAX_DEPTH {
+            self.children = Some(Box::new(self.bounds.quadrants().map(QuadNode::new)));
+            for (hash, bbox) in std::mem::take(&mut self.items) {
+                self.insert(hash, bbox, depth);
+            }
+        }
+    }
+
+    fn query(&self, region: &BoundingBox, found: &mut Vec<Sha256Hash>) {
+        if !self.bounds.intersects(region) {
+            return;
+        }
+        for (hash, bbox) in &self.items {
+            if bbox.intersects(region) {
+                found.push(*hash);
+            }
+        }
+        if let Some(children) = &self.children {
+            for child in children.iter() {
+                child.query(region, found);
+            }
+        }
+    }
+}
+
+/// Quadtree over a set of triangles (typically the UTXO set), answering
+/// which triangle covers a point, which lie in a region, and which touch a
+/// given one. Results are sorted by hash.
+#[derive(Debug, Clone)]
+pub struct TriangleIndex {
+    root: QuadNode,
+    triangles: HashMap<Sha256Hash, Triangle>,
+}
+
+impl TriangleIndex {
+    pub fn build<'a>(triangles: impl IntoIterator<Item = (&'a Sha256Hash, &'a Triangle)>) -> Self {
+        let triangles: HashMap<Sha256Hash, Triangle> = triangles
+            .into_iter()
+            .map(|(hash, triangle)| (*hash, triangle.clone()))
+            .collect();
+        let bounds = triangles
+            .values()
+            .map(Triangle::bounding_box)
+            .reduce(|a, b| a.union(&b))
+            .unwrap_or(BoundingBox::new(
+                Point::new(Coord::ZERO, Coord::ZERO),
+                Point::new(Coord::ZERO, Coord::ZERO),
+            ));
+
+        let mut root = QuadNode::new(bounds);
+        for (hash, triangle) in &triangles {
+            root.insert(*hash, triangle.bounding_box(), 0);
+        }
+        TriangleIndex { root, triangles }
+    }
+
+    pub fn len(&self) -> usize {
+        self.triangles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triangles.is_empty()
+    }
+
+    pub fn get(&self, hash: &Sha256Hash) -> Option<&Triangle> {
+        self.triangles.get(hash)
+    }
+
+    /// Triangles whose bounding box intersects `region`, e.g. a viewport.
+    pub fn in_region(&self, region: &BoundingBox) -> Vec<Sha256Hash> {
+        let mut found = Vec::new();
+        self.root.query(region, &mut found);
+        found.sort();
+        found
+    }
+
+    /// Triangles covering `p`. Normally one; more where triangles overlap.
+    pub fn at_point(&self, p: &Point) -> Vec<Sha256Hash> {
+        let mut found = self.in_region(&BoundingBox::new(*p, *p));
+        found.retain(|hash| self.triangles[hash].contains_point(p));
+        found
+    }
+
+    /// Triangles whose boundary meets the indexed triangle `hash` (see
+    /// [`Triangle::touches`]); empty when `hash` is not indexed.
+    pub fn neighbors_of(&self, hash: &Sha256Hash) -> Vec<Sha256Hash> {
+        let Some(triangle) = self.triangles.get(hash) else {
+            return Vec::new();
+        };
+        let mut found = self.in_region(&triangle.bounding_box());
+        found.retain(|other| other != hash && triangle.touches(&self.triangles[other]));
+        found
+    }
 }
 
 // ----------------------------------------------------------------------------
@@ -267,7 +520,7 @@ mod tests {
         let mut address = [0u8; 32];
         let bytes = s.as_bytes();
         address[..bytes.len()].copy_from_slice(bytes);
-        address
+        Address::from(address)
     }
 
     fn setup_test_triangle() -> Triangle {
@@ -330,6 +583,7 @@ mod tests {
         let parent = setup_test_triangle();
         let parent_value = parent.effective_value();
         let children = parent.subdivide();
+        assert!(children.iter().all(|t| t.depth == parent.depth + 1));
 
         // The sum of the children's effective values must equal the parent's.
         let total_child_value: Coord = children.iter().map(|t| t.effective_value()).sum();
@@ -362,4 +616,54 @@ mod tests {
         );
         assert!(!t_degenerate.is_valid());
     }
+
+    #[test]
+    fn test_contains_point() {
+        let t = setup_test_triangle();
+        let point = |x: f64, y: f64| Point::new(Coord::from_num(x), Coord::from_num(y));
+        assert!(t.contains_point(&point(2.0, 2.0)));
+        assert!(t.contains_point(&point(5.0, 5.0))); // on the hypotenuse
+        assert!(t.contains_point(&point(0.0, 0.0)));
+        assert!(!t.contains_point(&point(6.0, 6.0)));
+        assert!(!t.contains_point(&point(-0.5, 1.0)));
+    }
+
+    #[test]
+    fn test_triangle_index_queries() {
+        // Two levels of subdivision: 9 triangles, forcing the quadtree to split
+        let children = setup_test_triangle().subdivide();
+        let grandchildren: Vec<Triangle> = children.iter().flat_map(|c| c.subdivide()).collect();
+        let entries: Vec<(Sha256Hash, Triangle)> = grandchildren
+            .iter()
+            .map(|t| (t.hash(), t.clone()))
+            .collect();
+        let index = TriangleIndex::build(entries.iter().map(|(h, t)| (h, t)));
+        assert_eq!(index.len(), 9);
+
+        // Every query agrees with a brute-force scan
+        let point = Point::new(Coord::from_num(0.5), Coord::from_num(0.5));
+        let expected: Vec<Sha256Hash> = entries
+            .iter()
+            .filter(|(_, t)| t.contains_point(&point))
+            .map(|(h, _)| *h)
+            .collect();
+        assert_eq!(expected.len(), 1);
+        assert_eq!(index.at_point(&point), expected);
+
+        // The centre removed by the first subdivision belongs to no one
+        let hole = Point::new(Coord::from_num(4), Coord::from_num(4));
+        assert!(index.at_point(&hole).is_empty());
+
+        // A corner grandchild touches its two siblings and nothing else
+        let corner = grandchildren[0].hash();
+        let mut siblings = vec![grandchildren[1].hash(), grandchildren[2].hash()];
+        siblings.sort();
+        assert_eq!(index.neighbors_of(&corner), siblings);
+
+        let all = BoundingBox::new(
+            Point::new(Coord::from_num(0), Coord::from_num(0)),
+            Point::new(Coord::from_num(10), Coord::from_num(10)),
+        );
+        assert_eq!(index.in_region(&all).len(), 9);
+    }
 }
@@ -1,14 +1,111 @@
+use crate::crypto::{Address, KeyPair};
+use crate::error::ChainError;
+use crate::security::SecretBytes;
+use crate::wallet::{self, EncryptedWallet, Wallet};
+use bip32::{DerivationPath, XPrv};
 use bip39::Mnemonic;
 use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use tempfile::NamedTempFile;
+
+/// BIP-44 coin type used for TrinityChain derivation paths.
+pub const COIN_TYPE: u32 = 1618;
+
+/// Name of account 0, which every keystore starts with
+pub const DEFAULT_ACCOUNT: &str = "default";
+
+/// Build the BIP-44 derivation path for an account and receive index:
+/// `m/44'/1618'/{account}'/0/{index}`.
+pub fn derivation_path(account: u32, index: u32) -> String {
+    format!("m/44'/{}'/{}'/0/{}", COIN_TYPE, account, index)
+}
+
+/// Substitute `index` for the `i` placeholder in the last component of a path
+/// template, e.g. `m/44'/1618'/0'/0/i` or `m/44'/1618'/0'/i'`.
+pub fn expand_path(template: &str, index: u32) -> Result<String, String> {
+    let (prefix, last) = template
+        .rsplit_once('/')
+        .ok_or_else(|| format!("invalid derivation path: {}", template))?;
+    let hardened = match last {
+        "i" => "",
+        "i'" => "'",
+        _ => {
+            return Err(format!(
+                "derivation path must end in /i or /i' (got {})",
+                template
+            ))
+        }
+    };
+    Ok(format!("{}/{}{}", prefix, index, hardened))
+}
 
 /// Lightweight HD wallet helpers using BIP-39 for mnemonic and seed derivation.
 ///
-/// This module currently provides mnemonic generation and seed derivation
-/// (BIP-39). It intentionally keeps the API small: callers can obtain the
-/// mnemonic phrase and derive the 64-byte seed (PBKDF2-HMAC-SHA512) from it.
-pub struct HDWallet;
+/// Mnemonic generation and seed derivation (BIP-39) are available as
+/// associated functions. An `HDWallet` instance holds a seed and derives
+/// per-account, per-index keypairs from it (BIP-32).
+pub struct HDWallet {
+    seed: SecretBytes,
+}
 
 impl HDWallet {
+    /// Create an HD wallet from raw BIP-39 seed bytes.
+    pub fn from_seed(seed: Vec<u8>) -> Self {
+        HDWallet {
+            seed: SecretBytes::new(seed),
+        }
+    }
+
+    /// Create an HD wallet from a mnemonic phrase and optional passphrase.
+    pub fn from_mnemonic(phrase: &str, passphrase: Option<&str>) -> Result<Self, String> {
+        Ok(Self::from_seed(Self::seed_from_mnemonic(
+            phrase, passphrase,
+        )?))
+    }
+
+    /// Derive the keypair at `m/44'/1618'/{account}'/0/{index}`.
+    pub fn derive_keypair(&self, account: u32, index: u32) -> Result<KeyPair, String> {
+        self.derive_keypair_at(&derivation_path(account, index))
+    }
+
+    /// Derive the keypair at an arbitrary BIP-32 path such as `m/44'/1618'/0'/0/7`.
+    pub fn derive_keypair_at(&self, path: &str) -> Result<KeyPair, String> {
+        let path: DerivationPath = path
+            .parse()
+            .map_err(|e| format!("invalid derivation path: {}", e))?;
+        let xprv = XPrv::derive_from_path(self.seed.expose(), &path)
+            .map_err(|e| format!("key derivation failed: {}", e))?;
+        KeyPair::from_secret_bytes(&xprv.to_bytes()).map_err(|e| e.to_string())
+    }
+
+    /// Search indices `start..start + count` of a path template for an address.
+    /// Returns the matching index, so a receive address produced elsewhere can
+    /// be checked against this seed.
+    pub fn find_index(
+        &self,
+        template: &str,
+        address: &Address,
+        start: u32,
+        count: u32,
+    ) -> Result<Option<u32>, String> {
+        for index in start..start.saturating_add(count) {
+            let path = expand_path(template, index)?;
+            if self.derive_keypair_at(&path)?.address() == *address {
+                return Ok(Some(index));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Derive the address at the given account and receive index.
+    pub fn derive_address(&self, account: u32, index: u32) -> Result<Address, String> {
+        Ok(self.derive_keypair(account, index)?.address())
+    }
+
     /// Generate a new mnemonic phrase with the given word count.
     /// Supported `word_count` values: 12, 15, 18, 21, 24.
     pub fn generate_mnemonic(word_count: usize) -> Result<String, String> {
@@ -39,9 +136,280 @@ impl HDWallet {
     }
 }
 
+// ============================================================================
+// Key Mnemonics
+// ============================================================================
+
+/// Encode a 32-byte secret key as a 24-word BIP-39 phrase whose entropy is
+/// the key itself, so a wallet not created from a seed can still be written
+/// down. Unlike a seed phrase it is not hashed into a seed: the words are
+/// the key.
+pub fn key_to_mnemonic(secret: &[u8]) -> Result<String, String> {
+    if secret.len() != 32 {
+        return Err(format!("secret key must be 32 bytes, got {}", secret.len()));
+    }
+    let m =
+        Mnemonic::from_entropy(secret).map_err(|e| format!("mnemonic encoding failed: {}", e))?;
+    Ok(m.to_string())
+}
+
+/// Decode a phrase produced by `key_to_mnemonic` back into the keypair
+pub fn key_from_mnemonic(phrase: &str) -> Result<KeyPair, String> {
+    let m = Mnemonic::parse_normalized(phrase)
+        .map_err(|e| format!("invalid mnemonic phrase: {}", e))?;
+    let entropy = m.to_entropy();
+    if entropy.len() != 32 {
+        return Err(format!(
+            "a key phrase has 24 words, got {} (use --hd for a seed phrase)",
+            m.word_count()
+        ));
+    }
+    KeyPair::from_secret_bytes(&entropy).map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// HD Keystore
+// ============================================================================
+
+/// A derived receive address recorded in the keystore
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DerivedKey {
+    pub account: u32,
+    pub index: u32,
+    pub path: String,
+    pub address: String,
+    pub created: String,
+}
+
+/// Persistent keystore of HD-derived addresses.
+///
+/// The seed is stored encrypted with the wallet password; derived keys are
+/// recorded by path and address so balances can be aggregated without
+/// unlocking, and private keys are re-derived from the seed when needed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HDKeystore {
+    /// Hex-encoded seed, sealed with the same scheme as regular wallets
+    pub encrypted_seed: EncryptedWallet,
+    /// Next unused receive index per account
+    pub next_index: BTreeMap<u32, u32>,
+    /// All derived keys, in derivation order
+    pub keys: Vec<DerivedKey>,
+    /// Account names by BIP-44 account index; account 0 is `default` unless
+    /// renamed
+    #[serde(default)]
+    pub accounts: BTreeMap<u32, String>,
+}
+
+impl HDKeystore {
+    /// Create a keystore from a mnemonic, deriving the first address of account 0
+    pub fn create(
+        mnemonic: &str,
+        passphrase: Option<&str>,
+        password: &str,
+    ) -> Result<(Self, HDWallet), ChainError> {
+        let hd = HDWallet::from_mnemonic(mnemonic, passphrase).map_err(ChainError::WalletError)?;
+        let first = hd.derive_address(0, 0).map_err(ChainError::WalletError)?;
+
+        let seed_wallet = Wallet {
+            format_version: wallet::WALLET_FORMAT_VERSION,
+            name: Some("hd".to_string()),
+            address: hex::encode(first),
+            secret_key: hd.seed.clone(),
+            created: chrono::Utc::now().to_rfc3339(),
+            watch_only: false,
+        };
+
+        let mut keystore = HDKeystore {
+            encrypted_seed: EncryptedWallet::from_wallet(&seed_wallet, password)?,
+            next_index: BTreeMap::new(),
+            keys: Vec::new(),
+            accounts: BTreeMap::new(),
+        };
+        keystore.next_address(&hd, 0)?;
+
+        Ok((keystore, hd))
+    }
+
+    /// Decrypt the seed and return an `HDWallet` for deriving keys
+    pub fn unlock(&self, password: &str) -> Result<HDWallet, ChainError> {
+        let seed_wallet = self.encrypted_seed.decrypt(password)?;
+        Ok(HDWallet {
+            seed: seed_wallet.secret_key.clone(),
+        })
+    }
+
+    /// Derive and record the next unused receive address for an account
+    pub fn next_address(&mut self, hd: &HDWallet, account: u32) -> Result<DerivedKey, ChainError> {
+        let index = self.next_index.get(&account).copied().unwrap_or(0);
+        let address = hd
+            .derive_address(account, index)
+            .map_err(ChainError::WalletError)?;
+
+        let key = DerivedKey {
+            account,
+            index,
+            path: derivation_path(account, index),
+            address: hex::encode(address),
+            created: chrono::Utc::now().to_rfc3339(),
+        };
+        self.next_index.insert(account, index + 1);
+        self.keys.push(key.clone());
+        Ok(key)
+    }
+
+    /// Addresses of every derived key
+    pub fn addresses(&self) -> Vec<String> {
+        self.keys.iter().map(|k| k.address.clone()).collect()
+    }
+
+    /// Name of an account, if it has one
+    pub fn account_name(&self, account: u32) -> Option<&str> {
+        match self.accounts.get(&account) {
+            Some(name) => Some(name),
+            None if account == 0 => Some(DEFAULT_ACCOUNT),
+            None => None,
+        }
+    }
+
+    /// Every account that has a name or a derived key, in index order
+    pub fn account_indices(&self) -> Vec<u32> {
+        let mut indices: Vec<u32> = self
+            .accounts
+            .keys()
+            .copied()
+            .chain(self.keys.iter().map(|k| k.account))
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    }
+
+    /// Look up an account by name, or by index
+    pub fn resolve_account(&self, account: &str) -> Result<u32, ChainError> {
+        if let Some(index) = self
+            .account_indices()
+            .into_iter()
+            .find(|&i| self.account_name(i) == Some(account))
+        {
+            return Ok(index);
+        }
+        account
+            .parse()
+            .map_err(|_| ChainError::WalletError(format!("No HD account named '{}'", account)))
+    }
+
+    /// Open a named account at the next unused index and derive its first
+    /// receive address
+    pub fn create_account(
+        &mut self,
+        hd: &HDWallet,
+        name: &str,
+    ) -> Result<(u32, DerivedKey), ChainError> {
+        let name = name.trim();
+        if name.is_empty() || name.parse::<u32>().is_ok() {
+            return Err(ChainError::WalletError(format!(
+                "Invalid account name '{}': use a non-numeric name",
+                name
+            )));
+        }
+        if self.resolve_account(name).is_ok() {
+            return Err(ChainError::WalletError(format!(
+                "Account '{}' already exists",
+                name
+            )));
+        }
+        let account = self.account_indices().last().map_or(Ok(0), |last| {
+            last.checked_add(1)
+                .filter(|i| *i < 0x8000_0000)
+                .ok_or_else(|| ChainError::WalletError("No account indices left".to_string()))
+        })?;
+        self.accounts.insert(account, name.to_string());
+        let key = self.next_address(hd, account)?;
+        Ok((account, key))
+    }
+
+    /// Addresses derived for one account
+    pub fn account_addresses(&self, account: u32) -> Vec<String> {
+        self.keys
+            .iter()
+            .filter(|k| k.account == account)
+            .map(|k| k.address.clone())
+            .collect()
+    }
+
+    /// Account an address was derived for
+    pub fn account_of(&self, address: &Address) -> Option<u32> {
+        let address = hex::encode(address);
+        self.keys
+            .iter()
+            .find(|k| k.address == address)
+            .map(|k| k.account)
+    }
+
+    /// Re-derive the keypair for a recorded address
+    pub fn keypair_for(&self, hd: &HDWallet, address: &str) -> Result<KeyPair, ChainError> {
+        let key = self
+            .keys
+            .iter()
+            .find(|k| k.address == address)
+            .ok_or_else(|| {
+                ChainError::WalletError(format!("Address {} not in HD keystore", address))
+            })?;
+        hd.derive_keypair(key.account, key.index)
+            .map_err(ChainError::WalletError)
+    }
+
+    /// Save the keystore to a file
+    pub fn save(&self, path: &PathBuf) -> Result<(), ChainError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| ChainError::WalletError(format!("Failed to serialize keystore: {}", e)))?;
+
+        let mut temp_file = NamedTempFile::new()
+            .map_err(|e| ChainError::WalletError(format!("Failed to create temp file: {}", e)))?;
+        write!(temp_file, "{}", json)
+            .map_err(|e| ChainError::WalletError(format!("Failed to write to temp file: {}", e)))?;
+        temp_file.persist(path).map_err(|e| {
+            ChainError::WalletError(format!("Failed to persist keystore file: {}", e))
+        })?;
+
+        Ok(())
+    }
+
+    /// Load the keystore from a file
+    pub fn load(path: &PathBuf) -> Result<Self, ChainError> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ChainError::WalletError(format!("Failed to read keystore: {}", e)))?;
+
+        serde_json::from_str(&contents)
+            .map_err(|e| ChainError::WalletError(format!("Failed to parse keystore: {}", e)))
+    }
+}
+
+/// Get the default HD keystore path
+pub fn get_keystore_path() -> Result<PathBuf, ChainError> {
+    Ok(wallet::get_wallet_dir()?.join("hd_keystore.json"))
+}
+
+/// Remove an `--account <name|index>` option from `args`
+pub fn account_from_args(args: &mut Vec<String>) -> Result<Option<String>, ChainError> {
+    match args.iter().position(|a| a == "--account") {
+        Some(pos) if pos + 1 < args.len() => {
+            let account = args.remove(pos + 1);
+            args.remove(pos);
+            Ok(Some(account))
+        }
+        Some(_) => Err(ChainError::WalletError(
+            "--account requires an account name or index".to_string(),
+        )),
+        None => Ok(None),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::HDWallet;
+    use super::*;
+
+    const TEST_MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
 
     #[test]
     fn test_generate_mnemonic_and_seed() {
@@ -60,4 +428,117 @@ mod tests {
         let r = HDWallet::generate_mnemonic(13);
         assert!(r.is_err());
     }
+
+    #[test]
+    fn test_derive_address_is_deterministic() {
+        let hd = HDWallet::from_mnemonic(TEST_MNEMONIC, None).unwrap();
+        let a = hd.derive_address(0, 0).unwrap();
+        let b = hd.derive_address(0, 0).unwrap();
+        assert_eq!(a, b);
+
+        assert_ne!(a, hd.derive_address(0, 1).unwrap());
+        assert_ne!(a, hd.derive_address(1, 0).unwrap());
+    }
+
+    #[test]
+    fn test_path_template_matches_receive_path() {
+        let template = format!("m/44'/{}'/0'/0/i", COIN_TYPE);
+        assert_eq!(expand_path(&template, 7).unwrap(), derivation_path(0, 7));
+        assert_eq!(expand_path("m/44'/1'/i'", 3).unwrap(), "m/44'/1'/3'");
+        assert!(expand_path("m/44'/1618'/0'/0/5", 1).is_err());
+        assert!(expand_path("i", 1).is_err());
+
+        let hd = HDWallet::from_mnemonic(TEST_MNEMONIC, None).unwrap();
+        let third = hd.derive_address(0, 2).unwrap();
+        assert_eq!(hd.find_index(&template, &third, 0, 5).unwrap(), Some(2));
+        assert_eq!(hd.find_index(&template, &third, 3, 5).unwrap(), None);
+        assert_eq!(
+            hd.find_index(&template, &hd.derive_address(1, 2).unwrap(), 0, 5)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_key_mnemonic_restores_same_address() {
+        let keypair = KeyPair::generate().unwrap();
+        let phrase = key_to_mnemonic(&keypair.secret_key.secret_bytes()).unwrap();
+        assert_eq!(phrase.split_whitespace().count(), 24);
+
+        let restored = key_from_mnemonic(&phrase).unwrap();
+        assert_eq!(restored.address(), keypair.address());
+
+        // Seed phrases of other lengths are not key phrases
+        assert!(key_from_mnemonic(TEST_MNEMONIC).is_err());
+        assert!(key_to_mnemonic(&[1; 16]).is_err());
+    }
+
+    #[test]
+    fn test_keystore_rotation_and_persistence() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("hd_keystore.json");
+
+        let (mut keystore, hd) = HDKeystore::create(TEST_MNEMONIC, None, "pw").unwrap();
+        assert_eq!(keystore.keys.len(), 1);
+
+        let second = keystore.next_address(&hd, 0).unwrap();
+        assert_eq!(second.index, 1);
+        assert_eq!(
+            second.address,
+            hex::encode(hd.derive_address(0, 1).unwrap())
+        );
+
+        keystore.save(&path).unwrap();
+        let loaded = HDKeystore::load(&path).unwrap();
+        assert_eq!(loaded.addresses(), keystore.addresses());
+
+        let unlocked = loaded.unlock("pw").unwrap();
+        let keypair = loaded.keypair_for(&unlocked, &second.address).unwrap();
+        assert_eq!(hex::encode(keypair.address()), second.address);
+
+        assert!(loaded.unlock("wrong").is_err());
+    }
+
+    #[test]
+    fn test_named_accounts() {
+        let (mut keystore, hd) = HDKeystore::create(TEST_MNEMONIC, None, "pw").unwrap();
+        assert_eq!(keystore.resolve_account(DEFAULT_ACCOUNT).unwrap(), 0);
+
+        let (mining, first) = keystore.create_account(&hd, "mining").unwrap();
+        let (savings, _) = keystore.create_account(&hd, "savings").unwrap();
+        assert_eq!((mining, savings), (1, 2));
+        assert_eq!(first.path, derivation_path(1, 0));
+        keystore.next_address(&hd, mining).unwrap();
+
+        assert_eq!(keystore.resolve_account("mining").unwrap(), 1);
+        assert_eq!(keystore.resolve_account("2").unwrap(), 2);
+        assert!(keystore.resolve_account("spending").is_err());
+        assert!(keystore.create_account(&hd, "mining").is_err());
+        assert!(keystore.create_account(&hd, "7").is_err());
+
+        assert_eq!(keystore.account_indices(), vec![0, 1, 2]);
+        assert_eq!(keystore.account_addresses(mining).len(), 2);
+        let address = hd.derive_address(1, 1).unwrap();
+        assert_eq!(keystore.account_of(&address), Some(mining));
+
+        // Names survive a save, and older keystores load without them
+        let json = serde_json::to_value(&keystore).unwrap();
+        let loaded: HDKeystore = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(loaded.account_name(2), Some("savings"));
+        let mut legacy = json;
+        legacy.as_object_mut().unwrap().remove("accounts");
+        let legacy: HDKeystore = serde_json::from_value(legacy).unwrap();
+        assert_eq!(legacy.account_name(1), None);
+
+        let mut args = vec![
+            "balance".to_string(),
+            "--account".to_string(),
+            "mining".to_string(),
+        ];
+        assert_eq!(
+            account_from_args(&mut args).unwrap().as_deref(),
+            Some("mining")
+        );
+        assert_eq!(args, vec!["balance".to_string()]);
+    }
 }
@@ -0,0 +1,165 @@
+//! Inventory-based relay
+//!
+//! Blocks and transactions are announced to peers by hash (`Inv`) and only
+//! sent when a peer asks for them (`GetData`). The node remembers a rolling
+//! window of hashes it has already seen, so an item announced by several
+//! peers is fetched once and never echoed back, and keeps the bodies it
+//! announced recently so it can answer requests for them.
+
+use crate::blockchain::{Block, Sha256Hash};
+use crate::transaction::Transaction;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Hashes remembered as seen before the oldest are forgotten
+const SEEN_CAPACITY: usize = 20_000;
+
+/// Announced bodies kept to answer `GetData`
+const RELAY_CAPACITY: usize = 1_000;
+
+/// An unanswered request may be re-sent to another announcer after this long
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A block or transaction, identified by hash
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InvItem {
+    Block(Sha256Hash),
+    Transaction(Sha256Hash),
+}
+
+/// Body of an announced item
+#[derive(Debug, Clone)]
+pub enum RelayItem {
+    Block(Box<Block>),
+    Transaction(Box<Transaction>),
+}
+
+/// Seen hashes, outstanding requests and recently announced bodies
+pub struct Inventory {
+    seen: HashSet<InvItem>,
+    seen_order: VecDeque<InvItem>,
+    requested: HashMap<InvItem, Instant>,
+    relay: HashMap<InvItem, RelayItem>,
+    relay_order: VecDeque<InvItem>,
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Inventory {
+            seen: HashSet::new(),
+            seen_order: VecDeque::new(),
+            requested: HashMap::new(),
+            relay: HashMap::new(),
+            relay_order: VecDeque::new(),
+        }
+    }
+
+    pub fn has_seen(&self, item: &InvItem) -> bool {
+        self.seen.contains(item)
+    }
+
+    /// Record an item whose body we have; returns whether it was new
+    pub fn mark_seen(&mut self, item: InvItem) -> bool {
+        self.requested.remove(&item);
+        if !self.seen.insert(item) {
+            return false;
+        }
+        self.seen_order.push_back(item);
+        if self.seen_order.len() > SEEN_CAPACITY {
+            if let Some(oldest) = self.seen_order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        true
+    }
+
+    /// Whether an announced item should be requested: it is unseen and not
+    /// already requested within `REQUEST_TIMEOUT`. Records the request.
+    pub fn want(&mut self, item: InvItem, now: Instant) -> bool {
+        if self.seen.contains(&item) {
+            return false;
+        }
+        if let Some(at) = self.requested.get(&item) {
+            if now.duration_since(*at) < REQUEST_TIMEOUT {
+                return false;
+            }
+        }
+        self.requested.insert(item, now);
+        true
+    }
+
+    /// Keep an announced body so peers can fetch it
+    pub fn store_relay(&mut self, item: InvItem, body: RelayItem) {
+        if self.relay.insert(item, body).is_none() {
+            self.relay_order.push_back(item);
+        }
+        if self.relay_order.len() > RELAY_CAPACITY {
+            if let Some(oldest) = self.relay_order.pop_front() {
+                self.relay.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn relay_body(&self, item: &InvItem) -> Option<RelayItem> {
+        self.relay.get(item).cloned()
+    }
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_announced_item_requested_once() {
+        let mut inventory = Inventory::new();
+        let item = InvItem::Transaction([1; 32]);
+        let now = Instant::now();
+
+        assert!(inventory.want(item, now));
+        // Another peer announcing it while the request is outstanding
+        assert!(!inventory.want(item, now + Duration::from_secs(1)));
+        // The first announcer never answered
+        assert!(inventory.want(item, now + REQUEST_TIMEOUT));
+
+        assert!(inventory.mark_seen(item));
+        assert!(!inventory.mark_seen(item));
+        assert!(!inventory.want(item, now + REQUEST_TIMEOUT * 2));
+        // Blocks and transactions are tracked separately
+        assert!(inventory.want(InvItem::Block([1; 32]), now));
+    }
+
+    #[test]
+    fn test_seen_and_relay_windows_are_bounded() {
+        let mut inventory = Inventory::new();
+        for i in 0..=SEEN_CAPACITY as u32 {
+            let mut hash = [0u8; 32];
+            hash[..4].copy_from_slice(&i.to_be_bytes());
+            inventory.mark_seen(InvItem::Block(hash));
+        }
+        assert!(!inventory.has_seen(&InvItem::Block([0; 32])));
+        assert_eq!(inventory.seen.len(), SEEN_CAPACITY);
+
+        let tx = crate::transaction::Transaction::Coinbase(crate::transaction::CoinbaseTx {
+            reward_area: crate::geometry::Coord::from_num(1),
+            beneficiary_address: crate::crypto::Address::from([1; 32]),
+            nonce: 0,
+        });
+        for i in 0..=RELAY_CAPACITY as u32 {
+            let mut hash = [0u8; 32];
+            hash[..4].copy_from_slice(&i.to_be_bytes());
+            inventory.store_relay(
+                InvItem::Transaction(hash),
+                RelayItem::Transaction(Box::new(tx.clone())),
+            );
+        }
+        assert!(inventory.relay_body(&InvItem::Transaction([0; 32])).is_none());
+        assert_eq!(inventory.relay.len(), RELAY_CAPACITY);
+    }
+}
@@ -9,34 +9,67 @@
 //! - [`transaction`] - Transaction types and operations
 //! - [`block`] - Block structure and validation
 //! - [`mempool`] - Transaction mempool
+//! - [`events`] - Block, reorganization and mempool events published by the chain
+//! - [`policy`] - Node-local relay and mining filter
 //!
 //! ## Geometric System  
 //! - [`geometry`] - Triangle primitives and calculations
 //! - [`fees`] - Fee calculations (geometric)
+//! - [`lineage`] - Triangle genealogy graph (DOT/GraphML export)
 //!
 //! ## Consensus
 //! - [`miner`] - Proof-of-work mining
+//! - [`difficulty_sim`] - Difficulty adjustment simulation
+//! - [`versionbits`] - Soft-fork deployment signalling in block headers
 //!
 //! ## Cryptography
 //! - [`crypto`] - Signatures and verification (secp256k1)
 //! - [`security`] - Security utilities
+//! - [`capabilities`] - Scoped capability tokens for the wallet API
 //!
 //! ## State Management
 //! - [`wallet`] - Wallet operations and UTXO selection
 //! - [`hdwallet`] - HD wallet (BIP-39/BIP-32)
+//! - [`txlabels`] - Private, local-only transaction labels
+//! - [`drafts`] - Offline mode and transactions queued while offline
+//! - [`balance`] - Available / pending-out / unconfirmed-in balance breakdown
+//! - [`rawtx`] - Unsigned transactions for offline signing
 //! - [`persistence`] - Database layer (SQLite)
+//! - [`migrations`] - Versioned database schema migrations
+//! - [`snapshot`] - UTXO snapshots for fast bootstrapping
+//! - [`state_diff`] - UTXO set changes between two heights
 //! - [`cache`] - Caching utilities
+//! - [`analytics`] - Block interval, fee-rate and activity statistics
+//! - [`chain_export`] - Block and transaction dumps (JSON Lines, CSV) for offline analysis
+//! - [`metrics`] - Per-transaction block validation timings
 //!
 //! ## Networking & Integration
 //! - [`network`] - P2P networking
 //! - [`discovery`] - Peer discovery
 //! - [`sync`] - Chain synchronization
+//! - [`propagation`] - Block propagation latency and per-peer delivery stats
+//! - [`forks`] - Every known chain tip, for fork monitoring
+//! - [`inventory`] - Inv/GetData relay and the rolling seen-set
+//! - [`outbound`] - Bounded, prioritized per-peer outbound queues
+//! - [`peer_events`] - Peer lifecycle events and the live peer table
+//! - [`stale_tip`] - Stale-tip detection and the miner's response
+//! - [`shutdown`] - Shutdown signal shared by the node's tasks
+//! - [`replication`] - Hot standby nodes following a primary
+//! - [`remote`] - HTTP client for a remote node's API
+//! - [`rpc`] - JSON-RPC 2.0 method mapping served at `/rpc`
+//! - [`faucet`] - Rate-limited testnet faucet (feature `faucet`)
+//! - [`watchtower`] - Alerts on spends of cold-storage triangles
+//! - [`stats_feed`] - Live node statistics over WebSocket for the dashboard
 //!
 //! ## Configuration & Utilities
 //! - [`config`] - Configuration management
+//! - [`datadir`] - Data directory for wallets, the database and local files
 //! - [`error`] - Error types
+//! - [`logging`] - Structured logging (pretty or JSON, per-module levels, rotated files)
 //! - [`cli`] - CLI utilities
 //! - [`addressbook`] - Address book management
+//! - [`fixtures`] - Signed transaction and block fixtures (tests, feature `test-fixtures`)
+//! - [`vectors`] - Wallet compatibility test vectors (feature `test-vectors`)
 
 #![forbid(unsafe_code)]
 
@@ -46,31 +79,48 @@
 pub mod blockchain;
 pub mod transaction;
 pub mod mempool;
+pub mod events;
+pub mod policy;
 
 // ============================================================================
 // Geometric System
 // ============================================================================
 pub mod geometry;
 pub mod fees;
+pub mod lineage;
 
 // ============================================================================
 // Consensus & Mining
 // ============================================================================
 pub mod miner;
+pub mod difficulty_sim;
+pub mod versionbits;
 
 // ============================================================================
 // Cryptography & Security
 // ============================================================================
 pub mod crypto;
+pub mod sighash;
 pub mod security;
+pub mod capabilities;
 
 // ============================================================================
 // State Management
 // ============================================================================
 pub mod wallet;
 pub mod hdwallet;
+pub mod txlabels;
+pub mod drafts;
+pub mod balance;
+pub mod rawtx;
 pub mod persistence;
+pub mod migrations;
+pub mod snapshot;
+pub mod state_diff;
 pub mod cache;
+pub mod analytics;
+pub mod chain_export;
+pub mod metrics;
 
 // ============================================================================
 // Networking
@@ -78,17 +128,37 @@ pub mod cache;
 pub mod network;
 pub mod discovery;
 pub mod sync;
+pub mod propagation;
+pub mod forks;
+pub mod inventory;
+pub mod outbound;
+pub mod peer_events;
+pub mod stale_tip;
+pub mod shutdown;
+pub mod replication;
+pub mod remote;
+pub mod rpc;
+pub mod watchtower;
+pub mod stats_feed;
 
 // ============================================================================
 // Integration
 // ============================================================================
 #[cfg(feature = "api")]
 pub mod api;
+#[cfg(feature = "faucet")]
+pub mod faucet;
 
 // ============================================================================
 // Configuration & Utilities
 // ============================================================================
 pub mod config;
+pub mod datadir;
 pub mod error;
+pub mod logging;
 pub mod cli;
 pub mod addressbook;
+#[cfg(any(test, feature = "test-fixtures"))]
+pub mod fixtures;
+#[cfg(feature = "test-vectors")]
+pub mod vectors;
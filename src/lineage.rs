@@ -0,0 +1,453 @@
+//! Triangle genealogy graph
+//!
+//! Replays the chain and records every triangle ever created together with
+//! the transaction that consumed it, giving a parent → child graph of how the
+//! geometric state evolved. The graph can be narrowed to one owner or to the
+//! subtree below a triangle and written as Graphviz DOT or GraphML.
+
+use crate::blockchain::{Block, Sha256Hash, TriangleState};
+use crate::crypto::Address;
+use crate::error::ChainError;
+use crate::geometry::Coord;
+use crate::transaction::Transaction;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::Write;
+
+/// How a child triangle came out of its parent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EdgeKind {
+    Subdivision,
+    Transfer,
+    /// Change returned to the sender of a transfer
+    Change,
+}
+
+impl EdgeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EdgeKind::Subdivision => "subdivision",
+            EdgeKind::Transfer => "transfer",
+            EdgeKind::Change => "change",
+        }
+    }
+}
+
+/// A triangle that existed at some point in the chain
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineageNode {
+    pub owner: Address,
+    pub value: Coord,
+    /// Height of the block that created the triangle
+    pub created_at: u64,
+    /// Height of the block that consumed it, if any
+    pub spent_at: Option<u64>,
+}
+
+/// Parent/child relation between two triangles
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LineageEdge {
+    pub parent: Sha256Hash,
+    pub child: Sha256Hash,
+    pub kind: EdgeKind,
+}
+
+/// One triangle on the path from a triangle back to the coinbase that
+/// minted its value
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineageStep {
+    pub hash: Sha256Hash,
+    pub node: LineageNode,
+    /// How this triangle came out of the next step's; `None` for the coinbase
+    pub kind: Option<EdgeKind>,
+}
+
+/// Output formats for [`LineageGraph::render`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineageFormat {
+    Dot,
+    GraphMl,
+}
+
+impl std::str::FromStr for LineageFormat {
+    type Err = ChainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "dot" => Ok(LineageFormat::Dot),
+            "graphml" => Ok(LineageFormat::GraphMl),
+            other => Err(ChainError::InvalidTransaction(format!(
+                "Unknown lineage format '{}' (expected dot or graphml)",
+                other
+            ))),
+        }
+    }
+}
+
+/// The genealogy of every triangle in a chain
+#[derive(Debug, Clone, Default)]
+pub struct LineageGraph {
+    pub nodes: BTreeMap<Sha256Hash, LineageNode>,
+    pub edges: BTreeSet<LineageEdge>,
+}
+
+impl LineageGraph {
+    /// Build the graph by replaying `blocks` from genesis
+    pub fn from_blocks(blocks: &[Block]) -> Result<Self, ChainError> {
+        let mut graph = LineageGraph::default();
+        let mut state = TriangleState::new();
+
+        for block in blocks {
+            let height = block.header.height;
+            for tx in &block.transactions {
+                let mut outputs = Vec::new();
+                match tx {
+                    Transaction::Coinbase(_) => outputs.push((tx.hash(), None)),
+                    Transaction::Transfer(transfer) => {
+                        let input = transfer.input_hash;
+                        outputs.push((tx.hash(), Some((input, EdgeKind::Transfer))));
+                        if let Some(triangle) = state.utxo_set.get(&input) {
+                            let remaining = triangle.effective_value()
+                                - transfer.amount
+                                - transfer.fee_area;
+                            let change = TriangleState::change_output_hash(transfer, remaining);
+                            outputs.push((change, Some((input, EdgeKind::Change))));
+                        }
+                    }
+                    Transaction::BatchTransfer(batch) => {
+                        let input = batch.input_hash;
+                        let tx_hash = tx.hash();
+                        for index in 0..batch.outputs.len() {
+                            outputs.push((
+                                TriangleState::batch_output_hash(&tx_hash, index),
+                                Some((input, EdgeKind::Transfer)),
+                            ));
+                        }
+                        outputs.push((
+                            TriangleState::batch_output_hash(&tx_hash, batch.outputs.len()),
+                            Some((input, EdgeKind::Change)),
+                        ));
+                    }
+                    Transaction::Subdivision(subdivision) => {
+                        for child in &subdivision.children {
+                            outputs.push((
+                                child.hash(),
+                                Some((subdivision.parent_hash, EdgeKind::Subdivision)),
+                            ));
+                        }
+                    }
+                }
+
+                let spent = match tx {
+                    Transaction::Transfer(transfer) => Some(transfer.input_hash),
+                    Transaction::Subdivision(subdivision) => Some(subdivision.parent_hash),
+                    Transaction::BatchTransfer(batch) => Some(batch.input_hash),
+                    Transaction::Coinbase(_) => None,
+                };
+                state.apply_transaction(tx, height)?;
+
+                if let Some(node) = spent.and_then(|hash| graph.nodes.get_mut(&hash)) {
+                    node.spent_at = Some(height);
+                }
+                for (hash, parent) in outputs {
+                    // The change output only exists when value was left over
+                    let Some(triangle) = state.utxo_set.get(&hash) else {
+                        continue;
+                    };
+                    graph.nodes.insert(
+                        hash,
+                        LineageNode {
+                            owner: triangle.owner,
+                            value: triangle.effective_value(),
+                            created_at: height,
+                            spent_at: None,
+                        },
+                    );
+                    if let Some((parent, kind)) = parent {
+                        graph.edges.insert(LineageEdge {
+                            parent,
+                            child: hash,
+                            kind,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(graph)
+    }
+
+    /// Keep only triangles owned by `owner`, plus the parents and children
+    /// they are directly connected to
+    pub fn filter_owner(&self, owner: &Address) -> Self {
+        let owned: BTreeSet<Sha256Hash> = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.owner == *owner)
+            .map(|(hash, _)| *hash)
+            .collect();
+        let edges: BTreeSet<LineageEdge> = self
+            .edges
+            .iter()
+            .filter(|e| owned.contains(&e.parent) || owned.contains(&e.child))
+            .cloned()
+            .collect();
+
+        let mut keep = owned;
+        for edge in &edges {
+            keep.insert(edge.parent);
+            keep.insert(edge.child);
+        }
+        self.restrict(keep, edges)
+    }
+
+    /// Keep only `root` and its descendants
+    pub fn subtree(&self, root: &Sha256Hash) -> Result<Self, ChainError> {
+        if !self.nodes.contains_key(root) {
+            return Err(ChainError::TriangleNotFound(format!(
+                "Triangle {} is not in the chain history",
+                hex::encode(root)
+            )));
+        }
+
+        let mut children: BTreeMap<Sha256Hash, Vec<&LineageEdge>> = BTreeMap::new();
+        for edge in &self.edges {
+            children.entry(edge.parent).or_default().push(edge);
+        }
+
+        let mut keep = BTreeSet::from([*root]);
+        let mut edges = BTreeSet::new();
+        let mut queue = VecDeque::from([*root]);
+        while let Some(hash) = queue.pop_front() {
+            for edge in children.get(&hash).into_iter().flatten() {
+                edges.insert((*edge).clone());
+                if keep.insert(edge.child) {
+                    queue.push_back(edge.child);
+                }
+            }
+        }
+        Ok(self.restrict(keep, edges))
+    }
+
+    /// `hash` followed by each triangle it came out of, ending at the
+    /// coinbase that created the value
+    pub fn ancestry(&self, hash: &Sha256Hash) -> Result<Vec<LineageStep>, ChainError> {
+        let parents: BTreeMap<Sha256Hash, &LineageEdge> =
+            self.edges.iter().map(|edge| (edge.child, edge)).collect();
+
+        let mut steps = Vec::new();
+        let mut cursor = Some(*hash);
+        while let Some(current) = cursor {
+            let node = self.nodes.get(&current).ok_or_else(|| {
+                ChainError::TriangleNotFound(format!(
+                    "Triangle {} is not in the chain history",
+                    hex::encode(current)
+                ))
+            })?;
+            let parent = parents.get(&current);
+            steps.push(LineageStep {
+                hash: current,
+                node: node.clone(),
+                kind: parent.map(|edge| edge.kind),
+            });
+            // Degenerate triangles can repeat a hash; never walk in circles
+            cursor = parent
+                .map(|edge| edge.parent)
+                .filter(|parent| steps.iter().all(|step| step.hash != *parent));
+        }
+        Ok(steps)
+    }
+
+    fn restrict(&self, keep: BTreeSet<Sha256Hash>, edges: BTreeSet<LineageEdge>) -> Self {
+        LineageGraph {
+            nodes: self
+                .nodes
+                .iter()
+                .filter(|(hash, _)| keep.contains(*hash))
+                .map(|(hash, node)| (*hash, node.clone()))
+                .collect(),
+            edges,
+        }
+    }
+
+    pub fn render(&self, format: LineageFormat) -> String {
+        match format {
+            LineageFormat::Dot => self.to_dot(),
+            LineageFormat::GraphMl => self.to_graphml(),
+        }
+    }
+
+    /// Graphviz DOT; unspent triangles are filled
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph lineage {\n    rankdir=TB;\n    node [shape=triangle];\n");
+        for (hash, node) in &self.nodes {
+            let style = if node.spent_at.is_none() {
+                ", style=filled, fillcolor=lightblue"
+            } else {
+                ""
+            };
+            let _ = writeln!(
+                out,
+                "    \"{}\" [label=\"{}\\nowner {}\\nvalue {}\\nblock #{}\"{}];",
+                hex::encode(hash),
+                &hex::encode(hash)[..8],
+                &hex::encode(node.owner)[..8],
+                node.value,
+                node.created_at,
+                style
+            );
+        }
+        for edge in &self.edges {
+            let _ = writeln!(
+                out,
+                "    \"{}\" -> \"{}\" [label=\"{}\"];",
+                hex::encode(edge.parent),
+                hex::encode(edge.child),
+                edge.kind.as_str()
+            );
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// GraphML with owner, value and height attributes, for Gephi and friends
+    pub fn to_graphml(&self) -> String {
+        let mut out = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n  \
+             <key id=\"owner\" for=\"node\" attr.name=\"owner\" attr.type=\"string\"/>\n  \
+             <key id=\"value\" for=\"node\" attr.name=\"value\" attr.type=\"double\"/>\n  \
+             <key id=\"created\" for=\"node\" attr.name=\"created_at\" attr.type=\"long\"/>\n  \
+             <key id=\"spent\" for=\"node\" attr.name=\"spent_at\" attr.type=\"long\"/>\n  \
+             <key id=\"kind\" for=\"edge\" attr.name=\"kind\" attr.type=\"string\"/>\n  \
+             <graph id=\"lineage\" edgedefault=\"directed\">\n",
+        );
+        for (hash, node) in &self.nodes {
+            let _ = writeln!(out, "    <node id=\"{}\">", hex::encode(hash));
+            let _ = writeln!(out, "      <data key=\"owner\">{}</data>", hex::encode(node.owner));
+            let _ = writeln!(out, "      <data key=\"value\">{}</data>", node.value);
+            let _ = writeln!(out, "      <data key=\"created\">{}</data>", node.created_at);
+            if let Some(spent) = node.spent_at {
+                let _ = writeln!(out, "      <data key=\"spent\">{}</data>", spent);
+            }
+            out.push_str("    </node>\n");
+        }
+        for edge in &self.edges {
+            let _ = writeln!(
+                out,
+                "    <edge source=\"{}\" target=\"{}\">\n      <data key=\"kind\">{}</data>\n    </edge>",
+                hex::encode(edge.parent),
+                hex::encode(edge.child),
+                edge.kind.as_str()
+            );
+        }
+        out.push_str("  </graph>\n</graphml>\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::Blockchain;
+    use crate::crypto::KeyPair;
+    use crate::fixtures::{mine_next_block, signed_subdivision, signed_transfer};
+
+    /// Genesis → subdivide the genesis reward → transfer one child. Returns
+    /// the number of distinct children (coinbase triangles are degenerate,
+    /// so equal-valued children can share a hash).
+    fn history() -> (Blockchain, Address, usize) {
+        let miner = KeyPair::generate().unwrap();
+        let mut chain = Blockchain::new(miner.address(), 1).unwrap();
+        let (genesis_hash, genesis) = chain.state.utxo_set.iter().next().unwrap();
+        let subdivision =
+            signed_subdivision(&miner, *genesis_hash, genesis, Coord::from_num(0), 1);
+        let block = mine_next_block(&chain, miner.address(), vec![subdivision.clone()]);
+        chain.apply_block(block).unwrap();
+
+        let Transaction::Subdivision(sub) = &subdivision else {
+            unreachable!()
+        };
+        let children: BTreeSet<_> = sub.children.iter().map(|c| c.hash()).collect();
+        let child = &sub.children[0];
+        let recipient = Address::from([9; 32]);
+        let transfer = signed_transfer(
+            &miner,
+            child.hash(),
+            recipient,
+            child.effective_value() / 2,
+            Coord::from_num(0),
+            2,
+        );
+        let block = mine_next_block(&chain, miner.address(), vec![transfer]);
+        chain.apply_block(block).unwrap();
+        (chain, recipient, children.len())
+    }
+
+    #[test]
+    fn test_lineage_from_blocks() {
+        let (chain, recipient, children) = history();
+        let graph = LineageGraph::from_blocks(&chain.blocks).unwrap();
+
+        // 3 coinbases + children + transfer output + change
+        assert_eq!(graph.nodes.len(), 3 + children + 2);
+        assert_eq!(
+            graph.edges.iter().filter(|e| e.kind == EdgeKind::Subdivision).count(),
+            children
+        );
+        assert_eq!(graph.edges.iter().filter(|e| e.kind == EdgeKind::Change).count(), 1);
+
+        // Every live triangle is an unspent node
+        for hash in chain.state.utxo_set.keys() {
+            assert_eq!(graph.nodes[hash].spent_at, None);
+        }
+
+        let received = graph.filter_owner(&recipient);
+        assert_eq!(received.edges.len(), 1);
+        assert_eq!(received.nodes.len(), 2);
+    }
+
+    #[test]
+    fn test_trace_lineage_to_coinbase() {
+        let (chain, recipient, _) = history();
+        let (received, _) = chain
+            .state
+            .utxo_set
+            .iter()
+            .find(|(_, triangle)| triangle.owner == recipient)
+            .unwrap();
+
+        let steps = chain.trace_lineage(received).unwrap();
+        let kinds: Vec<_> = steps.iter().map(|step| step.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![Some(EdgeKind::Transfer), Some(EdgeKind::Subdivision), None]
+        );
+        assert_eq!(steps[0].node.owner, recipient);
+        assert_eq!(steps[2].hash, chain.blocks[0].transactions[0].hash());
+        assert_eq!(steps[2].node.created_at, 0);
+        assert!(chain.trace_lineage(&[0; 32]).is_err());
+    }
+
+    #[test]
+    fn test_subtree_and_rendering() {
+        let (chain, _, children) = history();
+        let graph = LineageGraph::from_blocks(&chain.blocks).unwrap();
+        let root = *graph
+            .edges
+            .iter()
+            .find(|e| e.kind == EdgeKind::Subdivision)
+            .map(|e| &e.parent)
+            .unwrap();
+
+        let subtree = graph.subtree(&root).unwrap();
+        assert_eq!(subtree.nodes.len(), 1 + children + 2);
+        assert!(graph.subtree(&[0; 32]).is_err());
+
+        let dot = subtree.render("dot".parse().unwrap());
+        assert!(dot.starts_with("digraph lineage {"));
+        assert_eq!(dot.matches("->").count(), subtree.edges.len());
+
+        let graphml = subtree.render("GraphML".parse().unwrap());
+        assert_eq!(graphml.matches("<node ").count(), subtree.nodes.len());
+        assert!("svg".parse::<LineageFormat>().is_err());
+    }
+}
@@ -0,0 +1,410 @@
+//! Structured logging
+//!
+//! Library code logs through `tracing` instead of printing, so a TUI can
+//! send its logs to a file rather than have them scribble over the
+//! alternate screen. `init` installs the process-wide subscriber described
+//! by `[logging]` in `config.toml`: human-readable lines or one JSON object
+//! per line, a default level with per-module overrides (`network =
+//! "debug"`), and a size-rotated log file under the data directory.
+//! `RUST_LOG` (`target=level,...` directives) replaces the configured
+//! levels when set. Records from crates using `log` are forwarded too.
+
+use crate::error::ChainError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, MakeWriter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::{LookupSpan, Registry};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+/// Crate whose modules the short names in `[logging.modules]` refer to
+const CRATE_TARGET: &str = "trinitychain";
+
+/// Output format of log lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// `2024-01-01T00:00:00Z  INFO trinitychain::network: message key=value`
+    #[default]
+    Pretty,
+    /// One JSON object per line with timestamp, level, target, message and fields
+    Json,
+}
+
+/// `[logging]` section of `config.toml`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggingConfig {
+    /// Level for everything not listed in `modules`
+    #[serde(default = "default_level")]
+    pub level: String,
+    #[serde(default)]
+    pub format: LogFormat,
+    /// Per-module levels; short names (`network`) mean `trinitychain::network`
+    #[serde(default)]
+    pub modules: BTreeMap<String, String>,
+    /// Also write logs to `<directory>/<program>.log`
+    #[serde(default = "default_file")]
+    pub file: bool,
+    /// Log directory; defaults to `logs/` next to the database
+    #[serde(default)]
+    pub directory: Option<String>,
+    /// Rotate the log file once it reaches this size
+    #[serde(default = "default_max_file_mb")]
+    pub max_file_mb: u64,
+    /// Rotated files kept (`<program>.log.1` is the newest)
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+}
+
+fn default_level() -> String {
+    "info".to_string()
+}
+
+fn default_file() -> bool {
+    true
+}
+
+fn default_max_file_mb() -> u64 {
+    10
+}
+
+fn default_max_files() -> usize {
+    5
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig {
+            level: default_level(),
+            format: LogFormat::default(),
+            modules: BTreeMap::new(),
+            file: default_file(),
+            directory: None,
+            max_file_mb: default_max_file_mb(),
+            max_files: default_max_files(),
+        }
+    }
+}
+
+impl LoggingConfig {
+    /// The level filter: `RUST_LOG` when set, otherwise `level` and `modules`
+    pub fn targets(&self) -> Result<Targets, ChainError> {
+        if let Ok(directives) = std::env::var("RUST_LOG") {
+            if !directives.trim().is_empty() {
+                return parse_targets(&directives);
+            }
+        }
+        let mut targets = Targets::new().with_default(parse_level(&self.level)?);
+        for (module, level) in &self.modules {
+            let target = if module == CRATE_TARGET || module.contains("::") {
+                module.clone()
+            } else {
+                format!("{}::{}", CRATE_TARGET, module)
+            };
+            targets = targets.with_target(target, parse_level(level)?);
+        }
+        Ok(targets)
+    }
+
+    /// Where log files go when `directory` is not set
+    pub fn log_dir(&self, database_path: &str) -> PathBuf {
+        match &self.directory {
+            Some(directory) => PathBuf::from(directory),
+            None => Path::new(database_path)
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join("logs"),
+        }
+    }
+}
+
+pub(crate) fn parse_level(level: &str) -> Result<LevelFilter, ChainError> {
+    level
+        .parse()
+        .map_err(|_| ChainError::IoError(format!("Invalid log level '{}'", level)))
+}
+
+fn parse_targets(directives: &str) -> Result<Targets, ChainError> {
+    directives.parse().map_err(|e| {
+        ChainError::IoError(format!("Invalid log levels '{}': {}", directives, e))
+    })
+}
+
+/// Where `init` sends logs besides the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Console {
+    /// Standard error, for daemons and command-line tools
+    Stderr,
+    /// Nowhere: the program draws a TUI, logs only go to the file
+    Off,
+}
+
+/// Install the global subscriber for `program` (which names its log file).
+/// Returns the log file path when one is written. Fails if a subscriber is
+/// already installed.
+pub fn init(
+    config: &LoggingConfig,
+    program: &str,
+    database_path: &str,
+    console: Console,
+) -> Result<Option<PathBuf>, ChainError> {
+    let targets = config.targets()?;
+    let mut layers: Vec<Box<dyn Layer<Registry> + Send + Sync>> = Vec::new();
+
+    if console == Console::Stderr {
+        layers.push(format_layer(config.format, true, io::stderr, targets.clone()));
+    }
+    let mut log_file = None;
+    if config.file {
+        let path = config.log_dir(database_path).join(format!("{}.log", program));
+        let file = RotatingFile::open(
+            &path,
+            config.max_file_mb.max(1) * 1024 * 1024,
+            config.max_files,
+        )?;
+        layers.push(format_layer(config.format, false, Arc::new(file), targets));
+        log_file = Some(path);
+    }
+
+    tracing_subscriber::registry()
+        .with(layers)
+        .try_init()
+        .map_err(|e| ChainError::IoError(format!("Failed to install logger: {}", e)))?;
+    Ok(log_file)
+}
+
+fn format_layer<W>(
+    format: LogFormat,
+    ansi: bool,
+    writer: W,
+    targets: Targets,
+) -> Box<dyn Layer<Registry> + Send + Sync>
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_ansi(ansi)
+        .with_writer(writer);
+    match format {
+        LogFormat::Pretty => layer.with_filter(targets).boxed(),
+        LogFormat::Json => layer.event_format(JsonFormat).with_filter(targets).boxed(),
+    }
+}
+
+// ============================================================================
+// JSON Lines
+// ============================================================================
+
+/// Formats each event as one JSON object
+struct JsonFormat;
+
+/// Collects an event's fields; `message` is the formatted message
+#[derive(Default)]
+struct JsonFields(serde_json::Map<String, serde_json::Value>);
+
+impl Visit for JsonFields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(
+            field.name().to_string(),
+            serde_json::Value::String(format!("{:?}", value)),
+        );
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0
+            .insert(field.name().to_string(), serde_json::Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0
+            .insert(field.name().to_string(), serde_json::Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0
+            .insert(field.name().to_string(), serde_json::Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0
+            .insert(field.name().to_string(), serde_json::Value::from(value));
+    }
+}
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        _ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let metadata = event.metadata();
+        let mut fields = JsonFields::default();
+        event.record(&mut fields);
+
+        let mut line = serde_json::Map::new();
+        line.insert(
+            "timestamp".to_string(),
+            chrono::Utc::now().to_rfc3339().into(),
+        );
+        line.insert("level".to_string(), metadata.level().as_str().into());
+        line.insert("target".to_string(), metadata.target().into());
+        // Records forwarded from `log` carry their target in a field
+        if let Some(target) = fields.0.remove("log.target") {
+            line.insert("target".to_string(), target);
+        }
+        fields.0.retain(|name, _| !name.starts_with("log."));
+        line.extend(fields.0);
+
+        let encoded = serde_json::to_string(&line).map_err(|_| fmt::Error)?;
+        writeln!(writer, "{}", encoded)
+    }
+}
+
+// ============================================================================
+// Rotating Log File
+// ============================================================================
+
+/// Appends to a file, rotating it to `.1`, `.2`, ... once it reaches
+/// `max_bytes`
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    state: Mutex<(File, u64)>,
+}
+
+impl RotatingFile {
+    pub fn open(path: &Path, max_bytes: u64, max_files: usize) -> Result<Self, ChainError> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| {
+                ChainError::IoError(format!("Failed to create {}: {}", dir.display(), e))
+            })?;
+        }
+        let file = Self::append(path)?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(RotatingFile {
+            path: path.to_path_buf(),
+            max_bytes,
+            max_files,
+            state: Mutex::new((file, size)),
+        })
+    }
+
+    fn append(path: &Path) -> Result<File, ChainError> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| ChainError::IoError(format!("Failed to open {}: {}", path.display(), e)))
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    /// Shift `.1`..`.n-1` up by one, move the live file to `.1` and start a
+    /// new one
+    fn rotate(&self, state: &mut (File, u64)) -> io::Result<()> {
+        state.0.flush()?;
+        if self.max_files == 0 {
+            state.0 = File::create(&self.path)?;
+        } else {
+            for n in (1..self.max_files).rev() {
+                let from = self.rotated(n);
+                if from.exists() {
+                    fs::rename(&from, self.rotated(n + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated(1))?;
+            state.0 = Self::append(&self.path).map_err(|e| io::Error::other(e.to_string()))?;
+        }
+        state.1 = 0;
+        Ok(())
+    }
+}
+
+impl Write for &RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.1 > 0 && state.1 + buf.len() as u64 > self.max_bytes {
+            self.rotate(&mut state)?;
+        }
+        let written = state.0.write(buf)?;
+        state.1 += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .0
+            .flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing::Level;
+
+    #[test]
+    fn test_module_levels() {
+        let mut config = LoggingConfig {
+            level: "warn".to_string(),
+            ..Default::default()
+        };
+        config
+            .modules
+            .insert("network".to_string(), "debug".to_string());
+        config
+            .modules
+            .insert("teloxide::dispatching".to_string(), "error".to_string());
+        let targets = config.targets().unwrap();
+
+        assert!(targets.would_enable("trinitychain::network", &Level::DEBUG));
+        assert!(!targets.would_enable("trinitychain::miner", &Level::INFO));
+        assert!(targets.would_enable("trinitychain::miner", &Level::WARN));
+        assert!(!targets.would_enable("teloxide::dispatching", &Level::WARN));
+
+        config.level = "loud".to_string();
+        assert!(config.targets().is_err());
+        assert_eq!(
+            config.log_dir("/var/lib/trinity/chain.db"),
+            PathBuf::from("/var/lib/trinity/logs")
+        );
+    }
+
+    #[test]
+    fn test_log_file_rotates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("logs").join("node.log");
+        let file = RotatingFile::open(&path, 10, 2).unwrap();
+        for line in ["first 1\n", "second\n", "third 3\n", "fourth\n"] {
+            (&file).write_all(line.as_bytes()).unwrap();
+        }
+
+        let read = |p: &Path| fs::read_to_string(p).unwrap();
+        assert_eq!(read(&path), "fourth\n");
+        assert_eq!(read(&file.rotated(1)), "third 3\n");
+        assert_eq!(read(&file.rotated(2)), "second\n");
+        assert!(!file.rotated(3).exists());
+    }
+}
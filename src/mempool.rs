@@ -3,10 +3,15 @@
 use crate::blockchain::Sha256Hash;
 use crate::crypto::Address;
 use crate::error::ChainError;
+use crate::events::{ChainEvent, EventBus};
+use crate::fees::estimate_transaction_size;
+use crate::policy::TxPolicy;
 use crate::transaction::Transaction;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
 
 const MAX_MEMPOOL_SIZE: usize = 10000; // Max transactions in mempool
 const MAX_TX_PER_ADDRESS: usize = 100; // Max transactions per sender address
@@ -17,11 +22,44 @@ pub struct MempoolTransaction {
     timestamp: i64,
 }
 
+impl MempoolTransaction {
+    /// Unix time (seconds) the transaction entered the mempool
+    pub fn received_at(&self) -> i64 {
+        self.timestamp
+    }
+
+    /// Position in the fee-rate index: highest rate first, then oldest
+    fn priority_key(&self) -> PriorityKey {
+        (Reverse(fee_rate(&self.tx)), self.timestamp, self.tx.hash())
+    }
+}
+
+/// Fee-rate index key: descending rate, then arrival time, then hash
+type PriorityKey = (Reverse<i128>, i64, Sha256Hash);
+
+/// Fee per 1000 estimated bytes, in raw [`Coord`](crate::geometry::Coord)
+/// bits so rates order exactly
+pub fn fee_rate(tx: &Transaction) -> i128 {
+    tx.fee_area().to_bits() as i128 * 1000 / estimate_transaction_size(tx).max(1) as i128
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Mempool {
     transactions: HashMap<Sha256Hash, MempoolTransaction>,
     #[serde(skip)]
     by_sender: HashMap<Address, Vec<Sha256Hash>>,
+    /// Input (or subdivision parent) hash -> pending transaction spending it
+    #[serde(skip)]
+    spent_inputs: HashMap<Sha256Hash, Sha256Hash>,
+    /// Every pending transaction, best fee rate first
+    #[serde(skip)]
+    by_fee_rate: BTreeSet<PriorityKey>,
+    /// Node-local filter applied on admission and template building
+    #[serde(skip)]
+    policy: Option<Arc<TxPolicy>>,
+    /// Where accepted transactions are announced (the owning chain's bus)
+    #[serde(skip)]
+    events: Option<EventBus>,
 }
 
 impl Default for Mempool {
@@ -35,24 +73,55 @@ impl Mempool {
         Mempool {
             transactions: HashMap::new(),
             by_sender: HashMap::new(),
+            spent_inputs: HashMap::new(),
+            by_fee_rate: BTreeSet::new(),
+            policy: None,
+            events: None,
         }
     }
 
-    pub fn add_transaction(&mut self, tx: Transaction) -> Result<(), ChainError> {
-        if self.transactions.len() >= MAX_MEMPOOL_SIZE {
-            self.evict_lowest_fee()?;
-        }
+    /// Refuse (and leave out of block templates) transactions `policy` rejects
+    pub fn set_policy(&mut self, policy: Arc<TxPolicy>) {
+        self.policy = Some(policy);
+    }
 
+    /// Publish a `TransactionAccepted` event on `events` for every
+    /// transaction added from now on
+    pub(crate) fn set_event_bus(&mut self, events: EventBus) {
+        self.events = Some(events);
+    }
+
+    /// Add a transaction. A transaction spending an input that a pending
+    /// transaction already spends replaces it if it pays a strictly higher
+    /// fee, and is rejected with `DoubleSpendDetected` otherwise.
+    pub fn add_transaction(&mut self, tx: Transaction) -> Result<(), ChainError> {
         let tx_hash = tx.hash();
         if self.transactions.contains_key(&tx_hash) {
             return Err(ChainError::InvalidTransaction(
                 "Transaction already in mempool".to_string(),
             ));
         }
+        if let Some(policy) = &self.policy {
+            policy.check(&tx)?;
+        }
+
+        let replaces = match self.conflicting_transaction(&tx) {
+            Some(existing) if tx.fee_area() > existing.fee_area() => Some(existing.hash()),
+            Some(existing) => {
+                return Err(ChainError::DoubleSpendDetected(format!(
+                    "Input {} is already spent by pending transaction {} (fee {}); a replacement must pay a higher fee",
+                    hex::encode(tx.input_hash().unwrap_or_default()),
+                    existing.hash_str(),
+                    existing.fee_area()
+                )))
+            }
+            None => None,
+        };
 
         let sender = match &tx {
             Transaction::Transfer(tx) => tx.sender,
             Transaction::Subdivision(tx) => tx.owner_address,
+            Transaction::BatchTransfer(tx) => tx.sender,
             Transaction::Coinbase(_) => {
                 return Err(ChainError::InvalidTransaction(
                     "Coinbase transactions cannot be in mempool".to_string(),
@@ -60,36 +129,52 @@ impl Mempool {
             }
         };
 
-        let sender_txs = self.by_sender.entry(sender).or_default();
-        if sender_txs.len() >= MAX_TX_PER_ADDRESS {
+        let sender_count = self.by_sender.get(&sender).map_or(0, Vec::len);
+        if replaces.is_none() && sender_count >= MAX_TX_PER_ADDRESS {
             return Err(ChainError::InvalidTransaction(
                 "Exceeded maximum transactions per address".to_string(),
             ));
         }
 
+        // Every check has passed; only now make room
+        if let Some(replaced) = replaces {
+            self.remove_transaction(&replaced);
+        } else if self.transactions.len() >= MAX_MEMPOOL_SIZE {
+            self.evict_lowest_fee()?;
+        }
+        if let Some(input) = tx.input_hash() {
+            self.spent_inputs.insert(input, tx_hash);
+        }
         let mempool_tx = MempoolTransaction {
             tx,
             timestamp: Utc::now().timestamp(),
         };
 
+        self.by_fee_rate.insert(mempool_tx.priority_key());
         self.transactions.insert(tx_hash, mempool_tx);
-        sender_txs.push(tx_hash);
+        self.by_sender.entry(sender).or_default().push(tx_hash);
 
+        if let Some(events) = self.events.as_ref().filter(|e| e.has_subscribers()) {
+            let tx = self.transactions[&tx_hash].tx.clone();
+            events.publish(ChainEvent::TransactionAccepted(Arc::new(tx)));
+        }
         Ok(())
     }
 
+    /// The pending transaction spending the same input as `tx`, if any
+    pub fn conflicting_transaction(&self, tx: &Transaction) -> Option<&Transaction> {
+        let input = tx.input_hash()?;
+        self.spent_inputs
+            .get(&input)
+            .and_then(|hash| self.get_transaction(hash))
+    }
+
+    /// Drop the transaction with the lowest fee rate (the newest among equals)
     fn evict_lowest_fee(&mut self) -> Result<(), ChainError> {
-        if let Some(eviction_candidate) = self
-            .transactions
-            .values()
-            .min_by_key(|tx| tx.tx.fee_area())
-            .map(|tx| tx.tx.hash())
-        {
-            self.remove_transaction(&eviction_candidate);
-            Ok(())
-        } else {
-            Err(ChainError::MempoolFull)
-        }
+        let (_, _, eviction_candidate) =
+            *self.by_fee_rate.last().ok_or(ChainError::MempoolFull)?;
+        self.remove_transaction(&eviction_candidate);
+        Ok(())
     }
 
     pub fn get_transactions_by_fee(&self, limit: usize) -> Vec<Transaction> {
@@ -103,11 +188,26 @@ impl Mempool {
         txs
     }
 
+    /// Pending transactions in fee-rate order (see [`fee_rate`]), highest
+    /// first; equal rates go oldest first
+    pub fn by_fee_rate(&self) -> impl Iterator<Item = &Transaction> {
+        self.by_fee_rate
+            .iter()
+            .filter_map(|(_, _, hash)| self.get_transaction(hash))
+    }
+
     pub fn remove_transaction(&mut self, tx_hash: &Sha256Hash) {
         if let Some(mempool_tx) = self.transactions.remove(tx_hash) {
+            self.by_fee_rate.remove(&mempool_tx.priority_key());
+            if let Some(input) = mempool_tx.tx.input_hash() {
+                if self.spent_inputs.get(&input) == Some(tx_hash) {
+                    self.spent_inputs.remove(&input);
+                }
+            }
             let sender = match &mempool_tx.tx {
                 Transaction::Transfer(tx) => tx.sender,
                 Transaction::Subdivision(tx) => tx.owner_address,
+                Transaction::BatchTransfer(tx) => tx.sender,
                 Transaction::Coinbase(_) => return,
             };
 
@@ -131,6 +231,21 @@ impl Mempool {
             .collect()
     }
 
+    /// Pending transactions the node policy still allows, for a block
+    /// template, in fee-rate order. The policy is asked again since its
+    /// answer may have changed since admission.
+    pub fn minable_transactions(&self) -> Vec<Transaction> {
+        self.by_fee_rate()
+            .filter(|tx| self.policy.as_ref().is_none_or(|policy| policy.allows(tx)))
+            .cloned()
+            .collect()
+    }
+
+    /// All pending entries, with the time each was received
+    pub fn entries(&self) -> impl Iterator<Item = &MempoolTransaction> {
+        self.transactions.values()
+    }
+
     pub fn remove_transactions(&mut self, tx_hashes: &[Sha256Hash]) {
         for hash in tx_hashes {
             self.remove_transaction(hash);
@@ -145,10 +260,11 @@ impl Mempool {
         self.transactions.is_empty()
     }
 
-    pub fn prune(&mut self, state: &crate::blockchain::TriangleState) {
+    /// Drop transactions that are no longer valid for a block at `height`
+    pub fn prune(&mut self, state: &crate::blockchain::TriangleState, height: u64) {
         let mut invalid_hashes = Vec::new();
         for (hash, mempool_tx) in self.transactions.iter() {
-            if mempool_tx.tx.validate(state).is_err() {
+            if mempool_tx.tx.validate(state, height).is_err() {
                 invalid_hashes.push(*hash);
             }
         }
@@ -158,3 +274,111 @@ impl Mempool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KeyPair;
+    use crate::fixtures::signed_transfer;
+    use crate::geometry::Coord;
+    use crate::transaction::TransferTx;
+
+    #[test]
+    fn test_conflicting_spend_rejected_or_replaced() {
+        let keypair = KeyPair::generate().unwrap();
+        let input = [7; 32];
+        let pay = |fee: f64, nonce| {
+            signed_transfer(
+                &keypair,
+                input,
+                Address::from([2; 32]),
+                Coord::from_num(5),
+                Coord::from_num(fee),
+                nonce,
+            )
+        };
+
+        let mut mempool = Mempool::new();
+        let original = pay(0.1, 1);
+        mempool.add_transaction(original.clone()).unwrap();
+
+        // Same fee: a double spend
+        let result = mempool.add_transaction(pay(0.1, 2));
+        assert!(matches!(result, Err(ChainError::DoubleSpendDetected(_))));
+        assert_eq!(mempool.len(), 1);
+
+        // Higher fee: replaces the pending transaction
+        let replacement = pay(0.5, 3);
+        mempool.add_transaction(replacement.clone()).unwrap();
+        assert_eq!(mempool.len(), 1);
+        assert!(mempool.get_transaction(&original.hash()).is_none());
+        assert_eq!(
+            mempool.conflicting_transaction(&original).map(|tx| tx.hash()),
+            Some(replacement.hash())
+        );
+
+        // Once the spend leaves the mempool the input is free again
+        mempool.remove_transaction(&replacement.hash());
+        mempool.add_transaction(original).unwrap();
+    }
+
+    #[test]
+    fn test_eviction_follows_fee_rate() {
+        let transfer = |input, fee: u32, memo: &str| {
+            Transaction::Transfer(
+                TransferTx::new(
+                    [input; 32],
+                    Address::from([2; 32]),
+                    Address::from([input; 32]),
+                    Coord::from_num(1),
+                    Coord::from_num(fee),
+                    0,
+                )
+                .with_memo(memo.to_string())
+                .unwrap(),
+            )
+        };
+        // The higher fee buys a long memo, leaving it the lower rate
+        let cheap = transfer(1, 1, "");
+        let bulky = transfer(2, 2, &"x".repeat(TransferTx::MAX_MEMO_LENGTH));
+        assert!(bulky.fee_area() > cheap.fee_area());
+        assert!(fee_rate(&bulky) < fee_rate(&cheap));
+
+        let mut mempool = Mempool::new();
+        mempool.add_transaction(cheap.clone()).unwrap();
+        mempool.add_transaction(bulky.clone()).unwrap();
+        mempool.evict_lowest_fee().unwrap();
+        assert!(mempool.get_transaction(&bulky.hash()).is_none());
+        assert!(mempool.get_transaction(&cheap.hash()).is_some());
+    }
+
+    #[test]
+    fn test_full_mempool_rejection_evicts_nothing() {
+        let transfer = |sender: u8, input: u32| {
+            let mut input_hash = [sender; 32];
+            input_hash[..4].copy_from_slice(&input.to_le_bytes());
+            Transaction::Transfer(TransferTx::new(
+                input_hash,
+                Address::from([0xee; 32]),
+                Address::from([sender; 32]),
+                Coord::from_num(1),
+                Coord::from_num(1),
+                0,
+            ))
+        };
+
+        let mut mempool = Mempool::new();
+        let senders = (MAX_MEMPOOL_SIZE / MAX_TX_PER_ADDRESS) as u8;
+        for sender in 1..=senders {
+            for input in 0..MAX_TX_PER_ADDRESS as u32 {
+                mempool.add_transaction(transfer(sender, input)).unwrap();
+            }
+        }
+        assert_eq!(mempool.len(), MAX_MEMPOOL_SIZE);
+
+        // Over the per-sender limit: refused before anything is evicted
+        let result = mempool.add_transaction(transfer(1, u32::MAX));
+        assert!(matches!(result, Err(ChainError::InvalidTransaction(_))));
+        assert_eq!(mempool.len(), MAX_MEMPOOL_SIZE);
+    }
+}
@@ -0,0 +1,218 @@
+//! Block validation timing metrics
+//!
+//! `Blockchain::apply_block` records how long each transaction spends in
+//! stateless checks (dominated by signature verification) and in state
+//! checks plus UTXO application, per transaction type, along with the total
+//! time per block. Timings go into fixed-bucket histograms, so recording is
+//! a few integer operations and memory stays constant.
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Upper bounds (microseconds) of the histogram buckets; slower samples go
+/// into a final overflow bucket
+const BUCKET_BOUNDS_US: [u64; 14] = [
+    10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 100_000, 500_000, 2_000_000,
+];
+
+/// Fixed-bucket latency histogram
+#[derive(Debug, Clone, Default)]
+pub struct Histogram {
+    buckets: [u64; BUCKET_BOUNDS_US.len() + 1],
+    count: u64,
+    sum_us: u64,
+    max_us: u64,
+}
+
+/// Summary statistics of a histogram. Percentiles are bucket upper bounds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct HistogramSummary {
+    pub count: u64,
+    pub mean_us: f64,
+    pub p50_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+}
+
+impl Histogram {
+    pub fn record(&mut self, elapsed: Duration) {
+        let us = elapsed.as_micros().min(u64::MAX as u128) as u64;
+        let bucket = BUCKET_BOUNDS_US
+            .iter()
+            .position(|&bound| us <= bound)
+            .unwrap_or(BUCKET_BOUNDS_US.len());
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.sum_us = self.sum_us.saturating_add(us);
+        self.max_us = self.max_us.max(us);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Upper bound of the bucket holding quantile `q` (0.0..=1.0)
+    fn quantile(&self, q: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let rank = ((self.count as f64 * q).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (i, &n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                return BUCKET_BOUNDS_US.get(i).copied().unwrap_or(self.max_us).min(self.max_us);
+            }
+        }
+        self.max_us
+    }
+
+    pub fn summary(&self) -> HistogramSummary {
+        HistogramSummary {
+            count: self.count,
+            mean_us: if self.count == 0 {
+                0.0
+            } else {
+                self.sum_us as f64 / self.count as f64
+            },
+            p50_us: self.quantile(0.5),
+            p99_us: self.quantile(0.99),
+            max_us: self.max_us,
+        }
+    }
+}
+
+/// Timings of one transaction type
+#[derive(Debug, Clone, Default)]
+struct TxTimings {
+    signature: Histogram,
+    state: Histogram,
+}
+
+/// Validation timings, per transaction type and per block
+#[derive(Debug, Clone, Default)]
+pub struct ValidationMetrics {
+    transactions: BTreeMap<&'static str, TxTimings>,
+    blocks: Histogram,
+}
+
+/// Timing summary of one transaction type
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TxTimingSummary {
+    /// Stateless checks, dominated by signature verification
+    pub signature: HistogramSummary,
+    /// UTXO checks and state application
+    pub state: HistogramSummary,
+}
+
+/// Serializable view of [`ValidationMetrics`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValidationSummary {
+    pub transactions: BTreeMap<String, TxTimingSummary>,
+    pub blocks: HistogramSummary,
+}
+
+impl ValidationMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record one transaction of type `kind`
+    pub fn record_transaction(&mut self, kind: &'static str, signature: Duration, state: Duration) {
+        let timings = self.transactions.entry(kind).or_default();
+        timings.signature.record(signature);
+        timings.state.record(state);
+    }
+
+    /// Record the total validation time of a block
+    pub fn record_block(&mut self, elapsed: Duration) {
+        self.blocks.record(elapsed);
+    }
+
+    pub fn summary(&self) -> ValidationSummary {
+        ValidationSummary {
+            transactions: self
+                .transactions
+                .iter()
+                .map(|(kind, t)| {
+                    (
+                        kind.to_string(),
+                        TxTimingSummary {
+                            signature: t.signature.summary(),
+                            state: t.state.summary(),
+                        },
+                    )
+                })
+                .collect(),
+            blocks: self.blocks.summary(),
+        }
+    }
+}
+
+/// Process-wide validation timings
+static VALIDATION: Lazy<Mutex<ValidationMetrics>> = Lazy::new(Mutex::default);
+
+/// Add a block's timings to the process-wide metrics
+pub fn record_validation(block: &ValidationMetrics) {
+    let mut global = VALIDATION.lock();
+    for (kind, timings) in &block.transactions {
+        let entry = global.transactions.entry(kind).or_default();
+        merge(&mut entry.signature, &timings.signature);
+        merge(&mut entry.state, &timings.state);
+    }
+    merge(&mut global.blocks, &block.blocks);
+}
+
+fn merge(into: &mut Histogram, from: &Histogram) {
+    for (a, b) in into.buckets.iter_mut().zip(from.buckets.iter()) {
+        *a += b;
+    }
+    into.count += from.count;
+    into.sum_us = into.sum_us.saturating_add(from.sum_us);
+    into.max_us = into.max_us.max(from.max_us);
+}
+
+/// Summary of every block validated by this process
+pub fn validation_summary() -> ValidationSummary {
+    VALIDATION.lock().summary()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_summary() {
+        let mut histogram = Histogram::default();
+        assert_eq!(histogram.summary(), HistogramSummary::default());
+
+        for _ in 0..98 {
+            histogram.record(Duration::from_micros(40));
+        }
+        histogram.record(Duration::from_micros(900));
+        histogram.record(Duration::from_millis(30));
+
+        let summary = histogram.summary();
+        assert_eq!(summary.count, 100);
+        assert_eq!(summary.p50_us, 50);
+        assert_eq!(summary.p99_us, 1_000);
+        assert_eq!(summary.max_us, 30_000);
+    }
+
+    #[test]
+    fn test_validation_metrics_by_kind() {
+        let mut metrics = ValidationMetrics::new();
+        metrics.record_transaction("transfer", Duration::from_micros(80), Duration::from_micros(5));
+        metrics.record_transaction("transfer", Duration::from_micros(90), Duration::from_micros(6));
+        metrics.record_transaction("coinbase", Duration::ZERO, Duration::from_micros(3));
+        metrics.record_block(Duration::from_micros(200));
+
+        let summary = metrics.summary();
+        assert_eq!(summary.transactions["transfer"].signature.count, 2);
+        assert_eq!(summary.transactions["coinbase"].state.count, 1);
+        assert_eq!(summary.blocks.count, 1);
+    }
+}
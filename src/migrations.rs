@@ -0,0 +1,378 @@
+//! Versioned schema migrations for the SQLite database
+//!
+//! Every schema change is an ordered, numbered migration. `Database::open`
+//! applies the ones an existing database has not seen yet, each in its own
+//! transaction, and records them in the `schema_version` table. New tables,
+//! indexes or columns therefore reach user databases without a chain resync.
+//!
+//! Migrations are append-only: never edit or reorder a released one, add a
+//! new one at the end of [`MIGRATIONS`] instead.
+
+use crate::error::ChainError;
+use rusqlite::{params, Connection};
+
+/// A single schema change
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    apply: fn(&Connection) -> Result<(), ChainError>,
+}
+
+/// All migrations, in the order they are applied
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "blocks, utxo_set and metadata tables",
+        apply: create_core_tables,
+    },
+    Migration {
+        version: 2,
+        description: "analytics_blocks table",
+        apply: create_analytics_table,
+    },
+    Migration {
+        version: 3,
+        description: "peers table",
+        apply: create_peers_table,
+    },
+    Migration {
+        version: 4,
+        description: "address → transaction index",
+        apply: create_address_index,
+    },
+    Migration {
+        version: 5,
+        description: "settings table",
+        apply: create_settings_table,
+    },
+    Migration {
+        version: 6,
+        description: "peer bans table",
+        apply: create_bans_table,
+    },
+    Migration {
+        version: 7,
+        description: "transaction → block index",
+        apply: create_tx_index,
+    },
+    Migration {
+        version: 8,
+        description: "UTXO coinbase heights",
+        apply: add_utxo_coinbase_height,
+    },
+    Migration {
+        version: 9,
+        description: "memo index",
+        apply: create_memo_index,
+    },
+    Migration {
+        version: 10,
+        description: "block header version",
+        apply: add_block_version,
+    },
+    Migration {
+        version: 11,
+        description: "block state root",
+        apply: add_block_state_root,
+    },
+    Migration {
+        version: 12,
+        description: "utxo_deltas table",
+        apply: create_utxo_deltas,
+    },
+];
+
+/// Schema version produced by applying every migration
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+fn db_err(what: &str) -> impl Fn(rusqlite::Error) -> ChainError + '_ {
+    move |e| ChainError::DatabaseError(format!("{}: {}", what, e))
+}
+
+/// Highest migration applied to `conn` (0 for a new database)
+pub fn schema_version(conn: &Connection) -> Result<u32, ChainError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at INTEGER NOT NULL
+        )",
+        [],
+    )
+    .map_err(db_err("Failed to create schema_version table"))?;
+
+    conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version", [], |row| {
+        row.get(0)
+    })
+    .map_err(db_err("Failed to read schema version"))
+}
+
+/// Apply every pending migration. Returns the number applied.
+///
+/// Refuses to touch a database written by a newer node, whose schema this
+/// build does not understand.
+pub fn run_migrations(conn: &Connection) -> Result<usize, ChainError> {
+    let current = schema_version(conn)?;
+    let latest = latest_version();
+    if current > latest {
+        return Err(ChainError::DatabaseError(format!(
+            "Database schema version {} is newer than this node supports ({}); upgrade the node",
+            current, latest
+        )));
+    }
+
+    let mut applied = 0;
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(db_err("Failed to start migration"))?;
+        (migration.apply)(&tx).map_err(|e| {
+            ChainError::DatabaseError(format!(
+                "Migration {} ({}) failed: {}",
+                migration.version, migration.description, e
+            ))
+        })?;
+        tx.execute(
+            "INSERT INTO schema_version (version, description, applied_at) VALUES (?1, ?2, ?3)",
+            params![
+                migration.version,
+                migration.description,
+                chrono::Utc::now().timestamp()
+            ],
+        )
+        .map_err(db_err("Failed to record migration"))?;
+        tx.commit().map_err(db_err("Failed to commit migration"))?;
+        applied += 1;
+    }
+    Ok(applied)
+}
+
+// ============================================================================
+// Migrations
+// ============================================================================
+//
+// Databases created before the migration framework already have some of
+// these tables, so the early migrations use `IF NOT EXISTS`.
+
+fn create_core_tables(conn: &Connection) -> Result<(), ChainError> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS blocks (
+            height INTEGER PRIMARY KEY,
+            hash BLOB NOT NULL,
+            previous_hash BLOB NOT NULL,
+            timestamp INTEGER NOT NULL,
+            difficulty INTEGER NOT NULL,
+            nonce INTEGER NOT NULL,
+            merkle_root BLOB NOT NULL,
+            transactions TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS utxo_set (
+            hash BLOB PRIMARY KEY,
+            triangle_data TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS metadata (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );",
+    )
+    .map_err(db_err("Failed to create core tables"))
+}
+
+fn create_analytics_table(conn: &Connection) -> Result<(), ChainError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS analytics_blocks (
+            height INTEGER PRIMARY KEY,
+            stats TEXT NOT NULL
+        )",
+        [],
+    )
+    .map(|_| ())
+    .map_err(db_err("Failed to create analytics_blocks table"))
+}
+
+fn create_peers_table(conn: &Connection) -> Result<(), ChainError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS peers (
+            addr TEXT PRIMARY KEY,
+            peer TEXT NOT NULL
+        )",
+        [],
+    )
+    .map(|_| ())
+    .map_err(db_err("Failed to create peers table"))
+}
+
+fn create_address_index(conn: &Connection) -> Result<(), ChainError> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS address_txs (
+            address BLOB NOT NULL,
+            height INTEGER NOT NULL,
+            tx_index INTEGER NOT NULL,
+            PRIMARY KEY (address, height, tx_index)
+        );
+        CREATE INDEX IF NOT EXISTS address_txs_height ON address_txs (height);",
+    )
+    .map_err(db_err("Failed to create address_txs table"))?;
+
+    // Index the blocks saved before the address index existed
+    crate::persistence::rebuild_address_index_in(conn)
+}
+
+fn create_settings_table(conn: &Connection) -> Result<(), ChainError> {
+    conn.execute(
+        "CREATE TABLE settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )
+    .map(|_| ())
+    .map_err(db_err("Failed to create settings table"))
+}
+
+fn create_bans_table(conn: &Connection) -> Result<(), ChainError> {
+    conn.execute(
+        "CREATE TABLE bans (
+            host TEXT PRIMARY KEY,
+            banned_until INTEGER NOT NULL,
+            reason TEXT NOT NULL
+        )",
+        [],
+    )
+    .map(|_| ())
+    .map_err(db_err("Failed to create bans table"))
+}
+
+fn create_tx_index(conn: &Connection) -> Result<(), ChainError> {
+    conn.execute_batch(
+        "CREATE TABLE tx_blocks (
+            tx_hash BLOB PRIMARY KEY,
+            height INTEGER NOT NULL,
+            tx_index INTEGER NOT NULL
+        );
+        CREATE INDEX tx_blocks_height ON tx_blocks (height);",
+    )
+    .map_err(db_err("Failed to create tx_blocks table"))?;
+
+    // Index the blocks saved before the transaction index existed
+    crate::persistence::rebuild_tx_index_in(conn)
+}
+
+fn add_utxo_coinbase_height(conn: &Connection) -> Result<(), ChainError> {
+    // Height a coinbase triangle was mined at, so its maturity survives a
+    // restart without the block bodies; NULL for every other triangle
+    conn.execute_batch("ALTER TABLE utxo_set ADD COLUMN coinbase_height INTEGER;")
+        .map_err(db_err("Failed to add coinbase_height column"))
+}
+
+fn create_memo_index(conn: &Connection) -> Result<(), ChainError> {
+    // Filled only once the memo index is switched on
+    conn.execute(
+        "CREATE TABLE memo_txs (
+            height INTEGER NOT NULL,
+            tx_index INTEGER NOT NULL,
+            memo TEXT NOT NULL,
+            PRIMARY KEY (height, tx_index)
+        )",
+        [],
+    )
+    .map(|_| ())
+    .map_err(db_err("Failed to create memo_txs table"))
+}
+
+fn add_block_version(conn: &Connection) -> Result<(), ChainError> {
+    // Blocks stored before version bits were mined with version 0
+    conn.execute(
+        "ALTER TABLE blocks ADD COLUMN version INTEGER NOT NULL DEFAULT 0",
+        [],
+    )
+    .map(|_| ())
+    .map_err(db_err("Failed to add blocks.version"))
+}
+
+fn add_block_state_root(conn: &Connection) -> Result<(), ChainError> {
+    // Stored blocks predate the commitment; a zero root marks that
+    conn.execute(
+        &format!(
+            "ALTER TABLE blocks ADD COLUMN state_root BLOB NOT NULL DEFAULT X'{}'",
+            "00".repeat(32)
+        ),
+        [],
+    )
+    .map(|_| ())
+    .map_err(db_err("Failed to add blocks.state_root"))
+}
+
+fn create_utxo_deltas(conn: &Connection) -> Result<(), ChainError> {
+    // Per-block UTXO changes on top of the utxo_set checkpoint; a NULL
+    // triangle_data marks a spent triangle
+    conn.execute_batch(
+        "CREATE TABLE utxo_deltas (
+            height INTEGER NOT NULL,
+            hash BLOB NOT NULL,
+            triangle_data TEXT,
+            coinbase_height INTEGER,
+            PRIMARY KEY (height, hash)
+        );
+        CREATE INDEX utxo_deltas_hash ON utxo_deltas (hash, height);",
+    )
+    .map_err(db_err("Failed to create utxo_deltas table"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_are_ordered() {
+        for pair in MIGRATIONS.windows(2) {
+            assert!(pair[0].version < pair[1].version);
+        }
+        assert_eq!(MIGRATIONS[0].version, 1);
+    }
+
+    #[test]
+    fn test_run_migrations_once() {
+        let conn = Connection::open_in_memory().unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), 0);
+        assert_eq!(run_migrations(&conn).unwrap(), MIGRATIONS.len());
+        assert_eq!(schema_version(&conn).unwrap(), latest_version());
+        // Reopening applies nothing
+        assert_eq!(run_migrations(&conn).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_upgrades_pre_migration_database() {
+        // A database created before versioning: tables exist, no schema_version
+        let conn = Connection::open_in_memory().unwrap();
+        create_core_tables(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO metadata (key, value) VALUES ('difficulty', '3')",
+            [],
+        )
+        .unwrap();
+
+        assert_eq!(run_migrations(&conn).unwrap(), MIGRATIONS.len());
+        let difficulty: String = conn
+            .query_row("SELECT value FROM metadata WHERE key = 'difficulty'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(difficulty, "3");
+    }
+
+    #[test]
+    fn test_refuses_newer_schema() {
+        let conn = Connection::open_in_memory().unwrap();
+        run_migrations(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO schema_version (version, description, applied_at) VALUES (?1, 'future', 0)",
+            params![latest_version() + 1],
+        )
+        .unwrap();
+        assert!(run_migrations(&conn).is_err());
+    }
+}
@@ -1,10 +1,14 @@
 //! Proof-of-Work (PoW) implementation for TrinityChain.
 
-use crate::blockchain::{Block, Sha256Hash};
+use crate::blockchain::{Block, Blockchain, Sha256Hash};
+use crate::crypto::Address;
 use crate::error::ChainError;
-use rayon::prelude::*;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use crate::fees::estimate_transaction_size;
+use crate::geometry::Coord;
+use crate::transaction::{CoinbaseTx, Transaction};
+use crossbeam_channel::{bounded, RecvTimeoutError};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
 /// Checks if a hash meets the required difficulty target.
 /// The difficulty is the required number of leading zeros in the hash.
@@ -58,45 +62,350 @@ pub fn mine_block(mut block: Block) -> Result<Block, ChainError> {
     }
 }
 
-use crossbeam_channel::{unbounded, Receiver, Sender};
+/// Hashes a worker computes between checking the stop flag and publishing
+/// its count
+const WORKER_BATCH: u64 = 4_096;
 
-type MiningResult = (u64, Sha256Hash);
+/// How often aggregate progress is reported while mining in parallel
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long a miner works on one block template before refreshing its
+/// timestamp and coinbase extranonce (see [`Block::update_merkle_incremental`])
+pub const TEMPLATE_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Added to the coinbase nonce each time the extranonce rolls. The default
+/// nonce is the block height, so extranonces live above every height a chain
+/// will reach and a rolled coinbase never hashes like a later block's default.
+pub const EXTRANONCE_STEP: u64 = 1 << 32;
+
+/// Largest block template built, in estimated bytes
+/// ([`estimate_transaction_size`]) including the coinbase
+pub const MAX_BLOCK_SIZE: usize = 1_000_000;
+
+/// A block ready for proof-of-work
+#[derive(Debug, Clone)]
+pub struct BlockTemplate {
+    pub block: Block,
+    /// Fees of the included transactions, claimed by the coinbase on top of
+    /// the subsidy
+    pub fees: Coord,
+    /// Estimated size in bytes
+    pub size: usize,
+}
+
+/// Assembles the next block from the chain's mempool.
+///
+/// Transactions are taken best fee rate first and each is validated against
+/// the state the ones before it leave behind, so conflicting spends and
+/// spends of inputs that no longer exist stay out. A transaction spending an
+/// output of one taken later is retried once its parent is in. Transactions
+/// that would push the block past its size limit are skipped while smaller
+/// ones may still fit.
+pub struct BlockTemplateBuilder<'a> {
+    chain: &'a Blockchain,
+    beneficiary: Address,
+    max_size: usize,
+    coinbase_nonce: Option<u64>,
+}
+
+impl<'a> BlockTemplateBuilder<'a> {
+    /// Build on `chain`'s tip, paying the reward to `beneficiary`
+    pub fn new(chain: &'a Blockchain, beneficiary: Address) -> Self {
+        BlockTemplateBuilder {
+            chain,
+            beneficiary,
+            max_size: MAX_BLOCK_SIZE,
+            coinbase_nonce: None,
+        }
+    }
+
+    /// Keep the template under `max_size` estimated bytes
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Coinbase nonce to start from; defaults to the height. Roll it with
+    /// [`EXTRANONCE_STEP`] so it stays clear of other heights' defaults.
+    pub fn with_coinbase_nonce(mut self, nonce: u64) -> Self {
+        self.coinbase_nonce = Some(nonce);
+        self
+    }
+
+    pub fn build(&self) -> Result<BlockTemplate, ChainError> {
+        let parent = self.chain.blocks.last().ok_or_else(|| {
+            ChainError::InvalidBlock("Cannot build on a chain without a genesis block".to_string())
+        })?;
+        let height = parent.header.height + 1;
+
+        let mut state = self.chain.state.clone();
+        let mut size = estimate_transaction_size(&Transaction::Coinbase(CoinbaseTx {
+            reward_area: Coord::from_num(0),
+            beneficiary_address: self.beneficiary,
+            nonce: 0,
+        }));
+        let mut fees = Coord::from_num(0);
+        let mut selected = Vec::new();
+        let mut pending = self.chain.mempool.minable_transactions();
+        loop {
+            let taken = selected.len();
+            let mut deferred = Vec::new();
+            for tx in pending {
+                let tx_size = estimate_transaction_size(&tx);
+                if size + tx_size > self.max_size {
+                    continue;
+                }
+                if tx.validate(&state, height).is_err()
+                    || state.apply_transaction(&tx, height).is_err()
+                {
+                    deferred.push(tx);
+                    continue;
+                }
+                size += tx_size;
+                fees = fees.saturating_add(tx.fee_area());
+                selected.push(tx);
+            }
+            // Failures may spend outputs of transactions this pass took
+            if deferred.is_empty() || selected.len() == taken {
+                break;
+            }
+            pending = deferred;
+        }
+
+        let coinbase = Transaction::Coinbase(CoinbaseTx {
+            reward_area: Blockchain::max_coinbase_reward(height, fees),
+            beneficiary_address: self.beneficiary,
+            nonce: self.coinbase_nonce.unwrap_or(height),
+        });
+        state.apply_transaction(&coinbase, height)?;
+        let mut transactions = vec![coinbase];
+        transactions.extend(selected);
+
+        let mut block = Block::new(height, parent.hash(), self.chain.difficulty, transactions);
+        block.header.version = self.chain.next_block_version();
+        // A clock running behind recent blocks still has to clear the
+        // median time past
+        block.header.timestamp = block
+            .header
+            .timestamp
+            .max(self.chain.median_time_past() + 1);
+        block.header.state_root = state.state_root();
+        Ok(BlockTemplate { block, fees, size })
+    }
+}
+
+/// Aggregate progress of a parallel mining run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MiningProgress {
+    /// Hashes computed by all workers so far
+    pub hashes: u64,
+    pub elapsed: Duration,
+    /// Average hashes per second since mining started
+    pub hashes_per_sec: f64,
+}
+
+impl MiningProgress {
+    fn new(hashes: u64, elapsed: Duration) -> Self {
+        let secs = elapsed.as_secs_f64();
+        MiningProgress {
+            hashes,
+            elapsed,
+            hashes_per_sec: if secs > 0.0 { hashes as f64 / secs } else { 0.0 },
+        }
+    }
+}
 
 /// Mines a new block using multi-threaded parallel nonce searching.
 /// Divides the nonce space among available CPU cores for faster mining.
 pub fn mine_block_parallel(block: Block) -> Result<Block, ChainError> {
-    let difficulty = block.header.difficulty;
-    let num_threads = rayon::current_num_threads();
-    let found = Arc::new(AtomicBool::new(false));
-    let (sender, receiver): (Sender<MiningResult>, Receiver<MiningResult>) = unbounded();
-
-    (0..num_threads)
-        .into_par_iter()
-        .for_each_with(sender, |s, thread_id| {
-            let mut nonce = thread_id as u64;
-            while !found.load(Ordering::Relaxed) {
-                let mut test_block = block.clone();
-                test_block.header.nonce = nonce;
-                let hash = test_block.hash();
-
-                if is_hash_valid(&hash, difficulty as u64) {
-                    if !found.swap(true, Ordering::SeqCst) {
-                        let _ = s.send((nonce, hash));
+    let stop = AtomicBool::new(false);
+    mine_block_parallel_with(block, rayon::current_num_threads(), &stop, |_| {})?
+        .ok_or(ChainError::InvalidProofOfWork)
+}
+
+/// Mines `block` on `threads` worker threads, each searching its own
+/// contiguous slice of the nonce space. `on_progress` is called on the
+/// calling thread roughly every 500ms with the aggregate hashrate, and once
+/// more when mining ends. Returns `Ok(None)` if `stop` is set (or the nonce
+/// space is exhausted) before a valid nonce is found.
+pub fn mine_block_parallel_with(
+    block: Block,
+    threads: usize,
+    stop: &AtomicBool,
+    mut on_progress: impl FnMut(MiningProgress),
+) -> Result<Option<Block>, ChainError> {
+    let threads = threads.max(1) as u64;
+    let difficulty = block.header.difficulty as u64;
+    let span = u64::MAX / threads;
+    let found = AtomicBool::new(false);
+    let hashes = AtomicU64::new(0);
+    let (sender, receiver) = bounded::<u64>(threads as usize);
+    let start = Instant::now();
+
+    let winner = std::thread::scope(|scope| {
+        for worker in 0..threads {
+            let sender = sender.clone();
+            let mut header = block.header.clone();
+            let (found, hashes) = (&found, &hashes);
+            let first = worker * span;
+            let last = if worker + 1 == threads { u64::MAX } else { first + span - 1 };
+
+            scope.spawn(move || {
+                let mut nonce = first;
+                loop {
+                    if stop.load(Ordering::Relaxed) || found.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    let batch_end = last.min(nonce.saturating_add(WORKER_BATCH - 1));
+                    for candidate in nonce..=batch_end {
+                        header.nonce = candidate;
+                        if is_hash_valid(&header.hash(), difficulty) {
+                            hashes.fetch_add(candidate - nonce + 1, Ordering::Relaxed);
+                            if !found.swap(true, Ordering::SeqCst) {
+                                let _ = sender.send(candidate);
+                            }
+                            return;
+                        }
                     }
-                    return;
+                    hashes.fetch_add(batch_end - nonce + 1, Ordering::Relaxed);
+                    if batch_end == last {
+                        return;
+                    }
+                    nonce = batch_end + 1;
+                }
+            });
+        }
+        // Workers hold the remaining senders; the channel disconnects once
+        // they have all returned
+        drop(sender);
+
+        loop {
+            match receiver.recv_timeout(PROGRESS_INTERVAL) {
+                Ok(nonce) => {
+                    found.store(true, Ordering::SeqCst);
+                    return Some(nonce);
                 }
-                nonce += num_threads as u64;
+                Err(RecvTimeoutError::Timeout) => on_progress(MiningProgress::new(
+                    hashes.load(Ordering::Relaxed),
+                    start.elapsed(),
+                )),
+                Err(RecvTimeoutError::Disconnected) => return None,
             }
+        }
+    });
+
+    on_progress(MiningProgress::new(
+        hashes.load(Ordering::Relaxed),
+        start.elapsed(),
+    ));
+
+    Ok(winner.map(|nonce| {
+        let mut mined = block;
+        mined.header.nonce = nonce;
+        mined
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KeyPair;
+    use crate::fixtures::signed_transfer;
+    use crate::geometry::{Point, Triangle};
+
+    fn block(difficulty: u32) -> Block {
+        let coinbase = Transaction::Coinbase(CoinbaseTx {
+            reward_area: crate::geometry::Coord::from_num(1),
+            beneficiary_address: Address::from([5; 32]),
+            nonce: 1,
         });
+        Block::new(1, [0; 32], difficulty, vec![coinbase])
+    }
+
+    #[test]
+    fn test_parallel_mining_finds_valid_nonce() {
+        let stop = AtomicBool::new(false);
+        let mut reports = Vec::new();
+        let mined = mine_block_parallel_with(block(3), 4, &stop, |p| reports.push(p))
+            .unwrap()
+            .unwrap();
+
+        assert!(is_hash_valid(&mined.hash(), 3));
+        let last = reports.last().unwrap();
+        assert!(last.hashes > 0);
+    }
 
-    // Block until a nonce is found by one of the threads
-    match receiver.recv() {
-        Ok((nonce, _hash)) => {
-            let mut mined_block = block;
-            mined_block.header.nonce = nonce;
-            Ok(mined_block)
+    #[test]
+    fn test_parallel_mining_stops_on_request() {
+        // Unreachable difficulty: only the stop flag ends the search
+        let stop = AtomicBool::new(true);
+        let mined = mine_block_parallel_with(block(64), 2, &stop, |_| {}).unwrap();
+        assert!(mined.is_none());
+    }
+
+    #[test]
+    fn test_template_selects_by_fee_rate() {
+        let keypair = KeyPair::generate().unwrap();
+        let miner = Address::from([5; 32]);
+        let mut chain = Blockchain::new(miner, 1).unwrap();
+        let point = |x: f64, y: f64| Point::new(Coord::from_num(x), Coord::from_num(y));
+        for input in 1..=3u8 {
+            let triangle = Triangle::new_with_value(
+                point(0.0, 0.0),
+                point(1.0, 0.0),
+                point(0.0, 1.0),
+                None,
+                keypair.address(),
+                Coord::from_num(10),
+            );
+            chain.state.utxo_set.insert([input; 32], triangle);
         }
-        // This error occurs if all senders hang up, which means mining was interrupted or failed.
-        Err(_) => Err(ChainError::InvalidProofOfWork),
+        let pay = |input: u8, fee: i32| {
+            signed_transfer(
+                &keypair,
+                [input; 32],
+                Address::from([9; 32]),
+                Coord::from_num(5),
+                Coord::from_num(fee),
+                input as u64,
+            )
+        };
+        for (input, fee) in [(1, 1), (2, 3), (3, 2), (4, 9)] {
+            chain.mempool.add_transaction(pay(input, fee)).unwrap();
+        }
+
+        // Best rate first; the spend of a missing triangle is left out
+        let template = BlockTemplateBuilder::new(&chain, miner).build().unwrap();
+        let fees: Vec<Coord> = template.block.transactions[1..]
+            .iter()
+            .map(|tx| tx.fee_area())
+            .collect();
+        assert_eq!(fees, [3, 2, 1].map(Coord::from_num));
+        assert_eq!(template.fees, Coord::from_num(6));
+        match &template.block.transactions[0] {
+            Transaction::Coinbase(coinbase) => assert_eq!(
+                coinbase.reward_area,
+                Blockchain::max_coinbase_reward(1, template.fees)
+            ),
+            other => panic!("expected a coinbase, got {:?}", other),
+        }
+
+        // Only room for the best transaction
+        let transfer_size = estimate_transaction_size(&pay(1, 1));
+        let small = BlockTemplateBuilder::new(&chain, miner)
+            .with_max_size(template.size - transfer_size)
+            .build()
+            .unwrap();
+        assert_eq!(small.block.transactions.len(), 3);
+        let small = BlockTemplateBuilder::new(&chain, miner)
+            .with_max_size(small.size)
+            .build()
+            .unwrap();
+        assert_eq!(small.fees, Coord::from_num(5));
+
+        chain
+            .apply_block(mine_block(template.block).unwrap())
+            .unwrap();
+        assert_eq!(chain.mempool.len(), 1);
     }
 }
@@ -1,16 +1,220 @@
 //! P2P Networking for TrinityChain
 
-use crate::blockchain::Blockchain;
+use crate::blockchain::{Block, Blockchain, Sha256Hash};
+use crate::cache::{BlockVerdict, ValidationCache};
+use crate::config::active_network;
+use crate::discovery::{backoff_delay, BanEntry, BanList, PeerDiscovery, PeerRecord, BAN_DURATION, BAN_THRESHOLD};
 use crate::error::ChainError;
-use std::collections::HashMap;
+use crate::forks::{ForkTip, ForkTree};
+use crate::inventory::{InvItem, Inventory, RelayItem};
+use crate::outbound::{OutboundQueue, Priority, PushOutcome};
+use crate::peer_events::{PeerEvent, PeerEventKind, PEER_EVENT_CHANNEL_CAPACITY};
+use crate::persistence::{Database, LAST_SYNC_HEIGHT};
+use crate::propagation::{PeerPropagation, PropagationTracker, LOCAL_SOURCE};
+use crate::security::TokenBucket;
+use crate::shutdown::Shutdown;
+use crate::stale_tip::{
+    stale_tip_threshold, StaleTipDetector, TipEvent, DEFAULT_STALE_TIP_MULTIPLE,
+    TIP_EVENT_CHANNEL_CAPACITY,
+};
+use crate::sync::{OrphanPool, SyncState, SyncTracker};
+use crate::transaction::Transaction;
+use crate::watchtower::Watchtower;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
 use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, Mutex, RwLock};
+use tracing::{error, info, warn};
 
 /// Maximum message size to prevent DoS attacks (10MB)
 const MAX_MESSAGE_SIZE: usize = 10 * 1024 * 1024;
 
+/// How often the connection manager checks for missing peers
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Most addresses taken from a single `Peers` message
+const MAX_GOSSIPED_PEERS: usize = 100;
+
+/// Most peers kept in the peer database; gossip beyond it is ignored
+const MAX_STORED_PEERS: usize = 2_000;
+
+/// Ban score added each time a peer sends a block that failed validation
+const INVALID_BLOCK_PENALTY: u32 = 100;
+
+/// Ban score added each time a peer relays a transaction that can never be valid
+const INVALID_TX_PENALTY: u32 = 10;
+
+/// Ban score added for a frame that does not decode to a message
+const MALFORMED_MESSAGE_PENALTY: u32 = 10;
+
+/// Ban score added for a frame larger than `MAX_MESSAGE_SIZE`
+const OVERSIZED_MESSAGE_PENALTY: u32 = 50;
+
+/// Ban score added when a peer exceeds its message rate and is disconnected
+const FLOOD_PENALTY: u32 = 20;
+
+/// Most items handled from a single `Inv` or `GetData` message
+const MAX_INV_ITEMS: usize = 1_000;
+
+/// How long shutdown waits for goodbye messages and queued frames to be written
+const GOODBYE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Tip height change between writes of the last sync height; shutdown
+/// writes the final one
+const SYNC_HEIGHT_SAVE_INTERVAL: u64 = 100;
+
+/// Read timeouts and message-rate caps applied to every peer connection.
+/// A peer that stays silent too long, trickles a frame, or floods messages
+/// is disconnected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PeerLimits {
+    /// Longest wait for the peer's handshake after connecting
+    pub handshake_timeout: Duration,
+    /// Longest silence between frames; keepalive pings keep live peers
+    /// well inside it
+    pub idle_timeout: Duration,
+    /// Longest time to receive the body of a frame once its length arrived
+    pub frame_timeout: Duration,
+    /// Sustained messages per second accepted from one peer
+    pub messages_per_sec: u32,
+    /// Messages a peer may send in a burst above the sustained rate
+    pub message_burst: u32,
+}
+
+impl Default for PeerLimits {
+    fn default() -> Self {
+        PeerLimits {
+            handshake_timeout: Duration::from_secs(10),
+            idle_timeout: Duration::from_secs(300),
+            frame_timeout: Duration::from_secs(60),
+            messages_per_sec: 100,
+            message_burst: 500,
+        }
+    }
+}
+
+/// Version of the peer-to-peer protocol spoken by this node. Version 2
+/// added the subdivision depth to every encoded triangle, version 3 the
+/// version field to block headers and version 4 the state root.
+pub const PROTOCOL_VERSION: u32 = 4;
+
+/// Oldest protocol version we accept from peers. Older peers encode headers
+/// without a state root, so their blocks don't decode.
+pub const MIN_PROTOCOL_VERSION: u32 = 4;
+
+/// From block `height` on, peers below `min_version` are disconnected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ProtocolDeadline {
+    pub height: u64,
+    pub min_version: u32,
+}
+
+/// Which peer protocol versions are accepted, and from which height. Lets
+/// operators retire broken protocol behaviour in a coordinated way: announce
+/// a deadline, and once the chain reaches it every node drops older peers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtocolPolicy {
+    min_version: u32,
+    deadlines: Vec<ProtocolDeadline>,
+}
+
+impl Default for ProtocolPolicy {
+    fn default() -> Self {
+        ProtocolPolicy::new(MIN_PROTOCOL_VERSION)
+    }
+}
+
+impl ProtocolPolicy {
+    /// Accept peers from `min_version` on (never below `MIN_PROTOCOL_VERSION`)
+    pub fn new(min_version: u32) -> Self {
+        ProtocolPolicy {
+            min_version: min_version.max(MIN_PROTOCOL_VERSION),
+            deadlines: Vec::new(),
+        }
+    }
+
+    /// Also require `deadline.min_version` once the chain reaches `deadline.height`
+    pub fn with_deadline(mut self, deadline: ProtocolDeadline) -> Self {
+        self.deadlines.push(deadline);
+        self
+    }
+
+    /// Whether the accepted versions can change as the chain grows
+    pub fn has_deadlines(&self) -> bool {
+        !self.deadlines.is_empty()
+    }
+
+    /// Oldest version accepted while our best block is at `height`
+    pub fn min_version_at(&self, height: u64) -> u32 {
+        self.deadlines
+            .iter()
+            .filter(|d| height >= d.height)
+            .map(|d| d.min_version)
+            .fold(self.min_version, u32::max)
+    }
+
+    /// Fails if a peer speaking `version` must be refused at `height`
+    pub fn check(&self, version: u32, height: u64) -> Result<(), ChainError> {
+        let required = self.min_version_at(height);
+        if version >= required {
+            return Ok(());
+        }
+        match self
+            .deadlines
+            .iter()
+            .filter(|d| height >= d.height && version < d.min_version)
+            .min_by_key(|d| d.height)
+        {
+            Some(deadline) => Err(ChainError::NetworkError(format!(
+                "Incompatible peer: protocol version {} was retired at height {} (minimum {})",
+                version, deadline.height, required
+            ))),
+            None => Err(ChainError::NetworkError(format!(
+                "Incompatible peer: protocol version {} is below minimum {}",
+                version, required
+            ))),
+        }
+    }
+}
+
+/// First message exchanged on every connection
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Handshake {
+    pub protocol_version: u32,
+    pub network_magic: u32,
+    pub genesis_hash: Sha256Hash,
+    pub best_height: u64,
+}
+
+impl Handshake {
+    /// Check that a peer's handshake is for the same network and chain as
+    /// ours, and speaks a protocol version `policy` accepts at our height
+    pub fn check_compatible(
+        &self,
+        local: &Handshake,
+        policy: &ProtocolPolicy,
+    ) -> Result<(), ChainError> {
+        if self.network_magic != local.network_magic {
+            return Err(ChainError::NetworkError(format!(
+                "Incompatible peer: network magic {:#010x}, expected {:#010x}",
+                self.network_magic, local.network_magic
+            )));
+        }
+        policy.check(self.protocol_version, local.best_height)?;
+        if self.genesis_hash != local.genesis_hash {
+            return Err(ChainError::NetworkError(format!(
+                "Incompatible peer: genesis {} does not match ours {}",
+                hex::encode(self.genesis_hash),
+                hex::encode(local.genesis_hash)
+            )));
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct Node {
     pub host: String,
@@ -27,50 +231,215 @@ impl Node {
     }
 }
 
+impl std::str::FromStr for Node {
+    type Err = ChainError;
+
+    /// Parse `host:port`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            ChainError::NetworkError(format!("Invalid peer address '{}' (expected host:port)", s))
+        };
+        let (host, port) = s.trim().rsplit_once(':').ok_or_else(invalid)?;
+        let port = port.parse().map_err(|_| invalid())?;
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Node::new(host.to_string(), port))
+    }
+}
+
+/// Length-prefix and encode a message for the wire
+fn encode_frame(message: &NetworkMessage) -> Result<Vec<u8>, ChainError> {
+    let data = bincode::serialize(message)?;
+    let mut frame = Vec::with_capacity(4 + data.len());
+    frame.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&data);
+    Ok(frame)
+}
+
+/// An open connection: the read half is owned by the connection's handler,
+/// writes go through the prioritized outbound queue
+#[derive(Clone)]
+struct PeerConnection {
+    reader: Arc<Mutex<OwnedReadHalf>>,
+    queue: Arc<OutboundQueue>,
+    writer: Arc<parking_lot::Mutex<Option<tokio::task::JoinHandle<()>>>>,
+}
+
+fn unix_now() -> u64 {
+    chrono::Utc::now().timestamp().max(0) as u64
+}
+
 /// Manages a pool of active P2P connections
 struct ConnectionPool {
-    connections: RwLock<HashMap<String, Arc<RwLock<TcpStream>>>>,
+    connections: RwLock<HashMap<String, PeerConnection>>,
+    /// Misbehaviour score per host, reset when the host is banned
+    ban_scores: parking_lot::Mutex<HashMap<String, u32>>,
+    bans: parking_lot::Mutex<BanList>,
 }
 
 impl ConnectionPool {
     fn new() -> Self {
         ConnectionPool {
             connections: RwLock::new(HashMap::new()),
+            ban_scores: parking_lot::Mutex::new(HashMap::new()),
+            bans: parking_lot::Mutex::new(BanList::new()),
         }
     }
 
-    /// Add a new connection to the pool
+    /// Add `penalty` to a host's ban score and return the new score. Once
+    /// the score reaches `BAN_THRESHOLD` the host is banned and the new ban
+    /// returned too.
+    fn penalize(&self, host: &str, penalty: u32, reason: &str) -> (u32, Option<BanEntry>) {
+        let mut scores = self.ban_scores.lock();
+        let score = scores.entry(host.to_string()).or_insert(0);
+        *score = score.saturating_add(penalty);
+        let score = *score;
+        if score < BAN_THRESHOLD {
+            return (score, None);
+        }
+        scores.remove(host);
+        drop(scores);
+
+        let ban = BanEntry::new(host.to_string(), reason.to_string(), unix_now());
+        self.ban(ban.clone());
+        (score, Some(ban))
+    }
+
+    fn ban(&self, ban: BanEntry) {
+        self.bans.lock().insert(ban);
+    }
+
+    #[cfg(test)]
+    fn ban_score(&self, host: &str) -> u32 {
+        self.ban_scores.lock().get(host).copied().unwrap_or(0)
+    }
+
+    fn is_banned(&self, host: &str) -> bool {
+        let mut bans = self.bans.lock();
+        let now = unix_now();
+        bans.prune(now);
+        bans.is_banned(host, now)
+    }
+
+    /// Close every connection to `host`
+    async fn remove_host(&self, host: &str) {
+        let mut connections = self.connections.write().await;
+        connections.retain(|addr, connection| {
+            let keep = addr
+                .rsplit_once(':')
+                .is_none_or(|(conn_host, _)| conn_host != host);
+            if !keep {
+                connection.queue.close();
+            }
+            keep
+        });
+    }
+
+    /// Add a new connection to the pool and start its writer task
     async fn add(&self, node: &Node, stream: TcpStream) {
+        let (reader, writer) = stream.into_split();
+        let queue = Arc::new(OutboundQueue::new());
+        let writer = tokio::spawn(Self::write_loop(node.addr(), queue.clone(), writer));
+
         let mut connections = self.connections.write().await;
-        connections.insert(node.addr(), Arc::new(RwLock::new(stream)));
+        let connection = PeerConnection {
+            reader: Arc::new(Mutex::new(reader)),
+            queue,
+            writer: Arc::new(parking_lot::Mutex::new(Some(writer))),
+        };
+        if let Some(old) = connections.insert(node.addr(), connection) {
+            old.queue.close();
+        }
     }
 
-    /// Remove a connection from the pool
+    /// Remove a connection from the pool, stopping its writer
     async fn remove(&self, node: &Node) {
         let mut connections = self.connections.write().await;
-        connections.remove(&node.addr());
+        if let Some(connection) = connections.remove(&node.addr()) {
+            connection.queue.close();
+        }
+    }
+
+    async fn get(&self, node: &Node) -> Result<PeerConnection, ChainError> {
+        self.connections
+            .read()
+            .await
+            .get(&node.addr())
+            .cloned()
+            .ok_or_else(|| ChainError::NetworkError("Connection not in pool".to_string()))
+    }
+
+    /// Write queued frames to the peer, highest priority first, until the
+    /// queue is closed or finished, or the socket fails
+    async fn write_loop(addr: String, queue: Arc<OutboundQueue>, mut writer: OwnedWriteHalf) {
+        while let Some(frame) = queue.pop().await {
+            if let Err(e) = writer.write_all(&frame).await {
+                error!("❌ Failed to write to {}: {}", addr, e);
+                queue.close();
+                return;
+            }
+        }
+        let _ = writer.shutdown().await;
+    }
+
+    /// Say goodbye to every peer and close the connections once their
+    /// queues are written (or `GOODBYE_TIMEOUT` passes)
+    async fn close_all(&self) {
+        self.broadcast(&NetworkMessage::Goodbye).await;
+        let connections: Vec<PeerConnection> = self
+            .connections
+            .write()
+            .await
+            .drain()
+            .map(|(_, connection)| connection)
+            .collect();
+
+        let mut writers = Vec::new();
+        for connection in &connections {
+            connection.queue.finish();
+            writers.extend(connection.writer.lock().take());
+        }
+        let drained = tokio::time::timeout(GOODBYE_TIMEOUT, async {
+            for writer in writers {
+                let _ = writer.await;
+            }
+        })
+        .await;
+        if drained.is_err() {
+            warn!("⚠️  Some peers did not receive goodbye before the timeout");
+        }
+        for connection in connections {
+            connection.queue.close();
+        }
     }
 
     /// Broadcast a message to all connected peers
     async fn broadcast(&self, message: &NetworkMessage) {
-        let connections = self.connections.read().await;
-        let data = match bincode::serialize(message) {
-            Ok(data) => data,
+        self.broadcast_except(message, None).await;
+    }
+
+    /// Broadcast a message to all connected peers except `exclude` (the
+    /// peer a relayed message came from)
+    async fn broadcast_except(&self, message: &NetworkMessage, exclude: Option<&str>) {
+        let frame = match encode_frame(message) {
+            Ok(frame) => frame,
             Err(e) => {
-                eprintln!("❌ Failed to serialize message for broadcast: {}", e);
+                error!("❌ Failed to serialize message for broadcast: {}", e);
                 return;
             }
         };
-        let len = data.len() as u32;
+        let priority = message.priority();
 
-        for (addr, stream_lock) in connections.iter() {
-            let mut stream = stream_lock.write().await;
-            if let Err(e) = stream.write_all(&len.to_be_bytes()).await {
-                eprintln!("❌ Failed to write len to {}: {}", addr, e);
+        let connections = self.connections.read().await;
+        for (addr, connection) in connections.iter() {
+            if exclude == Some(addr.as_str()) {
                 continue;
             }
-            if let Err(e) = stream.write_all(&data).await {
-                eprintln!("❌ Failed to write data to {}: {}", addr, e);
+            // The peer's reader notices the closed socket and disconnects it
+            if connection.queue.push(priority, frame.clone()) == PushOutcome::Overflow {
+                warn!("⚠️  {} cannot keep up with block relay, dropping it", addr);
+                connection.queue.close();
             }
         }
     }
@@ -92,6 +461,43 @@ impl ConnectionPool {
 pub struct NetworkNode {
     pub blockchain: Arc<RwLock<Blockchain>>,
     pool: Arc<ConnectionPool>,
+    orphans: Arc<RwLock<OrphanPool>>,
+    /// Best height advertised by each handshaken peer
+    peer_heights: RwLock<HashMap<String, u64>>,
+    /// Nonce and send time of the last unanswered ping to each peer
+    pings: parking_lot::Mutex<HashMap<String, (u64, Instant)>>,
+    /// Round-trip time of each peer's last answered ping
+    latencies: parking_lot::Mutex<HashMap<String, Duration>>,
+    /// Outbound peers that failed: consecutive failures and earliest retry
+    reconnect: RwLock<HashMap<String, (u32, Instant)>>,
+    /// Database used to persist known peers and sync progress across restarts
+    peer_db: Option<String>,
+    /// Checked whenever a block or transaction is accepted from a peer
+    watchtower: Option<Arc<Watchtower>>,
+    /// Stops the listener, connection handlers and reconnect loop
+    shutdown: Shutdown,
+    /// Outcomes of blocks already validated, so relayed copies are not re-validated
+    validation: ValidationCache,
+    /// First-seen times of recent blocks and per-peer delivery statistics
+    propagation: parking_lot::Mutex<PropagationTracker>,
+    /// Headers of recent blocks on every branch, for fork monitoring
+    forks: parking_lot::Mutex<ForkTree>,
+    /// Hashes already seen or requested, and bodies we announced
+    inventory: parking_lot::Mutex<Inventory>,
+    /// Connect, handshake, misbehaviour and disconnect events
+    peer_events: broadcast::Sender<PeerEvent>,
+    /// Peer protocol versions accepted, and upgrade deadlines
+    protocol: ProtocolPolicy,
+    /// When a block last arrived from the network, for stale-tip detection
+    tip_watch: parking_lot::Mutex<StaleTipDetector>,
+    /// Changes in whether the tip is stale
+    tip_events: broadcast::Sender<TipEvent>,
+    /// Read timeouts and message-rate caps for each connection
+    limits: PeerLimits,
+    /// Recent tip heights, for the sync speed
+    sync: parking_lot::Mutex<SyncTracker>,
+    /// Tip height last written to `peer_db`
+    saved_sync_height: parking_lot::Mutex<Option<u64>>,
 }
 
 impl NetworkNode {
@@ -99,7 +505,76 @@ impl NetworkNode {
         NetworkNode {
             blockchain,
             pool: Arc::new(ConnectionPool::new()),
+            orphans: Arc::new(RwLock::new(OrphanPool::default())),
+            peer_heights: RwLock::new(HashMap::new()),
+            pings: parking_lot::Mutex::new(HashMap::new()),
+            latencies: parking_lot::Mutex::new(HashMap::new()),
+            reconnect: RwLock::new(HashMap::new()),
+            peer_db: None,
+            watchtower: None,
+            shutdown: Shutdown::new(),
+            validation: ValidationCache::new(ValidationCache::DEFAULT_CAPACITY),
+            propagation: parking_lot::Mutex::new(PropagationTracker::new()),
+            forks: parking_lot::Mutex::new(ForkTree::new()),
+            inventory: parking_lot::Mutex::new(Inventory::new()),
+            peer_events: broadcast::channel(PEER_EVENT_CHANNEL_CAPACITY).0,
+            protocol: ProtocolPolicy::default(),
+            tip_watch: parking_lot::Mutex::new(StaleTipDetector::new(
+                stale_tip_threshold(DEFAULT_STALE_TIP_MULTIPLE),
+                unix_now(),
+            )),
+            tip_events: broadcast::channel(TIP_EVENT_CHANNEL_CAPACITY).0,
+            limits: PeerLimits::default(),
+            sync: parking_lot::Mutex::new(SyncTracker::default()),
+            saved_sync_height: parking_lot::Mutex::new(None),
+        }
+    }
+
+    /// Apply `limits` to connections instead of the defaults
+    pub fn with_peer_limits(mut self, limits: PeerLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Count the tip as stale after `threshold` without peers or network blocks
+    pub fn with_stale_tip_threshold(self, threshold: Duration) -> Self {
+        self.set_stale_tip_threshold(threshold);
+        self
+    }
+
+    /// Like `with_stale_tip_threshold`, for a node already shared; the
+    /// silence is counted from now
+    pub fn set_stale_tip_threshold(&self, threshold: Duration) {
+        *self.tip_watch.lock() = StaleTipDetector::new(threshold, unix_now());
+    }
+
+    /// Accept peer protocol versions according to `policy`
+    pub fn with_protocol_policy(mut self, policy: ProtocolPolicy) -> Self {
+        self.protocol = policy;
+        self
+    }
+
+    /// Check blocks and transactions received from peers against `watchtower`
+    pub fn with_watchtower(mut self, watchtower: Arc<Watchtower>) -> Self {
+        self.watchtower = Some(watchtower);
+        self
+    }
+
+    /// Stop the node's tasks when `shutdown` is triggered
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Persist known peers (addresses, last-seen) and bans in the database
+    /// at `path`. Bans still in force are restored immediately.
+    pub fn with_peer_db(mut self, path: &str) -> Self {
+        match Database::open(path).and_then(|db| db.load_bans(unix_now())) {
+            Ok(bans) => bans.into_iter().for_each(|ban| self.pool.ban(ban)),
+            Err(e) => warn!("⚠️  Failed to load bans: {}", e),
         }
+        self.peer_db = Some(path.to_string());
+        self
     }
 
     pub async fn start_server(self: Arc<Self>, port: u16) -> Result<(), ChainError> {
@@ -108,74 +583,463 @@ impl NetworkNode {
             .await
             .map_err(|e| ChainError::NetworkError(format!("Failed to bind: {}", e)))?;
 
-        println!("🌐 Node listening on {}", addr);
+        info!("🌐 Node listening on {}", addr);
 
         loop {
-            let (socket, peer_addr) = listener
-                .accept()
-                .await
-                .map_err(|e| ChainError::NetworkError(format!("Accept error: {}", e)))?;
+            let (socket, peer_addr) = tokio::select! {
+                accepted = listener.accept() => accepted
+                    .map_err(|e| ChainError::NetworkError(format!("Accept error: {}", e)))?,
+                _ = self.shutdown.cancelled() => {
+                    info!("🛑 Stopped listening on {}", addr);
+                    return Ok(());
+                }
+            };
 
-            println!("📡 New connection from {}", peer_addr);
             let node = Node::new(peer_addr.ip().to_string(), peer_addr.port());
+            if self.pool.is_banned(&node.host) {
+                warn!("⛔ Refused connection from banned {}", peer_addr);
+                continue;
+            }
+            info!("📡 New connection from {}", peer_addr);
             self.pool.add(&node, socket).await;
-
-            let self_clone = self.clone();
-            tokio::spawn(async move {
-                if let Err(e) = self_clone.handle_connection(&node).await {
-                    eprintln!("❌ Connection error with {}: {}", node.addr(), e);
-                    self_clone.pool.remove(&node).await;
-                }
-            });
+            self.emit_peer_event(&node, PeerEventKind::Connected { outbound: false });
+            tokio::spawn(self.clone().run_connection(node, false));
         }
     }
 
     pub async fn connect_peer(self: Arc<Self>, host: String, port: u16) -> Result<(), ChainError> {
         let addr = format!("{}:{}", host, port);
-        println!("🔗 Connecting to peer: {}", addr);
-
-        let stream = TcpStream::connect(&addr)
-            .await
-            .map_err(|e| ChainError::NetworkError(format!("Failed to connect: {}", e)))?;
+        info!("🔗 Connecting to peer: {}", addr);
 
         let node = Node::new(host, port);
+        if self.pool.is_banned(&node.host) {
+            return Err(ChainError::NetworkError(format!("Peer {} is banned", addr)));
+        }
+        let stream = match TcpStream::connect(&addr).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                self.record_peer_failure(&node).await;
+                return Err(ChainError::NetworkError(format!("Failed to connect: {}", e)));
+            }
+        };
+
         self.pool.add(&node, stream).await;
+        self.emit_peer_event(&node, PeerEventKind::Connected { outbound: true });
+        tokio::spawn(self.clone().run_connection(node, true));
 
-        let self_clone = self.clone();
-        tokio::spawn(async move {
-            if let Err(e) = self_clone.handle_connection(&node).await {
-                eprintln!("❌ Connection error with {}: {}", node.addr(), e);
-                self_clone.pool.remove(&node).await;
+        Ok(())
+    }
+
+    /// Serve a connection until it ends, then forget the peer and publish
+    /// why it went away
+    async fn run_connection(self: Arc<Self>, node: Node, outbound: bool) {
+        let reason = match self.handle_connection(&node, outbound).await {
+            Ok(()) if self.shutdown.is_triggered() => "local shutdown".to_string(),
+            Ok(()) => "peer said goodbye".to_string(),
+            Err(e) => {
+                error!("❌ Connection error with {}: {}", node.addr(), e);
+                if outbound {
+                    self.record_peer_failure(&node).await;
+                }
+                self.disconnect(&node).await;
+                e.to_string()
             }
-        });
+        };
+        self.emit_peer_event(&node, PeerEventKind::Disconnected { reason });
+    }
 
-        Ok(())
+    /// Receive every peer lifecycle event from now on
+    pub fn subscribe_peer_events(&self) -> broadcast::Receiver<PeerEvent> {
+        self.peer_events.subscribe()
     }
 
-    async fn handle_connection(&self, node: &Node) -> Result<(), ChainError> {
-        let stream_lock = self
-            .pool
-            .connections
+    fn emit_peer_event(&self, node: &Node, kind: PeerEventKind) {
+        // No subscribers is fine
+        let _ = self
+            .peer_events
+            .send(PeerEvent::new(node.addr(), kind, unix_now()));
+    }
+
+    /// Receive every stale-tip transition from now on
+    pub fn subscribe_tip_events(&self) -> broadcast::Receiver<TipEvent> {
+        self.tip_events.subscribe()
+    }
+
+    /// Re-evaluate whether the tip is stale (no peers and no block from the
+    /// network for the threshold), publishing a `TipEvent` if that changed.
+    /// Returns whether it is stale now.
+    pub async fn check_tip(&self) -> bool {
+        let peers = self.pool.list_peers().await.len();
+        let height = self
+            .blockchain
             .read()
             .await
-            .get(&node.addr())
-            .cloned()
-            .ok_or_else(|| ChainError::NetworkError("Connection not in pool".to_string()))?;
+            .blocks
+            .last()
+            .map_or(0, |b| b.header.height);
+        let mut watch = self.tip_watch.lock();
+        if let Some(event) = watch.check(height, peers, unix_now()) {
+            let _ = self.tip_events.send(event);
+        }
+        watch.is_stale()
+    }
 
+    /// Seconds since a block last arrived from the network
+    pub fn tip_silent_secs(&self) -> u64 {
+        self.tip_watch.lock().silent_secs(unix_now())
+    }
+
+    /// Check the tip every `interval` so subscribers hear about it even when
+    /// no miner is asking. Runs until shutdown.
+    pub async fn monitor_tip(self: Arc<Self>, interval: Duration) {
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = self.shutdown.cancelled() => return,
+            }
+            self.check_tip().await;
+        }
+    }
+
+    /// Keep up to `target_peers` outbound connections open. Known peers come
+    /// from `discovery` (DNS seeds, bootstrap peers) and the peer database;
+    /// failed peers are retried with exponential backoff and banned peers are
+    /// skipped. Runs until the task is dropped.
+    pub async fn maintain_connections(
+        self: Arc<Self>,
+        mut discovery: PeerDiscovery,
+        target_peers: usize,
+    ) {
         loop {
-            let mut stream = stream_lock.write().await;
-            let mut len_bytes = [0u8; 4];
-            stream.read_exact(&mut len_bytes).await?;
-            let len = u32::from_be_bytes(len_bytes) as usize;
+            if let Some(records) = self.load_peer_records() {
+                let bans = self.pool.bans.lock().clone();
+                discovery.add_peer_records(&records, &bans, unix_now());
+            }
+            if discovery.peer_count() == 0 {
+                if let Err(e) = discovery.discover_peers().await {
+                    warn!("⚠️  Peer discovery failed: {}", e);
+                }
+            }
+
+            let connected: HashSet<String> =
+                self.list_peers().await.iter().map(Node::addr).collect();
+            let mut missing = target_peers.saturating_sub(connected.len());
+
+            for peer in discovery.get_random_peers(discovery.peer_count()) {
+                if missing == 0 {
+                    break;
+                }
+                let addr = peer.addr();
+                if connected.contains(&addr)
+                    || self.pool.is_banned(&peer.host)
+                    || !self.reconnect_due(&addr).await
+                {
+                    continue;
+                }
+                match self.clone().connect_peer(peer.host, peer.port).await {
+                    Ok(()) => missing -= 1,
+                    Err(e) => warn!("⚠️  Could not reach {}: {}", addr, e),
+                }
+            }
 
-            if len > MAX_MESSAGE_SIZE {
-                return Err(ChainError::NetworkError("Message too large".to_string()));
+            // Keepalive: idle connections still see traffic every round
+            self.ping_peers().await;
+
+            tokio::select! {
+                _ = tokio::time::sleep(RECONNECT_INTERVAL) => {}
+                _ = self.shutdown.cancelled() => return,
             }
+        }
+    }
 
-            let mut buffer = vec![0u8; len];
-            stream.read_exact(&mut buffer).await?;
+    /// Whether a peer's reconnect backoff has elapsed
+    async fn reconnect_due(&self, addr: &str) -> bool {
+        self.reconnect
+            .read()
+            .await
+            .get(addr)
+            .is_none_or(|(_, retry_at)| Instant::now() >= *retry_at)
+    }
 
-            let message: NetworkMessage = bincode::deserialize(&buffer)?;
+    fn load_peer_records(&self) -> Option<Vec<PeerRecord>> {
+        let path = self.peer_db.as_ref()?;
+        match Database::open(path).and_then(|db| db.load_peers()) {
+            Ok(records) => Some(records),
+            Err(e) => {
+                warn!("⚠️  Failed to load peers: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Apply `update` to a peer's stored record, creating it if needed
+    fn update_peer_record(&self, node: &Node, update: impl FnOnce(&mut PeerRecord)) {
+        let Some(path) = &self.peer_db else {
+            return;
+        };
+        let result = Database::open(path).and_then(|db| {
+            let mut record = db
+                .get_peer(&node.addr())?
+                .unwrap_or_else(|| PeerRecord::new(node.clone()));
+            update(&mut record);
+            db.save_peer(&record)
+        });
+        if let Err(e) = result {
+            warn!("⚠️  Failed to save peer {}: {}", node.addr(), e);
+        }
+    }
+
+    /// Store the first `MAX_GOSSIPED_PEERS` addresses of a `Peers` message
+    /// that are new, up to `MAX_STORED_PEERS` in total. The writes share one
+    /// transaction, off the async runtime.
+    async fn store_gossiped_peers(&self, mut peers: Vec<Node>) {
+        let Some(path) = self.peer_db.clone() else {
+            return;
+        };
+        peers.truncate(MAX_GOSSIPED_PEERS);
+        let stored = tokio::task::spawn_blocking(move || {
+            Database::open(&path).and_then(|db| db.add_peers(&peers, MAX_STORED_PEERS))
+        })
+        .await;
+        match stored {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => warn!("⚠️  Failed to save gossiped peers: {}", e),
+            Err(e) => warn!("⚠️  Saving gossiped peers panicked: {}", e),
+        }
+    }
+
+    async fn record_peer_success(&self, node: &Node) {
+        self.reconnect.write().await.remove(&node.addr());
+        let now = unix_now();
+        self.update_peer_record(node, |record| {
+            record.last_seen = now;
+            record.failures = 0;
+        });
+    }
+
+    async fn record_peer_failure(&self, node: &Node) {
+        let failures = {
+            let mut reconnect = self.reconnect.write().await;
+            let entry = reconnect
+                .entry(node.addr())
+                .or_insert((0, Instant::now()));
+            entry.0 += 1;
+            entry.1 = Instant::now() + backoff_delay(entry.0);
+            entry.0
+        };
+        self.update_peer_record(node, |record| record.failures = failures);
+    }
+
+    /// Drop a peer's connection and everything we know about it
+    async fn disconnect(&self, node: &Node) {
+        self.pool.remove(node).await;
+        self.peer_heights.write().await.remove(&node.addr());
+        self.pings.lock().remove(&node.addr());
+        self.latencies.lock().remove(&node.addr());
+    }
+
+    /// Close the connection to a peer at our end. Returns whether it was
+    /// connected.
+    pub async fn disconnect_peer(&self, node: &Node) -> bool {
+        let connected = self.pool.get(node).await.is_ok();
+        if connected {
+            let _ = self.send_message(node, &NetworkMessage::Goodbye).await;
+            self.disconnect(node).await;
+        }
+        connected
+    }
+
+    /// Ping every peer, timing the round trip of each `Pong`
+    pub async fn ping_peers(&self) {
+        for node in self.list_peers().await {
+            let nonce = rand::random();
+            self.pings
+                .lock()
+                .insert(node.addr(), (nonce, Instant::now()));
+            if let Err(e) = self.send_message(&node, &NetworkMessage::Ping(nonce)).await {
+                warn!("⚠️  Could not ping {}: {}", node.addr(), e);
+            }
+        }
+    }
+
+    /// Round-trip time of a peer's last answered ping
+    pub fn peer_latency(&self, addr: &str) -> Option<Duration> {
+        self.latencies.lock().get(addr).copied()
+    }
+
+    fn record_pong(&self, node: &Node, nonce: u64) {
+        let addr = node.addr();
+        let mut pings = self.pings.lock();
+        if let Some((_, sent)) = pings.get(&addr).filter(|(expected, _)| *expected == nonce) {
+            self.latencies.lock().insert(addr.clone(), sent.elapsed());
+            pings.remove(&addr);
+        }
+    }
+
+    /// The handshake this node currently advertises
+    pub async fn local_handshake(&self) -> Handshake {
+        let chain = self.blockchain.read().await;
+        Handshake {
+            protocol_version: PROTOCOL_VERSION,
+            network_magic: active_network().magic(),
+            genesis_hash: chain.blocks.first().map(|b| b.hash()).unwrap_or([0; 32]),
+            best_height: chain.blocks.last().map_or(0, |b| b.header.height),
+        }
+    }
+
+    /// Best height advertised by a connected peer
+    pub async fn peer_height(&self, addr: &str) -> Option<u64> {
+        self.peer_heights.read().await.get(addr).copied()
+    }
+
+    /// Highest height advertised by any connected peer
+    pub async fn best_peer_height(&self) -> Option<u64> {
+        self.peer_heights.read().await.values().copied().max()
+    }
+
+    /// Local height against the best peer's, with the sync speed and ETA
+    pub async fn sync_state(&self) -> SyncState {
+        let height = self
+            .blockchain
+            .read()
+            .await
+            .blocks
+            .last()
+            .map_or(0, |b| b.header.height);
+        let target = self.best_peer_height().await;
+        self.sync.lock().state(height, target, Instant::now())
+    }
+
+    /// Read the next frame from a peer, waiting at most `idle` for it to
+    /// start and `PeerLimits::frame_timeout` for the rest. A frame that does
+    /// not decode is penalized and skipped (`None`), since the stream is
+    /// still in sync; an oversized frame is penalized and ends the
+    /// connection, as does a timeout.
+    async fn read_message(
+        &self,
+        node: &Node,
+        reader: &Mutex<OwnedReadHalf>,
+        idle: Duration,
+    ) -> Result<Option<NetworkMessage>, ChainError> {
+        let timed_out = |what: &str| {
+            ChainError::NetworkError(format!("Peer {} timed out {}", node.addr(), what))
+        };
+        let mut stream = reader.lock().await;
+        let mut len_bytes = [0u8; 4];
+        tokio::time::timeout(idle, stream.read_exact(&mut len_bytes))
+            .await
+            .map_err(|_| timed_out("waiting for a message"))??;
+        let len = u32::from_be_bytes(len_bytes) as usize;
+
+        if len > MAX_MESSAGE_SIZE {
+            drop(stream);
+            self.penalize_peer(node, OVERSIZED_MESSAGE_PENALTY, "oversized message")
+                .await?;
+            return Err(ChainError::NetworkError("Message too large".to_string()));
+        }
+
+        let mut buffer = vec![0u8; len];
+        tokio::time::timeout(self.limits.frame_timeout, stream.read_exact(&mut buffer))
+            .await
+            .map_err(|_| timed_out("sending a message"))??;
+        drop(stream);
+        match bincode::deserialize(&buffer) {
+            Ok(message) => Ok(Some(message)),
+            Err(e) => {
+                warn!("⚠️  Malformed message from {}: {}", node.addr(), e);
+                self.penalize_peer(node, MALFORMED_MESSAGE_PENALTY, "malformed message")
+                    .await?;
+                Ok(None)
+            }
+        }
+    }
+
+    async fn handle_connection(&self, node: &Node, outbound: bool) -> Result<(), ChainError> {
+        let reader = self.pool.get(node).await?.reader;
+
+        // Both sides announce themselves first; anything else is a protocol error
+        let local = self.local_handshake().await;
+        self.send_message(node, &NetworkMessage::Handshake(local.clone()))
+            .await?;
+        let peer_version;
+        match self
+            .read_message(node, &reader, self.limits.handshake_timeout)
+            .await?
+        {
+            Some(NetworkMessage::Handshake(remote)) => {
+                remote.check_compatible(&local, &self.protocol)?;
+                peer_version = remote.protocol_version;
+                info!(
+                    "🤝 Handshake with {} (protocol v{}, height {})",
+                    node.addr(),
+                    remote.protocol_version,
+                    remote.best_height
+                );
+                self.peer_heights
+                    .write()
+                    .await
+                    .insert(node.addr(), remote.best_height);
+                self.emit_peer_event(
+                    node,
+                    PeerEventKind::HandshakeComplete {
+                        protocol_version: remote.protocol_version,
+                        height: remote.best_height,
+                    },
+                );
+                // Only outbound addresses are dialable; inbound ports are ephemeral
+                if outbound {
+                    self.record_peer_success(node).await;
+                }
+            }
+            _ => {
+                return Err(ChainError::NetworkError(
+                    "Peer did not send a handshake".to_string(),
+                ))
+            }
+        }
+
+        let limits = self.limits;
+        let mut flood = TokenBucket::with_capacity(
+            limits.messages_per_sec as f64,
+            limits.message_burst.max(1) as f64,
+            Instant::now(),
+        );
+        loop {
+            let message = tokio::select! {
+                message = self.read_message(node, &reader, limits.idle_timeout) => message?,
+                _ = self.shutdown.cancelled() => return Ok(()),
+            };
+            // Malformed frames count too: they cost us a decode each
+            if flood.try_consume_at(Instant::now()).is_err() {
+                self.penalize_peer(node, FLOOD_PENALTY, "message flood")
+                    .await?;
+                return Err(ChainError::NetworkError(format!(
+                    "Peer {} exceeded {} messages/s",
+                    node.addr(),
+                    limits.messages_per_sec
+                )));
+            }
+            let Some(message) = message else {
+                continue;
+            };
+            // Banned for misbehaviour on another connection from the same host
+            if self.pool.is_banned(&node.host) {
+                return Err(ChainError::NetworkError(format!(
+                    "Peer {} is banned",
+                    node.addr()
+                )));
+            }
+            // Our chain may have passed an upgrade deadline since the handshake
+            if self.protocol.has_deadlines() {
+                let height = self
+                    .blockchain
+                    .read()
+                    .await
+                    .blocks
+                    .last()
+                    .map_or(0, |b| b.header.height);
+                self.protocol.check(peer_version, height)?;
+            }
 
             match message {
                 NetworkMessage::GetBlockHeaders { after_height } => {
@@ -191,7 +1055,9 @@ impl NetworkNode {
                 }
                 NetworkMessage::GetBlock(hash) => {
                     let chain = self.blockchain.read().await;
-                    if let Some(block) = chain.blocks.iter().find(|b| b.hash() == hash) {
+                    let block = chain.get_block_by_hash(&hash);
+                    // A header-only block would fail the peer's merkle check
+                    if let Some(block) = block.filter(|b| !b.is_header_only()) {
                         let response = NetworkMessage::Block(Box::new(block.clone()));
                         self.send_message(node, &response).await?;
                     }
@@ -202,47 +1068,526 @@ impl NetworkNode {
                     self.send_message(node, &response).await?;
                 }
                 NetworkMessage::Peers(peers) => {
-                    for _peer in peers {
-                        // self.clone().connect_peer(peer.host, peer.port).await?;
-                    }
+                    // Remember gossiped peers; the connection manager dials them
+                    self.store_gossiped_peers(peers).await;
+                }
+                NetworkMessage::Block(block) | NetworkMessage::NewBlock(block) => {
+                    self.process_block(node, *block).await?;
+                }
+                NetworkMessage::NewTransaction(tx) => {
+                    self.process_transaction(node, *tx).await?;
+                }
+                NetworkMessage::Inv(items) => {
+                    self.process_inv(node, items).await?;
+                }
+                NetworkMessage::GetData(items) => {
+                    self.serve_data(node, items).await?;
+                }
+                NetworkMessage::Handshake(_) => {
+                    // Heights come from the first handshake and from blocks
+                    self.penalize_peer(node, MALFORMED_MESSAGE_PENALTY, "repeated handshake")
+                        .await?;
+                }
+                NetworkMessage::Goodbye => {
+                    info!("👋 {} is shutting down", node.addr());
+                    self.disconnect(node).await;
+                    return Ok(());
+                }
+                NetworkMessage::Ping(nonce) => {
+                    self.send_message(node, &NetworkMessage::Pong(nonce)).await?;
+                }
+                NetworkMessage::Pong(nonce) => {
+                    self.record_pong(node, nonce);
                 }
                 _ => {} // Implement other message types
             }
         }
     }
 
-    async fn send_message(&self, node: &Node, message: &NetworkMessage) -> Result<(), ChainError> {
-        let stream_lock = self
-            .pool
-            .connections
-            .read()
+    /// Apply a block received from a peer. Blocks whose parent is unknown are
+    /// held in the orphan pool and the missing parent is requested; when a
+    /// block connects, any orphans waiting on it are connected too. Only a
+    /// block that is accepted or held raises the height recorded for the peer.
+    async fn process_block(&self, node: &Node, block: Block) -> Result<(), ChainError> {
+        let hash = block.hash();
+        let height = block.header.height;
+        self.propagation
+            .lock()
+            .record(hash, &node.addr(), Instant::now());
+        self.forks
+            .lock()
+            .record(&block.header, &node.addr(), unix_now());
+        self.inventory.lock().mark_seen(InvItem::Block(hash));
+
+        match self.validation.verdict(&hash).await {
+            // Already validated and connected
+            Some(BlockVerdict::Valid) => {
+                self.record_peer_height(node, height).await;
+                return Ok(());
+            }
+            Some(BlockVerdict::Invalid(reason)) => {
+                self.forks.lock().mark_invalid(&hash);
+                warn!(
+                    "⛔ {} re-sent invalid block #{}: {}",
+                    node.addr(),
+                    height,
+                    reason
+                );
+                return self
+                    .penalize_peer(node, INVALID_BLOCK_PENALTY, "invalid block")
+                    .await;
+            }
+            None => {}
+        }
+
+        let mut chain = self.blockchain.write().await;
+        // Failures of a block that links to our tip are properties of the
+        // block itself, not of our view of the chain, so they can be cached
+        let links_to_tip = chain.blocks.last().is_some_and(|tip| {
+            block.header.previous_hash == tip.hash() && height == tip.header.height + 1
+        });
+
+        match chain.apply_block(block.clone()) {
+            Ok(()) => {
+                self.validation.record_valid(hash).await;
+                self.tip_watch.lock().block_received(unix_now());
+                info!("📦 Block #{} accepted from {}", height, node.addr());
+                let connected = self.orphans.write().await.connect(&mut chain, hash);
+                if connected > 0 {
+                    info!("🔗 Connected {} orphan block(s)", connected);
+                }
+                self.check_watchtower(&chain);
+                self.record_sync_height(&chain);
+                drop(chain);
+                self.record_peer_height(node, height).await;
+                self.announce(
+                    InvItem::Block(hash),
+                    RelayItem::Block(Box::new(block)),
+                    Some(&node.addr()),
+                )
+                .await;
+            }
+            Err(ChainError::OrphanBlock) => {
+                if let Err(e) = chain.check_unconnected_block(&block) {
+                    drop(chain);
+                    return self.reject_unconnected_block(node, height, e).await;
+                }
+                let mut orphans = self.orphans.write().await;
+                if orphans.insert(block, Some(node.addr())) {
+                    let missing = orphans.missing_ancestor(&hash);
+                    // The held chain may fork off a block we already have
+                    if chain.get_block_by_hash(&missing).is_some() {
+                        drop(orphans);
+                        let resubmitted = self.try_reorg(&mut chain, hash).await;
+                        drop(chain);
+                        self.record_peer_height(node, height).await;
+                        self.rebroadcast(resubmitted).await;
+                        return Ok(());
+                    }
+                    drop(orphans);
+                    drop(chain);
+                    self.record_peer_height(node, height).await;
+                    info!(
+                        "🧩 Orphan block #{} from {}, requesting parent {}",
+                        height,
+                        node.addr(),
+                        hex::encode(missing)
+                    );
+                    self.send_message(node, &NetworkMessage::GetBlock(missing))
+                        .await?;
+                }
+            }
+            Err(ChainError::FutureBlock) => {
+                // Not cached: the block may be valid once our clock catches up
+                warn!(
+                    "⏰ Block #{} from {} is stamped too far in the future",
+                    height,
+                    node.addr()
+                );
+            }
+            Err(e) if links_to_tip => {
+                drop(chain);
+                warn!("⚠️  Rejected block #{} from {}: {}", height, node.addr(), e);
+                self.validation.record_invalid(hash, &e.to_string()).await;
+                self.forks.lock().mark_invalid(&hash);
+                return self
+                    .penalize_peer(node, INVALID_BLOCK_PENALTY, "invalid block")
+                    .await;
+            }
+            Err(e) => {
+                // A block extending a side branch is held until that branch
+                // outgrows the main chain
+                let parent = block.header.previous_hash;
+                let known = chain.get_block_by_hash(&hash).is_some();
+                let forks_off_main = chain.get_block_by_hash(&parent).is_some();
+                let mut orphans = self.orphans.write().await;
+                if known {
+                    // Already on our main chain
+                    drop(orphans);
+                    drop(chain);
+                    self.record_peer_height(node, height).await;
+                } else if forks_off_main || orphans.contains(&parent) {
+                    if let Err(e) = chain.check_unconnected_block(&block) {
+                        drop(orphans);
+                        drop(chain);
+                        return self.reject_unconnected_block(node, height, e).await;
+                    }
+                    if orphans.insert(block, Some(node.addr())) {
+                        info!("🍴 Side-branch block #{} from {}", height, node.addr());
+                    }
+                    drop(orphans);
+                    let resubmitted = self.try_reorg(&mut chain, hash).await;
+                    drop(chain);
+                    self.record_peer_height(node, height).await;
+                    self.rebroadcast(resubmitted).await;
+                } else {
+                    drop(orphans);
+                    drop(chain);
+                    return self.reject_unconnected_block(node, height, e).await;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Penalize a peer for an orphan or side-branch block that failed the
+    /// cheap checks; it is not held
+    async fn reject_unconnected_block(
+        &self,
+        node: &Node,
+        height: u64,
+        error: ChainError,
+    ) -> Result<(), ChainError> {
+        warn!(
+            "⚠️  Rejected unconnected block #{} from {}: {}",
+            height,
+            node.addr(),
+            error
+        );
+        self.penalize_peer(node, INVALID_BLOCK_PENALTY, "invalid block")
             .await
-            .get(&node.addr())
-            .cloned()
-            .ok_or_else(|| ChainError::NetworkError("Connection not in pool".to_string()))?;
+    }
+
+    /// Switch to the held side branch through `hash` if it is longer than
+    /// the main chain. Returns the transactions knocked out of the old branch
+    /// that went back to the mempool; the caller rebroadcasts them once the
+    /// chain lock is released.
+    async fn try_reorg(&self, chain: &mut Blockchain, hash: Sha256Hash) -> Vec<Transaction> {
+        let mut orphans = self.orphans.write().await;
+        let branch = orphans.branch(&hash);
+        let tip_height = chain.blocks.last().map_or(0, |b| b.header.height);
+        if branch.last().is_none_or(|b| b.header.height <= tip_height) {
+            return Vec::new();
+        }
+
+        let hashes: Vec<Sha256Hash> = branch.iter().map(|b| b.hash()).collect();
+        orphans.discard(&hashes);
+        let outcome = match chain.reorganize(branch) {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                warn!(
+                    "⚠️  Side branch through {} rejected: {}",
+                    hex::encode(hash),
+                    e
+                );
+                self.forks.lock().mark_invalid(&hash);
+                return Vec::new();
+            }
+        };
+
+        for hash in &hashes {
+            self.validation.record_valid(*hash).await;
+        }
+        self.tip_watch.lock().block_received(unix_now());
+        let new_tip = chain.blocks.last().map(|b| b.hash()).unwrap_or_default();
+        let connected = orphans.connect(chain, new_tip);
+        drop(orphans);
+        info!(
+            "🔀 Reorganized at #{}: {} block(s) disconnected, {} connected, {} transaction(s) resubmitted, {} dropped",
+            outcome.fork_height,
+            outcome.disconnected.len(),
+            hashes.len() + connected,
+            outcome.resubmitted.len(),
+            outcome.dropped.len()
+        );
+        for (hash, reason) in &outcome.dropped {
+            warn!("   ✗ {} not resubmitted: {}", hex::encode(hash), reason);
+        }
+        self.check_watchtower(chain);
+        self.record_sync_height(chain);
+        outcome.resubmitted
+    }
+
+    /// Track the tip height for the sync speed, and remember it every
+    /// `SYNC_HEIGHT_SAVE_INTERVAL` blocks so it survives restarts
+    fn record_sync_height(&self, chain: &Blockchain) {
+        let height = chain.blocks.last().map_or(0, |b| b.header.height);
+        self.sync.lock().record(height, Instant::now());
+        let saved = *self.saved_sync_height.lock();
+        if saved.is_none_or(|saved| saved.abs_diff(height) >= SYNC_HEIGHT_SAVE_INTERVAL) {
+            self.save_sync_height(height);
+        }
+    }
 
-        let data = bincode::serialize(message)?;
-        let len = data.len() as u32;
+    fn save_sync_height(&self, height: u64) {
+        let Some(path) = &self.peer_db else {
+            return;
+        };
+        match Database::open(path).and_then(|db| db.set_setting(&LAST_SYNC_HEIGHT, &height)) {
+            Ok(()) => *self.saved_sync_height.lock() = Some(height),
+            Err(e) => warn!("⚠️  Failed to save sync height: {}", e),
+        }
+    }
 
-        let mut stream = stream_lock.write().await;
-        stream.write_all(&len.to_be_bytes()).await?;
-        stream.write_all(&data).await?;
+    async fn rebroadcast(&self, transactions: Vec<Transaction>) {
+        for tx in transactions {
+            let item = InvItem::Transaction(tx.hash());
+            self.announce(item, RelayItem::Transaction(Box::new(tx)), None)
+                .await;
+        }
+    }
+
+    /// Announce an item by hash to every peer except `exclude`, keeping its
+    /// body to answer their `GetData`
+    async fn announce(&self, item: InvItem, body: RelayItem, exclude: Option<&str>) {
+        {
+            let mut inventory = self.inventory.lock();
+            inventory.mark_seen(item);
+            inventory.store_relay(item, body);
+        }
+        self.pool
+            .broadcast_except(&NetworkMessage::Inv(vec![item]), exclude)
+            .await;
+    }
+
+    /// Request the announced items we have neither seen nor already asked
+    /// another peer for
+    async fn process_inv(&self, node: &Node, items: Vec<InvItem>) -> Result<(), ChainError> {
+        let now = Instant::now();
+        let items = &items[..items.len().min(MAX_INV_ITEMS)];
+        for item in items {
+            if let InvItem::Block(hash) = item {
+                self.propagation.lock().record(*hash, &node.addr(), now);
+            }
+        }
+        let wanted: Vec<InvItem> = {
+            let mut inventory = self.inventory.lock();
+            items
+                .iter()
+                .copied()
+                .filter(|item| inventory.want(*item, now))
+                .collect()
+        };
+        if wanted.is_empty() {
+            return Ok(());
+        }
+        self.send_message(node, &NetworkMessage::GetData(wanted))
+            .await
+    }
+
+    /// Answer a peer's `GetData` from recently announced bodies, the chain
+    /// or the mempool; unknown items are skipped
+    async fn serve_data(&self, node: &Node, items: Vec<InvItem>) -> Result<(), ChainError> {
+        for item in items.into_iter().take(MAX_INV_ITEMS) {
+            let cached = self.inventory.lock().relay_body(&item);
+            let response = match (item, cached) {
+                (_, Some(RelayItem::Block(block))) => Some(NetworkMessage::Block(block)),
+                (_, Some(RelayItem::Transaction(tx))) => Some(NetworkMessage::NewTransaction(tx)),
+                (InvItem::Block(hash), None) => {
+                    let chain = self.blockchain.read().await;
+                    chain
+                        .get_block_by_hash(&hash)
+                        .filter(|b| !b.is_header_only())
+                        .map(|b| NetworkMessage::Block(Box::new(b.clone())))
+                }
+                (InvItem::Transaction(hash), None) => {
+                    let chain = self.blockchain.read().await;
+                    chain
+                        .mempool
+                        .get_transaction(&hash)
+                        .map(|tx| NetworkMessage::NewTransaction(Box::new(tx.clone())))
+                }
+            };
+            if let Some(response) = response {
+                self.send_message(node, &response).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Raise a peer's ban score. Once it crosses the ban threshold the host
+    /// is banned for `BAN_DURATION` (persisted when a peer database is set),
+    /// all its connections are closed and this returns an error so the
+    /// calling connection handler stops too.
+    async fn penalize_peer(&self, node: &Node, penalty: u32, reason: &str) -> Result<(), ChainError> {
+        let (score, ban) = self.pool.penalize(&node.host, penalty, reason);
+        self.emit_peer_event(
+            node,
+            PeerEventKind::Misbehavior {
+                reason: reason.to_string(),
+                penalty,
+                score,
+                banned: ban.is_some(),
+            },
+        );
+        let Some(ban) = ban else {
+            return Ok(());
+        };
+        warn!(
+            "⛔ Banned {} for {}h: {}",
+            node.host,
+            BAN_DURATION.as_secs() / 3600,
+            reason
+        );
+        if let Some(path) = &self.peer_db {
+            if let Err(e) = Database::open(path).and_then(|db| db.save_ban(&ban)) {
+                warn!("⚠️  Failed to save ban of {}: {}", node.host, e);
+            }
+        }
+        self.pool.remove_host(&node.host).await;
+        Err(ChainError::NetworkError(format!(
+            "Peer {} banned for misbehaviour",
+            node.addr()
+        )))
+    }
+
+    /// Add a transaction received from a peer to the mempool and relay it to
+    /// the other peers. Only transactions passing the stateful checks (input
+    /// exists, sender owns it, signature valid) are relayed; a peer sending
+    /// one that can never be valid is penalized. A missing input or an
+    /// immature coinbase is not penalized, since we may simply not have the
+    /// blocks creating or maturing it yet (or the peer may be ahead of us).
+    async fn process_transaction(
+        &self,
+        node: &Node,
+        tx: crate::transaction::Transaction,
+    ) -> Result<(), ChainError> {
+        self.inventory
+            .lock()
+            .mark_seen(InvItem::Transaction(tx.hash()));
+        let mut chain = self.blockchain.write().await;
+        let result = match &tx {
+            crate::transaction::Transaction::Coinbase(_) => Err(ChainError::InvalidTransaction(
+                "Coinbase transactions are not relayed".to_string(),
+            )),
+            _ => {
+                let next_height = chain.blocks.last().map_or(0, |b| b.header.height + 1);
+                tx.validate(&chain.state, next_height)
+            }
+        };
+        if let Err(e) = result {
+            drop(chain);
+            warn!("⚠️  Rejected transaction from {}: {}", node.addr(), e);
+            return match e {
+                ChainError::InvalidTransaction(_) => {
+                    self.penalize_peer(node, INVALID_TX_PENALTY, "invalid transaction")
+                        .await
+                }
+                _ => Ok(()),
+            };
+        }
+
+        // Duplicates and per-address limits are not the peer's fault, but
+        // the transaction is not relayed again either
+        if chain.mempool.add_transaction(tx.clone()).is_err() {
+            return Ok(());
+        }
+        self.check_watchtower(&chain);
+        drop(chain);
+
+        let item = InvItem::Transaction(tx.hash());
+        self.announce(item, RelayItem::Transaction(Box::new(tx)), Some(&node.addr()))
+            .await;
         Ok(())
     }
 
+    fn check_watchtower(&self, chain: &Blockchain) {
+        if let Some(watchtower) = &self.watchtower {
+            for alert in watchtower.scan(chain) {
+                warn!("{}", alert.describe());
+            }
+        }
+    }
+
+    async fn record_peer_height(&self, node: &Node, height: u64) {
+        let mut heights = self.peer_heights.write().await;
+        let entry = heights.entry(node.addr()).or_insert(0);
+        *entry = (*entry).max(height);
+    }
+
+    /// Number of blocks currently held waiting for their parent
+    pub async fn orphan_count(&self) -> usize {
+        self.orphans.read().await.len()
+    }
+
+    /// Queue a message for one peer. Errors if the peer's queue overflowed
+    /// (or its writer stopped), which ends the connection.
+    async fn send_message(&self, node: &Node, message: &NetworkMessage) -> Result<(), ChainError> {
+        let connection = self.pool.get(node).await?;
+        match connection.queue.push(message.priority(), encode_frame(message)?) {
+            PushOutcome::Overflow => Err(ChainError::NetworkError(format!(
+                "Outbound queue to {} overflowed",
+                node.addr()
+            ))),
+            PushOutcome::Queued | PushOutcome::Dropped => Ok(()),
+        }
+    }
+
+    /// Announce a transaction to all peers; they fetch the body with `GetData`
     pub async fn broadcast_transaction(&self, tx: &crate::transaction::Transaction) {
-        let message = NetworkMessage::NewTransaction(Box::new(tx.clone()));
-        self.pool.broadcast(&message).await;
+        let item = InvItem::Transaction(tx.hash());
+        self.announce(item, RelayItem::Transaction(Box::new(tx.clone())), None)
+            .await;
     }
 
     pub async fn broadcast_block(&self, block: &crate::blockchain::Block) {
-        let message = NetworkMessage::NewBlock(Box::new(block.clone()));
-        self.pool.broadcast(&message).await;
+        // Our own blocks are first seen here; peers echoing them back are late
+        self.propagation
+            .lock()
+            .record(block.hash(), LOCAL_SOURCE, Instant::now());
+        self.forks
+            .lock()
+            .record(&block.header, LOCAL_SOURCE, unix_now());
+        let item = InvItem::Block(block.hash());
+        self.announce(item, RelayItem::Block(Box::new(block.clone())), None)
+            .await;
     }
 
     pub async fn list_peers(&self) -> Vec<Node> {
         self.pool.list_peers().await
     }
+
+    /// Stop accepting connections and messages, then send every peer a
+    /// `Goodbye` and close its connection once queued messages are written.
+    /// Block application in progress holds the chain lock, so callers
+    /// flushing state afterwards see its result.
+    pub async fn shutdown(&self) {
+        self.shutdown.trigger();
+        self.pool.close_all().await;
+        self.peer_heights.write().await.clear();
+
+        let height = self
+            .blockchain
+            .read()
+            .await
+            .blocks
+            .last()
+            .map_or(0, |b| b.header.height);
+        if *self.saved_sync_height.lock() != Some(height) {
+            self.save_sync_height(height);
+        }
+    }
+
+    /// Per-peer block propagation statistics, best deliverers first
+    pub fn propagation_stats(&self) -> Vec<PeerPropagation> {
+        self.propagation.lock().stats()
+    }
+
+    /// Every known chain tip: the active one first, then side branches
+    pub async fn fork_tips(&self) -> Vec<ForkTip> {
+        let chain = self.blockchain.read().await;
+        self.forks.lock().tips(&chain.blocks)
+    }
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
@@ -255,4 +1600,642 @@ pub enum NetworkMessage {
     NewTransaction(Box<crate::transaction::Transaction>),
     GetPeers,
     Peers(Vec<Node>),
+    Handshake(Handshake),
+    /// Keepalive; answered with a `Pong` carrying the same nonce
+    Ping(u64),
+    Pong(u64),
+    /// The sender is shutting down and closing the connection
+    Goodbye,
+    /// Hashes of blocks and transactions the sender has; peers request the
+    /// ones they lack with `GetData`
+    Inv(Vec<InvItem>),
+    GetData(Vec<InvItem>),
+}
+
+impl NetworkMessage {
+    /// Outbound queue class of this message
+    pub fn priority(&self) -> Priority {
+        match self {
+            NetworkMessage::Handshake(_)
+            | NetworkMessage::GetBlockHeaders { .. }
+            | NetworkMessage::BlockHeaders(_)
+            | NetworkMessage::GetBlock(_)
+            | NetworkMessage::Block(_)
+            | NetworkMessage::NewBlock(_)
+            | NetworkMessage::Goodbye => Priority::Block,
+            NetworkMessage::Ping(_) | NetworkMessage::Pong(_) => Priority::Ping,
+            NetworkMessage::NewTransaction(_) => Priority::Transaction,
+            NetworkMessage::GetPeers | NetworkMessage::Peers(_) => Priority::Addr,
+            NetworkMessage::Inv(items) | NetworkMessage::GetData(items) => {
+                if items.iter().any(|item| matches!(item, InvItem::Block(_))) {
+                    Priority::Block
+                } else {
+                    Priority::Transaction
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Address;
+
+    fn handshake() -> Handshake {
+        Handshake {
+            protocol_version: PROTOCOL_VERSION,
+            network_magic: active_network().magic(),
+            genesis_hash: [1; 32],
+            best_height: 10,
+        }
+    }
+
+    /// Next message on a raw peer socket; `None` once it is closed
+    async fn read_frame(stream: &mut TcpStream) -> Option<NetworkMessage> {
+        let mut len = [0u8; 4];
+        stream.read_exact(&mut len).await.ok()?;
+        let mut data = vec![0u8; u32::from_be_bytes(len) as usize];
+        stream.read_exact(&mut data).await.ok()?;
+        bincode::deserialize(&data).ok()
+    }
+
+    /// Start `node`'s listener and return a raw peer that completed the
+    /// handshake, plus the listener task
+    async fn handshaken_peer(
+        node: &Arc<NetworkNode>,
+    ) -> (TcpStream, tokio::task::JoinHandle<Result<(), ChainError>>) {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let server = tokio::spawn(node.clone().start_server(port));
+        let mut peer = loop {
+            match TcpStream::connect(("127.0.0.1", port)).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        };
+        let local = node.local_handshake().await;
+        let hello = NetworkMessage::Handshake(Handshake { best_height: 0, ..local });
+        peer.write_all(&encode_frame(&hello).unwrap()).await.unwrap();
+        assert!(matches!(read_frame(&mut peer).await, Some(NetworkMessage::Handshake(_))));
+        (peer, server)
+    }
+
+    #[test]
+    fn test_handshake_compatibility() {
+        let local = handshake();
+        let policy = ProtocolPolicy::default();
+        assert!(handshake().check_compatible(&local, &policy).is_ok());
+
+        let other_network = Handshake { network_magic: 0, ..handshake() };
+        assert!(other_network.check_compatible(&local, &policy).is_err());
+
+        let other_chain = Handshake { genesis_hash: [2; 32], ..handshake() };
+        assert!(other_chain.check_compatible(&local, &policy).is_err());
+
+        let too_old = Handshake { protocol_version: 0, ..handshake() };
+        assert!(too_old.check_compatible(&local, &policy).is_err());
+    }
+
+    #[test]
+    fn test_protocol_deadlines() {
+        let base = MIN_PROTOCOL_VERSION;
+        let policy = ProtocolPolicy::new(0)
+            .with_deadline(ProtocolDeadline {
+                height: 100,
+                min_version: base + 1,
+            })
+            .with_deadline(ProtocolDeadline {
+                height: 50,
+                min_version: base + 2,
+            });
+        assert_eq!(policy.min_version_at(0), base);
+        assert_eq!(policy.min_version_at(50), base + 2);
+        assert_eq!(policy.min_version_at(100), base + 2);
+        assert!(policy.check(base, 49).is_ok());
+        let err = policy.check(base + 1, 120).unwrap_err().to_string();
+        assert!(err.contains("retired at height 50"), "{}", err);
+        assert!(ProtocolPolicy::new(base + 1).check(base, 0).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failed_peer_backs_off_and_is_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("peers.db");
+        let db_path = db_path.to_str().unwrap();
+
+        let chain = Blockchain::new(Address::from([1; 32]), 1).unwrap();
+        let node = Arc::new(NetworkNode::new(Arc::new(RwLock::new(chain))).with_peer_db(db_path));
+
+        // Grab a free port, then close it so the connection is refused
+        let port = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let addr = format!("127.0.0.1:{}", port);
+
+        assert!(node.clone().connect_peer("127.0.0.1".to_string(), port).await.is_err());
+        assert!(!node.reconnect_due(&addr).await);
+
+        let record = Database::open(db_path).unwrap().get_peer(&addr).unwrap().unwrap();
+        assert_eq!(record.failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_gossiped_peers_capped() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("peers.db");
+        let db_path = db_path.to_str().unwrap();
+        let chain = Blockchain::new(Address::from([1; 32]), 1).unwrap();
+        let node = NetworkNode::new(Arc::new(RwLock::new(chain))).with_peer_db(db_path);
+
+        let gossip = (0..MAX_GOSSIPED_PEERS as u16 + 50)
+            .map(|port| Node::new("10.0.0.1".to_string(), 1_000 + port))
+            .collect();
+        node.store_gossiped_peers(gossip).await;
+
+        let stored = Database::open(db_path).unwrap().load_peers().unwrap();
+        assert_eq!(stored.len(), MAX_GOSSIPED_PEERS);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_block_is_cached_and_resender_banned() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("peers.db");
+        let db_path = db_path.to_str().unwrap();
+
+        let chain = Blockchain::new(Address::from([1; 32]), 1).unwrap();
+        let genesis = chain.blocks[0].clone();
+        let node = NetworkNode::new(Arc::new(RwLock::new(chain))).with_peer_db(db_path);
+        let peer = Node::new("127.0.0.1".to_string(), 9);
+
+        // Claims far more than the block subsidy
+        let coinbase = crate::transaction::Transaction::Coinbase(crate::transaction::CoinbaseTx {
+            reward_area: crate::geometry::Coord::from_num(1_000_000),
+            beneficiary_address: Address::from([2; 32]),
+            nonce: 1,
+        });
+        let mut block = Block::new(1, genesis.hash(), 1, vec![coinbase]);
+        block.header.timestamp = genesis.header.timestamp + 1;
+        let block = crate::miner::mine_block(block).unwrap();
+        let hash = block.hash();
+
+        // An invalid block bans the sender outright
+        assert!(node.process_block(&peer, block.clone()).await.is_err());
+        assert!(matches!(
+            node.validation.verdict(&hash).await,
+            Some(BlockVerdict::Invalid(_))
+        ));
+        assert!(node.pool.is_banned(&peer.host));
+        assert_eq!(node.pool.ban_score(&peer.host), 0);
+        assert_eq!(node.peer_height(&peer.addr()).await, None);
+
+        // Another peer re-sending it is rejected from the cache and banned too
+        let other = Node::new("127.0.0.2".to_string(), 9);
+        assert!(node.process_block(&other, block).await.is_err());
+        assert!(node.pool.is_banned(&other.host));
+        assert_eq!(node.blockchain.read().await.blocks.len(), 1);
+
+        // Bans survive a restart
+        let chain = Blockchain::new(Address::from([1; 32]), 1).unwrap();
+        let restarted = Arc::new(NetworkNode::new(Arc::new(RwLock::new(chain))).with_peer_db(db_path));
+        assert!(restarted.pool.is_banned("127.0.0.1"));
+        assert!(restarted
+            .clone()
+            .connect_peer("127.0.0.1".to_string(), 9)
+            .await
+            .is_err());
+        assert_eq!(
+            Database::open(db_path).unwrap().load_bans(unix_now()).unwrap().len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unmined_orphan_not_held() {
+        let chain = Blockchain::new(Address::from([1; 32]), 1).unwrap();
+        let node = NetworkNode::new(Arc::new(RwLock::new(chain)));
+        let peer = Node::new("127.0.0.1".to_string(), 9);
+
+        // Unknown parent, and a claimed difficulty its hash doesn't meet
+        let coinbase = crate::transaction::Transaction::Coinbase(crate::transaction::CoinbaseTx {
+            reward_area: crate::geometry::Coord::from_num(1),
+            beneficiary_address: Address::from([2; 32]),
+            nonce: 1,
+        });
+        let junk = Block::new(5, [7; 32], 200, vec![coinbase]);
+
+        assert!(node.process_block(&peer, junk).await.is_err());
+        assert_eq!(node.orphan_count().await, 0);
+        assert!(node.pool.is_banned(&peer.host));
+        assert_eq!(node.peer_height(&peer.addr()).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_valid_transaction_relayed_and_invalid_penalized() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("peers.db");
+        let db_path = db_path.to_str().unwrap();
+
+        let keypair = crate::crypto::KeyPair::generate().unwrap();
+        let mut chain = Blockchain::new(keypair.address(), 1).unwrap();
+        let input = *chain.state.utxo_set.keys().next().unwrap();
+        let block = crate::fixtures::mine_next_block(&chain, keypair.address(), vec![]);
+        let reward = block.transactions[0].hash();
+        chain.apply_block(block).unwrap();
+        let node = NetworkNode::new(Arc::new(RwLock::new(chain))).with_peer_db(db_path);
+        let sender = Node::new("127.0.0.1".to_string(), 9);
+
+        // A second peer that should receive the relay
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let outbound = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let (mut other, _) = listener.accept().await.unwrap();
+        node.pool
+            .add(&Node::new("127.0.0.1".to_string(), port), outbound)
+            .await;
+
+        // Signed by a key that does not own the input
+        let thief = crate::crypto::KeyPair::generate().unwrap();
+        let forged = crate::fixtures::signed_transfer(
+            &thief,
+            input,
+            Address::from([3; 32]),
+            crate::geometry::Coord::from_num(1),
+            crate::geometry::Coord::from_num(0),
+            1,
+        );
+        node.process_transaction(&sender, forged).await.unwrap();
+        assert_eq!(node.pool.ban_score(&sender.host), INVALID_TX_PENALTY);
+        assert_eq!(node.blockchain.read().await.mempool.len(), 0);
+
+        let valid = crate::fixtures::signed_transfer(
+            &keypair,
+            input,
+            Address::from([3; 32]),
+            crate::geometry::Coord::from_num(1),
+            crate::geometry::Coord::from_num(0),
+            1,
+        );
+        node.process_transaction(&sender, valid.clone()).await.unwrap();
+        assert_eq!(node.blockchain.read().await.mempool.len(), 1);
+
+        let mut len = [0u8; 4];
+        other.read_exact(&mut len).await.unwrap();
+        let mut data = vec![0u8; u32::from_be_bytes(len) as usize];
+        other.read_exact(&mut data).await.unwrap();
+        match bincode::deserialize(&data).unwrap() {
+            NetworkMessage::Inv(items) => {
+                assert_eq!(items, vec![InvItem::Transaction(valid.hash())])
+            }
+            _ => panic!("expected the transaction to be announced"),
+        }
+
+        // Unknown inputs are not the sender's fault
+        let orphan = crate::fixtures::signed_transfer(
+            &keypair,
+            [9; 32],
+            Address::from([3; 32]),
+            crate::geometry::Coord::from_num(1),
+            crate::geometry::Coord::from_num(0),
+            2,
+        );
+        node.process_transaction(&sender, orphan).await.unwrap();
+        assert_eq!(node.pool.ban_score(&sender.host), INVALID_TX_PENALTY);
+
+        // Nor is spending a reward that has not matured here yet
+        let early = crate::fixtures::signed_transfer(
+            &keypair,
+            reward,
+            Address::from([3; 32]),
+            crate::geometry::Coord::from_num(1),
+            crate::geometry::Coord::from_num(0),
+            3,
+        );
+        assert!(matches!(
+            early.validate(&node.blockchain.read().await.state, 2),
+            Err(ChainError::ImmatureCoinbase(_))
+        ));
+        node.process_transaction(&sender, early).await.unwrap();
+        assert_eq!(node.pool.ban_score(&sender.host), INVALID_TX_PENALTY);
+        assert_eq!(node.blockchain.read().await.mempool.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_says_goodbye_and_stops_listener() {
+        let chain = Blockchain::new(Address::from([1; 32]), 1).unwrap();
+        let node = Arc::new(NetworkNode::new(Arc::new(RwLock::new(chain))));
+        let (mut peer, server) = handshaken_peer(&node).await;
+        while node.peer_height(&peer.local_addr().unwrap().to_string()).await.is_none() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        node.shutdown().await;
+        assert!(matches!(read_frame(&mut peer).await, Some(NetworkMessage::Goodbye)));
+        // The connection is closed after the goodbye
+        assert!(read_frame(&mut peer).await.is_none());
+        assert!(node.list_peers().await.is_empty());
+        tokio::time::timeout(Duration::from_secs(1), server)
+            .await
+            .expect("listener still running")
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_inventory_announced_then_fetched() {
+        let keypair = crate::crypto::KeyPair::generate().unwrap();
+        let chain = Blockchain::new(keypair.address(), 1).unwrap();
+        let input = *chain.state.utxo_set.keys().next().unwrap();
+        let node = Arc::new(NetworkNode::new(Arc::new(RwLock::new(chain))));
+        let (mut peer, _server) = handshaken_peer(&node).await;
+
+        let tx = crate::fixtures::signed_transfer(
+            &keypair,
+            input,
+            Address::from([3; 32]),
+            crate::geometry::Coord::from_num(1),
+            crate::geometry::Coord::from_num(0),
+            1,
+        );
+        let item = InvItem::Transaction(tx.hash());
+        let send = |message: NetworkMessage| encode_frame(&message).unwrap();
+
+        // An unseen hash is requested once, even when announced again
+        peer.write_all(&send(NetworkMessage::Inv(vec![item]))).await.unwrap();
+        match read_frame(&mut peer).await {
+            Some(NetworkMessage::GetData(items)) => assert_eq!(items, vec![item]),
+            other => panic!("expected GetData, got {:?}", other),
+        }
+        peer.write_all(&send(NetworkMessage::Inv(vec![item]))).await.unwrap();
+        peer.write_all(&send(NetworkMessage::Ping(7))).await.unwrap();
+        assert!(matches!(read_frame(&mut peer).await, Some(NetworkMessage::Pong(7))));
+
+        // Once delivered it is served back on request, not echoed to the sender
+        peer.write_all(&send(NetworkMessage::NewTransaction(Box::new(tx.clone()))))
+            .await
+            .unwrap();
+        peer.write_all(&send(NetworkMessage::GetData(vec![item]))).await.unwrap();
+        match read_frame(&mut peer).await {
+            Some(NetworkMessage::NewTransaction(served)) => assert_eq!(served.hash(), tx.hash()),
+            other => panic!("expected the transaction, got {:?}", other),
+        }
+        assert_eq!(node.blockchain.read().await.mempool.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_messages_get_peer_banned() {
+        let chain = Blockchain::new(Address::from([1; 32]), 1).unwrap();
+        let node = Arc::new(NetworkNode::new(Arc::new(RwLock::new(chain))));
+        let (mut peer, _server) = handshaken_peer(&node).await;
+        // Unknown message variant
+        let garbage = [0, 0, 0, 4, 0xff, 0xff, 0xff, 0xff];
+
+        // Malformed frames are skipped until the score reaches the threshold
+        let below = BAN_THRESHOLD / MALFORMED_MESSAGE_PENALTY - 1;
+        for _ in 0..below {
+            peer.write_all(&garbage).await.unwrap();
+        }
+        peer.write_all(&encode_frame(&NetworkMessage::Ping(1)).unwrap())
+            .await
+            .unwrap();
+        assert!(matches!(read_frame(&mut peer).await, Some(NetworkMessage::Pong(1))));
+        assert_eq!(node.pool.ban_score("127.0.0.1"), below * MALFORMED_MESSAGE_PENALTY);
+
+        peer.write_all(&garbage).await.unwrap();
+        assert!(read_frame(&mut peer).await.is_none());
+        assert!(node.pool.is_banned("127.0.0.1"));
+        assert!(node.list_peers().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_idle_and_flooding_peers_disconnected() {
+        let limits = PeerLimits {
+            idle_timeout: Duration::from_millis(200),
+            messages_per_sec: 1,
+            message_burst: 3,
+            ..PeerLimits::default()
+        };
+        let chain = Blockchain::new(Address::from([1; 32]), 1).unwrap();
+        let node =
+            Arc::new(NetworkNode::new(Arc::new(RwLock::new(chain))).with_peer_limits(limits));
+
+        // A silent peer is dropped once the idle timeout passes
+        let (mut idle, _server) = handshaken_peer(&node).await;
+        let closed = tokio::time::timeout(Duration::from_secs(5), async {
+            while read_frame(&mut idle).await.is_some() {}
+        });
+        assert!(closed.await.is_ok());
+        assert_eq!(node.pool.ban_score("127.0.0.1"), 0);
+
+        // A peer sending faster than the cap is penalized and dropped
+        let (mut flooder, _server) = handshaken_peer(&node).await;
+        for nonce in 0..5 {
+            let ping = encode_frame(&NetworkMessage::Ping(nonce)).unwrap();
+            if flooder.write_all(&ping).await.is_err() {
+                break;
+            }
+        }
+        while read_frame(&mut flooder).await.is_some() {}
+        assert_eq!(node.pool.ban_score("127.0.0.1"), FLOOD_PENALTY);
+        assert!(node.list_peers().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_accepted_blocks_published() {
+        use crate::events::ChainEvent;
+
+        let chain = Blockchain::new(Address::from([1; 32]), 1).unwrap();
+        let first = crate::fixtures::mine_next_block(&chain, Address::from([2; 32]), vec![]);
+        let mut events = chain.subscribe_events();
+        let node = NetworkNode::new(Arc::new(RwLock::new(chain)));
+        let peer = Node::new("127.0.0.1".to_string(), 9);
+
+        node.process_block(&peer, first.clone()).await.unwrap();
+        assert!(matches!(
+            events.try_recv(),
+            Ok(ChainEvent::BlockConnected(block)) if block.hash() == first.hash()
+        ));
+        assert_eq!(node.peer_height(&peer.addr()).await, Some(1));
+
+        // A block already on the main chain is not published again
+        node.process_block(&peer, first).await.unwrap();
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sync_height_saved_periodically_and_on_shutdown() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("peers.db");
+        let db_path = db_path.to_str().unwrap();
+        let saved = || {
+            Database::open(db_path)
+                .unwrap()
+                .get_setting(&LAST_SYNC_HEIGHT)
+                .unwrap()
+        };
+
+        let chain = Blockchain::new(Address::from([1; 32]), 1).unwrap();
+        let first = crate::fixtures::mine_next_block(&chain, Address::from([2; 32]), vec![]);
+        let node = NetworkNode::new(Arc::new(RwLock::new(chain))).with_peer_db(db_path);
+        let peer = Node::new("127.0.0.1".to_string(), 9);
+
+        // The first height is written; the next ones wait for the interval
+        node.record_sync_height(&*node.blockchain.read().await);
+        assert_eq!(saved(), Some(0));
+        node.process_block(&peer, first).await.unwrap();
+        assert_eq!(saved(), Some(0));
+
+        node.shutdown().await;
+        assert_eq!(saved(), Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_peer_lifecycle_events_published() {
+        let chain = Blockchain::new(Address::from([1; 32]), 1).unwrap();
+        let node = Arc::new(NetworkNode::new(Arc::new(RwLock::new(chain))));
+        let mut events = node.subscribe_peer_events();
+        let (mut peer, _server) = handshaken_peer(&node).await;
+        peer.write_all(&[0, 0, 0, 4, 0xff, 0xff, 0xff, 0xff]).await.unwrap();
+        peer.write_all(&encode_frame(&NetworkMessage::Goodbye).unwrap())
+            .await
+            .unwrap();
+
+        let mut kinds = Vec::new();
+        for _ in 0..4 {
+            let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+                .await
+                .expect("peer event not published")
+                .unwrap();
+            assert!(event.peer.starts_with("127.0.0.1:"));
+            kinds.push(event.kind);
+        }
+        assert_eq!(
+            kinds,
+            vec![
+                PeerEventKind::Connected { outbound: false },
+                PeerEventKind::HandshakeComplete {
+                    protocol_version: PROTOCOL_VERSION,
+                    height: 0,
+                },
+                PeerEventKind::Misbehavior {
+                    reason: "malformed message".to_string(),
+                    penalty: MALFORMED_MESSAGE_PENALTY,
+                    score: MALFORMED_MESSAGE_PENALTY,
+                    banned: false,
+                },
+                PeerEventKind::Disconnected {
+                    reason: "peer said goodbye".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ping_latency_and_manual_disconnect() {
+        let chain = Blockchain::new(Address::from([1; 32]), 1).unwrap();
+        let node = Arc::new(NetworkNode::new(Arc::new(RwLock::new(chain))));
+        let (mut peer, _server) = handshaken_peer(&node).await;
+        let remote = node.list_peers().await.remove(0);
+
+        node.ping_peers().await;
+        let nonce = loop {
+            match read_frame(&mut peer).await {
+                Some(NetworkMessage::Ping(nonce)) => break nonce,
+                Some(_) => continue,
+                None => panic!("connection closed before the ping"),
+            }
+        };
+        // A stale nonce is ignored
+        peer.write_all(&encode_frame(&NetworkMessage::Pong(nonce.wrapping_add(1))).unwrap())
+            .await
+            .unwrap();
+        peer.write_all(&encode_frame(&NetworkMessage::Pong(nonce)).unwrap())
+            .await
+            .unwrap();
+        for _ in 0..50 {
+            if node.peer_latency(&remote.addr()).is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(node.peer_latency(&remote.addr()).is_some());
+
+        assert!(node.disconnect_peer(&remote).await);
+        assert!(node.list_peers().await.is_empty());
+        assert!(node.peer_latency(&remote.addr()).is_none());
+        assert!(!node.disconnect_peer(&remote).await);
+        assert_eq!(
+            "127.0.0.1:8333".parse::<Node>().unwrap(),
+            Node::new("127.0.0.1".to_string(), 8333)
+        );
+        assert!("127.0.0.1".parse::<Node>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_handshake_records_peer_height() {
+        let chain = Blockchain::new(Address::from([1; 32]), 1).unwrap();
+        let genesis = chain.blocks[0].clone();
+        let node = Arc::new(NetworkNode::new(Arc::new(RwLock::new(chain))));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let peer = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let hello = NetworkMessage::Handshake(Handshake {
+                protocol_version: PROTOCOL_VERSION,
+                network_magic: active_network().magic(),
+                genesis_hash: genesis.hash(),
+                best_height: 42,
+            });
+            let data = bincode::serialize(&hello).unwrap();
+            socket.write_all(&(data.len() as u32).to_be_bytes()).await.unwrap();
+            socket.write_all(&data).await.unwrap();
+            socket
+        });
+
+        node.clone()
+            .connect_peer("127.0.0.1".to_string(), port)
+            .await
+            .unwrap();
+        let mut socket = peer.await.unwrap();
+
+        let addr = format!("127.0.0.1:{}", port);
+        for _ in 0..50 {
+            if node.peer_height(&addr).await.is_some() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(node.peer_height(&addr).await, Some(42));
+        assert_eq!(node.best_peer_height().await, Some(42));
+
+        // A later handshake is penalized rather than taken as a new height
+        let again = NetworkMessage::Handshake(Handshake {
+            best_height: 1_000,
+            ..node.local_handshake().await
+        });
+        let data = bincode::serialize(&again).unwrap();
+        socket.write_all(&(data.len() as u32).to_be_bytes()).await.unwrap();
+        socket.write_all(&data).await.unwrap();
+        for _ in 0..50 {
+            if node.pool.ban_score("127.0.0.1") > 0 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(node.pool.ban_score("127.0.0.1"), MALFORMED_MESSAGE_PENALTY);
+        assert_eq!(node.peer_height(&addr).await, Some(42));
+
+        let sync = node.sync_state().await;
+        assert_eq!(sync.phase, crate::sync::SyncPhase::Syncing);
+        assert_eq!((sync.height, sync.target_height), (0, Some(42)));
+        assert_eq!(sync.remaining(), 42);
+    }
 }
@@ -0,0 +1,257 @@
+//! Per-peer outbound message queues
+//!
+//! Every connection gets one bounded queue per priority class, drained by a
+//! writer task in strict priority order: blocks, then pings, then
+//! transaction relay, then address gossip. A peer that reads slowly fills
+//! its lower-priority queues first, so transaction gossip is dropped long
+//! before block propagation or keepalives are delayed.
+
+use std::collections::VecDeque;
+use tokio::sync::Notify;
+
+/// Priority class of an outbound message, highest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Handshakes, headers and blocks
+    Block,
+    /// Keepalive pings and pongs
+    Ping,
+    /// Transaction relay
+    Transaction,
+    /// Peer address gossip
+    Addr,
+}
+
+/// What happens when a message arrives for a full queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// Discard the oldest queued message to make room
+    DropOldest,
+    /// Discard the new message
+    DropNewest,
+    /// The peer cannot keep up at all; the connection should be closed
+    Disconnect,
+}
+
+impl Priority {
+    pub const ALL: [Priority; 4] = [
+        Priority::Block,
+        Priority::Ping,
+        Priority::Transaction,
+        Priority::Addr,
+    ];
+
+    /// Maximum number of queued messages in this class
+    pub fn capacity(self) -> usize {
+        match self {
+            Priority::Block => 256,
+            Priority::Ping => 8,
+            Priority::Transaction => 1024,
+            Priority::Addr => 32,
+        }
+    }
+
+    /// Missing blocks would leave the peer stuck, so a full block queue ends
+    /// the connection (the peer resyncs on reconnect). Only the latest ping
+    /// and address gossip matter; transaction relay backpressures by
+    /// dropping new announcements.
+    pub fn drop_policy(self) -> DropPolicy {
+        match self {
+            Priority::Block => DropPolicy::Disconnect,
+            Priority::Ping => DropPolicy::DropOldest,
+            Priority::Transaction => DropPolicy::DropNewest,
+            Priority::Addr => DropPolicy::DropOldest,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Result of queueing a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    Queued,
+    /// The queue was full and a message (new or old) was discarded
+    Dropped,
+    /// The queue was full under `DropPolicy::Disconnect`, or already closed
+    Overflow,
+}
+
+#[derive(Default)]
+struct QueueState {
+    queues: [VecDeque<Vec<u8>>; 4],
+    dropped: [u64; 4],
+    closed: bool,
+    /// No new frames; the writer stops once the queued ones are written
+    finishing: bool,
+}
+
+/// Bounded, prioritized queue of encoded frames waiting to be written to one peer
+#[derive(Default)]
+pub struct OutboundQueue {
+    state: parking_lot::Mutex<QueueState>,
+    notify: Notify,
+}
+
+impl OutboundQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue an encoded frame, applying the class's drop policy when full
+    pub fn push(&self, priority: Priority, frame: Vec<u8>) -> PushOutcome {
+        let mut state = self.state.lock();
+        if state.closed || state.finishing {
+            return PushOutcome::Overflow;
+        }
+        let i = priority.index();
+        let mut outcome = PushOutcome::Queued;
+        if state.queues[i].len() >= priority.capacity() {
+            match priority.drop_policy() {
+                DropPolicy::DropOldest => {
+                    state.queues[i].pop_front();
+                    outcome = PushOutcome::Dropped;
+                }
+                DropPolicy::DropNewest => {
+                    state.dropped[i] += 1;
+                    return PushOutcome::Dropped;
+                }
+                DropPolicy::Disconnect => return PushOutcome::Overflow,
+            }
+            state.dropped[i] += 1;
+        }
+        state.queues[i].push_back(frame);
+        drop(state);
+        self.notify.notify_one();
+        outcome
+    }
+
+    /// Wait for the highest-priority queued frame. Returns `None` once the
+    /// queue is closed, or finished and empty.
+    pub async fn pop(&self) -> Option<Vec<u8>> {
+        loop {
+            {
+                let mut state = self.state.lock();
+                if state.closed {
+                    return None;
+                }
+                if let Some(frame) = state.queues.iter_mut().find_map(VecDeque::pop_front) {
+                    return Some(frame);
+                }
+                if state.finishing {
+                    return None;
+                }
+            }
+            self.notify.notified().await;
+        }
+    }
+
+    /// Stop the writer; queued frames are discarded
+    pub fn close(&self) {
+        let mut state = self.state.lock();
+        state.closed = true;
+        state.queues.iter_mut().for_each(VecDeque::clear);
+        drop(state);
+        self.notify.notify_one();
+    }
+
+    /// Accept no more frames but let the writer drain what is queued
+    pub fn finish(&self) {
+        self.state.lock().finishing = true;
+        self.notify.notify_one();
+    }
+
+    /// Frames currently waiting in a class
+    pub fn queued(&self, priority: Priority) -> usize {
+        self.state.lock().queues[priority.index()].len()
+    }
+
+    /// Frames discarded from a class because it was full
+    pub fn dropped(&self, priority: Priority) -> u64 {
+        self.state.lock().dropped[priority.index()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_higher_priority_drains_first() {
+        let queue = OutboundQueue::new();
+        queue.push(Priority::Addr, vec![4]);
+        queue.push(Priority::Transaction, vec![3]);
+        queue.push(Priority::Ping, vec![2]);
+        queue.push(Priority::Block, vec![1]);
+        queue.push(Priority::Block, vec![5]);
+
+        let mut order = Vec::new();
+        for _ in 0..5 {
+            order.push(queue.pop().await.unwrap()[0]);
+        }
+        assert_eq!(order, vec![1, 5, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_full_queues_apply_drop_policy() {
+        let queue = OutboundQueue::new();
+
+        // Transaction relay keeps the oldest announcements
+        for i in 0..Priority::Transaction.capacity() {
+            assert_eq!(
+                queue.push(Priority::Transaction, vec![i as u8]),
+                PushOutcome::Queued
+            );
+        }
+        assert_eq!(
+            queue.push(Priority::Transaction, vec![0xff]),
+            PushOutcome::Dropped
+        );
+        assert_eq!(
+            queue.queued(Priority::Transaction),
+            Priority::Transaction.capacity()
+        );
+        assert_eq!(queue.dropped(Priority::Transaction), 1);
+
+        // Pings keep the newest
+        for i in 0..=Priority::Ping.capacity() {
+            queue.push(Priority::Ping, vec![i as u8]);
+        }
+        assert_eq!(queue.queued(Priority::Ping), Priority::Ping.capacity());
+        assert_eq!(queue.dropped(Priority::Ping), 1);
+
+        // Blocks are never dropped silently
+        for _ in 0..Priority::Block.capacity() {
+            queue.push(Priority::Block, vec![]);
+        }
+        assert_eq!(queue.push(Priority::Block, vec![]), PushOutcome::Overflow);
+    }
+
+    #[tokio::test]
+    async fn test_close_wakes_writer() {
+        let queue = std::sync::Arc::new(OutboundQueue::new());
+        let writer = {
+            let queue = queue.clone();
+            tokio::spawn(async move { queue.pop().await })
+        };
+        tokio::task::yield_now().await;
+        queue.close();
+        assert_eq!(writer.await.unwrap(), None);
+        assert_eq!(queue.push(Priority::Block, vec![1]), PushOutcome::Overflow);
+    }
+
+    #[tokio::test]
+    async fn test_finish_drains_queued_frames() {
+        let queue = OutboundQueue::new();
+        queue.push(Priority::Transaction, vec![2]);
+        queue.push(Priority::Block, vec![1]);
+        queue.finish();
+        assert_eq!(queue.push(Priority::Block, vec![3]), PushOutcome::Overflow);
+
+        assert_eq!(queue.pop().await, Some(vec![1]));
+        assert_eq!(queue.pop().await, Some(vec![2]));
+        assert_eq!(queue.pop().await, None);
+    }
+}
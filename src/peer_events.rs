@@ -0,0 +1,327 @@
+//! Peer lifecycle events
+//!
+//! The network layer publishes an event whenever a peer connects, completes
+//! its handshake, misbehaves or disconnects. Events go out on a broadcast
+//! channel so the node TUI, the WebSocket API and an optional webhook can all
+//! follow the peer set without polling. `PeerTable` folds the stream back
+//! into the current set of peers for display.
+
+use crate::error::ChainError;
+use crate::remote::RemoteNode;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+/// Events buffered per subscriber before slow subscribers start missing them
+pub const PEER_EVENT_CHANNEL_CAPACITY: usize = 512;
+
+/// What happened to a peer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum PeerEventKind {
+    /// A TCP connection was opened (by us when `outbound`)
+    Connected { outbound: bool },
+    /// Both sides exchanged compatible handshakes
+    HandshakeComplete { protocol_version: u32, height: u64 },
+    /// The peer's ban score was raised; `banned` once it crossed the threshold
+    Misbehavior {
+        reason: String,
+        penalty: u32,
+        score: u32,
+        banned: bool,
+    },
+    /// The connection ended
+    Disconnected { reason: String },
+}
+
+/// A lifecycle event of one peer connection
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerEvent {
+    /// `host:port` of the connection
+    pub peer: String,
+    #[serde(flatten)]
+    pub kind: PeerEventKind,
+    /// Unix time the event happened
+    pub at: u64,
+}
+
+impl PeerEvent {
+    pub fn new(peer: String, kind: PeerEventKind, at: u64) -> Self {
+        PeerEvent { peer, kind, at }
+    }
+
+    /// One-line human readable description
+    pub fn describe(&self) -> String {
+        match &self.kind {
+            PeerEventKind::Connected { outbound: true } => format!("🔗 Connected to {}", self.peer),
+            PeerEventKind::Connected { outbound: false } => {
+                format!("📡 Connection from {}", self.peer)
+            }
+            PeerEventKind::HandshakeComplete {
+                protocol_version,
+                height,
+            } => format!(
+                "🤝 Handshake with {} (protocol v{}, height {})",
+                self.peer, protocol_version, height
+            ),
+            PeerEventKind::Misbehavior {
+                reason,
+                score,
+                banned,
+                ..
+            } => format!(
+                "{} {} {}: {} (score {})",
+                if *banned { "⛔" } else { "⚠️ " },
+                self.peer,
+                if *banned { "banned" } else { "misbehaved" },
+                reason,
+                score
+            ),
+            PeerEventKind::Disconnected { reason } => {
+                format!("🔌 Disconnected from {}: {}", self.peer, reason)
+            }
+        }
+    }
+}
+
+/// A connected peer as seen through its events
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerInfo {
+    pub addr: String,
+    pub outbound: bool,
+    /// Unix time the connection opened
+    pub connected_at: u64,
+    /// Protocol version and height from the handshake, once it completed
+    pub protocol_version: Option<u32>,
+    pub height: Option<u64>,
+    /// Current ban score
+    pub score: u32,
+}
+
+/// Column the peers pane is sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PeerSortColumn {
+    #[default]
+    Address,
+    Direction,
+    Height,
+    Score,
+    Connected,
+}
+
+impl PeerSortColumn {
+    pub const ALL: [PeerSortColumn; 5] = [
+        PeerSortColumn::Address,
+        PeerSortColumn::Direction,
+        PeerSortColumn::Height,
+        PeerSortColumn::Score,
+        PeerSortColumn::Connected,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            PeerSortColumn::Address => "Address",
+            PeerSortColumn::Direction => "Direction",
+            PeerSortColumn::Height => "Height",
+            PeerSortColumn::Score => "Score",
+            PeerSortColumn::Connected => "Connected",
+        }
+    }
+
+    /// The column after this one, wrapping around
+    pub fn next(self) -> PeerSortColumn {
+        let i = Self::ALL.iter().position(|c| *c == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    fn compare(self, a: &PeerInfo, b: &PeerInfo) -> Ordering {
+        match self {
+            PeerSortColumn::Address => a.addr.cmp(&b.addr),
+            PeerSortColumn::Direction => a.outbound.cmp(&b.outbound),
+            PeerSortColumn::Height => a.height.cmp(&b.height),
+            PeerSortColumn::Score => a.score.cmp(&b.score),
+            PeerSortColumn::Connected => a.connected_at.cmp(&b.connected_at),
+        }
+    }
+}
+
+/// Currently connected peers, maintained from the event stream
+#[derive(Debug, Default)]
+pub struct PeerTable {
+    peers: HashMap<String, PeerInfo>,
+}
+
+impl PeerTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn apply(&mut self, event: &PeerEvent) {
+        match &event.kind {
+            PeerEventKind::Connected { outbound } => {
+                self.peers.insert(
+                    event.peer.clone(),
+                    PeerInfo {
+                        addr: event.peer.clone(),
+                        outbound: *outbound,
+                        connected_at: event.at,
+                        protocol_version: None,
+                        height: None,
+                        score: 0,
+                    },
+                );
+            }
+            PeerEventKind::HandshakeComplete {
+                protocol_version,
+                height,
+            } => {
+                if let Some(peer) = self.peers.get_mut(&event.peer) {
+                    peer.protocol_version = Some(*protocol_version);
+                    peer.height = Some(*height);
+                }
+            }
+            PeerEventKind::Misbehavior { score, .. } => {
+                if let Some(peer) = self.peers.get_mut(&event.peer) {
+                    peer.score = *score;
+                }
+            }
+            PeerEventKind::Disconnected { .. } => {
+                self.peers.remove(&event.peer);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+
+    /// Peers ordered by `column`, ties broken by address
+    pub fn sorted(&self, column: PeerSortColumn, descending: bool) -> Vec<PeerInfo> {
+        let mut peers: Vec<PeerInfo> = self.peers.values().cloned().collect();
+        peers.sort_by(|a, b| {
+            let order = column.compare(a, b).then_with(|| a.addr.cmp(&b.addr));
+            if descending {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+        peers
+    }
+}
+
+/// Forward every peer event as a JSON `POST` to `url`
+pub fn spawn_webhook(
+    mut events: broadcast::Receiver<PeerEvent>,
+    url: &str,
+) -> Result<JoinHandle<()>, ChainError> {
+    let client = RemoteNode::new(url)?;
+
+    Ok(tokio::spawn(async move {
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("⚠️  Peer event webhook missed {} event(s)", missed);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+
+            let client = client.clone();
+            let result = tokio::task::spawn_blocking(move || client.post_json("", &event)).await;
+            match result {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => warn!("⚠️  Peer event webhook failed: {}", e),
+                Err(e) => warn!("⚠️  Peer event webhook task failed: {}", e),
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(peer: &str, kind: PeerEventKind, at: u64) -> PeerEvent {
+        PeerEvent::new(peer.to_string(), kind, at)
+    }
+
+    #[test]
+    fn test_table_follows_lifecycle_and_sorts() {
+        let mut table = PeerTable::new();
+        table.apply(&event(
+            "10.0.0.2:8333",
+            PeerEventKind::Connected { outbound: true },
+            5,
+        ));
+        table.apply(&event(
+            "10.0.0.1:8333",
+            PeerEventKind::Connected { outbound: false },
+            9,
+        ));
+        table.apply(&event(
+            "10.0.0.2:8333",
+            PeerEventKind::HandshakeComplete {
+                protocol_version: 1,
+                height: 40,
+            },
+            6,
+        ));
+        table.apply(&event(
+            "10.0.0.1:8333",
+            PeerEventKind::Misbehavior {
+                reason: "malformed message".to_string(),
+                penalty: 10,
+                score: 10,
+                banned: false,
+            },
+            10,
+        ));
+
+        let by_address = table.sorted(PeerSortColumn::Address, false);
+        assert_eq!(by_address[0].addr, "10.0.0.1:8333");
+        assert_eq!(by_address[0].score, 10);
+        let by_height = table.sorted(PeerSortColumn::Height, true);
+        assert_eq!(by_height[0].height, Some(40));
+        assert_eq!(by_height[1].height, None);
+
+        table.apply(&event(
+            "10.0.0.2:8333",
+            PeerEventKind::Disconnected {
+                reason: "peer said goodbye".to_string(),
+            },
+            11,
+        ));
+        assert_eq!(table.len(), 1);
+        assert_eq!(PeerSortColumn::Connected.next(), PeerSortColumn::Address);
+    }
+
+    #[test]
+    fn test_event_json_shape() {
+        let json = serde_json::to_value(event(
+            "10.0.0.1:8333",
+            PeerEventKind::Disconnected {
+                reason: "local shutdown".to_string(),
+            },
+            7,
+        ))
+        .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "peer": "10.0.0.1:8333",
+                "event": "disconnected",
+                "reason": "local shutdown",
+                "at": 7
+            })
+        );
+    }
+}
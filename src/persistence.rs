@@ -1,259 +1,1203 @@
 //! Database persistence layer for TrinityChain
 
-use crate::blockchain::{Block, BlockHeader, Blockchain, TriangleState};
+use crate::analytics::BlockStats;
+use crate::blockchain::{
+    Block, BlockHeader, Blockchain, TriangleState, TxStatus, COINBASE_MATURITY,
+};
+use crate::crypto::Address;
+use crate::discovery::{BanEntry, PeerRecord};
 use crate::error::ChainError;
+use crate::events::EventBus;
 use crate::geometry::Triangle;
 use crate::mempool::Mempool;
+use crate::migrations::{run_migrations, schema_version};
+use crate::network::Node;
+use crate::snapshot::{Snapshot, SnapshotInfo};
 use crate::transaction::Transaction;
-use rusqlite::{params, Connection};
-use std::collections::HashMap;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::time::Duration;
+use tracing::warn;
+
+/// Metadata key recording the height a database was bootstrapped from
+const SNAPSHOT_HEIGHT_KEY: &str = "snapshot_height";
+
+/// Metadata key recording the highest block whose body has been pruned
+const PRUNED_HEIGHT_KEY: &str = "pruned_height";
+
+/// Metadata key holding the hash of the block the stored UTXO set is at,
+/// which the next block's delta must build on
+const UTXO_TIP_KEY: &str = "utxo_tip";
+
+/// Blocks between full UTXO set checkpoints; the blocks in between are
+/// stored as per-block deltas
+pub const UTXO_CHECKPOINT_INTERVAL: u64 = 1_000;
+
+/// Blocks applied per commit by `Database::apply_blocks` during initial
+/// block download
+pub const IBD_COMMIT_BLOCKS: usize = 500;
+
+/// How long a write waits for another connection's lock before failing
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Fewest recent block bodies a pruned database keeps, so peers catching up
+/// from a recent height can still be served
+pub const MIN_PRUNE_DEPTH: u64 = 100;
+
+/// `SCHEMA_FLAGS` entry switching the address index off (on when absent)
+pub const ADDRESS_INDEX_FLAG: &str = "address_index";
+
+/// `SCHEMA_FLAGS` entry switching the memo index on (off when absent)
+pub const MEMO_INDEX_FLAG: &str = "memo_index";
+
+/// Shortest memo search accepted, so a query cannot list every memo
+pub const MIN_MEMO_QUERY_LEN: usize = 3;
+
+// ============================================================================
+// Settings
+// ============================================================================
+
+/// A key in the settings table together with the type stored under it.
+/// Values are stored as JSON, so adding fields to a stored struct stays
+/// readable as long as they have serde defaults.
+pub struct Setting<T> {
+    pub key: &'static str,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T> Setting<T> {
+    pub const fn new(key: &'static str) -> Self {
+        Self {
+            key,
+            _type: PhantomData,
+        }
+    }
+}
+
+/// Height of the tip after the last block accepted from a peer
+pub const LAST_SYNC_HEIGHT: Setting<u64> = Setting::new("last_sync_height");
+
+/// Optional schema behaviours switched on for this database, by name
+pub const SCHEMA_FLAGS: Setting<BTreeMap<String, bool>> = Setting::new("schema_flags");
+
+/// Height at which each consensus feature activated, by feature name
+pub const FEATURE_ACTIVATIONS: Setting<BTreeMap<String, u64>> =
+    Setting::new("feature_activations");
+
+/// Mempool contents written at shutdown, re-validated on the next start
+pub const PENDING_TRANSACTIONS: Setting<Vec<Transaction>> = Setting::new("pending_transactions");
+
+/// Hex addresses each Telegram chat asked `/watch` to notify it about
+pub const ADDRESS_WATCHES: Setting<BTreeMap<i64, BTreeSet<String>>> =
+    Setting::new("address_watches");
+
+/// A confirmed transaction found through the address index
+#[derive(Debug, Clone)]
+pub struct IndexedTransaction {
+    pub transaction: Transaction,
+    pub block_height: u64,
+    /// Containing block timestamp (milliseconds)
+    pub timestamp: u64,
+}
+
+/// Size of the memo index, for the privacy and disk-usage warnings shown
+/// when it is in use
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoIndexStats {
+    /// Indexed transactions carrying a memo
+    pub entries: u64,
+    /// Total memo text stored, in bytes
+    pub bytes: u64,
+}
 
 pub struct Database {
     conn: Connection,
+    /// Load chains whatever their genesis block
+    custom_genesis: bool,
 }
 
 impl Database {
-    pub fn open(path: &str) -> Result<Self, ChainError> {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ChainError> {
         let conn = Connection::open(path)
             .map_err(|e| ChainError::DatabaseError(format!("Failed to open database: {}", e)))?;
 
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS blocks (
-                height INTEGER PRIMARY KEY,
-                hash BLOB NOT NULL,
-                previous_hash BLOB NOT NULL,
-                timestamp INTEGER NOT NULL,
-                difficulty INTEGER NOT NULL,
-                nonce INTEGER NOT NULL,
-                merkle_root BLOB NOT NULL,
-                transactions TEXT NOT NULL
-            )",
-            [],
-        )
-        .map_err(|e| ChainError::DatabaseError(format!("Failed to create blocks table: {}", e)))?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS utxo_set (
-                hash BLOB PRIMARY KEY,
-                triangle_data TEXT NOT NULL
-            )",
-            [],
-        )
-        .map_err(|e| {
-            ChainError::DatabaseError(format!("Failed to create utxo_set table: {}", e))
-        })?;
+        // Readers (the API, wallet tools) no longer block the writer, and a
+        // commit only syncs the log. In-memory databases stay in memory mode.
+        conn.query_row("PRAGMA journal_mode = WAL", [], |row| {
+            row.get::<_, String>(0)
+        })
+        .map_err(|e| ChainError::DatabaseError(format!("Failed to enable WAL: {}", e)))?;
+        conn.pragma_update(None, "synchronous", "NORMAL")
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to set synchronous: {}", e)))?;
+        conn.busy_timeout(BUSY_TIMEOUT)
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to set busy timeout: {}", e)))?;
 
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS metadata (
-                key TEXT PRIMARY KEY,
-                value TEXT NOT NULL
-            )",
-            [],
-        )
-        .map_err(|e| {
-            ChainError::DatabaseError(format!("Failed to create metadata table: {}", e))
-        })?;
+        run_migrations(&conn)?;
+        Ok(Database {
+            conn,
+            custom_genesis: false,
+        })
+    }
 
-        Ok(Database { conn })
+    /// Load a chain that starts from a custom genesis block (see
+    /// [`Blockchain::new`]) on any network, not only regtest
+    pub fn with_custom_genesis(mut self) -> Self {
+        self.custom_genesis = true;
+        self
     }
 
-    pub fn save_block(&self, block: &Block) -> Result<(), ChainError> {
-        let transactions_json = serde_json::to_string(&block.transactions).map_err(|e| {
-            ChainError::DatabaseError(format!("Failed to serialize transactions: {}", e))
-        })?;
+    fn begin(&self) -> Result<rusqlite::Transaction<'_>, ChainError> {
+        self.conn
+            .unchecked_transaction()
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to start transaction: {}", e)))
+    }
+
+    /// Schema version of the open database
+    pub fn schema_version(&self) -> Result<u32, ChainError> {
+        schema_version(&self.conn)
+    }
+
+    /// Rebuild the address → transaction index from the stored blocks
+    pub fn rebuild_address_index(&self) -> Result<(), ChainError> {
+        let tx = self.begin()?;
+
+        rebuild_address_index_in(&tx)?;
 
-        self.conn.execute(
-            "INSERT OR REPLACE INTO blocks (height, hash, previous_hash, timestamp, difficulty, nonce, merkle_root, transactions)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        commit(tx)
+    }
+
+    /// Whether blocks are indexed by address (see `set_address_index`)
+    pub fn address_index_enabled(&self) -> bool {
+        address_index_enabled_in(&self.conn)
+    }
+
+    /// Turn the address index on or off for this database, e.g. to match a
+    /// node role. Disabling drops the index; enabling rebuilds it from the
+    /// stored blocks. Returns whether the setting changed.
+    pub fn set_address_index(&self, enabled: bool) -> Result<bool, ChainError> {
+        if self.address_index_enabled() == enabled {
+            return Ok(false);
+        }
+        let mut flags = self.get_setting(&SCHEMA_FLAGS)?.unwrap_or_default();
+        flags.insert(ADDRESS_INDEX_FLAG.to_string(), enabled);
+        self.set_setting(&SCHEMA_FLAGS, &flags)?;
+
+        if enabled {
+            self.rebuild_address_index()?;
+        } else {
+            self.conn
+                .execute("DELETE FROM address_txs", [])
+                .map_err(|e| {
+                    ChainError::DatabaseError(format!("Failed to clear address index: {}", e))
+                })?;
+        }
+        Ok(true)
+    }
+
+    fn require_address_index(&self) -> Result<(), ChainError> {
+        if self.address_index_enabled() {
+            Ok(())
+        } else {
+            Err(ChainError::DatabaseError(
+                "The address index is disabled on this node".to_string(),
+            ))
+        }
+    }
+
+    /// Confirmed transactions touching `address`, newest first
+    pub fn get_transactions_for_address(
+        &self,
+        address: &Address,
+        page: u64,
+        limit: u64,
+    ) -> Result<Vec<IndexedTransaction>, ChainError> {
+        self.require_address_index()?;
+        self.indexed_transactions(
+            "SELECT a.height, a.tx_index, b.timestamp, b.transactions
+             FROM address_txs a JOIN blocks b ON b.height = a.height
+             WHERE a.address = ?1
+             ORDER BY a.height DESC, a.tx_index DESC
+             LIMIT ?2 OFFSET ?3",
             params![
-                block.header.height as i64,
-                block.hash().to_vec(),
-                block.header.previous_hash.to_vec(),
-                block.header.timestamp,
-                block.header.difficulty as i64,
-                block.header.nonce as i64,
-                block.header.merkle_root.to_vec(),
-                transactions_json,
+                address.to_vec(),
+                limit as i64,
+                page.saturating_mul(limit) as i64
             ],
-        ).map_err(|e| ChainError::DatabaseError(format!("Failed to save block: {}", e)))?;
-
-        Ok(())
+        )
     }
 
-    pub fn save_utxo_set(&self, state: &TriangleState) -> Result<(), ChainError> {
-        // Use a transaction for atomic UTXO set update
-        let tx = self.conn.unchecked_transaction().map_err(|e| {
-            ChainError::DatabaseError(format!("Failed to start transaction: {}", e))
-        })?;
+    /// Runs an index query selecting height, tx_index, block timestamp and
+    /// block transactions, and picks each row's transaction out of its block
+    fn indexed_transactions(
+        &self,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<IndexedTransaction>, ChainError> {
+        let mut stmt = self
+            .conn
+            .prepare(sql)
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to prepare query: {}", e)))?;
 
-        tx.execute("DELETE FROM utxo_set", [])
-            .map_err(|e| ChainError::DatabaseError(format!("Failed to clear utxo_set: {}", e)))?;
+        let rows = stmt
+            .query_map(params, |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            })
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to query index: {}", e)))?;
 
-        for (hash, triangle) in &state.utxo_set {
-            let triangle_json = serde_json::to_string(triangle).map_err(|e| {
-                ChainError::DatabaseError(format!("Failed to serialize triangle: {}", e))
-            })?;
+        let mut entries = Vec::new();
+        for row in rows {
+            let (height, tx_index, timestamp, transactions_json) =
+                row.map_err(|e| ChainError::DatabaseError(format!("Failed to read row: {}", e)))?;
+            let mut transactions: Vec<Transaction> = serde_json::from_str(&transactions_json)
+                .map_err(|e| {
+                    ChainError::DatabaseError(format!("Failed to deserialize transactions: {}", e))
+                })?;
+            if (tx_index as usize) < transactions.len() {
+                entries.push(IndexedTransaction {
+                    transaction: transactions.swap_remove(tx_index as usize),
+                    block_height: height as u64,
+                    timestamp: timestamp as u64,
+                });
+            }
+        }
 
-            tx.execute(
-                "INSERT INTO utxo_set (hash, triangle_data) VALUES (?1, ?2)",
-                params![hash.to_vec(), triangle_json],
+        Ok(entries)
+    }
+
+    /// Number of confirmed transactions touching `address`
+    pub fn count_transactions_for_address(&self, address: &Address) -> Result<u64, ChainError> {
+        self.require_address_index()?;
+        self.conn
+            .query_row(
+                "SELECT COUNT(*) FROM address_txs WHERE address = ?1",
+                params![address.to_vec()],
+                |row| row.get::<_, i64>(0),
             )
-            .map_err(|e| ChainError::DatabaseError(format!("Failed to save UTXO: {}", e)))?;
+            .map(|count| count as u64)
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to count transactions: {}", e)))
+    }
+
+    /// Whether memos are indexed for search (see `set_memo_index`)
+    pub fn memo_index_enabled(&self) -> bool {
+        schema_flag_in(&self.conn, MEMO_INDEX_FLAG, false)
+    }
+
+    /// Turn the memo index on or off for this database. Enabling indexes
+    /// the memos of every stored block; disabling drops them. Returns
+    /// whether the setting changed.
+    pub fn set_memo_index(&self, enabled: bool) -> Result<bool, ChainError> {
+        if self.memo_index_enabled() == enabled {
+            return Ok(false);
         }
+        let mut flags = self.get_setting(&SCHEMA_FLAGS)?.unwrap_or_default();
+        flags.insert(MEMO_INDEX_FLAG.to_string(), enabled);
+        self.set_setting(&SCHEMA_FLAGS, &flags)?;
+        self.rebuild_memo_index()?;
+        Ok(true)
+    }
 
-        tx.commit().map_err(|e| {
-            ChainError::DatabaseError(format!("Failed to commit transaction: {}", e))
+    /// Rebuild the memo index from the stored blocks
+    pub fn rebuild_memo_index(&self) -> Result<(), ChainError> {
+        let tx = self.begin()?;
+
+        tx.execute("DELETE FROM memo_txs", [])
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to clear memo_txs: {}", e)))?;
+        for_each_stored_block(&tx, |height, transactions| {
+            index_memos(&tx, height, transactions)
         })?;
 
+        commit(tx)
+    }
+
+    fn require_memo_index(&self, query: &str) -> Result<(), ChainError> {
+        if !self.memo_index_enabled() {
+            return Err(ChainError::DatabaseError(
+                "The memo index is disabled on this node".to_string(),
+            ));
+        }
+        if query.chars().count() < MIN_MEMO_QUERY_LEN {
+            return Err(ChainError::InvalidTransaction(format!(
+                "Memo searches need at least {} characters",
+                MIN_MEMO_QUERY_LEN
+            )));
+        }
         Ok(())
     }
 
-    pub fn load_utxo_set(&self) -> Result<TriangleState, ChainError> {
-        let mut utxo_set = HashMap::new();
+    /// Confirmed transactions whose memo contains `query` (ignoring ASCII
+    /// case), newest first
+    pub fn search_memos(
+        &self,
+        query: &str,
+        page: u64,
+        limit: u64,
+    ) -> Result<Vec<IndexedTransaction>, ChainError> {
+        self.require_memo_index(query)?;
+        self.indexed_transactions(
+            "SELECT m.height, m.tx_index, b.timestamp, b.transactions
+             FROM memo_txs m JOIN blocks b ON b.height = m.height
+             WHERE m.memo LIKE ?1 ESCAPE '\\'
+             ORDER BY m.height DESC, m.tx_index DESC
+             LIMIT ?2 OFFSET ?3",
+            params![
+                like_pattern(query),
+                limit as i64,
+                page.saturating_mul(limit) as i64
+            ],
+        )
+    }
 
-        let mut stmt = self
-            .conn
-            .prepare("SELECT hash, triangle_data FROM utxo_set")
-            .map_err(|e| ChainError::DatabaseError(format!("Failed to prepare query: {}", e)))?;
+    /// Number of confirmed transactions whose memo contains `query`
+    pub fn count_memo_matches(&self, query: &str) -> Result<u64, ChainError> {
+        self.require_memo_index(query)?;
+        self.conn
+            .query_row(
+                "SELECT COUNT(*) FROM memo_txs WHERE memo LIKE ?1 ESCAPE '\\'",
+                params![like_pattern(query)],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|count| count as u64)
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to count memos: {}", e)))
+    }
 
-        let rows = stmt
-            .query_map([], |row| {
-                let hash_bytes: Vec<u8> = row.get(0)?;
-                let triangle_json: String = row.get(1)?;
-                Ok((hash_bytes, triangle_json))
+    /// How many memos the index holds and how much text they take up
+    pub fn memo_index_stats(&self) -> Result<MemoIndexStats, ChainError> {
+        self.conn
+            .query_row(
+                "SELECT COUNT(*), COALESCE(SUM(LENGTH(CAST(memo AS BLOB))), 0) FROM memo_txs",
+                [],
+                |row| {
+                    Ok(MemoIndexStats {
+                        entries: row.get::<_, i64>(0)? as u64,
+                        bytes: row.get::<_, i64>(1)? as u64,
+                    })
+                },
+            )
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to read memo index: {}", e)))
+    }
+
+    /// Height of the stored block containing a transaction, from the
+    /// transaction index
+    pub fn transaction_height(&self, hash: &[u8; 32]) -> Result<Option<u64>, ChainError> {
+        self.conn
+            .query_row(
+                "SELECT height FROM tx_blocks WHERE tx_hash = ?1",
+                params![hash.to_vec()],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|height| Some(height as u64))
+            .or_else(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => Ok(None),
+                e => Err(ChainError::DatabaseError(format!("Failed to query tx index: {}", e))),
             })
-            .map_err(|e| ChainError::DatabaseError(format!("Failed to query UTXO set: {}", e)))?;
+    }
 
-        for row_result in rows {
-            let (hash_bytes, triangle_json) = row_result
-                .map_err(|e| ChainError::DatabaseError(format!("Failed to read row: {}", e)))?;
+    /// Status of a transaction as of the last flush: confirmed if the
+    /// transaction index has it, pending if it was in the saved mempool
+    pub fn transaction_status(&self, hash: &[u8; 32]) -> Result<TxStatus, ChainError> {
+        if let Some(height) = self.transaction_height(hash)? {
+            let (block_hash, tip_height) = self
+                .conn
+                .query_row(
+                    "SELECT hash, (SELECT MAX(height) FROM blocks) FROM blocks WHERE height = ?1",
+                    params![height as i64],
+                    |row| Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, i64>(1)?)),
+                )
+                .map_err(|e| ChainError::DatabaseError(format!("Failed to query block: {}", e)))?;
+            let block_hash: [u8; 32] = block_hash.try_into().map_err(|_| {
+                ChainError::DatabaseError(format!("Corrupt hash of block #{}", height))
+            })?;
+            return Ok(TxStatus::confirmed(height, &block_hash, tip_height as u64));
+        }
+        let pending = self.get_setting(&PENDING_TRANSACTIONS)?.unwrap_or_default();
+        if pending.iter().any(|tx| tx.hash() == *hash) {
+            return Ok(TxStatus::Pending);
+        }
+        Ok(TxStatus::Unknown)
+    }
+
+    /// Save a block and its index entries in one transaction
+    pub fn save_block(&self, block: &Block) -> Result<(), ChainError> {
+        let tx = self.begin()?;
+        write_block(&tx, block)?;
+        commit(tx)
+    }
 
-            let mut hash = [0u8; 32];
-            hash.copy_from_slice(&hash_bytes);
+    pub fn save_utxo_set(&self, state: &TriangleState) -> Result<(), ChainError> {
+        let tx = self.begin()?;
+        write_utxo_set(&tx, state, None)?;
+        commit(tx)
+    }
 
-            let triangle: Triangle = serde_json::from_str(&triangle_json).map_err(|e| {
-                ChainError::DatabaseError(format!("Failed to deserialize triangle: {}", e))
+    /// The UTXO set at the stored tip: the last checkpoint with every
+    /// block delta since applied in height order. Coinbase heights are those
+    /// recorded with the unspent triangles; pruning them to the ones still
+    /// immature is left to the caller.
+    pub fn load_utxo_at_tip(&self) -> Result<TriangleState, ChainError> {
+        let mut utxo_set = HashMap::new();
+        let mut coinbase_heights = HashMap::new();
+
+        for sql in [
+            "SELECT hash, triangle_data, coinbase_height FROM utxo_set",
+            "SELECT hash, triangle_data, coinbase_height FROM utxo_deltas ORDER BY height ASC",
+        ] {
+            let mut stmt = self.conn.prepare(sql).map_err(|e| {
+                ChainError::DatabaseError(format!("Failed to prepare query: {}", e))
             })?;
 
-            utxo_set.insert(hash, triangle);
+            let rows = stmt
+                .query_map([], |row| {
+                    let hash_bytes: Vec<u8> = row.get(0)?;
+                    let triangle_json: Option<String> = row.get(1)?;
+                    let coinbase_height: Option<i64> = row.get(2)?;
+                    Ok((hash_bytes, triangle_json, coinbase_height))
+                })
+                .map_err(|e| {
+                    ChainError::DatabaseError(format!("Failed to query UTXO set: {}", e))
+                })?;
+
+            for row_result in rows {
+                let (hash_bytes, triangle_json, coinbase_height) = row_result
+                    .map_err(|e| ChainError::DatabaseError(format!("Failed to read row: {}", e)))?;
+
+                let mut hash = [0u8; 32];
+                hash.copy_from_slice(&hash_bytes);
+
+                // A delta without a triangle spent it
+                match triangle_json {
+                    Some(json) => {
+                        utxo_set.insert(hash, parse_triangle(&json)?);
+                        if let Some(height) = coinbase_height {
+                            coinbase_heights.insert(hash, height as u64);
+                        }
+                    }
+                    None => {
+                        utxo_set.remove(&hash);
+                        coinbase_heights.remove(&hash);
+                    }
+                }
+            }
         }
 
         Ok(TriangleState {
             utxo_set,
             address_balances: HashMap::new(), // Will be rebuilt by caller
+            coinbase_heights,
         })
     }
 
     pub fn save_difficulty(&self, difficulty: u64) -> Result<(), ChainError> {
-        self.conn
-            .execute(
-                "INSERT OR REPLACE INTO metadata (key, value) VALUES ('difficulty', ?1)",
-                params![difficulty.to_string()],
-            )
-            .map_err(|e| ChainError::DatabaseError(format!("Failed to save difficulty: {}", e)))?;
-
-        Ok(())
+        write_difficulty(&self.conn, difficulty)
     }
 
     /// Atomically saves a block and the associated blockchain state
-    /// This ensures database consistency by wrapping all operations in a transaction
+    /// This ensures database consistency by wrapping all operations in a transaction.
+    /// `state` is only written in full at checkpoints; otherwise the block's
+    /// UTXO delta is stored.
     pub fn save_blockchain_state(
         &self,
         block: &Block,
         state: &TriangleState,
         difficulty: u64,
     ) -> Result<(), ChainError> {
-        let tx = self.conn.unchecked_transaction().map_err(|e| {
-            ChainError::DatabaseError(format!("Failed to start transaction: {}", e))
-        })?;
+        let tx = self.begin()?;
+        write_block_with_state(&tx, block, state)?;
+        write_difficulty(&tx, difficulty)?;
+        commit(tx)
+    }
 
-        // Save block
-        let transactions_json = serde_json::to_string(&block.transactions).map_err(|e| {
-            ChainError::DatabaseError(format!("Failed to serialize transactions: {}", e))
+    /// Apply `blocks` to `chain` and store them, committing the blocks and
+    /// their UTXO deltas every `commit_every` blocks instead of after each
+    /// one. Meant for initial block download, where per-block commits make
+    /// sync IO-bound. Stops at the first invalid block, after storing every
+    /// block applied before it. Returns the number of blocks applied.
+    ///
+    /// A block that applies but fails to store is rolled back alone and the
+    /// blocks before it are committed; the `DatabaseError` returned says how
+    /// many were stored, and `chain` is then one block ahead of the database
+    /// until `flush_blockchain` catches it up.
+    pub fn apply_blocks(
+        &self,
+        chain: &mut Blockchain,
+        blocks: impl IntoIterator<Item = Block>,
+        commit_every: usize,
+    ) -> Result<usize, ChainError> {
+        let commit_every = commit_every.max(1);
+        let mut applied = 0;
+        let mut pending = 0;
+        let mut tx = self.begin()?;
+        let mut result = Ok(());
+        for block in blocks {
+            if let Err(e) = chain.apply_block(block) {
+                result = Err(e);
+                break;
+            }
+            if let Some(block) = chain.blocks.last() {
+                if let Err(e) = write_block_savepoint(&mut tx, block, &chain.state) {
+                    commit(tx)?;
+                    return Err(ChainError::DatabaseError(format!(
+                        "Stored {} block(s); block #{} was applied but not stored: {}",
+                        applied, block.header.height, e
+                    )));
+                }
+            }
+            applied += 1;
+            pending += 1;
+            if pending == commit_every {
+                write_difficulty(&tx, chain.difficulty as u64)?;
+                commit(tx)?;
+                tx = self.begin()?;
+                pending = 0;
+            }
+        }
+        if pending > 0 {
+            write_difficulty(&tx, chain.difficulty as u64)?;
+        }
+        commit(tx)?;
+        result.map(|()| applied)
+    }
+
+    /// Bring the stored chain in line with an in-memory one: blocks the
+    /// database is missing (or holds from a branch since reorganized away)
+    /// are written, followed by the UTXO set, difficulty and pending
+    /// transactions, all in one transaction. Returns the number of blocks
+    /// written.
+    pub fn flush_blockchain(&self, chain: &Blockchain) -> Result<usize, ChainError> {
+        let Some(tip) = chain.blocks.last() else {
+            return Ok(0);
+        };
+        let stored = self.stored_block_hashes()?;
+
+        // First height whose stored block differs from the in-memory chain
+        let first_new = chain
+            .blocks
+            .iter()
+            .position(|block| stored.get(&block.header.height) != Some(&block.hash()))
+            .unwrap_or(chain.blocks.len());
+        // Stale blocks at or above that height leave stale address index rows
+        let replaced_from = chain
+            .blocks
+            .get(first_new)
+            .map_or(tip.header.height + 1, |block| block.header.height);
+        let replaced = stored.keys().any(|&height| height >= replaced_from);
+
+        let tx = self.begin()?;
+        for table in ["blocks", "tx_blocks", "memo_txs", "utxo_deltas"] {
+            tx.execute(
+                &format!("DELETE FROM {} WHERE height > ?1", table),
+                params![tip.header.height as i64],
+            )
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to delete blocks: {}", e)))?;
+        }
+        let written = chain.blocks.len() - first_new;
+        if written > 0 {
+            // Blocks extending the stored chain go in as deltas; after a
+            // reorganization the whole set is checkpointed instead
+            let mut checkpoint = replaced;
+            for block in &chain.blocks[first_new..] {
+                if checkpoint {
+                    write_block(&tx, block)?;
+                } else if !write_block_delta(&tx, block)? {
+                    checkpoint = true;
+                }
+            }
+            if checkpoint {
+                write_utxo_set(&tx, &chain.state, Some(&tip.hash()))?;
+            }
+            write_difficulty(&tx, chain.difficulty as u64)?;
+        }
+        if replaced {
+            rebuild_address_index_in(&tx)?;
+        }
+        commit(tx)?;
+
+        self.set_setting(&PENDING_TRANSACTIONS, &chain.mempool.get_all_transactions())?;
+        Ok(written)
+    }
+
+    /// Hash of every stored block, by height
+    fn stored_block_hashes(&self) -> Result<HashMap<u64, [u8; 32]>, ChainError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT height, hash FROM blocks")
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to prepare query: {}", e)))?;
+        let rows = stmt
+            .query_map([], |row| {
+                let height: i64 = row.get(0)?;
+                let hash: Vec<u8> = row.get(1)?;
+                Ok((height as u64, hash))
+            })
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to query blocks: {}", e)))?;
+
+        let mut hashes = HashMap::new();
+        for row in rows {
+            let (height, hash) =
+                row.map_err(|e| ChainError::DatabaseError(format!("Failed to read row: {}", e)))?;
+            if let Ok(hash) = hash.try_into() {
+                hashes.insert(height, hash);
+            }
+        }
+        Ok(hashes)
+    }
+
+    /// Persist per-block analytics, replacing any existing rows at the same heights
+    pub fn save_block_stats(&self, stats: &[BlockStats]) -> Result<(), ChainError> {
+        let tx = self.begin()?;
+
+        for entry in stats {
+            let stats_json = serde_json::to_string(entry).map_err(|e| {
+                ChainError::DatabaseError(format!("Failed to serialize block stats: {}", e))
+            })?;
+            tx.execute(
+                "INSERT OR REPLACE INTO analytics_blocks (height, stats) VALUES (?1, ?2)",
+                params![entry.height as i64, stats_json],
+            )
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to save block stats: {}", e)))?;
+        }
+
+        commit(tx)
+    }
+
+    /// Load all persisted per-block analytics, ordered by height
+    pub fn load_block_stats(&self) -> Result<Vec<BlockStats>, ChainError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT stats FROM analytics_blocks ORDER BY height ASC")
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to prepare query: {}", e)))?;
+
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to query block stats: {}", e)))?;
+
+        let mut stats = Vec::new();
+        for row in rows {
+            let stats_json =
+                row.map_err(|e| ChainError::DatabaseError(format!("Failed to read row: {}", e)))?;
+            stats.push(serde_json::from_str(&stats_json).map_err(|e| {
+                ChainError::DatabaseError(format!("Failed to deserialize block stats: {}", e))
+            })?);
+        }
+
+        Ok(stats)
+    }
+
+    /// Insert or replace a peer record
+    pub fn save_peer(&self, peer: &PeerRecord) -> Result<(), ChainError> {
+        let peer_json = serde_json::to_string(peer)
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to serialize peer: {}", e)))?;
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO peers (addr, peer) VALUES (?1, ?2)",
+                params![peer.node.addr(), peer_json],
+            )
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to save peer: {}", e)))?;
+        Ok(())
+    }
+
+    /// Store gossiped peers not known yet, in one transaction, while fewer
+    /// than `max_stored` peers are stored. Known peers are left untouched.
+    /// Returns how many were added.
+    pub fn add_peers(&self, nodes: &[Node], max_stored: usize) -> Result<usize, ChainError> {
+        let tx = self.begin()?;
+        let stored: i64 = tx
+            .query_row("SELECT COUNT(*) FROM peers", [], |row| row.get(0))
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to count peers: {}", e)))?;
+        let mut room = max_stored.saturating_sub(stored as usize);
+        let mut added = 0;
+        for node in nodes {
+            if room == 0 {
+                break;
+            }
+            let peer_json = serde_json::to_string(&PeerRecord::new(node.clone())).map_err(|e| {
+                ChainError::DatabaseError(format!("Failed to serialize peer: {}", e))
+            })?;
+            let inserted = tx
+                .execute(
+                    "INSERT OR IGNORE INTO peers (addr, peer) VALUES (?1, ?2)",
+                    params![node.addr(), peer_json],
+                )
+                .map_err(|e| ChainError::DatabaseError(format!("Failed to save peer: {}", e)))?;
+            room -= inserted;
+            added += inserted;
+        }
+        commit(tx)?;
+        Ok(added)
+    }
+
+    /// Look up a single peer by `host:port`
+    pub fn get_peer(&self, addr: &str) -> Result<Option<PeerRecord>, ChainError> {
+        let peer_json: Option<String> = self
+            .conn
+            .query_row("SELECT peer FROM peers WHERE addr = ?1", params![addr], |row| {
+                row.get(0)
+            })
+            .map(Some)
+            .or_else(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => Ok(None),
+                e => Err(ChainError::DatabaseError(format!("Failed to query peer: {}", e))),
+            })?;
+
+        peer_json
+            .map(|json| {
+                serde_json::from_str(&json).map_err(|e| {
+                    ChainError::DatabaseError(format!("Failed to deserialize peer: {}", e))
+                })
+            })
+            .transpose()
+    }
+
+    /// Load every known peer
+    pub fn load_peers(&self) -> Result<Vec<PeerRecord>, ChainError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT peer FROM peers ORDER BY addr ASC")
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to prepare query: {}", e)))?;
+
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to query peers: {}", e)))?;
+
+        let mut peers = Vec::new();
+        for row in rows {
+            let peer_json =
+                row.map_err(|e| ChainError::DatabaseError(format!("Failed to read row: {}", e)))?;
+            peers.push(serde_json::from_str(&peer_json).map_err(|e| {
+                ChainError::DatabaseError(format!("Failed to deserialize peer: {}", e))
+            })?);
+        }
+
+        Ok(peers)
+    }
+
+    /// Insert or replace a host ban
+    pub fn save_ban(&self, ban: &BanEntry) -> Result<(), ChainError> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO bans (host, banned_until, reason) VALUES (?1, ?2, ?3)",
+                params![ban.host, ban.banned_until as i64, ban.reason],
+            )
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to save ban: {}", e)))?;
+        Ok(())
+    }
+
+    /// Load the bans still active at `now`, deleting the expired ones
+    pub fn load_bans(&self, now: u64) -> Result<Vec<BanEntry>, ChainError> {
+        self.conn
+            .execute("DELETE FROM bans WHERE banned_until <= ?1", params![now as i64])
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to prune bans: {}", e)))?;
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT host, banned_until, reason FROM bans ORDER BY host ASC")
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to prepare query: {}", e)))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(BanEntry {
+                    host: row.get(0)?,
+                    banned_until: row.get::<_, i64>(1)?.max(0) as u64,
+                    reason: row.get(2)?,
+                })
+            })
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to query bans: {}", e)))?;
+
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to read row: {}", e)))
+    }
+
+    /// Read a setting; `None` if it was never set
+    pub fn get_setting<T: DeserializeOwned>(
+        &self,
+        setting: &Setting<T>,
+    ) -> Result<Option<T>, ChainError> {
+        let value: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT value FROM settings WHERE key = ?1",
+                params![setting.key],
+                |row| row.get(0),
+            )
+            .map(Some)
+            .or_else(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => Ok(None),
+                e => Err(ChainError::DatabaseError(format!("Failed to query setting: {}", e))),
+            })?;
+
+        value
+            .map(|json| {
+                serde_json::from_str(&json).map_err(|e| {
+                    ChainError::DatabaseError(format!(
+                        "Failed to deserialize setting '{}': {}",
+                        setting.key, e
+                    ))
+                })
+            })
+            .transpose()
+    }
+
+    /// Insert or replace a setting
+    pub fn set_setting<T: Serialize>(&self, setting: &Setting<T>, value: &T) -> Result<(), ChainError> {
+        let json = serde_json::to_string(value).map_err(|e| {
+            ChainError::DatabaseError(format!("Failed to serialize setting '{}': {}", setting.key, e))
         })?;
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO settings (key, value, updated_at) VALUES (?1, ?2, ?3)",
+                params![setting.key, json, chrono::Utc::now().timestamp()],
+            )
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to save setting: {}", e)))?;
+        Ok(())
+    }
+
+    /// Remove a setting; returns whether it was set
+    pub fn remove_setting<T>(&self, setting: &Setting<T>) -> Result<bool, ChainError> {
+        self.conn
+            .execute("DELETE FROM settings WHERE key = ?1", params![setting.key])
+            .map(|removed| removed > 0)
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to remove setting: {}", e)))
+    }
+
+    /// All metadata entries, ordered by key
+    fn load_metadata(&self) -> Result<Vec<(String, String)>, ChainError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT key, value FROM metadata ORDER BY key ASC")
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to prepare query: {}", e)))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to query metadata: {}", e)))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to read row: {}", e)))
+    }
+
+    /// Height of the snapshot this database was bootstrapped from, if any.
+    /// Blocks below it are stored header-only.
+    pub fn snapshot_height(&self) -> Result<Option<u64>, ChainError> {
+        Ok(self
+            .load_metadata()?
+            .into_iter()
+            .find(|(key, _)| key == SNAPSHOT_HEIGHT_KEY)
+            .and_then(|(_, value)| value.parse().ok()))
+    }
+
+    /// Highest block whose body has been pruned, if any
+    pub fn pruned_height(&self) -> Result<Option<u64>, ChainError> {
+        Ok(self
+            .load_metadata()?
+            .into_iter()
+            .find(|(key, _)| key == PRUNED_HEIGHT_KEY)
+            .and_then(|(_, value)| value.parse().ok()))
+    }
+
+    /// Highest block stored header-only, because it was pruned or imported
+    /// from a snapshot. History at and below it can't be replayed.
+    pub fn header_only_height(&self) -> Result<Option<u64>, ChainError> {
+        Ok(self.snapshot_height()?.max(self.pruned_height()?))
+    }
+
+    /// Discard the bodies of blocks buried more than `depth` blocks below the
+    /// tip (at least `MIN_PRUNE_DEPTH`), keeping their headers and dropping
+    /// their index entries. Returns the number of bodies discarded.
+    ///
+    /// SQLite reuses the freed pages for new blocks, so the file stops
+    /// growing with the chain rather than shrinking.
+    pub fn prune_blocks(&self, depth: u64) -> Result<usize, ChainError> {
+        let tip: Option<i64> = self
+            .conn
+            .query_row("SELECT MAX(height) FROM blocks", [], |row| row.get(0))
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to query tip: {}", e)))?;
+        let Some(cutoff) = tip.and_then(|tip| (tip as u64).checked_sub(depth.max(MIN_PRUNE_DEPTH)))
+        else {
+            return Ok(0);
+        };
+        if self.pruned_height()?.is_some_and(|pruned| pruned >= cutoff) {
+            return Ok(0);
+        }
 
+        let tx = self.begin()?;
+        let pruned = tx
+            .execute(
+                "UPDATE blocks SET transactions = '[]' WHERE height <= ?1 AND transactions != '[]'",
+                params![cutoff as i64],
+            )
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to prune blocks: {}", e)))?;
+        for table in ["tx_blocks", "address_txs", "memo_txs"] {
+            tx.execute(
+                &format!("DELETE FROM {} WHERE height <= ?1", table),
+                params![cutoff as i64],
+            )
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to prune {}: {}", table, e)))?;
+        }
         tx.execute(
-            "INSERT OR REPLACE INTO blocks (height, hash, previous_hash, timestamp, difficulty, nonce, merkle_root, transactions)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-            params![
-                block.header.height as i64,
-                block.hash().to_vec(),
-                block.header.previous_hash.to_vec(),
-                block.header.timestamp,
-                block.header.difficulty as i64,
-                block.header.nonce as i64,
-                block.header.merkle_root.to_vec(),
-                transactions_json,
-            ],
-        ).map_err(|e| ChainError::DatabaseError(format!("Failed to save block: {}", e)))?;
+            "INSERT OR REPLACE INTO metadata (key, value) VALUES (?1, ?2)",
+            params![PRUNED_HEIGHT_KEY, cutoff.to_string()],
+        )
+        .map_err(|e| ChainError::DatabaseError(format!("Failed to save metadata: {}", e)))?;
+        commit(tx)?;
 
-        // Save UTXO set
-        tx.execute("DELETE FROM utxo_set", [])
-            .map_err(|e| ChainError::DatabaseError(format!("Failed to clear utxo_set: {}", e)))?;
+        Ok(pruned)
+    }
+
+    /// Write the header chain and UTXO set at `height` to a snapshot file.
+    /// The state below the tip is rebuilt by replaying the stored blocks.
+    pub fn export_snapshot(&self, height: u64, path: &Path) -> Result<SnapshotInfo, ChainError> {
+        let mut chain = self.load_blockchain()?;
+        let tip = chain.blocks.last().map_or(0, |b| b.header.height);
+        if height > tip {
+            return Err(ChainError::InvalidBlock(format!(
+                "Cannot snapshot height {} beyond the tip ({})",
+                height, tip
+            )));
+        }
+
+        let state = if height == tip {
+            std::mem::take(&mut chain.state)
+        } else {
+            if let Some(base) = self.header_only_height()? {
+                return Err(ChainError::InvalidBlock(format!(
+                    "Blocks up to #{} were pruned or imported from a snapshot; only the tip can be exported",
+                    base
+                )));
+            }
+            let mut state = TriangleState::new();
+            for block in &chain.blocks[..=height as usize] {
+                for tx in &block.transactions {
+                    state.apply_transaction(tx, block.header.height)?;
+                }
+            }
+            state
+        };
+
+        self.write_snapshot(&chain, height, state, path)
+    }
 
-        for (hash, triangle) in &state.utxo_set {
+    /// Write a snapshot of `chain`, already stored in this database, at its
+    /// tip without reloading it
+    pub fn snapshot_tip(
+        &self,
+        chain: &Blockchain,
+        path: &Path,
+    ) -> Result<SnapshotInfo, ChainError> {
+        let height = chain.blocks.last().map_or(0, |b| b.header.height);
+        self.write_snapshot(chain, height, chain.state.clone(), path)
+    }
+
+    fn write_snapshot(
+        &self,
+        chain: &Blockchain,
+        height: u64,
+        state: TriangleState,
+        path: &Path,
+    ) -> Result<SnapshotInfo, ChainError> {
+        let mut utxos: Vec<_> = state.utxo_set.into_iter().collect();
+        utxos.sort_unstable_by_key(|(hash, _)| *hash);
+        let mut coinbase_heights: Vec<_> = state.coinbase_heights.into_iter().collect();
+        coinbase_heights.sort_unstable_by_key(|(hash, _)| *hash);
+
+        let headers: Vec<BlockHeader> = chain.blocks[..=height as usize]
+            .iter()
+            .map(|b| b.header.clone())
+            .collect();
+        let difficulty = headers[headers.len() - 1].difficulty;
+        let mut metadata = self.load_metadata()?;
+        for (key, value) in metadata.iter_mut() {
+            if key == "difficulty" {
+                *value = difficulty.to_string();
+            }
+        }
+
+        Snapshot {
+            height,
+            tip_hash: headers[headers.len() - 1].hash(),
+            difficulty,
+            headers,
+            utxos,
+            coinbase_heights,
+            metadata,
+        }
+        .write(path)
+    }
+
+    /// Bootstrap an empty database from a snapshot file. Blocks up to the
+    /// snapshot height are stored header-only; syncing continues from there.
+    pub fn import_snapshot(&self, path: &Path) -> Result<SnapshotInfo, ChainError> {
+        let (snapshot, info) = Snapshot::read(path)?;
+
+        let blocks: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM blocks", [], |row| row.get(0))
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to count blocks: {}", e)))?;
+        if blocks > 0 {
+            return Err(ChainError::DatabaseError(
+                "Snapshots can only be imported into an empty database".to_string(),
+            ));
+        }
+
+        let tx = self.begin()?;
+
+        for header in &snapshot.headers {
+            tx.execute(
+                "INSERT INTO blocks (height, hash, previous_hash, timestamp, difficulty, nonce, merkle_root, transactions, version, state_root)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, '[]', ?8, ?9)",
+                params![
+                    header.height as i64,
+                    header.hash().to_vec(),
+                    header.previous_hash.to_vec(),
+                    header.timestamp,
+                    header.difficulty as i64,
+                    header.nonce as i64,
+                    header.merkle_root.to_vec(),
+                    header.version,
+                    header.state_root.to_vec(),
+                ],
+            )
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to save header: {}", e)))?;
+        }
+
+        let coinbase_heights: HashMap<_, _> = snapshot.coinbase_heights.iter().copied().collect();
+        for (hash, triangle) in &snapshot.utxos {
             let triangle_json = serde_json::to_string(triangle).map_err(|e| {
                 ChainError::DatabaseError(format!("Failed to serialize triangle: {}", e))
             })?;
-
+            let coinbase_height = coinbase_heights.get(hash).map(|h| *h as i64);
             tx.execute(
-                "INSERT INTO utxo_set (hash, triangle_data) VALUES (?1, ?2)",
-                params![hash.to_vec(), triangle_json],
+                "INSERT INTO utxo_set (hash, triangle_data, coinbase_height) VALUES (?1, ?2, ?3)",
+                params![hash.to_vec(), triangle_json, coinbase_height],
             )
             .map_err(|e| ChainError::DatabaseError(format!("Failed to save UTXO: {}", e)))?;
         }
 
-        // Save difficulty
-        tx.execute(
-            "INSERT OR REPLACE INTO metadata (key, value) VALUES ('difficulty', ?1)",
-            params![difficulty.to_string()],
-        )
-        .map_err(|e| ChainError::DatabaseError(format!("Failed to save difficulty: {}", e)))?;
+        let mut metadata = snapshot.metadata;
+        metadata.retain(|(key, _)| {
+            ![
+                "difficulty",
+                SNAPSHOT_HEIGHT_KEY,
+                PRUNED_HEIGHT_KEY,
+                UTXO_TIP_KEY,
+            ]
+            .contains(&key.as_str())
+        });
+        metadata.push(("difficulty".to_string(), snapshot.difficulty.to_string()));
+        metadata.push((SNAPSHOT_HEIGHT_KEY.to_string(), snapshot.height.to_string()));
+        metadata.push((UTXO_TIP_KEY.to_string(), hex::encode(snapshot.tip_hash)));
+        for (key, value) in &metadata {
+            tx.execute(
+                "INSERT OR REPLACE INTO metadata (key, value) VALUES (?1, ?2)",
+                params![key, value],
+            )
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to save metadata: {}", e)))?;
+        }
+
+        commit(tx)?;
+
+        Ok(info)
+    }
+
+    /// Pass the stored blocks with heights `from..=to` to `f` in height
+    /// order, reading one at a time instead of loading the chain. Pruned
+    /// blocks come back header-only.
+    pub fn iterate_blocks(
+        &self,
+        from: u64,
+        to: u64,
+        mut f: impl FnMut(Block) -> Result<(), ChainError>,
+    ) -> Result<(), ChainError> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT {} FROM blocks WHERE height BETWEEN ?1 AND ?2 ORDER BY height ASC",
+                BLOCK_COLUMNS
+            ))
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to prepare query: {}", e)))?;
+        let to = to.min(i64::MAX as u64);
+        let mut rows = stmt
+            .query(params![from as i64, to as i64])
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to query blocks: {}", e)))?;
+        while let Some(row) = rows
+            .next()
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to read row: {}", e)))?
+        {
+            let block = block_from_row(row)
+                .map_err(|e| ChainError::DatabaseError(format!("Failed to load block: {}", e)))?;
+            f(block)?;
+        }
+        Ok(())
+    }
+
+    /// The stored blocks from height `from` up, read `page` heights at a
+    /// time so a long chain can be streamed without loading it
+    pub fn blocks_from(
+        &self,
+        from: u64,
+        page: u64,
+    ) -> impl Iterator<Item = Result<Block, ChainError>> + '_ {
+        let page = page.max(1);
+        let mut next = from;
+        let mut buffered = std::collections::VecDeque::new();
+        let mut done = false;
+        std::iter::from_fn(move || loop {
+            if let Some(block) = buffered.pop_front() {
+                return Some(Ok(block));
+            }
+            if done {
+                return None;
+            }
+            let to = next.saturating_add(page - 1);
+            if let Err(e) = self.iterate_blocks(next, to, |block| {
+                buffered.push_back(block);
+                Ok(())
+            }) {
+                done = true;
+                return Some(Err(e));
+            }
+            // Heights are contiguous, so a short page is the tip
+            done = (buffered.len() as u64) < page || to == u64::MAX;
+            next = to.saturating_add(1);
+        })
+    }
 
-        // Commit all changes atomically
-        tx.commit().map_err(|e| {
-            ChainError::DatabaseError(format!("Failed to commit transaction: {}", e))
+    /// Apply the blocks stored above `chain`'s tip to it, so a process
+    /// following a database another one writes need not reload the whole
+    /// chain. Returns false when the stored chain no longer runs through
+    /// that tip (it was reorganized away); reload with `load_blockchain` then.
+    pub fn catch_up(&self, chain: &mut Blockchain) -> Result<bool, ChainError> {
+        let Some(tip) = chain.blocks.last() else {
+            return Ok(false);
+        };
+        let (height, hash) = (tip.header.height, tip.hash());
+        let mut on_chain = false;
+        self.iterate_blocks(height, u64::MAX, |block| {
+            if block.header.height == height {
+                on_chain = block.hash() == hash;
+                Ok(())
+            } else if on_chain {
+                chain.apply_block(block)
+            } else {
+                Ok(())
+            }
         })?;
-
-        Ok(())
+        Ok(on_chain)
     }
 
+    /// The stored chain, or the network's canonical genesis if nothing is
+    /// stored yet. Fails if the stored genesis block is not the active
+    /// network's, unless opened [`with_custom_genesis`](Self::with_custom_genesis).
     pub fn load_blockchain(&self) -> Result<Blockchain, ChainError> {
-        let mut stmt = self.conn.prepare(
-            "SELECT height, previous_hash, timestamp, difficulty, nonce, merkle_root, transactions
-             FROM blocks ORDER BY height ASC"
-        ).map_err(|e| ChainError::DatabaseError(format!("Failed to prepare query: {}", e)))?;
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT {} FROM blocks ORDER BY height ASC", BLOCK_COLUMNS))
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to prepare query: {}", e)))?;
 
         let blocks_iter = stmt
-            .query_map([], |row| {
-                let transactions_json: String = row.get(6)?;
-                let transactions: Vec<Transaction> = serde_json::from_str(&transactions_json)
-                    .map_err(|_e| rusqlite::Error::InvalidQuery)?;
-
-                let height: i64 = row.get(0)?;
-                let timestamp: i64 = row.get(2)?;
-                let difficulty: i64 = row.get(3)?;
-                let nonce: i64 = row.get(4)?;
-                let previous_hash_vec: Vec<u8> = row.get(1)?;
-                let merkle_root_vec: Vec<u8> = row.get(5)?;
-
-                let mut previous_hash = [0u8; 32];
-                previous_hash.copy_from_slice(&previous_hash_vec);
-                let mut merkle_root = [0u8; 32];
-                merkle_root.copy_from_slice(&merkle_root_vec);
-
-                Ok(Block {
-                    header: BlockHeader {
-                        height: height as u64,
-                        previous_hash,
-                        timestamp: timestamp as u64,
-                        difficulty: difficulty as u32,
-                        nonce: nonce as u64,
-                        merkle_root,
-                    },
-                    transactions,
-                })
-            })
+            .query_map([], block_from_row)
             .map_err(|e| ChainError::DatabaseError(format!("Failed to query blocks: {}", e)))?;
 
         let mut blocks = Vec::new();
@@ -265,34 +1209,10 @@ impl Database {
             );
         }
 
-        if blocks.is_empty() {
-            return Blockchain::new([0; 32], 0);
-        }
-
-        let mut utxo_set = HashMap::new();
-        let mut stmt = self
-            .conn
-            .prepare("SELECT hash, triangle_data FROM utxo_set")
-            .map_err(|e| {
-                ChainError::DatabaseError(format!("Failed to prepare UTXO query: {}", e))
-            })?;
-
-        let utxo_iter = stmt
-            .query_map([], |row| {
-                let hash_vec: Vec<u8> = row.get(0)?;
-                let mut hash = [0u8; 32];
-                hash.copy_from_slice(&hash_vec);
-                let triangle_json: String = row.get(1)?;
-                let triangle: Triangle = serde_json::from_str(&triangle_json)
-                    .map_err(|_| rusqlite::Error::InvalidQuery)?;
-                Ok((hash, triangle))
-            })
-            .map_err(|e| ChainError::DatabaseError(format!("Failed to query UTXOs: {}", e)))?;
-
-        for utxo_result in utxo_iter {
-            let (hash, triangle) = utxo_result
-                .map_err(|e| ChainError::DatabaseError(format!("Failed to load UTXO: {}", e)))?;
-            utxo_set.insert(hash, triangle);
+        match blocks.first() {
+            None => return Blockchain::canonical(),
+            Some(genesis) if !self.custom_genesis => Blockchain::check_genesis(genesis)?,
+            Some(_) => {}
         }
 
         // Load difficulty from metadata, but verify against actual blocks
@@ -317,35 +1237,458 @@ impl Database {
 
         // If there's a mismatch, warn and use the actual block difficulty
         let difficulty = if metadata_difficulty != actual_difficulty && !blocks.is_empty() {
-            eprintln!("⚠️  Warning: Metadata difficulty ({}) doesn't match last block difficulty ({}). Using block data.",
+            warn!("⚠️  Warning: Metadata difficulty ({}) doesn't match last block difficulty ({}). Using block data.",
                       metadata_difficulty, actual_difficulty);
-            eprintln!("   Updating metadata to match...");
+            warn!("   Updating metadata to match...");
             // Fix the metadata - errors here are non-critical since we're using actual_difficulty anyway
             if let Err(e) = self.conn.execute(
                 "INSERT OR REPLACE INTO metadata (key, value) VALUES ('difficulty', ?1)",
                 params![actual_difficulty.to_string()],
             ) {
-                eprintln!("⚠️  Warning: Failed to update difficulty metadata: {}", e);
+                warn!("⚠️  Warning: Failed to update difficulty metadata: {}", e);
             }
             actual_difficulty
         } else {
             actual_difficulty
         };
 
-        let mut state = self.load_utxo_set()?;
+        let mut state = self.load_utxo_at_tip()?;
         state.rebuild_address_balances();
+        // Rows written before coinbase heights were stored carry none; the
+        // recent block bodies fill those in where they are available
+        let stored = std::mem::take(&mut state.coinbase_heights);
+        state.rebuild_coinbase_heights(&blocks);
+        state.coinbase_heights.extend(stored);
+        let tip = blocks.last().map_or(0, |b| b.header.height);
+        state
+            .coinbase_heights
+            .retain(|_, height| *height + COINBASE_MATURITY > tip);
 
-        let blockchain = Blockchain {
+        let mut blockchain = Blockchain {
             blocks,
             difficulty,
             mempool: Mempool::new(),
             state,
+            block_index: HashMap::new(),
+            events: EventBus::new(),
         };
+        blockchain.mempool.set_event_bus(blockchain.events.clone());
+        blockchain.rebuild_block_index();
+        // The tip's header holds the difficulty it was mined at; if it closed
+        // a retarget interval, the next block is expected at the new one
+        blockchain.adjust_difficulty();
 
         Ok(blockchain)
     }
 }
 
+/// Rebuild the address index from the stored blocks, inside the caller's
+/// transaction
+pub(crate) fn rebuild_address_index_in(conn: &Connection) -> Result<(), ChainError> {
+    conn.execute("DELETE FROM address_txs", [])
+        .map_err(|e| ChainError::DatabaseError(format!("Failed to clear address_txs: {}", e)))?;
+    for_each_stored_block(conn, |height, transactions| {
+        index_addresses(conn, height, transactions)
+    })
+}
+
+/// Rebuild the transaction → block index from the stored blocks, inside the
+/// caller's transaction
+pub(crate) fn rebuild_tx_index_in(conn: &Connection) -> Result<(), ChainError> {
+    conn.execute("DELETE FROM tx_blocks", [])
+        .map_err(|e| ChainError::DatabaseError(format!("Failed to clear tx_blocks: {}", e)))?;
+    for_each_stored_block(conn, |height, transactions| {
+        index_tx_hashes(conn, height, transactions)
+    })
+}
+
+/// Columns [`block_from_row`] reads, in order
+const BLOCK_COLUMNS: &str =
+    "height, previous_hash, timestamp, difficulty, nonce, merkle_root, transactions, version, state_root";
+
+fn block_from_row(row: &rusqlite::Row) -> rusqlite::Result<Block> {
+    let transactions_json: String = row.get(6)?;
+    let transactions: Vec<Transaction> =
+        serde_json::from_str(&transactions_json).map_err(|_e| rusqlite::Error::InvalidQuery)?;
+
+    let height: i64 = row.get(0)?;
+    let timestamp: i64 = row.get(2)?;
+    let difficulty: i64 = row.get(3)?;
+    let nonce: i64 = row.get(4)?;
+    let previous_hash_vec: Vec<u8> = row.get(1)?;
+    let merkle_root_vec: Vec<u8> = row.get(5)?;
+    let version: u32 = row.get(7)?;
+    let state_root_vec: Vec<u8> = row.get(8)?;
+
+    let mut previous_hash = [0u8; 32];
+    previous_hash.copy_from_slice(&previous_hash_vec);
+    let mut merkle_root = [0u8; 32];
+    merkle_root.copy_from_slice(&merkle_root_vec);
+    let mut state_root = [0u8; 32];
+    state_root.copy_from_slice(&state_root_vec);
+
+    Ok(Block {
+        header: BlockHeader {
+            version,
+            height: height as u64,
+            previous_hash,
+            timestamp: timestamp as u64,
+            difficulty: difficulty as u32,
+            nonce: nonce as u64,
+            merkle_root,
+            state_root,
+        },
+        transactions,
+    })
+}
+
+fn for_each_stored_block(
+    conn: &Connection,
+    mut f: impl FnMut(u64, &[Transaction]) -> Result<(), ChainError>,
+) -> Result<(), ChainError> {
+    let mut stmt = conn
+        .prepare("SELECT height, transactions FROM blocks")
+        .map_err(|e| ChainError::DatabaseError(format!("Failed to prepare query: {}", e)))?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| ChainError::DatabaseError(format!("Failed to query blocks: {}", e)))?;
+
+    for row in rows {
+        let (height, transactions_json) =
+            row.map_err(|e| ChainError::DatabaseError(format!("Failed to read row: {}", e)))?;
+        let transactions: Vec<Transaction> = serde_json::from_str(&transactions_json)
+            .map_err(|e| {
+                ChainError::DatabaseError(format!("Failed to deserialize transactions: {}", e))
+            })?;
+        f(height as u64, &transactions)?;
+    }
+    Ok(())
+}
+
+/// Reads the address index flag straight from the settings table; databases
+/// predating the settings table always index
+fn address_index_enabled_in(conn: &Connection) -> bool {
+    schema_flag_in(conn, ADDRESS_INDEX_FLAG, true)
+}
+
+/// Reads a `SCHEMA_FLAGS` entry, falling back to `default` when unset
+fn schema_flag_in(conn: &Connection, flag: &str, default: bool) -> bool {
+    conn.query_row(
+        "SELECT value FROM settings WHERE key = ?1",
+        params![SCHEMA_FLAGS.key],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|json| serde_json::from_str::<BTreeMap<String, bool>>(&json).ok())
+    .and_then(|flags| flags.get(flag).copied())
+    .unwrap_or(default)
+}
+
+/// `LIKE` pattern matching `query` anywhere, with its wildcards escaped
+fn like_pattern(query: &str) -> String {
+    let mut pattern = String::from("%");
+    for c in query.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+/// Replace the index rows for the block at `height`
+/// Run a statement through the connection's prepared statement cache,
+/// for writes repeated once per block or transaction
+fn execute_cached(
+    conn: &Connection,
+    sql: &str,
+    params: impl rusqlite::Params,
+) -> rusqlite::Result<usize> {
+    conn.prepare_cached(sql)?.execute(params)
+}
+
+fn commit(tx: rusqlite::Transaction<'_>) -> Result<(), ChainError> {
+    tx.commit()
+        .map_err(|e| ChainError::DatabaseError(format!("Failed to commit transaction: {}", e)))
+}
+
+/// Insert or replace a block with its index entries
+fn write_block(conn: &Connection, block: &Block) -> Result<(), ChainError> {
+    let transactions_json = serde_json::to_string(&block.transactions).map_err(|e| {
+        ChainError::DatabaseError(format!("Failed to serialize transactions: {}", e))
+    })?;
+
+    execute_cached(
+        conn,
+        "INSERT OR REPLACE INTO blocks (height, hash, previous_hash, timestamp, difficulty, nonce, merkle_root, transactions, version, state_root)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            block.header.height as i64,
+            block.hash().to_vec(),
+            block.header.previous_hash.to_vec(),
+            block.header.timestamp,
+            block.header.difficulty as i64,
+            block.header.nonce as i64,
+            block.header.merkle_root.to_vec(),
+            transactions_json,
+            block.header.version,
+            block.header.state_root.to_vec(),
+        ],
+    )
+    .map_err(|e| ChainError::DatabaseError(format!("Failed to save block: {}", e)))?;
+
+    index_transactions(conn, block.header.height, &block.transactions)
+}
+
+/// Store `block` with the UTXO set after it, `state`: as a delta when the
+/// block builds on the stored set, otherwise as a full checkpoint
+fn write_block_with_state(
+    conn: &Connection,
+    block: &Block,
+    state: &TriangleState,
+) -> Result<(), ChainError> {
+    if !write_block_delta(conn, block)? {
+        write_utxo_set(conn, state, Some(&block.hash()))?;
+    }
+    Ok(())
+}
+
+/// `write_block_with_state` under a savepoint, so a failure leaves the rest
+/// of `tx` intact
+fn write_block_savepoint(
+    tx: &mut rusqlite::Transaction<'_>,
+    block: &Block,
+    state: &TriangleState,
+) -> Result<(), ChainError> {
+    let savepoint = tx
+        .savepoint()
+        .map_err(|e| ChainError::DatabaseError(format!("Failed to start savepoint: {}", e)))?;
+    write_block_with_state(&savepoint, block, state)?;
+    savepoint
+        .commit()
+        .map_err(|e| ChainError::DatabaseError(format!("Failed to release savepoint: {}", e)))
+}
+
+/// Store `block` and, when it builds on the block the stored UTXO set is
+/// at, its UTXO delta. Returns false, with only the block written, when the
+/// set doesn't line up or a checkpoint is due; the caller must then write
+/// the whole set.
+fn write_block_delta(conn: &Connection, block: &Block) -> Result<bool, ChainError> {
+    let height = block.header.height;
+    let delta = if !height.is_multiple_of(UTXO_CHECKPOINT_INTERVAL)
+        && stored_utxo_tip(conn)? == Some(block.header.previous_hash)
+    {
+        TriangleState::block_delta(block, |hash| stored_utxo(conn, hash)).ok()
+    } else {
+        None
+    };
+    write_block(conn, block)?;
+    let Some(delta) = delta else {
+        return Ok(false);
+    };
+
+    // The block's own coinbase is the only triangle it creates immature
+    let coinbase = match block.transactions.first() {
+        Some(tx @ Transaction::Coinbase(_)) if height > 0 => Some(tx.hash()),
+        _ => None,
+    };
+    let rows = delta
+        .created
+        .iter()
+        .map(|(hash, triangle)| (hash, Some(triangle)))
+        .chain(delta.spent.iter().map(|hash| (hash, None)));
+    for (hash, triangle) in rows {
+        let triangle_json = triangle
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| {
+                ChainError::DatabaseError(format!("Failed to serialize triangle: {}", e))
+            })?;
+        let coinbase_height = (coinbase == Some(*hash)).then_some(height as i64);
+        execute_cached(
+            conn,
+            "INSERT OR REPLACE INTO utxo_deltas (height, hash, triangle_data, coinbase_height)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![height as i64, hash.to_vec(), triangle_json, coinbase_height],
+        )
+        .map_err(|e| ChainError::DatabaseError(format!("Failed to save UTXO delta: {}", e)))?;
+    }
+    set_utxo_tip(conn, Some(&block.hash()))?;
+    Ok(true)
+}
+
+/// A triangle in the stored UTXO set: the newest delta touching `hash`,
+/// falling back to the checkpoint
+fn stored_utxo(conn: &Connection, hash: &[u8; 32]) -> Result<Option<Triangle>, ChainError> {
+    let query = |sql: &str| -> rusqlite::Result<Option<Option<String>>> {
+        conn.prepare_cached(sql)?
+            .query_row(params![hash.to_vec()], |row| row.get(0))
+            .optional()
+    };
+    let delta =
+        query("SELECT triangle_data FROM utxo_deltas WHERE hash = ?1 ORDER BY height DESC LIMIT 1");
+    let triangle_json = match delta {
+        Ok(Some(triangle_json)) => Ok(triangle_json),
+        Ok(None) => {
+            query("SELECT triangle_data FROM utxo_set WHERE hash = ?1").map(Option::flatten)
+        }
+        Err(e) => Err(e),
+    }
+    .map_err(|e| ChainError::DatabaseError(format!("Failed to load UTXO: {}", e)))?;
+    triangle_json.as_deref().map(parse_triangle).transpose()
+}
+
+/// Hash of the block the stored UTXO set is at, if known
+fn stored_utxo_tip(conn: &Connection) -> Result<Option<[u8; 32]>, ChainError> {
+    let value: Option<String> = conn
+        .query_row(
+            "SELECT value FROM metadata WHERE key = ?1",
+            params![UTXO_TIP_KEY],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| ChainError::DatabaseError(format!("Failed to load metadata: {}", e)))?;
+    Ok(value
+        .and_then(|hex_hash| hex::decode(hex_hash).ok())
+        .and_then(|bytes| bytes.try_into().ok()))
+}
+
+fn set_utxo_tip(conn: &Connection, tip: Option<&[u8; 32]>) -> Result<(), ChainError> {
+    match tip {
+        Some(hash) => execute_cached(
+            conn,
+            "INSERT OR REPLACE INTO metadata (key, value) VALUES (?1, ?2)",
+            params![UTXO_TIP_KEY, hex::encode(hash)],
+        ),
+        None => conn.execute("DELETE FROM metadata WHERE key = ?1", params![UTXO_TIP_KEY]),
+    }
+    .map(|_| ())
+    .map_err(|e| ChainError::DatabaseError(format!("Failed to save metadata: {}", e)))
+}
+
+fn parse_triangle(triangle_json: &str) -> Result<Triangle, ChainError> {
+    serde_json::from_str(triangle_json)
+        .map_err(|e| ChainError::DatabaseError(format!("Failed to deserialize triangle: {}", e)))
+}
+
+/// Replace the stored UTXO set with `state`'s as a checkpoint, superseding
+/// every delta. `tip` is the block the set is at, if known.
+fn write_utxo_set(
+    conn: &Connection,
+    state: &TriangleState,
+    tip: Option<&[u8; 32]>,
+) -> Result<(), ChainError> {
+    conn.execute("DELETE FROM utxo_set", [])
+        .map_err(|e| ChainError::DatabaseError(format!("Failed to clear utxo_set: {}", e)))?;
+    conn.execute("DELETE FROM utxo_deltas", [])
+        .map_err(|e| ChainError::DatabaseError(format!("Failed to clear utxo_deltas: {}", e)))?;
+    set_utxo_tip(conn, tip)?;
+
+    let mut stmt = conn
+        .prepare_cached(
+            "INSERT INTO utxo_set (hash, triangle_data, coinbase_height) VALUES (?1, ?2, ?3)",
+        )
+        .map_err(|e| ChainError::DatabaseError(format!("Failed to prepare statement: {}", e)))?;
+    for (hash, triangle) in &state.utxo_set {
+        let triangle_json = serde_json::to_string(triangle).map_err(|e| {
+            ChainError::DatabaseError(format!("Failed to serialize triangle: {}", e))
+        })?;
+        let coinbase_height = state.coinbase_heights.get(hash).map(|h| *h as i64);
+        stmt.execute(params![hash.to_vec(), triangle_json, coinbase_height])
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to save UTXO: {}", e)))?;
+    }
+    Ok(())
+}
+
+fn write_difficulty(conn: &Connection, difficulty: u64) -> Result<(), ChainError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO metadata (key, value) VALUES ('difficulty', ?1)",
+        params![difficulty.to_string()],
+    )
+    .map(|_| ())
+    .map_err(|e| ChainError::DatabaseError(format!("Failed to save difficulty: {}", e)))
+}
+
+fn index_transactions(
+    conn: &Connection,
+    height: u64,
+    transactions: &[Transaction],
+) -> Result<(), ChainError> {
+    index_addresses(conn, height, transactions)?;
+    index_tx_hashes(conn, height, transactions)?;
+    index_memos(conn, height, transactions)
+}
+
+fn index_addresses(
+    conn: &Connection,
+    height: u64,
+    transactions: &[Transaction],
+) -> Result<(), ChainError> {
+    conn.execute("DELETE FROM address_txs WHERE height = ?1", params![height as i64])
+        .map_err(|e| ChainError::DatabaseError(format!("Failed to clear address index: {}", e)))?;
+    if !address_index_enabled_in(conn) {
+        return Ok(());
+    }
+
+    for (tx_index, tx) in transactions.iter().enumerate() {
+        for address in tx.addresses() {
+            execute_cached(
+                conn,
+                "INSERT OR IGNORE INTO address_txs (address, height, tx_index) VALUES (?1, ?2, ?3)",
+                params![address.to_vec(), height as i64, tx_index as i64],
+            )
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to index transaction: {}", e)))?;
+        }
+    }
+    Ok(())
+}
+
+fn index_memos(
+    conn: &Connection,
+    height: u64,
+    transactions: &[Transaction],
+) -> Result<(), ChainError> {
+    conn.execute(
+        "DELETE FROM memo_txs WHERE height = ?1",
+        params![height as i64],
+    )
+    .map_err(|e| ChainError::DatabaseError(format!("Failed to clear memo index: {}", e)))?;
+    if !schema_flag_in(conn, MEMO_INDEX_FLAG, false) {
+        return Ok(());
+    }
+
+    for (tx_index, tx) in transactions.iter().enumerate() {
+        if let Some(memo) = tx.memo() {
+            execute_cached(
+                conn,
+                "INSERT OR REPLACE INTO memo_txs (height, tx_index, memo) VALUES (?1, ?2, ?3)",
+                params![height as i64, tx_index as i64, memo],
+            )
+            .map_err(|e| ChainError::DatabaseError(format!("Failed to index memo: {}", e)))?;
+        }
+    }
+    Ok(())
+}
+
+fn index_tx_hashes(
+    conn: &Connection,
+    height: u64,
+    transactions: &[Transaction],
+) -> Result<(), ChainError> {
+    conn.execute("DELETE FROM tx_blocks WHERE height = ?1", params![height as i64])
+        .map_err(|e| ChainError::DatabaseError(format!("Failed to clear tx index: {}", e)))?;
+    for (tx_index, tx) in transactions.iter().enumerate() {
+        execute_cached(
+            conn,
+            "INSERT OR REPLACE INTO tx_blocks (tx_hash, height, tx_index) VALUES (?1, ?2, ?3)",
+            params![tx.hash().to_vec(), height as i64, tx_index as i64],
+        )
+        .map_err(|e| ChainError::DatabaseError(format!("Failed to index transaction: {}", e)))?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -356,19 +1699,23 @@ mod tests {
         let mut address = [0u8; 32];
         let bytes = s.as_bytes();
         address[..bytes.len()].copy_from_slice(bytes);
-        address
+        Address::from(address)
     }
 
     #[test]
     fn test_database_open() {
         let db = Database::open(":memory:").unwrap();
         assert!(db.conn.is_autocommit());
+        assert_eq!(
+            db.schema_version().unwrap(),
+            crate::migrations::latest_version()
+        );
     }
 
     #[test]
     fn test_save_and_load_blockchain() {
         let db = Database::open(":memory:").unwrap();
-        let chain = Blockchain::new(create_test_address("miner"), 1).unwrap();
+        let chain = Blockchain::canonical().unwrap();
 
         db.save_blockchain_state(&chain.blocks[0], &chain.state, chain.difficulty as u64)
             .unwrap();
@@ -378,5 +1725,591 @@ mod tests {
         assert_eq!(loaded_chain.blocks.len(), 1);
         assert_eq!(loaded_chain.blocks[0].header.height, 0);
         assert_eq!(loaded_chain.difficulty, chain.difficulty);
+
+        // A chain minted locally is another network's
+        let custom = Blockchain::new(create_test_address("miner"), 1).unwrap();
+        let db = Database::open(":memory:").unwrap();
+        db.save_blockchain_state(&custom.blocks[0], &custom.state, 1)
+            .unwrap();
+        let err = db.load_blockchain().err().unwrap();
+        assert!(
+            err.to_string().contains("not the mainnet genesis"),
+            "{}",
+            err
+        );
+        let db = db.with_custom_genesis();
+        assert_eq!(
+            db.load_blockchain().unwrap().blocks[0].hash(),
+            custom.blocks[0].hash()
+        );
+    }
+
+    #[test]
+    fn test_load_expects_retargeted_difficulty() {
+        let miner = create_test_address("miner");
+        let mut source = Blockchain::new(miner, 1).unwrap();
+        for _ in 0..crate::blockchain::DIFFICULTY_ADJUSTMENT_INTERVAL {
+            let block = crate::fixtures::mine_next_block(&source, miner, vec![]);
+            source.apply_block(block).unwrap();
+        }
+        assert_ne!(source.difficulty, 1);
+
+        let db = Database::open(":memory:").unwrap().with_custom_genesis();
+        let mut chain = Blockchain::new(miner, 1).unwrap();
+        db.save_blockchain_state(&chain.blocks[0], &chain.state, 1)
+            .unwrap();
+        db.apply_blocks(&mut chain, source.blocks[1..].to_vec(), 100)
+            .unwrap();
+
+        // The tip was mined at 1 but closed the interval
+        let mut loaded = db.load_blockchain().unwrap();
+        assert_eq!(loaded.blocks.last().unwrap().header.difficulty, 1);
+        assert_eq!(loaded.difficulty, source.difficulty);
+        let next = crate::fixtures::mine_next_block(&source, miner, vec![]);
+        loaded.apply_block(next).unwrap();
+    }
+
+    #[test]
+    fn test_address_transaction_index() {
+        let db = Database::open(":memory:").unwrap();
+        let miner = create_test_address("miner");
+        let mut chain = Blockchain::new(miner, 1).unwrap();
+        db.save_blockchain_state(&chain.blocks[0], &chain.state, 1).unwrap();
+
+        for height in 1..=3 {
+            let coinbase = Transaction::Coinbase(crate::transaction::CoinbaseTx {
+                reward_area: crate::geometry::Coord::from_num(10),
+                beneficiary_address: miner,
+                nonce: height,
+            });
+            let previous = chain.blocks.last().unwrap();
+            let mut block = Block::new(height, previous.hash(), 1, vec![coinbase]);
+            block.header.timestamp = previous.header.timestamp + 1;
+            let block = crate::fixtures::seal_block(&chain, block);
+            chain.apply_block(block.clone()).unwrap();
+            db.save_blockchain_state(&block, &chain.state, 1).unwrap();
+        }
+
+        assert_eq!(db.count_transactions_for_address(&miner).unwrap(), 4);
+        let page = db.get_transactions_for_address(&miner, 0, 2).unwrap();
+        assert_eq!(page.len(), 2);
+        assert_eq!(page[0].block_height, 3);
+        assert_eq!(page[1].block_height, 2);
+        let page = db.get_transactions_for_address(&miner, 1, 2).unwrap();
+        assert_eq!(page[1].block_height, 0);
+
+        assert!(db
+            .get_transactions_for_address(&create_test_address("nobody"), 0, 10)
+            .unwrap()
+            .is_empty());
+
+        // Rebuilding from stored blocks yields the same index
+        db.rebuild_address_index().unwrap();
+        assert_eq!(db.count_transactions_for_address(&miner).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_export_and_import_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open(":memory:").unwrap().with_custom_genesis();
+        let miner = create_test_address("miner");
+        let mut chain = Blockchain::new(miner, 1).unwrap();
+        db.save_blockchain_state(&chain.blocks[0], &chain.state, 1).unwrap();
+
+        let mut states = vec![chain.state.clone()];
+        for height in 1..=3 {
+            let coinbase = Transaction::Coinbase(crate::transaction::CoinbaseTx {
+                reward_area: crate::geometry::Coord::from_num(10),
+                beneficiary_address: miner,
+                nonce: height,
+            });
+            let previous = chain.blocks.last().unwrap();
+            let mut block = Block::new(height, previous.hash(), 1, vec![coinbase]);
+            block.header.timestamp = previous.header.timestamp + 1;
+            let block = crate::fixtures::seal_block(&chain, block);
+            chain.apply_block(block.clone()).unwrap();
+            db.save_blockchain_state(&block, &chain.state, 1).unwrap();
+            states.push(chain.state.clone());
+        }
+
+        // A past height is rebuilt by replaying blocks
+        let path = dir.path().join("h1.snapshot");
+        let info = db.export_snapshot(1, &path).unwrap();
+        assert_eq!(info.tip_hash, chain.blocks[1].hash());
+        assert_eq!(info.utxo_count, states[1].utxo_set.len());
+        assert!(db.export_snapshot(4, &path).is_err());
+
+        let path = dir.path().join("tip.snapshot");
+        db.export_snapshot(3, &path).unwrap();
+
+        let fresh = Database::open(":memory:").unwrap().with_custom_genesis();
+        fresh.import_snapshot(&path).unwrap();
+        assert_eq!(fresh.snapshot_height().unwrap(), Some(3));
+        assert!(db.import_snapshot(&path).is_err());
+
+        let mut imported = fresh.load_blockchain().unwrap();
+        assert_eq!(imported.blocks.last().unwrap().hash(), chain.blocks[3].hash());
+        assert_eq!(imported.state.get_balance(&miner), chain.state.get_balance(&miner));
+        // The snapshot carries the maturity of the recent rewards, whose
+        // blocks the fresh node only holds as headers
+        assert!(!chain.state.coinbase_heights.is_empty());
+        assert_eq!(
+            imported.state.coinbase_heights,
+            chain.state.coinbase_heights
+        );
+
+        // The imported chain keeps extending
+        let coinbase = Transaction::Coinbase(crate::transaction::CoinbaseTx {
+            reward_area: crate::geometry::Coord::from_num(10),
+            beneficiary_address: miner,
+            nonce: 4,
+        });
+        let previous = imported.blocks.last().unwrap();
+        let mut block = Block::new(4, previous.hash(), 1, vec![coinbase]);
+        block.header.timestamp = previous.header.timestamp + 1;
+        imported
+            .apply_block(crate::fixtures::seal_block(&imported, block))
+            .unwrap();
+
+        // History below the snapshot is header-only, so only the tip exports
+        assert!(fresh.export_snapshot(1, &dir.path().join("x")).is_err());
+    }
+
+    #[test]
+    fn test_apply_blocks_commits_in_batches() {
+        let miner = create_test_address("miner");
+        let mut source = Blockchain::new(miner, 1).unwrap();
+        for _ in 0..7 {
+            let block = crate::fixtures::mine_next_block(&source, miner, vec![]);
+            source.apply_block(block).unwrap();
+        }
+        let mut bad = crate::fixtures::mine_next_block(&source, miner, vec![]);
+        bad.header.previous_hash = [1; 32];
+
+        let db = Database::open(":memory:").unwrap().with_custom_genesis();
+        let mut chain = Blockchain::new(miner, 1).unwrap();
+        db.save_blockchain_state(&chain.blocks[0], &chain.state, 1)
+            .unwrap();
+        let mut blocks = source.blocks[1..].to_vec();
+        blocks.push(bad);
+        assert!(db.apply_blocks(&mut chain, blocks, 3).is_err());
+
+        // Every block before the invalid one was stored with its state
+        let loaded = db.load_blockchain().unwrap();
+        assert_eq!(loaded.blocks.len(), 8);
+        assert_eq!(
+            loaded.blocks.last().unwrap().hash(),
+            source.blocks.last().unwrap().hash()
+        );
+        assert_eq!(loaded.state.state_root(), source.state.state_root());
+        let coinbase = source.blocks[7].transactions[0].hash();
+        assert!(matches!(
+            db.transaction_status(&coinbase).unwrap(),
+            TxStatus::Confirmed { .. }
+        ));
+    }
+
+    #[test]
+    fn test_apply_blocks_keeps_blocks_stored_before_a_write_failure() {
+        let miner = create_test_address("miner");
+        let mut source = Blockchain::new(miner, 1).unwrap();
+        for _ in 0..5 {
+            let block = crate::fixtures::mine_next_block(&source, miner, vec![]);
+            source.apply_block(block).unwrap();
+        }
+
+        let db = Database::open(":memory:").unwrap().with_custom_genesis();
+        let mut chain = Blockchain::new(miner, 1).unwrap();
+        db.save_blockchain_state(&chain.blocks[0], &chain.state, 1)
+            .unwrap();
+        db.conn
+            .execute_batch(
+                "CREATE TEMP TRIGGER fail_block BEFORE INSERT ON blocks WHEN NEW.height = 4
+                 BEGIN SELECT RAISE(ABORT, 'injected'); END",
+            )
+            .unwrap();
+
+        let result = db.apply_blocks(&mut chain, source.blocks[1..].to_vec(), 100);
+        assert!(matches!(
+            result,
+            Err(ChainError::DatabaseError(msg)) if msg.starts_with("Stored 3 block(s)")
+        ));
+        assert_eq!(chain.blocks.len(), 5);
+
+        // Blocks 1-3 were committed with their state; block 4 left no trace
+        let loaded = db.load_blockchain().unwrap();
+        assert_eq!(loaded.blocks.len(), 4);
+        assert_eq!(
+            loaded.blocks.last().unwrap().hash(),
+            source.blocks[3].hash()
+        );
+
+        // Flushing catches the database up with the chain in memory
+        db.conn.execute_batch("DROP TRIGGER fail_block").unwrap();
+        db.flush_blockchain(&chain).unwrap();
+        assert_eq!(db.load_blockchain().unwrap().blocks.len(), 5);
+    }
+
+    #[test]
+    fn test_catch_up_applies_only_new_blocks() {
+        let miner = create_test_address("miner");
+        let mut source = Blockchain::new(miner, 1).unwrap();
+        for _ in 0..4 {
+            let block = crate::fixtures::mine_next_block(&source, miner, vec![]);
+            source.apply_block(block).unwrap();
+        }
+
+        let db = Database::open(":memory:").unwrap().with_custom_genesis();
+        let mut writer = Blockchain::new(miner, 1).unwrap();
+        db.save_blockchain_state(&writer.blocks[0], &writer.state, 1)
+            .unwrap();
+        db.apply_blocks(&mut writer, source.blocks[1..3].to_vec(), 100)
+            .unwrap();
+        let mut follower = db.load_blockchain().unwrap();
+
+        db.apply_blocks(&mut writer, source.blocks[3..].to_vec(), 100)
+            .unwrap();
+        assert!(db.catch_up(&mut follower).unwrap());
+        assert_eq!(follower.blocks.len(), 5);
+        assert_eq!(follower.state.state_root(), source.state.state_root());
+
+        // A tip the database does not hold calls for a reload
+        let mut other = Blockchain::new(miner, 1).unwrap();
+        let block = crate::fixtures::mine_next_block(&other, create_test_address("other"), vec![]);
+        other.apply_block(block).unwrap();
+        assert!(!db.catch_up(&mut other).unwrap());
+        assert_eq!(other.blocks.len(), 2);
+    }
+
+    #[test]
+    fn test_utxo_deltas_compose_to_tip() {
+        use crate::fixtures::{mine_next_block, signed_batch_transfer, signed_transfer};
+        use crate::geometry::Coord;
+
+        let keypair = crate::crypto::KeyPair::generate().unwrap();
+        let miner = create_test_address("miner");
+        let mut chain = Blockchain::new(keypair.address(), 1).unwrap();
+        let db = Database::open(":memory:").unwrap();
+        db.save_blockchain_state(&chain.blocks[0], &chain.state, 1)
+            .unwrap();
+
+        let genesis_output = chain.blocks[0].transactions[0].hash();
+        let transfer = signed_transfer(
+            &keypair,
+            genesis_output,
+            miner,
+            Coord::from_num(10),
+            Coord::from_num(1),
+            0,
+        );
+        let block = mine_next_block(&chain, miner, vec![transfer]);
+        chain.apply_block(block).unwrap();
+        db.save_blockchain_state(chain.blocks.last().unwrap(), &chain.state, 1)
+            .unwrap();
+
+        // Spend the change of the previous block's transfer
+        let change = chain
+            .state
+            .utxo_set
+            .iter()
+            .find(|(_, triangle)| triangle.owner == keypair.address())
+            .map(|(hash, _)| *hash)
+            .unwrap();
+        let batch = signed_batch_transfer(
+            &keypair,
+            change,
+            &[(miner, Coord::from_num(5))],
+            Coord::from_num(1),
+            1,
+        );
+        let block = mine_next_block(&chain, miner, vec![batch]);
+        chain.apply_block(block).unwrap();
+        db.save_blockchain_state(chain.blocks.last().unwrap(), &chain.state, 1)
+            .unwrap();
+
+        // The checkpoint still holds only the genesis output
+        let count = |table: &str| -> i64 {
+            db.conn
+                .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                    row.get(0)
+                })
+                .unwrap()
+        };
+        assert_eq!(count("utxo_set"), 1);
+        assert!(count("utxo_deltas") > 0);
+
+        let state = db.load_utxo_at_tip().unwrap();
+        assert_eq!(state.state_root(), chain.state.state_root());
+        assert!(!state.utxo_set.contains_key(&genesis_output));
+        assert!(!state.utxo_set.contains_key(&change));
+        // The block rewards are still immature
+        assert_eq!(state.coinbase_heights.len(), 2);
+        assert_eq!(state.coinbase_heights, chain.state.coinbase_heights);
+
+        // A full save, e.g. after a reorganization, checkpoints and drops the deltas
+        db.save_utxo_set(&chain.state).unwrap();
+        assert_eq!(count("utxo_deltas"), 0);
+        assert_eq!(
+            db.load_utxo_at_tip().unwrap().state_root(),
+            chain.state.state_root()
+        );
+    }
+
+    #[test]
+    fn test_prune_blocks() {
+        let db = Database::open(":memory:").unwrap().with_custom_genesis();
+        let miner = create_test_address("miner");
+        let mut chain = Blockchain::new(miner, 1).unwrap();
+        db.save_blockchain_state(&chain.blocks[0], &chain.state, 1)
+            .unwrap();
+        for _ in 0..MIN_PRUNE_DEPTH + 5 {
+            let block = crate::fixtures::mine_next_block(&chain, miner, vec![]);
+            chain.apply_block(block.clone()).unwrap();
+            db.save_blockchain_state(&block, &chain.state, 1).unwrap();
+        }
+        let early_coinbase = chain.blocks[3].transactions[0].hash();
+
+        // Asking for less than the minimum depth still keeps it
+        assert_eq!(db.prune_blocks(10).unwrap(), 6);
+        assert_eq!(db.header_only_height().unwrap(), Some(5));
+        assert_eq!(db.prune_blocks(10).unwrap(), 0);
+        assert!(matches!(
+            db.transaction_status(&early_coinbase).unwrap(),
+            TxStatus::Unknown
+        ));
+
+        let loaded = db.load_blockchain().unwrap();
+        assert_eq!(loaded.blocks.len(), chain.blocks.len());
+        assert!(loaded.blocks[5].transactions.is_empty());
+        assert!(!loaded.blocks[6].transactions.is_empty());
+        assert_eq!(
+            loaded.blocks.last().unwrap().hash(),
+            chain.blocks.last().unwrap().hash()
+        );
+        assert_eq!(
+            loaded.state.get_balance(&miner),
+            chain.state.get_balance(&miner)
+        );
+    }
+
+    #[test]
+    fn test_settings_roundtrip() {
+        let db = Database::open(":memory:").unwrap();
+        assert_eq!(db.get_setting(&LAST_SYNC_HEIGHT).unwrap(), None);
+
+        db.set_setting(&LAST_SYNC_HEIGHT, &41).unwrap();
+        db.set_setting(&LAST_SYNC_HEIGHT, &42).unwrap();
+        assert_eq!(db.get_setting(&LAST_SYNC_HEIGHT).unwrap(), Some(42));
+
+        let flags = BTreeMap::from([("address_index".to_string(), true)]);
+        db.set_setting(&SCHEMA_FLAGS, &flags).unwrap();
+        assert_eq!(db.get_setting(&SCHEMA_FLAGS).unwrap(), Some(flags));
+
+        // Integer map keys survive the JSON encoding
+        let watches = BTreeMap::from([(-1001, BTreeSet::from(["ab".repeat(32)]))]);
+        db.set_setting(&ADDRESS_WATCHES, &watches).unwrap();
+        assert_eq!(db.get_setting(&ADDRESS_WATCHES).unwrap(), Some(watches));
+
+        // A value stored under the wrong type is an error, not a silent default
+        const WRONG: Setting<String> = Setting::new("last_sync_height");
+        assert!(db.get_setting(&WRONG).is_err());
+
+        assert!(db.remove_setting(&LAST_SYNC_HEIGHT).unwrap());
+        assert!(!db.remove_setting(&LAST_SYNC_HEIGHT).unwrap());
+        assert_eq!(db.get_setting(&LAST_SYNC_HEIGHT).unwrap(), None);
+    }
+
+    #[test]
+    fn test_flush_blockchain_writes_missing_and_replaced_blocks() {
+        use crate::fixtures::{mine_next_block, signed_transfer};
+
+        let db = Database::open(":memory:").unwrap().with_custom_genesis();
+        let keypair = crate::crypto::KeyPair::generate().unwrap();
+        let mut chain = Blockchain::new(keypair.address(), 1).unwrap();
+        let genesis = chain.clone();
+        for _ in 0..2 {
+            let block = mine_next_block(&chain, keypair.address(), vec![]);
+            chain.apply_block(block).unwrap();
+        }
+        let input = *chain.state.utxo_set.keys().next().unwrap();
+        let pending = signed_transfer(
+            &keypair,
+            input,
+            create_test_address("bob"),
+            crate::geometry::Coord::from_num(1),
+            crate::geometry::Coord::from_num(0),
+            1,
+        );
+        chain.mempool.add_transaction(pending.clone()).unwrap();
+
+        assert_eq!(db.flush_blockchain(&chain).unwrap(), 3);
+        assert_eq!(db.flush_blockchain(&chain).unwrap(), 0);
+        let loaded = db.load_blockchain().unwrap();
+        assert_eq!(loaded.blocks.len(), 3);
+        assert_eq!(loaded.state.utxo_set.len(), chain.state.utxo_set.len());
+        assert_eq!(loaded.state.coinbase_heights, chain.state.coinbase_heights);
+        let saved = db.get_setting(&PENDING_TRANSACTIONS).unwrap().unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].hash(), pending.hash());
+
+        // A shorter competing branch replaces the stored blocks above genesis
+        let mut branch = genesis;
+        let block = mine_next_block(&branch, create_test_address("other"), vec![]);
+        branch.apply_block(block).unwrap();
+        assert_eq!(db.flush_blockchain(&branch).unwrap(), 1);
+        let loaded = db.load_blockchain().unwrap();
+        assert_eq!(loaded.blocks.len(), 2);
+        assert_eq!(loaded.blocks[1].hash(), branch.blocks[1].hash());
+    }
+
+    #[test]
+    fn test_transaction_status_from_index() {
+        use crate::fixtures::{mine_next_block, signed_transfer};
+
+        let db = Database::open(":memory:").unwrap();
+        let keypair = crate::crypto::KeyPair::generate().unwrap();
+        let mut chain = Blockchain::new(keypair.address(), 1).unwrap();
+        let genesis = chain.clone();
+        let block = mine_next_block(&chain, keypair.address(), vec![]);
+        chain.apply_block(block).unwrap();
+        // The block 1 reward is still immature
+        let input = chain.blocks[0].transactions[0].hash();
+        let tx = signed_transfer(
+            &keypair,
+            input,
+            create_test_address("bob"),
+            crate::geometry::Coord::from_num(1),
+            crate::geometry::Coord::from_num(0),
+            1,
+        );
+        let tx_hash = tx.hash();
+
+        chain.mempool.add_transaction(tx.clone()).unwrap();
+        db.flush_blockchain(&chain).unwrap();
+        assert_eq!(db.transaction_status(&tx_hash).unwrap(), TxStatus::Pending);
+        assert_eq!(db.transaction_status(&[7; 32]).unwrap(), TxStatus::Unknown);
+
+        let block = mine_next_block(&chain, keypair.address(), vec![tx]);
+        chain.apply_block(block).unwrap();
+        let block = mine_next_block(&chain, keypair.address(), vec![]);
+        chain.apply_block(block).unwrap();
+        db.flush_blockchain(&chain).unwrap();
+        assert_eq!(db.transaction_height(&tx_hash).unwrap(), Some(2));
+        let expected = TxStatus::confirmed(2, &chain.blocks[2].hash(), 3);
+        assert_eq!(db.transaction_status(&tx_hash).unwrap(), expected);
+        assert_eq!(chain.transaction_status(&tx_hash, None), expected);
+
+        // Reorganized onto a branch without it: the index row goes too
+        let mut branch = genesis;
+        let block = mine_next_block(&branch, create_test_address("other"), vec![]);
+        branch.apply_block(block).unwrap();
+        db.flush_blockchain(&branch).unwrap();
+        assert_eq!(db.transaction_height(&tx_hash).unwrap(), None);
+        assert_eq!(db.transaction_status(&tx_hash).unwrap(), TxStatus::Unknown);
+    }
+
+    #[test]
+    fn test_address_index_can_be_switched_off() {
+        let db = Database::open(":memory:").unwrap();
+        let miner = create_test_address("miner");
+        let mut chain = Blockchain::new(miner, 1).unwrap();
+        let block = crate::fixtures::mine_next_block(&chain, miner, vec![]);
+        chain.apply_block(block).unwrap();
+        db.flush_blockchain(&chain).unwrap();
+        assert_eq!(db.count_transactions_for_address(&miner).unwrap(), 2);
+
+        assert!(db.set_address_index(false).unwrap());
+        assert!(!db.set_address_index(false).unwrap());
+        assert!(db.count_transactions_for_address(&miner).is_err());
+
+        // Blocks saved while disabled are picked up by the rebuild
+        let block = crate::fixtures::mine_next_block(&chain, miner, vec![]);
+        chain.apply_block(block).unwrap();
+        db.flush_blockchain(&chain).unwrap();
+        assert!(db.set_address_index(true).unwrap());
+        assert_eq!(db.count_transactions_for_address(&miner).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_memo_index_search() {
+        use crate::geometry::Coord;
+        use crate::transaction::TransferTx;
+
+        let db = Database::open(":memory:").unwrap();
+        let miner = create_test_address("miner");
+        let chain = Blockchain::new(miner, 1).unwrap();
+        let with_memo = |memo: &str| {
+            let tx = TransferTx::new([1; 32], miner, miner, Coord::from_num(1), Coord::ZERO, 1);
+            Transaction::Transfer(tx.with_memo(memo.to_string()).unwrap())
+        };
+        let genesis = &chain.blocks[0];
+        let transactions = vec![with_memo("Order #A-1042"), with_memo("refund 100%")];
+        db.save_block(genesis).unwrap();
+        db.save_block(&Block::new(1, genesis.hash(), 1, transactions))
+            .unwrap();
+
+        // Off until switched on, then built from the stored blocks
+        assert!(db.search_memos("order", 0, 10).is_err());
+        assert!(db.set_memo_index(true).unwrap());
+        let found = db.search_memos("ORDER #a", 0, 10).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].transaction.memo(), Some("Order #A-1042"));
+        assert_eq!(found[0].block_height, 1);
+
+        // LIKE wildcards in the query match literally
+        assert_eq!(db.count_memo_matches("00%").unwrap(), 1);
+        assert_eq!(db.count_memo_matches("d_1").unwrap(), 0);
+        assert!(db.search_memos("or", 0, 10).is_err());
+        assert_eq!(db.memo_index_stats().unwrap().entries, 2);
+
+        assert!(db.set_memo_index(false).unwrap());
+        assert_eq!(db.memo_index_stats().unwrap(), MemoIndexStats::default());
+    }
+
+    #[test]
+    fn test_save_and_load_peers() {
+        use crate::network::Node;
+
+        let db = Database::open(":memory:").unwrap();
+        let mut peer = PeerRecord::new(Node::new("10.0.0.1".to_string(), 8333));
+        db.save_peer(&peer).unwrap();
+
+        peer.failures = 2;
+        peer.last_seen = 1_700_000_000;
+        db.save_peer(&peer).unwrap();
+
+        assert_eq!(db.load_peers().unwrap(), vec![peer.clone()]);
+        assert_eq!(db.get_peer("10.0.0.1:8333").unwrap(), Some(peer));
+        assert_eq!(db.get_peer("10.0.0.2:8333").unwrap(), None);
+    }
+
+    #[test]
+    fn test_add_peers_capped() {
+        let db = Database::open(":memory:").unwrap();
+        let mut known = PeerRecord::new(Node::new("10.0.0.1".to_string(), 8333));
+        known.last_seen = 1_700_000_000;
+        db.save_peer(&known).unwrap();
+
+        let gossip: Vec<Node> = (1..=5)
+            .map(|i| Node::new(format!("10.0.0.{}", i), 8333))
+            .collect();
+        assert_eq!(db.add_peers(&gossip, 3).unwrap(), 2);
+        let peers = db.load_peers().unwrap();
+        assert_eq!(peers.len(), 3);
+        // Gossip doesn't overwrite what we know about a peer
+        assert_eq!(peers[0], known);
+        assert_eq!(db.add_peers(&gossip, 3).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_bans_persist_until_expiry() {
+        let db = Database::open(":memory:").unwrap();
+        let ban = BanEntry::new("10.0.0.1".to_string(), "invalid block".to_string(), 1_000);
+        db.save_ban(&ban).unwrap();
+
+        assert_eq!(db.load_bans(1_000).unwrap(), vec![ban.clone()]);
+        assert!(db.load_bans(ban.banned_until).unwrap().is_empty());
+        // Expired bans are deleted, not just filtered
+        assert!(db.load_bans(0).unwrap().is_empty());
     }
 }
@@ -0,0 +1,213 @@
+//! Node-local transaction policy
+//!
+//! Operators subject to local legal requirements may need to keep certain
+//! addresses out of what they relay and mine. The policy is consulted at
+//! mempool admission and again when a block template is built; it never
+//! affects consensus, so blocks from other miners containing filtered
+//! transactions are still accepted.
+//!
+//! Addresses can be listed in `config.toml`, and an external command can
+//! decide per transaction: it receives the transaction as JSON on stdin and
+//! allows it by exiting with status 0. Any other outcome (non-zero exit,
+//! failure to start, timeout) rejects it, with the first line the command
+//! printed as the reason.
+
+use crate::config::PolicyConfig;
+use crate::crypto::Address;
+use crate::error::ChainError;
+use crate::transaction::Transaction;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// How often a running policy command is polled for completion
+const COMMAND_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// What the policy command receives on stdin
+#[derive(Serialize)]
+struct PolicyRequest<'a> {
+    hash: String,
+    addresses: Vec<String>,
+    transaction: &'a Transaction,
+}
+
+/// Relay and mining filter applied by this node only
+#[derive(Debug, Clone, Default)]
+pub struct TxPolicy {
+    blocked: HashSet<Address>,
+    /// Program and arguments of the external policy command
+    command: Vec<String>,
+    command_timeout: Duration,
+}
+
+impl TxPolicy {
+    pub fn new(blocked: HashSet<Address>) -> Self {
+        TxPolicy {
+            blocked,
+            command: Vec::new(),
+            command_timeout: Duration::ZERO,
+        }
+    }
+
+    /// Also ask `command` (program followed by its arguments) about every
+    /// transaction, giving it `timeout` to answer
+    pub fn with_command(mut self, command: Vec<String>, timeout: Duration) -> Self {
+        self.command = command;
+        self.command_timeout = timeout;
+        self
+    }
+
+    /// Builds the policy from the `[policy]` section; `None` when it filters
+    /// nothing
+    pub fn from_config(config: &PolicyConfig) -> Result<Option<Self>, ChainError> {
+        if config.blocked_addresses.is_empty() && config.command.is_empty() {
+            return Ok(None);
+        }
+        let blocked = config
+            .blocked_addresses
+            .iter()
+            .map(|a| Address::parse(a))
+            .collect::<Result<_, ChainError>>()?;
+        Ok(Some(TxPolicy::new(blocked).with_command(
+            config.command.clone(),
+            Duration::from_millis(config.command_timeout_ms),
+        )))
+    }
+
+    /// Number of listed addresses
+    pub fn blocked_count(&self) -> usize {
+        self.blocked.len()
+    }
+
+    /// Fails if this node should neither relay nor mine `tx`
+    pub fn check(&self, tx: &Transaction) -> Result<(), ChainError> {
+        let addresses = tx.addresses();
+        if let Some(address) = addresses.iter().find(|a| self.blocked.contains(a)) {
+            return Err(ChainError::InvalidTransaction(format!(
+                "Rejected by node policy: address {} is blocked",
+                address
+            )));
+        }
+        if self.command.is_empty() {
+            return Ok(());
+        }
+        self.run_command(tx, &addresses).map_err(|reason| {
+            ChainError::InvalidTransaction(format!("Rejected by node policy: {}", reason))
+        })
+    }
+
+    pub fn allows(&self, tx: &Transaction) -> bool {
+        self.check(tx).is_ok()
+    }
+
+    fn run_command(&self, tx: &Transaction, addresses: &[Address]) -> Result<(), String> {
+        let request = PolicyRequest {
+            hash: tx.hash_str(),
+            addresses: addresses.iter().map(|a| a.to_string()).collect(),
+            transaction: tx,
+        };
+        let input = serde_json::to_vec(&request).map_err(|e| e.to_string())?;
+
+        let mut child = Command::new(&self.command[0])
+            .args(&self.command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("policy command failed to start: {}", e))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // A command that decides without reading its input is fine
+            let _ = stdin.write_all(&input);
+        }
+
+        let deadline = Instant::now() + self.command_timeout;
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) if Instant::now() >= deadline => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(format!(
+                        "policy command timed out after {}ms",
+                        self.command_timeout.as_millis()
+                    ));
+                }
+                Ok(None) => std::thread::sleep(COMMAND_POLL_INTERVAL),
+                Err(e) => return Err(format!("policy command failed: {}", e)),
+            }
+        };
+        if status.success() {
+            return Ok(());
+        }
+
+        let mut output = String::new();
+        if let Some(mut stdout) = child.stdout.take() {
+            let _ = stdout.read_to_string(&mut output);
+        }
+        match output
+            .lines()
+            .next()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+        {
+            Some(reason) => Err(reason.to_string()),
+            None => Err(format!("policy command exited with {}", status)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Coord;
+    use crate::mempool::Mempool;
+    use crate::transaction::TransferTx;
+    use std::sync::Arc;
+
+    fn transfer(to: Address) -> Transaction {
+        Transaction::Transfer(TransferTx::new(
+            [1; 32],
+            to,
+            Address::from([1; 32]),
+            Coord::from_num(1),
+            Coord::from_num(0),
+            1,
+        ))
+    }
+
+    #[test]
+    fn test_blocked_address_kept_out_of_mempool() {
+        let blocked = Address::from([9; 32]);
+        let mut mempool = Mempool::new();
+        mempool.set_policy(Arc::new(TxPolicy::new(HashSet::from([blocked]))));
+
+        let err = mempool.add_transaction(transfer(blocked)).unwrap_err();
+        assert!(err.to_string().contains("blocked"), "{}", err);
+        mempool
+            .add_transaction(transfer(Address::from([2; 32])))
+            .unwrap();
+        assert_eq!(mempool.minable_transactions().len(), 1);
+    }
+
+    #[test]
+    fn test_policy_command_decides() {
+        let tx = transfer(Address::from([2; 32]));
+        let timeout = Duration::from_secs(5);
+        let allow = TxPolicy::default().with_command(vec!["true".to_string()], timeout);
+        assert!(allow.allows(&tx));
+
+        let script = "cat >/dev/null; echo sanctioned counterparty; exit 3";
+        let deny = TxPolicy::default().with_command(
+            vec!["sh".to_string(), "-c".to_string(), script.to_string()],
+            timeout,
+        );
+        let err = deny.check(&tx).unwrap_err().to_string();
+        assert!(err.ends_with("sanctioned counterparty"), "{}", err);
+
+        let missing =
+            TxPolicy::default().with_command(vec!["/nonexistent/policy".to_string()], timeout);
+        assert!(!missing.allows(&tx));
+    }
+}
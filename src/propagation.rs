@@ -0,0 +1,195 @@
+//! Block propagation measurement
+//!
+//! Records when each block is first seen and by which peer, then measures how
+//! far behind the first delivery every other peer's copy arrives. The
+//! resulting per-peer statistics show which peers deliver blocks first and
+//! which ones lag, so operators can keep the former and drop the latter.
+
+use crate::blockchain::Sha256Hash;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Blocks remembered for matching later deliveries
+const TRACKED_BLOCKS: usize = 512;
+
+/// Name under which locally mined blocks are recorded
+pub const LOCAL_SOURCE: &str = "local";
+
+struct Sighting {
+    first_seen: Instant,
+    /// Peers (or `LOCAL_SOURCE`) that delivered the block so far
+    sources: HashSet<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct PeerCounters {
+    blocks_seen: u64,
+    first_deliveries: u64,
+    total_delay: Duration,
+    max_delay: Duration,
+}
+
+/// Propagation statistics for one peer
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PeerPropagation {
+    pub peer: String,
+    /// Distinct blocks received from the peer
+    pub blocks_seen: u64,
+    /// Blocks this peer delivered before anyone else (or local mining)
+    pub first_deliveries: u64,
+    /// Share of the peer's blocks it delivered first
+    pub first_rate: f64,
+    /// Average delay behind the first delivery, over the blocks it did not
+    /// deliver first
+    pub avg_delay_ms: f64,
+    pub max_delay_ms: u64,
+}
+
+/// First-seen times of recent blocks and per-peer delivery statistics
+#[derive(Default)]
+pub struct PropagationTracker {
+    sightings: HashMap<Sha256Hash, Sighting>,
+    order: VecDeque<Sha256Hash>,
+    peers: HashMap<String, PeerCounters>,
+}
+
+impl PropagationTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that `source` delivered block `hash` at `now`. Returns how far
+    /// behind the first delivery it arrived (zero if it was first), or `None`
+    /// if `source` already delivered this block.
+    pub fn record(&mut self, hash: Sha256Hash, source: &str, now: Instant) -> Option<Duration> {
+        let (delay, first) = match self.sightings.get_mut(&hash) {
+            Some(sighting) => {
+                if !sighting.sources.insert(source.to_string()) {
+                    return None;
+                }
+                (now.saturating_duration_since(sighting.first_seen), false)
+            }
+            None => {
+                self.sightings.insert(
+                    hash,
+                    Sighting {
+                        first_seen: now,
+                        sources: HashSet::from([source.to_string()]),
+                    },
+                );
+                self.order.push_back(hash);
+                if self.order.len() > TRACKED_BLOCKS {
+                    if let Some(oldest) = self.order.pop_front() {
+                        self.sightings.remove(&oldest);
+                    }
+                }
+                (Duration::ZERO, true)
+            }
+        };
+
+        if source != LOCAL_SOURCE {
+            let counters = self.peers.entry(source.to_string()).or_default();
+            counters.blocks_seen += 1;
+            if first {
+                counters.first_deliveries += 1;
+            } else {
+                counters.total_delay += delay;
+                counters.max_delay = counters.max_delay.max(delay);
+            }
+        }
+        Some(delay)
+    }
+
+    /// Per-peer statistics, best deliverers first
+    pub fn stats(&self) -> Vec<PeerPropagation> {
+        let mut stats: Vec<PeerPropagation> = self
+            .peers
+            .iter()
+            .map(|(peer, c)| {
+                let late = c.blocks_seen - c.first_deliveries;
+                PeerPropagation {
+                    peer: peer.clone(),
+                    blocks_seen: c.blocks_seen,
+                    first_deliveries: c.first_deliveries,
+                    first_rate: c.first_deliveries as f64 / c.blocks_seen.max(1) as f64,
+                    avg_delay_ms: if late == 0 {
+                        0.0
+                    } else {
+                        c.total_delay.as_secs_f64() * 1000.0 / late as f64
+                    },
+                    max_delay_ms: c.max_delay.as_millis() as u64,
+                }
+            })
+            .collect();
+        stats.sort_by(|a, b| {
+            b.first_rate
+                .partial_cmp(&a.first_rate)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(
+                    a.avg_delay_ms
+                        .partial_cmp(&b.avg_delay_ms)
+                        .unwrap_or(std::cmp::Ordering::Equal),
+                )
+                .then(a.peer.cmp(&b.peer))
+        });
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_delivery_and_delays() {
+        let mut tracker = PropagationTracker::new();
+        let start = Instant::now();
+
+        assert_eq!(
+            tracker.record([1; 32], "fast:8333", start),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            tracker.record([1; 32], "slow:8333", start + Duration::from_millis(300)),
+            Some(Duration::from_millis(300))
+        );
+        // Duplicate delivery from the same peer is ignored
+        assert_eq!(
+            tracker.record([1; 32], "slow:8333", start + Duration::from_millis(400)),
+            None
+        );
+
+        // A locally mined block credits no peer with a first delivery
+        tracker.record([2; 32], LOCAL_SOURCE, start);
+        tracker.record([2; 32], "fast:8333", start + Duration::from_millis(100));
+
+        let stats = tracker.stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].peer, "fast:8333");
+        assert_eq!(stats[0].blocks_seen, 2);
+        assert_eq!(stats[0].first_deliveries, 1);
+        assert_eq!(stats[0].avg_delay_ms, 100.0);
+        assert_eq!(stats[1].peer, "slow:8333");
+        assert_eq!(stats[1].first_rate, 0.0);
+        assert_eq!(stats[1].max_delay_ms, 300);
+    }
+
+    #[test]
+    fn test_old_blocks_are_forgotten() {
+        let mut tracker = PropagationTracker::new();
+        let start = Instant::now();
+        for i in 0..=TRACKED_BLOCKS {
+            let mut hash = [0u8; 32];
+            hash[..8].copy_from_slice(&(i as u64).to_le_bytes());
+            tracker.record(hash, "peer:1", start);
+        }
+        assert_eq!(tracker.sightings.len(), TRACKED_BLOCKS);
+
+        // The evicted first block counts as a fresh first delivery
+        assert_eq!(
+            tracker.record([0; 32], "peer:2", start),
+            Some(Duration::ZERO)
+        );
+    }
+}
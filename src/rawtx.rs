@@ -0,0 +1,242 @@
+//! Raw transactions: create, sign and broadcast as separate steps
+//!
+//! `send` builds, signs and submits in one go, so the signing key has to sit
+//! on a machine that can see the chain. Splitting the steps lets a watch-only
+//! machine pick the input and build an unsigned transaction, an offline
+//! machine holding the key sign it, and any node broadcast the result. The
+//! unsigned and signed forms travel as JSON (reviewable) or as the raw hex of
+//! [`Transaction::to_hex`].
+
+use crate::balance::OutpointLocker;
+use crate::blockchain::Sha256Hash;
+use crate::crypto::{Address, Signer};
+use crate::error::ChainError;
+use crate::geometry::{Coord, Triangle, GEOMETRIC_TOLERANCE};
+use crate::transaction::{BatchTransferTx, Transaction, TransferOutput, TransferTx};
+
+/// Encoding of a raw transaction file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawFormat {
+    Json,
+    Hex,
+}
+
+impl std::str::FromStr for RawFormat {
+    type Err = ChainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(RawFormat::Json),
+            "hex" => Ok(RawFormat::Hex),
+            other => Err(ChainError::InvalidTransaction(format!(
+                "Unknown raw transaction format '{}' (expected json or hex)",
+                other
+            ))),
+        }
+    }
+}
+
+/// Encode a transaction in `format`
+pub fn encode(tx: &Transaction, format: RawFormat) -> Result<String, ChainError> {
+    match format {
+        RawFormat::Json => serde_json::to_string_pretty(tx)
+            .map_err(|e| ChainError::InvalidTransaction(format!("Failed to encode: {}", e))),
+        RawFormat::Hex => tx.to_hex(),
+    }
+}
+
+/// Decode a transaction written by [`encode`] in either format
+pub fn decode(text: &str) -> Result<Transaction, ChainError> {
+    let text = text.trim();
+    if text.starts_with('{') {
+        serde_json::from_str(text).map_err(|e| {
+            ChainError::InvalidTransaction(format!("Raw transaction is not valid JSON: {}", e))
+        })
+    } else {
+        Transaction::from_hex(text)
+    }
+}
+
+/// The smallest unlocked triangle worth more than `total`, so the spend
+/// leaves change
+pub fn select_input<'a>(
+    owned: &'a [(Sha256Hash, Triangle)],
+    locker: &OutpointLocker,
+    total: Coord,
+) -> Option<&'a (Sha256Hash, Triangle)> {
+    owned
+        .iter()
+        .filter(|(hash, triangle)| {
+            !locker.is_locked(hash) && triangle.effective_value() - total >= GEOMETRIC_TOLERANCE
+        })
+        .min_by_key(|(_, triangle)| triangle.effective_value())
+}
+
+/// Build an unsigned payment spending `input_hash`: a transfer for one
+/// output, a batch transfer for several
+pub fn create(
+    input_hash: Sha256Hash,
+    sender: Address,
+    outputs: Vec<TransferOutput>,
+    fee: Coord,
+    nonce: u64,
+    memo: Option<String>,
+) -> Result<Transaction, ChainError> {
+    match outputs.as_slice() {
+        [] => Err(ChainError::InvalidTransaction(
+            "A transaction needs at least one output".to_string(),
+        )),
+        [output] => {
+            let mut tx = TransferTx::new(
+                input_hash,
+                output.recipient,
+                sender,
+                output.amount,
+                fee,
+                nonce,
+            );
+            if let Some(memo) = memo {
+                tx = tx.with_memo(memo)?;
+            }
+            Ok(Transaction::Transfer(tx))
+        }
+        _ if outputs.len() > BatchTransferTx::MAX_OUTPUTS => {
+            Err(ChainError::InvalidTransaction(format!(
+                "{} outputs; a batch pays at most {}",
+                outputs.len(),
+                BatchTransferTx::MAX_OUTPUTS
+            )))
+        }
+        _ => {
+            let mut tx = BatchTransferTx::new(input_hash, sender, outputs, fee, nonce);
+            if let Some(memo) = memo {
+                tx = tx.with_memo(memo)?;
+            }
+            Ok(Transaction::BatchTransfer(tx))
+        }
+    }
+}
+
+/// Address whose key must sign `tx`; coinbases are never signed
+pub fn signer(tx: &Transaction) -> Option<Address> {
+    match tx {
+        Transaction::Transfer(tx) => Some(tx.sender),
+        Transaction::Subdivision(tx) => Some(tx.owner_address),
+        Transaction::BatchTransfer(tx) => Some(tx.sender),
+        Transaction::Coinbase(_) => None,
+    }
+}
+
+/// Whether `tx` carries a signature
+pub fn is_signed(tx: &Transaction) -> bool {
+    match tx {
+        Transaction::Transfer(tx) => tx.signature.is_some(),
+        Transaction::Subdivision(tx) => tx.signature.is_some(),
+        Transaction::BatchTransfer(tx) => tx.signature.is_some(),
+        Transaction::Coinbase(_) => false,
+    }
+}
+
+/// Sign `tx` with `key` (a local keypair or an external signer), replacing
+/// any earlier signature. Fails if the key doesn't own the spent triangle.
+pub fn sign<S: Signer + ?Sized>(tx: &mut Transaction, key: &S) -> Result<(), ChainError> {
+    let expected = signer(tx).ok_or_else(|| {
+        ChainError::InvalidTransaction("Coinbase transactions are not signed".to_string())
+    })?;
+    let address = key.address()?;
+    if address != expected {
+        return Err(ChainError::WalletError(format!(
+            "Transaction spends from {}, not the signer's address {}",
+            expected, address
+        )));
+    }
+    let public_key = key.public_key()?.to_vec();
+    match tx {
+        Transaction::Transfer(tx) => {
+            let signature = key.sign(&tx.signable_message())?;
+            tx.sign(signature.to_vec(), public_key);
+        }
+        Transaction::Subdivision(tx) => {
+            let signature = key.sign(&tx.signable_message())?;
+            tx.sign(signature.to_vec(), public_key);
+        }
+        Transaction::BatchTransfer(tx) => {
+            let signature = key.sign(&tx.signable_message())?;
+            tx.sign(signature.to_vec(), public_key);
+        }
+        Transaction::Coinbase(_) => unreachable!(),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::KeyPair;
+
+    fn outputs(amounts: &[i32]) -> Vec<TransferOutput> {
+        amounts
+            .iter()
+            .enumerate()
+            .map(|(i, &amount)| TransferOutput {
+                recipient: Address::from_bytes([i as u8 + 1; 32]),
+                amount: Coord::from_num(amount),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_create_sign_roundtrip() {
+        let keypair = KeyPair::generate().unwrap();
+        for amounts in [&[5][..], &[1, 2, 3][..]] {
+            let unsigned = create(
+                [7; 32],
+                keypair.address(),
+                outputs(amounts),
+                Coord::from_num(1),
+                3,
+                Some("rent".to_string()),
+            )
+            .unwrap();
+            assert!(!is_signed(&unsigned));
+            assert!(unsigned.validate_stateless(u64::MAX).is_err());
+
+            // Unsigned transactions survive the trip to the signing machine
+            let hex = encode(&unsigned, RawFormat::Hex).unwrap();
+            let json = encode(&unsigned, RawFormat::Json).unwrap();
+            assert_eq!(decode(&json).unwrap().hash(), unsigned.hash());
+            let mut tx = decode(&hex).unwrap();
+            assert_eq!(tx.hash(), unsigned.hash());
+
+            sign(&mut tx, &keypair).unwrap();
+            assert!(is_signed(&tx));
+            tx.validate_stateless(u64::MAX).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_sign_rejects_foreign_key() {
+        let owner = KeyPair::generate().unwrap();
+        let other = KeyPair::generate().unwrap();
+        let mut tx = create(
+            [7; 32],
+            owner.address(),
+            outputs(&[5]),
+            Coord::from_num(0),
+            0,
+            None,
+        )
+        .unwrap();
+        assert!(sign(&mut tx, &other).is_err());
+        assert!(!is_signed(&tx));
+        assert!(create(
+            [7; 32],
+            owner.address(),
+            vec![],
+            Coord::from_num(0),
+            0,
+            None
+        )
+        .is_err());
+    }
+}
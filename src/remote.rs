@@ -0,0 +1,490 @@
+//! Remote node client for TrinityChain wallet tools
+//!
+//! Lets the CLI query a node's HTTP API (balance, history, UTXOs) and submit
+//! transactions without opening the local database. Only plain `http://`
+//! URLs are supported; put a TLS-terminating proxy in front for anything else.
+
+use crate::blockchain::{Sha256Hash, TxStatus};
+use crate::error::ChainError;
+use crate::forks::ForkTip;
+use crate::geometry::Triangle;
+use crate::sync::SyncState;
+use crate::transaction::Transaction;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// Environment variable holding the default node URL
+pub const NODE_URL_ENV: &str = "TRINITY_NODE_URL";
+/// Environment variable holding the API bearer token
+pub const API_TOKEN_ENV: &str = "TRINITY_API_TOKEN";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A confirmed or pending transaction touching an address
+#[derive(Debug, Clone, Deserialize)]
+pub struct HistoryEntry {
+    pub transaction: Transaction,
+    /// Containing block height (0 for mempool entries)
+    pub block_height: u64,
+    /// Containing block timestamp (0 for mempool entries)
+    #[serde(default)]
+    pub timestamp: u64,
+}
+
+/// An unspent triangle owned by an address
+#[derive(Debug, Clone, Deserialize)]
+pub struct OwnedTriangle {
+    pub hash: String,
+    pub triangle: Triangle,
+    /// Height a still immature coinbase triangle becomes spendable at
+    #[serde(default)]
+    pub spendable_at: Option<u64>,
+    /// Confirmations of a triangle received too recently to be settled
+    #[serde(default)]
+    pub confirmations: Option<u64>,
+}
+
+/// Owned triangles, plus the hashes of the immature coinbase rewards among them
+pub type TrianglesWithMaturity = (Vec<(Sha256Hash, Triangle)>, HashSet<Sha256Hash>);
+
+#[derive(Deserialize)]
+struct BalanceBody {
+    balance: String,
+}
+
+#[derive(Deserialize)]
+struct HistoryBody {
+    transactions: Vec<HistoryEntry>,
+}
+
+#[derive(Deserialize)]
+struct TrianglesBody {
+    triangles: Vec<OwnedTriangle>,
+}
+
+#[derive(Deserialize)]
+struct MemoSearchBody {
+    transactions: Vec<HistoryEntry>,
+    total: u64,
+}
+
+#[derive(Deserialize)]
+struct ForksBody {
+    tips: Vec<ForkTip>,
+}
+
+#[derive(Deserialize)]
+struct MempoolBody {
+    transactions: Vec<Transaction>,
+}
+
+/// Client for a remote node's REST API
+#[derive(Debug, Clone)]
+pub struct RemoteNode {
+    host: String,
+    port: u16,
+    base_path: String,
+    token: Option<String>,
+}
+
+impl RemoteNode {
+    /// Parse an `http://host[:port][/prefix]` URL
+    pub fn new(url: &str) -> Result<Self, ChainError> {
+        let rest = if let Some(rest) = url.strip_prefix("http://") {
+            rest
+        } else if url.starts_with("https://") {
+            return Err(ChainError::NetworkError(
+                "https node URLs are not supported; use http:// or a local proxy".to_string(),
+            ));
+        } else {
+            url
+        };
+
+        let (authority, path) = match rest.find('/') {
+            Some(idx) => (&rest[..idx], rest[idx..].trim_end_matches('/')),
+            None => (rest, ""),
+        };
+        if authority.is_empty() {
+            return Err(ChainError::NetworkError(format!("Invalid node URL: {}", url)));
+        }
+
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => {
+                let port = port
+                    .parse()
+                    .map_err(|e| ChainError::NetworkError(format!("Invalid port in {}: {}", url, e)))?;
+                (host.to_string(), port)
+            }
+            None => (authority.to_string(), 80),
+        };
+
+        Ok(RemoteNode {
+            host,
+            port,
+            base_path: path.to_string(),
+            token: std::env::var(API_TOKEN_ENV).ok().filter(|t| !t.is_empty()),
+        })
+    }
+
+    /// Use the given bearer token for authenticated endpoints
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    /// Build a client from a `--node-url <url>` argument (removed from `args`),
+    /// the `TRINITY_NODE_URL` environment variable or `[client] node_url` in
+    /// `config.toml`, in that order. Returns `None` when none is set so
+    /// callers fall back to the local database.
+    pub fn from_args_or_env(args: &mut Vec<String>) -> Result<Option<Self>, ChainError> {
+        if let Some(pos) = args.iter().position(|a| a == "--node-url") {
+            if pos + 1 >= args.len() {
+                return Err(ChainError::NetworkError(
+                    "--node-url requires a value".to_string(),
+                ));
+            }
+            let url = args.remove(pos + 1);
+            args.remove(pos);
+            return Self::new(&url).map(Some);
+        }
+        Self::from_url_or_env(None)
+    }
+
+    /// `from_args_or_env` honouring offline mode (see [`crate::drafts`]): no
+    /// client is ever built, an explicit `--node-url` is an error and the
+    /// environment and config defaults are ignored
+    pub fn from_args_unless_offline(
+        args: &mut Vec<String>,
+        offline: bool,
+    ) -> Result<Option<Self>, ChainError> {
+        if !offline {
+            return Self::from_args_or_env(args);
+        }
+        if args.iter().any(|a| a == "--node-url") {
+            return Err(ChainError::NetworkError(
+                "--offline cannot be combined with --node-url".to_string(),
+            ));
+        }
+        Ok(None)
+    }
+
+    /// `from_url_or_env` honouring offline mode, like `from_args_unless_offline`
+    pub fn from_url_unless_offline(
+        url: Option<String>,
+        offline: bool,
+    ) -> Result<Option<Self>, ChainError> {
+        match (offline, url) {
+            (false, url) => Self::from_url_or_env(url),
+            (true, Some(_)) => Err(ChainError::NetworkError(
+                "--offline cannot be combined with --node-url".to_string(),
+            )),
+            (true, None) => Ok(None),
+        }
+    }
+
+    /// Like `from_args_or_env`, for tools that parse `--node-url` themselves
+    pub fn from_url_or_env(url: Option<String>) -> Result<Option<Self>, ChainError> {
+        if let Some(url) = url {
+            return Self::new(&url).map(Some);
+        }
+        match std::env::var(NODE_URL_ENV) {
+            Ok(url) if !url.is_empty() => Self::new(&url).map(Some),
+            _ => match crate::config::load_client_config().node_url {
+                Some(url) if !url.is_empty() => Self::new(&url).map(Some),
+                _ => Ok(None),
+            },
+        }
+    }
+
+    /// The node URL this client talks to
+    pub fn url(&self) -> String {
+        format!("http://{}:{}{}", self.host, self.port, self.base_path)
+    }
+
+    /// Current chain height
+    pub fn height(&self) -> Result<u64, ChainError> {
+        self.get_json("/api/blockchain/height")
+    }
+
+    /// Balance of an address, as reported by the node
+    pub fn balance(&self, address_hex: &str) -> Result<String, ChainError> {
+        let body: BalanceBody = self.get_json(&format!("/api/address/{}/balance", address_hex))?;
+        Ok(body.balance)
+    }
+
+    /// Unspent triangles owned by an address
+    pub fn triangles(&self, address_hex: &str) -> Result<Vec<(Sha256Hash, Triangle)>, ChainError> {
+        Ok(self.triangles_with_maturity(address_hex)?.0)
+    }
+
+    /// Unspent triangles owned by an address, plus the hashes of those that
+    /// are coinbase rewards still inside the maturity window
+    pub fn triangles_with_maturity(
+        &self,
+        address_hex: &str,
+    ) -> Result<TrianglesWithMaturity, ChainError> {
+        Ok(self.triangles_with_confirmations(address_hex)?.0)
+    }
+
+    /// Like [`RemoteNode::triangles_with_maturity`], plus the confirmations
+    /// of the triangles the node reports as not yet settled
+    pub fn triangles_with_confirmations(
+        &self,
+        address_hex: &str,
+    ) -> Result<(TrianglesWithMaturity, HashMap<Sha256Hash, u64>), ChainError> {
+        let body: TrianglesBody =
+            self.get_json(&format!("/api/address/{}/triangles", address_hex))?;
+        let mut owned = Vec::with_capacity(body.triangles.len());
+        let mut immature = HashSet::new();
+        let mut confirmations = HashMap::new();
+        for t in body.triangles {
+            let mut hash = [0u8; 32];
+            hex::decode_to_slice(&t.hash, &mut hash).map_err(|e| {
+                ChainError::NetworkError(format!("Invalid triangle hash from node: {}", e))
+            })?;
+            if t.spendable_at.is_some() {
+                immature.insert(hash);
+            }
+            if let Some(count) = t.confirmations {
+                confirmations.insert(hash, count);
+            }
+            owned.push((hash, t.triangle));
+        }
+        Ok(((owned, immature), confirmations))
+    }
+
+    /// Confirmed and pending transactions touching an address
+    pub fn history(&self, address_hex: &str) -> Result<Vec<HistoryEntry>, ChainError> {
+        let body: HistoryBody =
+            self.get_json(&format!("/api/address/{}/transactions", address_hex))?;
+        Ok(body.transactions)
+    }
+
+    /// Confirmed transactions whose memo contains `query`, newest first,
+    /// with the total number of matches
+    pub fn search_memos(
+        &self,
+        query: &str,
+        limit: u64,
+    ) -> Result<(Vec<HistoryEntry>, u64), ChainError> {
+        let body: MemoSearchBody = self.get_json(&format!(
+            "/api/search/memo?q={}&limit={}",
+            encode_query(query),
+            limit
+        ))?;
+        Ok((body.transactions, body.total))
+    }
+
+    /// Transactions currently in the node's mempool
+    pub fn mempool(&self) -> Result<Vec<Transaction>, ChainError> {
+        let body: MempoolBody = self.get_json("/api/mempool")?;
+        Ok(body.transactions)
+    }
+
+    /// Every chain tip the node knows of, the active one first
+    pub fn forks(&self) -> Result<Vec<ForkTip>, ChainError> {
+        let body: ForksBody = self.get_json("/api/forks")?;
+        Ok(body.tips)
+    }
+
+    /// The node's height against its best peer's, with sync speed and ETA
+    pub fn sync_status(&self) -> Result<SyncState, ChainError> {
+        self.get_json("/api/sync/status")
+    }
+
+    /// Whether a transaction is pending, confirmed or unknown to the node
+    pub fn transaction_status(&self, hash_hex: &str) -> Result<TxStatus, ChainError> {
+        self.get_json(&format!("/api/transaction/{}/status", hash_hex))
+    }
+
+    /// Submit a signed transaction to the node's mempool
+    pub fn submit_transaction(&self, tx: &Transaction) -> Result<(), ChainError> {
+        self.post_json("/api/transaction", tx)?;
+        Ok(())
+    }
+
+    /// `POST` a JSON body to `path` and return the response body
+    pub fn post_json<T: Serialize>(&self, path: &str, body: &T) -> Result<String, ChainError> {
+        let body = serde_json::to_string(body)
+            .map_err(|e| ChainError::NetworkError(format!("Failed to serialize request: {}", e)))?;
+        self.request("POST", path, Some(&body))
+    }
+
+    fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T, ChainError> {
+        let body = self.request("GET", path, None)?;
+        serde_json::from_str(&body)
+            .map_err(|e| ChainError::NetworkError(format!("Invalid response from node: {}", e)))
+    }
+
+    fn request(&self, method: &str, path: &str, body: Option<&str>) -> Result<String, ChainError> {
+        let mut stream = TcpStream::connect((self.host.as_str(), self.port)).map_err(|e| {
+            ChainError::NetworkError(format!("Failed to connect to {}: {}", self.url(), e))
+        })?;
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+
+        let target = format!("{}{}", self.base_path, path);
+        let mut request = format!(
+            "{} {} HTTP/1.1\r\nHost: {}:{}\r\nAccept: application/json\r\nConnection: close\r\n",
+            method,
+            if target.is_empty() { "/" } else { &target },
+            self.host,
+            self.port
+        );
+        if let Some(token) = &self.token {
+            request.push_str(&format!("Authorization: Bearer {}\r\n", token));
+        }
+        let body = body.unwrap_or("");
+        if method != "GET" {
+            request.push_str("Content-Type: application/json\r\n");
+            request.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        }
+        request.push_str("\r\n");
+        request.push_str(body);
+
+        stream.write_all(request.as_bytes())?;
+
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw)?;
+
+        let (status, body) = parse_response(&raw)?;
+        if !(200..300).contains(&status) {
+            let message = serde_json::from_str::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|v| v["error"].as_str().map(str::to_string))
+                .unwrap_or(body);
+            return Err(match status {
+                401 | 403 => ChainError::AuthenticationError(message),
+                _ => ChainError::ApiError(format!("{} (HTTP {})", message, status)),
+            });
+        }
+        Ok(body)
+    }
+}
+
+/// Split a raw HTTP/1.1 response into status code and (de-chunked) body
+fn parse_response(raw: &[u8]) -> Result<(u16, String), ChainError> {
+    let text = String::from_utf8_lossy(raw);
+    let (head, body) = text
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| ChainError::NetworkError("Malformed HTTP response".to_string()))?;
+
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|l| l.split_whitespace().nth(1))
+        .and_then(|s| s.parse::<u16>().ok())
+        .ok_or_else(|| ChainError::NetworkError("Malformed HTTP status line".to_string()))?;
+
+    let chunked = lines.any(|l| {
+        let l = l.to_ascii_lowercase();
+        l.starts_with("transfer-encoding:") && l.contains("chunked")
+    });
+
+    let body = if chunked {
+        decode_chunked(body)?
+    } else {
+        body.to_string()
+    };
+    Ok((status, body))
+}
+
+/// Percent-encode a query string value
+fn encode_query(value: &str) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn decode_chunked(mut body: &str) -> Result<String, ChainError> {
+    let mut out = String::new();
+    loop {
+        let (size_line, rest) = body
+            .split_once("\r\n")
+            .ok_or_else(|| ChainError::NetworkError("Malformed chunked body".to_string()))?;
+        let size = usize::from_str_radix(size_line.split(';').next().unwrap_or("").trim(), 16)
+            .map_err(|e| ChainError::NetworkError(format!("Invalid chunk size: {}", e)))?;
+        if size == 0 {
+            return Ok(out);
+        }
+        if rest.len() < size {
+            return Err(ChainError::NetworkError("Truncated chunked body".to_string()));
+        }
+        out.push_str(&rest[..size]);
+        body = rest[size..].trim_start_matches("\r\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_node_url() {
+        let node = RemoteNode::new("http://127.0.0.1:3000").unwrap();
+        assert_eq!(node.host, "127.0.0.1");
+        assert_eq!(node.port, 3000);
+        assert_eq!(node.base_path, "");
+
+        let node = RemoteNode::new("node.example.org/trinity/").unwrap();
+        assert_eq!(node.host, "node.example.org");
+        assert_eq!(node.port, 80);
+        assert_eq!(node.base_path, "/trinity");
+
+        assert!(RemoteNode::new("https://node.example.org").is_err());
+        assert!(RemoteNode::new("http://host:notaport").is_err());
+    }
+
+    #[test]
+    fn test_node_url_from_args() {
+        let mut args = vec![
+            "trinity-balance".to_string(),
+            "--node-url".to_string(),
+            "http://10.0.0.1:3000".to_string(),
+            "abcd".to_string(),
+        ];
+        let node = RemoteNode::from_args_or_env(&mut args).unwrap().unwrap();
+        assert_eq!(node.port, 3000);
+        assert_eq!(args, vec!["trinity-balance".to_string(), "abcd".to_string()]);
+    }
+
+    #[test]
+    fn test_offline_never_builds_a_client() {
+        let mut args = vec!["trinity-balance".to_string(), "abcd".to_string()];
+        assert!(RemoteNode::from_args_unless_offline(&mut args, true)
+            .unwrap()
+            .is_none());
+        args.push("--node-url".to_string());
+        args.push("http://10.0.0.1:3000".to_string());
+        assert!(RemoteNode::from_args_unless_offline(&mut args, true).is_err());
+        assert!(RemoteNode::from_url_unless_offline(Some("http://10.0.0.1".into()), true).is_err());
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let raw = b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\n42";
+        assert_eq!(parse_response(raw).unwrap(), (200, "42".to_string()));
+
+        let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\n{\"a\"\r\n3\r\n:1}\r\n0\r\n\r\n";
+        assert_eq!(parse_response(raw).unwrap(), (200, "{\"a\":1}".to_string()));
+
+        assert!(parse_response(b"garbage").is_err());
+    }
+
+    #[test]
+    fn test_encode_query() {
+        assert_eq!(encode_query("order-42"), "order-42");
+        assert_eq!(encode_query("a&b #1"), "a%26b%20%231");
+        assert_eq!(encode_query("é"), "%C3%A9");
+    }
+}
@@ -0,0 +1,806 @@
+//! Hot standby replication
+//!
+//! A primary node serves its chain to standbys on a dedicated replication
+//! port. A standby connects with the shared secret from `[replication]`,
+//! receives every block after the point where its chain joins the
+//! primary's, validates and applies each one itself, and then follows new
+//! blocks (and reorgs) as the primary accepts them. Heartbeats carry the
+//! primary's tip hash and UTXO count, so a standby that ever diverges notices
+//! at once instead of serving a different state.
+//!
+//! Both sides prove they know the secret by hashing it with a nonce the other
+//! side picked, so the secret never crosses the wire and a recorded session
+//! can't be replayed. When the primary stays silent for the failover timeout
+//! the standby reports it lost; it is promoted by the operator or, with
+//! `auto_promote`, by itself, after which it stops following and starts
+//! serving like any other node.
+
+use crate::blockchain::{Block, Blockchain, Sha256Hash};
+use crate::error::ChainError;
+use crate::persistence::Database;
+use crate::security::{constant_time_eq, SecretBytes};
+use crate::shutdown::Shutdown;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+/// Shortest shared secret accepted
+pub const MIN_SECRET_LEN: usize = 16;
+
+/// Silence after which a standby counts the primary as lost
+pub const DEFAULT_FAILOVER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often an idle primary proves it is alive
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often the primary looks for new blocks to stream
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Blocks streamed per chain lock, so a standby catching up from genesis
+/// doesn't starve the primary's other tasks
+const BATCH_BLOCKS: usize = 500;
+
+/// Streamed block hashes the primary remembers per standby; a reorg deeper
+/// than this makes the standby reconnect and resync from its locator
+const SENT_HISTORY: usize = 1_000;
+
+/// Largest frame accepted on the replication channel
+const MAX_FRAME_SIZE: usize = 10 * 1024 * 1024;
+
+/// Time allowed to connect and authenticate
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pause between a standby's reconnect attempts
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+const PRIMARY_ROLE: &[u8] = b"primary";
+const STANDBY_ROLE: &[u8] = b"standby";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum ReplicationMessage {
+    /// Primary, on connect: the nonce the standby proves the secret over
+    Challenge {
+        nonce: [u8; 32],
+        genesis_hash: Sha256Hash,
+    },
+    /// Standby: its proof, the nonce the primary must prove the secret over,
+    /// and a locator of its chain (see [`locator`])
+    Subscribe {
+        proof: Sha256Hash,
+        nonce: [u8; 32],
+        locator: Vec<Sha256Hash>,
+    },
+    /// Primary: its proof; blocks follow
+    Welcome {
+        proof: Sha256Hash,
+    },
+    Block(Box<Block>),
+    /// Primary, once the standby has every block: the state it should have
+    Heartbeat {
+        height: u64,
+        tip_hash: Sha256Hash,
+        utxo_count: usize,
+    },
+    /// The session can't continue; the reason is shown to the operator
+    Rejected(String),
+}
+
+/// Fail unless `secret` is long enough to authenticate replication
+pub fn check_secret(secret: &SecretBytes) -> Result<(), ChainError> {
+    if secret.len() < MIN_SECRET_LEN {
+        return Err(ChainError::NetworkError(format!(
+            "Replication secret must be at least {} characters",
+            MIN_SECRET_LEN
+        )));
+    }
+    Ok(())
+}
+
+/// Proof that the `role` side knows `secret`, bound to the other side's nonce
+fn proof(secret: &SecretBytes, role: &[u8], nonce: &[u8; 32]) -> Sha256Hash {
+    let mut hasher = Sha256::new();
+    hasher.update(b"trinitychain-replication");
+    hasher.update(role);
+    hasher.update(nonce);
+    hasher.update(secret.expose());
+    hasher.finalize().into()
+}
+
+/// Hashes of `chain` from the tip back: the last 16 blocks one by one, then
+/// at doubling distances, always ending with genesis
+pub fn locator(chain: &Blockchain) -> Vec<Sha256Hash> {
+    let mut hashes = Vec::new();
+    let Some(mut index) = chain.blocks.len().checked_sub(1) else {
+        return hashes;
+    };
+    let mut step = 1;
+    loop {
+        hashes.push(chain.blocks[index].hash());
+        if index == 0 {
+            return hashes;
+        }
+        if hashes.len() >= 16 {
+            step *= 2;
+        }
+        index = index.saturating_sub(step);
+    }
+}
+
+/// Index in `chain` of the first `locator` hash it contains
+fn common_index(chain: &Blockchain, locator: &[Sha256Hash]) -> Option<usize> {
+    let index: HashMap<Sha256Hash, usize> = chain
+        .blocks
+        .iter()
+        .enumerate()
+        .map(|(i, b)| (b.hash(), i))
+        .collect();
+    locator.iter().find_map(|hash| index.get(hash).copied())
+}
+
+fn io_error(e: std::io::Error) -> ChainError {
+    ChainError::NetworkError(format!("Replication connection failed: {}", e))
+}
+
+fn unexpected(message: &ReplicationMessage) -> ChainError {
+    ChainError::NetworkError(format!("Unexpected replication message: {:?}", message))
+}
+
+async fn write_message(
+    stream: &mut TcpStream,
+    message: &ReplicationMessage,
+) -> Result<(), ChainError> {
+    let data = bincode::serialize(message)
+        .map_err(|e| ChainError::NetworkError(format!("Failed to encode message: {}", e)))?;
+    stream
+        .write_all(&(data.len() as u32).to_be_bytes())
+        .await
+        .map_err(io_error)?;
+    stream.write_all(&data).await.map_err(io_error)
+}
+
+async fn read_message(stream: &mut TcpStream) -> Result<ReplicationMessage, ChainError> {
+    let mut len = [0u8; 4];
+    stream.read_exact(&mut len).await.map_err(io_error)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_SIZE {
+        return Err(ChainError::NetworkError(format!(
+            "Replication frame of {} bytes exceeds the {} byte limit",
+            len, MAX_FRAME_SIZE
+        )));
+    }
+    let mut data = vec![0u8; len];
+    stream.read_exact(&mut data).await.map_err(io_error)?;
+    bincode::deserialize(&data)
+        .map_err(|e| ChainError::NetworkError(format!("Malformed replication message: {}", e)))
+}
+
+/// Next message, failing if none arrives within `wait`
+async fn read_within(
+    stream: &mut TcpStream,
+    wait: Duration,
+) -> Result<ReplicationMessage, ChainError> {
+    tokio::time::timeout(wait, read_message(stream))
+        .await
+        .map_err(|_| {
+            ChainError::NetworkError(format!("No replication message for {}s", wait.as_secs()))
+        })?
+}
+
+/// Serve `blockchain` to standbys on `port` until `shutdown` triggers
+pub async fn serve(
+    blockchain: Arc<RwLock<Blockchain>>,
+    port: u16,
+    secret: SecretBytes,
+    shutdown: Shutdown,
+) -> Result<(), ChainError> {
+    check_secret(&secret)?;
+    let addr = format!("0.0.0.0:{}", port);
+    let listener = TcpListener::bind(&addr)
+        .await
+        .map_err(|e| ChainError::NetworkError(format!("Failed to bind: {}", e)))?;
+    info!("🪞 Replication listening on {}", addr);
+
+    let secret = Arc::new(secret);
+    loop {
+        let (stream, peer_addr) = tokio::select! {
+            accepted = listener.accept() => accepted.map_err(io_error)?,
+            _ = shutdown.cancelled() => {
+                info!("🛑 Stopped replication on {}", addr);
+                return Ok(());
+            }
+        };
+        let blockchain = blockchain.clone();
+        let secret = secret.clone();
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            match serve_standby(stream, blockchain, &secret, shutdown).await {
+                Ok(()) => info!("🪞 Standby {} disconnected", peer_addr),
+                Err(e) => warn!("⚠️  Standby {} dropped: {}", peer_addr, e),
+            }
+        });
+    }
+}
+
+/// Authenticate one standby, then stream blocks and heartbeats to it
+async fn serve_standby(
+    mut stream: TcpStream,
+    blockchain: Arc<RwLock<Blockchain>>,
+    secret: &SecretBytes,
+    shutdown: Shutdown,
+) -> Result<(), ChainError> {
+    let nonce: [u8; 32] = rand::random();
+    let genesis_hash = blockchain
+        .read()
+        .await
+        .blocks
+        .first()
+        .map(|b| b.hash())
+        .unwrap_or_default();
+    write_message(
+        &mut stream,
+        &ReplicationMessage::Challenge {
+            nonce,
+            genesis_hash,
+        },
+    )
+    .await?;
+
+    let (standby_proof, standby_nonce, locator) =
+        match read_within(&mut stream, HANDSHAKE_TIMEOUT).await? {
+            ReplicationMessage::Subscribe {
+                proof,
+                nonce,
+                locator,
+            } => (proof, nonce, locator),
+            other => return Err(unexpected(&other)),
+        };
+    if !constant_time_eq(&standby_proof, &proof(secret, STANDBY_ROLE, &nonce)) {
+        let reason = "authentication failed".to_string();
+        let _ = write_message(&mut stream, &ReplicationMessage::Rejected(reason)).await;
+        return Err(ChainError::NetworkError(
+            "Standby failed authentication".to_string(),
+        ));
+    }
+    let fork = common_index(&*blockchain.read().await, &locator);
+    let Some(fork) = fork else {
+        let reason = "no block in common with the primary".to_string();
+        let _ = write_message(&mut stream, &ReplicationMessage::Rejected(reason)).await;
+        return Err(ChainError::ForkNotFound);
+    };
+    write_message(
+        &mut stream,
+        &ReplicationMessage::Welcome {
+            proof: proof(secret, PRIMARY_ROLE, &standby_nonce),
+        },
+    )
+    .await?;
+    info!(
+        "🪞 Standby authenticated, streaming from block index {}",
+        fork + 1
+    );
+
+    // Hashes of the standby's chain, newest last
+    let mut sent = VecDeque::from([blockchain.read().await.blocks[fork].hash()]);
+    let mut last_heartbeat: Option<Instant> = None;
+    loop {
+        let (blocks, heartbeat) = {
+            let chain = blockchain.read().await;
+            // Where the standby's chain joins ours, after any reorg
+            let joined = sent
+                .iter()
+                .rev()
+                .find_map(|hash| chain.block_index.get(hash).map(|&index| (index, *hash)));
+            let Some((index, hash)) = joined else {
+                drop(chain);
+                let reason = format!("reorg deeper than {} blocks", SENT_HISTORY);
+                let _ = write_message(&mut stream, &ReplicationMessage::Rejected(reason)).await;
+                return Err(ChainError::ForkNotFound);
+            };
+            while sent.back() != Some(&hash) {
+                sent.pop_back();
+            }
+            let end = chain.blocks.len().min(index + 1 + BATCH_BLOCKS);
+            let blocks = chain.blocks[index + 1..end].to_vec();
+            let heartbeat = (end == chain.blocks.len()).then(|| {
+                let tip = &chain.blocks[end - 1];
+                ReplicationMessage::Heartbeat {
+                    height: tip.header.height,
+                    tip_hash: tip.hash(),
+                    utxo_count: chain.state.utxo_set.len(),
+                }
+            });
+            (blocks, heartbeat)
+        };
+
+        let streamed = !blocks.is_empty();
+        for block in blocks {
+            sent.push_back(block.hash());
+            if sent.len() > SENT_HISTORY {
+                sent.pop_front();
+            }
+            write_message(&mut stream, &ReplicationMessage::Block(Box::new(block))).await?;
+        }
+        if let Some(heartbeat) = heartbeat {
+            if streamed || last_heartbeat.is_none_or(|t| t.elapsed() >= HEARTBEAT_INTERVAL) {
+                write_message(&mut stream, &heartbeat).await?;
+                last_heartbeat = Some(Instant::now());
+            }
+        } else {
+            // Still catching up: stream the next batch right away
+            continue;
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            _ = shutdown.cancelled() => return Ok(()),
+        }
+    }
+}
+
+/// Where a standby is in following its primary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StandbyState {
+    /// Connecting to or authenticating with the primary
+    Connecting,
+    /// Receiving blocks it doesn't have yet
+    Syncing,
+    /// In lockstep with the primary's last heartbeat
+    Following,
+    /// The primary has been silent for the failover timeout
+    PrimaryLost,
+    /// No longer following; serving on its own
+    Promoted,
+}
+
+impl StandbyState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StandbyState::Connecting => "connecting",
+            StandbyState::Syncing => "syncing",
+            StandbyState::Following => "following",
+            StandbyState::PrimaryLost => "primary lost",
+            StandbyState::Promoted => "promoted",
+        }
+    }
+}
+
+/// A standby's view of its replication, for status displays
+#[derive(Debug, Clone, Serialize)]
+pub struct StandbyStatus {
+    pub state: StandbyState,
+    /// Primary replication address (`host:port`)
+    pub primary: String,
+    /// Tip height in the primary's last heartbeat
+    pub primary_height: Option<u64>,
+    /// Unix time of the primary's last heartbeat
+    pub last_heartbeat: Option<u64>,
+    /// Why the last session ended, if it failed
+    pub last_error: Option<String>,
+}
+
+/// Why [`Standby::follow`] returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FollowOutcome {
+    /// The standby should start serving in the primary's place
+    Promoted,
+    Shutdown,
+}
+
+/// A node following a primary over the replication channel
+pub struct Standby {
+    blockchain: Arc<RwLock<Blockchain>>,
+    primary: String,
+    secret: SecretBytes,
+    failover_timeout: Duration,
+    auto_promote: bool,
+    /// Database the followed chain is flushed to after each heartbeat
+    db_path: Option<String>,
+    shutdown: Shutdown,
+    /// Triggered by [`Standby::promote`]
+    promotion: Shutdown,
+    status: parking_lot::Mutex<StandbyStatus>,
+    /// Tip last flushed to the database
+    flushed: parking_lot::Mutex<Option<Sha256Hash>>,
+}
+
+impl Standby {
+    /// Follow the primary replicating on `primary` (`host:port`)
+    pub fn new(
+        blockchain: Arc<RwLock<Blockchain>>,
+        primary: String,
+        secret: SecretBytes,
+    ) -> Result<Self, ChainError> {
+        check_secret(&secret)?;
+        Ok(Standby {
+            blockchain,
+            status: parking_lot::Mutex::new(StandbyStatus {
+                state: StandbyState::Connecting,
+                primary: primary.clone(),
+                primary_height: None,
+                last_heartbeat: None,
+                last_error: None,
+            }),
+            primary,
+            secret,
+            failover_timeout: DEFAULT_FAILOVER_TIMEOUT,
+            auto_promote: false,
+            db_path: None,
+            shutdown: Shutdown::new(),
+            promotion: Shutdown::new(),
+            flushed: parking_lot::Mutex::new(None),
+        })
+    }
+
+    /// Count the primary as lost after `timeout` without a message
+    pub fn with_failover_timeout(mut self, timeout: Duration) -> Self {
+        self.failover_timeout = timeout;
+        self
+    }
+
+    /// Promote without waiting for the operator once the primary is lost
+    pub fn with_auto_promote(mut self, auto_promote: bool) -> Self {
+        self.auto_promote = auto_promote;
+        self
+    }
+
+    /// Keep the database at `path` in step with the followed chain
+    pub fn with_database(mut self, path: &str) -> Self {
+        self.db_path = Some(path.to_string());
+        self
+    }
+
+    /// Stop following when `shutdown` is triggered
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    pub fn status(&self) -> StandbyStatus {
+        self.status.lock().clone()
+    }
+
+    /// Stop following; [`Standby::follow`] returns `Promoted`
+    pub fn promote(&self) {
+        self.promotion.trigger();
+    }
+
+    /// Follow the primary, reconnecting as needed, until promoted or shut down
+    pub async fn follow(&self) -> FollowOutcome {
+        let mut last_contact = Instant::now();
+        loop {
+            let result = tokio::select! {
+                result = self.session(&mut last_contact) => result,
+                _ = self.promotion.cancelled() => return self.promoted("promoted by the operator"),
+                _ = self.shutdown.cancelled() => return FollowOutcome::Shutdown,
+            };
+            if let Err(e) = result {
+                warn!("⚠️  Replication from {} interrupted: {}", self.primary, e);
+                self.status.lock().last_error = Some(e.to_string());
+            }
+
+            if last_contact.elapsed() >= self.failover_timeout {
+                if self.auto_promote {
+                    return self.promoted("the primary is lost");
+                }
+                let mut status = self.status.lock();
+                if status.state != StandbyState::PrimaryLost {
+                    warn!(
+                        "🚨 Primary {} silent for {}s; waiting for it or for promotion",
+                        self.primary,
+                        last_contact.elapsed().as_secs()
+                    );
+                    status.state = StandbyState::PrimaryLost;
+                }
+            } else {
+                self.status.lock().state = StandbyState::Connecting;
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(RECONNECT_DELAY) => {}
+                _ = self.promotion.cancelled() => return self.promoted("promoted by the operator"),
+                _ = self.shutdown.cancelled() => return FollowOutcome::Shutdown,
+            }
+        }
+    }
+
+    fn promoted(&self, reason: &str) -> FollowOutcome {
+        info!("👑 Standby promoted: {}", reason);
+        self.status.lock().state = StandbyState::Promoted;
+        FollowOutcome::Promoted
+    }
+
+    /// One authenticated session; `last_contact` is bumped on every message
+    async fn session(&self, last_contact: &mut Instant) -> Result<(), ChainError> {
+        let mut stream = tokio::time::timeout(HANDSHAKE_TIMEOUT, TcpStream::connect(&self.primary))
+            .await
+            .map_err(|_| ChainError::NetworkError("Timed out connecting".to_string()))?
+            .map_err(io_error)?;
+        let (nonce, genesis_hash) = match read_within(&mut stream, HANDSHAKE_TIMEOUT).await? {
+            ReplicationMessage::Challenge {
+                nonce,
+                genesis_hash,
+            } => (nonce, genesis_hash),
+            other => return Err(unexpected(&other)),
+        };
+        let locator = {
+            let chain = self.blockchain.read().await;
+            if chain.blocks.first().map(|b| b.hash()) != Some(genesis_hash) {
+                return Err(ChainError::NetworkError(
+                    "The primary runs a different genesis block".to_string(),
+                ));
+            }
+            locator(&chain)
+        };
+        let our_nonce: [u8; 32] = rand::random();
+        write_message(
+            &mut stream,
+            &ReplicationMessage::Subscribe {
+                proof: proof(&self.secret, STANDBY_ROLE, &nonce),
+                nonce: our_nonce,
+                locator,
+            },
+        )
+        .await?;
+        match read_within(&mut stream, HANDSHAKE_TIMEOUT).await? {
+            ReplicationMessage::Welcome { proof: theirs } => {
+                if !constant_time_eq(&theirs, &proof(&self.secret, PRIMARY_ROLE, &our_nonce)) {
+                    return Err(ChainError::NetworkError(
+                        "Primary failed authentication".to_string(),
+                    ));
+                }
+            }
+            ReplicationMessage::Rejected(reason) => {
+                return Err(ChainError::NetworkError(format!(
+                    "Primary refused replication: {}",
+                    reason
+                )))
+            }
+            other => return Err(unexpected(&other)),
+        }
+        *last_contact = Instant::now();
+        info!("🪞 Replicating from primary {}", self.primary);
+        {
+            let mut status = self.status.lock();
+            status.state = StandbyState::Syncing;
+            status.last_error = None;
+        }
+
+        // Blocks of a primary-side reorg not yet longer than our chain
+        let mut branch = Vec::new();
+        loop {
+            let wait = self.failover_timeout.saturating_sub(last_contact.elapsed());
+            let message = read_within(&mut stream, wait).await?;
+            *last_contact = Instant::now();
+            match message {
+                ReplicationMessage::Block(block) => {
+                    self.status.lock().state = StandbyState::Syncing;
+                    self.receive_block(*block, &mut branch).await?;
+                }
+                ReplicationMessage::Heartbeat {
+                    height,
+                    tip_hash,
+                    utxo_count,
+                } => self.check_heartbeat(height, tip_hash, utxo_count).await?,
+                ReplicationMessage::Rejected(reason) => {
+                    return Err(ChainError::NetworkError(format!(
+                        "Primary ended replication: {}",
+                        reason
+                    )))
+                }
+                other => return Err(unexpected(&other)),
+            }
+        }
+    }
+
+    /// Validate and apply a streamed block, collecting a reorg branch until
+    /// it outgrows our chain
+    async fn receive_block(&self, block: Block, branch: &mut Vec<Block>) -> Result<(), ChainError> {
+        let mut chain = self.blockchain.write().await;
+        let tip = chain.blocks.last().map(|b| b.hash()).unwrap_or_default();
+        if branch.is_empty() && block.header.previous_hash == tip {
+            return chain.apply_block(block);
+        }
+
+        let links = match branch.last() {
+            Some(last) => block.header.previous_hash == last.hash(),
+            None => chain
+                .blocks
+                .iter()
+                .any(|b| b.hash() == block.header.previous_hash),
+        };
+        if !links {
+            return Err(ChainError::NetworkError(format!(
+                "Block #{} from the primary doesn't connect to the standby's chain",
+                block.header.height
+            )));
+        }
+        branch.push(block);
+        let tip_height = chain.blocks.last().map_or(0, |b| b.header.height);
+        if branch.last().is_some_and(|b| b.header.height > tip_height) {
+            let outcome = chain.reorganize(std::mem::take(branch))?;
+            info!(
+                "🔀 Followed the primary's reorg at #{}: {} block(s) disconnected",
+                outcome.fork_height,
+                outcome.disconnected.len()
+            );
+        }
+        Ok(())
+    }
+
+    /// Compare our state with the primary's; persist it once it matches
+    async fn check_heartbeat(
+        &self,
+        height: u64,
+        tip_hash: Sha256Hash,
+        utxo_count: usize,
+    ) -> Result<(), ChainError> {
+        let chain = self.blockchain.read().await;
+        {
+            let mut status = self.status.lock();
+            status.primary_height = Some(height);
+            status.last_heartbeat = Some(chrono::Utc::now().timestamp().max(0) as u64);
+        }
+        let ours = chain.blocks.last().map(|b| b.hash()).unwrap_or_default();
+        if ours != tip_hash {
+            return Err(ChainError::NetworkError(format!(
+                "Tip diverged from the primary's at #{}",
+                height
+            )));
+        }
+        if chain.state.utxo_set.len() != utxo_count {
+            return Err(ChainError::NetworkError(format!(
+                "UTXO set diverged from the primary's at #{}: {} entries, primary has {}",
+                height,
+                chain.state.utxo_set.len(),
+                utxo_count
+            )));
+        }
+        self.status.lock().state = StandbyState::Following;
+
+        if let Some(path) = &self.db_path {
+            let mut flushed = self.flushed.lock();
+            if *flushed != Some(ours) {
+                Database::open(path)?.flush_blockchain(&chain)?;
+                *flushed = Some(ours);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::Address;
+    use crate::geometry::Coord;
+    use crate::transaction::{CoinbaseTx, Transaction};
+
+    const SECRET: &str = "correct horse battery staple";
+
+    fn free_port() -> u16 {
+        std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    /// Extend `chain` by `count` blocks
+    fn mine(chain: &mut Blockchain, count: usize) {
+        for _ in 0..count {
+            let tip = chain.blocks.last().unwrap();
+            let coinbase = Transaction::Coinbase(CoinbaseTx {
+                reward_area: Coord::from_num(1),
+                beneficiary_address: Address::from([1; 32]),
+                nonce: tip.header.height + 1,
+            });
+            let mut block = Block::new(tip.header.height + 1, tip.hash(), 1, vec![coinbase]);
+            block.header.timestamp = tip.header.timestamp + 1;
+            let block = crate::fixtures::seal_block(chain, block);
+            chain.apply_block(block).unwrap();
+        }
+    }
+
+    async fn wait_for(standby: &Standby, state: StandbyState) {
+        for _ in 0..200 {
+            if standby.status().state == state {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(25)).await;
+        }
+        panic!("standby never reached {:?}: {:?}", state, standby.status());
+    }
+
+    #[test]
+    fn test_locator_ends_at_genesis() {
+        let mut chain = Blockchain::new(Address::ZERO, 1).unwrap();
+        mine(&mut chain, 40);
+        let hashes = locator(&chain);
+        assert_eq!(hashes[0], chain.blocks[40].hash());
+        assert_eq!(hashes[15], chain.blocks[25].hash());
+        assert_eq!(*hashes.last().unwrap(), chain.blocks[0].hash());
+        assert!(hashes.len() < chain.blocks.len());
+        assert_eq!(common_index(&chain, &hashes), Some(40));
+    }
+
+    #[tokio::test]
+    async fn test_standby_follows_and_promotes() {
+        let mut primary = Blockchain::new(Address::ZERO, 1).unwrap();
+        mine(&mut primary, 3);
+        let primary = Arc::new(RwLock::new(primary));
+        let shutdown = Shutdown::new();
+        let port = free_port();
+        let server = tokio::spawn(serve(
+            primary.clone(),
+            port,
+            SecretBytes::new(SECRET.into()),
+            shutdown.clone(),
+        ));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // A wrong secret never gets a block
+        let intruder_chain = Arc::new(RwLock::new(Blockchain::new(Address::ZERO, 1).unwrap()));
+        let intruder = Arc::new(
+            Standby::new(
+                intruder_chain.clone(),
+                format!("127.0.0.1:{}", port),
+                SecretBytes::new(b"not the shared secret".to_vec()),
+            )
+            .unwrap(),
+        );
+        let intruder_task = tokio::spawn({
+            let intruder = intruder.clone();
+            async move { intruder.follow().await }
+        });
+
+        let chain = Arc::new(RwLock::new(Blockchain::new(Address::ZERO, 1).unwrap()));
+        let standby = Arc::new(
+            Standby::new(
+                chain.clone(),
+                format!("127.0.0.1:{}", port),
+                SecretBytes::new(SECRET.into()),
+            )
+            .unwrap()
+            .with_failover_timeout(Duration::from_millis(500))
+            .with_auto_promote(true),
+        );
+        let follower = tokio::spawn({
+            let standby = standby.clone();
+            async move { standby.follow().await }
+        });
+        wait_for(&standby, StandbyState::Following).await;
+        assert_eq!(chain.read().await.blocks.len(), 4);
+
+        // New blocks on the primary are streamed as they arrive
+        mine(&mut *primary.write().await, 2);
+        for _ in 0..200 {
+            if chain.read().await.blocks.len() == 6 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(25)).await;
+        }
+        let tip = primary.read().await.blocks.last().unwrap().hash();
+        assert_eq!(chain.read().await.blocks.last().unwrap().hash(), tip);
+
+        assert_eq!(intruder_chain.read().await.blocks.len(), 1);
+        assert!(intruder
+            .status()
+            .last_error
+            .is_some_and(|e| e.contains("authentication failed")));
+        intruder.promote();
+        assert_eq!(intruder_task.await.unwrap(), FollowOutcome::Promoted);
+
+        // The primary going away promotes the standby
+        shutdown.trigger();
+        server.await.unwrap().unwrap();
+        assert_eq!(follower.await.unwrap(), FollowOutcome::Promoted);
+        assert_eq!(standby.status().state, StandbyState::Promoted);
+    }
+}
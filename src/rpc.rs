@@ -0,0 +1,557 @@
+//! JSON-RPC 2.0 compatibility layer
+//!
+//! Maps the method names generic explorers and wallet libraries expect
+//! (`getblockcount`, `getblock`, `sendrawtransaction`, ...) onto the node's
+//! chain state. The API serves it at `POST /rpc`; this module parses
+//! requests, answers the read-only methods and leaves transaction
+//! submission to the caller, which shares the REST admission path.
+//!
+//! Parameters may be positional (`[hash, 1]`) or named
+//! (`{"blockhash": hash, "verbosity": 1}`). Raw blocks and transactions are
+//! hex-encoded bincode, the same encoding the P2P protocol uses.
+
+use crate::blockchain::{Block, Blockchain, Sha256Hash, TxStatus};
+use crate::capabilities::Scope;
+use crate::config::active_network;
+use crate::crypto::{verify_message, Address, MessageSignature};
+use crate::error::ChainError;
+use crate::transaction::Transaction;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Most calls accepted in one batch request
+pub const MAX_BATCH_SIZE: usize = 100;
+
+// Standard JSON-RPC 2.0 error codes
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+
+// Application error codes, as used by Bitcoin-style nodes
+/// Block or transaction not found
+pub const NOT_FOUND: i64 = -5;
+/// Caller lacks the scope for the method
+pub const UNAUTHORIZED: i64 = -32001;
+/// Transaction rejected by validation or the mempool
+pub const TRANSACTION_REJECTED: i64 = -26;
+/// Client is over its per-IP rate limit
+pub const RATE_LIMITED: i64 = -32005;
+
+/// A single call
+#[derive(Debug, Clone, Deserialize)]
+pub struct RpcRequest {
+    pub jsonrpc: String,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+    /// Absent for notifications, which get no response
+    #[serde(default)]
+    pub id: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn invalid_params(message: impl Into<String>) -> Self {
+        RpcError::new(INVALID_PARAMS, message)
+    }
+}
+
+impl From<ChainError> for RpcError {
+    fn from(e: ChainError) -> Self {
+        let code = match e {
+            ChainError::InvalidTransaction(_)
+            | ChainError::DoubleSpendDetected(_)
+            | ChainError::TriangleNotFound(_)
+            | ChainError::ImmatureCoinbase(_)
+            | ChainError::MempoolFull => TRANSACTION_REJECTED,
+            _ => INTERNAL_ERROR,
+        };
+        RpcError::new(code, e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RpcResponse {
+    pub jsonrpc: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+    pub id: Value,
+}
+
+impl RpcResponse {
+    pub fn result(id: Value, result: Value) -> Self {
+        RpcResponse {
+            jsonrpc: "2.0",
+            result: Some(result),
+            error: None,
+            id,
+        }
+    }
+
+    pub fn error(id: Value, error: RpcError) -> Self {
+        RpcResponse {
+            jsonrpc: "2.0",
+            result: None,
+            error: Some(error),
+            id,
+        }
+    }
+}
+
+/// A request body: one call or a batch
+#[derive(Debug)]
+pub enum RpcBody {
+    Single(Value),
+    Batch(Vec<Value>),
+}
+
+impl RpcBody {
+    /// Parse a request body. Malformed JSON, an empty batch or an oversized
+    /// one yields the error response to send back.
+    pub fn parse(body: &[u8]) -> Result<Self, RpcResponse> {
+        let value: Value = serde_json::from_slice(body).map_err(|e| {
+            RpcResponse::error(Value::Null, RpcError::new(PARSE_ERROR, e.to_string()))
+        })?;
+        match value {
+            Value::Array(calls) if calls.is_empty() => Err(RpcResponse::error(
+                Value::Null,
+                RpcError::new(INVALID_REQUEST, "Empty batch"),
+            )),
+            Value::Array(calls) if calls.len() > MAX_BATCH_SIZE => Err(RpcResponse::error(
+                Value::Null,
+                RpcError::new(
+                    INVALID_REQUEST,
+                    format!("Batch of {} calls (max: {})", calls.len(), MAX_BATCH_SIZE),
+                ),
+            )),
+            Value::Array(calls) => Ok(RpcBody::Batch(calls)),
+            call => Ok(RpcBody::Single(call)),
+        }
+    }
+}
+
+/// Which block a call refers to
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockRef {
+    Hash(Sha256Hash),
+    Height(u64),
+}
+
+/// A call with validated parameters
+#[derive(Debug, Clone)]
+pub enum RpcMethod {
+    GetBlockCount,
+    GetBestBlockHash,
+    GetBlockHash(u64),
+    /// Verbosity 0: raw hex, 1: header fields and txids, 2: full transactions
+    GetBlock(BlockRef, u8),
+    GetBlockHeader(BlockRef, bool),
+    GetDifficulty,
+    GetBlockchainInfo,
+    GetRawMempool,
+    GetMempoolInfo,
+    GetRawTransaction(Sha256Hash, bool),
+    SendRawTransaction(Transaction),
+    VerifyMessage(Address, MessageSignature, String),
+}
+
+/// Method names served, for `METHOD_NOT_FOUND` messages and docs
+pub const METHODS: &[&str] = &[
+    "getblockcount",
+    "getbestblockhash",
+    "getblockhash",
+    "getblock",
+    "getblockheader",
+    "getdifficulty",
+    "getblockchaininfo",
+    "getrawmempool",
+    "getmempoolinfo",
+    "getrawtransaction",
+    "sendrawtransaction",
+    "verifymessage",
+];
+
+impl RpcMethod {
+    /// Validate a call's method name and parameters
+    pub fn parse(request: &RpcRequest) -> Result<Self, RpcError> {
+        let params = Params(&request.params);
+        if !matches!(
+            request.params,
+            Value::Null | Value::Array(_) | Value::Object(_)
+        ) {
+            return Err(RpcError::invalid_params(
+                "params must be an array or object",
+            ));
+        }
+        Ok(match request.method.as_str() {
+            "getblockcount" => RpcMethod::GetBlockCount,
+            "getbestblockhash" => RpcMethod::GetBestBlockHash,
+            "getblockhash" => RpcMethod::GetBlockHash(params.required_u64(0, "height")?),
+            "getblock" => RpcMethod::GetBlock(
+                params.block_ref(0, "blockhash")?,
+                params.verbosity(1, "verbosity")?,
+            ),
+            "getblockheader" => RpcMethod::GetBlockHeader(
+                params.block_ref(0, "blockhash")?,
+                params.bool_or(1, "verbose", true)?,
+            ),
+            "getdifficulty" => RpcMethod::GetDifficulty,
+            "getblockchaininfo" => RpcMethod::GetBlockchainInfo,
+            "getrawmempool" => RpcMethod::GetRawMempool,
+            "getmempoolinfo" => RpcMethod::GetMempoolInfo,
+            "getrawtransaction" => RpcMethod::GetRawTransaction(
+                params.hash(0, "txid")?,
+                params.bool_or(1, "verbose", false)?,
+            ),
+            "sendrawtransaction" => {
+                let raw = params.required_str(0, "hexstring")?;
+                RpcMethod::SendRawTransaction(
+                    Transaction::from_hex(raw)
+                        .map_err(|e| RpcError::invalid_params(e.to_string()))?,
+                )
+            }
+            "verifymessage" => RpcMethod::VerifyMessage(
+                Address::parse(params.required_str(0, "address")?)
+                    .map_err(|e| RpcError::invalid_params(e.to_string()))?,
+                MessageSignature::from_hex(params.required_str(1, "signature")?)
+                    .map_err(|e| RpcError::invalid_params(e.to_string()))?,
+                params.required_str(2, "message")?.to_string(),
+            ),
+            other => {
+                return Err(RpcError::new(
+                    METHOD_NOT_FOUND,
+                    format!(
+                        "Method not found: {} (available: {})",
+                        other,
+                        METHODS.join(", ")
+                    ),
+                ))
+            }
+        })
+    }
+
+    /// Scope an API key or capability token needs to make this call
+    pub fn scope(&self) -> Scope {
+        match self {
+            RpcMethod::SendRawTransaction(_) => Scope::Send,
+            _ => Scope::Read,
+        }
+    }
+
+    /// Answer a read-only call. `SendRawTransaction` changes node state and
+    /// is left to the caller.
+    pub fn query(&self, chain: &Blockchain) -> Result<Value, RpcError> {
+        let tip = chain
+            .blocks
+            .last()
+            .ok_or_else(|| RpcError::new(INTERNAL_ERROR, "Chain has no blocks"))?;
+        let tip_height = tip.header.height;
+        Ok(match self {
+            RpcMethod::GetBlockCount => json!(tip_height),
+            RpcMethod::GetBestBlockHash => json!(hex::encode(tip.hash())),
+            RpcMethod::GetBlockHash(height) => {
+                json!(hex::encode(
+                    find_block(chain, &BlockRef::Height(*height))?.hash()
+                ))
+            }
+            RpcMethod::GetBlock(block_ref, verbosity) => {
+                let block = find_block(chain, block_ref)?;
+                match verbosity {
+                    0 => json!(hex::encode(
+                        bincode::serialize(block).map_err(ChainError::from)?
+                    )),
+                    _ => {
+                        let mut value = block_json(block, tip_height);
+                        value["tx"] = match verbosity {
+                            1 => json!(block
+                                .transactions
+                                .iter()
+                                .map(|tx| tx.hash_str())
+                                .collect::<Vec<_>>()),
+                            _ => json!(block.transactions),
+                        };
+                        value
+                    }
+                }
+            }
+            RpcMethod::GetBlockHeader(block_ref, verbose) => {
+                let block = find_block(chain, block_ref)?;
+                if *verbose {
+                    block_json(block, tip_height)
+                } else {
+                    let raw = bincode::serialize(&block.header).map_err(ChainError::from)?;
+                    json!(hex::encode(raw))
+                }
+            }
+            RpcMethod::GetDifficulty => json!(chain.difficulty),
+            RpcMethod::GetBlockchainInfo => json!({
+                "chain": active_network().name(),
+                "blocks": tip_height,
+                "bestblockhash": hex::encode(tip.hash()),
+                "difficulty": chain.difficulty,
+                "mempool_size": chain.mempool.len(),
+            }),
+            RpcMethod::GetRawMempool => json!(chain
+                .mempool
+                .get_all_transactions()
+                .iter()
+                .map(|tx| tx.hash_str())
+                .collect::<Vec<_>>()),
+            RpcMethod::GetMempoolInfo => json!({ "size": chain.mempool.len() }),
+            RpcMethod::GetRawTransaction(txid, verbose) => {
+                let (tx, status) = find_transaction(chain, txid)?;
+                if *verbose {
+                    json!({
+                        "txid": tx.hash_str(),
+                        "hex": tx.to_hex()?,
+                        "transaction": tx,
+                        "status": status,
+                    })
+                } else {
+                    json!(tx.to_hex()?)
+                }
+            }
+            RpcMethod::VerifyMessage(address, signature, message) => {
+                json!(verify_message(address, message, signature).is_ok())
+            }
+            RpcMethod::SendRawTransaction(_) => {
+                return Err(RpcError::new(
+                    INTERNAL_ERROR,
+                    "sendrawtransaction is not a query",
+                ))
+            }
+        })
+    }
+}
+
+/// Parse one call of a body into the request and its id (`None` for a
+/// notification, which gets no response). `Err` carries the response for a
+/// malformed call.
+pub fn parse_call(call: Value) -> Result<(RpcRequest, Option<Value>), RpcResponse> {
+    let id = call.get("id").cloned();
+    let request: RpcRequest = serde_json::from_value(call).map_err(|e| {
+        RpcResponse::error(
+            id.clone().unwrap_or(Value::Null),
+            RpcError::new(INVALID_REQUEST, e.to_string()),
+        )
+    })?;
+    if request.jsonrpc != "2.0" {
+        return Err(RpcResponse::error(
+            id.unwrap_or(Value::Null),
+            RpcError::new(INVALID_REQUEST, "jsonrpc must be \"2.0\""),
+        ));
+    }
+    let id = request.id.clone();
+    Ok((request, id))
+}
+
+fn find_block<'a>(chain: &'a Blockchain, block_ref: &BlockRef) -> Result<&'a Block, RpcError> {
+    let block = match block_ref {
+        BlockRef::Height(height) => chain.get_block_by_height(*height),
+        BlockRef::Hash(hash) => chain.get_block_by_hash(hash),
+    };
+    block.ok_or_else(|| RpcError::new(NOT_FOUND, "Block not found"))
+}
+
+fn find_transaction(
+    chain: &Blockchain,
+    txid: &Sha256Hash,
+) -> Result<(Transaction, TxStatus), RpcError> {
+    let tx = chain
+        .mempool
+        .get_transaction(txid)
+        .cloned()
+        .or_else(|| {
+            chain
+                .blocks
+                .iter()
+                .rev()
+                .flat_map(|b| &b.transactions)
+                .find(|tx| tx.hash() == *txid)
+                .cloned()
+        })
+        .ok_or_else(|| RpcError::new(NOT_FOUND, "Transaction not found"))?;
+    Ok((tx, chain.transaction_status(txid, None)))
+}
+
+fn block_json(block: &Block, tip_height: u64) -> Value {
+    let header = &block.header;
+    json!({
+        "hash": hex::encode(block.hash()),
+        "confirmations": tip_height.saturating_sub(header.height) + 1,
+        "height": header.height,
+        "version": header.version,
+        "versionHex": format!("{:08x}", header.version),
+        "merkleroot": hex::encode(header.merkle_root),
+        "stateroot": hex::encode(header.state_root),
+        "time": header.timestamp / 1000,
+        "difficulty": header.difficulty,
+        "nonce": header.nonce,
+        "previousblockhash": hex::encode(header.previous_hash),
+        "nTx": block.transactions.len(),
+    })
+}
+
+/// Positional or named parameters
+struct Params<'a>(&'a Value);
+
+impl Params<'_> {
+    fn get(&self, index: usize, name: &str) -> Option<&Value> {
+        match self.0 {
+            Value::Array(values) => values.get(index),
+            Value::Object(map) => map.get(name),
+            _ => None,
+        }
+        .filter(|v| !v.is_null())
+    }
+
+    fn required(&self, index: usize, name: &str) -> Result<&Value, RpcError> {
+        self.get(index, name)
+            .ok_or_else(|| RpcError::invalid_params(format!("Missing parameter: {}", name)))
+    }
+
+    fn required_u64(&self, index: usize, name: &str) -> Result<u64, RpcError> {
+        self.required(index, name)?.as_u64().ok_or_else(|| {
+            RpcError::invalid_params(format!("{} must be a non-negative integer", name))
+        })
+    }
+
+    fn required_str(&self, index: usize, name: &str) -> Result<&str, RpcError> {
+        self.required(index, name)?
+            .as_str()
+            .ok_or_else(|| RpcError::invalid_params(format!("{} must be a string", name)))
+    }
+
+    fn hash(&self, index: usize, name: &str) -> Result<Sha256Hash, RpcError> {
+        let mut hash = [0u8; 32];
+        hex::decode_to_slice(self.required_str(index, name)?, &mut hash).map_err(|_| {
+            RpcError::invalid_params(format!("{} must be a 64-character hex string", name))
+        })?;
+        Ok(hash)
+    }
+
+    /// A block hash, or a height for tools that pass one instead
+    fn block_ref(&self, index: usize, name: &str) -> Result<BlockRef, RpcError> {
+        match self.required(index, name)? {
+            Value::Number(_) => Ok(BlockRef::Height(self.required_u64(index, name)?)),
+            _ => Ok(BlockRef::Hash(self.hash(index, name)?)),
+        }
+    }
+
+    fn bool_or(&self, index: usize, name: &str, default: bool) -> Result<bool, RpcError> {
+        match self.get(index, name) {
+            None => Ok(default),
+            Some(v) => v
+                .as_bool()
+                .ok_or_else(|| RpcError::invalid_params(format!("{} must be a boolean", name))),
+        }
+    }
+
+    /// 0, 1 or 2; booleans map to 0 and 1 as older clients send them
+    fn verbosity(&self, index: usize, name: &str) -> Result<u8, RpcError> {
+        match self.get(index, name) {
+            None => Ok(1),
+            Some(Value::Bool(verbose)) => Ok(*verbose as u8),
+            Some(v) => match v.as_u64() {
+                Some(level @ 0..=2) => Ok(level as u8),
+                _ => Err(RpcError::invalid_params(format!(
+                    "{} must be 0, 1 or 2",
+                    name
+                ))),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{sign_message, KeyPair};
+
+    fn call(chain: &Blockchain, body: Value) -> Result<Value, RpcError> {
+        let (request, _) = parse_call(body).unwrap();
+        RpcMethod::parse(&request)?.query(chain)
+    }
+
+    #[test]
+    fn test_rpc_block_queries() {
+        let miner = KeyPair::generate().unwrap();
+        let chain = Blockchain::new(miner.address(), 1).unwrap();
+        let genesis = hex::encode(chain.blocks[0].hash());
+
+        let count = json!({"jsonrpc": "2.0", "method": "getblockcount", "id": 1});
+        assert_eq!(call(&chain, count).unwrap(), json!(0));
+        let by_height = json!({"jsonrpc": "2.0", "method": "getblockhash", "params": [0], "id": 2});
+        assert_eq!(call(&chain, by_height).unwrap(), json!(genesis));
+
+        let named = json!({
+            "jsonrpc": "2.0",
+            "method": "getblock",
+            "params": {"blockhash": genesis, "verbosity": 1},
+            "id": 3
+        });
+        let block = call(&chain, named).unwrap();
+        assert_eq!(block["confirmations"], json!(1));
+        let coinbase = chain.blocks[0].transactions[0].hash_str();
+        assert_eq!(block["tx"], json!([coinbase]));
+
+        let raw =
+            json!({"jsonrpc": "2.0", "method": "getrawtransaction", "params": [coinbase], "id": 4});
+        let hex = call(&chain, raw).unwrap();
+        let decoded = Transaction::from_hex(hex.as_str().unwrap()).unwrap();
+        assert_eq!(decoded.hash_str(), coinbase);
+
+        let signature = sign_message(&miner, "proof").unwrap().to_hex();
+        let verify = |message: &str| {
+            json!({
+                "jsonrpc": "2.0",
+                "method": "verifymessage",
+                "params": [miner.address().to_string(), signature, message],
+                "id": 5
+            })
+        };
+        assert_eq!(call(&chain, verify("proof")).unwrap(), json!(true));
+        assert_eq!(call(&chain, verify("other")).unwrap(), json!(false));
+    }
+
+    #[test]
+    fn test_rpc_errors() {
+        let miner = KeyPair::generate().unwrap();
+        let chain = Blockchain::new(miner.address(), 1).unwrap();
+
+        let unknown = json!({"jsonrpc": "2.0", "method": "getwork", "id": 1});
+        assert_eq!(call(&chain, unknown).unwrap_err().code, METHOD_NOT_FOUND);
+        let missing = json!({"jsonrpc": "2.0", "method": "getblockhash", "id": 2});
+        assert_eq!(call(&chain, missing).unwrap_err().code, INVALID_PARAMS);
+        let absent = json!({"jsonrpc": "2.0", "method": "getblockhash", "params": [5], "id": 3});
+        assert_eq!(call(&chain, absent).unwrap_err().code, NOT_FOUND);
+
+        let legacy = json!({"jsonrpc": "1.0", "method": "getblockcount", "id": 4});
+        let response = parse_call(legacy).unwrap_err();
+        assert_eq!(response.error.unwrap().code, INVALID_REQUEST);
+        assert_eq!(response.id, json!(4));
+
+        assert_eq!(
+            RpcBody::parse(b"{").unwrap_err().error.unwrap().code,
+            PARSE_ERROR
+        );
+        assert!(RpcBody::parse(b"[]").is_err());
+        assert!(matches!(RpcBody::parse(b"[{}]"), Ok(RpcBody::Batch(_))));
+    }
+}
@@ -1,16 +1,22 @@
 //! Security module for TrinityChain
 //!
-//! Provides peer authentication, firewall rules, rate limiting, and VPN support
+//! Provides peer authentication, firewall rules, rate limiting, and VPN support,
+//! plus [`SecretBytes`] for key material that should not outlive its use and
+//! constant-time comparison of secrets such as API tokens
 
+use crate::config::{ApiRateLimitConfig, RouteRateLimit};
 use crate::crypto::KeyPair;
 use crate::error::ChainError;
 use ipnetwork::IpNetwork;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, Zeroizing};
 
 /// Peer authentication handshake challenge
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -197,41 +203,52 @@ impl Default for RateLimitConfig {
 
 /// Token bucket rate limiter entry
 #[derive(Debug, Clone)]
-struct TokenBucket {
+pub(crate) struct TokenBucket {
     tokens: f64,
-    last_refill: u64,
-    rate: u32,
+    capacity: f64,
+    /// Tokens added per second
+    refill: f64,
+    last_refill: Instant,
 }
 
 impl TokenBucket {
     fn new(rate: u32) -> Self {
+        Self::with_capacity(rate as f64, rate as f64, Instant::now())
+    }
+
+    pub(crate) fn with_capacity(refill: f64, capacity: f64, now: Instant) -> Self {
         Self {
-            tokens: rate as f64,
-            last_refill: current_timestamp(),
-            rate,
+            tokens: capacity,
+            capacity,
+            refill,
+            last_refill: now,
         }
     }
 
-    fn try_consume(&mut self) -> bool {
-        let now = current_timestamp();
-        let elapsed = now.saturating_sub(self.last_refill);
-
-        // Refill tokens based on elapsed time
-        self.tokens += (elapsed as f64) * (self.rate as f64);
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.refill).min(self.capacity);
         self.last_refill = now;
+    }
 
-        // Cap tokens at rate
-        if self.tokens > self.rate as f64 {
-            self.tokens = self.rate as f64;
-        }
+    fn try_consume(&mut self) -> bool {
+        self.try_consume_at(Instant::now()).is_ok()
+    }
 
+    /// Take a token, or say how long until one is available
+    pub(crate) fn try_consume_at(&mut self, now: Instant) -> Result<(), Duration> {
+        self.refill(now);
         if self.tokens >= 1.0 {
             self.tokens -= 1.0;
-            true
+            Ok(())
         } else {
-            false
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / self.refill))
         }
     }
+
+    fn is_full(&self) -> bool {
+        self.tokens >= self.capacity
+    }
 }
 
 /// Per-entity rate limiter
@@ -299,6 +316,91 @@ impl RequestRateLimiter {
     }
 }
 
+/// Clients tracked before idle (fully refilled) buckets are dropped
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// What an HTTP API route does, for rate limiting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RouteClass {
+    Read,
+    Submit,
+    Control,
+}
+
+/// Requests turned away per route class since the server started
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimitCounters {
+    pub read: u64,
+    pub submit: u64,
+    pub control: u64,
+}
+
+/// Per-IP token buckets for the HTTP API, configured by `[api.rate_limit]`
+#[derive(Debug)]
+pub struct ApiRateLimiter {
+    config: ApiRateLimitConfig,
+    buckets: Mutex<HashMap<(IpAddr, RouteClass), TokenBucket>>,
+    rejected: [AtomicU64; 3],
+}
+
+impl ApiRateLimiter {
+    pub fn new(config: ApiRateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+            rejected: Default::default(),
+        }
+    }
+
+    /// Charge one request from `ip` to `class`. `Err` carries how long the
+    /// client should wait before retrying.
+    pub fn check(&self, ip: IpAddr, class: RouteClass) -> Result<(), Duration> {
+        self.check_at(ip, class, Instant::now())
+    }
+
+    pub fn check_at(&self, ip: IpAddr, class: RouteClass, now: Instant) -> Result<(), Duration> {
+        let limit = self.limit(class);
+        let mut buckets = self.buckets.lock();
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            buckets.retain(|_, bucket| {
+                bucket.refill(now);
+                !bucket.is_full()
+            });
+        }
+        let result = buckets
+            .entry((ip, class))
+            .or_insert_with(|| {
+                TokenBucket::with_capacity(
+                    limit.per_minute.max(1) as f64 / 60.0,
+                    limit.burst.max(1) as f64,
+                    now,
+                )
+            })
+            .try_consume_at(now);
+        if result.is_err() {
+            self.rejected[class as usize].fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+
+    pub fn rejected(&self) -> RateLimitCounters {
+        let count = |class: RouteClass| self.rejected[class as usize].load(Ordering::Relaxed);
+        RateLimitCounters {
+            read: count(RouteClass::Read),
+            submit: count(RouteClass::Submit),
+            control: count(RouteClass::Control),
+        }
+    }
+
+    fn limit(&self, class: RouteClass) -> RouteRateLimit {
+        match class {
+            RouteClass::Read => self.config.read,
+            RouteClass::Submit => self.config.submit,
+            RouteClass::Control => self.config.control,
+        }
+    }
+}
+
 /// Security manager combining all security features
 #[derive(Debug)]
 pub struct SecurityManager {
@@ -440,6 +542,95 @@ impl SecurityManager {
     }
 }
 
+/// Secret key material, overwritten with zeros when dropped.
+///
+/// Compares in constant time, prints as `[REDACTED]` and serializes as hex
+/// (the format wallet files already use for secret keys).
+#[derive(Clone, Default)]
+pub struct SecretBytes(Vec<u8>);
+
+impl SecretBytes {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        SecretBytes(bytes)
+    }
+
+    /// Decode a hex-encoded secret; an empty string gives an empty secret
+    pub fn from_hex(hex_str: &str) -> Result<Self, ChainError> {
+        hex::decode(hex_str)
+            .map(SecretBytes)
+            .map_err(|e| ChainError::CryptoError(format!("Invalid hex secret: {}", e)))
+    }
+
+    /// The secret itself. Avoid copying it into buffers that are not wiped.
+    pub fn expose(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Hex encoding, wiped when dropped
+    pub fn to_hex(&self) -> Zeroizing<String> {
+        Zeroizing::new(hex::encode(&self.0))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        SecretBytes(bytes)
+    }
+}
+
+impl Drop for SecretBytes {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl PartialEq for SecretBytes {
+    fn eq(&self, other: &Self) -> bool {
+        constant_time_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SecretBytes {}
+
+impl std::fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SecretBytes([REDACTED; {}])", self.0.len())
+    }
+}
+
+impl Serialize for SecretBytes {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretBytes {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex_str = Zeroizing::new(String::deserialize(deserializer)?);
+        SecretBytes::from_hex(&hex_str).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Compare two secrets without returning early at the first differing
+/// byte, so response timing does not reveal how much of a guess was right.
+/// Lengths are not hidden.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+/// [`constant_time_eq`] for string secrets such as bearer tokens
+pub fn constant_time_str_eq(a: &str, b: &str) -> bool {
+    constant_time_eq(a.as_bytes(), b.as_bytes())
+}
+
 /// Get current Unix timestamp
 pub fn current_timestamp() -> u64 {
     SystemTime::now()
@@ -490,4 +681,64 @@ mod tests {
         // Should allow first request
         assert!(limiter.check_peer_rate_limit("peer1").is_ok());
     }
+
+    #[test]
+    fn test_secret_bytes() {
+        let secret = SecretBytes::from_hex("00ff10").unwrap();
+        assert_eq!(secret.expose(), &[0x00, 0xff, 0x10]);
+        assert_eq!(*secret.to_hex(), "00ff10");
+        assert_eq!(format!("{:?}", secret), "SecretBytes([REDACTED; 3])");
+        assert_eq!(secret, SecretBytes::new(vec![0x00, 0xff, 0x10]));
+        assert_ne!(secret, SecretBytes::new(vec![0x00, 0xff, 0x11]));
+        assert_ne!(secret, SecretBytes::new(vec![0x00, 0xff]));
+        assert!(SecretBytes::from_hex("xyz").is_err());
+
+        let json = serde_json::to_string(&secret).unwrap();
+        assert_eq!(json, "\"00ff10\"");
+        let parsed: SecretBytes = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, secret);
+        let empty: SecretBytes = serde_json::from_str("\"\"").unwrap();
+        assert!(empty.is_empty());
+
+        assert!(constant_time_str_eq("token", "token"));
+        assert!(!constant_time_str_eq("token", "tokeN"));
+        assert!(!constant_time_str_eq("token", "token2"));
+    }
+
+    #[test]
+    fn test_api_rate_limiter() {
+        let limiter = ApiRateLimiter::new(ApiRateLimitConfig {
+            submit: RouteRateLimit {
+                per_minute: 6,
+                burst: 2,
+            },
+            ..Default::default()
+        });
+        let client: IpAddr = "10.0.0.1".parse().unwrap();
+        let other: IpAddr = "10.0.0.2".parse().unwrap();
+        let start = Instant::now();
+
+        assert!(limiter.check_at(client, RouteClass::Submit, start).is_ok());
+        assert!(limiter.check_at(client, RouteClass::Submit, start).is_ok());
+        // Burst spent: one token every 10 seconds
+        let wait = limiter
+            .check_at(client, RouteClass::Submit, start)
+            .unwrap_err();
+        assert_eq!(wait.as_secs(), 10);
+        // Other clients and route classes have their own buckets
+        assert!(limiter.check_at(other, RouteClass::Submit, start).is_ok());
+        assert!(limiter.check_at(client, RouteClass::Read, start).is_ok());
+
+        let later = start + Duration::from_secs(10);
+        assert!(limiter.check_at(client, RouteClass::Submit, later).is_ok());
+        assert!(limiter.check_at(client, RouteClass::Submit, later).is_err());
+        assert_eq!(
+            limiter.rejected(),
+            RateLimitCounters {
+                read: 0,
+                submit: 2,
+                control: 0
+            }
+        );
+    }
 }
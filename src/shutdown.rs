@@ -0,0 +1,82 @@
+//! Coordinated node shutdown
+//!
+//! A `Shutdown` handle is cloned into every long-running task (the P2P
+//! listener, peer connections, background loops). Triggering any clone wakes
+//! all of them, so the node can stop accepting work, say goodbye to peers and
+//! flush its state before the process exits.
+
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Cancellation signal shared by the node's tasks
+#[derive(Clone)]
+pub struct Shutdown {
+    sender: Arc<watch::Sender<bool>>,
+    receiver: watch::Receiver<bool>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        let (sender, receiver) = watch::channel(false);
+        Shutdown {
+            sender: Arc::new(sender),
+            receiver,
+        }
+    }
+
+    /// Signal every task holding a clone to stop
+    pub fn trigger(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.receiver.borrow()
+    }
+
+    /// Resolves once `trigger` has been called on any clone
+    pub async fn cancelled(&self) {
+        let mut receiver = self.receiver.clone();
+        // The sender lives as long as this handle, so `changed` cannot fail
+        while !*receiver.borrow_and_update() {
+            if receiver.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_trigger_wakes_every_clone() {
+        let shutdown = Shutdown::new();
+        let waiters: Vec<_> = (0..3)
+            .map(|_| {
+                let shutdown = shutdown.clone();
+                tokio::spawn(async move { shutdown.cancelled().await })
+            })
+            .collect();
+
+        assert!(!shutdown.is_triggered());
+        shutdown.clone().trigger();
+        for waiter in waiters {
+            tokio::time::timeout(Duration::from_secs(1), waiter)
+                .await
+                .expect("waiter not woken")
+                .unwrap();
+        }
+
+        // Already triggered: resolves immediately
+        assert!(shutdown.is_triggered());
+        shutdown.cancelled().await;
+    }
+}
@@ -0,0 +1,263 @@
+//! UTXO snapshots for fast bootstrapping
+//!
+//! A snapshot captures the header chain, the UTXO set and node metadata at a
+//! given height in a single file. A new node imports it and starts syncing
+//! from the snapshot height instead of replaying every block from genesis.
+//!
+//! File layout:
+//!
+//! ```text
+//! magic "TRISNAP\0" | format version (u32 LE) | SHA-256 of payload | payload
+//! ```
+//!
+//! The payload is the bincode-encoded [`Snapshot`]. The checksum is verified
+//! before anything is decoded, so a truncated or corrupted download is
+//! rejected without touching the database.
+
+use crate::blockchain::{BlockHeader, Sha256Hash, TriangleState};
+use crate::error::ChainError;
+use crate::geometry::Triangle;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use tempfile::NamedTempFile;
+
+const SNAPSHOT_MAGIC: &[u8; 8] = b"TRISNAP\0";
+/// Version 2: the heights of immature coinbase triangles.
+/// Version 3: triangles carry their subdivision depth.
+/// Version 4: headers carry a version field.
+/// Version 5: headers carry a state root.
+const SNAPSHOT_FORMAT_VERSION: u32 = 5;
+const HEADER_LEN: usize = 8 + 4 + 32;
+
+/// Chain state at a height
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub height: u64,
+    /// Hash of the block at `height`
+    pub tip_hash: Sha256Hash,
+    pub difficulty: u32,
+    /// Headers from genesis up to and including `height`
+    pub headers: Vec<BlockHeader>,
+    /// Unspent triangles after applying the block at `height`, sorted by hash
+    pub utxos: Vec<(Sha256Hash, Triangle)>,
+    /// Height each still immature coinbase triangle in `utxos` was mined at,
+    /// sorted by hash
+    pub coinbase_heights: Vec<(Sha256Hash, u64)>,
+    /// Node metadata entries (key, value)
+    pub metadata: Vec<(String, String)>,
+}
+
+/// Summary of a written or imported snapshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotInfo {
+    pub height: u64,
+    pub tip_hash: Sha256Hash,
+    pub utxo_count: usize,
+    /// SHA-256 of the payload, as recorded in the file
+    pub checksum: Sha256Hash,
+    pub size_bytes: u64,
+}
+
+impl Snapshot {
+    /// Check that the headers form a contiguous chain from genesis ending at
+    /// `tip_hash`, and that the UTXO set matches the tip's state root unless
+    /// the tip is the genesis block, which commits to none
+    pub fn verify(&self) -> Result<(), ChainError> {
+        let first = self
+            .headers
+            .first()
+            .ok_or_else(|| ChainError::InvalidBlock("Snapshot has no headers".to_string()))?;
+        if first.height != 0 {
+            return Err(ChainError::InvalidBlock(
+                "Snapshot headers must start at genesis".to_string(),
+            ));
+        }
+
+        for pair in self.headers.windows(2) {
+            if pair[1].height != pair[0].height + 1 || pair[1].previous_hash != pair[0].hash() {
+                return Err(ChainError::InvalidBlockLinkage);
+            }
+        }
+
+        let last = &self.headers[self.headers.len() - 1];
+        if last.height != self.height || last.hash() != self.tip_hash {
+            return Err(ChainError::InvalidBlock(format!(
+                "Snapshot tip does not match header #{}",
+                last.height
+            )));
+        }
+
+        for (hash, height) in &self.coinbase_heights {
+            let unspent = self.utxos.iter().any(|(utxo, _)| utxo == hash);
+            if !unspent || *height == 0 || *height > self.height {
+                return Err(ChainError::InvalidBlock(format!(
+                    "Snapshot lists coinbase {} at height {} it cannot hold",
+                    hex::encode(hash),
+                    height
+                )));
+            }
+        }
+
+        if last.height > 0 {
+            let root = TriangleState::root_of(self.utxos.iter().map(|(hash, t)| (hash, t)));
+            if root != last.state_root {
+                return Err(ChainError::InvalidBlock(format!(
+                    "Snapshot UTXO set does not match the state root of header #{}",
+                    last.height
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Atomically write the snapshot to `path`
+    pub fn write(&self, path: &Path) -> Result<SnapshotInfo, ChainError> {
+        self.verify()?;
+        let payload = bincode::serialize(self)?;
+        let checksum: Sha256Hash = Sha256::digest(&payload).into();
+
+        let dir = path.parent().filter(|p| !p.as_os_str().is_empty());
+        let mut temp_file = NamedTempFile::new_in(dir.unwrap_or_else(|| Path::new(".")))
+            .map_err(|e| ChainError::IoError(format!("Failed to create temp file: {}", e)))?;
+        temp_file.write_all(SNAPSHOT_MAGIC)?;
+        temp_file.write_all(&SNAPSHOT_FORMAT_VERSION.to_le_bytes())?;
+        temp_file.write_all(&checksum)?;
+        temp_file.write_all(&payload)?;
+        temp_file
+            .persist(path)
+            .map_err(|e| ChainError::IoError(format!("Failed to persist snapshot: {}", e)))?;
+
+        Ok(SnapshotInfo {
+            height: self.height,
+            tip_hash: self.tip_hash,
+            utxo_count: self.utxos.len(),
+            checksum,
+            size_bytes: (HEADER_LEN + payload.len()) as u64,
+        })
+    }
+
+    /// Read and verify a snapshot file
+    pub fn read(path: &Path) -> Result<(Self, SnapshotInfo), ChainError> {
+        let data = fs::read(path)?;
+        if data.len() < HEADER_LEN || &data[..8] != SNAPSHOT_MAGIC {
+            return Err(ChainError::InvalidBlock(
+                "Not a TrinityChain snapshot file".to_string(),
+            ));
+        }
+
+        let mut version = [0u8; 4];
+        version.copy_from_slice(&data[8..12]);
+        let version = u32::from_le_bytes(version);
+        if version != SNAPSHOT_FORMAT_VERSION {
+            return Err(ChainError::InvalidBlock(format!(
+                "Unsupported snapshot format version {}",
+                version
+            )));
+        }
+
+        let mut checksum = [0u8; 32];
+        checksum.copy_from_slice(&data[12..HEADER_LEN]);
+        let payload = &data[HEADER_LEN..];
+        let actual: Sha256Hash = Sha256::digest(payload).into();
+        if actual != checksum {
+            return Err(ChainError::InvalidBlock(
+                "Snapshot checksum mismatch; the file is corrupted".to_string(),
+            ));
+        }
+
+        let snapshot: Snapshot = bincode::deserialize(payload)?;
+        snapshot.verify()?;
+        let info = SnapshotInfo {
+            height: snapshot.height,
+            tip_hash: snapshot.tip_hash,
+            utxo_count: snapshot.utxos.len(),
+            checksum,
+            size_bytes: data.len() as u64,
+        };
+        Ok((snapshot, info))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::Blockchain;
+    use crate::crypto::Address;
+
+    fn snapshot() -> Snapshot {
+        let chain = Blockchain::new(Address::from([4; 32]), 1).unwrap();
+        let genesis = &chain.blocks[0];
+        Snapshot {
+            height: 0,
+            tip_hash: genesis.hash(),
+            difficulty: chain.difficulty,
+            headers: vec![genesis.header.clone()],
+            utxos: chain.state.utxo_set.clone().into_iter().collect(),
+            coinbase_heights: vec![],
+            metadata: vec![("difficulty".to_string(), "1".to_string())],
+        }
+    }
+
+    #[test]
+    fn test_write_and_read_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chain.snapshot");
+
+        let written = snapshot().write(&path).unwrap();
+        let (read, info) = Snapshot::read(&path).unwrap();
+        assert_eq!(info, written);
+        assert_eq!(read.utxos.len(), 1);
+    }
+
+    #[test]
+    fn test_corrupted_snapshot_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chain.snapshot");
+        snapshot().write(&path).unwrap();
+
+        let mut data = fs::read(&path).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 0xff;
+        fs::write(&path, &data).unwrap();
+        assert!(Snapshot::read(&path).is_err());
+
+        let mut broken = snapshot();
+        broken.tip_hash = [0; 32];
+        assert!(broken.write(&path).is_err());
+
+        // Maturity can only be tracked for an unspent coinbase below the tip
+        let mut broken = snapshot();
+        let (genesis_output, _) = broken.utxos[0];
+        broken.coinbase_heights = vec![(genesis_output, 1)];
+        assert!(broken.write(&path).is_err());
+        broken.coinbase_heights = vec![([9; 32], 0)];
+        assert!(broken.write(&path).is_err());
+    }
+
+    #[test]
+    fn test_utxos_must_match_state_root() {
+        let miner = Address::from([4; 32]);
+        let mut chain = Blockchain::new(miner, 1).unwrap();
+        let block = crate::fixtures::mine_next_block(&chain, miner, vec![]);
+        chain.apply_block(block).unwrap();
+        let tip = chain.blocks.last().unwrap();
+        assert_eq!(tip.header.state_root, chain.state.state_root());
+
+        let mut snapshot = Snapshot {
+            height: 1,
+            tip_hash: tip.hash(),
+            difficulty: chain.difficulty,
+            headers: chain.blocks.iter().map(|b| b.header.clone()).collect(),
+            utxos: chain.state.utxo_set.clone().into_iter().collect(),
+            coinbase_heights: chain.state.coinbase_heights.clone().into_iter().collect(),
+            metadata: vec![],
+        };
+        snapshot.verify().unwrap();
+
+        snapshot.utxos.pop();
+        assert!(snapshot.verify().is_err());
+    }
+}
@@ -0,0 +1,196 @@
+//! Stale-tip detection
+//!
+//! A miner that has lost every peer keeps extending its own tip, building a
+//! private fork that the rest of the network will eventually discard along
+//! with any payments made on it. The network layer counts the tip as stale
+//! once no peer is connected and no block has arrived from the network for
+//! many multiples of the target block time (blocks mined locally don't
+//! count), and publishes a `TipEvent` whenever that changes. Miners react
+//! according to `[miner] on_stale_tip`.
+
+use crate::blockchain::TARGET_BLOCK_TIME;
+use crate::config::Network;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Default number of target block times without a network block before an
+/// isolated tip is stale
+pub const DEFAULT_STALE_TIP_MULTIPLE: u64 = 12;
+
+/// Events buffered per subscriber
+pub const TIP_EVENT_CHANNEL_CAPACITY: usize = 16;
+
+/// Staleness threshold for `multiple` target block times
+pub fn stale_tip_threshold(multiple: u64) -> Duration {
+    Duration::from_secs(multiple.max(1).saturating_mul(TARGET_BLOCK_TIME))
+}
+
+/// What a miner does while the tip is stale
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StaleTipAction {
+    /// Stop starting new blocks until a peer connects
+    Pause,
+    /// Keep mining, but warn loudly
+    Warn,
+    /// Keep mining silently
+    Ignore,
+}
+
+impl StaleTipAction {
+    /// Pause everywhere except regtest, where mining alone is the point
+    pub fn default_for(network: Network) -> Self {
+        match network {
+            Network::Regtest => StaleTipAction::Ignore,
+            Network::Mainnet | Network::Testnet => StaleTipAction::Pause,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StaleTipAction::Pause => "pause",
+            StaleTipAction::Warn => "warn",
+            StaleTipAction::Ignore => "ignore",
+        }
+    }
+}
+
+/// A change in whether the tip is stale
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TipEvent {
+    /// No peer connected and no network block for `silent_secs`
+    Stale {
+        height: u64,
+        silent_secs: u64,
+        at: u64,
+    },
+    /// A peer connected or a block arrived from the network
+    Recovered { height: u64, peers: usize, at: u64 },
+}
+
+impl TipEvent {
+    /// One-line human readable description
+    pub fn describe(&self) -> String {
+        match self {
+            TipEvent::Stale {
+                height,
+                silent_secs,
+                ..
+            } => format!(
+                "🚨 Stale tip at #{}: no peers and no block from the network for {}s",
+                height, silent_secs
+            ),
+            TipEvent::Recovered { height, peers, .. } => {
+                format!("✅ Tip #{} no longer stale ({} peer(s))", height, peers)
+            }
+        }
+    }
+}
+
+/// Tracks when a block last arrived from the network
+#[derive(Debug, Clone)]
+pub struct StaleTipDetector {
+    threshold_secs: u64,
+    /// Unix time of the last network block (or of startup)
+    last_block_at: u64,
+    stale: bool,
+}
+
+impl StaleTipDetector {
+    /// A detector that starts counting at `now`
+    pub fn new(threshold: Duration, now: u64) -> Self {
+        StaleTipDetector {
+            threshold_secs: threshold.as_secs().max(1),
+            last_block_at: now,
+            stale: false,
+        }
+    }
+
+    pub fn threshold_secs(&self) -> u64 {
+        self.threshold_secs
+    }
+
+    /// Record a block accepted from a peer at unix time `at`
+    pub fn block_received(&mut self, at: u64) {
+        self.last_block_at = self.last_block_at.max(at);
+    }
+
+    /// Seconds since the last network block
+    pub fn silent_secs(&self, now: u64) -> u64 {
+        now.saturating_sub(self.last_block_at)
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    /// Re-evaluate with the current tip height and peer count; returns an
+    /// event only when the tip became stale or stopped being stale
+    pub fn check(&mut self, height: u64, peers: usize, now: u64) -> Option<TipEvent> {
+        let silent_secs = self.silent_secs(now);
+        let stale = peers == 0 && silent_secs >= self.threshold_secs;
+        if stale == self.stale {
+            return None;
+        }
+        self.stale = stale;
+        Some(if stale {
+            TipEvent::Stale {
+                height,
+                silent_secs,
+                at: now,
+            }
+        } else {
+            TipEvent::Recovered {
+                height,
+                peers,
+                at: now,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_only_without_peers_or_blocks() {
+        let mut detector = StaleTipDetector::new(stale_tip_threshold(2), 1_000);
+        assert_eq!(detector.threshold_secs(), 2 * TARGET_BLOCK_TIME);
+        let late = 1_000 + detector.threshold_secs();
+
+        // Quiet but connected, or isolated but recent: not stale
+        assert_eq!(detector.check(5, 3, late), None);
+        assert_eq!(detector.check(5, 0, late - 1), None);
+
+        match detector.check(5, 0, late) {
+            Some(TipEvent::Stale { silent_secs, .. }) => {
+                assert_eq!(silent_secs, detector.threshold_secs())
+            }
+            other => panic!("expected a stale event, got {:?}", other),
+        }
+        assert!(detector.is_stale());
+        // Reported once
+        assert_eq!(detector.check(6, 0, late + 30), None);
+
+        detector.block_received(late + 40);
+        assert!(matches!(
+            detector.check(7, 0, late + 41),
+            Some(TipEvent::Recovered { height: 7, .. })
+        ));
+        assert!(!detector.is_stale());
+    }
+
+    #[test]
+    fn test_default_action_per_network() {
+        assert_eq!(
+            StaleTipAction::default_for(Network::Mainnet),
+            StaleTipAction::Pause
+        );
+        assert_eq!(
+            StaleTipAction::default_for(Network::Regtest),
+            StaleTipAction::Ignore
+        );
+    }
+}
@@ -0,0 +1,224 @@
+//! Chainstate diff between two heights
+//!
+//! Replays the chain to rebuild the UTXO set as it stood after each of two
+//! blocks and compares them: which triangles were created, which were spent
+//! and how every affected address balance moved. Triangles both created and
+//! spent between the two heights don't appear, since neither state holds
+//! them. There is no undo data to walk backwards from the tip, so the replay
+//! always starts at genesis and needs the full block history.
+
+use crate::blockchain::{Block, Sha256Hash, TriangleState};
+use crate::crypto::Address;
+use crate::error::ChainError;
+use crate::geometry::{Coord, Triangle};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// A triangle present in only one of the two states
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TriangleChange {
+    pub hash: String,
+    pub owner: String,
+    pub value: Coord,
+}
+
+impl TriangleChange {
+    fn new(hash: &Sha256Hash, triangle: &Triangle) -> Self {
+        TriangleChange {
+            hash: hex::encode(hash),
+            owner: triangle.owner.to_string(),
+            value: triangle.effective_value(),
+        }
+    }
+}
+
+/// Balance of one address at both heights
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BalanceChange {
+    pub address: String,
+    pub before: Coord,
+    pub after: Coord,
+    /// `after - before`
+    pub delta: Coord,
+}
+
+/// What changed in the UTXO set from `from_height` to `to_height`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StateDiff {
+    pub from_height: u64,
+    pub to_height: u64,
+    /// Unspent at `to_height` but not at `from_height`, largest first
+    pub created: Vec<TriangleChange>,
+    /// Unspent at `from_height` but not at `to_height`, largest first
+    pub spent: Vec<TriangleChange>,
+    /// Addresses whose balance differs, by address
+    pub balances: Vec<BalanceChange>,
+}
+
+impl StateDiff {
+    /// Diff the states after blocks `from` and `to` of `blocks`, which must
+    /// start at genesis. The heights may be given in either order.
+    pub fn between(blocks: &[Block], from: u64, to: u64) -> Result<Self, ChainError> {
+        let tip = blocks.last().map_or(0, |b| b.header.height);
+        if from.max(to) > tip {
+            return Err(ChainError::InvalidBlock(format!(
+                "Height #{} is above the tip (#{})",
+                from.max(to),
+                tip
+            )));
+        }
+        let (low, high) = (from.min(to), from.max(to));
+
+        let mut state = TriangleState::new();
+        let mut before = None;
+        for block in &blocks[..=high as usize] {
+            for tx in &block.transactions {
+                state.apply_transaction(tx, block.header.height)?;
+            }
+            if block.header.height == low {
+                before = Some(state.utxo_set.clone());
+            }
+        }
+        let before = before.unwrap_or_default();
+        let diff = Self::from_utxo_sets(low, &before, high, &state.utxo_set);
+        Ok(if from <= to { diff } else { diff.reversed() })
+    }
+
+    /// Diff two UTXO sets directly
+    pub fn from_utxo_sets(
+        from_height: u64,
+        before: &HashMap<Sha256Hash, Triangle>,
+        to_height: u64,
+        after: &HashMap<Sha256Hash, Triangle>,
+    ) -> Self {
+        let only_in = |a: &HashMap<Sha256Hash, Triangle>, b: &HashMap<Sha256Hash, Triangle>| {
+            let mut changes: Vec<TriangleChange> = a
+                .iter()
+                .filter(|(hash, _)| !b.contains_key(*hash))
+                .map(|(hash, triangle)| TriangleChange::new(hash, triangle))
+                .collect();
+            changes.sort_by(|x, y| y.value.cmp(&x.value).then(x.hash.cmp(&y.hash)));
+            changes
+        };
+        let created = only_in(after, before);
+        let spent = only_in(before, after);
+
+        // Balances at both heights; unchanged ones are dropped
+        let mut balances: BTreeMap<Address, (Coord, Coord)> = BTreeMap::new();
+        for triangle in before.values() {
+            balances.entry(triangle.owner).or_default().0 += triangle.effective_value();
+        }
+        for triangle in after.values() {
+            balances.entry(triangle.owner).or_default().1 += triangle.effective_value();
+        }
+        let balances = balances
+            .into_iter()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(address, (before, after))| BalanceChange {
+                address: address.to_string(),
+                before,
+                after,
+                delta: after - before,
+            })
+            .collect();
+
+        StateDiff {
+            from_height,
+            to_height,
+            created,
+            spent,
+            balances,
+        }
+    }
+
+    /// Keep only changes touching `address`
+    pub fn filter_address(mut self, address: &Address) -> Self {
+        let address = address.to_string();
+        self.created.retain(|c| c.owner == address);
+        self.spent.retain(|c| c.owner == address);
+        self.balances.retain(|b| b.address == address);
+        self
+    }
+
+    /// Net change in total unspent value
+    pub fn net_value(&self) -> Coord {
+        let sum = |changes: &[TriangleChange]| {
+            changes
+                .iter()
+                .fold(Coord::from_num(0), |total, c| total + c.value)
+        };
+        sum(&self.created) - sum(&self.spent)
+    }
+
+    fn reversed(self) -> Self {
+        StateDiff {
+            from_height: self.to_height,
+            to_height: self.from_height,
+            created: self.spent,
+            spent: self.created,
+            balances: self
+                .balances
+                .into_iter()
+                .map(|b| BalanceChange {
+                    address: b.address,
+                    before: b.after,
+                    after: b.before,
+                    delta: b.before - b.after,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::Blockchain;
+    use crate::crypto::KeyPair;
+    use crate::fixtures::{mine_next_block, signed_transfer};
+    use crate::transaction::Transaction;
+
+    #[test]
+    fn test_diff_tracks_spends_and_balances() {
+        let alice = KeyPair::generate().unwrap();
+        let bob = Address::from([7; 32]);
+        let mut chain = Blockchain::new(alice.address(), 1).unwrap();
+        let block = mine_next_block(&chain, alice.address(), vec![]);
+        chain.apply_block(block).unwrap();
+
+        let (genesis_hash, genesis_triangle) = chain
+            .state
+            .utxo_set
+            .iter()
+            .find(|(hash, _)| !chain.state.coinbase_heights.contains_key(*hash))
+            .map(|(hash, triangle)| (*hash, triangle.clone()))
+            .unwrap();
+        let amount = Coord::from_num(1);
+        let transfer = signed_transfer(&alice, genesis_hash, bob, amount, Coord::ZERO, 1);
+        let block = mine_next_block(&chain, alice.address(), vec![transfer]);
+        chain.apply_block(block).unwrap();
+
+        let diff = StateDiff::between(&chain.blocks, 1, 2).unwrap();
+        assert_eq!(diff.spent.len(), 1);
+        assert_eq!(diff.spent[0].hash, hex::encode(genesis_hash));
+        // Coinbase, payment and change
+        assert_eq!(diff.created.len(), 3);
+        // Transfers only move value; the new coinbase adds its reward
+        match &chain.blocks[2].transactions[0] {
+            Transaction::Coinbase(coinbase) => assert_eq!(diff.net_value(), coinbase.reward_area),
+            other => panic!("expected a coinbase, got {:?}", other),
+        }
+
+        let bob_change = diff.clone().filter_address(&bob);
+        assert_eq!(bob_change.balances.len(), 1);
+        assert_eq!(bob_change.balances[0].delta, amount);
+        assert_eq!(bob_change.created.len(), 1);
+        assert!(genesis_triangle.effective_value() > amount);
+
+        // Reversed heights swap the roles
+        let back = StateDiff::between(&chain.blocks, 2, 1).unwrap();
+        assert_eq!(back.created, diff.spent);
+        assert_eq!(back.filter_address(&bob).balances[0].delta, -amount);
+        assert!(StateDiff::between(&chain.blocks, 0, 9).is_err());
+    }
+}
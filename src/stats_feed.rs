@@ -0,0 +1,130 @@
+//! Live node statistics for the web dashboard
+//!
+//! `trinity-node` publishes a [`NodeStats`] snapshot each time it redraws
+//! its TUI. A [`StatsFeed`] serves the latest snapshot as JSON over a
+//! WebSocket on a local port, pushed to every client once a second, so the
+//! dashboard can chart height, peers and uptime live without an API server.
+
+use crate::error::ChainError;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tracing::{info, warn};
+
+/// How often each client is sent the latest snapshot
+pub const STATS_PUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What the node TUI shows, as sent to dashboard clients
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeStats {
+    pub status: String,
+    pub chain_height: u64,
+    pub last_block_hash: String,
+    pub peers: usize,
+    pub uptime_secs: u64,
+}
+
+impl Default for NodeStats {
+    fn default() -> Self {
+        Self {
+            status: "Initializing...".to_string(),
+            chain_height: 0,
+            last_block_hash: "N/A".to_string(),
+            peers: 0,
+            uptime_secs: 0,
+        }
+    }
+}
+
+/// The latest [`NodeStats`], shared between the node and its WebSocket clients
+#[derive(Clone, Default)]
+pub struct StatsFeed {
+    latest: Arc<RwLock<NodeStats>>,
+}
+
+impl StatsFeed {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the snapshot clients are sent next
+    pub fn publish(&self, stats: NodeStats) {
+        *self.latest.write() = stats;
+    }
+
+    pub fn latest(&self) -> NodeStats {
+        self.latest.read().clone()
+    }
+
+    /// Accept WebSocket clients on `addr` in the background, each served on
+    /// its own thread until it goes away. Returns the bound address.
+    pub fn serve(&self, addr: &str) -> Result<SocketAddr, ChainError> {
+        let listener = TcpListener::bind(addr).map_err(|e| {
+            ChainError::NetworkError(format!("Failed to bind stats feed on {}: {}", addr, e))
+        })?;
+        let local = listener.local_addr().map_err(|e| {
+            ChainError::NetworkError(format!("Failed to read stats feed address: {}", e))
+        })?;
+        info!("📈 Dashboard stats feed on ws://{}", local);
+
+        let feed = self.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let feed = feed.clone();
+                        thread::spawn(move || feed.stream_to(stream));
+                    }
+                    Err(e) => warn!("⚠️  Stats feed connection failed: {}", e),
+                }
+            }
+        });
+        Ok(local)
+    }
+
+    /// Push the latest snapshot to one client until it disconnects
+    fn stream_to(&self, stream: TcpStream) {
+        let mut socket = match tungstenite::accept(stream) {
+            Ok(socket) => socket,
+            Err(e) => {
+                warn!("⚠️  Stats feed handshake failed: {}", e);
+                return;
+            }
+        };
+        loop {
+            let json = serde_json::to_string(&self.latest()).unwrap_or_default();
+            if socket.send(Message::Text(json)).is_err() {
+                return;
+            }
+            thread::sleep(STATS_PUSH_INTERVAL);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_feed_pushes_latest_snapshot() {
+        let feed = StatsFeed::new();
+        let addr = feed.serve("127.0.0.1:0").unwrap();
+        feed.publish(NodeStats {
+            status: "Running".to_string(),
+            chain_height: 42,
+            peers: 3,
+            ..NodeStats::default()
+        });
+
+        let (mut client, _) = tungstenite::connect(format!("ws://{}", addr)).unwrap();
+        let text = client.read().unwrap().into_text().unwrap();
+        let stats: NodeStats = serde_json::from_str(&text).unwrap();
+        assert_eq!(stats.chain_height, 42);
+        assert_eq!(stats.peers, 3);
+        assert_eq!(stats.status, "Running");
+    }
+}
@@ -6,14 +6,17 @@
 //! - Peer management and selection
 //! - Sync progress tracking
 //! - Automatic peer discovery
+//! - Orphan block pool for blocks that arrive before their parent
 
-use crate::blockchain::{Block, Blockchain};
+use crate::blockchain::{Block, Blockchain, Sha256Hash};
 use crate::error::ChainError;
 use crate::network::Node;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
+use tracing::{info, warn};
 
 /// Peer sync information
 #[derive(Debug, Clone)]
@@ -73,19 +76,403 @@ impl Default for SyncStats {
     }
 }
 
-/// Sync state tracking
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum SyncState {
+/// Maximum number of orphan blocks held at once
+pub const MAX_ORPHAN_BLOCKS: usize = 100;
+
+/// Most serialized bytes of orphan blocks held at once
+pub const MAX_ORPHAN_BYTES: usize = 32 * 1024 * 1024;
+
+/// Most orphan blocks held from any one peer; more evict that peer's oldest
+pub const MAX_ORPHANS_PER_PEER: usize = 20;
+
+/// How long an orphan block is kept while waiting for its parent
+pub const ORPHAN_EXPIRY: Duration = Duration::from_secs(20 * 60);
+
+/// A block held until its parent is connected
+#[derive(Debug, Clone)]
+pub struct OrphanBlock {
+    pub block: Block,
+    /// Address of the peer that sent the block, if any
+    pub from_peer: Option<String>,
+    pub received: Instant,
+    /// Serialized size in bytes
+    pub size: usize,
+}
+
+/// Bounded pool of blocks whose parent is not yet known
+#[derive(Debug)]
+pub struct OrphanPool {
+    orphans: HashMap<Sha256Hash, OrphanBlock>,
+    /// Parent hash -> hashes of orphans waiting on it
+    by_parent: HashMap<Sha256Hash, Vec<Sha256Hash>>,
+    max_orphans: usize,
+    max_bytes: usize,
+    max_per_peer: usize,
+    expiry: Duration,
+    /// Total serialized size of the held blocks
+    bytes: usize,
+}
+
+impl OrphanPool {
+    pub fn new(max_orphans: usize, expiry: Duration) -> Self {
+        Self {
+            orphans: HashMap::new(),
+            by_parent: HashMap::new(),
+            max_orphans,
+            max_bytes: MAX_ORPHAN_BYTES,
+            max_per_peer: MAX_ORPHANS_PER_PEER,
+            expiry,
+            bytes: 0,
+        }
+    }
+
+    /// Hold at most `max_bytes` of blocks in total and `max_per_peer` blocks
+    /// from any one peer
+    pub fn with_limits(mut self, max_bytes: usize, max_per_peer: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self.max_per_peer = max_per_peer;
+        self
+    }
+
+    /// Serialized size of the held blocks
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.orphans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orphans.is_empty()
+    }
+
+    pub fn contains(&self, hash: &Sha256Hash) -> bool {
+        self.orphans.contains_key(hash)
+    }
+
+    /// Add an orphan block. Expired entries are dropped first. A peer at
+    /// its quota loses its own oldest orphan; otherwise, while the pool is
+    /// full by count or bytes, the oldest orphan is evicted. Returns false if
+    /// the block was already held or is larger than the whole pool.
+    pub fn insert(&mut self, block: Block, from_peer: Option<String>) -> bool {
+        let hash = block.hash();
+        let size = bincode::serialized_size(&block).map_or(usize::MAX, |n| n as usize);
+        if self.orphans.contains_key(&hash) || size > self.max_bytes {
+            return false;
+        }
+
+        self.expire();
+        if from_peer.is_some() {
+            let from_same_peer = || {
+                self.orphans
+                    .iter()
+                    .filter(|(_, o)| o.from_peer == from_peer)
+            };
+            if from_same_peer().count() >= self.max_per_peer {
+                let oldest = from_same_peer()
+                    .min_by_key(|(_, o)| o.received)
+                    .map(|(h, _)| *h);
+                if let Some(oldest) = oldest {
+                    self.remove(&oldest);
+                }
+            }
+        }
+        while self.orphans.len() >= self.max_orphans || self.bytes + size > self.max_bytes {
+            let oldest = self
+                .orphans
+                .iter()
+                .min_by_key(|(_, o)| o.received)
+                .map(|(h, _)| *h);
+            let Some(oldest) = oldest else {
+                break;
+            };
+            self.remove(&oldest);
+        }
+
+        self.by_parent
+            .entry(block.header.previous_hash)
+            .or_default()
+            .push(hash);
+        self.bytes += size;
+        self.orphans.insert(
+            hash,
+            OrphanBlock {
+                block,
+                from_peer,
+                received: Instant::now(),
+                size,
+            },
+        );
+        true
+    }
+
+    /// Drop orphans older than the expiry and return how many were removed
+    pub fn expire(&mut self) -> usize {
+        let expired: Vec<Sha256Hash> = self
+            .orphans
+            .iter()
+            .filter(|(_, o)| o.received.elapsed() > self.expiry)
+            .map(|(h, _)| *h)
+            .collect();
+        for hash in &expired {
+            self.remove(hash);
+        }
+        expired.len()
+    }
+
+    fn remove(&mut self, hash: &Sha256Hash) -> Option<OrphanBlock> {
+        let orphan = self.orphans.remove(hash)?;
+        self.bytes -= orphan.size;
+        let parent = orphan.block.header.previous_hash;
+        if let Some(children) = self.by_parent.get_mut(&parent) {
+            children.retain(|h| h != hash);
+            if children.is_empty() {
+                self.by_parent.remove(&parent);
+            }
+        }
+        Some(orphan)
+    }
+
+    /// Remove and return the orphans whose parent is `parent`
+    pub fn take_children(&mut self, parent: &Sha256Hash) -> Vec<Block> {
+        let children = self.by_parent.remove(parent).unwrap_or_default();
+        children
+            .into_iter()
+            .filter_map(|h| self.remove(&h))
+            .map(|o| o.block)
+            .collect()
+    }
+
+    /// The block that must be fetched to connect the orphan chain ending in
+    /// `hash`: walks up through held orphans to the first unknown parent.
+    pub fn missing_ancestor(&self, hash: &Sha256Hash) -> Sha256Hash {
+        let mut current = *hash;
+        while let Some(orphan) = self.orphans.get(&current) {
+            current = orphan.block.header.previous_hash;
+        }
+        current
+    }
+
+    /// The held chain running through `hash`: its held ancestors, oldest
+    /// first, then the longest chain of held descendants. Empty if `hash` is
+    /// not held.
+    pub fn branch(&self, hash: &Sha256Hash) -> Vec<Block> {
+        let mut ancestors = Vec::new();
+        let mut current = *hash;
+        while let Some(orphan) = self.orphans.get(&current) {
+            ancestors.push(orphan.block.clone());
+            current = orphan.block.header.previous_hash;
+        }
+        ancestors.reverse();
+        if ancestors.is_empty() {
+            return ancestors;
+        }
+        ancestors.extend(self.longest_descendants(hash));
+        ancestors
+    }
+
+    fn longest_descendants(&self, parent: &Sha256Hash) -> Vec<Block> {
+        self.by_parent
+            .get(parent)
+            .into_iter()
+            .flatten()
+            .filter_map(|h| self.orphans.get(h))
+            .map(|child| {
+                let mut chain = vec![child.block.clone()];
+                chain.extend(self.longest_descendants(&child.block.hash()));
+                chain
+            })
+            .max_by_key(|chain| chain.len())
+            .unwrap_or_default()
+    }
+
+    /// Drop held blocks, e.g. once a reorg connected them
+    pub fn discard(&mut self, hashes: &[Sha256Hash]) {
+        for hash in hashes {
+            self.remove(hash);
+        }
+    }
+
+    /// Apply every held descendant of `parent` to the chain, breadth first,
+    /// and return how many blocks were connected. Descendants that fail
+    /// validation are dropped.
+    pub fn connect(&mut self, chain: &mut Blockchain, parent: Sha256Hash) -> usize {
+        let mut connected = 0;
+        let mut queue = VecDeque::from([parent]);
+        while let Some(parent) = queue.pop_front() {
+            for block in self.take_children(&parent) {
+                let hash = block.hash();
+                let height = block.header.height;
+                match chain.apply_block(block) {
+                    Ok(()) => {
+                        connected += 1;
+                        queue.push_back(hash);
+                    }
+                    Err(e) => {
+                        warn!("⚠️  Dropping orphan block #{}: {}", height, e);
+                    }
+                }
+            }
+        }
+        connected
+    }
+}
+
+impl Default for OrphanPool {
+    fn default() -> Self {
+        Self::new(MAX_ORPHAN_BLOCKS, ORPHAN_EXPIRY)
+    }
+}
+
+/// Where the synchronizer is in catching up with its peers
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncPhase {
+    /// No peer has advertised a height yet
+    #[default]
     Idle,
     Syncing,
     Synced,
     Failed,
 }
 
+impl SyncPhase {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SyncPhase::Idle => "idle",
+            SyncPhase::Syncing => "syncing",
+            SyncPhase::Synced => "synced",
+            SyncPhase::Failed => "failed",
+        }
+    }
+}
+
+/// How far the node is from its best peer's tip, and how fast it is closing in
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct SyncState {
+    pub phase: SyncPhase,
+    /// Height of the local tip
+    pub height: u64,
+    /// Highest height advertised by a connected peer
+    pub target_height: Option<u64>,
+    /// Blocks connected per second over the last `SyncTracker` window
+    pub blocks_per_sec: f64,
+    /// Seconds until the target is reached at the current speed; `None`
+    /// when already there or not progressing
+    pub eta_secs: Option<u64>,
+}
+
+impl SyncState {
+    pub fn new(height: u64, target_height: Option<u64>, blocks_per_sec: f64) -> Self {
+        let phase = match target_height {
+            None => SyncPhase::Idle,
+            Some(target) if height >= target => SyncPhase::Synced,
+            Some(_) => SyncPhase::Syncing,
+        };
+        let remaining = target_height.map_or(0, |target| target.saturating_sub(height));
+        let eta_secs = (remaining > 0 && blocks_per_sec > 0.0)
+            .then(|| (remaining as f64 / blocks_per_sec).ceil() as u64);
+        SyncState {
+            phase,
+            height,
+            target_height,
+            blocks_per_sec,
+            eta_secs,
+        }
+    }
+
+    /// Blocks still to download
+    pub fn remaining(&self) -> u64 {
+        self.target_height
+            .map_or(0, |target| target.saturating_sub(self.height))
+    }
+
+    /// Fraction of the target height reached, 1.0 when synced or idle
+    pub fn progress(&self) -> f64 {
+        match self.target_height {
+            Some(target) if target > self.height => self.height as f64 / target as f64,
+            _ => 1.0,
+        }
+    }
+}
+
+/// Short human form of an ETA, e.g. `42s`, `5m03s` or `2h07m`
+pub fn format_eta(secs: u64) -> String {
+    match secs {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m{:02}s", s / 60, s % 60),
+        s => format!("{}h{:02}m", s / 3600, (s % 3600) / 60),
+    }
+}
+
+/// Measures how fast the local tip advances, from the heights seen over a
+/// sliding window
+#[derive(Debug, Clone)]
+pub struct SyncTracker {
+    window: Duration,
+    /// (time, height) whenever the tip changed, oldest first; the first
+    /// sample may predate the window, as the baseline
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl SyncTracker {
+    /// Window the sync speed is averaged over
+    pub const DEFAULT_WINDOW: Duration = Duration::from_secs(60);
+
+    pub fn new(window: Duration) -> Self {
+        SyncTracker {
+            window,
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Record the tip height at `now`
+    pub fn record(&mut self, height: u64, now: Instant) {
+        if self.samples.back().is_some_and(|&(_, last)| last == height) {
+            return;
+        }
+        self.samples.push_back((now, height));
+        // Keep one sample from before the window as the baseline
+        while self
+            .samples
+            .get(1)
+            .is_some_and(|&(at, _)| now.saturating_duration_since(at) >= self.window)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Blocks per second from the baseline sample to `now`, so the speed
+    /// decays while the tip stands still
+    pub fn blocks_per_sec(&self, now: Instant) -> f64 {
+        let (Some(&(since, first)), Some(&(_, last))) = (self.samples.front(), self.samples.back())
+        else {
+            return 0.0;
+        };
+        let elapsed = now.saturating_duration_since(since).as_secs_f64();
+        if elapsed <= 0.0 {
+            return 0.0;
+        }
+        last.saturating_sub(first) as f64 / elapsed
+    }
+
+    /// Sync state of a tip at `height` chasing `target_height`
+    pub fn state(&self, height: u64, target_height: Option<u64>, now: Instant) -> SyncState {
+        SyncState::new(height, target_height, self.blocks_per_sec(now))
+    }
+}
+
+impl Default for SyncTracker {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_WINDOW)
+    }
+}
+
 /// Node synchronizer
 pub struct NodeSynchronizer {
     peers: Arc<RwLock<HashMap<String, PeerSyncInfo>>>,
-    sync_state: Arc<RwLock<SyncState>>,
+    phase: Arc<RwLock<SyncPhase>>,
     stats: Arc<RwLock<SyncStats>>,
     /// Queue of blocks pending application
     pending_blocks: Arc<RwLock<VecDeque<Block>>>,
@@ -95,7 +482,7 @@ impl NodeSynchronizer {
     pub fn new() -> Self {
         Self {
             peers: Arc::new(RwLock::new(HashMap::new())),
-            sync_state: Arc::new(RwLock::new(SyncState::Idle)),
+            phase: Arc::new(RwLock::new(SyncPhase::Idle)),
             stats: Arc::new(RwLock::new(SyncStats::default())),
             pending_blocks: Arc::new(RwLock::new(VecDeque::new())),
         }
@@ -150,7 +537,7 @@ impl NodeSynchronizer {
             .collect();
 
         // Sort by height (descending) and return top N
-        valid_peers.sort_by(|a, b| b.height.cmp(&a.height));
+        valid_peers.sort_by_key(|b| std::cmp::Reverse(b.height));
         valid_peers
             .into_iter()
             .take(count)
@@ -160,17 +547,17 @@ impl NodeSynchronizer {
 
     /// Check if node is synced
     pub async fn is_synced(&self) -> bool {
-        *self.sync_state.read().await == SyncState::Synced
+        *self.phase.read().await == SyncPhase::Synced
     }
 
     /// Check if sync is in progress
     pub async fn is_syncing(&self) -> bool {
-        *self.sync_state.read().await == SyncState::Syncing
+        *self.phase.read().await == SyncPhase::Syncing
     }
 
-    /// Get current sync state
-    pub async fn get_sync_state(&self) -> SyncState {
-        *self.sync_state.read().await
+    /// Get current sync phase
+    pub async fn get_sync_phase(&self) -> SyncPhase {
+        *self.phase.read().await
     }
 
     /// Get sync statistics
@@ -178,9 +565,9 @@ impl NodeSynchronizer {
         self.stats.read().await.clone()
     }
 
-    /// Set sync state
-    async fn set_sync_state(&self, state: SyncState) {
-        *self.sync_state.write().await = state;
+    /// Set sync phase
+    async fn set_sync_phase(&self, state: SyncPhase) {
+        *self.phase.write().await = state;
     }
 
     /// Record successful block sync from peer
@@ -218,7 +605,7 @@ impl NodeSynchronizer {
             peer.last_seen = Instant::now();
 
             if peer.is_unreliable() {
-                println!("⚠️  Peer {} marked as unreliable", node_addr);
+                warn!("⚠️  Peer {} marked as unreliable", node_addr);
             }
 
             Ok(())
@@ -293,10 +680,10 @@ impl NodeSynchronizer {
             .last()
             .map_or(0, |b| b.header.height);
 
-        self.set_sync_state(SyncState::Syncing).await;
+        self.set_sync_phase(SyncPhase::Syncing).await;
         self.set_peer_syncing(&peer_addr, true).await?;
 
-        println!(
+        info!(
             "🔄 Starting sync from peer {} (local: {}, remote: estimated)",
             peer_addr, local_height
         );
@@ -308,7 +695,7 @@ impl NodeSynchronizer {
         // The NetworkNode calls record_block_received() and record_sync_failure() to update our state.
 
         self.set_peer_syncing(&peer_addr, false).await?;
-        self.set_sync_state(SyncState::Synced).await;
+        self.set_sync_phase(SyncPhase::Synced).await;
 
         let new_height = local_blockchain
             .blocks
@@ -318,11 +705,11 @@ impl NodeSynchronizer {
     }
 
     /// Check sync health by verifying we're not falling too far behind
-    pub async fn check_sync_health(&self, local_height: u64) -> SyncState {
+    pub async fn check_sync_health(&self, local_height: u64) -> SyncPhase {
         let peers = self.peers.read().await;
 
         if peers.is_empty() {
-            return SyncState::Idle;
+            return SyncPhase::Idle;
         }
 
         let max_peer_height = peers.values().map(|p| p.height).max().unwrap_or(0);
@@ -331,13 +718,13 @@ impl NodeSynchronizer {
         let is_caught_up = local_height >= max_peer_height.saturating_sub(1);
 
         if is_caught_up {
-            SyncState::Synced
+            SyncPhase::Synced
         } else {
             let lag = max_peer_height.saturating_sub(local_height);
             if lag > 100 {
-                println!("⚠️  Node is {} blocks behind best peer", lag);
+                warn!("⚠️  Node is {} blocks behind best peer", lag);
             }
-            SyncState::Syncing
+            SyncPhase::Syncing
         }
     }
 
@@ -351,7 +738,7 @@ impl NodeSynchronizer {
             .collect();
 
         for addr in stale_peers {
-            println!("🗑️  Removing stale peer: {}", addr);
+            info!("🗑️  Removing stale peer: {}", addr);
             peers.remove(&addr);
         }
     }
@@ -372,6 +759,7 @@ impl Default for NodeSynchronizer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypto::Address;
 
     #[tokio::test]
     async fn test_register_peer() {
@@ -425,6 +813,170 @@ mod tests {
         assert!(info.unwrap().is_unreliable());
     }
 
+    fn mined_child(parent: &Block) -> Block {
+        let coinbase = crate::transaction::Transaction::Coinbase(
+            crate::transaction::CoinbaseTx {
+                reward_area: crate::geometry::Coord::from_num(1),
+                beneficiary_address: Address::from([1; 32]),
+                nonce: parent.header.height + 1,
+            },
+        );
+        let mut block = Block::new(parent.header.height + 1, parent.hash(), 1, vec![coinbase]);
+        block.header.timestamp = parent.header.timestamp + 1;
+        crate::miner::mine_block(block).unwrap()
+    }
+
+    #[test]
+    fn test_orphan_pool_bounded() {
+        let chain = Blockchain::new(Address::from([1; 32]), 1).unwrap();
+        let mut pool = OrphanPool::new(2, ORPHAN_EXPIRY);
+
+        let b1 = mined_child(&chain.blocks[0]);
+        let b2 = mined_child(&b1);
+        let b3 = mined_child(&b2);
+
+        assert!(pool.insert(b1.clone(), None));
+        assert!(!pool.insert(b1.clone(), None));
+        assert!(pool.insert(b2.clone(), None));
+        assert!(pool.insert(b3.clone(), None));
+        assert_eq!(pool.len(), 2);
+        assert!(!pool.contains(&b1.hash()));
+
+        // b3's chain is missing b1
+        assert_eq!(pool.missing_ancestor(&b3.hash()), b1.hash());
+    }
+
+    #[test]
+    fn test_orphan_pool_limits_bytes_and_peers() {
+        let chain = Blockchain::new(Address::from([1; 32]), 1).unwrap();
+        let b1 = mined_child(&chain.blocks[0]);
+        let b2 = mined_child(&b1);
+        let b3 = mined_child(&b2);
+        let size = |b: &Block| bincode::serialized_size(b).unwrap() as usize;
+        let insert = |pool: &mut OrphanPool, block: &Block, peer: &str| {
+            // Distinct receive times, so "oldest" is well defined
+            std::thread::sleep(Duration::from_millis(2));
+            pool.insert(block.clone(), Some(peer.to_string()))
+        };
+
+        // A peer at its quota only evicts its own orphans
+        let mut pool = OrphanPool::new(10, ORPHAN_EXPIRY).with_limits(MAX_ORPHAN_BYTES, 1);
+        assert!(insert(&mut pool, &b1, "honest"));
+        assert!(insert(&mut pool, &b2, "spammer"));
+        assert!(insert(&mut pool, &b3, "spammer"));
+        assert!(pool.contains(&b1.hash()) && !pool.contains(&b2.hash()));
+
+        // The byte budget evicts the oldest, and rejects blocks that never fit
+        let budget = size(&b2) + size(&b3);
+        let mut pool = OrphanPool::new(10, ORPHAN_EXPIRY).with_limits(budget, 10);
+        assert!(insert(&mut pool, &b1, "a"));
+        assert!(insert(&mut pool, &b2, "a"));
+        assert!(insert(&mut pool, &b3, "a"));
+        assert!(!pool.contains(&b1.hash()));
+        assert_eq!(pool.bytes(), budget);
+        assert!(!OrphanPool::new(10, ORPHAN_EXPIRY)
+            .with_limits(size(&b1) - 1, 10)
+            .insert(b1.clone(), None));
+
+        pool.take_children(&b1.hash());
+        pool.take_children(&b2.hash());
+        assert_eq!(pool.bytes(), 0);
+    }
+
+    #[test]
+    fn test_orphan_pool_expiry() {
+        let chain = Blockchain::new(Address::from([1; 32]), 1).unwrap();
+        let mut pool = OrphanPool::new(10, Duration::from_millis(0));
+
+        pool.insert(mined_child(&chain.blocks[0]), None);
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(pool.expire(), 1);
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn test_orphans_connect_when_parent_arrives() {
+        let miner = Address::from([1; 32]);
+        let mut chain = Blockchain::new(miner, 1).unwrap();
+        // Connecting blocks checks their state roots, so mine them on a
+        // copy of the chain
+        let mut source = Blockchain::new(miner, 1).unwrap();
+        for _ in 0..3 {
+            let block = crate::fixtures::mine_next_block(&source, miner, vec![]);
+            source.apply_block(block).unwrap();
+        }
+        let [b1, b2, b3] = [1, 2, 3].map(|height| source.blocks[height].clone());
+
+        let mut pool = OrphanPool::default();
+        for block in [b3, b2] {
+            let result = chain.apply_block(block.clone());
+            assert!(matches!(result, Err(ChainError::OrphanBlock)));
+            pool.insert(block, Some("127.0.0.1:8333".to_string()));
+        }
+
+        let parent = b1.hash();
+        chain.apply_block(b1).unwrap();
+        assert_eq!(pool.connect(&mut chain, parent), 2);
+        assert_eq!(chain.blocks.len(), 4);
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn test_orphan_branch_follows_longest_descendants() {
+        let chain = Blockchain::new(Address::from([1; 32]), 1).unwrap();
+        let b1 = mined_child(&chain.blocks[0]);
+        let b2 = mined_child(&b1);
+        let b3 = mined_child(&b2);
+        let mut b2_alt = mined_child(&b1);
+        b2_alt.header.timestamp += 1;
+        let b2_alt = crate::miner::mine_block(b2_alt).unwrap();
+
+        let mut pool = OrphanPool::default();
+        for block in [&b1, &b2, &b3, &b2_alt] {
+            pool.insert(block.clone(), None);
+        }
+
+        let hashes: Vec<_> = pool.branch(&b2.hash()).iter().map(|b| b.hash()).collect();
+        assert_eq!(hashes, vec![b1.hash(), b2.hash(), b3.hash()]);
+        assert_eq!(pool.branch(&b1.hash()).len(), 3);
+        assert!(pool.branch(&chain.blocks[0].hash()).is_empty());
+
+        pool.discard(&hashes);
+        assert_eq!(pool.len(), 1);
+    }
+
+    #[test]
+    fn test_sync_tracker_speed_and_eta() {
+        let start = Instant::now();
+        let mut tracker = SyncTracker::new(Duration::from_secs(60));
+        assert_eq!(tracker.state(0, None, start).phase, SyncPhase::Idle);
+
+        tracker.record(100, start);
+        tracker.record(300, start + Duration::from_secs(10));
+        let now = start + Duration::from_secs(20);
+        assert_eq!(tracker.blocks_per_sec(now), 10.0);
+
+        let state = tracker.state(300, Some(1_300), now);
+        assert_eq!(state.phase, SyncPhase::Syncing);
+        assert_eq!(state.remaining(), 1_000);
+        assert_eq!(state.eta_secs, Some(100));
+        assert!((state.progress() - 300.0 / 1_300.0).abs() < 1e-9);
+
+        // Old samples leave the window; the newest before it stays as baseline
+        tracker.record(400, start + Duration::from_secs(100));
+        let later = start + Duration::from_secs(110);
+        assert_eq!(tracker.blocks_per_sec(later), 1.0);
+
+        let synced = tracker.state(1_300, Some(1_300), now);
+        assert_eq!(synced.phase, SyncPhase::Synced);
+        assert_eq!(synced.eta_secs, None);
+        assert_eq!(synced.progress(), 1.0);
+
+        assert_eq!(format_eta(42), "42s");
+        assert_eq!(format_eta(303), "5m03s");
+        assert_eq!(format_eta(7_620), "2h07m");
+    }
+
     #[tokio::test]
     async fn test_pending_blocks_queue() {
         let sync = NodeSynchronizer::new();
@@ -1,21 +1,28 @@
 //! Transaction types for TrinityChain
 
 use crate::crypto::Address;
-use crate::blockchain::{Sha256Hash, TriangleState};
+use crate::blockchain::{Sha256Hash, TriangleState, MAX_SUBDIVISION_DEPTH, MIN_SUBDIVISION_VALUE};
 use crate::error::ChainError;
 use crate::geometry::{Coord, Triangle};
+use crate::sighash::{sighash_v1, SighashVersion};
 use sha2::{Digest, Sha256};
 
 
 /// Maximum transaction size in bytes (100KB) to prevent DoS
 pub const MAX_TRANSACTION_SIZE: usize = 100_000;
 
+/// Largest amount or fee a transaction may carry. No triangle is worth more
+/// than the genesis one, and a full batch of outputs at this cap still sums
+/// within `Coord`.
+pub const MAX_TRANSACTION_AREA: Coord = Coord::from_bits(20_000_000i64 << 32);
+
 /// A transaction that can occur in a block
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum Transaction {
     Transfer(TransferTx),
     Subdivision(SubdivisionTx),
     Coinbase(CoinbaseTx),
+    BatchTransfer(BatchTransferTx),
 }
 
 impl Transaction {
@@ -38,12 +45,33 @@ impl Transaction {
         Ok(())
     }
 
+    /// Raw transaction: the canonical bincode encoding, hex-encoded
+    pub fn to_hex(&self) -> Result<String, ChainError> {
+        Ok(hex::encode(bincode::serialize(self)?))
+    }
+
+    /// Decode a raw transaction produced by [`Transaction::to_hex`]
+    pub fn from_hex(raw: &str) -> Result<Self, ChainError> {
+        let bytes = hex::decode(raw.trim()).map_err(|e| {
+            ChainError::InvalidTransaction(format!("Raw transaction is not hex: {}", e))
+        })?;
+        if bytes.len() > MAX_TRANSACTION_SIZE {
+            return Err(ChainError::InvalidTransaction(format!(
+                "Transaction too large: {} bytes (max: {})",
+                bytes.len(),
+                MAX_TRANSACTION_SIZE
+            )));
+        }
+        Ok(bincode::deserialize(&bytes)?)
+    }
+
     /// Get the geometric fee area for this transaction
     pub fn fee_area(&self) -> crate::geometry::Coord {
         match self {
             Transaction::Subdivision(tx) => tx.fee_area,
             Transaction::Transfer(tx) => tx.fee_area,
             Transaction::Coinbase(_) => Coord::from_num(0), // Coinbase has no fee
+            Transaction::BatchTransfer(tx) => tx.fee_area,
         }
     }
 
@@ -81,20 +109,154 @@ impl Transaction {
                 hasher.update(tx.fee_area.to_le_bytes());
                 hasher.update(tx.nonce.to_le_bytes());
             }
+            Transaction::BatchTransfer(tx) => {
+                hasher.update("batch_transfer".as_bytes());
+                hasher.update(tx.input_hash);
+                hasher.update(tx.sender);
+                for output in &tx.outputs {
+                    hasher.update(output.recipient);
+                    hasher.update(output.amount.to_le_bytes());
+                }
+                hasher.update(tx.fee_area.to_le_bytes());
+                hasher.update(tx.nonce.to_le_bytes());
+                if let Some(memo) = &tx.memo {
+                    hasher.update(memo.as_bytes());
+                }
+            }
         };
         hasher.finalize().into()
     }
 
-    /// Validate this transaction against the current UTXO state
-    pub fn validate(&self, state: &TriangleState) -> Result<(), ChainError> {
+    /// Addresses this transaction touches (sender, recipient, owner or miner)
+    pub fn addresses(&self) -> Vec<Address> {
+        match self {
+            Transaction::Transfer(tx) if tx.sender == tx.new_owner => vec![tx.sender],
+            Transaction::Transfer(tx) => vec![tx.sender, tx.new_owner],
+            Transaction::Subdivision(tx) => vec![tx.owner_address],
+            Transaction::Coinbase(tx) => vec![tx.beneficiary_address],
+            Transaction::BatchTransfer(tx) => {
+                let mut addresses = vec![tx.sender];
+                for output in &tx.outputs {
+                    if !addresses.contains(&output.recipient) {
+                        addresses.push(output.recipient);
+                    }
+                }
+                addresses
+            }
+        }
+    }
+
+    /// Public memo attached by the sender, if any
+    pub fn memo(&self) -> Option<&str> {
+        match self {
+            Transaction::Transfer(tx) => tx.memo.as_deref(),
+            Transaction::BatchTransfer(tx) => tx.memo.as_deref(),
+            Transaction::Subdivision(_) | Transaction::Coinbase(_) => None,
+        }
+    }
+
+    /// Whether the memo contains `query`, ignoring ASCII case (the same
+    /// match the memo index makes)
+    pub fn memo_contains(&self, query: &str) -> bool {
+        self.memo().is_some_and(|memo| {
+            memo.to_ascii_lowercase()
+                .contains(&query.to_ascii_lowercase())
+        })
+    }
+
+    /// The UTXO this transaction consumes (none for a coinbase)
+    pub fn input_hash(&self) -> Option<Sha256Hash> {
+        match self {
+            Transaction::Transfer(tx) => Some(tx.input_hash),
+            Transaction::Subdivision(tx) => Some(tx.parent_hash),
+            Transaction::Coinbase(_) => None,
+            Transaction::BatchTransfer(tx) => Some(tx.input_hash),
+        }
+    }
+
+    /// Validate this transaction against the current UTXO state, for
+    /// inclusion in a block at `height`
+    pub fn validate(&self, state: &TriangleState, height: u64) -> Result<(), ChainError> {
+        self.validate_stateless(height)?;
+        self.validate_state(state, height)
+    }
+
+    /// Checks that need no chain state, including signature verification
+    /// under the sighash schemes accepted at `height`
+    pub fn validate_stateless(&self, height: u64) -> Result<(), ChainError> {
         match self {
-            Transaction::Subdivision(tx) => tx.validate(state),
+            Transaction::Subdivision(tx) => tx.validate_signature_at(height),
             Transaction::Coinbase(tx) => tx.validate(),
-            Transaction::Transfer(tx) => tx.validate(),
+            Transaction::Transfer(tx) => tx.validate_at(height),
+            Transaction::BatchTransfer(tx) => tx.validate_at(height),
+        }
+    }
+
+    /// Checks against the UTXO set for a block at `height` (including
+    /// coinbase maturity), assuming `validate_stateless` passed
+    pub fn validate_state(&self, state: &TriangleState, height: u64) -> Result<(), ChainError> {
+        match self {
+            Transaction::Subdivision(tx) => tx.validate_state(state, height),
+            Transaction::Coinbase(_) => Ok(()),
+            Transaction::Transfer(tx) => tx.validate_state(state, height),
+            Transaction::BatchTransfer(tx) => tx.validate_state(state, height),
+        }
+    }
+
+    /// Short name of the transaction type
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Transaction::Transfer(_) => "transfer",
+            Transaction::Subdivision(_) => "subdivision",
+            Transaction::Coinbase(_) => "coinbase",
+            Transaction::BatchTransfer(_) => "batch_transfer",
         }
     }
 }
 
+/// Check that `signature` is a valid signature by `public_key` of the
+/// transaction's sighash under some scheme accepted at `height`, and that
+/// `public_key` belongs to `expected` (the address being spent from)
+fn verify_signed_by(
+    signature: &Option<Vec<u8>>,
+    public_key: &Option<Vec<u8>>,
+    sighash: impl Fn(SighashVersion) -> Vec<u8>,
+    height: u64,
+    expected: &Address,
+) -> Result<(), ChainError> {
+    let (signature, public_key) = match (signature, public_key) {
+        (Some(sig), Some(pk)) => (sig, pk),
+        _ => {
+            return Err(ChainError::InvalidTransaction(
+                "Transaction not signed".to_string(),
+            ))
+        }
+    };
+
+    if crate::crypto::address_from_public_key(public_key) != *expected {
+        return Err(ChainError::InvalidTransaction(format!(
+            "Public key does not belong to address {}",
+            hex::encode(expected)
+        )));
+    }
+
+    let minimum = SighashVersion::minimum_at(height);
+    let mut result = Ok(());
+    for version in SighashVersion::ALL {
+        result = crate::crypto::verify_signature(public_key, &sighash(version), signature);
+        if result.is_ok() {
+            if version < minimum {
+                return Err(ChainError::InvalidTransaction(format!(
+                    "{:?} sighash signatures are not accepted at height {}; re-sign the transaction",
+                    version, height
+                )));
+            }
+            return Ok(());
+        }
+    }
+    result
+}
+
 /// Subdivision transaction: splits one parent triangle into three children
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct SubdivisionTx {
@@ -126,16 +288,38 @@ impl SubdivisionTx {
         }
     }
 
+    /// Message to sign: the sighash under the current scheme
     pub fn signable_message(&self) -> Vec<u8> {
-        let mut message = Vec::new();
-        message.extend_from_slice(&self.parent_hash);
-        for child in &self.children {
-            message.extend_from_slice(&child.hash());
+        self.sighash(SighashVersion::CURRENT)
+    }
+
+    /// Covers every field except the signature and public key; children are
+    /// committed to by hash
+    pub fn sighash(&self, version: SighashVersion) -> Vec<u8> {
+        let child_hashes: Vec<Sha256Hash> = self.children.iter().map(Triangle::hash).collect();
+        match version {
+            SighashVersion::V1 => sighash_v1(
+                "subdivision",
+                &(
+                    self.parent_hash,
+                    &child_hashes,
+                    self.owner_address,
+                    self.fee_area,
+                    self.nonce,
+                ),
+            ),
+            SighashVersion::Legacy => {
+                let mut message = Vec::new();
+                message.extend_from_slice(&self.parent_hash);
+                for hash in &child_hashes {
+                    message.extend_from_slice(hash);
+                }
+                message.extend_from_slice(self.owner_address.as_bytes());
+                message.extend_from_slice(&self.fee_area.to_le_bytes());
+                message.extend_from_slice(&self.nonce.to_le_bytes());
+                message
+            }
         }
-        message.extend_from_slice(&self.owner_address);
-        message.extend_from_slice(&self.fee_area.to_le_bytes());
-        message.extend_from_slice(&self.nonce.to_le_bytes());
-        message
     }
 
     pub fn sign(&mut self, signature: Vec<u8>, public_key: Vec<u8>) {
@@ -144,29 +328,36 @@ impl SubdivisionTx {
     }
 
     /// Validates just the signature of the transaction, without access to blockchain state.
-    /// This is useful for early validation in the mempool.
+    /// This is useful for early validation in the mempool. Only the current
+    /// sighash scheme is accepted; see `validate_signature_at`.
     pub fn validate_signature(&self) -> Result<(), ChainError> {
-        let (signature, public_key) = match (&self.signature, &self.public_key) {
-            (Some(sig), Some(pk)) => (sig, pk),
-            _ => {
-                return Err(ChainError::InvalidTransaction(
-                    "Transaction not signed".to_string(),
-                ))
-            }
-        };
-
-        let message = self.signable_message();
-        crate::crypto::verify_signature(public_key, &message, signature)?;
+        self.validate_signature_at(u64::MAX)
+    }
 
-        Ok(())
+    /// Validates the signature under the sighash schemes accepted in a block at `height`
+    pub fn validate_signature_at(&self, height: u64) -> Result<(), ChainError> {
+        verify_signed_by(
+            &self.signature,
+            &self.public_key,
+            |version| self.sighash(version),
+            height,
+            &self.owner_address,
+        )
     }
 
-    /// Performs a full validation of the transaction against the current blockchain state.
-    pub fn validate(&self, state: &TriangleState) -> Result<(), ChainError> {
+    /// Performs a full validation of the transaction against the current
+    /// blockchain state, for inclusion in a block at `height`.
+    pub fn validate(&self, state: &TriangleState, height: u64) -> Result<(), ChainError> {
         // First, perform a stateless signature check.
         self.validate_signature()?;
 
         // Then, validate against the current state (UTXO set).
+        self.validate_state(state, height)
+    }
+
+    /// Validates the parent and children against the UTXO set, without
+    /// checking the signature
+    pub fn validate_state(&self, state: &TriangleState, height: u64) -> Result<(), ChainError> {
         let parent = match state.utxo_set.get(&self.parent_hash) {
             Some(triangle) => triangle,
             None => {
@@ -184,6 +375,14 @@ impl SubdivisionTx {
                 hex::encode(self.owner_address), hex::encode(parent.owner)
             )));
         }
+        state.check_mature(&self.parent_hash, height)?;
+
+        if parent.depth >= MAX_SUBDIVISION_DEPTH {
+            return Err(ChainError::InvalidTransaction(format!(
+                "Parent triangle is at the maximum subdivision depth {}",
+                MAX_SUBDIVISION_DEPTH
+            )));
+        }
 
         let expected_children = parent.subdivide();
 
@@ -204,6 +403,21 @@ impl SubdivisionTx {
                     i
                 )));
             }
+            if child.depth != expected.depth {
+                return Err(ChainError::InvalidTransaction(format!(
+                    "Child {} depth {} should be {}",
+                    i, child.depth, expected.depth
+                )));
+            }
+            // Dust protection
+            if child.effective_value() < MIN_SUBDIVISION_VALUE {
+                return Err(ChainError::InvalidTransaction(format!(
+                    "Child {} value {} is below the minimum {}",
+                    i,
+                    child.effective_value(),
+                    MIN_SUBDIVISION_VALUE
+                )));
+            }
         }
 
         Ok(())
@@ -303,17 +517,39 @@ impl TransferTx {
         Ok(self)
     }
 
+    /// Message to sign: the sighash under the current scheme
     pub fn signable_message(&self) -> Vec<u8> {
-        let mut message = Vec::new();
-        message.extend_from_slice("TRANSFER:".as_bytes());
-        message.extend_from_slice(&self.input_hash);
-        message.extend_from_slice(&self.new_owner);
-        message.extend_from_slice(&self.sender);
-        message.extend_from_slice(&self.amount.to_le_bytes());
-        // Use f64 bytes for geometric fee
-        message.extend_from_slice(&self.fee_area.to_le_bytes());
-        message.extend_from_slice(&self.nonce.to_le_bytes());
-        message
+        self.sighash(SighashVersion::CURRENT)
+    }
+
+    /// V1 covers every field except the signature and public key, including
+    /// the memo; the legacy scheme omits the memo
+    pub fn sighash(&self, version: SighashVersion) -> Vec<u8> {
+        match version {
+            SighashVersion::V1 => sighash_v1(
+                "transfer",
+                &(
+                    self.input_hash,
+                    self.new_owner,
+                    self.sender,
+                    self.amount,
+                    self.fee_area,
+                    self.nonce,
+                    &self.memo,
+                ),
+            ),
+            SighashVersion::Legacy => {
+                let mut message = Vec::new();
+                message.extend_from_slice("TRANSFER:".as_bytes());
+                message.extend_from_slice(&self.input_hash);
+                message.extend_from_slice(self.new_owner.as_bytes());
+                message.extend_from_slice(self.sender.as_bytes());
+                message.extend_from_slice(&self.amount.to_le_bytes());
+                message.extend_from_slice(&self.fee_area.to_le_bytes());
+                message.extend_from_slice(&self.nonce.to_le_bytes());
+                message
+            }
+        }
     }
 
     pub fn sign(&mut self, signature: Vec<u8>, public_key: Vec<u8>) {
@@ -323,7 +559,13 @@ impl TransferTx {
 
     /// Stateless validation: checks signature, addresses, memo, and fee bounds.
     /// Does NOT validate against UTXO state - use validate_with_state() for that.
+    /// Only the current sighash scheme is accepted; see `validate_at`.
     pub fn validate(&self) -> Result<(), ChainError> {
+        self.validate_at(u64::MAX)
+    }
+
+    /// Stateless validation for inclusion in a block at `height`
+    pub fn validate_at(&self, height: u64) -> Result<(), ChainError> {
         if self.signature.is_none() || self.public_key.is_none() {
             return Err(ChainError::InvalidTransaction(
                 "Transfer not signed".to_string(),
@@ -364,6 +606,12 @@ impl TransferTx {
                 "Amount and fee cannot both be zero".to_string(),
             ));
         }
+        if self.amount > MAX_TRANSACTION_AREA || self.fee_area > MAX_TRANSACTION_AREA {
+            return Err(ChainError::InvalidTransaction(format!(
+                "Transfer amount and fee may not exceed {}",
+                MAX_TRANSACTION_AREA
+            )));
+        }
 
         // Validate memo length to prevent DoS attacks
         if let Some(ref memo) = self.memo {
@@ -375,27 +623,41 @@ impl TransferTx {
             }
         }
 
-        let (signature, public_key) = match (&self.signature, &self.public_key) {
-            (Some(sig), Some(pk)) => (sig, pk),
-            _ => {
-                return Err(ChainError::InvalidTransaction(
-                    "Transfer not signed".to_string(),
-                ))
-            }
-        };
-
-        let message = self.signable_message();
-        crate::crypto::verify_signature(public_key, &message, signature)?;
+        verify_signed_by(
+            &self.signature,
+            &self.public_key,
+            |version| self.sighash(version),
+            height,
+            &self.sender,
+        )
+    }
 
-        Ok(())
+    /// Amount plus fee: the area taken from the input
+    pub fn total_spent(&self) -> Result<Coord, ChainError> {
+        self.amount.checked_add(self.fee_area).ok_or_else(|| {
+            ChainError::InvalidTransaction(format!(
+                "Transfer amount {} plus fee {} overflows",
+                self.amount, self.fee_area
+            ))
+        })
     }
 
-    /// Full validation including UTXO state check.
-    /// Ensures: input triangle exists AND input.effective_value() > fee_area + TOLERANCE
-    pub fn validate_with_state(&self, state: &TriangleState) -> Result<(), ChainError> {
+    /// Full validation including UTXO state check, for a block at `height`.
+    /// Ensures: input triangle exists, is mature AND
+    /// input.effective_value() > fee_area + TOLERANCE
+    pub fn validate_with_state(
+        &self,
+        state: &TriangleState,
+        height: u64,
+    ) -> Result<(), ChainError> {
         // First perform stateless validation
-        self.validate()?;
+        self.validate_at(height)?;
+        self.validate_state(state, height)
+    }
 
+    /// UTXO checks only (input exists, is mature, covers amount + fee, owned
+    /// by the sender), without the stateless checks
+    pub fn validate_state(&self, state: &TriangleState, height: u64) -> Result<(), ChainError> {
         // Check input triangle exists in UTXO set
         let input_triangle = state.utxo_set.get(&self.input_hash).ok_or_else(|| {
             ChainError::TriangleNotFound(format!(
@@ -406,7 +668,7 @@ impl TransferTx {
 
         // Area balance check: input value must be strictly greater than fee
         let input_value = input_triangle.effective_value();
-        let total_spent = self.amount + self.fee_area;
+        let total_spent = self.total_spent()?;
         let remaining_value = input_value - total_spent;
 
         if remaining_value < crate::geometry::GEOMETRIC_TOLERANCE {
@@ -424,7 +686,222 @@ impl TransferTx {
             )));
         }
 
-        Ok(())
+        // Freshly mined rewards must mature first
+        state.check_mature(&self.input_hash, height)
+    }
+}
+
+/// One payment of a batch transfer
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TransferOutput {
+    pub recipient: Address,
+    pub amount: Coord,
+}
+
+/// Batch transfer transaction - pays several recipients out of one triangle
+///
+/// Each output becomes its own triangle; whatever is left after the outputs
+/// and the fee returns to the sender as change, as with `TransferTx`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BatchTransferTx {
+    pub input_hash: Sha256Hash,
+    pub sender: Address,
+    pub outputs: Vec<TransferOutput>,
+    /// Geometric fee: area deducted from triangle value and given to miner
+    pub fee_area: Coord,
+    pub nonce: u64,
+    pub signature: Option<Vec<u8>>,
+    pub public_key: Option<Vec<u8>>,
+    #[serde(default)]
+    pub memo: Option<String>,
+}
+
+impl BatchTransferTx {
+    /// Maximum number of outputs in one batch
+    pub const MAX_OUTPUTS: usize = 100;
+
+    pub fn new(
+        input_hash: Sha256Hash,
+        sender: Address,
+        outputs: Vec<TransferOutput>,
+        fee_area: Coord,
+        nonce: u64,
+    ) -> Self {
+        BatchTransferTx {
+            input_hash,
+            sender,
+            outputs,
+            fee_area,
+            nonce,
+            signature: None,
+            public_key: None,
+            memo: None,
+        }
+    }
+
+    pub fn with_memo(mut self, memo: String) -> Result<Self, ChainError> {
+        if memo.len() > TransferTx::MAX_MEMO_LENGTH {
+            return Err(ChainError::InvalidTransaction(format!(
+                "Memo exceeds maximum length of {} characters",
+                TransferTx::MAX_MEMO_LENGTH
+            )));
+        }
+        self.memo = Some(memo);
+        Ok(self)
+    }
+
+    /// Sum of all output amounts
+    pub fn total_amount(&self) -> Result<Coord, ChainError> {
+        self.outputs
+            .iter()
+            .try_fold(Coord::from_num(0), |total, o| total.checked_add(o.amount))
+            .ok_or_else(|| {
+                ChainError::InvalidTransaction("Batch transfer outputs overflow".to_string())
+            })
+    }
+
+    /// Area taken from the input: every output plus the fee
+    pub fn total_spent(&self) -> Result<Coord, ChainError> {
+        self.total_amount()?
+            .checked_add(self.fee_area)
+            .ok_or_else(|| {
+                ChainError::InvalidTransaction(format!(
+                    "Batch transfer outputs plus fee {} overflow",
+                    self.fee_area
+                ))
+            })
+    }
+
+    /// Message to sign: the sighash under the current scheme
+    pub fn signable_message(&self) -> Vec<u8> {
+        self.sighash(SighashVersion::CURRENT)
+    }
+
+    /// Batch transfers postdate the legacy scheme, so every version hashes
+    /// all unsigned fields the V1 way
+    pub fn sighash(&self, _version: SighashVersion) -> Vec<u8> {
+        sighash_v1(
+            "batch_transfer",
+            &(
+                self.input_hash,
+                self.sender,
+                &self.outputs,
+                self.fee_area,
+                self.nonce,
+                &self.memo,
+            ),
+        )
+    }
+
+    pub fn sign(&mut self, signature: Vec<u8>, public_key: Vec<u8>) {
+        self.signature = Some(signature);
+        self.public_key = Some(public_key);
+    }
+
+    /// Stateless validation for inclusion in a block at `height`: outputs,
+    /// fee, memo and signature
+    pub fn validate_at(&self, height: u64) -> Result<(), ChainError> {
+        if self.sender == [0; 32] {
+            return Err(ChainError::InvalidTransaction(
+                "Sender address cannot be empty".to_string(),
+            ));
+        }
+        if self.outputs.is_empty() {
+            return Err(ChainError::InvalidTransaction(
+                "Batch transfer has no outputs".to_string(),
+            ));
+        }
+        if self.outputs.len() > Self::MAX_OUTPUTS {
+            return Err(ChainError::InvalidTransaction(format!(
+                "Batch transfer has {} outputs (max: {})",
+                self.outputs.len(),
+                Self::MAX_OUTPUTS
+            )));
+        }
+        for (i, output) in self.outputs.iter().enumerate() {
+            if output.recipient == [0; 32] {
+                return Err(ChainError::InvalidTransaction(format!(
+                    "Output {} recipient address cannot be empty",
+                    i
+                )));
+            }
+            if output.recipient == self.sender {
+                return Err(ChainError::InvalidTransaction(format!(
+                    "Output {} pays the sender; change is returned automatically",
+                    i
+                )));
+            }
+            if output.amount <= Coord::from_num(0) {
+                return Err(ChainError::InvalidTransaction(format!(
+                    "Output {} amount must be positive",
+                    i
+                )));
+            }
+            if output.amount > MAX_TRANSACTION_AREA {
+                return Err(ChainError::InvalidTransaction(format!(
+                    "Output {} amount may not exceed {}",
+                    i, MAX_TRANSACTION_AREA
+                )));
+            }
+        }
+        if self.fee_area < Coord::from_num(0) {
+            return Err(ChainError::InvalidTransaction(
+                "Fee area cannot be negative".to_string(),
+            ));
+        }
+        if self.fee_area > MAX_TRANSACTION_AREA {
+            return Err(ChainError::InvalidTransaction(format!(
+                "Fee area may not exceed {}",
+                MAX_TRANSACTION_AREA
+            )));
+        }
+        if let Some(ref memo) = self.memo {
+            if memo.len() > TransferTx::MAX_MEMO_LENGTH {
+                return Err(ChainError::InvalidTransaction(format!(
+                    "Memo exceeds maximum length of {} characters",
+                    TransferTx::MAX_MEMO_LENGTH
+                )));
+            }
+        }
+
+        verify_signed_by(
+            &self.signature,
+            &self.public_key,
+            |version| self.sighash(version),
+            height,
+            &self.sender,
+        )
+    }
+
+    /// UTXO checks only (input exists, is mature, covers every output plus
+    /// the fee, owned by the sender)
+    pub fn validate_state(&self, state: &TriangleState, height: u64) -> Result<(), ChainError> {
+        let input_triangle = state.utxo_set.get(&self.input_hash).ok_or_else(|| {
+            ChainError::TriangleNotFound(format!(
+                "Batch transfer input {} not found in UTXO set",
+                hex::encode(self.input_hash)
+            ))
+        })?;
+
+        let input_value = input_triangle.effective_value();
+        let total_spent = self.total_spent()?;
+        let remaining_value = input_value - total_spent;
+        if remaining_value < crate::geometry::GEOMETRIC_TOLERANCE {
+            return Err(ChainError::InvalidTransaction(format!(
+                "Insufficient triangle value: input has {} but outputs + fee_area is {}, leaving {} (minimum: {})",
+                input_value, total_spent, remaining_value, crate::geometry::GEOMETRIC_TOLERANCE
+            )));
+        }
+
+        if input_triangle.owner != self.sender {
+            return Err(ChainError::InvalidTransaction(format!(
+                "Sender {} does not own input triangle (owned by {})",
+                hex::encode(self.sender),
+                hex::encode(input_triangle.owner)
+            )));
+        }
+
+        state.check_mature(&self.input_hash, height)
     }
 }
 
@@ -439,7 +916,7 @@ mod tests {
         let mut address = [0u8; 32];
         let bytes = s.as_bytes();
         address[..bytes.len()].copy_from_slice(bytes);
-        address
+        Address::from(address)
     }
 
     #[test]
@@ -453,7 +930,7 @@ mod tests {
             Point::new(Coord::from_num(1.0), Coord::from_num(0.0)),
             Point::new(Coord::from_num(0.5), Coord::from_num(0.866)),
             None,
-            address.clone(),
+            address,
         );
         let parent_hash = parent.hash();
         state.utxo_set.insert(parent_hash, parent.clone());
@@ -471,7 +948,47 @@ mod tests {
         let signature = keypair.sign(&message).unwrap();
         let public_key = keypair.public_key.serialize().to_vec();
         tx.sign(signature.to_vec(), public_key);
-        assert!(tx.validate(&state).is_ok());
+        assert!(tx.validate(&state, 1).is_ok());
+    }
+
+    #[test]
+    fn test_subdivision_depth_and_dust_limits() {
+        let mut state = TriangleState::new();
+        let owner = create_test_address("owner");
+        let parent = Triangle::new(
+            Point::new(Coord::from_num(0.0), Coord::from_num(0.0)),
+            Point::new(Coord::from_num(1.0), Coord::from_num(0.0)),
+            Point::new(Coord::from_num(0.5), Coord::from_num(0.866)),
+            None,
+            owner,
+        );
+        let fee = Coord::from_num(0);
+        let subdivide = |state: &mut TriangleState, parent: &Triangle| {
+            state.utxo_set.insert(parent.hash(), parent.clone());
+            let children = parent.subdivide().to_vec();
+            SubdivisionTx::new(parent.hash(), children, owner, fee, 1)
+        };
+
+        let tx = subdivide(&mut state, &parent);
+        assert!(tx.children.iter().all(|c| c.depth == 1));
+        assert!(tx.validate_state(&state, 1).is_ok());
+
+        // Children must be exactly one level deeper than their parent
+        let mut shallow = tx.clone();
+        shallow.children[0].depth = 0;
+        assert!(shallow.validate_state(&state, 1).is_err());
+
+        let mut deepest = parent.clone();
+        deepest.depth = MAX_SUBDIVISION_DEPTH;
+        let tx = subdivide(&mut state, &deepest);
+        let err = tx.validate_state(&state, 1).unwrap_err().to_string();
+        assert!(err.contains("maximum subdivision depth"), "{}", err);
+
+        // A third of the parent's value must still clear the dust limit
+        let dust = parent.with_effective_value(MIN_SUBDIVISION_VALUE * 2);
+        let tx = subdivide(&mut state, &dust);
+        let err = tx.validate_state(&state, 1).unwrap_err().to_string();
+        assert!(err.contains("below the minimum"), "{}", err);
     }
 
     #[test]
@@ -497,7 +1014,7 @@ mod tests {
             Coord::from_num(0),
             1,
         );
-        assert!(tx.validate(&state).is_err());
+        assert!(tx.validate(&state, 1).is_err());
     }
 
     #[test]
@@ -528,7 +1045,7 @@ mod tests {
         let public_key = keypair.public_key.serialize().to_vec();
         tx.sign(fake_signature, public_key);
 
-        assert!(tx.validate(&state).is_err());
+        assert!(tx.validate(&state, 1).is_err());
     }
 
     #[test]
@@ -557,7 +1074,7 @@ mod tests {
         let address = keypair.address();
 
         let tx = SubdivisionTx::new(parent_hash, children, address, Coord::from_num(0), 1);
-        assert!(tx.validate(&state).is_err());
+        assert!(tx.validate(&state, 1).is_err());
     }
 
     #[test]
@@ -583,7 +1100,7 @@ mod tests {
             1,
         );
 
-        assert!(tx.validate(&state).is_err());
+        assert!(tx.validate(&state, 1).is_err());
     }
 
     #[test]
@@ -597,7 +1114,7 @@ mod tests {
             Point::new(Coord::from_num(4.0), Coord::from_num(0.0)),
             Point::new(Coord::from_num(0.0), Coord::from_num(5.0)),
             None,
-            sender_address.clone(),
+            sender_address,
         );
 
         let triangle_hash = large_triangle.hash();
@@ -610,8 +1127,8 @@ mod tests {
 
         let mut tx = TransferTx::new(
             triangle_hash,
-            recipient_address.clone(),
-            sender_address.clone(),
+            recipient_address,
+            sender_address,
             Coord::from_num(1.0), // Amount > fee
             fee_area,
             1,
@@ -622,7 +1139,7 @@ mod tests {
         let public_key = keypair.public_key.serialize().to_vec();
         tx.sign(signature.to_vec(), public_key);
 
-        assert!(tx.validate_with_state(&state).is_ok());
+        assert!(tx.validate_with_state(&state, 1).is_ok());
 
         let old_triangle = state.utxo_set.remove(&triangle_hash).unwrap();
         let new_value = old_triangle.effective_value() - fee_area;
@@ -632,7 +1149,7 @@ mod tests {
             old_triangle.b,
             old_triangle.c,
             old_triangle.parent_hash,
-            recipient_address.clone(),
+            recipient_address,
             new_value,
         );
 
@@ -658,7 +1175,7 @@ mod tests {
             Point::new(Coord::from_num(1.0), Coord::from_num(0.0)),
             Point::new(Coord::from_num(0.5), Coord::from_num(1.0)),
             None,
-            sender_address.clone(),
+            sender_address,
         );
 
         let triangle_hash = small_triangle.hash();
@@ -670,7 +1187,7 @@ mod tests {
         let mut tx = TransferTx::new(
             triangle_hash,
             create_test_address("recipient"),
-            sender_address.clone(),
+            sender_address,
             Coord::from_num(0),
             fee_area,
             1,
@@ -681,7 +1198,7 @@ mod tests {
         let public_key = keypair.public_key.serialize().to_vec();
         tx.sign(signature.to_vec(), public_key);
 
-        let result = tx.validate_with_state(&state);
+        let result = tx.validate_with_state(&state, 1);
         assert!(result.is_err());
 
         if let Err(ChainError::InvalidTransaction(msg)) = result {
@@ -712,4 +1229,169 @@ mod tests {
         let result = tx.validate();
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_overflowing_transfer_rejected() {
+        let keypair = KeyPair::generate().unwrap();
+        let input = Triangle::new(
+            Point::new(Coord::from_num(0), Coord::from_num(0)),
+            Point::new(Coord::from_num(1), Coord::from_num(0)),
+            Point::new(Coord::from_num(0), Coord::from_num(1)),
+            None,
+            keypair.address(),
+        );
+        let mut state = TriangleState::new();
+        state.utxo_set.insert([1; 32], input);
+
+        let Transaction::Transfer(tx) = crate::fixtures::signed_transfer(
+            &keypair,
+            [1; 32],
+            create_test_address("recipient"),
+            Coord::MAX,
+            Coord::MAX,
+            0,
+        ) else {
+            unreachable!()
+        };
+        assert!(matches!(
+            tx.validate(),
+            Err(ChainError::InvalidTransaction(_))
+        ));
+        assert!(matches!(
+            tx.validate_state(&state, 1),
+            Err(ChainError::InvalidTransaction(_))
+        ));
+    }
+
+    #[test]
+    fn test_batch_transfer_validation() {
+        let keypair = KeyPair::generate().unwrap();
+        let sender = keypair.address();
+        let sign = |outputs: Vec<(Address, f64)>| {
+            let outputs = outputs
+                .into_iter()
+                .map(|(recipient, amount)| TransferOutput {
+                    recipient,
+                    amount: Coord::from_num(amount),
+                })
+                .collect();
+            let mut tx = BatchTransferTx::new([1; 32], sender, outputs, Coord::from_num(1), 0);
+            let signature = keypair.sign(&tx.signable_message()).unwrap();
+            tx.sign(signature.to_vec(), keypair.public_key_bytes().to_vec());
+            tx
+        };
+        let (alice, bob) = (create_test_address("alice"), create_test_address("bob"));
+
+        let batch = sign(vec![(alice, 3.0), (bob, 4.0)]);
+        assert!(batch.validate_at(u64::MAX).is_ok());
+        assert_eq!(batch.total_amount().unwrap(), Coord::from_num(7));
+        assert_eq!(
+            Transaction::BatchTransfer(batch.clone()).addresses(),
+            vec![sender, alice, bob]
+        );
+        // Signature covers the outputs
+        let mut tampered = batch;
+        tampered.outputs[1].amount = Coord::from_num(40);
+        assert!(tampered.validate_at(u64::MAX).is_err());
+
+        for outputs in [vec![], vec![(alice, 3.0), (bob, 0.0)], vec![(sender, 3.0)]] {
+            assert!(sign(outputs).validate_at(u64::MAX).is_err());
+        }
+
+        // The input must cover every output plus the fee, with change left over
+        let mut state = TriangleState::new();
+        let input = Triangle::new(
+            Point::new(Coord::from_num(0), Coord::from_num(0)),
+            Point::new(Coord::from_num(1), Coord::from_num(0)),
+            Point::new(Coord::from_num(0), Coord::from_num(1)),
+            None,
+            sender,
+        );
+        let batch = sign(vec![(alice, 3.0), (bob, 4.0)]);
+        let exact = input.clone().with_effective_value(Coord::from_num(8));
+        state.utxo_set.insert([1; 32], exact);
+        assert!(batch.validate_state(&state, 1).is_err());
+        let with_change = input.with_effective_value(Coord::from_num(9));
+        state.utxo_set.insert([1; 32], with_change);
+        assert!(batch.validate_state(&state, 1).is_ok());
+    }
+
+    #[test]
+    fn test_overflowing_batch_transfer_rejected() {
+        let keypair = KeyPair::generate().unwrap();
+        let input = Triangle::new(
+            Point::new(Coord::from_num(0), Coord::from_num(0)),
+            Point::new(Coord::from_num(1), Coord::from_num(0)),
+            Point::new(Coord::from_num(0), Coord::from_num(1)),
+            None,
+            keypair.address(),
+        );
+        let mut state = TriangleState::new();
+        state.utxo_set.insert([1; 32], input);
+        let (alice, bob) = (create_test_address("alice"), create_test_address("bob"));
+
+        let Transaction::BatchTransfer(outputs_overflow) = crate::fixtures::signed_batch_transfer(
+            &keypair,
+            [1; 32],
+            &[(alice, Coord::MAX), (bob, Coord::MAX)],
+            Coord::from_num(1),
+            0,
+        ) else {
+            unreachable!()
+        };
+        let Transaction::BatchTransfer(fee_overflow) = crate::fixtures::signed_batch_transfer(
+            &keypair,
+            [1; 32],
+            &[(alice, Coord::MAX)],
+            Coord::MAX,
+            0,
+        ) else {
+            unreachable!()
+        };
+        for tx in [outputs_overflow, fee_overflow] {
+            assert!(matches!(
+                tx.validate_at(u64::MAX),
+                Err(ChainError::InvalidTransaction(_))
+            ));
+            assert!(matches!(
+                tx.validate_state(&state, 1),
+                Err(ChainError::InvalidTransaction(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_sighash_covers_memo_and_retires_legacy() {
+        let keypair = KeyPair::generate().unwrap();
+        let public_key = keypair.public_key.serialize().to_vec();
+        let tx = TransferTx::new(
+            [7; 32],
+            create_test_address("recipient"),
+            keypair.address(),
+            Coord::from_num(1),
+            Coord::from_num(0),
+            1,
+        )
+        .with_memo("invoice 42".to_string())
+        .unwrap();
+
+        // The memo cannot be changed without invalidating a v1 signature
+        let mut signed = tx.clone();
+        let signature = keypair.sign(&signed.signable_message()).unwrap();
+        signed.sign(signature.to_vec(), public_key.clone());
+        assert!(signed.validate().is_ok());
+        signed.memo = Some("invoice 43".to_string());
+        assert!(signed.validate().is_err());
+
+        // Legacy signatures verify until the flag day
+        let flag_day = crate::config::active_network().sighash_v1_height();
+        let mut legacy = tx.clone();
+        let message = legacy.sighash(SighashVersion::Legacy);
+        legacy.sign(keypair.sign(&message).unwrap().to_vec(), public_key);
+        assert!(legacy.validate_at(flag_day).is_err());
+        assert!(legacy.validate().is_err());
+        if flag_day > 0 {
+            assert!(legacy.validate_at(flag_day - 1).is_ok());
+        }
+    }
 }
@@ -0,0 +1,168 @@
+//! Private transaction labels for TrinityChain wallets
+//!
+//! Labels and comments are local-only metadata attached to transaction hashes
+//! (e.g. "rent March"). Unlike the on-chain memo they are never broadcast;
+//! they live next to the wallet files and are shown in history and exports.
+
+use crate::error::ChainError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+const MAX_LABEL_LENGTH: usize = 64;
+const MAX_COMMENT_LENGTH: usize = 512;
+
+/// A label and optional comment for one transaction
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TxLabel {
+    pub label: String,
+    pub comment: Option<String>,
+    /// RFC3339 timestamp when the label was recorded
+    pub created_at: String,
+}
+
+/// Transaction labels keyed by transaction hash (hex)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TxLabels {
+    labels: HashMap<String, TxLabel>,
+}
+
+impl TxLabels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load labels from `path`, or start empty if the file doesn't exist
+    pub fn load_or_new(path: &Path) -> Result<Self, ChainError> {
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+        let contents = fs::read_to_string(path)
+            .map_err(|e| ChainError::WalletError(format!("Failed to read tx labels: {}", e)))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| ChainError::WalletError(format!("Failed to parse tx labels: {}", e)))
+    }
+
+    /// Atomically write labels to `path`
+    pub fn save(&self, path: &Path) -> Result<(), ChainError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| ChainError::WalletError(format!("Failed to serialize tx labels: {}", e)))?;
+
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let mut temp_file = NamedTempFile::new_in(dir)
+            .map_err(|e| ChainError::WalletError(format!("Failed to create temp file: {}", e)))?;
+        write!(temp_file, "{}", json)
+            .map_err(|e| ChainError::WalletError(format!("Failed to write to temp file: {}", e)))?;
+        temp_file
+            .persist(path)
+            .map_err(|e| ChainError::WalletError(format!("Failed to persist tx labels: {}", e)))?;
+        Ok(())
+    }
+
+    /// Attach a label (and optional comment) to a transaction, replacing any
+    /// existing one
+    pub fn set(
+        &mut self,
+        tx_hash: &str,
+        label: &str,
+        comment: Option<&str>,
+    ) -> Result<(), ChainError> {
+        let label = label.trim();
+        let comment = comment.map(str::trim).filter(|c| !c.is_empty());
+
+        if label.is_empty() {
+            return Err(ChainError::WalletError("Label cannot be empty".to_string()));
+        }
+        if label.len() > MAX_LABEL_LENGTH {
+            return Err(ChainError::WalletError(format!(
+                "Label too long (max {} characters)",
+                MAX_LABEL_LENGTH
+            )));
+        }
+        if comment.is_some_and(|c| c.len() > MAX_COMMENT_LENGTH) {
+            return Err(ChainError::WalletError(format!(
+                "Comment too long (max {} characters)",
+                MAX_COMMENT_LENGTH
+            )));
+        }
+
+        self.labels.insert(
+            tx_hash.to_lowercase(),
+            TxLabel {
+                label: label.to_string(),
+                comment: comment.map(str::to_string),
+                created_at: chrono::Utc::now().to_rfc3339(),
+            },
+        );
+        Ok(())
+    }
+
+    pub fn get(&self, tx_hash: &str) -> Option<&TxLabel> {
+        self.labels.get(&tx_hash.to_lowercase())
+    }
+
+    pub fn remove(&mut self, tx_hash: &str) -> Option<TxLabel> {
+        self.labels.remove(&tx_hash.to_lowercase())
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+}
+
+/// Get the default transaction label file path
+pub fn get_tx_labels_path() -> Result<PathBuf, ChainError> {
+    Ok(crate::wallet::get_wallet_dir()?.join("tx_labels.json"))
+}
+
+/// Load the default transaction labels, starting empty if missing
+pub fn load_default() -> Result<TxLabels, ChainError> {
+    TxLabels::load_or_new(&get_tx_labels_path()?)
+}
+
+/// Save to the default transaction label location
+pub fn save_default(labels: &TxLabels) -> Result<(), ChainError> {
+    crate::wallet::ensure_wallet_dir()?;
+    labels.save(&get_tx_labels_path()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_and_get_label() {
+        let mut labels = TxLabels::new();
+        labels.set("ABCD", "  rent March ", Some("  ")).unwrap();
+
+        let label = labels.get("abcd").unwrap();
+        assert_eq!(label.label, "rent March");
+        assert_eq!(label.comment, None);
+
+        assert!(labels.set("ef01", "", None).is_err());
+        assert!(labels.set("ef01", &"x".repeat(65), None).is_err());
+        assert_eq!(labels.len(), 1);
+    }
+
+    #[test]
+    fn test_labels_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tx_labels.json");
+
+        assert!(TxLabels::load_or_new(&path).unwrap().is_empty());
+
+        let mut labels = TxLabels::new();
+        labels.set("abcd", "rent March", Some("paid early")).unwrap();
+        labels.save(&path).unwrap();
+
+        let loaded = TxLabels::load_or_new(&path).unwrap();
+        assert_eq!(loaded.get("abcd"), labels.get("abcd"));
+    }
+}
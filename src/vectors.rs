@@ -0,0 +1,467 @@
+//! Wallet compatibility test vectors (feature `test-vectors`)
+//!
+//! Third-party wallets must derive the same addresses and produce the same
+//! signatures and transaction ids as this implementation, or their users
+//! lose funds. The suite pins every step from a recovery phrase to a signed
+//! transaction: BIP-39 seed, BIP-32 keys along TrinityChain's BIP-44 paths,
+//! addresses on every network, message signatures, and for each
+//! transaction type its sighash, signature, canonical (bincode) encoding
+//! and txid.
+//!
+//! The vectors ship as `test-vectors/wallet.json`. They are generated from
+//! fixed inputs by `generate` (`trinity test-vectors` prints them), and
+//! `verify` recomputes every entry of a suite from its inputs, so a suite
+//! produced by another implementation can be checked the same way.
+//!
+//! Signatures are ECDSA over secp256k1 with RFC 6979 nonces, computed on the
+//! SHA-256 of the message (for transactions, of the sighash), serialized in
+//! 64-byte compact form.
+
+use crate::config::Network;
+use crate::crypto::{verify_signature, KeyPair};
+use crate::error::ChainError;
+use crate::geometry::{Coord, Point, Triangle};
+use crate::hdwallet::{derivation_path, HDWallet};
+use crate::transaction::{
+    BatchTransferTx, CoinbaseTx, SubdivisionTx, Transaction, TransferOutput, TransferTx,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Format version of the vector file
+pub const VECTORS_VERSION: u32 = 1;
+
+/// The shipped vector file
+pub const WALLET_VECTORS_JSON: &str = include_str!("../test-vectors/wallet.json");
+
+/// Message signed by every key vector
+pub const VECTOR_MESSAGE: &str = "TrinityChain test vector";
+
+/// Recovery phrases and passphrases the wallet vectors derive from
+const MNEMONICS: [(&str, &str); 3] = [
+    (
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        "",
+    ),
+    (
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        "TREZOR",
+    ),
+    (
+        "legal winner thank year wave sausage worth useful legal winner thank year wave \
+         sausage worth useful legal winner thank year wave sausage worth title",
+        "",
+    ),
+];
+
+/// (account, receive index) pairs derived for each phrase
+const PATHS: [(u32, u32); 3] = [(0, 0), (0, 1), (1, 0)];
+
+/// The complete suite, as stored in the vector file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorSuite {
+    pub version: u32,
+    pub wallets: Vec<WalletVector>,
+    pub transactions: Vec<TransactionVector>,
+}
+
+/// One recovery phrase and the keys derived from it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletVector {
+    pub mnemonic: String,
+    pub passphrase: String,
+    /// 64-byte BIP-39 seed (hex)
+    pub seed: String,
+    pub keys: Vec<KeyVector>,
+}
+
+/// A key at one derivation path, its addresses and a message signature
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyVector {
+    pub path: String,
+    /// 32-byte secret key (hex)
+    pub secret_key: String,
+    /// 33-byte compressed public key (hex)
+    pub public_key: String,
+    /// SHA-256 of the public key (hex)
+    pub address_hex: String,
+    /// Base58check address by network name
+    pub addresses: BTreeMap<String, String>,
+    /// Signature of `VECTOR_MESSAGE` (hex)
+    pub message_signature: String,
+}
+
+/// A signed transaction and its canonical forms
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionVector {
+    pub description: String,
+    /// Secret key that signed it (hex); absent for coinbase transactions
+    pub signer_secret_key: Option<String>,
+    /// The transaction as the node's JSON API shows it
+    pub transaction: Transaction,
+    /// Sighash the signature commits to (hex); empty for coinbase
+    pub sighash: String,
+    /// Compact signature (hex); empty for coinbase
+    pub signature: String,
+    /// Canonical bincode encoding used on the wire and in blocks (hex)
+    pub encoding: String,
+    /// Transaction id (hex)
+    pub txid: String,
+}
+
+/// Base58check form of `address_hex`'s address on `network`
+fn encode_address(bytes: &[u8; 32], network: Network) -> String {
+    bs58::encode(bytes)
+        .with_check_version(network.address_version())
+        .into_string()
+}
+
+fn key_vector(hd: &HDWallet, path: &str) -> Result<KeyVector, ChainError> {
+    let keypair = hd
+        .derive_keypair_at(path)
+        .map_err(ChainError::WalletError)?;
+    let address = keypair.address();
+    let addresses = [Network::Mainnet, Network::Testnet, Network::Regtest]
+        .into_iter()
+        .map(|n| (n.name().to_string(), encode_address(&address, n)))
+        .collect();
+    Ok(KeyVector {
+        path: path.to_string(),
+        secret_key: hex::encode(keypair.secret_key.secret_bytes()),
+        public_key: hex::encode(keypair.public_key_bytes()),
+        address_hex: address.to_hex(),
+        addresses,
+        message_signature: hex::encode(keypair.sign(VECTOR_MESSAGE.as_bytes())?),
+    })
+}
+
+/// Sighash of a signed transaction type; `None` for coinbase
+fn sighash(tx: &Transaction) -> Option<Vec<u8>> {
+    match tx {
+        Transaction::Transfer(tx) => Some(tx.signable_message()),
+        Transaction::Subdivision(tx) => Some(tx.signable_message()),
+        Transaction::BatchTransfer(tx) => Some(tx.signable_message()),
+        Transaction::Coinbase(_) => None,
+    }
+}
+
+fn signature(tx: &Transaction) -> Option<&Vec<u8>> {
+    match tx {
+        Transaction::Transfer(tx) => tx.signature.as_ref(),
+        Transaction::Subdivision(tx) => tx.signature.as_ref(),
+        Transaction::BatchTransfer(tx) => tx.signature.as_ref(),
+        Transaction::Coinbase(_) => None,
+    }
+}
+
+fn encode(tx: &Transaction) -> Result<Vec<u8>, ChainError> {
+    bincode::serialize(tx).map_err(|e| ChainError::BincodeError(e.to_string()))
+}
+
+fn transaction_vector(
+    description: &str,
+    signer: Option<&KeyPair>,
+    mut tx: Transaction,
+) -> Result<TransactionVector, ChainError> {
+    if let (Some(keypair), Some(message)) = (signer, sighash(&tx)) {
+        let sig = keypair.sign(&message)?.to_vec();
+        let public_key = keypair.public_key_bytes().to_vec();
+        match &mut tx {
+            Transaction::Transfer(tx) => tx.sign(sig, public_key),
+            Transaction::Subdivision(tx) => tx.sign(sig, public_key),
+            Transaction::BatchTransfer(tx) => tx.sign(sig, public_key),
+            Transaction::Coinbase(_) => {}
+        }
+    }
+    Ok(TransactionVector {
+        description: description.to_string(),
+        signer_secret_key: signer.map(|k| hex::encode(k.secret_key.secret_bytes())),
+        sighash: sighash(&tx).map(hex::encode).unwrap_or_default(),
+        signature: signature(&tx).map(hex::encode).unwrap_or_default(),
+        encoding: hex::encode(encode(&tx)?),
+        txid: tx.hash_str(),
+        transaction: tx,
+    })
+}
+
+/// Build the suite from its fixed inputs
+pub fn generate() -> Result<VectorSuite, ChainError> {
+    let mut wallets = Vec::new();
+    for (mnemonic, passphrase) in MNEMONICS {
+        let seed = HDWallet::seed_from_mnemonic(mnemonic, Some(passphrase))
+            .map_err(ChainError::WalletError)?;
+        let hd = HDWallet::from_seed(seed.clone());
+        let keys = PATHS
+            .iter()
+            .map(|&(account, index)| key_vector(&hd, &derivation_path(account, index)))
+            .collect::<Result<_, _>>()?;
+        wallets.push(WalletVector {
+            mnemonic: mnemonic.to_string(),
+            passphrase: passphrase.to_string(),
+            seed: hex::encode(seed),
+            keys,
+        });
+    }
+
+    let hd = HDWallet::from_mnemonic(MNEMONICS[0].0, None).map_err(ChainError::WalletError)?;
+    let sender = hd.derive_keypair(0, 0).map_err(ChainError::WalletError)?;
+    let recipient = hd.derive_address(0, 1).map_err(ChainError::WalletError)?;
+    let other = hd.derive_address(1, 0).map_err(ChainError::WalletError)?;
+    let from = sender.address();
+
+    let parent = Triangle::new(
+        Point::new(Coord::from_num(0), Coord::from_num(0)),
+        Point::new(Coord::from_num(8), Coord::from_num(0)),
+        Point::new(Coord::from_num(4), Coord::from_num(6)),
+        None,
+        from,
+    );
+    let [a, b, c] = parent.subdivide();
+    let children = vec![
+        a.with_effective_value(Coord::from_num(7)),
+        b.with_effective_value(Coord::from_num(8)),
+        c.with_effective_value(Coord::from_num(8)),
+    ];
+
+    let transactions = vec![
+        transaction_vector(
+            "transfer",
+            Some(&sender),
+            Transaction::Transfer(TransferTx::new(
+                [0x11; 32],
+                recipient,
+                from,
+                Coord::from_num(2.5),
+                Coord::from_num(0.125),
+                1,
+            )),
+        )?,
+        transaction_vector(
+            "transfer with memo",
+            Some(&sender),
+            Transaction::Transfer(
+                TransferTx::new(
+                    [0x22; 32],
+                    recipient,
+                    from,
+                    Coord::from_num(1),
+                    Coord::from_num(0),
+                    2,
+                )
+                .with_memo("invoice #42".to_string())?,
+            ),
+        )?,
+        transaction_vector(
+            "batch transfer",
+            Some(&sender),
+            Transaction::BatchTransfer(BatchTransferTx::new(
+                [0x33; 32],
+                from,
+                vec![
+                    TransferOutput {
+                        recipient,
+                        amount: Coord::from_num(3),
+                    },
+                    TransferOutput {
+                        recipient: other,
+                        amount: Coord::from_num(0.5),
+                    },
+                ],
+                Coord::from_num(0.25),
+                3,
+            )),
+        )?,
+        transaction_vector(
+            "subdivision",
+            Some(&sender),
+            Transaction::Subdivision(SubdivisionTx::new(
+                parent.hash(),
+                children,
+                from,
+                Coord::from_num(1),
+                4,
+            )),
+        )?,
+        transaction_vector(
+            "coinbase",
+            None,
+            Transaction::Coinbase(CoinbaseTx {
+                reward_area: Coord::from_num(1000),
+                beneficiary_address: from,
+                nonce: 1,
+            }),
+        )?,
+    ];
+
+    Ok(VectorSuite {
+        version: VECTORS_VERSION,
+        wallets,
+        transactions,
+    })
+}
+
+/// Parse the shipped vector file
+pub fn load() -> Result<VectorSuite, ChainError> {
+    serde_json::from_str(WALLET_VECTORS_JSON)
+        .map_err(|e| ChainError::WalletError(format!("Invalid vector file: {}", e)))
+}
+
+/// Recompute every entry of `suite` from its inputs and describe each
+/// mismatch; an empty list means the suite agrees with this implementation
+pub fn verify(suite: &VectorSuite) -> Vec<String> {
+    let mut failures = Vec::new();
+    let mut expect = |what: String, expected: &str, actual: &str| {
+        if expected != actual {
+            failures.push(format!("{}: expected {}, got {}", what, expected, actual));
+        }
+    };
+
+    if suite.version != VECTORS_VERSION {
+        expect(
+            "version".to_string(),
+            &suite.version.to_string(),
+            &VECTORS_VERSION.to_string(),
+        );
+    }
+
+    for wallet in &suite.wallets {
+        let seed = match HDWallet::seed_from_mnemonic(&wallet.mnemonic, Some(&wallet.passphrase)) {
+            Ok(seed) => seed,
+            Err(e) => {
+                expect(format!("seed of '{}'", wallet.mnemonic), &wallet.seed, &e);
+                continue;
+            }
+        };
+        expect(
+            format!("seed of '{}'", wallet.mnemonic),
+            &wallet.seed,
+            &hex::encode(&seed),
+        );
+        let hd = HDWallet::from_seed(seed);
+        for key in &wallet.keys {
+            let actual = match key_vector(&hd, &key.path) {
+                Ok(actual) => actual,
+                Err(e) => {
+                    expect(key.path.clone(), &key.secret_key, &e.to_string());
+                    continue;
+                }
+            };
+            expect(
+                format!("{} secret key", key.path),
+                &key.secret_key,
+                &actual.secret_key,
+            );
+            expect(
+                format!("{} public key", key.path),
+                &key.public_key,
+                &actual.public_key,
+            );
+            expect(
+                format!("{} address", key.path),
+                &key.address_hex,
+                &actual.address_hex,
+            );
+            for (network, address) in &actual.addresses {
+                let listed = key.addresses.get(network).map(String::as_str).unwrap_or("");
+                expect(format!("{} {} address", key.path, network), listed, address);
+            }
+            expect(
+                format!("{} message signature", key.path),
+                &key.message_signature,
+                &actual.message_signature,
+            );
+        }
+    }
+
+    for vector in &suite.transactions {
+        let what = |field: &str| format!("{} {}", vector.description, field);
+        let tx = &vector.transaction;
+        let encoding = encode(tx).map(hex::encode).unwrap_or_default();
+        expect(what("encoding"), &vector.encoding, &encoding);
+        match hex::decode(&vector.encoding)
+            .ok()
+            .and_then(|bytes| bincode::deserialize::<Transaction>(&bytes).ok())
+        {
+            Some(decoded) => expect(what("decoded txid"), &vector.txid, &decoded.hash_str()),
+            None => expect(
+                what("encoding"),
+                &vector.encoding,
+                "an undecodable transaction",
+            ),
+        }
+        expect(what("txid"), &vector.txid, &tx.hash_str());
+        let sighash = sighash(tx).map(hex::encode).unwrap_or_default();
+        expect(what("sighash"), &vector.sighash, &sighash);
+        let signature = signature(tx).map(hex::encode).unwrap_or_default();
+        expect(what("signature"), &vector.signature, &signature);
+
+        if let Some(secret) = &vector.signer_secret_key {
+            let keypair = hex::decode(secret)
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| KeyPair::from_secret_bytes(&bytes).map_err(|e| e.to_string()));
+            let resigned = keypair.and_then(|keypair| {
+                let message = hex::decode(&sighash).map_err(|e| e.to_string())?;
+                keypair
+                    .sign(&message)
+                    .map(hex::encode)
+                    .map_err(|e| e.to_string())
+            });
+            match resigned {
+                Ok(resigned) => expect(what("re-signed signature"), &vector.signature, &resigned),
+                Err(e) => expect(what("signer"), secret, &e),
+            }
+        }
+        if let Err(e) = tx.validate_stateless(u64::MAX) {
+            expect(what("validation"), "ok", &e.to_string());
+        }
+        if let (Some(public_key), Ok(message), Ok(sig)) = (
+            tx_public_key(tx),
+            hex::decode(&vector.sighash),
+            hex::decode(&vector.signature),
+        ) {
+            if let Err(e) = verify_signature(public_key, &message, &sig) {
+                expect(what("signature check"), "ok", &e.to_string());
+            }
+        }
+    }
+    failures
+}
+
+fn tx_public_key(tx: &Transaction) -> Option<&[u8]> {
+    match tx {
+        Transaction::Transfer(tx) => tx.public_key.as_deref(),
+        Transaction::Subdivision(tx) => tx.public_key.as_deref(),
+        Transaction::BatchTransfer(tx) => tx.public_key.as_deref(),
+        Transaction::Coinbase(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shipped_vectors_match_implementation() {
+        let shipped = load().unwrap();
+        assert_eq!(verify(&shipped), Vec::<String>::new());
+
+        let generated = serde_json::to_value(generate().unwrap()).unwrap();
+        let file: serde_json::Value = serde_json::from_str(WALLET_VECTORS_JSON).unwrap();
+        assert_eq!(generated, file, "regenerate with `trinity test-vectors`");
+    }
+
+    #[test]
+    fn test_verify_reports_mismatches() {
+        let mut suite = generate().unwrap();
+        let wrong = suite.wallets[1].keys[0].addresses["mainnet"].clone();
+        suite.wallets[0].keys[0]
+            .addresses
+            .insert("mainnet".to_string(), wrong);
+        suite.transactions[0].txid = suite.transactions[1].txid.clone();
+
+        let failures = verify(&suite);
+        assert_eq!(failures.len(), 3, "{:#?}", failures);
+        assert!(failures[0].contains("mainnet address"));
+        assert!(failures.iter().any(|f| f.starts_with("transfer txid")));
+    }
+}
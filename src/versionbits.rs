@@ -0,0 +1,242 @@
+//! Soft-fork deployment signalling through the block header version
+//!
+//! Each consensus feature that needs miner coordination gets a
+//! [`Deployment`]: a header version bit, the height signalling starts and
+//! a timeout. The chain is cut into windows of `window` blocks; a window in
+//! which at least `threshold` blocks set the bit locks the feature in, and
+//! it becomes active one window later. A deployment that hasn't locked in
+//! by its timeout fails.
+//!
+//! States only change on window boundaries and depend solely on the headers
+//! below the boundary, so every node computes the same state for a height.
+
+use crate::blockchain::BlockHeader;
+use serde::Serialize;
+
+/// The top three version bits must be `001` for the other bits to count as
+/// signals, which leaves room to redefine the version field later
+pub const VERSIONBITS_TOP_BITS: u32 = 0x2000_0000;
+
+/// Mask selecting the top three version bits
+pub const VERSIONBITS_TOP_MASK: u32 = 0xE000_0000;
+
+/// Highest usable signal bit
+pub const MAX_SIGNAL_BIT: u8 = 28;
+
+/// Deployments known to this build. Add new consensus features here; never
+/// reuse a bit while a deployment using it can still be started or locked in.
+pub const DEPLOYMENTS: &[Deployment] = &[];
+
+/// A consensus feature rolled out through version-bit signalling
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Deployment {
+    pub name: &'static str,
+    /// Version bit miners set to signal readiness
+    pub bit: u8,
+    /// First height signalling counts; rounded up to a window boundary
+    pub start_height: u64,
+    /// Signalling stops at the first window boundary at or after this height
+    pub timeout_height: u64,
+    /// Blocks per signalling window
+    pub window: u64,
+    /// Signalling blocks needed in one window to lock in
+    pub threshold: u64,
+}
+
+/// Where a deployment is in its rollout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeploymentState {
+    /// Signalling hasn't started
+    Defined,
+    /// Miners are signalling
+    Started,
+    /// The threshold was reached; the feature activates next window
+    LockedIn,
+    /// The feature's consensus rules are enforced
+    Active,
+    /// The timeout passed without lock-in
+    Failed,
+}
+
+impl DeploymentState {
+    /// Whether blocks in this state should set the deployment's bit
+    pub fn signals(self) -> bool {
+        matches!(self, DeploymentState::Started | DeploymentState::LockedIn)
+    }
+}
+
+/// A deployment's state for the next block, as served by the API
+#[derive(Debug, Clone, Serialize)]
+pub struct DeploymentStatus {
+    #[serde(flatten)]
+    pub deployment: Deployment,
+    pub state: DeploymentState,
+    /// First height of the window the current state began in
+    pub since_height: u64,
+    /// Signalling blocks so far in the current window
+    pub signalling: u64,
+}
+
+impl Deployment {
+    /// Whether a header signals for this deployment
+    pub fn is_signalled_by(&self, header: &BlockHeader) -> bool {
+        header.version & VERSIONBITS_TOP_MASK == VERSIONBITS_TOP_BITS
+            && header.version & (1 << self.bit) != 0
+    }
+
+    /// State for a block at `height`. `headers` is the chain from genesis,
+    /// indexed by height, and must cover every window below `height`'s.
+    pub fn state_at(&self, headers: &[&BlockHeader], height: u64) -> DeploymentState {
+        self.state_since(headers, height).0
+    }
+
+    /// State for a block at `height` and the first height of the window it
+    /// began in
+    fn state_since(&self, headers: &[&BlockHeader], height: u64) -> (DeploymentState, u64) {
+        let window = self.window.max(1);
+        let mut state = DeploymentState::Defined;
+        let mut since = 0;
+        let mut boundary = 0;
+        while boundary + window <= height {
+            boundary += window;
+            let next = match state {
+                DeploymentState::Defined if boundary >= self.timeout_height => {
+                    DeploymentState::Failed
+                }
+                DeploymentState::Defined if boundary >= self.start_height => {
+                    DeploymentState::Started
+                }
+                DeploymentState::Started => {
+                    if self.count_signals(headers, boundary - window, boundary) >= self.threshold {
+                        DeploymentState::LockedIn
+                    } else if boundary >= self.timeout_height {
+                        DeploymentState::Failed
+                    } else {
+                        DeploymentState::Started
+                    }
+                }
+                DeploymentState::LockedIn => DeploymentState::Active,
+                other => other,
+            };
+            if next != state {
+                state = next;
+                since = boundary;
+            }
+        }
+        (state, since)
+    }
+
+    /// Signalling headers with heights in `from..to`
+    fn count_signals(&self, headers: &[&BlockHeader], from: u64, to: u64) -> u64 {
+        let end = (to as usize).min(headers.len());
+        let start = (from as usize).min(end);
+        headers[start..end]
+            .iter()
+            .filter(|h| self.is_signalled_by(h))
+            .count() as u64
+    }
+
+    /// State and signalling progress for a block at `height`
+    pub fn status_at(&self, headers: &[&BlockHeader], height: u64) -> DeploymentStatus {
+        let (state, since_height) = self.state_since(headers, height);
+        let window_start = height - height % self.window.max(1);
+        DeploymentStatus {
+            deployment: *self,
+            state,
+            since_height,
+            signalling: self.count_signals(headers, window_start, height),
+        }
+    }
+}
+
+/// Header version for a block at `height`: the top bits plus the bit of
+/// every deployment that is started or locked in
+pub fn block_version(deployments: &[Deployment], headers: &[&BlockHeader], height: u64) -> u32 {
+    deployments
+        .iter()
+        .filter(|d| d.state_at(headers, height).signals())
+        .fold(VERSIONBITS_TOP_BITS, |version, d| version | (1 << d.bit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_DEPLOYMENT: Deployment = Deployment {
+        name: "test",
+        bit: 3,
+        start_height: 10,
+        timeout_height: 40,
+        window: 10,
+        threshold: 8,
+    };
+
+    fn headers(versions: &[u32]) -> Vec<BlockHeader> {
+        versions
+            .iter()
+            .enumerate()
+            .map(|(height, &version)| BlockHeader {
+                version,
+                height: height as u64,
+                timestamp: 0,
+                previous_hash: [0; 32],
+                merkle_root: [0; 32],
+                state_root: [0; 32],
+                difficulty: 1,
+                nonce: 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_deployment_locks_in_and_activates() {
+        let signal = VERSIONBITS_TOP_BITS | 1 << 3;
+        // Window 1 (10..20) signals 7/10, window 2 (20..30) signals 8/10
+        let mut versions = vec![VERSIONBITS_TOP_BITS; 10];
+        versions.extend([signal; 7].iter().chain(&[0; 3]));
+        versions.extend([signal; 8].iter().chain(&[VERSIONBITS_TOP_BITS; 2]));
+        versions.extend([VERSIONBITS_TOP_BITS; 15]);
+        let owned = headers(&versions);
+        let headers: Vec<&BlockHeader> = owned.iter().collect();
+
+        let d = TEST_DEPLOYMENT;
+        assert_eq!(d.state_at(&headers, 9), DeploymentState::Defined);
+        assert_eq!(d.state_at(&headers, 10), DeploymentState::Started);
+        assert_eq!(d.state_at(&headers, 20), DeploymentState::Started);
+        assert_eq!(d.state_at(&headers, 30), DeploymentState::LockedIn);
+        assert_eq!(d.state_at(&headers, 39), DeploymentState::LockedIn);
+        assert_eq!(d.state_at(&headers, 40), DeploymentState::Active);
+        assert_eq!(d.status_at(&headers, 45).since_height, 40);
+        assert_eq!(d.status_at(&headers, 15).signalling, 5);
+
+        assert_eq!(block_version(&[d], &headers, 35), signal);
+        assert_eq!(block_version(&[d], &headers, 45), VERSIONBITS_TOP_BITS);
+        // Bits only count under the top-bit prefix
+        assert!(!d.is_signalled_by(&headers_with(1 << 3)));
+    }
+
+    #[test]
+    fn test_deployment_fails_at_timeout() {
+        let owned = headers(&[VERSIONBITS_TOP_BITS; 60]);
+        let headers: Vec<&BlockHeader> = owned.iter().collect();
+        let d = TEST_DEPLOYMENT;
+        assert_eq!(d.state_at(&headers, 39), DeploymentState::Started);
+        assert_eq!(d.state_at(&headers, 40), DeploymentState::Failed);
+        assert_eq!(d.state_at(&headers, 59), DeploymentState::Failed);
+        assert_eq!(block_version(&[d], &headers, 50), VERSIONBITS_TOP_BITS);
+    }
+
+    #[test]
+    fn test_known_deployments_use_distinct_bits() {
+        let mut bits: Vec<u8> = DEPLOYMENTS.iter().map(|d| d.bit).collect();
+        assert!(bits.iter().all(|&bit| bit <= MAX_SIGNAL_BIT));
+        bits.sort_unstable();
+        bits.dedup();
+        assert_eq!(bits.len(), DEPLOYMENTS.len());
+    }
+
+    fn headers_with(version: u32) -> BlockHeader {
+        headers(&[version]).remove(0)
+    }
+}
@@ -6,52 +6,156 @@
 // Suppress deprecation warnings from aes-gcm's generic-array dependency
 #![allow(deprecated)]
 
-use crate::crypto::KeyPair;
+use crate::crypto::{address_from_hex, Address, KeyPair};
+use crate::datadir::DataDir;
 use crate::error::ChainError;
+use crate::security::SecretBytes;
 use rpassword::prompt_password;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::Write;
 use std::path::PathBuf;
 use tempfile::NamedTempFile;
+use zeroize::Zeroizing;
+
+/// Current wallet file format. Files written before versioning have no
+/// `format_version` field and are treated as version 0.
+pub const WALLET_FORMAT_VERSION: u32 = 1;
+
+/// Rewrites the JSON of a wallet file from one format version to the next
+type WalletUpgrade = fn(&mut serde_json::Value) -> Result<(), ChainError>;
+
+/// Upgrades between file format versions: `WALLET_UPGRADES[n]` turns a
+/// version `n` file into a version `n + 1` file. Append-only.
+const WALLET_UPGRADES: &[WalletUpgrade] = &[upgrade_v0_to_v1];
+
+/// Version 1 introduced `format_version` itself; plain wallets written
+/// before watch-only support also lack the `watch_only` flag
+fn upgrade_v0_to_v1(value: &mut serde_json::Value) -> Result<(), ChainError> {
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| ChainError::WalletError("Wallet file is not a JSON object".to_string()))?;
+    if object.contains_key("secret_key") && !object.contains_key("watch_only") {
+        object.insert("watch_only".to_string(), serde_json::Value::Bool(false));
+    }
+    Ok(())
+}
+
+/// Parse a wallet file and bring it up to `WALLET_FORMAT_VERSION`. Returns
+/// the upgraded JSON and, if any upgrade ran, the version it was upgraded from.
+fn read_versioned(path: &PathBuf) -> Result<(serde_json::Value, Option<u32>), ChainError> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| ChainError::WalletError(format!("Failed to read wallet: {}", e)))?;
+    let mut value: serde_json::Value = serde_json::from_str(&contents)
+        .map_err(|e| ChainError::WalletError(format!("Failed to parse wallet: {}", e)))?;
+
+    let version = value["format_version"].as_u64().unwrap_or(0) as u32;
+    if version > WALLET_FORMAT_VERSION {
+        return Err(ChainError::WalletError(format!(
+            "Wallet file format version {} is newer than this build supports ({}); upgrade TrinityChain",
+            version, WALLET_FORMAT_VERSION
+        )));
+    }
+    if version == WALLET_FORMAT_VERSION {
+        return Ok((value, None));
+    }
+
+    for upgrade in &WALLET_UPGRADES[version as usize..] {
+        upgrade(&mut value)?;
+    }
+    value["format_version"] = serde_json::Value::from(WALLET_FORMAT_VERSION);
+    Ok((value, Some(version)))
+}
+
+/// Rewrite an upgraded wallet file at the latest version, but only after the
+/// original has been copied to `<file>.v<old>.bak`. If the backup fails the
+/// original is left untouched and the upgrade is redone on every load.
+fn persist_upgrade(
+    path: &PathBuf,
+    from_version: u32,
+    save: impl FnOnce() -> Result<(), ChainError>,
+) {
+    let mut backup = path.clone().into_os_string();
+    backup.push(format!(".v{}.bak", from_version));
+    if let Err(e) = fs::copy(path, &backup) {
+        eprintln!(
+            "Warning: Not upgrading wallet file {}: backup failed: {}",
+            path.display(),
+            e
+        );
+        return;
+    }
+    if let Err(e) = save() {
+        eprintln!(
+            "Warning: Failed to write upgraded wallet file {}: {}",
+            path.display(),
+            e
+        );
+    }
+}
 
 /// Wallet data structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Wallet {
+    /// File format version (see `WALLET_FORMAT_VERSION`)
+    #[serde(default)]
+    pub format_version: u32,
     /// Optional wallet name
     pub name: Option<String>,
     /// Wallet address (derived from public key)
     pub address: String,
-    /// Secret key (hex-encoded)
-    #[serde(rename = "secret_key")]
-    pub secret_key_hex: String,
+    /// Secret key (hex-encoded on disk, empty for watch-only wallets)
+    #[serde(default)]
+    pub secret_key: SecretBytes,
     /// Creation timestamp
     pub created: String,
+    /// Watch-only wallets track an address without holding its secret key
+    #[serde(default)]
+    pub watch_only: bool,
 }
 
 impl Wallet {
     /// Create a new wallet with a generated keypair
     pub fn new(name: Option<String>) -> Result<Self, ChainError> {
-        let keypair = KeyPair::generate()?;
-        let address = hex::encode(keypair.address());
-        let secret_key_hex = hex::encode(keypair.secret_key.secret_bytes());
+        Ok(Self::from_keypair(name, &KeyPair::generate()?))
+    }
 
-        Ok(Wallet {
+    /// Create a wallet holding an existing keypair (e.g. one restored from a backup)
+    pub fn from_keypair(name: Option<String>, keypair: &KeyPair) -> Self {
+        Wallet {
+            format_version: WALLET_FORMAT_VERSION,
             name,
-            address,
-            secret_key_hex,
+            address: hex::encode(keypair.address()),
+            secret_key: keypair.secret_bytes(),
             created: chrono::Utc::now().to_rfc3339(),
+            watch_only: false,
+        }
+    }
+
+    /// Create a watch-only wallet for an address whose key lives elsewhere
+    pub fn new_watch_only(address: &str) -> Result<Self, ChainError> {
+        let address_bytes = address_from_hex(address)
+            .map_err(|e| ChainError::WalletError(format!("Invalid watch-only address: {}", e)))?;
+
+        Ok(Wallet {
+            format_version: WALLET_FORMAT_VERSION,
+            name: None,
+            address: hex::encode(address_bytes),
+            secret_key: SecretBytes::default(),
+            created: chrono::Utc::now().to_rfc3339(),
+            watch_only: true,
         })
     }
 
-    /// Load a wallet from a file
+    /// Load a wallet from a file, upgrading older file formats
     pub fn load(path: &PathBuf) -> Result<Self, ChainError> {
-        let contents = fs::read_to_string(path)
-            .map_err(|e| ChainError::WalletError(format!("Failed to read wallet: {}", e)))?;
-
-        let wallet: Wallet = serde_json::from_str(&contents)
+        let (value, upgraded_from) = read_versioned(path)?;
+        let wallet: Wallet = serde_json::from_value(value)
             .map_err(|e| ChainError::WalletError(format!("Failed to parse wallet: {}", e)))?;
 
+        if let Some(version) = upgraded_from {
+            persist_upgrade(path, version, || wallet.save(path));
+        }
         Ok(wallet)
     }
 
@@ -73,18 +177,20 @@ impl Wallet {
 
     /// Get the keypair from the wallet
     pub fn get_keypair(&self) -> Result<KeyPair, ChainError> {
-        let secret_bytes = hex::decode(&self.secret_key_hex)
-            .map_err(|e| ChainError::WalletError(format!("Failed to decode secret key: {}", e)))?;
+        if self.watch_only {
+            return Err(ChainError::WalletError(format!(
+                "Wallet {} is watch-only and cannot sign transactions",
+                self.address
+            )));
+        }
 
-        KeyPair::from_secret_bytes(&secret_bytes)
+        KeyPair::from_secret_bytes(self.secret_key.expose())
     }
 }
 
 /// Get the default wallet directory
 pub fn get_wallet_dir() -> Result<PathBuf, ChainError> {
-    let home = std::env::var("HOME")
-        .map_err(|e| ChainError::WalletError(format!("Failed to get HOME env var: {}", e)))?;
-    Ok(PathBuf::from(home).join(".trinitychain"))
+    Ok(DataDir::current().wallet_dir().to_path_buf())
 }
 
 /// Get the default wallet file path
@@ -149,10 +255,7 @@ pub fn load_default_wallet() -> Result<Wallet, ChainError> {
             "No wallet found. Run 'trinity-wallet new' first.".to_string(),
         ));
     }
-    let encrypted_wallet = EncryptedWallet::load(&path)?;
-    let password = prompt_password("Enter your wallet password: ")
-        .map_err(|e| ChainError::WalletError(format!("Failed to read password: {}", e)))?;
-    encrypted_wallet.decrypt(&password)
+    load_wallet_file(&path)
 }
 
 /// Load a named wallet
@@ -164,7 +267,83 @@ pub fn load_named_wallet(name: &str) -> Result<Wallet, ChainError> {
             name
         )));
     }
-    let encrypted_wallet = EncryptedWallet::load(&path)?;
+    load_wallet_file(&path)
+}
+
+/// Create a watch-only wallet under the given name in the wallet directory
+pub fn create_watch_only_wallet(name: &str, address: &str) -> Result<Wallet, ChainError> {
+    ensure_wallet_dir()?;
+    let path = get_named_wallet_path(name)?;
+    if path.exists() {
+        return Err(ChainError::WalletError(format!(
+            "Wallet '{}' already exists",
+            name
+        )));
+    }
+    let mut wallet = Wallet::new_watch_only(address)?;
+    wallet.name = Some(name.to_string());
+    wallet.save(&path)?;
+    Ok(wallet)
+}
+
+/// Check whether the wallet file at `path` is a watch-only wallet
+pub fn is_watch_only_file(path: &PathBuf) -> Result<bool, ChainError> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| ChainError::WalletError(format!("Failed to read wallet: {}", e)))?;
+    let value: serde_json::Value = serde_json::from_str(&contents)
+        .map_err(|e| ChainError::WalletError(format!("Failed to parse wallet: {}", e)))?;
+    Ok(value["watch_only"].as_bool().unwrap_or(false))
+}
+
+/// Address of a named wallet (`default` for the default wallet), read
+/// without decrypting the key
+pub fn named_wallet_address(name: &str) -> Result<String, ChainError> {
+    let path = if name == "default" {
+        get_default_wallet_path()?
+    } else {
+        get_named_wallet_path(name)?
+    };
+    if !path.exists() {
+        return Err(ChainError::WalletError(format!(
+            "Wallet '{}' not found",
+            name
+        )));
+    }
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| ChainError::WalletError(format!("Failed to read wallet: {}", e)))?;
+    let value: serde_json::Value = serde_json::from_str(&contents)
+        .map_err(|e| ChainError::WalletError(format!("Failed to parse wallet: {}", e)))?;
+    value["address"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| ChainError::WalletError(format!("Wallet '{}' has no address", name)))
+}
+
+/// Name of the wallet holding `address` (`default` for the default wallet),
+/// found without decrypting any key
+pub fn wallet_name_for_address(address: &Address) -> Result<Option<String>, ChainError> {
+    let named = list_wallets()?.into_iter().filter_map(|file| {
+        file.strip_prefix("wallet_")
+            .and_then(|f| f.strip_suffix(".json"))
+            .map(str::to_string)
+    });
+    for name in std::iter::once("default".to_string()).chain(named) {
+        let holds = named_wallet_address(&name)
+            .and_then(|a| Address::parse(&a))
+            .is_ok_and(|a| a == *address);
+        if holds {
+            return Ok(Some(name));
+        }
+    }
+    Ok(None)
+}
+
+/// Load a wallet file, prompting for a password unless it is watch-only
+fn load_wallet_file(path: &PathBuf) -> Result<Wallet, ChainError> {
+    if is_watch_only_file(path)? {
+        return Wallet::load(path);
+    }
+    let encrypted_wallet = EncryptedWallet::load(path)?;
     let password = prompt_password("Enter your wallet password: ")
         .map_err(|e| ChainError::WalletError(format!("Failed to read password: {}", e)))?;
     encrypted_wallet.decrypt(&password)
@@ -214,6 +393,9 @@ use argon2::{
 /// Encrypted wallet structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EncryptedWallet {
+    /// File format version (see `WALLET_FORMAT_VERSION`)
+    #[serde(default)]
+    pub format_version: u32,
     pub name: Option<String>,
     pub address: String,
     pub encrypted_secret_key: String, // Base64 encoded encrypted data
@@ -240,13 +422,15 @@ impl EncryptedWallet {
             .to_string();
 
         // Derive encryption key from the raw hash
-        let hash_bytes = argon2
-            .hash_password(password.as_bytes(), &salt)
-            .map_err(|e| ChainError::CryptoError(format!("Password hashing failed: {}", e)))?
-            .hash
-            .ok_or_else(|| ChainError::CryptoError("No hash generated".to_string()))?
-            .as_bytes()
-            .to_vec();
+        let hash_bytes = Zeroizing::new(
+            argon2
+                .hash_password(password.as_bytes(), &salt)
+                .map_err(|e| ChainError::CryptoError(format!("Password hashing failed: {}", e)))?
+                .hash
+                .ok_or_else(|| ChainError::CryptoError("No hash generated".to_string()))?
+                .as_bytes()
+                .to_vec(),
+        );
 
         // Create cipher
         let cipher = Aes256Gcm::new_from_slice(&hash_bytes[..32])
@@ -257,12 +441,13 @@ impl EncryptedWallet {
 
         // Encrypt the secret key
         let ciphertext = cipher
-            .encrypt(&nonce, wallet.secret_key_hex.as_bytes())
+            .encrypt(&nonce, wallet.secret_key.to_hex().as_bytes())
             .map_err(|e| ChainError::CryptoError(format!("Encryption failed: {}", e)))?;
 
         use base64::{engine::general_purpose, Engine as _};
 
         Ok(EncryptedWallet {
+            format_version: WALLET_FORMAT_VERSION,
             name: wallet.name.clone(),
             address: wallet.address.clone(),
             encrypted_secret_key: general_purpose::STANDARD.encode(&ciphertext),
@@ -295,13 +480,15 @@ impl EncryptedWallet {
             Params::new(19456, 2, 1, None)
                 .map_err(|e| ChainError::CryptoError(format!("Argon2 params error: {}", e)))?,
         );
-        let hash_bytes = argon2
-            .hash_password(password.as_bytes(), salt)
-            .map_err(|e| ChainError::CryptoError(format!("Password hashing failed: {}", e)))?
-            .hash
-            .ok_or_else(|| ChainError::CryptoError("No hash generated".to_string()))?
-            .as_bytes()
-            .to_vec();
+        let hash_bytes = Zeroizing::new(
+            argon2
+                .hash_password(password.as_bytes(), salt)
+                .map_err(|e| ChainError::CryptoError(format!("Password hashing failed: {}", e)))?
+                .hash
+                .ok_or_else(|| ChainError::CryptoError("No hash generated".to_string()))?
+                .as_bytes()
+                .to_vec(),
+        );
 
         // Create cipher
         let cipher = Aes256Gcm::new_from_slice(&hash_bytes[..32])
@@ -324,14 +511,18 @@ impl EncryptedWallet {
             ChainError::CryptoError("Decryption failed - wrong password?".to_string())
         })?;
 
-        let secret_key_hex = String::from_utf8(plaintext)
+        let plaintext = Zeroizing::new(plaintext);
+        let secret_key_hex = std::str::from_utf8(&plaintext)
             .map_err(|e| ChainError::CryptoError(format!("Invalid UTF-8: {}", e)))?;
+        let secret_key = SecretBytes::from_hex(secret_key_hex)?;
 
         Ok(Wallet {
+            format_version: WALLET_FORMAT_VERSION,
             name: self.name.clone(),
             address: self.address.clone(),
-            secret_key_hex,
+            secret_key,
             created: self.created.clone(),
+            watch_only: false,
         })
     }
 
@@ -373,15 +564,16 @@ impl EncryptedWallet {
         Ok(())
     }
 
-    /// Load encrypted wallet from file
+    /// Load encrypted wallet from file, upgrading older file formats
     pub fn load(path: &PathBuf) -> Result<Self, ChainError> {
-        let contents = fs::read_to_string(path)
-            .map_err(|e| ChainError::WalletError(format!("Failed to read wallet: {}", e)))?;
-
-        let wallet: EncryptedWallet = serde_json::from_str(&contents).map_err(|e| {
+        let (value, upgraded_from) = read_versioned(path)?;
+        let wallet: EncryptedWallet = serde_json::from_value(value).map_err(|e| {
             ChainError::WalletError(format!("Failed to parse encrypted wallet: {}", e))
         })?;
 
+        if let Some(version) = upgraded_from {
+            persist_upgrade(path, version, || wallet.save(path));
+        }
         Ok(wallet)
     }
 }
@@ -399,7 +591,7 @@ mod tests {
 
         assert_eq!(wallet.name, Some("test".to_string()));
         assert!(!wallet.address.is_empty());
-        assert!(!wallet.secret_key_hex.is_empty());
+        assert!(!wallet.secret_key.is_empty());
 
         let keypair_result = wallet.get_keypair();
         assert!(keypair_result.is_ok());
@@ -425,7 +617,7 @@ mod tests {
         let decrypted_wallet = decrypted_wallet_result.unwrap();
 
         assert_eq!(wallet.address, decrypted_wallet.address);
-        assert_eq!(wallet.secret_key_hex, decrypted_wallet.secret_key_hex);
+        assert_eq!(wallet.secret_key, decrypted_wallet.secret_key);
     }
 
     #[test]
@@ -443,6 +635,73 @@ mod tests {
         assert!(loaded_wallet.is_ok());
     }
 
+    #[test]
+    fn test_watch_only_wallet() {
+        let temp_dir = tempdir().unwrap();
+        let wallet_path = temp_dir.path().join("wallet_watch.json");
+
+        let source = Wallet::new(None).unwrap();
+        let watch = Wallet::new_watch_only(&source.address).unwrap();
+        assert!(watch.watch_only);
+        assert_eq!(watch.address, source.address);
+        assert!(watch.secret_key.is_empty());
+
+        watch.save(&wallet_path).unwrap();
+        assert!(is_watch_only_file(&wallet_path).unwrap());
+
+        let loaded = Wallet::load(&wallet_path).unwrap();
+        assert!(loaded.watch_only);
+        assert_eq!(loaded.address, source.address);
+
+        let err = loaded.get_keypair().unwrap_err();
+        assert!(err.to_string().contains("watch-only"));
+    }
+
+    #[test]
+    fn test_watch_only_rejects_invalid_address() {
+        assert!(Wallet::new_watch_only("not-hex").is_err());
+        assert!(Wallet::new_watch_only("abcd").is_err());
+    }
+
+    #[test]
+    fn test_legacy_wallet_is_upgraded_after_backup() {
+        let temp_dir = tempdir().unwrap();
+        let wallet_path = temp_dir.path().join("wallet_legacy.json");
+
+        let wallet = Wallet::new(None).unwrap();
+        let mut legacy = serde_json::to_value(&wallet).unwrap();
+        let object = legacy.as_object_mut().unwrap();
+        object.remove("format_version");
+        object.remove("watch_only");
+        let original = serde_json::to_string_pretty(&legacy).unwrap();
+        fs::write(&wallet_path, &original).unwrap();
+
+        let loaded = Wallet::load(&wallet_path).unwrap();
+        assert_eq!(loaded.format_version, WALLET_FORMAT_VERSION);
+        assert_eq!(loaded.secret_key, wallet.secret_key);
+        assert!(!loaded.watch_only);
+
+        let backup = temp_dir.path().join("wallet_legacy.json.v0.bak");
+        assert_eq!(fs::read_to_string(backup).unwrap(), original);
+        let rewritten: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&wallet_path).unwrap()).unwrap();
+        assert_eq!(rewritten["format_version"], WALLET_FORMAT_VERSION);
+    }
+
+    #[test]
+    fn test_newer_wallet_format_is_rejected() {
+        let temp_dir = tempdir().unwrap();
+        let wallet_path = temp_dir.path().join("wallet_future.json");
+
+        let mut wallet = Wallet::new(None).unwrap();
+        wallet.format_version = WALLET_FORMAT_VERSION + 1;
+        wallet.save(&wallet_path).unwrap();
+
+        let err = Wallet::load(&wallet_path).unwrap_err();
+        assert!(err.to_string().contains("newer"));
+        assert!(EncryptedWallet::load(&wallet_path).is_err());
+    }
+
     #[test]
     fn test_wrong_password_fails() {
         let temp_dir = tempdir().unwrap();
@@ -0,0 +1,330 @@
+//! Watchtower for cold-storage triangles
+//!
+//! Monitors a configured set of triangle hashes and addresses and raises an
+//! alert as soon as a transaction spending one of them appears in the
+//! mempool, and again when it is confirmed in a block. Alerts are published
+//! on a broadcast channel so any number of sinks (webhook, Telegram, console)
+//! can subscribe.
+
+use crate::blockchain::{Blockchain, Sha256Hash};
+use crate::config::WatchtowerConfig;
+use crate::crypto::{address_from_hex, Address};
+use crate::error::ChainError;
+use crate::remote::RemoteNode;
+use crate::transaction::Transaction;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, RwLock};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+/// Alerts buffered per subscriber before slow subscribers start missing them
+const ALERT_CHANNEL_CAPACITY: usize = 256;
+
+/// What a spend was matched against
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "lowercase")]
+pub enum WatchedItem {
+    /// A watched triangle (hex hash) was used as an input
+    Triangle(String),
+    /// A watched address (hex) signed a spend
+    Address(String),
+}
+
+/// Where the spending transaction was seen
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "stage", rename_all = "lowercase")]
+pub enum AlertStage {
+    Mempool,
+    Block { height: u64 },
+}
+
+/// A watched triangle or address was spent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchAlert {
+    pub tx_hash: String,
+    pub watched: WatchedItem,
+    #[serde(flatten)]
+    pub stage: AlertStage,
+    /// RFC3339 time the spend was detected
+    pub detected_at: String,
+}
+
+impl WatchAlert {
+    /// One-line human readable description
+    pub fn describe(&self) -> String {
+        let what = match &self.watched {
+            WatchedItem::Triangle(hash) => format!("watched triangle {}", hash),
+            WatchedItem::Address(addr) => format!("watched address {}", addr),
+        };
+        let where_ = match &self.stage {
+            AlertStage::Mempool => "in the mempool".to_string(),
+            AlertStage::Block { height } => format!("in block #{}", height),
+        };
+        format!("🚨 Spend of {} {} (tx {})", what, where_, self.tx_hash)
+    }
+}
+
+#[derive(Default)]
+struct ScanState {
+    /// Highest block already scanned; `None` until the first scan
+    scanned_height: Option<u64>,
+    /// Pending transactions already alerted on
+    alerted_pending: HashSet<Sha256Hash>,
+}
+
+/// Watches for spends of designated triangles and addresses
+pub struct Watchtower {
+    triangles: HashSet<Sha256Hash>,
+    addresses: HashSet<Address>,
+    state: Mutex<ScanState>,
+    sender: broadcast::Sender<WatchAlert>,
+}
+
+impl Watchtower {
+    pub fn new(triangles: HashSet<Sha256Hash>, addresses: HashSet<Address>) -> Self {
+        let (sender, _) = broadcast::channel(ALERT_CHANNEL_CAPACITY);
+        Watchtower {
+            triangles,
+            addresses,
+            state: Mutex::new(ScanState::default()),
+            sender,
+        }
+    }
+
+    /// Build the watchtower described by `config`. Returns `None` when
+    /// nothing is being watched.
+    pub fn from_config(config: &WatchtowerConfig) -> Result<Option<Self>, ChainError> {
+        if config.triangles.is_empty() && config.addresses.is_empty() {
+            return Ok(None);
+        }
+
+        let triangles = config
+            .triangles
+            .iter()
+            .map(|h| {
+                let mut hash = [0u8; 32];
+                hex::decode_to_slice(h, &mut hash).map_err(|e| {
+                    ChainError::CryptoError(format!("Invalid watched triangle hash {}: {}", h, e))
+                })?;
+                Ok(hash)
+            })
+            .collect::<Result<_, ChainError>>()?;
+        let addresses = config
+            .addresses
+            .iter()
+            .map(|a| address_from_hex(a))
+            .collect::<Result<_, ChainError>>()?;
+
+        Ok(Some(Watchtower::new(triangles, addresses)))
+    }
+
+    /// Number of watched triangles and addresses
+    pub fn watched_count(&self) -> usize {
+        self.triangles.len() + self.addresses.len()
+    }
+
+    /// Receive every alert raised from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<WatchAlert> {
+        self.sender.subscribe()
+    }
+
+    /// Watched items a transaction spends
+    fn spent_items(&self, tx: &Transaction) -> Vec<WatchedItem> {
+        let (input, spender) = match tx {
+            Transaction::Transfer(t) => (t.input_hash, t.sender),
+            Transaction::Subdivision(t) => (t.parent_hash, t.owner_address),
+            Transaction::BatchTransfer(t) => (t.input_hash, t.sender),
+            Transaction::Coinbase(_) => return Vec::new(),
+        };
+
+        let mut items = Vec::new();
+        if self.triangles.contains(&input) {
+            items.push(WatchedItem::Triangle(hex::encode(input)));
+        }
+        if self.addresses.contains(&spender) {
+            items.push(WatchedItem::Address(hex::encode(spender)));
+        }
+        items
+    }
+
+    /// Check new blocks and the mempool for watched spends, publish an alert
+    /// for each one not reported before, and return them. The first scan
+    /// only records the current tip so historical spends are not reported.
+    pub fn scan(&self, chain: &Blockchain) -> Vec<WatchAlert> {
+        let mut state = self.state.lock();
+        let tip = chain.blocks.last().map_or(0, |b| b.header.height);
+        let detected_at = chrono::Utc::now().to_rfc3339();
+        let mut alerts = Vec::new();
+
+        // Blocks connected since the last scan (rewind after a reorg)
+        let scanned = *state.scanned_height.get_or_insert(tip);
+        let scanned = scanned.min(tip);
+        for block in chain.blocks.iter().filter(|b| b.header.height > scanned) {
+            for tx in &block.transactions {
+                for watched in self.spent_items(tx) {
+                    alerts.push(WatchAlert {
+                        tx_hash: tx.hash_str(),
+                        watched,
+                        stage: AlertStage::Block {
+                            height: block.header.height,
+                        },
+                        detected_at: detected_at.clone(),
+                    });
+                }
+            }
+        }
+        state.scanned_height = Some(tip);
+
+        // Pending spends, reported once while they stay in the mempool
+        let mut pending = HashSet::new();
+        for tx in chain.mempool.get_all_transactions() {
+            let items = self.spent_items(&tx);
+            if items.is_empty() {
+                continue;
+            }
+            let hash = tx.hash();
+            pending.insert(hash);
+            if state.alerted_pending.contains(&hash) {
+                continue;
+            }
+            for watched in items {
+                alerts.push(WatchAlert {
+                    tx_hash: tx.hash_str(),
+                    watched,
+                    stage: AlertStage::Mempool,
+                    detected_at: detected_at.clone(),
+                });
+            }
+        }
+        state.alerted_pending = pending;
+
+        for alert in &alerts {
+            // No subscribers is fine; the alerts are still returned
+            let _ = self.sender.send(alert.clone());
+        }
+        alerts
+    }
+
+    /// Rescan `chain` every `interval`, catching spends from any source
+    /// (local mining, API submissions, peers)
+    pub async fn watch(self: Arc<Self>, chain: Arc<RwLock<Blockchain>>, interval: Duration) {
+        loop {
+            self.scan(&*chain.read().await);
+            tokio::time::sleep(interval).await;
+        }
+    }
+}
+
+/// Forward every alert as a JSON `POST` to `url`
+pub fn spawn_webhook(watchtower: &Watchtower, url: &str) -> Result<JoinHandle<()>, ChainError> {
+    let client = RemoteNode::new(url)?;
+    let mut alerts = watchtower.subscribe();
+
+    Ok(tokio::spawn(async move {
+        loop {
+            let alert = match alerts.recv().await {
+                Ok(alert) => alert,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("⚠️  Watchtower webhook missed {} alert(s)", missed);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+
+            let client = client.clone();
+            let result =
+                tokio::task::spawn_blocking(move || client.post_json("", &alert)).await;
+            match result {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => warn!("⚠️  Watchtower webhook failed: {}", e),
+                Err(e) => warn!("⚠️  Watchtower webhook task failed: {}", e),
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::Block;
+    use crate::crypto::KeyPair;
+    use crate::geometry::Coord;
+    use crate::transaction::TransferTx;
+
+    fn signed_transfer(keypair: &KeyPair, input_hash: Sha256Hash) -> Transaction {
+        let mut tx = TransferTx::new(
+            input_hash,
+            Address::from([9u8; 32]),
+            keypair.address(),
+            Coord::from_num(5),
+            Coord::from_num(0),
+            1,
+        );
+        let signature = keypair.sign(&tx.signable_message()).unwrap();
+        tx.sign(signature.to_vec(), keypair.public_key_bytes().to_vec());
+        Transaction::Transfer(tx)
+    }
+
+    #[test]
+    fn test_alerts_on_mempool_and_block_spend() {
+        let keypair = KeyPair::generate().unwrap();
+        let mut chain = Blockchain::new(keypair.address(), 1).unwrap();
+        let cold = *chain.state.utxo_set.keys().next().unwrap();
+
+        let watchtower = Watchtower::new(HashSet::from([cold]), HashSet::new());
+        let mut alerts = watchtower.subscribe();
+        assert!(watchtower.scan(&chain).is_empty());
+
+        let spend = signed_transfer(&keypair, cold);
+        chain.mempool.add_transaction(spend.clone()).unwrap();
+
+        let raised = watchtower.scan(&chain);
+        assert_eq!(raised.len(), 1);
+        assert_eq!(raised[0].stage, AlertStage::Mempool);
+        assert_eq!(raised[0].watched, WatchedItem::Triangle(hex::encode(cold)));
+        assert_eq!(alerts.try_recv().unwrap().tx_hash, spend.hash_str());
+
+        // Still pending: not reported again
+        assert!(watchtower.scan(&chain).is_empty());
+
+        let previous = chain.blocks.last().unwrap();
+        let coinbase = Transaction::Coinbase(crate::transaction::CoinbaseTx {
+            reward_area: Coord::from_num(1),
+            beneficiary_address: keypair.address(),
+            nonce: 1,
+        });
+        let mut block = Block::new(1, previous.hash(), 1, vec![coinbase, spend]);
+        block.header.timestamp = previous.header.timestamp + 1;
+        let block = crate::fixtures::seal_block(&chain, block);
+        chain.apply_block(block).unwrap();
+
+        let raised = watchtower.scan(&chain);
+        assert_eq!(raised.len(), 1);
+        assert_eq!(raised[0].stage, AlertStage::Block { height: 1 });
+    }
+
+    #[test]
+    fn test_alerts_on_watched_address() {
+        let keypair = KeyPair::generate().unwrap();
+        let mut chain = Blockchain::new(keypair.address(), 1).unwrap();
+        let input = *chain.state.utxo_set.keys().next().unwrap();
+
+        let watchtower = Watchtower::new(HashSet::new(), HashSet::from([keypair.address()]));
+        watchtower.scan(&chain);
+
+        chain
+            .mempool
+            .add_transaction(signed_transfer(&keypair, input))
+            .unwrap();
+        let raised = watchtower.scan(&chain);
+        assert_eq!(raised.len(), 1);
+        assert_eq!(
+            raised[0].watched,
+            WatchedItem::Address(hex::encode(keypair.address()))
+        );
+    }
+}
@@ -2,6 +2,7 @@
 
 use trinitychain::wallet::Wallet;
 use trinitychain::blockchain::Blockchain;
+use trinitychain::crypto::Address;
 use trinitychain::transaction::{Transaction, TransferTx};
 use trinitychain::geometry::Coord;
 use tempfile::TempDir;
@@ -23,7 +24,7 @@ fn test_wallet_creation() -> Result<(), Box<dyn std::error::Error>> {
     // Verify wallet has required fields
     assert_eq!(wallet.name, Some("test_wallet".to_string()));
     assert!(!wallet.address.is_empty());
-    assert!(!wallet.secret_key_hex.is_empty());
+    assert!(!wallet.secret_key.is_empty());
     assert!(!wallet.created.is_empty());
 
     // Verify address is 64 hex characters (32 bytes)
@@ -46,7 +47,7 @@ fn test_create_two_wallets() -> Result<(), Box<dyn std::error::Error>> {
     assert_ne!(alice.address, bob.address);
 
     // Verify they have different secret keys
-    assert_ne!(alice.secret_key_hex, bob.secret_key_hex);
+    assert_ne!(alice.secret_key, bob.secret_key);
 
     Ok(())
 }
@@ -69,7 +70,7 @@ fn test_wallet_persistence() -> Result<(), Box<dyn std::error::Error>> {
     // Verify all fields match
     assert_eq!(original_wallet.address, loaded_wallet.address);
     assert_eq!(original_wallet.name, loaded_wallet.name);
-    assert_eq!(original_wallet.secret_key_hex, loaded_wallet.secret_key_hex);
+    assert_eq!(original_wallet.secret_key, loaded_wallet.secret_key);
     assert_eq!(original_wallet.created, loaded_wallet.created);
 
     Ok(())
@@ -92,14 +93,13 @@ fn test_wallet_keypair_derivation() -> Result<(), Box<dyn std::error::Error>> {
 #[test]
 fn test_blockchain_initialization() -> Result<(), Box<dyn std::error::Error>> {
     let alice = create_test_wallet("miner")?;
-    let mut alice_addr = [0u8; 32];
-    hex::decode_to_slice(&alice.address, &mut alice_addr)?;
+    let alice_addr = Address::parse(&alice.address)?;
 
     // Create blockchain
     let blockchain = Blockchain::new(alice_addr, 4)?;
 
     // Verify blockchain has genesis block
-    assert!(blockchain.blocks.len() > 0);
+    assert!(!blockchain.blocks.is_empty());
     assert_eq!(blockchain.blocks[0].header.height, 0);
 
     Ok(())
@@ -111,10 +111,8 @@ fn test_transfer_transaction_creation() -> Result<(), Box<dyn std::error::Error>
     let bob = create_test_wallet("recipient")?;
 
     // Parse addresses
-    let mut alice_addr = [0u8; 32];
-    let mut bob_addr = [0u8; 32];
-    hex::decode_to_slice(&alice.address, &mut alice_addr)?;
-    hex::decode_to_slice(&bob.address, &mut bob_addr)?;
+    let alice_addr = Address::parse(&alice.address)?;
+    let bob_addr = Address::parse(&bob.address)?;
 
     // Create transfer transaction
     let transfer = TransferTx {
@@ -179,7 +177,7 @@ fn test_wallet_secret_key_encoding() -> Result<(), Box<dyn std::error::Error>> {
     let wallet = create_test_wallet("secret_test")?;
 
     // Secret key should be valid hex
-    let decoded = hex::decode(&wallet.secret_key_hex);
+    let decoded = hex::decode(wallet.secret_key.to_hex().as_str());
     assert!(decoded.is_ok());
 
     // Decoded secret key should be 32 bytes (256-bit private key)
@@ -194,10 +192,8 @@ fn test_transaction_fee_calculation() -> Result<(), Box<dyn std::error::Error>>
     let alice = create_test_wallet("sender")?;
     let bob = create_test_wallet("recipient")?;
 
-    let mut alice_addr = [0u8; 32];
-    let mut bob_addr = [0u8; 32];
-    hex::decode_to_slice(&alice.address, &mut alice_addr)?;
-    hex::decode_to_slice(&bob.address, &mut bob_addr)?;
+    let alice_addr = Address::parse(&alice.address)?;
+    let bob_addr = Address::parse(&bob.address)?;
 
     let fee_area = Coord::from_num(10);
 
@@ -229,10 +225,8 @@ fn test_alice_to_bob_transaction() -> Result<(), Box<dyn std::error::Error>> {
     let bob = create_test_wallet("bob")?;
 
     // Parse addresses
-    let mut alice_addr = [0u8; 32];
-    let mut bob_addr = [0u8; 32];
-    hex::decode_to_slice(&alice.address, &mut alice_addr)?;
-    hex::decode_to_slice(&bob.address, &mut bob_addr)?;
+    let alice_addr = Address::parse(&alice.address)?;
+    let bob_addr = Address::parse(&bob.address)?;
 
     // Create transfer: Alice sends 50 TRC to Bob with 2.5 fee
     let transfer = TransferTx {
@@ -264,3 +258,29 @@ fn test_alice_to_bob_transaction() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[cfg(feature = "test-fixtures")]
+#[test]
+fn test_signed_transfer_is_accepted_by_consensus() -> Result<(), Box<dyn std::error::Error>> {
+    use trinitychain::fixtures::{mine_next_block, signed_transfer};
+
+    let alice = create_test_wallet("alice")?.get_keypair()?;
+    let bob = create_test_wallet("bob")?.get_keypair()?;
+
+    let mut blockchain = Blockchain::new(alice.address(), 1)?;
+    let input_hash = *blockchain.state.utxo_set.keys().next().unwrap();
+
+    let transfer = signed_transfer(
+        &alice,
+        input_hash,
+        bob.address(),
+        Coord::from_num(50),
+        Coord::from_num(1),
+        1,
+    );
+    let block = mine_next_block(&blockchain, alice.address(), vec![transfer]);
+    blockchain.apply_block(block)?;
+
+    assert_eq!(blockchain.state.get_balance(&bob.address()), Coord::from_num(50));
+    Ok(())
+}